@@ -54,6 +54,7 @@ download_registry_canisters
 echo "Installing NNS"
 time $TMPDIR/ic-nns-init \
     --url $NNS_URL \
+    --nns-public-key-pem-file $TMPDIR/nns_public_key.pem \
     --registry-local-store-dir $TMPDIR/ic_registry_local_store \
     --wasm-dir "$TMPDIR/canisters"
 
@@ -152,6 +152,8 @@ def nns_install(ic_config, ic_url):
         FLAGS.install_nns_bin,
         "--url",
         ic_url,
+        "--nns-public-key-pem-file",
+        "%s/nns_public_key.pem" % ic_config.workdir,
         "--registry-local-store-dir",
         "%s/ic_registry_local_store" % ic_config.workdir,
         "--wasm-dir",
@@ -109,7 +109,7 @@ fi
 export TMP=$(mktemp -d)
 download_binaries
 
-$TMP/ic-admin --nns-url $NNS_URL propose-to-bless-replica-version-flexible \
+$TMP/ic-admin --nns-url $NNS_URL --fetch-root-key propose-to-bless-replica-version-flexible \
     --test-neuron-proposer $VERSION \
     $UPGRADE_URL $SHA256 2>&1 | tee $TMP/blessing.log
 
@@ -117,7 +117,7 @@ export PROPOSAL_ID=$(grep '^proposal' $TMP/blessing.log | awk '{print $2}')
 echo "Assuming proposal ID is $PROPOSAL_ID"
 sleep 5
 
-$TMP/ic-admin --nns-url=$NNS_URL forward-test-neuron-vote ${PROPOSAL_ID}
+$TMP/ic-admin --nns-url=$NNS_URL --fetch-root-key forward-test-neuron-vote ${PROPOSAL_ID}
 $TMP/ic-admin --nns-url=$NNS_URL execute-eligible-proposals
 
 echo "Waiting 30 seconds, just to be sure"
@@ -126,14 +126,14 @@ sleep 30
 $TMP/ic-admin --nns-url=$NNS_URL get-replica-version $VERSION
 $TMP/ic-admin --nns-url=$NNS_URL get-subnet 0 | grep replica_version
 
-$TMP/ic-admin --nns-url=$NNS_URL propose-to-update-subnet-replica-version \
+$TMP/ic-admin --nns-url=$NNS_URL --fetch-root-key propose-to-update-subnet-replica-version \
     --test-neuron-proposer 0 $VERSION | tee $TMP/blessing.log
 
 export PROPOSAL_ID=$(grep '^proposal' $TMP/blessing.log | awk '{print $2}')
 echo "Assuming proposal ID is $PROPOSAL_ID"
 sleep 5
 
-$TMP/ic-admin --nns-url=$NNS_URL forward-test-neuron-vote ${PROPOSAL_ID}
+$TMP/ic-admin --nns-url=$NNS_URL --fetch-root-key forward-test-neuron-vote ${PROPOSAL_ID}
 $TMP/ic-admin --nns-url=$NNS_URL execute-eligible-proposals
 
 $TMP/ic-admin --nns-url=$NNS_URL get-subnet 0 | grep replica_version
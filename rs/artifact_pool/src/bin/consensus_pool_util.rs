@@ -1,7 +1,8 @@
 use clap::{App, Arg, SubCommand};
 use ic_artifact_pool::{
     certification_pool::CertificationPoolImpl,
-    consensus_pool::{PoolSectionOps, UncachedConsensusPoolImpl},
+    consensus_pool::{ConsensusPoolImpl, PoolSectionOps, UncachedConsensusPoolImpl},
+    consensus_pool_snapshot::ConsensusPoolSnapshot,
 };
 use ic_config::artifact_pool::ArtifactPoolConfig;
 use ic_consensus_message::ConsensusMessageHashable;
@@ -9,8 +10,9 @@ use ic_interfaces::consensus_pool::*;
 use ic_logger::{LoggerImpl, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
 use ic_types::{
-    consensus::{certification::CertificationMessage, CatchUpPackage},
+    consensus::{certification::CertificationMessage, CatchUpPackage, HasHeight},
     time::current_time,
+    Height,
 };
 use prost::Message;
 use serde::{Deserialize, Serialize};
@@ -52,6 +54,59 @@ fn main() {
                         .takes_value(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("export-snapshot")
+                .about("Export the consensus artifacts within a height range to a snapshot file")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output filename")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("HEIGHT")
+                        .help("Lowest height to export, defaults to the highest CatchUpPackage height")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("HEIGHT")
+                        .help("Highest height to export, defaults to the highest height in the pool")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("load-snapshot")
+                .about("Load a snapshot file into an in-memory pool and print its state")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .value_name("FILE")
+                        .help("Input filename")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("print-snapshot")
+                .about("Print the artifacts of a snapshot file to stdout")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .value_name("FILE")
+                        .help("Input filename")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
         .args_from_usage("<PATH>       'PATH to the consensus pool directory'");
     let mut help = Vec::new();
     app.write_help(&mut help)
@@ -66,6 +121,12 @@ fn main() {
         import(path)
     } else if let Some(matches) = matches.subcommand_matches("export-cup-proto") {
         export_cup_proto(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("export-snapshot") {
+        export_snapshot(path, matches)
+    } else if let Some(matches) = matches.subcommand_matches("load-snapshot") {
+        load_snapshot(matches)
+    } else if let Some(matches) = matches.subcommand_matches("print-snapshot") {
+        print_snapshot(matches)
     } else {
         eprintln!(
             "{}",
@@ -274,3 +335,88 @@ fn export_cup_proto(path: &str, matches: &clap::ArgMatches) {
     file.write_all(&buf)
         .unwrap_or_else(|err| panic!("Cannot write to file {}: {:?}", filename, err));
 }
+
+fn parse_height(matches: &clap::ArgMatches, name: &str) -> Option<Height> {
+    matches.value_of(name).map(|height| {
+        Height::from(
+            height
+                .parse::<u64>()
+                .unwrap_or_else(|err| panic!("Invalid height '{}': {:?}", height, err)),
+        )
+    })
+}
+
+fn export_snapshot(path: &str, matches: &clap::ArgMatches) {
+    let filename = matches
+        .value_of("output")
+        .expect("Expect an output filename");
+    let consensus_pool = open_consensus_pool(path, true);
+    let from = parse_height(matches, "from").unwrap_or_else(|| {
+        consensus_pool
+            .validated()
+            .catch_up_package()
+            .max_height()
+            .unwrap_or_default()
+    });
+    let to = parse_height(matches, "to").unwrap_or_else(|| Height::from(u64::MAX));
+    let snapshot =
+        ConsensusPoolSnapshot::new(consensus_pool.validated(), HeightRange::new(from, to));
+    let file = std::fs::File::create(filename)
+        .unwrap_or_else(|err| panic!("Cannot open file {} for write: {:?}", filename, err));
+    snapshot
+        .write_to(std::io::BufWriter::new(file))
+        .unwrap_or_else(|err| panic!("Cannot write to file {}: {:?}", filename, err));
+    eprintln!(
+        "Exported {} validated artifacts to {}",
+        snapshot.validated.len(),
+        filename
+    );
+}
+
+fn read_snapshot(matches: &clap::ArgMatches) -> ConsensusPoolSnapshot {
+    let filename = matches.value_of("input").expect("Expect an input filename");
+    let file = std::fs::File::open(filename)
+        .unwrap_or_else(|err| panic!("Cannot open file {} for read: {:?}", filename, err));
+    ConsensusPoolSnapshot::read_from(std::io::BufReader::new(file))
+        .unwrap_or_else(|err| panic!("Cannot read snapshot from file {}: {:?}", filename, err))
+}
+
+fn load_snapshot(matches: &clap::ArgMatches) {
+    let logger = LoggerImpl::new(&Default::default(), "dump_consensus_pool".to_string());
+    let log = ReplicaLogger::new(logger.root.clone().into());
+    let snapshot = read_snapshot(matches);
+    println!("Replica version: {}", snapshot.replica_version);
+    let pool = ConsensusPoolImpl::from_snapshot(snapshot, MetricsRegistry::new(), log);
+    let cache = pool.as_cache();
+    println!(
+        "CatchUpPackage height: {}",
+        cache.catch_up_package().height()
+    );
+    println!("Finalized height: {}", cache.finalized_block().height());
+    println!(
+        "Highest validated block proposal: {:?}",
+        pool.validated().block_proposal().max_height()
+    );
+    println!(
+        "Highest unvalidated block proposal: {:?}",
+        pool.unvalidated().block_proposal().max_height()
+    );
+}
+
+fn print_snapshot(matches: &clap::ArgMatches) {
+    let snapshot = read_snapshot(matches);
+    println!(
+        "{}",
+        to_string(&(
+            &snapshot.replica_version,
+            snapshot.min_height,
+            snapshot.max_height
+        ))
+    );
+    for artifact in snapshot.validated {
+        println!("{}", to_string(&artifact));
+    }
+    for artifact in snapshot.unvalidated {
+        println!("{}", to_string(&artifact));
+    }
+}
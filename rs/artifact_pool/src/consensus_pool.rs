@@ -4,6 +4,7 @@ use crate::{
         get_highest_catch_up_package, get_highest_finalized_block, update_summary_block,
         ConsensusBlockChainImpl, ConsensusCacheImpl,
     },
+    consensus_pool_snapshot::{messages_in_range, ConsensusPoolSnapshot},
     inmemory_pool::InMemoryPoolSection,
     metrics::{LABEL_POOL_TYPE, POOL_TYPE_UNVALIDATED, POOL_TYPE_VALIDATED},
 };
@@ -57,7 +58,7 @@ impl<T> PoolSectionOps<T> {
 }
 
 pub trait InitializablePoolSection: MutablePoolSection<ValidatedConsensusArtifact> {
-    fn insert_cup_with_proto(&self, cup_with_proto: CUPWithOriginalProtobuf);
+    fn insert_cup_with_proto(&mut self, cup_with_proto: CUPWithOriginalProtobuf);
 }
 
 pub trait MutablePoolSection<T>: PoolSection<T> {
@@ -190,7 +191,7 @@ impl PoolMetrics {
 
 pub struct ConsensusPoolImpl {
     validated: Box<dyn InitializablePoolSection + Send + Sync>,
-    unvalidated: Box<dyn MutablePoolSection<UnvalidatedConsensusArtifact> + Send + Sync>,
+    unvalidated: InMemoryPoolSection<UnvalidatedConsensusArtifact>,
     validated_metrics: PoolMetrics,
     unvalidated_metrics: PoolMetrics,
     cache: Arc<ConsensusCacheImpl>,
@@ -200,7 +201,7 @@ pub struct ConsensusPoolImpl {
 // A temporary pool implementation used for genesis initialization.
 pub struct UncachedConsensusPoolImpl {
     pub validated: Box<dyn InitializablePoolSection + Send + Sync>,
-    unvalidated: Box<dyn MutablePoolSection<UnvalidatedConsensusArtifact> + Send + Sync>,
+    unvalidated: InMemoryPoolSection<UnvalidatedConsensusArtifact>,
 }
 
 impl UncachedConsensusPoolImpl {
@@ -228,7 +229,7 @@ impl UncachedConsensusPoolImpl {
 
         UncachedConsensusPoolImpl {
             validated,
-            unvalidated: Box::new(InMemoryPoolSection::new(log)),
+            unvalidated: InMemoryPoolSection::new(log),
         }
     }
 }
@@ -388,6 +389,54 @@ impl ConsensusPoolImpl {
         )
     }
 
+    /// Create an in-memory pool holding the artifacts of the given
+    /// `snapshot`, e.g. to re-run consensus on them offline. The snapshot
+    /// must contain a catch-up package.
+    pub fn from_snapshot(
+        snapshot: ConsensusPoolSnapshot,
+        registry: ic_metrics::MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> ConsensusPoolImpl {
+        let mut validated = InMemoryPoolSection::new(log.clone());
+        let mut ops = PoolSectionOps::new();
+        snapshot
+            .validated
+            .into_iter()
+            .for_each(|artifact| ops.insert(artifact));
+        validated.mutate(ops);
+
+        let mut unvalidated = InMemoryPoolSection::new(log);
+        let mut ops = PoolSectionOps::new();
+        snapshot
+            .unvalidated
+            .into_iter()
+            .for_each(|artifact| ops.insert(artifact));
+        unvalidated.mutate(ops);
+
+        let mut pool = Self::from_uncached(
+            UncachedConsensusPoolImpl {
+                validated: Box::new(validated),
+                unvalidated,
+            },
+            registry,
+        );
+        pool.validated_metrics.update(pool.validated.pool_section());
+        pool.unvalidated_metrics
+            .update(pool.unvalidated.pool_section());
+        pool
+    }
+
+    /// Take a snapshot of the validated and unvalidated artifacts within the
+    /// given height range.
+    pub fn snapshot(&self, range: HeightRange) -> ConsensusPoolSnapshot {
+        let unvalidated: Vec<_> = messages_in_range(self.unvalidated.pool_section(), &range)
+            .into_iter()
+            .filter_map(|msg| self.unvalidated.get_by_hash(msg.get_cm_hash().digest()))
+            .collect();
+        ConsensusPoolSnapshot::new(self.validated.pool_section(), range)
+            .with_unvalidated(unvalidated.into_iter())
+    }
+
     /// Get a copy of ConsensusPoolCache.
     pub fn get_cache(&self) -> Arc<dyn ConsensusPoolCache> {
         Arc::clone(&self.cache) as Arc<_>
@@ -869,6 +918,89 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_snapshot() {
+        ic_test_utilities::artifact_pool_config::with_test_pool_config(|pool_config| {
+            let time_source = FastForwardTimeSource::new();
+            let mut pool = ConsensusPoolImpl::new_from_cup_without_bytes(
+                subnet_test_id(0),
+                make_genesis(ic_types::consensus::dkg::Summary::fake()),
+                pool_config,
+                ic_metrics::MetricsRegistry::new(),
+                no_op_logger(),
+            );
+
+            // Random beacons at heights 1 to 3 are validated, the one at height 4
+            // is unvalidated.
+            let mut random_beacon = RandomBeacon::fake(RandomBeaconContent::new(
+                Height::from(0),
+                CryptoHashOf::from(CryptoHash(Vec::new())),
+            ));
+            let mut changeset = Vec::new();
+            for height in 1..=4 {
+                random_beacon.content.height = Height::from(height);
+                let msg = random_beacon.clone().into_message();
+                pool.insert(UnvalidatedArtifact {
+                    message: msg.clone(),
+                    peer_id: node_test_id(height),
+                    timestamp: time_source.get_relative_time(),
+                });
+                if height < 4 {
+                    changeset.push(ChangeAction::MoveToValidated(msg));
+                }
+            }
+            pool.apply_changes(time_source.as_ref(), changeset);
+
+            // The snapshot contains the random beacons at heights 2 and 3, and the
+            // genesis CUP with its random beacon below the range.
+            let snapshot = pool.snapshot(HeightRange::new(Height::from(2), Height::from(4)));
+            assert_eq!(snapshot.validated.len(), 4);
+            assert_eq!(snapshot.unvalidated.len(), 1);
+            assert_eq!(snapshot.unvalidated[0].peer_id, node_test_id(4));
+            assert_eq!(
+                &snapshot.replica_version,
+                pool.as_cache()
+                    .catch_up_package()
+                    .content
+                    .block
+                    .as_ref()
+                    .version()
+            );
+
+            let mut buffer = Vec::new();
+            snapshot.write_to(&mut buffer).unwrap();
+            let read_snapshot = ConsensusPoolSnapshot::read_from(buffer.as_slice()).unwrap();
+            assert_eq!(read_snapshot, snapshot);
+
+            let loaded_pool = ConsensusPoolImpl::from_snapshot(
+                read_snapshot,
+                ic_metrics::MetricsRegistry::new(),
+                no_op_logger(),
+            );
+            assert_eq!(
+                loaded_pool
+                    .validated()
+                    .random_beacon()
+                    .get_all()
+                    .map(|beacon| beacon.content.height.get())
+                    .collect::<Vec<_>>(),
+                vec![0, 2, 3]
+            );
+            assert_eq!(
+                loaded_pool
+                    .unvalidated()
+                    .random_beacon()
+                    .get_by_height(Height::from(4))
+                    .count(),
+                1
+            );
+            assert_eq!(
+                loaded_pool.as_cache().catch_up_package(),
+                pool.as_cache().catch_up_package()
+            );
+        })
+    }
+
     #[test]
     // We create multiple artifacts for multiple heights, check that all of them are
     // written to the disk and can be restored.
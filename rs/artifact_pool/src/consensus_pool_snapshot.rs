@@ -0,0 +1,155 @@
+//! Portable snapshots of the consensus pool.
+//!
+//! A snapshot holds the validated and unvalidated consensus artifacts of a
+//! pool within a height range, together with the highest catch-up package at
+//! or below that range. Snapshots are meant for debugging stalled subnets:
+//! they can be taken from a pool, written to a file, and loaded into an
+//! in-memory pool with `ConsensusPoolImpl::from_snapshot` to re-run consensus
+//! offline, e.g. with `ic-replay rerun-consensus`.
+//!
+//! Note that the unvalidated section of a pool only lives in memory, so
+//! snapshots taken from a pool opened from disk only contain validated
+//! artifacts.
+
+use ic_consensus_message::ConsensusMessageHashable;
+use ic_interfaces::consensus_pool::{
+    HeightIndexedPool, HeightRange, PoolSection, UnvalidatedConsensusArtifact,
+    ValidatedConsensusArtifact,
+};
+use ic_types::{
+    consensus::{CatchUpPackage, ConsensusMessage, HasHeight, HasVersion},
+    Height, ReplicaVersion,
+};
+use serde::{Deserialize, Serialize};
+use serde_bytes_repr::{ByteFmtDeserializer, ByteFmtSerializer};
+use std::io::{Read, Write};
+
+/// The consensus artifacts of a pool within a height range.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsensusPoolSnapshot {
+    /// The replica version of the highest finalized block in the snapshot,
+    /// i.e. the version the subnet was running when the snapshot was taken.
+    pub replica_version: ReplicaVersion,
+    /// The lowest height of the exported range.
+    pub min_height: Height,
+    /// The highest height of the exported range.
+    pub max_height: Height,
+    /// The validated artifacts, including the highest catch-up package at or
+    /// below `max_height` and its random beacon.
+    pub validated: Vec<ValidatedConsensusArtifact>,
+    /// The unvalidated artifacts.
+    pub unvalidated: Vec<UnvalidatedConsensusArtifact>,
+}
+
+impl ConsensusPoolSnapshot {
+    /// Creates a snapshot of the artifacts in `validated` within `range`.
+    ///
+    /// The highest catch-up package at or below `range.max` and its random
+    /// beacon are always included, so that a pool can be initialized from
+    /// the snapshot. Unvalidated artifacts are added with `with_unvalidated`.
+    pub fn new(
+        validated: &dyn PoolSection<ValidatedConsensusArtifact>,
+        range: HeightRange,
+    ) -> Self {
+        let mut messages = messages_in_range(validated, &range);
+        let cup = validated
+            .catch_up_package()
+            .get_by_height_range(HeightRange::new(Height::from(0), range.max))
+            .last();
+        let replica_version = finalized_replica_version(validated, &range, cup.as_ref());
+        if let Some(cup) = cup {
+            if cup.height() < range.min {
+                messages.push(cup.content.random_beacon.as_ref().clone().into_message());
+                messages.push(cup.into_message());
+            }
+        }
+        let validated = messages
+            .into_iter()
+            .filter_map(|msg| {
+                validated
+                    .get_timestamp(&msg.get_id())
+                    .map(|timestamp| ValidatedConsensusArtifact { msg, timestamp })
+            })
+            .collect();
+        Self {
+            replica_version,
+            min_height: range.min,
+            max_height: range.max,
+            validated,
+            unvalidated: Vec::new(),
+        }
+    }
+
+    /// Adds the given unvalidated artifacts to the snapshot.
+    pub fn with_unvalidated(
+        mut self,
+        unvalidated: impl Iterator<Item = UnvalidatedConsensusArtifact>,
+    ) -> Self {
+        self.unvalidated.extend(unvalidated);
+        self
+    }
+
+    /// Writes the snapshot as JSON, with byte arrays encoded in hex.
+    pub fn write_to<W: Write>(&self, writer: W) -> serde_json::Result<()> {
+        let mut json_ser = serde_json::Serializer::new(writer);
+        self.serialize(ByteFmtSerializer::hex(&mut json_ser))
+    }
+
+    /// Reads a snapshot written by `write_to`.
+    pub fn read_from<R: Read>(reader: R) -> serde_json::Result<Self> {
+        let mut json_de = serde_json::Deserializer::from_reader(reader);
+        Self::deserialize(ByteFmtDeserializer::new_hex(&mut json_de))
+    }
+}
+
+/// Returns the replica version of the highest finalized block in `section` at
+/// or below `range.max`, falling back to the block of the given catch-up
+/// package if there is no finalization above it in the section.
+fn finalized_replica_version(
+    section: &dyn PoolSection<ValidatedConsensusArtifact>,
+    range: &HeightRange,
+    cup: Option<&CatchUpPackage>,
+) -> ReplicaVersion {
+    let cup_height = cup.map(|cup| cup.height()).unwrap_or_default();
+    match section
+        .finalization()
+        .get_by_height_range(HeightRange::new(cup_height, range.max))
+        .last()
+    {
+        Some(finalization) => finalization.version().clone(),
+        None => cup
+            .map(|cup| cup.content.block.as_ref().version().clone())
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the consensus messages of all types in `section` within `range`.
+pub(crate) fn messages_in_range<T>(
+    section: &dyn PoolSection<T>,
+    range: &HeightRange,
+) -> Vec<ConsensusMessage> {
+    fn collect<T: ConsensusMessageHashable>(
+        pool: &dyn HeightIndexedPool<T>,
+        range: &HeightRange,
+        messages: &mut Vec<ConsensusMessage>,
+    ) {
+        messages.extend(
+            pool.get_by_height_range(HeightRange::new(range.min, range.max))
+                .map(ConsensusMessageHashable::into_message),
+        );
+    }
+
+    let mut messages = Vec::new();
+    collect(section.random_beacon(), range, &mut messages);
+    collect(section.random_tape(), range, &mut messages);
+    collect(section.finalization(), range, &mut messages);
+    collect(section.notarization(), range, &mut messages);
+    collect(section.catch_up_package(), range, &mut messages);
+    collect(section.block_proposal(), range, &mut messages);
+    collect(section.random_beacon_share(), range, &mut messages);
+    collect(section.random_tape_share(), range, &mut messages);
+    collect(section.notarization_share(), range, &mut messages);
+    collect(section.finalization_share(), range, &mut messages);
+    collect(section.catch_up_package_share(), range, &mut messages);
+    messages
+}
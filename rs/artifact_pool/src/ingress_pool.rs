@@ -2,7 +2,8 @@
 /// Logically it can be viewed as part of the artifact pool
 /// But we keep it separated for code readability
 use crate::{
-    metrics::{PoolMetrics, POOL_TYPE_UNVALIDATED, POOL_TYPE_VALIDATED},
+    ingress_quotas::{IngressQuotas, QuotaKey},
+    metrics::{IngressQuotaMetrics, PoolMetrics, POOL_TYPE_UNVALIDATED, POOL_TYPE_VALIDATED},
     peer_index::PeerIndex,
 };
 use ic_config::artifact_pool::ArtifactPoolConfig;
@@ -142,6 +143,9 @@ pub struct IngressPoolImpl {
     unvalidated: IngressPoolSection<UnvalidatedIngressArtifact>,
     // Track unvalidated pool quota usage only
     peer_index: PeerIndex,
+    // Track byte quotas per peer, sender and canister across both sections
+    quotas: IngressQuotas,
+    quota_metrics: IngressQuotaMetrics,
     ingress_pool_size_threshold: Option<usize>,
     ingress_messages_throttled: IntCounter,
     log: ReplicaLogger,
@@ -161,6 +165,8 @@ impl IngressPoolImpl {
                 "ingress_messages_throttled",
                 "Number of throttled ingress messages",
             ),
+            quotas: IngressQuotas::new(&config),
+            quota_metrics: IngressQuotaMetrics::new(metrics_registry.clone()),
             validated: IngressPoolSection::new(PoolMetrics::new(
                 metrics_registry.clone(),
                 POOL_INGRESS,
@@ -194,6 +200,27 @@ impl IngressPoolImpl {
             }
         }
     }
+
+    /// Evict the given messages, which exceeded one of the byte quotas, from
+    /// whichever section holds them.
+    fn evict(&mut self, evicted: Vec<(IngressMessageId, QuotaKey)>) {
+        for (message_id, quota) in evicted {
+            let size = match self.remove_unvalidated(&message_id) {
+                Some((_, size)) => size,
+                None => match self.validated.remove(&message_id) {
+                    Some(artifact) => artifact.msg.signed_ingress.count_bytes(),
+                    None => continue,
+                },
+            };
+            self.quota_metrics.observe_eviction(quota.kind(), size);
+            debug!(
+                self.log,
+                "Ingress pool: evict {} bytes exceeding the {} quota",
+                size,
+                quota.kind()
+            );
+        }
+    }
 }
 
 impl IngressPool for IngressPoolImpl {
@@ -224,8 +251,17 @@ impl MutableIngressPool for IngressPoolImpl {
             ingress_message.message_id => format!("{}", ingress_pool_obj.message_id)
         );
 
+        let message_id = IngressMessageId::from(&ingress_pool_obj);
+        let evicted = self.quotas.insert(
+            message_id.clone(),
+            peer_id,
+            ingress_pool_obj.signed_ingress.sender(),
+            ingress_pool_obj.signed_ingress.canister_id(),
+            size,
+            timestamp,
+        );
         self.unvalidated.insert(
-            IngressMessageId::from(&ingress_pool_obj),
+            message_id,
             UnvalidatedIngressArtifact {
                 message: ingress_pool_obj,
                 peer_id,
@@ -236,6 +272,9 @@ impl MutableIngressPool for IngressPoolImpl {
             self.log,
             "Ingress pool: insert {} bytes into unvalidated", size
         );
+        // Evict the oldest messages of any bucket over its quota, rather than
+        // rejecting the new message.
+        self.evict(evicted);
     }
 
     /// Apply changeset to the Ingress Pool
@@ -270,6 +309,7 @@ impl MutableIngressPool for IngressPoolImpl {
                 ChangeAction::RemoveFromUnvalidated(message_id) => {
                     match self.remove_unvalidated(&message_id) {
                         Some((_, size)) => {
+                            self.quotas.remove(&message_id);
                             debug!(
                                 self.log,
                                 "Ingress pool: remove {} bytes from unvalidated", size
@@ -287,6 +327,7 @@ impl MutableIngressPool for IngressPoolImpl {
                 ChangeAction::RemoveFromValidated(message_id) => {
                     match self.validated.remove(&message_id) {
                         Some(artifact) => {
+                            self.quotas.remove(&message_id);
                             let size = artifact.msg.signed_ingress.count_bytes();
                             debug!(
                                 self.log,
@@ -303,10 +344,14 @@ impl MutableIngressPool for IngressPoolImpl {
                     }
                 }
                 ChangeAction::PurgeBelowExpiry(expiry) => {
-                    let _unused = self.validated.purge_below(expiry);
+                    for artifact in self.validated.purge_below(expiry) {
+                        self.quotas.remove(&IngressMessageId::from(&artifact.msg));
+                    }
                     for artifact in self.unvalidated.purge_below(expiry) {
                         let size = artifact.message.signed_ingress.count_bytes();
                         self.peer_index.remove(artifact.peer_id, size);
+                        self.quotas
+                            .remove(&IngressMessageId::from(&artifact.message));
                     }
                 }
             }
@@ -396,7 +441,9 @@ mod tests {
     use ic_constants::MAX_INGRESS_TTL;
     use ic_interfaces::time_source::TimeSource;
     use ic_test_utilities::{
-        mock_time, types::ids::node_test_id, types::messages::SignedIngressBuilder,
+        mock_time,
+        types::ids::{node_test_id, user_test_id},
+        types::messages::SignedIngressBuilder,
         with_test_replica_logger, FastForwardTimeSource,
     };
     use ic_types::artifact::IngressMessageAttribute;
@@ -723,4 +770,59 @@ mod tests {
             })
         })
     }
+
+    #[test]
+    fn test_sender_quota_evicts_oldest_messages() {
+        with_test_replica_logger(|log| {
+            ic_test_utilities::artifact_pool_config::with_test_pool_config(|mut pool_config| {
+                let messages: Vec<_> = (0..3)
+                    .map(|nonce| {
+                        SignedIngressBuilder::new()
+                            .sender(user_test_id(1))
+                            .nonce(nonce)
+                            .build()
+                    })
+                    .collect();
+                let other_sender = SignedIngressBuilder::new()
+                    .sender(user_test_id(2))
+                    .nonce(3)
+                    .build();
+                let size = messages[0].count_bytes();
+                // Room for two messages per sender.
+                pool_config.ingress_pool_max_bytes_per_sender = Some(2 * size + size / 2);
+                let metrics_registry = MetricsRegistry::new();
+                let mut ingress_pool = IngressPoolImpl::new(pool_config, metrics_registry, log);
+
+                let ids: Vec<_> = messages.iter().map(IngressMessageId::from).collect();
+                let other_id = IngressMessageId::from(&other_sender);
+                for (i, message) in messages.into_iter().enumerate() {
+                    ingress_pool.insert(UnvalidatedArtifact {
+                        message,
+                        peer_id: node_test_id(0),
+                        timestamp: mock_time() + Duration::from_secs(i as u64),
+                    });
+                    if i == 0 {
+                        ingress_pool.insert(UnvalidatedArtifact {
+                            message: other_sender.clone(),
+                            peer_id: node_test_id(0),
+                            timestamp: mock_time(),
+                        });
+                    }
+                }
+
+                assert!(!ingress_pool.contains(&ids[0]));
+                assert!(ingress_pool.contains(&ids[1]));
+                assert!(ingress_pool.contains(&ids[2]));
+                assert!(ingress_pool.contains(&other_id));
+                assert_eq!(
+                    ingress_pool
+                        .quota_metrics
+                        .evicted_messages
+                        .with_label_values(&["sender"])
+                        .get(),
+                    1
+                );
+            })
+        })
+    }
 }
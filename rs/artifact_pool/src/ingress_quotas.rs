@@ -0,0 +1,295 @@
+use ic_config::artifact_pool::ArtifactPoolConfig;
+use ic_types::{artifact::IngressMessageId, CanisterId, NodeId, Time, UserId};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A bucket of ingress messages that share a byte quota.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum QuotaKey {
+    /// Messages received from the same peer.
+    Peer(NodeId),
+    /// Messages sent by the same principal.
+    Sender(UserId),
+    /// Messages to the same canister.
+    Canister(CanisterId),
+}
+
+impl QuotaKey {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            QuotaKey::Peer(_) => "peer",
+            QuotaKey::Sender(_) => "sender",
+            QuotaKey::Canister(_) => "canister",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    keys: [QuotaKey; 3],
+    size: usize,
+    timestamp: Time,
+}
+
+#[derive(Clone, Default)]
+struct Bucket {
+    bytes: usize,
+    // Ordered by arrival, so that the oldest messages are evicted first.
+    messages: BTreeSet<(Time, IngressMessageId)>,
+}
+
+/// IngressQuotas tracks the bytes of the ingress messages in the pool, both
+/// validated and unvalidated, per originating peer, sender and target
+/// canister, and determines which messages to evict when a quota is exceeded.
+#[derive(Clone)]
+pub(crate) struct IngressQuotas {
+    max_bytes_per_peer: Option<usize>,
+    max_bytes_per_sender: Option<usize>,
+    max_bytes_per_canister: Option<usize>,
+    entries: BTreeMap<IngressMessageId, Entry>,
+    buckets: BTreeMap<QuotaKey, Bucket>,
+}
+
+impl IngressQuotas {
+    pub(crate) fn new(config: &ArtifactPoolConfig) -> Self {
+        Self {
+            max_bytes_per_peer: config.ingress_pool_max_bytes_per_peer,
+            max_bytes_per_sender: config.ingress_pool_max_bytes_per_sender,
+            max_bytes_per_canister: config.ingress_pool_max_bytes_per_canister,
+            entries: BTreeMap::new(),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    fn max_bytes(&self, key: &QuotaKey) -> Option<usize> {
+        match key {
+            QuotaKey::Peer(_) => self.max_bytes_per_peer,
+            QuotaKey::Sender(_) => self.max_bytes_per_sender,
+            QuotaKey::Canister(_) => self.max_bytes_per_canister,
+        }
+    }
+
+    /// Accounts for a message added to the pool, and returns the messages to
+    /// evict, oldest first, so that all quotas hold again, together with the
+    /// quota each of them was evicted for. This may include the added message
+    /// itself. The returned messages are no longer accounted for.
+    pub(crate) fn insert(
+        &mut self,
+        message_id: IngressMessageId,
+        peer_id: NodeId,
+        sender: UserId,
+        canister_id: CanisterId,
+        size: usize,
+        timestamp: Time,
+    ) -> Vec<(IngressMessageId, QuotaKey)> {
+        self.remove(&message_id);
+        let keys = [
+            QuotaKey::Peer(peer_id),
+            QuotaKey::Sender(sender),
+            QuotaKey::Canister(canister_id),
+        ];
+        for key in keys.iter() {
+            let bucket = self.buckets.entry(*key).or_default();
+            bucket.bytes += size;
+            bucket.messages.insert((timestamp, message_id.clone()));
+        }
+        self.entries.insert(
+            message_id,
+            Entry {
+                keys,
+                size,
+                timestamp,
+            },
+        );
+
+        let mut evicted = Vec::new();
+        for key in keys.iter() {
+            let max_bytes = match self.max_bytes(key) {
+                Some(max_bytes) => max_bytes,
+                None => continue,
+            };
+            while let Some(bucket) = self.buckets.get(key) {
+                if bucket.bytes <= max_bytes {
+                    break;
+                }
+                let (_, oldest) = bucket
+                    .messages
+                    .iter()
+                    .next()
+                    .cloned()
+                    .expect("A bucket with bytes has messages.");
+                self.remove(&oldest);
+                evicted.push((oldest, *key));
+            }
+        }
+        evicted
+    }
+
+    /// Stops accounting for a message that was removed from the pool.
+    pub(crate) fn remove(&mut self, message_id: &IngressMessageId) {
+        let entry = match self.entries.remove(message_id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        for key in entry.keys.iter() {
+            if let Some(bucket) = self.buckets.get_mut(key) {
+                bucket.bytes -= entry.size;
+                bucket
+                    .messages
+                    .remove(&(entry.timestamp, message_id.clone()));
+                if bucket.messages.is_empty() {
+                    self.buckets.remove(key);
+                }
+            }
+        }
+    }
+
+    /// Returns the bytes accounted for `key`.
+    #[cfg(test)]
+    pub(crate) fn bytes(&self, key: &QuotaKey) -> usize {
+        self.buckets.get(key).map_or(0, |bucket| bucket.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::{
+        mock_time,
+        types::ids::{canister_test_id, message_test_id, node_test_id, user_test_id},
+    };
+    use std::time::Duration;
+
+    fn quotas(
+        peer: Option<usize>,
+        sender: Option<usize>,
+        canister: Option<usize>,
+    ) -> IngressQuotas {
+        let mut config = ArtifactPoolConfig::new(std::path::PathBuf::from("/tmp"));
+        config.ingress_pool_max_bytes_per_peer = peer;
+        config.ingress_pool_max_bytes_per_sender = sender;
+        config.ingress_pool_max_bytes_per_canister = canister;
+        IngressQuotas::new(&config)
+    }
+
+    fn id(n: u64) -> IngressMessageId {
+        IngressMessageId::new(mock_time(), message_test_id(n))
+    }
+
+    fn at(seconds: u64) -> Time {
+        mock_time() + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn unlimited_quotas_never_evict() {
+        let mut quotas = quotas(None, None, None);
+        for n in 0..100 {
+            let evicted = quotas.insert(
+                id(n),
+                node_test_id(0),
+                user_test_id(0),
+                canister_test_id(0),
+                1000,
+                at(n),
+            );
+            assert!(evicted.is_empty());
+        }
+        assert_eq!(quotas.bytes(&QuotaKey::Peer(node_test_id(0))), 100_000);
+    }
+
+    #[test]
+    fn evicts_oldest_messages_of_the_offending_sender() {
+        let mut quotas = quotas(None, Some(250), None);
+        quotas.insert(
+            id(1),
+            node_test_id(0),
+            user_test_id(1),
+            canister_test_id(0),
+            100,
+            at(1),
+        );
+        quotas.insert(
+            id(2),
+            node_test_id(0),
+            user_test_id(2),
+            canister_test_id(0),
+            100,
+            at(2),
+        );
+        quotas.insert(
+            id(3),
+            node_test_id(0),
+            user_test_id(1),
+            canister_test_id(0),
+            100,
+            at(3),
+        );
+        let evicted = quotas.insert(
+            id(4),
+            node_test_id(0),
+            user_test_id(1),
+            canister_test_id(0),
+            100,
+            at(4),
+        );
+        assert_eq!(evicted, vec![(id(1), QuotaKey::Sender(user_test_id(1)))]);
+        assert_eq!(quotas.bytes(&QuotaKey::Sender(user_test_id(1))), 200);
+        assert_eq!(quotas.bytes(&QuotaKey::Sender(user_test_id(2))), 100);
+        assert_eq!(quotas.bytes(&QuotaKey::Canister(canister_test_id(0))), 300);
+    }
+
+    #[test]
+    fn evicts_oversized_message_itself() {
+        let mut quotas = quotas(Some(500), None, Some(500));
+        quotas.insert(
+            id(1),
+            node_test_id(1),
+            user_test_id(1),
+            canister_test_id(1),
+            100,
+            at(1),
+        );
+        let evicted = quotas.insert(
+            id(2),
+            node_test_id(2),
+            user_test_id(2),
+            canister_test_id(2),
+            600,
+            at(2),
+        );
+        assert_eq!(evicted, vec![(id(2), QuotaKey::Peer(node_test_id(2)))]);
+        assert_eq!(quotas.bytes(&QuotaKey::Peer(node_test_id(2))), 0);
+        assert_eq!(quotas.bytes(&QuotaKey::Peer(node_test_id(1))), 100);
+    }
+
+    #[test]
+    fn removed_messages_free_their_quota() {
+        let mut quotas = quotas(None, None, Some(200));
+        quotas.insert(
+            id(1),
+            node_test_id(0),
+            user_test_id(0),
+            canister_test_id(0),
+            100,
+            at(1),
+        );
+        quotas.insert(
+            id(2),
+            node_test_id(0),
+            user_test_id(0),
+            canister_test_id(0),
+            100,
+            at(2),
+        );
+        quotas.remove(&id(1));
+        let evicted = quotas.insert(
+            id(3),
+            node_test_id(0),
+            user_test_id(0),
+            canister_test_id(0),
+            100,
+            at(3),
+        );
+        assert!(evicted.is_empty());
+        assert_eq!(quotas.bytes(&QuotaKey::Canister(canister_test_id(0))), 200);
+    }
+}
@@ -1,11 +1,13 @@
 use crate::{
-    consensus_pool::{MutablePoolSection, PoolSectionOp, PoolSectionOps},
+    consensus_pool::{InitializablePoolSection, MutablePoolSection, PoolSectionOp, PoolSectionOps},
     height_index::{HeightIndex, Indexes, SelectIndex},
 };
 use ic_consensus_message::ConsensusMessageHashable;
 use ic_interfaces::{
     artifact_pool::{HasTimestamp, IntoInner},
-    consensus_pool::{HeightIndexedPool, HeightRange, OnlyError, PoolSection},
+    consensus_pool::{
+        HeightIndexedPool, HeightRange, OnlyError, PoolSection, ValidatedConsensusArtifact,
+    },
 };
 use ic_logger::{warn, ReplicaLogger};
 use ic_types::{
@@ -318,6 +320,18 @@ impl<T: IntoInner<ConsensusMessage> + HasTimestamp + Clone> MutablePoolSection<T
     }
 }
 
+impl InitializablePoolSection for InMemoryPoolSection<ValidatedConsensusArtifact> {
+    /// Insert a cup. The original protobuf is not kept, as in-memory pool
+    /// sections are never persisted.
+    fn insert_cup_with_proto(&mut self, cup_with_proto: CUPWithOriginalProtobuf) {
+        let timestamp = cup_with_proto.cup.content.block.as_ref().context.time;
+        self.insert(ValidatedConsensusArtifact {
+            msg: cup_with_proto.cup.into_message(),
+            timestamp,
+        });
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
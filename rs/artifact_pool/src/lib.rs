@@ -2,10 +2,12 @@ pub mod canister_http_pool;
 pub mod certification_pool;
 pub mod consensus_pool;
 mod consensus_pool_cache;
+pub mod consensus_pool_snapshot;
 pub mod dkg_pool;
 pub mod ecdsa_pool;
 mod height_index;
 pub mod ingress_pool;
+mod ingress_quotas;
 mod inmemory_pool;
 mod metrics;
 mod peer_index;
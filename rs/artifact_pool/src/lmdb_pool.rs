@@ -589,7 +589,7 @@ impl<Artifact: PoolArtifact> PersistentHeightIndexedPool<Artifact> {
 
 impl InitializablePoolSection for PersistentHeightIndexedPool<ConsensusMessage> {
     /// Insert a cup with the original bytes from which that cup was received.
-    fn insert_cup_with_proto(&self, cup_with_proto: CUPWithOriginalProtobuf) {
+    fn insert_cup_with_proto(&mut self, cup_with_proto: CUPWithOriginalProtobuf) {
         let mut tx = self
             .db_env
             .begin_rw_txn()
@@ -8,6 +8,7 @@ pub const LABEL_POOL: &str = "pool";
 pub const LABEL_POOL_TYPE: &str = "pool_type";
 pub const POOL_TYPE_VALIDATED: &str = "validated";
 pub const POOL_TYPE_UNVALIDATED: &str = "unvalidated";
+pub const LABEL_QUOTA: &str = "quota";
 
 /// Metrics for a given artifact pool's validated/unvalidated section.
 #[derive(Clone)]
@@ -136,3 +137,35 @@ impl EcdsaPoolMetrics {
         self.persistence_errors.with_label_values(&[label]).inc();
     }
 }
+
+/// Metrics for the evictions of ingress messages that exceed the byte quotas
+/// of the ingress pool.
+#[derive(Clone)]
+pub struct IngressQuotaMetrics {
+    pub evicted_messages: IntCounterVec,
+    pub evicted_bytes: IntCounterVec,
+}
+
+impl IngressQuotaMetrics {
+    pub fn new(metrics_registry: MetricsRegistry) -> Self {
+        Self {
+            evicted_messages: metrics_registry.int_counter_vec(
+                "ingress_pool_quota_evicted_messages",
+                "Ingress messages evicted from the pool because the given quota was exceeded",
+                &[LABEL_QUOTA],
+            ),
+            evicted_bytes: metrics_registry.int_counter_vec(
+                "ingress_pool_quota_evicted_bytes",
+                "Bytes of ingress messages evicted from the pool because the given quota was exceeded",
+                &[LABEL_QUOTA],
+            ),
+        }
+    }
+
+    pub fn observe_eviction(&self, quota: &str, size_bytes: usize) {
+        self.evicted_messages.with_label_values(&[quota]).inc();
+        self.evicted_bytes
+            .with_label_values(&[quota])
+            .inc_by(size_bytes as u64);
+    }
+}
@@ -387,7 +387,7 @@ impl PersistentHeightIndexedPool<ConsensusMessage> {
 
 impl InitializablePoolSection for PersistentHeightIndexedPool<ConsensusMessage> {
     /// Insert a cup with the original bytes from which that cup was received.
-    fn insert_cup_with_proto(&self, cup_with_proto: CUPWithOriginalProtobuf) {
+    fn insert_cup_with_proto(&mut self, cup_with_proto: CUPWithOriginalProtobuf) {
         let height = cup_with_proto.cup.height();
         let info = &CATCH_UP_PACKAGE_CF_INFO;
         let key = make_key(height.get(), &cup_with_proto.cup.get_cm_hash().digest().0);
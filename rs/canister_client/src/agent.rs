@@ -1,25 +1,39 @@
 //! An agent to talk to the Internet Computer through the public endpoints.
 use crate::{
-    cbor::{parse_canister_query_response, parse_read_state_response, RequestStatus},
+    cbor::{
+        parse_canister_query_response, parse_read_state_certificate,
+        request_status_from_certificate, RequestStatus,
+    },
     http_client::{HttpClient, HttpClientConfig},
+    signer::Signer,
 };
 use backoff::backoff::Backoff;
-use ed25519_dalek::{Keypair, Signer, KEYPAIR_LENGTH};
+use ed25519_dalek::{Keypair, KEYPAIR_LENGTH};
+use ic_certified_vars::{verify_read_state_certificate, CertificateValidationError};
 use ic_crypto_sha::Sha256;
 use ic_crypto_tree_hash::Path;
+use ic_crypto_utils_threshold_sig::parse_threshold_sig_key_from_der;
 use ic_interfaces::crypto::DOMAIN_IC_REQUEST;
 use ic_protobuf::types::v1 as pb;
 use ic_types::{
     consensus::catchup::CatchUpPackageParam,
+    crypto::threshold_sig::ThresholdSigPublicKey,
     messages::{
-        Blob, HttpCallContent, HttpQueryContent, HttpReadStateContent, HttpRequestEnvelope,
-        HttpStatusResponse, MessageId, ReplicaHealthStatus,
+        Blob, Certificate, HttpCallContent, HttpQueryContent, HttpReadStateContent,
+        HttpRequestEnvelope, HttpStatusResponse, MessageId, ReplicaHealthStatus, SignedDelegation,
     },
+    time::current_time,
     CanisterId, PrincipalId,
 };
 use prost::Message;
 use serde_cbor::value::Value as CBOR;
-use std::{error::Error, fmt, sync::Arc, time::Duration, time::Instant};
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+    time::Instant,
+};
 use tokio::time::sleep_until;
 use url::Url;
 
@@ -52,6 +66,11 @@ pub fn update_path(cid: CanisterId) -> String {
     format!("api/v2/canister/{}/call", cid)
 }
 
+/// The HTTP path for update calls that wait for the certified response.
+pub fn sync_update_path(cid: CanisterId) -> String {
+    format!("api/v3/canister/{}/call", cid)
+}
+
 const NODE_STATUS_PATH: &str = "api/v2/status";
 const CATCH_UP_PACKAGE_PATH: &str = "/_/catch_up_package";
 
@@ -109,6 +128,9 @@ pub enum Sender {
         /// Function that abstracts the external HSM.
         sign: SignF,
     },
+    /// The sender is authenticated by a reusable [`Signer`], e.g. a PEM file,
+    /// a PKCS#11 token or a delegation chain.
+    Signer(Arc<dyn Signer>),
     /// The anonymous sender is used (no signature).
     Anonymous,
     /// Principal ID (no signature)
@@ -130,6 +152,10 @@ impl Sender {
         Sender::ExternalHsm { pub_key, sign }
     }
 
+    pub fn from_signer(signer: Arc<dyn Signer>) -> Self {
+        Sender::Signer(signer)
+    }
+
     pub fn from_principal_id(principal_id: PrincipalId) -> Self {
         Sender::PrincipalId(principal_id)
     }
@@ -145,6 +171,9 @@ impl Sender {
                 ),
             },
             Self::ExternalHsm { pub_key, .. } => PrincipalId::new_self_authenticating(pub_key),
+            Self::Signer(signer) => {
+                PrincipalId::new_self_authenticating(&signer.sender_pubkey_der())
+            }
             Self::Anonymous => PrincipalId::new_anonymous(),
             Self::PrincipalId(id) => *id,
         }
@@ -159,7 +188,7 @@ impl Sender {
 
     pub fn sign(&self, msg: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
         match self {
-            Self::KeyPair(keypair) => Ok(Some(keypair.get().sign(msg).to_bytes().to_vec())),
+            Self::KeyPair(keypair) => keypair.get().sign(msg).map(Some),
             Self::SigKeys(sig_keys) => match sig_keys {
                 SigKeys::EcdsaSecp256k1(key_pair) => {
                     // ECDSA CLib impl. does not hash the message (as hash algorithm can vary
@@ -175,6 +204,7 @@ impl Sender {
                 }
             },
             Self::ExternalHsm { sign, .. } => sign(msg).map(Some),
+            Self::Signer(signer) => signer.sign(msg).map(Some),
             Self::Anonymous => Ok(None),
             Self::PrincipalId(_) => Ok(None),
         }
@@ -191,10 +221,18 @@ impl Sender {
                 }
             },
             Self::ExternalHsm { pub_key, .. } => Some(pub_key.clone()),
+            Self::Signer(signer) => Some(signer.sender_pubkey_der()),
             Self::Anonymous => None,
             Self::PrincipalId(_) => None,
         }
     }
+
+    pub fn sender_delegation(&self) -> Option<Vec<SignedDelegation>> {
+        match self {
+            Self::Signer(signer) => signer.sender_delegation(),
+            _ => None,
+        }
+    }
 }
 
 pub fn get_backoff_policy() -> backoff::ExponentialBackoff {
@@ -210,6 +248,31 @@ pub fn get_backoff_policy() -> backoff::ExponentialBackoff {
     }
 }
 
+/// An error returned by a `read_state` request.
+#[derive(Debug)]
+pub enum ReadStateError {
+    /// The request failed or its response could not be decoded.
+    Request(String),
+    /// The root key to verify the certificate against is not available.
+    RootKeyUnavailable(String),
+    /// The certificate in the response failed verification.
+    InvalidCertificate(CertificateValidationError),
+}
+
+impl fmt::Display for ReadStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(err) => write!(f, "read_state request failed: {}", err),
+            Self::RootKeyUnavailable(err) => write!(f, "root key is not available: {}", err),
+            Self::InvalidCertificate(err) => {
+                write!(f, "read_state certificate verification failed: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for ReadStateError {}
+
 /// An agent to talk to the Internet Computer through the public endpoints.
 #[derive(Clone)]
 pub struct Agent {
@@ -234,6 +297,22 @@ pub struct Agent {
     /// The values that any 'sender' field should have when issuing
     /// calls with the user corresponding to this Agent.
     pub sender_field: Blob,
+
+    // The root key `read_state` certificates are verified against. Unless it
+    // is set with `with_root_key`, certificates are rejected, or, if
+    // `fetch_root_key` is set, the root key reported by the replica's status
+    // endpoint is fetched and cached on first use.
+    root_key: Arc<Mutex<Option<ThresholdSigPublicKey>>>,
+
+    // Whether the root key may be fetched from the replica.
+    fetch_root_key: bool,
+
+    // Whether `read_state` certificates are verified.
+    verify_certificates: bool,
+
+    // Whether updates are submitted to the `/api/v3` endpoint, which returns
+    // the certified response if the call completes quickly enough.
+    sync_calls: bool,
 }
 
 impl fmt::Debug for Agent {
@@ -280,7 +359,12 @@ impl Agent {
 
     /// This is needed by rust_canister tests
     pub fn new_for_test(&self, sender: Sender) -> Self {
-        Self::build_agent(self.url.clone(), self.http_client.clone(), sender)
+        Self {
+            root_key: self.root_key.clone(),
+            fetch_root_key: self.fetch_root_key,
+            verify_certificates: self.verify_certificates,
+            ..Self::build_agent(self.url.clone(), self.http_client.clone(), sender)
+        }
     }
 
     /// Helper to create the agent
@@ -293,6 +377,10 @@ impl Agent {
             http_client,
             sender,
             sender_field,
+            root_key: Arc::new(Mutex::new(None)),
+            fetch_root_key: false,
+            verify_certificates: true,
+            sync_calls: false,
         }
     }
 
@@ -308,6 +396,45 @@ impl Agent {
         self
     }
 
+    /// Sets the root key that `read_state` certificates are verified against.
+    ///
+    /// Unless a root key is set, or fetching it is explicitly enabled with
+    /// `with_fetched_root_key`, all certificates are rejected.
+    pub fn with_root_key(mut self, root_key: ThresholdSigPublicKey) -> Self {
+        self.root_key = Arc::new(Mutex::new(Some(root_key)));
+        self
+    }
+
+    /// Verifies `read_state` certificates against the root key reported by
+    /// the replica's status endpoint, unless a root key is set with
+    /// `with_root_key`.
+    ///
+    /// The status endpoint is not authenticated, so this must only be used
+    /// in development and against test networks.
+    pub fn with_fetched_root_key(mut self) -> Self {
+        self.fetch_root_key = true;
+        self
+    }
+
+    /// Disables the verification of `read_state` certificates.
+    ///
+    /// This must only be used in tests against replicas that don't produce
+    /// valid certificates.
+    pub fn without_certificate_verification_for_testing(mut self) -> Self {
+        self.verify_certificates = false;
+        self
+    }
+
+    /// Makes `execute_update` submit calls to the `/api/v3` endpoint, which
+    /// waits for the call to complete and returns the certified response. If
+    /// the call doesn't complete in time, the agent falls back to polling.
+    ///
+    /// The replica must support the `/api/v3` call endpoint.
+    pub fn with_sync_calls(mut self) -> Self {
+        self.sync_calls = true;
+        self
+    }
+
     /// Queries the cup endpoint given the provided CatchUpPackageParams.
     pub async fn query_cup_endpoint(
         &self,
@@ -388,15 +515,34 @@ impl Agent {
         let (http_body, request_id) = self
             .prepare_update(canister_id, method, arguments, nonce)
             .map_err(|err| format!("{}", err))?;
-        self.http_client
-            .post_with_response(
+        let path = if self.sync_calls {
+            sync_update_path(*canister_id)
+        } else {
+            update_path(*canister_id)
+        };
+        let (response, status) = self
+            .http_client
+            .post_with_status_and_response(
                 &self.url,
-                &update_path(*canister_id),
+                &path,
                 http_body,
                 tokio::time::Instant::from_std(deadline),
             )
             .await?;
 
+        // A synchronous call that completed in time returns the certificate of
+        // its status, as `read_state` does.
+        if self.sync_calls && status == hyper::StatusCode::OK {
+            let certificate = bytes_to_cbor(response).and_then(parse_read_state_certificate)?;
+            self.verify_certificate(&certificate, canister_id)
+                .await
+                .map_err(|e| format!("Unexpected error: {:?}", e))?;
+            let request_status = request_status_from_certificate(&request_id, certificate)?;
+            return final_update_result(request_status).unwrap_or_else(|| {
+                Err("The replica responded before the call completed.".to_string())
+            });
+        }
+
         // Check request status for the first time after 2s (~ time between blocks)
         let mut next_poll_time = Instant::now() + Duration::from_secs(2);
 
@@ -409,24 +555,11 @@ impl Agent {
                 .wait_ingress(request_id.clone(), deadline, canister_id)
                 .await
             {
-                Ok(request_status) => match request_status.status.as_ref() {
-                    "replied" => {
-                        return Ok(request_status.reply);
-                    }
-                    "done" => {
-                        return Err(
-                            "The call has completed but the reply/reject data has been pruned."
-                                .to_string(),
-                        );
-                    }
-                    "unknown" | "received" | "processing" => {}
-                    _ => {
-                        return Err(format!(
-                            "unexpected result: {:?} - {:?}",
-                            request_status.status, request_status.reject_message
-                        ))
+                Ok(request_status) => {
+                    if let Some(result) = final_update_result(request_status) {
+                        return result;
                     }
-                },
+                }
                 Err(e) => return Err(format!("Unexpected error: {:?}", e)),
             }
         }
@@ -436,33 +569,87 @@ impl Agent {
         ))
     }
 
-    /// Requests the status of a pending request once.
-    ///
-    /// This is intended to be used in a loop until a final state is reached.
+    /// Reads the given `paths` of the state tree through the `read_state`
+    /// endpoint of `effective_canister_id` and returns the certificate.
     ///
-    /// Returns the entire CBOR value from the response, without trying to
-    /// interpret it.
-    async fn request_status_once(
+    /// Unless disabled for testing, the certificate is verified against the
+    /// root key: its signature, the NNS delegation, the canister ranges of the
+    /// delegated subnet and the certificate time.
+    pub async fn read_state(
         &self,
-        request_id: MessageId,
+        effective_canister_id: &CanisterId,
+        paths: &[Path],
         deadline: Instant,
-        canister_id: &CanisterId,
-    ) -> Result<CBOR, String> {
-        let path = Path::new(vec!["request_status".into(), request_id.into()]);
-        let status_request_body = self
-            .prepare_read_state(&[path])
-            .map_err(|e| format!("Failed to prepare read state: {:?}", e))?;
+    ) -> Result<Certificate, ReadStateError> {
+        let read_state_body = self.prepare_read_state(paths).map_err(|e| {
+            ReadStateError::Request(format!("Failed to prepare read state: {:?}", e))
+        })?;
 
         let bytes = self
             .http_client
             .post_with_response(
                 &self.url,
-                &read_state_path(*canister_id),
-                status_request_body,
+                &read_state_path(*effective_canister_id),
+                read_state_body,
                 tokio::time::Instant::from_std(deadline),
             )
+            .await
+            .map_err(ReadStateError::Request)?;
+        let certificate = bytes_to_cbor(bytes)
+            .and_then(parse_read_state_certificate)
+            .map_err(ReadStateError::Request)?;
+        self.verify_certificate(&certificate, effective_canister_id)
             .await?;
-        bytes_to_cbor(bytes)
+        Ok(certificate)
+    }
+
+    /// Verifies `certificate` against the root key, unless disabled for
+    /// testing.
+    async fn verify_certificate(
+        &self,
+        certificate: &Certificate,
+        effective_canister_id: &CanisterId,
+    ) -> Result<(), ReadStateError> {
+        if self.verify_certificates {
+            let root_key = self.trusted_root_key().await?;
+            verify_read_state_certificate(
+                certificate,
+                effective_canister_id,
+                &root_key,
+                current_time(),
+            )
+            .map_err(ReadStateError::InvalidCertificate)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the root key set with `with_root_key` or, if none was set and
+    /// fetching it is enabled, the root key reported by the replica.
+    async fn trusted_root_key(&self) -> Result<ThresholdSigPublicKey, ReadStateError> {
+        let cached_root_key = *self.root_key.lock().unwrap();
+        if let Some(root_key) = cached_root_key {
+            return Ok(root_key);
+        }
+        if !self.fetch_root_key {
+            return Err(ReadStateError::RootKeyUnavailable(
+                "no root key is configured and fetching it from the replica is not enabled"
+                    .to_string(),
+            ));
+        }
+        let der = self
+            .root_key()
+            .await
+            .map_err(ReadStateError::RootKeyUnavailable)?
+            .ok_or_else(|| {
+                ReadStateError::RootKeyUnavailable(
+                    "the replica does not report a root key".to_string(),
+                )
+            })?;
+        let root_key = parse_threshold_sig_key_from_der(&der.0).map_err(|e| {
+            ReadStateError::RootKeyUnavailable(format!("failed to parse the root key: {}", e))
+        })?;
+        *self.root_key.lock().unwrap() = Some(root_key);
+        Ok(root_key)
     }
 
     /// Requests the status of a pending canister update call request exactly
@@ -474,11 +661,10 @@ impl Agent {
         request_id: MessageId,
         deadline: Instant,
         canister_id: &CanisterId,
-    ) -> Result<RequestStatus, String> {
-        let cbor = self
-            .request_status_once(request_id.clone(), deadline, canister_id)
-            .await?;
-        parse_read_state_response(&request_id, cbor)
+    ) -> Result<RequestStatus, ReadStateError> {
+        let path = Path::new(vec!["request_status".into(), request_id.clone().into()]);
+        let certificate = self.read_state(canister_id, &[path], deadline).await?;
+        request_status_from_certificate(&request_id, certificate).map_err(ReadStateError::Request)
     }
 
     async fn get_status(&self) -> Result<HttpStatusResponse, String> {
@@ -534,6 +720,22 @@ pub fn ed25519_public_key_to_der(mut key: Vec<u8>) -> Vec<u8> {
     encoded
 }
 
+/// Returns the result of an update call with status `request_status`, or
+/// `None` if the call has not completed yet.
+fn final_update_result(request_status: RequestStatus) -> Option<Result<Option<Vec<u8>>, String>> {
+    match request_status.status.as_ref() {
+        "replied" => Some(Ok(request_status.reply)),
+        "done" => Some(Err(
+            "The call has completed but the reply/reject data has been pruned.".to_string(),
+        )),
+        "unknown" | "received" | "processing" => None,
+        _ => Some(Err(format!(
+            "unexpected result: {:?} - {:?}",
+            request_status.status, request_status.reject_message
+        ))),
+    }
+}
+
 /// Wraps the content into an envelope that contains the message signature.
 ///
 /// Prerequisite: `content` contains a `sender` field that is compatible with
@@ -557,7 +759,7 @@ pub fn sign_submit(
         content,
         sender_pubkey: pub_key_der,
         sender_sig,
-        sender_delegation: None,
+        sender_delegation: sender.sender_delegation(),
     };
     Ok((envelope, message_id))
 }
@@ -579,7 +781,7 @@ pub fn sign_read_state(
         content,
         sender_pubkey: pub_key_der,
         sender_sig,
-        sender_delegation: None,
+        sender_delegation: sender.sender_delegation(),
     })
 }
 
@@ -600,7 +802,7 @@ pub fn sign_query(
         content,
         sender_pubkey: pub_key_der,
         sender_sig,
-        sender_delegation: None,
+        sender_delegation: sender.sender_delegation(),
     })
 }
 
@@ -626,13 +828,17 @@ fn bytes_to_cbor(bytes: Vec<u8>) -> Result<CBOR, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ic_certified_vars_test_utils::{CertificateBuilder, CertificateData};
+    use ic_crypto_tree_hash::Digest;
     use ic_test_utilities::crypto::temp_crypto_component_with_fake_registry;
     use ic_test_utilities::types::ids::node_test_id;
     use ic_types::malicious_flags::MaliciousFlags;
-    use ic_types::messages::{HttpCanisterUpdate, HttpRequest, HttpUserQuery, UserQuery};
+    use ic_types::messages::{
+        HttpCanisterUpdate, HttpReadStateResponse, HttpRequest, HttpUserQuery, UserQuery,
+    };
     use ic_types::time::current_time;
     use ic_types::{PrincipalId, RegistryVersion, UserId};
-    use ic_validator::get_authorized_canisters;
+    use ic_validator::{get_authorized_canisters, MAXIMUM_NUMBER_OF_DELEGATIONS};
     use rand_chacha::ChaChaRng;
     use rand_core::SeedableRng;
     use std::convert::TryFrom;
@@ -689,6 +895,7 @@ mod tests {
             &validator,
             test_start_time,
             mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
             &MaliciousFlags::default(),
         )
         .unwrap()
@@ -742,6 +949,70 @@ mod tests {
             &validator,
             test_start_time,
             mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
+            &MaliciousFlags::default(),
+        )
+        .unwrap()
+        .contains(&request.content().canister_id()));
+    }
+
+    /// Create an HttpRequest signed by a session key that an Ed25519 identity
+    /// delegated to, and then verify that `validate_message` manages to
+    /// authenticate it as the identity.
+    #[test]
+    fn sign_and_verify_submit_content_with_delegation_chain() {
+        use crate::signer::{sign_delegation, DelegationChainSigner, PemSigner};
+        use ic_types::messages::Delegation;
+        use openssl::{ec::EcGroup, ec::EcKey, nid::Nid, pkey::PKey};
+
+        let test_start_time = current_time();
+        let expiry_time = test_start_time + Duration::from_secs(4 * 60);
+        let identity = PemSigner::from_pem(
+            &PKey::generate_ed25519()
+                .unwrap()
+                .private_key_to_pem_pkcs8()
+                .unwrap(),
+        )
+        .unwrap();
+        let session = {
+            let group = EcGroup::from_curve_name(Nid::SECP256K1).unwrap();
+            let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+            Arc::new(PemSigner::from_pem(&key.private_key_to_pem_pkcs8().unwrap()).unwrap())
+        };
+        let delegation = sign_delegation(
+            &identity,
+            Delegation::new(
+                session.sender_pubkey_der(),
+                expiry_time + Duration::from_secs(60),
+            ),
+        )
+        .unwrap();
+        let sender = Sender::from_signer(Arc::new(DelegationChainSigner::new(
+            identity.sender_pubkey_der(),
+            vec![delegation],
+            session,
+        )));
+        let content = HttpCallContent::Call {
+            update: HttpCanisterUpdate {
+                canister_id: Blob(vec![51]),
+                method_name: "foo".to_string(),
+                arg: Blob(vec![12, 13, 99]),
+                nonce: None,
+                sender: Blob(sender.get_principal_id().into_vec()),
+                ingress_expiry: expiry_time.as_nanos_since_unix_epoch(),
+            },
+        };
+        let (submit, _id) = sign_submit(content, &sender).unwrap();
+        assert_eq!(submit.sender_delegation.as_ref().map(Vec::len), Some(1));
+
+        let request = HttpRequest::try_from(submit).unwrap();
+        let validator = temp_crypto_component_with_fake_registry(node_test_id(VALIDATOR_NODE_ID));
+        assert!(get_authorized_canisters(
+            &request,
+            &validator,
+            test_start_time,
+            mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
             &MaliciousFlags::default(),
         )
         .unwrap()
@@ -783,6 +1054,7 @@ mod tests {
             &validator,
             test_start_time,
             mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
             &MaliciousFlags::default(),
         )
         .unwrap()
@@ -831,6 +1103,7 @@ mod tests {
             &validator,
             test_start_time,
             mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
             &MaliciousFlags::default(),
         ));
     }
@@ -884,7 +1157,130 @@ mod tests {
             &validator,
             test_start_time,
             mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
             &MaliciousFlags::default(),
         ));
     }
+
+    /// Serves a single HTTP request on a local port with `body` and returns
+    /// the URL to reach it.
+    fn serve_once(body: Vec<u8>) -> Url {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Consume the request before responding.
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text[..header_end]
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .map(|value| value.trim().parse::<usize>().unwrap())
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+            }
+            let header = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/cbor\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        url
+    }
+
+    /// Returns a `read_state` response with a certificate for canister 1
+    /// that is signed by a fresh root key, and that root key.
+    fn read_state_response() -> (Vec<u8>, ThresholdSigPublicKey) {
+        let (_, root_key, certificate) = CertificateBuilder::new(CertificateData::CanisterData {
+            canister_id: CanisterId::from_u64(1),
+            certified_data: Digest([0; 32]),
+        })
+        .with_time(current_time().as_nanos_since_unix_epoch())
+        .build();
+        let response = HttpReadStateResponse {
+            certificate: Blob(certificate),
+        };
+        (serde_cbor::to_vec(&response).unwrap(), root_key)
+    }
+
+    fn read_state(agent: Agent) -> Result<Certificate, ReadStateError> {
+        let path = Path::new(vec!["time".into()]);
+        tokio_test::block_on(agent.read_state(
+            &CanisterId::from_u64(1),
+            &[path],
+            Instant::now() + Duration::from_secs(10),
+        ))
+    }
+
+    #[test]
+    fn read_state_accepts_certificate_signed_by_root_key() {
+        let (response, root_key) = read_state_response();
+        let agent = Agent::new(serve_once(response), Sender::Anonymous).with_root_key(root_key);
+
+        assert!(read_state(agent).is_ok());
+    }
+
+    #[test]
+    fn read_state_rejects_certificate_signed_by_foreign_key() {
+        let (response, _) = read_state_response();
+        let (_, foreign_key) = read_state_response();
+        let agent = Agent::new(serve_once(response), Sender::Anonymous).with_root_key(foreign_key);
+
+        assert!(matches!(
+            read_state(agent),
+            Err(ReadStateError::InvalidCertificate(
+                CertificateValidationError::InvalidSignature(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn read_state_fails_closed_without_root_key() {
+        let (response, _) = read_state_response();
+        let agent = Agent::new(serve_once(response), Sender::Anonymous);
+
+        assert!(matches!(
+            read_state(agent),
+            Err(ReadStateError::RootKeyUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn final_update_result_distinguishes_pending_and_completed_calls() {
+        let status = |status: &str, reply: Option<Vec<u8>>| RequestStatus {
+            status: status.to_string(),
+            reply,
+            reject_message: None,
+        };
+        for pending in ["unknown", "received", "processing"] {
+            assert_eq!(final_update_result(status(pending, None)), None);
+        }
+        assert_eq!(
+            final_update_result(status("replied", Some(vec![1, 2, 3]))),
+            Some(Ok(Some(vec![1, 2, 3])))
+        );
+        assert!(matches!(
+            final_update_result(status("done", None)),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            final_update_result(status("rejected", None)),
+            Some(Err(_))
+        ));
+    }
 }
@@ -42,16 +42,31 @@ impl RequestStatus {
 
 /// Given a CBOR response from a `read_state` and a `request_id` extracts
 /// the `RequestStatus` if available.
+///
+/// Note that the certificate is not verified.
 pub fn parse_read_state_response(
     request_id: &MessageId,
     message: CBOR,
 ) -> Result<RequestStatus, String> {
+    let certificate = parse_read_state_certificate(message)?;
+    request_status_from_certificate(request_id, certificate)
+}
+
+/// Given a CBOR response from a `read_state`, extracts the `Certificate`.
+pub(crate) fn parse_read_state_certificate(message: CBOR) -> Result<Certificate, String> {
     let response = serde_cbor::value::from_value::<HttpReadStateResponse>(message)
         .map_err(|source| format!("decoding to HttpReadStateResponse failed: {}", source))?;
 
-    let certificate: Certificate = serde_cbor::from_slice(response.certificate.as_slice())
-        .map_err(|source| format!("decoding Certificate failed: {}", source))?;
+    serde_cbor::from_slice(response.certificate.as_slice())
+        .map_err(|source| format!("decoding Certificate failed: {}", source))
+}
 
+/// Extracts the `RequestStatus` of `request_id` from a `read_state`
+/// certificate.
+pub(crate) fn request_status_from_certificate(
+    request_id: &MessageId,
+    certificate: Certificate,
+) -> Result<RequestStatus, String> {
     // Parse the tree.
     let tree = LabeledTree::try_from(certificate.tree)
         .map_err(|e| format!("parsing tree in certificate failed: {:?}", e))?;
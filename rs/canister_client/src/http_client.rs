@@ -89,7 +89,7 @@ impl HttpClient {
         uri: HyperUri,
         response_future: HyperFuture,
         deadline: tokio::time::Instant,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<(Vec<u8>, hyper::StatusCode), String> {
         let result = tokio::time::timeout_at(deadline, response_future)
             .await
             .map_err(|e| format!("HttpClient: Request timed out for {:?}: {:?}", uri, e))?;
@@ -119,7 +119,7 @@ impl HttpClient {
                 readable_response,
             ));
         }
-        Ok(parsed_body)
+        Ok((parsed_body, status))
     }
 
     pub(crate) async fn get_with_response(
@@ -130,7 +130,9 @@ impl HttpClient {
     ) -> Result<Vec<u8>, String> {
         let uri = self.build_uri(url, end_point)?;
         let response_future = self.hyper.get(uri.clone());
-        Self::wait_for_one_http_request(uri, response_future, deadline).await
+        Self::wait_for_one_http_request(uri, response_future, deadline)
+            .await
+            .map(|(body, _status)| body)
     }
 
     pub(crate) async fn post_with_response(
@@ -140,6 +142,20 @@ impl HttpClient {
         http_body: Vec<u8>,
         deadline: tokio::time::Instant,
     ) -> Result<Vec<u8>, String> {
+        self.post_with_status_and_response(url, end_point, http_body, deadline)
+            .await
+            .map(|(body, _status)| body)
+    }
+
+    /// Like `post_with_response`, but also returns the (successful) status
+    /// code of the response.
+    pub(crate) async fn post_with_status_and_response(
+        &self,
+        url: &Url,
+        end_point: &str,
+        http_body: Vec<u8>,
+        deadline: tokio::time::Instant,
+    ) -> Result<(Vec<u8>, hyper::StatusCode), String> {
         let uri = self.build_uri(url, end_point)?;
         let response_future = self.build_post_request(uri.clone(), http_body)?;
         Self::wait_for_one_http_request(uri, response_future, deadline).await
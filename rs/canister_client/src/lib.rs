@@ -4,11 +4,17 @@ mod canister_management;
 /// Asynchronous method to interact with canisters.
 mod cbor;
 mod http_client;
+mod signer;
 
 pub use agent::{
     ed25519_public_key_to_der, get_backoff_policy, query_path, read_state_path, update_path, Agent,
-    Sender,
+    ReadStateError, Sender,
 };
 pub use cbor::parse_read_state_response;
 pub use http_client::{HttpClient, HttpClientConfig};
 pub use hyper::StatusCode as HttpStatusCode;
+pub use ic_certified_vars::{
+    verify_read_state_certificate, CertificateValidationError, MAX_CERTIFICATE_AGE,
+    MAX_CERTIFICATE_CLOCK_SKEW,
+};
+pub use signer::{sign_delegation, DelegationChainSigner, PemSigner, Pkcs11Signer, Signer};
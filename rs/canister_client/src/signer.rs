@@ -0,0 +1,407 @@
+//! Signers that authenticate the requests sent on behalf of a
+//! [`Sender`](crate::Sender), independently of where the secret key lives.
+use crate::agent::ed25519_public_key_to_der;
+use ed25519_dalek::Keypair;
+use ic_crypto_internal_types::sign::eddsa::ed25519::SecretKey;
+use ic_crypto_sha::Sha256;
+use ic_crypto_utils_basic_sig::conversions::Ed25519SecretKeyConversions;
+use ic_interfaces::crypto::Signable;
+use ic_sys::utility_command::UtilityCommand;
+use ic_types::messages::{Delegation, SignedDelegation};
+use openssl::{
+    ec::EcKey,
+    ecdsa::EcdsaSig,
+    nid::Nid,
+    pkey::{Id, PKey, Private},
+};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Size in bytes of the `r` and `s` components of the supported ECDSA
+/// signatures.
+const ECDSA_FIELD_SIZE: usize = 32;
+
+/// Signs requests on behalf of a sender.
+pub trait Signer: Send + Sync {
+    /// DER encoded public key that the sender's principal is derived from.
+    fn sender_pubkey_der(&self) -> Vec<u8>;
+
+    /// Signs `msg` with the key of the sender, or with the key of the last
+    /// delegation in `sender_delegation`, if any.
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    /// The chain of delegations from `sender_pubkey_der` to the key that
+    /// signs, if that is a different key.
+    fn sender_delegation(&self) -> Option<Vec<SignedDelegation>> {
+        None
+    }
+}
+
+enum PemKey {
+    /// An Ed25519 key in the PKCS#8 v2 format that the IC tools produce,
+    /// which embeds the public key and which some parsers reject.
+    IcEd25519(Keypair),
+    Ed25519(PKey<Private>),
+    Ecdsa(EcKey<Private>),
+}
+
+/// Signs with a secret key read from a PEM file. Ed25519, ECDSA secp256k1 and
+/// ECDSA P-256 keys are supported, in PKCS#8 or SEC1 format.
+pub struct PemSigner {
+    key: PemKey,
+    public_key_der: Vec<u8>,
+}
+
+impl PemSigner {
+    pub fn from_pem(pem: &[u8]) -> Result<Self, String> {
+        if let Some(signer) = Self::from_ic_ed25519_pem(pem) {
+            return Ok(signer);
+        }
+        let pkey = PKey::private_key_from_pem(pem)
+            .map_err(|e| format!("Invalid PEM secret key: {}", e))?;
+        let public_key_der = pkey
+            .public_key_to_der()
+            .map_err(|e| format!("Cannot encode the public key: {}", e))?;
+        let key = match pkey.id() {
+            Id::ED25519 => PemKey::Ed25519(pkey),
+            Id::EC => {
+                let ec_key = pkey
+                    .ec_key()
+                    .map_err(|e| format!("Invalid EC secret key: {}", e))?;
+                match ec_key.group().curve_name() {
+                    Some(Nid::SECP256K1) | Some(Nid::X9_62_PRIME256V1) => PemKey::Ecdsa(ec_key),
+                    curve => return Err(format!("Unsupported elliptic curve: {:?}", curve)),
+                }
+            }
+            id => return Err(format!("Unsupported key type: {:?}", id)),
+        };
+        Ok(Self {
+            key,
+            public_key_der,
+        })
+    }
+
+    /// Parses `pem` as an Ed25519 key in the PKCS#8 v2 format of the IC tools.
+    fn from_ic_ed25519_pem(pem: &[u8]) -> Option<Self> {
+        let pem = std::str::from_utf8(pem).ok()?;
+        let (secret_key, public_key) = SecretKey::from_pem(pem).ok()?;
+        let mut bytes = secret_key.as_bytes().to_vec();
+        bytes.extend_from_slice(public_key.as_bytes());
+        let keypair = Keypair::from_bytes(&bytes).ok()?;
+        Some(Self {
+            key: PemKey::IcEd25519(keypair),
+            public_key_der: ed25519_public_key_to_der(public_key.as_bytes().to_vec()),
+        })
+    }
+
+    pub fn from_pem_file(path: &Path) -> Result<Self, String> {
+        let pem = fs::read(path)
+            .map_err(|e| format!("Cannot read key file {}: {}", path.display(), e))?;
+        Self::from_pem(&pem)
+    }
+}
+
+impl Signer for PemSigner {
+    fn sender_pubkey_der(&self) -> Vec<u8> {
+        self.public_key_der.clone()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.key {
+            PemKey::IcEd25519(keypair) => Signer::sign(keypair, msg),
+            PemKey::Ed25519(pkey) => {
+                let mut signer = openssl::sign::Signer::new_without_digest(pkey)?;
+                Ok(signer.sign_oneshot_to_vec(msg)?)
+            }
+            PemKey::Ecdsa(ec_key) => {
+                let sig = EcdsaSig::sign(&Sha256::hash(msg), ec_key)?;
+                Ok(ecdsa_sig_to_bytes(&sig))
+            }
+        }
+    }
+}
+
+/// Signs with an Ed25519 key pair held in memory.
+impl Signer for Keypair {
+    fn sender_pubkey_der(&self) -> Vec<u8> {
+        ed25519_public_key_to_der(self.public.to_bytes().to_vec())
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(ed25519_dalek::Signer::sign(self, msg).to_bytes().to_vec())
+    }
+}
+
+/// Encodes an ECDSA signature as the concatenation of `r` and `s`, as
+/// expected by the IC.
+fn ecdsa_sig_to_bytes(sig: &EcdsaSig) -> Vec<u8> {
+    let mut bytes = vec![0; 2 * ECDSA_FIELD_SIZE];
+    let r = sig.r().to_vec();
+    let s = sig.s().to_vec();
+    bytes[ECDSA_FIELD_SIZE - r.len()..ECDSA_FIELD_SIZE].copy_from_slice(&r);
+    bytes[2 * ECDSA_FIELD_SIZE - s.len()..].copy_from_slice(&s);
+    bytes
+}
+
+/// Signs with an ECDSA key held by a PKCS#11 token, through `pkcs11-tool`.
+///
+/// The token is accessed through the PKCS#11 library at `module` if given,
+/// e.g. SoftHSM's `libsofthsm2.so`, or through the default library of
+/// `pkcs11-tool` otherwise.
+pub struct Pkcs11Signer {
+    module: Option<PathBuf>,
+    slot: String,
+    key_id: String,
+    pin: String,
+    public_key_der: Vec<u8>,
+}
+
+impl Pkcs11Signer {
+    pub fn new(
+        module: Option<PathBuf>,
+        slot: &str,
+        key_id: &str,
+        pin: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let public_key_der = Self::command(
+            &module,
+            UtilityCommand::read_public_key(Some(slot), Some(key_id)),
+        )
+        .execute()?;
+        Ok(Self {
+            module,
+            slot: slot.to_string(),
+            key_id: key_id.to_string(),
+            pin: pin.to_string(),
+            public_key_der,
+        })
+    }
+
+    fn command(module: &Option<PathBuf>, command: UtilityCommand) -> UtilityCommand {
+        match module {
+            Some(module) => command.with_pkcs11_module(module),
+            None => command,
+        }
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn sender_pubkey_der(&self) -> Vec<u8> {
+        self.public_key_der.clone()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let command = UtilityCommand::sign_message(
+            msg.to_vec(),
+            Some(&self.slot),
+            Some(&self.pin),
+            Some(&self.key_id),
+        );
+        Ok(Self::command(&self.module, command).execute()?)
+    }
+}
+
+/// Signs on behalf of an identity that delegated, through a chain of
+/// delegations, to the key of `session_signer`.
+pub struct DelegationChainSigner {
+    identity_pubkey_der: Vec<u8>,
+    delegations: Vec<SignedDelegation>,
+    session_signer: Arc<dyn Signer>,
+}
+
+impl DelegationChainSigner {
+    /// `delegations` must start at `identity_pubkey_der` and end at the key of
+    /// `session_signer`.
+    pub fn new(
+        identity_pubkey_der: Vec<u8>,
+        delegations: Vec<SignedDelegation>,
+        session_signer: Arc<dyn Signer>,
+    ) -> Self {
+        Self {
+            identity_pubkey_der,
+            delegations,
+            session_signer,
+        }
+    }
+}
+
+impl Signer for DelegationChainSigner {
+    fn sender_pubkey_der(&self) -> Vec<u8> {
+        self.identity_pubkey_der.clone()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.session_signer.sign(msg)
+    }
+
+    fn sender_delegation(&self) -> Option<Vec<SignedDelegation>> {
+        Some(self.delegations.clone())
+    }
+}
+
+/// Signs `delegation` with `signer`, whose key delegates to the key in
+/// `delegation`.
+pub fn sign_delegation(
+    signer: &dyn Signer,
+    delegation: Delegation,
+) -> Result<SignedDelegation, Box<dyn Error>> {
+    let signature = signer.sign(&delegation.as_signed_bytes())?;
+    Ok(SignedDelegation::new(delegation, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_crypto_internal_types::sign::eddsa::ed25519::PublicKey;
+    use openssl::ec::EcGroup;
+    use openssl::pkey::Public;
+    use std::convert::TryFrom;
+    use std::process::Command;
+
+    fn ecdsa_pem(curve: Nid) -> Vec<u8> {
+        let group = EcGroup::from_curve_name(curve).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        PKey::from_ec_key(key)
+            .unwrap()
+            .private_key_to_pem_pkcs8()
+            .unwrap()
+    }
+
+    fn verify_ecdsa(public_key_der: &[u8], msg: &[u8], sig: &[u8]) -> bool {
+        let public_key: PKey<Public> = PKey::public_key_from_der(public_key_der).unwrap();
+        let r = openssl::bn::BigNum::from_slice(&sig[..ECDSA_FIELD_SIZE]).unwrap();
+        let s = openssl::bn::BigNum::from_slice(&sig[ECDSA_FIELD_SIZE..]).unwrap();
+        EcdsaSig::from_private_components(r, s)
+            .unwrap()
+            .verify(&Sha256::hash(msg), &public_key.ec_key().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn pem_signer_signs_with_ed25519() {
+        let pem = PKey::generate_ed25519()
+            .unwrap()
+            .private_key_to_pem_pkcs8()
+            .unwrap();
+        let signer = PemSigner::from_pem(&pem).unwrap();
+        let sig = signer.sign(b"message").unwrap();
+        assert_eq!(sig.len(), 64);
+
+        let public_key = PKey::public_key_from_der(&signer.sender_pubkey_der()).unwrap();
+        let mut verifier = openssl::sign::Verifier::new_without_digest(&public_key).unwrap();
+        assert!(verifier.verify_oneshot(&sig, b"message").unwrap());
+    }
+
+    #[test]
+    fn pem_signer_signs_with_ic_pkcs8_v2_ed25519_key() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[0x23; 32]).unwrap();
+        let keypair = Keypair {
+            public: (&secret).into(),
+            secret,
+        };
+        let secret_key = SecretKey::try_from(keypair.secret.as_bytes().as_ref()).unwrap();
+        let public_key = PublicKey::try_from(keypair.public.as_bytes().as_ref()).unwrap();
+        // The PKCS#8 v2 encoding that embeds the public key, as written by the
+        // IC key generation tools.
+        let pem = secret_key.to_pem(&public_key);
+
+        let signer = PemSigner::from_pem(pem.as_bytes()).unwrap();
+        assert_eq!(
+            signer.sender_pubkey_der(),
+            ed25519_public_key_to_der(keypair.public.as_bytes().to_vec())
+        );
+        let sig = signer.sign(b"message").unwrap();
+        assert_eq!(sig, Signer::sign(&keypair, b"message").unwrap());
+    }
+
+    #[test]
+    fn pem_signer_signs_with_ecdsa_secp256k1_and_p256() {
+        for curve in [Nid::SECP256K1, Nid::X9_62_PRIME256V1] {
+            let signer = PemSigner::from_pem(&ecdsa_pem(curve)).unwrap();
+            let sig = signer.sign(b"message").unwrap();
+            assert_eq!(sig.len(), 2 * ECDSA_FIELD_SIZE);
+            assert!(verify_ecdsa(&signer.sender_pubkey_der(), b"message", &sig));
+            assert!(!verify_ecdsa(&signer.sender_pubkey_der(), b"other", &sig));
+        }
+    }
+
+    #[test]
+    fn pem_signer_rejects_unsupported_keys() {
+        let p384 = ecdsa_pem(Nid::SECP384R1);
+        assert!(PemSigner::from_pem(&p384).is_err());
+        assert!(PemSigner::from_pem(b"not a pem").is_err());
+    }
+
+    #[test]
+    fn delegation_chain_signer_signs_with_session_key() {
+        let identity = PemSigner::from_pem(&ecdsa_pem(Nid::SECP256K1)).unwrap();
+        let session = Arc::new(PemSigner::from_pem(&ecdsa_pem(Nid::X9_62_PRIME256V1)).unwrap());
+        let delegation = sign_delegation(
+            &identity,
+            Delegation::new(session.sender_pubkey_der(), ic_types::time::UNIX_EPOCH),
+        )
+        .unwrap();
+        let signer = DelegationChainSigner::new(
+            identity.sender_pubkey_der(),
+            vec![delegation.clone()],
+            session.clone(),
+        );
+
+        assert_eq!(signer.sender_pubkey_der(), identity.sender_pubkey_der());
+        assert_eq!(signer.sender_delegation(), Some(vec![delegation.clone()]));
+        assert!(verify_ecdsa(
+            &identity.sender_pubkey_der(),
+            &delegation.delegation().as_signed_bytes(),
+            delegation.signature().0.as_slice()
+        ));
+        let sig = signer.sign(b"message").unwrap();
+        assert!(verify_ecdsa(&session.sender_pubkey_der(), b"message", &sig));
+    }
+
+    /// Requires `softhsm2-util` and `pkcs11-tool`, and the path of SoftHSM's
+    /// PKCS#11 library in `SOFTHSM2_MODULE`.
+    #[test]
+    #[ignore]
+    fn pkcs11_signer_signs_with_softhsm() {
+        let module = PathBuf::from(std::env::var("SOFTHSM2_MODULE").unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        let tokens = dir.path().join("tokens");
+        fs::create_dir(&tokens).unwrap();
+        let conf = dir.path().join("softhsm2.conf");
+        fs::write(
+            &conf,
+            format!("directories.tokendir = {}\n", tokens.display()),
+        )
+        .unwrap();
+        std::env::set_var("SOFTHSM2_CONF", &conf);
+
+        let output = Command::new("softhsm2-util")
+            .args(&["--init-token", "--free", "--label", "test"])
+            .args(&["--pin", "1234", "--so-pin", "5678"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let slot = String::from_utf8(output.stdout)
+            .unwrap()
+            .split_whitespace()
+            .last()
+            .unwrap()
+            .to_string();
+        let status = Command::new("pkcs11-tool")
+            .arg("--module")
+            .arg(&module)
+            .args(&["--slot", &slot, "--login", "--pin", "1234", "--keypairgen"])
+            .args(&["--key-type", "EC:prime256v1", "--id", "01"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let signer = Pkcs11Signer::new(Some(module), &slot, "01", "1234").unwrap();
+        let sig = signer.sign(b"message").unwrap();
+        assert!(verify_ecdsa(&signer.sender_pubkey_der(), b"message", &sig));
+    }
+}
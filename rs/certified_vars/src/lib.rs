@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::time::Duration;
 
 use serde::Deserialize;
 
@@ -20,8 +21,16 @@ use tree_deserializer::{types::Leb128EncodedU64, LabeledTreeDeserializer};
 #[cfg(test)]
 mod tests;
 
-/// Describes an error that occurred during parsing and validation of the result
-/// of a `RegistryCanister::get_certified_changes_since()` method call.
+/// `read_state` certificates older than this are rejected as stale.
+pub const MAX_CERTIFICATE_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// `read_state` certificates whose time is ahead of the local clock by more
+/// than this are rejected.
+pub const MAX_CERTIFICATE_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
+/// Describes an error that occurred during parsing and validation of a
+/// certificate, e.g. the one in the result of a
+/// `RegistryCanister::get_certified_changes_since()` method call.
 #[derive(Debug)]
 pub enum CertificateValidationError {
     /// Failed to deserialize some part of the certificate.
@@ -41,6 +50,8 @@ pub enum CertificateValidationError {
     MultipleSubnetDelegationsNotAllowed,
     /// The given canister id is not contained in the ranges specified by the subnet delegation.
     CanisterIdOutOfRange,
+    /// The time in a `read_state` certificate is too far from the local time.
+    StaleCertificate { certificate_time: Time, now: Time },
 }
 
 impl fmt::Display for CertificateValidationError {
@@ -60,7 +71,7 @@ impl fmt::Display for CertificateValidationError {
                 hex::encode(&computed[..])
             ),
 
-            Self::MalformedHashTree(err) => write!(f, "hash tree in not well-formed: {}", err),
+            Self::MalformedHashTree(err) => write!(f, "hash tree is not well-formed: {}", err),
             Self::MultipleSubnetDelegationsNotAllowed => write!(
                 f,
                 "expected certificate with a maximum of one delegations but found nested delegations in the certificate"
@@ -71,10 +82,20 @@ impl fmt::Display for CertificateValidationError {
                     "canister id does not match the canister id range specified in the certificate"
                 )
             }
+            Self::StaleCertificate {
+                certificate_time,
+                now,
+            } => write!(
+                f,
+                "certificate time {} is too far from the local time {}",
+                certificate_time, now
+            ),
         }
     }
 }
 
+impl std::error::Error for CertificateValidationError {}
+
 /// Checks if the specified certificate verifies the certified data of
 /// specified canister.
 ///
@@ -99,20 +120,7 @@ pub fn verify_certificate(
 
     let certificate: Certificate = parse_certificate(certificate)?;
 
-    let key = if let Some(delegation) = &certificate.delegation {
-        let subnet_id = PrincipalId::try_from(&*delegation.subnet_id)
-            .map(SubnetId::from)
-            .map_err(|err| {
-                CertificateValidationError::DeserError(format!(
-                    "failed to parse delegation subnet id: {}",
-                    err
-                ))
-            })?;
-        verify_delegation_certificate(&delegation.certificate, &subnet_id, root_pk, canister_id)?
-    } else {
-        *root_pk
-    };
-
+    let key = certificate_key(&certificate, root_pk, canister_id)?;
     verify_certificate_signature(&certificate, &key)?;
 
     let replica_labeled_tree = parse_tree(certificate.tree)?;
@@ -147,6 +155,77 @@ pub fn verify_certificate(
     Ok(Time::from_nanos_since_unix_epoch(replica_state.time.0))
 }
 
+/// Verifies a certificate returned by a `read_state` request addressed to
+/// `effective_canister_id`.
+///
+/// Checks the signature against `root_pk` (or against the subnet key in the
+/// NNS delegation, after verifying the delegation against `root_pk`), that
+/// `effective_canister_id` is in the canister ranges of the delegated subnet,
+/// and that the certificate time is not too far from `now`.
+pub fn verify_read_state_certificate(
+    certificate: &Certificate,
+    effective_canister_id: &CanisterId,
+    root_pk: &ThresholdSigPublicKey,
+    now: Time,
+) -> Result<(), CertificateValidationError> {
+    #[derive(Deserialize, Debug)]
+    struct ReplicaState {
+        time: Leb128EncodedU64,
+    }
+
+    let key = certificate_key(certificate, root_pk, effective_canister_id)?;
+    verify_certificate_signature(certificate, &key)?;
+
+    let replica_labeled_tree = parse_tree(certificate.tree.clone())?;
+    let replica_state = ReplicaState::deserialize(LabeledTreeDeserializer::new(
+        &replica_labeled_tree,
+    ))
+    .map_err(|err| {
+        CertificateValidationError::DeserError(format!(
+            "failed to unpack replica state from a labeled tree: {}",
+            err
+        ))
+    })?;
+
+    let certificate_time = Time::from_nanos_since_unix_epoch(replica_state.time.0);
+    let cert_nanos = certificate_time.as_nanos_since_unix_epoch();
+    let now_nanos = now.as_nanos_since_unix_epoch();
+    if cert_nanos.saturating_add(MAX_CERTIFICATE_AGE.as_nanos() as u64) < now_nanos
+        || cert_nanos > now_nanos.saturating_add(MAX_CERTIFICATE_CLOCK_SKEW.as_nanos() as u64)
+    {
+        return Err(CertificateValidationError::StaleCertificate {
+            certificate_time,
+            now,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the key the signature of `certificate` has to be verified against:
+/// `root_pk` or, if the certificate has a delegation, the public key of the
+/// delegated subnet.
+fn certificate_key(
+    certificate: &Certificate,
+    root_pk: &ThresholdSigPublicKey,
+    canister_id: &CanisterId,
+) -> Result<ThresholdSigPublicKey, CertificateValidationError> {
+    match &certificate.delegation {
+        Some(delegation) => {
+            let subnet_id = PrincipalId::try_from(&*delegation.subnet_id)
+                .map(SubnetId::from)
+                .map_err(|err| {
+                    CertificateValidationError::DeserError(format!(
+                        "failed to parse delegation subnet id: {}",
+                        err
+                    ))
+                })?;
+            verify_delegation_certificate(&delegation.certificate, &subnet_id, root_pk, canister_id)
+        }
+        None => Ok(*root_pk),
+    }
+}
+
 fn verify_delegation_certificate(
     certificate: &[u8],
     subnet_id: &SubnetId,
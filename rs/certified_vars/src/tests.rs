@@ -9,9 +9,13 @@ use ic_certified_vars_test_utils::{
 };
 use ic_crypto_tree_hash::{flatmap, Digest, Label, LabeledTree};
 use ic_crypto_utils_threshold_sig::parse_threshold_sig_key_from_der;
-use ic_types::Time;
+use ic_types::{messages::Certificate, Time};
+use std::time::Duration;
 
-use crate::{verify_certificate, CanisterId, CertificateValidationError};
+use crate::{
+    verify_certificate, verify_read_state_certificate, CanisterId, CertificateValidationError,
+    MAX_CERTIFICATE_AGE, MAX_CERTIFICATE_CLOCK_SKEW,
+};
 
 #[test]
 fn should_validate_subnet_delegation_test_vector() {
@@ -435,6 +439,103 @@ fn should_fail_on_unexpected_tree() {
     ));
 }
 
+const READ_STATE_CERTIFICATE_TIME: u64 = 1_600_000_000_000_000_000;
+
+fn read_state_certificate_builder() -> CertificateBuilder {
+    CertificateBuilder::new(CanisterData {
+        canister_id: canister_id(1),
+        certified_data: random_certified_data(),
+    })
+    .with_time(READ_STATE_CERTIFICATE_TIME)
+}
+
+fn read_state_certificate_time() -> Time {
+    Time::from_nanos_since_unix_epoch(READ_STATE_CERTIFICATE_TIME)
+}
+
+fn decode_certificate(cbor: &[u8]) -> Certificate {
+    serde_cbor::from_slice(cbor).unwrap()
+}
+
+#[test]
+fn should_validate_read_state_certificate_signed_by_root_key() {
+    let (_cert, pk, cbor) = read_state_certificate_builder().build();
+
+    let verification_result = verify_read_state_certificate(
+        &decode_certificate(&cbor),
+        &canister_id(1),
+        &pk,
+        read_state_certificate_time(),
+    );
+
+    verification_result.expect("expect valid certificate");
+}
+
+#[test]
+fn should_fail_read_state_certificate_validation_with_wrong_public_key() {
+    let (_cert, _pk, cbor) = read_state_certificate_builder().build();
+    let (_cert, other_pk, _cbor) = read_state_certificate_builder().build();
+
+    let verification_result = verify_read_state_certificate(
+        &decode_certificate(&cbor),
+        &canister_id(1),
+        &other_pk,
+        read_state_certificate_time(),
+    );
+
+    assert!(matches!(
+        verification_result,
+        Err(CertificateValidationError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn should_fail_on_stale_read_state_certificate() {
+    let (_cert, pk, cbor) = read_state_certificate_builder().build();
+    let certificate = decode_certificate(&cbor);
+
+    let too_late = read_state_certificate_time() + MAX_CERTIFICATE_AGE + Duration::from_secs(1);
+    assert!(matches!(
+        verify_read_state_certificate(&certificate, &canister_id(1), &pk, too_late),
+        Err(CertificateValidationError::StaleCertificate { .. })
+    ));
+
+    let too_early =
+        read_state_certificate_time() - MAX_CERTIFICATE_CLOCK_SKEW - Duration::from_secs(1);
+    assert!(matches!(
+        verify_read_state_certificate(&certificate, &canister_id(1), &pk, too_early),
+        Err(CertificateValidationError::StaleCertificate { .. })
+    ));
+}
+
+#[test]
+fn should_validate_read_state_certificate_delegation_and_canister_ranges() {
+    let (_cert, pk, cbor) = read_state_certificate_builder()
+        .with_delegation(CertificateBuilder::new(SubnetData {
+            subnet_id: subnet_id(1),
+            canister_id_ranges: vec![(canister_id(10), canister_id(20))],
+        }))
+        .build();
+    let certificate = decode_certificate(&cbor);
+
+    verify_read_state_certificate(
+        &certificate,
+        &canister_id(15),
+        &pk,
+        read_state_certificate_time(),
+    )
+    .expect("expect valid certificate");
+    assert!(matches!(
+        verify_read_state_certificate(
+            &certificate,
+            &canister_id(21),
+            &pk,
+            read_state_certificate_time()
+        ),
+        Err(CertificateValidationError::CanisterIdOutOfRange)
+    ));
+}
+
 fn random_certified_data() -> Digest {
     let mut random_certified_data: [u8; 32] = [0; 32];
     thread_rng().fill(&mut random_certified_data);
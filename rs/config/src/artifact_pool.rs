@@ -26,6 +26,24 @@ pub struct ArtifactPoolTomlConfig {
     /// specified, throttling would be disabled.
     pub ingress_pool_size_threshold: Option<usize>,
 
+    /// The maximum total size in bytes of the ingress messages in the pool
+    /// that were received from the same peer. If it is exceeded, the oldest
+    /// of these messages are evicted. If not specified, there is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingress_pool_max_bytes_per_peer: Option<usize>,
+
+    /// The maximum total size in bytes of the ingress messages in the pool
+    /// that were sent by the same principal. If it is exceeded, the oldest
+    /// of these messages are evicted. If not specified, there is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingress_pool_max_bytes_per_sender: Option<usize>,
+
+    /// The maximum total size in bytes of the ingress messages in the pool
+    /// that target the same canister. If it is exceeded, the oldest of these
+    /// messages are evicted. If not specified, there is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingress_pool_max_bytes_per_canister: Option<usize>,
+
     /// Choice of persistent pool backend database. None means default choice,
     /// which at the moment is "lmdb".
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -43,6 +61,9 @@ impl ArtifactPoolTomlConfig {
         Self {
             consensus_pool_path,
             ingress_pool_size_threshold: None,
+            ingress_pool_max_bytes_per_peer: None,
+            ingress_pool_max_bytes_per_sender: None,
+            ingress_pool_max_bytes_per_canister: None,
             consensus_pool_backend: Some("lmdb".to_string()),
             backup,
         }
@@ -74,6 +95,15 @@ pub struct ArtifactPoolConfig {
     /// Threshold for ingress rate limiting. If this field is not
     /// specified, throttling would be disabled.
     pub ingress_pool_size_threshold: Option<usize>,
+    /// The maximum size in bytes of the ingress messages in the pool per
+    /// originating peer, if any.
+    pub ingress_pool_max_bytes_per_peer: Option<usize>,
+    /// The maximum size in bytes of the ingress messages in the pool per
+    /// sender, if any.
+    pub ingress_pool_max_bytes_per_sender: Option<usize>,
+    /// The maximum size in bytes of the ingress messages in the pool per
+    /// target canister, if any.
+    pub ingress_pool_max_bytes_per_canister: Option<usize>,
     /// The maximum size, in number of messages, of the unvalidated section
     /// of the artifact pool, per peer.
     pub consensus_pool_unvalidated_capacity_per_peer: usize,
@@ -144,6 +174,9 @@ impl From<ArtifactPoolTomlConfig> for ArtifactPoolConfig {
             ingress_pool_unvalidated_capacity_per_peer:
                 MAX_INGRESS_POOL_UNVALIDATED_CAPACITY_PER_PEER,
             ingress_pool_size_threshold: toml_config.ingress_pool_size_threshold,
+            ingress_pool_max_bytes_per_peer: toml_config.ingress_pool_max_bytes_per_peer,
+            ingress_pool_max_bytes_per_sender: toml_config.ingress_pool_max_bytes_per_sender,
+            ingress_pool_max_bytes_per_canister: toml_config.ingress_pool_max_bytes_per_canister,
             consensus_pool_unvalidated_capacity_per_peer: MAX_CONSENSUS_POOL_VALIDATED_CAPACITY,
             consensus_pool_validated_capacity: MAX_CONSENSUS_POOL_UNVALIDATED_CAPACITY_PER_PEER,
             persistent_pool_backend,
@@ -13,6 +13,7 @@ use crate::{
     firewall::Config as FirewallConfig,
     http_handler,
     http_handler::Config as HttpHandlerConfig,
+    ingress_manager::Config as IngressManagerConfig,
     logger::Config as LoggerConfig,
     message_routing::Config as MessageRoutingConfig,
     metrics::Config as MetricsConfig,
@@ -35,6 +36,7 @@ pub struct Config {
     pub state_manager: StateManagerConfig,
     pub hypervisor: HypervisorConfig,
     pub http_handler: HttpHandlerConfig,
+    pub ingress_manager: IngressManagerConfig,
     pub metrics: MetricsConfig,
     pub artifact_pool: ArtifactPoolTomlConfig,
     pub consensus: ConsensusConfig,
@@ -63,6 +65,7 @@ pub struct ConfigOptional {
     pub state_manager: Option<StateManagerConfig>,
     pub hypervisor: Option<HypervisorConfig>,
     pub http_handler: Option<http_handler::ExternalConfig>,
+    pub ingress_manager: Option<IngressManagerConfig>,
     pub metrics: Option<MetricsConfig>,
     pub artifact_pool: Option<ArtifactPoolTomlConfig>,
     pub consensus: Option<ConsensusConfig>,
@@ -92,6 +95,7 @@ impl Config {
             state_manager: StateManagerConfig::new(parent_dir.join("state")),
             hypervisor: HypervisorConfig::default(),
             http_handler: HttpHandlerConfig::default(),
+            ingress_manager: IngressManagerConfig::default(),
             metrics: MetricsConfig::default(),
             artifact_pool: ArtifactPoolTomlConfig::new(parent_dir.join("consensus_pool"), None),
             consensus: ConsensusConfig::default(),
@@ -146,6 +150,7 @@ impl Config {
                     message: msg.to_string(),
                 }
             })?,
+            ingress_manager: cfg.ingress_manager.unwrap_or(default.ingress_manager),
             metrics: cfg.metrics.unwrap_or(default.metrics),
             artifact_pool: cfg.artifact_pool.unwrap_or(default.artifact_pool),
             consensus: cfg.consensus.unwrap_or(default.consensus),
@@ -143,6 +143,18 @@ pub const SAMPLE_CONFIG: &str = r#"
         // - EXAMPLE: csp_vault_type: { unix_socket: "/some/path/to/socket" },
         //   CspVault is run as a separate process, which can be reached via a Unix socket.
         csp_vault_type: { unix_socket: "/some/path/to/socket" },
+        // Optional file holding the key material for encrypting the secret key store
+        // at rest. If not set, the secret key store is not encrypted.
+        // - EXAMPLE: sks_encryption_key_file: "/run/ic-node/sks_encryption_key",
+        // Optional append-only file where the remote CspVault records every request to
+        // sign or decrypt with a secret key.
+        // - EXAMPLE: csp_vault_audit_log_file: "/var/log/ic-crypto-csp/audit.log",
+        // Optional maximum number of requests per second that the remote CspVault serves,
+        // per operation (sign, multi_sign, threshold_sign, tls_sign, ecdsa_sign_share,
+        // idkg_open_dealing, ni_dkg_create_dealing, ni_dkg_load_threshold_signing_key,
+        // idkg_verify_dealing_private, idkg_load_transcript,
+        // idkg_load_transcript_with_openings).
+        // - EXAMPLE: csp_vault_rate_limits: { threshold_sign: 100, tls_sign: 50 },
     },
     // ========================================
     // Configuration of the message scheduling.
@@ -176,7 +188,25 @@ pub const SAMPLE_CONFIG: &str = r#"
     // ====================================
     http_handler: {
         // The address to listen on.
-        listen_addr: "127.0.0.1:8080"
+        listen_addr: "127.0.0.1:8080",
+        // Whether to serve `/http/<canister_id>/...` requests as certified
+        // `http_request` queries to the canister.
+        http_gateway_enabled: false,
+        // Token bucket limits on the rate of ingress messages accepted per
+        // sender and per target canister. Unlimited if not set.
+        //
+        // ingress_rate_limit_per_sender: { requests_per_second: 10, burst: 50 },
+        // ingress_rate_limit_per_canister: { requests_per_second: 100, burst: 500 },
+        // The maximum number of sender delegations in a request.
+        max_delegations: 20,
+    },
+    // ======================================
+    // Configuration of the ingress manager.
+    // ======================================
+    ingress_manager: {
+        // The maximum number of sender delegations in an ingress message.
+        // Must be the same on all nodes of a subnet.
+        max_delegations: 20,
     },
     // ==================================================
     // Configuration of the metrics collection subsystem.
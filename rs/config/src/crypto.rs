@@ -5,6 +5,7 @@
 #![allow(clippy::unit_arg)]
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
@@ -40,6 +41,26 @@ impl Default for CspVaultType {
     }
 }
 
+/// The operations of the remote CSP vault that use secret keys to sign or to
+/// decrypt. They are audited and can be rate limited.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum CspVaultOperation {
+    Sign,
+    MultiSign,
+    ThresholdSign,
+    TlsSign,
+    EcdsaSignShare,
+    Bip340SignShare,
+    IdkgOpenDealing,
+    NiDkgCreateDealing,
+    NiDkgLoadThresholdSigningKey,
+    IdkgVerifyDealingPrivate,
+    IdkgLoadTranscript,
+    IdkgLoadTranscriptWithOpenings,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default)]
 #[cfg_attr(test, derive(Arbitrary))]
@@ -59,6 +80,30 @@ pub struct CryptoConfig {
     )]
     pub crypto_root: PathBuf,
     pub csp_vault_type: CspVaultType,
+    /// Path to a file holding the key material from which the key encrypting
+    /// the secret key store at rest is derived. If not set, the secret key
+    /// store is not encrypted.
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::option::of(any::<String>().prop_map(|x| PathBuf::from(x)))"
+        )
+    )]
+    pub sks_encryption_key_file: Option<PathBuf>,
+    /// Path to the append-only file where the remote CSP vault records every
+    /// request to sign or decrypt with a secret key. If not set, the requests
+    /// are not audited.
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::option::of(any::<String>().prop_map(|x| PathBuf::from(x)))"
+        )
+    )]
+    pub csp_vault_audit_log_file: Option<PathBuf>,
+    /// The maximum number of requests per second that the remote CSP vault
+    /// serves for each operation. Operations without a limit are not rate
+    /// limited.
+    pub csp_vault_rate_limits: BTreeMap<CspVaultOperation, u32>,
 }
 
 impl Default for CryptoConfig {
@@ -66,6 +111,9 @@ impl Default for CryptoConfig {
         Self {
             crypto_root: PathBuf::from(CRYPTO_ROOT_DEFAULT_PATH),
             csp_vault_type: CspVaultType::InReplica,
+            sks_encryption_key_file: None,
+            csp_vault_audit_log_file: None,
+            csp_vault_rate_limits: BTreeMap::new(),
         }
     }
 }
@@ -76,6 +124,9 @@ impl CryptoConfig {
         Self {
             crypto_root,
             csp_vault_type: CspVaultType::InReplica,
+            sks_encryption_key_file: None,
+            csp_vault_audit_log_file: None,
+            csp_vault_rate_limits: BTreeMap::new(),
         }
     }
 
@@ -85,6 +136,9 @@ impl CryptoConfig {
         Self {
             crypto_root,
             csp_vault_type: CspVaultType::UnixSocket(socket_path),
+            sks_encryption_key_file: None,
+            csp_vault_audit_log_file: None,
+            csp_vault_rate_limits: BTreeMap::new(),
         }
     }
 
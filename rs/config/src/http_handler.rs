@@ -20,6 +20,14 @@ pub enum PortConfig {
     WritePortTo(PathBuf),
 }
 
+/// A token bucket limit: up to `burst` requests are accepted at once, and the
+/// bucket refills at `requests_per_second`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub requests_per_second: u32,
+    pub burst: u32,
+}
+
 /// The external configuration that can be loaded from a configuration file.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
@@ -85,6 +93,62 @@ pub struct ExternalConfig {
     //       major security risk for the IC, but developers should not be
     //       tempted to get the IC's root key from this insecure location.
     pub show_root_key_in_status: bool,
+
+    /// If set to `true`, the replica serves `/http/<canister_id>/...` by
+    /// translating the requests into `http_request` queries to the canister
+    /// and returning the certified responses.
+    ///
+    /// ```json5
+    /// {
+    ///   http_handler: {
+    ///     http_gateway_enabled: true
+    ///   }
+    /// }
+    /// ```
+    pub http_gateway_enabled: bool,
+
+    /// If set, limits the rate of ingress messages accepted from each sender.
+    /// Requests over the limit are rejected with `429 Too Many Requests`.
+    ///
+    /// ```json5
+    /// {
+    ///   http_handler: {
+    ///     ingress_rate_limit_per_sender: {
+    ///       requests_per_second: 10,
+    ///       burst: 50
+    ///     }
+    ///   }
+    /// }
+    /// ```
+    pub ingress_rate_limit_per_sender: Option<RateLimit>,
+
+    /// If set, limits the rate of ingress messages accepted for each target
+    /// canister. Requests over the limit are rejected with `429 Too Many
+    /// Requests`.
+    ///
+    /// ```json5
+    /// {
+    ///   http_handler: {
+    ///     ingress_rate_limit_per_canister: {
+    ///       requests_per_second: 100,
+    ///       burst: 500
+    ///     }
+    ///   }
+    /// }
+    /// ```
+    pub ingress_rate_limit_per_canister: Option<RateLimit>,
+
+    /// The maximum number of sender delegations in a request. Requests with
+    /// more delegations are rejected.
+    ///
+    /// ```json5
+    /// {
+    ///   http_handler: {
+    ///     max_delegations: 20
+    ///   }
+    /// }
+    /// ```
+    pub max_delegations: usize,
 }
 
 impl Default for ExternalConfig {
@@ -94,6 +158,10 @@ impl Default for ExternalConfig {
             allow_ipv6_my_users_have_no_privacy: None,
             port: None,
             show_root_key_in_status: true,
+            http_gateway_enabled: false,
+            ingress_rate_limit_per_sender: None,
+            ingress_rate_limit_per_canister: None,
+            max_delegations: crate::ingress_manager::DEFAULT_MAX_DELEGATIONS,
         }
     }
 }
@@ -110,6 +178,15 @@ pub struct Config {
     pub port_file_path: Option<PathBuf>,
     /// True if the replica public key is returned from the `/status` endpoint
     pub show_root_key_in_status: bool,
+    /// True if `/http/<canister_id>/...` requests are served as `http_request`
+    /// queries
+    pub http_gateway_enabled: bool,
+    /// The limit on the rate of ingress messages per sender, if any
+    pub ingress_rate_limit_per_sender: Option<RateLimit>,
+    /// The limit on the rate of ingress messages per target canister, if any
+    pub ingress_rate_limit_per_canister: Option<RateLimit>,
+    /// The maximum number of sender delegations in a request
+    pub max_delegations: usize,
 }
 
 impl Default for Config {
@@ -121,6 +198,10 @@ impl Default for Config {
             ),
             port_file_path: None,
             show_root_key_in_status: true,
+            http_gateway_enabled: false,
+            ingress_rate_limit_per_sender: None,
+            ingress_rate_limit_per_canister: None,
+            max_delegations: crate::ingress_manager::DEFAULT_MAX_DELEGATIONS,
         }
     }
 }
@@ -155,6 +236,10 @@ impl TryFrom<ExternalConfig> for Config {
         }?;
 
         config.show_root_key_in_status = ec.show_root_key_in_status;
+        config.http_gateway_enabled = ec.http_gateway_enabled;
+        config.ingress_rate_limit_per_sender = ec.ingress_rate_limit_per_sender;
+        config.ingress_rate_limit_per_canister = ec.ingress_rate_limit_per_canister;
+        config.max_delegations = ec.max_delegations;
         Ok(config)
     }
 }
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of delegations allowed in a request, as specified in
+/// the interface spec.
+pub const DEFAULT_MAX_DELEGATIONS: usize = 20;
+
+/// Configuration of the ingress manager.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The maximum number of sender delegations in an ingress message.
+    /// Messages with more delegations are rejected. Since this limit is
+    /// also applied when validating blocks, it must be the same on all
+    /// nodes of a subnet.
+    pub max_delegations: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_delegations: DEFAULT_MAX_DELEGATIONS,
+        }
+    }
+}
@@ -7,6 +7,28 @@ pub struct TransportConfig {
 
     /// P2P specific config. In future, this will be made more generic.
     pub p2p_flows: Vec<TransportFlowConfig>,
+
+    /// The implementation used to exchange messages with peers.
+    #[serde(default)]
+    pub backend: TransportBackend,
+}
+
+/// The transport implementation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportBackend {
+    /// One TLS/TCP connection per flow.
+    Tcp,
+    /// One QUIC connection per peer, with one stream per flow. The QUIC
+    /// endpoint listens on the server port of the first flow, over UDP, and
+    /// connects to the endpoint of the first flow in the peer's node record.
+    Quic,
+}
+
+impl Default for TransportBackend {
+    fn default() -> Self {
+        Self::Tcp
+    }
 }
 
 /// Per-flow config
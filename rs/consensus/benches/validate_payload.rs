@@ -12,7 +12,9 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use ic_artifact_pool::{consensus_pool::ConsensusPoolImpl, ingress_pool::IngressPoolImpl};
-use ic_config::state_manager::Config as StateManagerConfig;
+use ic_config::{
+    ingress_manager::Config as IngressManagerConfig, state_manager::Config as StateManagerConfig,
+};
 use ic_consensus::consensus::{
     payload_builder::{PayloadBuilder, PayloadBuilderImpl},
     pool_reader::PoolReader,
@@ -147,6 +149,7 @@ where
             no_op_logger(),
             Arc::new(state_manager),
             cycles_account_manager,
+            IngressManagerConfig::default(),
             ic_types::malicious_flags::MaliciousFlags::default(),
         ));
 
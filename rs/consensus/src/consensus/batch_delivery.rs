@@ -22,6 +22,7 @@ use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_replicated_state::{metadata_state::subnet_call_context_manager::*, ReplicatedState};
 use ic_types::{
     consensus::ecdsa::{CompletedSignature, EcdsaBlockReader},
+    crypto::canister_threshold_sig::ThresholdSignatureScheme,
     crypto::threshold_sig::ni_dkg::{
         NiDkgId, NiDkgTag, NiDkgTargetSubnet::Remote, NiDkgTranscript,
     },
@@ -348,13 +349,13 @@ pub fn generate_responses_to_setup_initial_dkg_calls(
     consensus_responses
 }
 
-/// Creates responses to `SignWithECDSA` system calls with the computed
-/// signature.
+/// Creates responses to `SignWithECDSA` and `SignWithSchnorr` system calls
+/// with the computed signature.
 pub fn generate_responses_to_sign_with_ecdsa_calls(
     contexts: &BTreeMap<CallbackId, SignWithEcdsaContext>,
     ecdsa_payload: &ecdsa::EcdsaPayload,
 ) -> Vec<Response> {
-    use ic_ic00_types::{Payload, SignWithECDSAReply};
+    use ic_ic00_types::{Payload, SignWithECDSAReply, SignWithSchnorrReply};
     let mut consensus_responses = Vec::<Response>::new();
     for (callback_id, context) in contexts.iter() {
         let request_id = ecdsa::RequestId::from(context.pseudo_random_id.to_vec());
@@ -369,12 +370,16 @@ pub fn generate_responses_to_sign_with_ecdsa_calls(
                 // before pushing the new context, so any remaining cycles can
                 // be refunded to the canister.
                 refund: context.request.payment,
-                response_payload: messages::Payload::Data(
-                    SignWithECDSAReply {
+                response_payload: messages::Payload::Data(match context.scheme {
+                    ThresholdSignatureScheme::Ecdsa => SignWithECDSAReply {
                         signature: response.signature,
                     }
                     .encode(),
-                ),
+                    ThresholdSignatureScheme::Bip340 => SignWithSchnorrReply {
+                        signature: response.signature,
+                    }
+                    .encode(),
+                }),
             });
         }
     }
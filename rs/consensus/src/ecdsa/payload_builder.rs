@@ -1101,6 +1101,7 @@ fn build_signature_inputs(
         derivation_path: context.derivation_path.clone(),
     };
     ecdsa::ThresholdEcdsaSigInputsRef::new(
+        context.scheme,
         extended_derivation_path,
         context.message_hash.clone(),
         Id::from(context.pseudo_random_id),
@@ -1258,7 +1259,9 @@ mod tests {
     use ic_types::consensus::dkg::{Dealings, Summary};
     use ic_types::consensus::{BlockPayload, DataPayload, HashedBlock, Payload, SummaryPayload};
     use ic_types::crypto::canister_threshold_sig::idkg::IDkgTranscriptId;
-    use ic_types::crypto::canister_threshold_sig::ThresholdEcdsaCombinedSignature;
+    use ic_types::crypto::canister_threshold_sig::{
+        ThresholdEcdsaCombinedSignature, ThresholdSignatureScheme,
+    };
     use ic_types::{messages::CallbackId, Height, RegistryVersion};
     use std::collections::BTreeSet;
     use std::convert::TryInto;
@@ -1422,6 +1425,7 @@ mod tests {
                 CallbackId::from(1),
                 SignWithEcdsaContext {
                     request: RequestBuilder::new().build(),
+                    scheme: ThresholdSignatureScheme::Ecdsa,
                     pseudo_random_id,
                     message_hash: vec![],
                     derivation_path: vec![],
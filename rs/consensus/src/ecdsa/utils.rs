@@ -180,7 +180,7 @@ pub(crate) mod test_utils {
         IDkgTranscriptId, IDkgTranscriptType, IDkgUnmaskedTranscriptOrigin,
     };
     use ic_types::crypto::canister_threshold_sig::{
-        ExtendedDerivationPath, ThresholdEcdsaSigShare,
+        ExtendedDerivationPath, ThresholdEcdsaSigShare, ThresholdSignatureScheme,
     };
     use ic_types::crypto::AlgorithmId;
     use ic_types::malicious_behaviour::MaliciousBehaviour;
@@ -725,6 +725,7 @@ pub(crate) mod test_utils {
             key_unmasked_times_lambda_masked_ref,
         );
         let sig_inputs_ref = ThresholdEcdsaSigInputsRef::new(
+            ThresholdSignatureScheme::Ecdsa,
             ExtendedDerivationPath {
                 caller: PrincipalId::try_from(&vec![caller]).unwrap(),
                 derivation_path: vec![],
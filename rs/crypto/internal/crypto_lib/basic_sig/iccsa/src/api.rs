@@ -115,7 +115,8 @@ fn verify_certified_vars_certificate(
             CertificateValidationError::InvalidSignature(_)
             | CertificateValidationError::CertifiedDataMismatch { .. }
             | CertificateValidationError::MultipleSubnetDelegationsNotAllowed
-            | CertificateValidationError::CanisterIdOutOfRange => {
+            | CertificateValidationError::CanisterIdOutOfRange
+            | CertificateValidationError::StaleCertificate { .. } => {
                 CryptoError::SignatureVerification {
                     algorithm: AlgorithmId::IcCanisterSignature,
                     public_key_bytes: pk.0.clone(),
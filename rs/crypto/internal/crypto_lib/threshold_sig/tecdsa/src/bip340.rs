@@ -0,0 +1,436 @@
+//! Threshold BIP340 Schnorr signatures over secp256k1
+//!
+//! See <https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki>
+//!
+//! The protocol reuses the IDKG transcripts of threshold ECDSA: the key
+//! transcript and the presignature transcript must both be unmasked
+//! (simple commitments). Unlike ECDSA no multiplication is required, so
+//! the shares of the nonce and of the key are used directly.
+//!
+//! BIP340 requires both the public key and the nonce commitment R to have
+//! an even y coordinate. Since the (derived) key and the (rerandomized)
+//! presignature are public, every party knows whether they must be
+//! negated, and applies the negation to its shares locally.
+use crate::*;
+use ic_crypto_sha::Sha256;
+use ic_types::crypto::canister_threshold_sig::MasterEcdsaPublicKey;
+
+const BIP340_CHALLENGE_TAG: &[u8] = b"BIP0340/challenge";
+
+const BIP340_KEY_DERIVATION_DOMAIN: &[u8] = b"ic-crypto-tbip340-key-derivation";
+
+/// Derive the tweak of the BIP340 key for `derivation_path`
+///
+/// The derivation starts from a chain key specific to BIP340 so that the
+/// BIP340 and ECDSA keys derived for the same path are unrelated.
+fn derive_key_tweak(
+    derivation_path: &DerivationPath,
+    master_public_key: &EccPoint,
+) -> ThresholdEcdsaResult<(EccScalar, Vec<u8>)> {
+    let initial_chain_key = Sha256::hash(BIP340_KEY_DERIVATION_DOMAIN);
+    derivation_path.derive_tweak_with_chain_key(master_public_key, &initial_chain_key)
+}
+
+fn tagged_hash(tag: &[u8], inputs: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::hash(tag);
+    let mut sha = Sha256::new();
+    sha.write(&tag_hash);
+    sha.write(&tag_hash);
+    for input in inputs {
+        sha.write(input);
+    }
+    sha.finish()
+}
+
+fn has_even_y(pt: &EccPoint) -> ThresholdEcdsaResult<bool> {
+    if pt.is_infinity()? {
+        return Err(ThresholdEcdsaError::InvalidPoint);
+    }
+    // The compressed SEC1 encoding stores the parity of y in the header byte
+    Ok(pt.serialize()[0] == 0x02)
+}
+
+fn negate_point(pt: &EccPoint) -> ThresholdEcdsaResult<EccPoint> {
+    EccPoint::identity(pt.curve_type()).sub_points(pt)
+}
+
+/// Returns the point with even y coordinate whose x coordinate is `x`
+fn lift_x(x: &[u8]) -> ThresholdEcdsaResult<EccPoint> {
+    if x.len() != EccCurveType::K256.field_bytes() {
+        return Err(ThresholdEcdsaError::InvalidPoint);
+    }
+    let mut encoded = Vec::with_capacity(x.len() + 1);
+    encoded.push(0x02);
+    encoded.extend_from_slice(x);
+    EccPoint::deserialize(EccCurveType::K256, &encoded)
+}
+
+fn bip340_challenge(r_x: &[u8], p_x: &[u8], message: &[u8]) -> ThresholdEcdsaResult<EccScalar> {
+    let e = tagged_hash(BIP340_CHALLENGE_TAG, &[r_x, p_x, message]);
+    EccScalar::from_bytes_wide(EccCurveType::K256, &e)
+}
+
+/// Values derived from the public inputs of a signing instance which are
+/// shared by signing, share verification and combination.
+struct Bip340SigningContext {
+    key_tweak: EccScalar,
+    randomizer: EccScalar,
+    /// The rerandomized presignature, with even y
+    r: EccPoint,
+    /// True if the rerandomized presignature had to be negated
+    negate_nonce: bool,
+    /// The derived public key, with even y
+    public_key: EccPoint,
+    /// True if the derived public key had to be negated
+    negate_key: bool,
+    challenge: EccScalar,
+}
+
+impl Bip340SigningContext {
+    fn new(
+        derivation_path: &DerivationPath,
+        message: &[u8],
+        randomness: &Randomness,
+        key_transcript: &IDkgTranscriptInternal,
+        presig_transcript: &IDkgTranscriptInternal,
+    ) -> ThresholdEcdsaResult<Self> {
+        let curve_type = EccCurveType::K256;
+
+        let pre_sig = match &presig_transcript.combined_commitment {
+            CombinedCommitment::ByInterpolation(PolynomialCommitment::Simple(c)) => {
+                c.constant_term()
+            }
+            _ => return Err(ThresholdEcdsaError::InconsistentCommitments),
+        };
+
+        let master_public_key = match &key_transcript.combined_commitment {
+            CombinedCommitment::ByInterpolation(PolynomialCommitment::Simple(c)) => {
+                c.constant_term()
+            }
+            _ => return Err(ThresholdEcdsaError::InconsistentCommitments),
+        };
+
+        if pre_sig.curve_type() != curve_type || master_public_key.curve_type() != curve_type {
+            return Err(ThresholdEcdsaError::CurveMismatch);
+        }
+
+        let (key_tweak, _chain_key) = derive_key_tweak(derivation_path, &master_public_key)?;
+
+        let mut ro = ro::RandomOracle::new("ic-crypto-tbip340-rerandomize-presig");
+        ro.add_bytestring("randomness", &randomness.get())?;
+        ro.add_bytestring("message", message)?;
+        ro.add_point("pre_sig", &pre_sig)?;
+        ro.add_scalar("key_tweak", &key_tweak)?;
+        let randomizer = ro.output_scalar(curve_type)?;
+
+        let r = pre_sig.add_points(&EccPoint::mul_by_g(&randomizer)?)?;
+        let negate_nonce = !has_even_y(&r)?;
+        let r = if negate_nonce { negate_point(&r)? } else { r };
+
+        let public_key = master_public_key.add_points(&EccPoint::mul_by_g(&key_tweak)?)?;
+        let negate_key = !has_even_y(&public_key)?;
+        let public_key = if negate_key {
+            negate_point(&public_key)?
+        } else {
+            public_key
+        };
+
+        let challenge = bip340_challenge(
+            &r.affine_x()?.as_bytes(),
+            &public_key.affine_x()?.as_bytes(),
+            message,
+        )?;
+
+        Ok(Self {
+            key_tweak,
+            randomizer,
+            r,
+            negate_nonce,
+            public_key,
+            negate_key,
+            challenge,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdBip340SigShareInternal {
+    s: EccScalar,
+}
+
+impl ThresholdBip340SigShareInternal {
+    /// Create a BIP340 signature share
+    ///
+    /// `key_opening` and `presig_opening` are our openings of the key and
+    /// presignature transcripts, both of which must be unmasked.
+    pub(crate) fn new(
+        derivation_path: &DerivationPath,
+        message: &[u8],
+        randomness: Randomness,
+        key_transcript: &IDkgTranscriptInternal,
+        key_opening: &CommitmentOpening,
+        presig_transcript: &IDkgTranscriptInternal,
+        presig_opening: &CommitmentOpening,
+    ) -> ThresholdEcdsaResult<Self> {
+        let ctx = Bip340SigningContext::new(
+            derivation_path,
+            message,
+            &randomness,
+            key_transcript,
+            presig_transcript,
+        )?;
+
+        let (key_share, presig_share) = match (key_opening, presig_opening) {
+            (CommitmentOpening::Simple(key), CommitmentOpening::Simple(presig)) => (key, presig),
+            _ => return Err(ThresholdEcdsaError::InconsistentCommitments),
+        };
+
+        // Shares of the tweaked secret key and of the rerandomized nonce.
+        // Adding a public constant to every share adds it to the shared
+        // value, since the Lagrange coefficients at zero sum to one.
+        let key_share = key_share.add(&ctx.key_tweak)?;
+        let key_share = if ctx.negate_key {
+            key_share.negate()
+        } else {
+            key_share
+        };
+
+        let nonce_share = presig_share.add(&ctx.randomizer)?;
+        let nonce_share = if ctx.negate_nonce {
+            nonce_share.negate()
+        } else {
+            nonce_share
+        };
+
+        let s = nonce_share.add(&ctx.challenge.mul(&key_share)?)?;
+
+        Ok(Self { s })
+    }
+
+    /// Verify a BIP340 signature share
+    ///
+    /// This function returns Ok(true) if the share is valid, Ok(false) if it
+    /// is inconsistent with the commitments of the signer, and some Err if
+    /// the inputs are otherwise invalid.
+    pub fn verify(
+        &self,
+        derivation_path: &DerivationPath,
+        message: &[u8],
+        randomness: Randomness,
+        signer_index: NodeIndex,
+        key_transcript: &IDkgTranscriptInternal,
+        presig_transcript: &IDkgTranscriptInternal,
+    ) -> ThresholdEcdsaResult<bool> {
+        let ctx = Bip340SigningContext::new(
+            derivation_path,
+            message,
+            &randomness,
+            key_transcript,
+            presig_transcript,
+        )?;
+
+        let key_j = key_transcript
+            .evaluate_at(signer_index)?
+            .add_points(&EccPoint::mul_by_g(&ctx.key_tweak)?)?;
+        let key_j = if ctx.negate_key {
+            negate_point(&key_j)?
+        } else {
+            key_j
+        };
+
+        let nonce_j = presig_transcript
+            .evaluate_at(signer_index)?
+            .add_points(&EccPoint::mul_by_g(&ctx.randomizer)?)?;
+        let nonce_j = if ctx.negate_nonce {
+            negate_point(&nonce_j)?
+        } else {
+            nonce_j
+        };
+
+        let expected = nonce_j.add_points(&key_j.scalar_mul(&ctx.challenge)?)?;
+
+        Ok(EccPoint::mul_by_g(&self.s)? == expected)
+    }
+
+    pub fn serialize(&self) -> ThresholdEcdsaResult<Vec<u8>> {
+        serde_cbor::to_vec(self)
+            .map_err(|e| ThresholdEcdsaError::SerializationError(format!("{}", e)))
+    }
+
+    pub fn deserialize(raw: &[u8]) -> ThresholdEcdsaResult<Self> {
+        serde_cbor::from_slice::<Self>(raw)
+            .map_err(|e| ThresholdEcdsaError::SerializationError(format!("{}", e)))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdBip340CombinedSigInternal {
+    r: EccPoint,
+    s: EccScalar,
+}
+
+impl ThresholdBip340CombinedSigInternal {
+    /// Serialize in the 64 byte format of BIP340 (x coordinate of R || s)
+    pub fn serialize(&self) -> ThresholdEcdsaResult<Vec<u8>> {
+        let r_bytes = self.r.affine_x()?.as_bytes();
+        let s_bytes = self.s.serialize();
+
+        let mut sig = Vec::with_capacity(r_bytes.len() + s_bytes.len());
+        sig.extend_from_slice(&r_bytes);
+        sig.extend_from_slice(&s_bytes);
+        Ok(sig)
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> ThresholdEcdsaResult<Self> {
+        let curve_type = EccCurveType::K256;
+        let flen = curve_type.field_bytes();
+        let slen = curve_type.scalar_bytes();
+
+        if bytes.len() != flen + slen {
+            return Err(ThresholdEcdsaError::SerializationError(
+                "Bad signature length".to_string(),
+            ));
+        }
+
+        let r = lift_x(&bytes[..flen])?;
+        let s = EccScalar::deserialize(curve_type, &bytes[flen..])?;
+
+        Ok(Self { r, s })
+    }
+
+    pub(crate) fn new(
+        derivation_path: &DerivationPath,
+        message: &[u8],
+        randomness: Randomness,
+        key_transcript: &IDkgTranscriptInternal,
+        presig_transcript: &IDkgTranscriptInternal,
+        reconstruction_threshold: NumberOfNodes,
+        sig_shares: &BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>,
+    ) -> ThresholdEcdsaResult<Self> {
+        let reconstruction_threshold = reconstruction_threshold.get() as usize;
+        if sig_shares.len() < reconstruction_threshold {
+            return Err(ThresholdEcdsaError::InsufficientDealings);
+        }
+
+        let ctx = Bip340SigningContext::new(
+            derivation_path,
+            message,
+            &randomness,
+            key_transcript,
+            presig_transcript,
+        )?;
+
+        let mut x_values = Vec::with_capacity(reconstruction_threshold);
+        let mut samples = Vec::with_capacity(reconstruction_threshold);
+
+        for (index, sig_share) in sig_shares.iter().take(reconstruction_threshold) {
+            x_values.push(*index);
+            samples.push(sig_share.s);
+        }
+
+        let coefficients = LagrangeCoefficients::at_zero(EccCurveType::K256, &x_values)?;
+        let s = coefficients.interpolate_scalar(&samples)?;
+
+        Ok(Self { r: ctx.r, s })
+    }
+
+    /// Verify a threshold BIP340 signature
+    ///
+    /// In addition to the BIP340 verification equation, this checks that
+    /// the signature was generated with the given presignature transcript
+    /// and randomness.
+    ///
+    /// Returns Ok(true) if the signature is valid, Ok(false) if it is
+    /// not, and some Err if the parameters are otherwise invalid.
+    pub fn verify(
+        &self,
+        derivation_path: &DerivationPath,
+        message: &[u8],
+        randomness: Randomness,
+        presig_transcript: &IDkgTranscriptInternal,
+        key_transcript: &IDkgTranscriptInternal,
+    ) -> ThresholdEcdsaResult<bool> {
+        let ctx = Bip340SigningContext::new(
+            derivation_path,
+            message,
+            &randomness,
+            key_transcript,
+            presig_transcript,
+        )?;
+
+        if self.r != ctx.r {
+            return Ok(false);
+        }
+
+        verify_bip340_signature(
+            &ctx.public_key.affine_x()?.as_bytes(),
+            message,
+            &self.serialize()?,
+        )
+    }
+}
+
+/// Verify a BIP340 signature
+///
+/// `public_key` is the 32 byte x-only public key. Returns Ok(false) if
+/// the signature is invalid, and an Err if the public key is invalid.
+pub fn verify_bip340_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> ThresholdEcdsaResult<bool> {
+    let curve_type = EccCurveType::K256;
+    let flen = curve_type.field_bytes();
+
+    let pk = lift_x(public_key)?;
+
+    if signature.len() != flen + curve_type.scalar_bytes() {
+        return Ok(false);
+    }
+
+    let r_x = &signature[..flen];
+    // Both functions reject values which are not fully reduced
+    if lift_x(r_x).is_err() {
+        return Ok(false);
+    }
+    let s = match EccScalar::deserialize(curve_type, &signature[flen..]) {
+        Ok(s) => s,
+        Err(_) => return Ok(false),
+    };
+
+    let e = bip340_challenge(r_x, public_key, message)?;
+
+    // R = s*G - e*P
+    let r = EccPoint::mul_points(&EccPoint::generator_g(curve_type)?, &s, &pk, &e.negate())?;
+
+    if r.is_infinity()? || !has_even_y(&r)? {
+        return Ok(false);
+    }
+
+    Ok(r.affine_x()?.as_bytes() == r_x)
+}
+
+/// Returns the x-only BIP340 public key derived from `master_public_key`
+/// according to `derivation_path`.
+///
+/// The derivation follows that of threshold ECDSA, but is domain separated
+/// so that the BIP340 key is unrelated to the ECDSA key for the same path.
+pub fn derive_public_key(
+    master_public_key: &MasterEcdsaPublicKey,
+    derivation_path: &DerivationPath,
+) -> ThresholdEcdsaResult<EcdsaPublicKey> {
+    let raw_master_pk = match master_public_key.algorithm_id {
+        AlgorithmId::EcdsaSecp256k1 => {
+            EccPoint::deserialize(EccCurveType::K256, &master_public_key.public_key)?
+        }
+        _ => return Err(ThresholdEcdsaError::CurveMismatch),
+    };
+    let (key_tweak, chain_key) = derive_key_tweak(derivation_path, &raw_master_pk)?;
+    let public_key = raw_master_pk.add_points(&EccPoint::mul_by_g(&key_tweak)?)?;
+
+    Ok(EcdsaPublicKey {
+        algorithm_id: AlgorithmId::SchnorrSecp256k1,
+        public_key: public_key.affine_x()?.as_bytes(),
+        chain_key,
+    })
+}
@@ -97,12 +97,25 @@ impl DerivationPath {
     pub fn derive_tweak(
         &self,
         master_public_key: &EccPoint,
+    ) -> ThresholdEcdsaResult<(EccScalar, Vec<u8>)> {
+        self.derive_tweak_with_chain_key(master_public_key, &[0; 32])
+    }
+
+    /// Like [`Self::derive_tweak`], but starting from `initial_chain_key`
+    /// instead of the all-zero chain key
+    ///
+    /// Protocols other than ECDSA use this to derive keys which are
+    /// independent of the ECDSA keys for the same derivation path.
+    pub fn derive_tweak_with_chain_key(
+        &self,
+        master_public_key: &EccPoint,
+        initial_chain_key: &[u8],
     ) -> ThresholdEcdsaResult<(EccScalar, Vec<u8>)> {
         let curve_type = master_public_key.curve_type();
 
         if curve_type == EccCurveType::K256 {
             let mut derived_key = *master_public_key;
-            let mut derived_chain_key = vec![0; 32];
+            let mut derived_chain_key = initial_chain_key.to_vec();
             let mut derived_offset = EccScalar::zero(curve_type);
 
             for idx in &self.path {
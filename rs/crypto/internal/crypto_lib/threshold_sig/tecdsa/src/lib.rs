@@ -69,6 +69,19 @@
 //! * Generation and verification of signature shares
 //! * Generation and verification of combined signatures
 //!
+//! ## Protocol: BIP340 Signature Generation and Verification
+//!
+//! File: `bip340.rs`
+//!
+//! Threshold Schnorr signatures following BIP340 over secp256k1. The
+//! protocol reuses the key transcript of threshold ECDSA and requires an
+//! unmasked presignature transcript, but no multiplication transcripts.
+//! Key derivation follows ECDSA, but starts from a BIP340 specific chain
+//! key so that BIP340 and ECDSA keys for the same path are unrelated.
+//!
+//! Ed25519 is not supported, as no implementation of the Ed25519 group
+//! is included in this crate.
+//!
 //! ## Protocol: MEGa Encryption
 //!
 //! File: `mega.rs`
@@ -241,6 +254,7 @@ pub enum ThresholdEcdsaError {
 
 pub type ThresholdEcdsaResult<T> = std::result::Result<T, ThresholdEcdsaError>;
 
+pub mod bip340;
 mod complaints;
 mod dealings;
 mod fe;
@@ -268,6 +282,7 @@ pub use crate::transcript::*;
 pub use crate::xmd::*;
 
 pub use crate::key_derivation::{DerivationIndex, DerivationPath};
+pub use bip340::{ThresholdBip340CombinedSigInternal, ThresholdBip340SigShareInternal};
 pub use sign::{ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaSigShareInternal};
 
 /// Create MEGa encryption keypair
@@ -911,6 +926,124 @@ pub fn derive_public_key(
     )?)
 }
 
+/// Create a new threshold BIP340 signature share
+///
+/// Both the key transcript and the presignature transcript must be
+/// unmasked; key_opening and presig_opening are our openings of them.
+///
+/// Unlike ECDSA, the message is not hashed beforehand and may be of any
+/// length.
+pub fn sign_share_bip340(
+    derivation_path: &DerivationPath,
+    message: &[u8],
+    randomness: Randomness,
+    key_transcript: &IDkgTranscriptInternal,
+    key_opening: &CommitmentOpening,
+    presig_transcript: &IDkgTranscriptInternal,
+    presig_opening: &CommitmentOpening,
+) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaGenerateSigShareInternalError> {
+    ThresholdBip340SigShareInternal::new(
+        derivation_path,
+        message,
+        randomness,
+        key_transcript,
+        key_opening,
+        presig_transcript,
+        presig_opening,
+    )
+    .map_err(|e| e.into())
+}
+
+/// Verify a threshold BIP340 signature share
+pub fn verify_bip340_signature_share(
+    sig_share: &ThresholdBip340SigShareInternal,
+    derivation_path: &DerivationPath,
+    message: &[u8],
+    randomness: Randomness,
+    signer_index: NodeIndex,
+    key_transcript: &IDkgTranscriptInternal,
+    presig_transcript: &IDkgTranscriptInternal,
+) -> Result<(), ThresholdEcdsaVerifySigShareInternalError> {
+    let accept = sig_share.verify(
+        derivation_path,
+        message,
+        randomness,
+        signer_index,
+        key_transcript,
+        presig_transcript,
+    )?;
+
+    if !accept {
+        return Err(ThresholdEcdsaVerifySigShareInternalError::InvalidSignatureShare);
+    }
+
+    Ok(())
+}
+
+/// Combine sufficient signature shares into a BIP340 signature
+///
+/// The signature shares must be verified prior to use, and there must
+/// be at least reconstruction_threshold many of them.
+pub fn combine_bip340_sig_shares(
+    derivation_path: &DerivationPath,
+    message: &[u8],
+    randomness: Randomness,
+    key_transcript: &IDkgTranscriptInternal,
+    presig_transcript: &IDkgTranscriptInternal,
+    reconstruction_threshold: NumberOfNodes,
+    sig_shares: &BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>,
+) -> Result<ThresholdBip340CombinedSigInternal, ThresholdEcdsaCombineSigSharesInternalError> {
+    ThresholdBip340CombinedSigInternal::new(
+        derivation_path,
+        message,
+        randomness,
+        key_transcript,
+        presig_transcript,
+        reconstruction_threshold,
+        sig_shares,
+    )
+    .map_err(|e| e.into())
+}
+
+/// Verify a threshold BIP340 signature
+///
+/// In addition to the BIP340 verification, this checks that the
+/// signature was generated with regards to the provided presignature
+/// transcript and randomness.
+pub fn verify_threshold_bip340_signature(
+    signature: &ThresholdBip340CombinedSigInternal,
+    derivation_path: &DerivationPath,
+    message: &[u8],
+    randomness: Randomness,
+    presig_transcript: &IDkgTranscriptInternal,
+    key_transcript: &IDkgTranscriptInternal,
+) -> Result<(), ThresholdEcdsaVerifySignatureInternalError> {
+    let accept = signature.verify(
+        derivation_path,
+        message,
+        randomness,
+        presig_transcript,
+        key_transcript,
+    )?;
+
+    if !accept {
+        return Err(ThresholdEcdsaVerifySignatureInternalError::InvalidSignature);
+    }
+
+    Ok(())
+}
+
+/// Derive the x-only BIP340 public key for `derivation_path`
+pub fn derive_bip340_public_key(
+    master_public_key: &MasterEcdsaPublicKey,
+    derivation_path: &DerivationPath,
+) -> Result<EcdsaPublicKey, ThresholdEcdsaDerivePublicKeyError> {
+    Ok(crate::bip340::derive_public_key(
+        master_public_key,
+        derivation_path,
+    )?)
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum IDkgGenerateComplaintsInternalError {
     InvalidArguments(String),
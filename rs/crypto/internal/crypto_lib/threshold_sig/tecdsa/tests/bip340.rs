@@ -0,0 +1,156 @@
+use ic_crypto_internal_threshold_sig_ecdsa::*;
+use ic_types::crypto::canister_threshold_sig::MasterEcdsaPublicKey;
+use ic_types::crypto::AlgorithmId;
+use ic_types::*;
+use rand::Rng;
+
+mod test_utils;
+
+use crate::test_utils::*;
+
+#[test]
+fn should_verify_bip340_test_vector() -> Result<(), ThresholdEcdsaError> {
+    // Test vector 0 from BIP340
+    let public_key =
+        hex::decode("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9").unwrap();
+    let message = [0u8; 32];
+    let signature = hex::decode("E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0").unwrap();
+
+    assert!(bip340::verify_bip340_signature(
+        &public_key,
+        &message,
+        &signature
+    )?);
+
+    let mut bad_message = message;
+    bad_message[0] ^= 1;
+    assert!(!bip340::verify_bip340_signature(
+        &public_key,
+        &bad_message,
+        &signature
+    )?);
+
+    let sig = ThresholdBip340CombinedSigInternal::deserialize(&signature)?;
+    assert_eq!(sig.serialize()?, signature);
+
+    Ok(())
+}
+
+#[test]
+fn should_derive_bip340_key_unrelated_to_ecdsa_key() -> Result<(), ThresholdEcdsaError> {
+    let setup = SignatureProtocolSetup::new(EccCurveType::K256, 4, 2, 0, random_seed())?;
+
+    let path = DerivationPath::new_bip32(&[1, 2, 3]);
+    let ecdsa_key = setup.public_key(&path)?;
+
+    let master_public_key = MasterEcdsaPublicKey {
+        algorithm_id: AlgorithmId::EcdsaSecp256k1,
+        public_key: setup.key.transcript.constant_term().serialize(),
+    };
+    let bip340_key = bip340::derive_public_key(&master_public_key, &path)?;
+
+    assert_eq!(bip340_key.public_key.len(), 32);
+    assert_ne!(bip340_key.public_key, ecdsa_key.public_key[1..]);
+    assert_ne!(bip340_key.chain_key, ecdsa_key.chain_key);
+
+    Ok(())
+}
+
+#[test]
+fn should_bip340_signing_protocol_work() -> Result<(), ThresholdEcdsaError> {
+    let nodes = 10;
+    let threshold = nodes / 3;
+    let number_of_dealings_corrupted = threshold;
+    let setup = SignatureProtocolSetup::new(
+        EccCurveType::K256,
+        nodes,
+        threshold,
+        number_of_dealings_corrupted,
+        random_seed(),
+    )?;
+
+    let mut rng = rand::thread_rng();
+    let message = rng.gen::<[u8; 32]>().to_vec();
+    let random_beacon = Randomness::from(rng.gen::<[u8; 32]>());
+
+    let derivation_path = DerivationPath::new_bip32(&[1, 2, 3]);
+    let proto = Bip340ProtocolExecution::new(
+        setup.clone(),
+        message.clone(),
+        random_beacon,
+        derivation_path.clone(),
+    );
+
+    let shares = proto.generate_shares()?;
+
+    let mut insufficient = shares.clone();
+    while insufficient.len() >= threshold {
+        let first = *insufficient.keys().next().unwrap();
+        insufficient.remove(&first);
+    }
+    assert!(proto.generate_signature(&insufficient).is_err());
+
+    let sig = proto.generate_signature(&shares).unwrap();
+    assert_eq!(
+        ThresholdBip340CombinedSigInternal::deserialize(&sig.serialize()?)?,
+        sig
+    );
+    assert!(proto.verify_signature(&sig).is_ok());
+
+    // A signature share computed for another message is rejected
+    let other_message = rng.gen::<[u8; 32]>().to_vec();
+    let other_proto = Bip340ProtocolExecution::new(
+        setup.clone(),
+        other_message,
+        random_beacon,
+        derivation_path.clone(),
+    );
+    let other_shares = other_proto.generate_shares()?;
+    let (index, other_share) = other_shares.iter().next().unwrap();
+    assert_eq!(
+        verify_bip340_signature_share(
+            other_share,
+            &derivation_path,
+            &message,
+            random_beacon,
+            *index,
+            &setup.key.transcript,
+            &setup.kappa.transcript,
+        ),
+        Err(ThresholdEcdsaVerifySigShareInternalError::InvalidSignatureShare)
+    );
+
+    // Signatures generated with other randomness do not verify in this run
+    let random_beacon2 = Randomness::from(rng.gen::<[u8; 32]>());
+    let proto2 = Bip340ProtocolExecution::new(setup, message, random_beacon2, derivation_path);
+    let sig2 = proto2
+        .generate_signature(&proto2.generate_shares()?)
+        .unwrap();
+
+    assert!(proto.verify_signature(&sig2).is_err());
+    assert!(proto2.verify_signature(&sig2).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn should_reject_masked_presignature_for_bip340() -> Result<(), ThresholdEcdsaError> {
+    let setup = SignatureProtocolSetup::new(EccCurveType::K256, 4, 2, 0, random_seed())?;
+
+    let result = sign_share_bip340(
+        &DerivationPath::new_bip32(&[]),
+        b"message",
+        Randomness::from([0u8; 32]),
+        &setup.key.transcript,
+        &setup.key.openings[0],
+        &setup.lambda.transcript,
+        &setup.lambda.openings[0],
+    );
+
+    assert_eq!(
+        result.unwrap_err(),
+        ThresholdEcdsaGenerateSigShareInternalError::InconsistentCommitments
+    );
+
+    Ok(())
+}
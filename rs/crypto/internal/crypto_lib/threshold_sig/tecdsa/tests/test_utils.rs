@@ -799,6 +799,112 @@ impl SignatureProtocolExecution {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct Bip340ProtocolExecution {
+    setup: SignatureProtocolSetup,
+    message: Vec<u8>,
+    random_beacon: Randomness,
+    derivation_path: DerivationPath,
+}
+
+impl Bip340ProtocolExecution {
+    pub fn new(
+        setup: SignatureProtocolSetup,
+        message: Vec<u8>,
+        random_beacon: Randomness,
+        derivation_path: DerivationPath,
+    ) -> Self {
+        Self {
+            setup,
+            message,
+            random_beacon,
+            derivation_path,
+        }
+    }
+
+    pub fn generate_shares(
+        &self,
+    ) -> ThresholdEcdsaResult<BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>> {
+        let mut shares = BTreeMap::new();
+
+        for node_index in 0..self.setup.setup.receivers {
+            let share = sign_share_bip340(
+                &self.derivation_path,
+                &self.message,
+                self.random_beacon,
+                &self.setup.key.transcript,
+                &self.setup.key.openings[node_index],
+                &self.setup.kappa.transcript,
+                &self.setup.kappa.openings[node_index],
+            )
+            .expect("Failed to create sig share");
+
+            verify_bip340_signature_share(
+                &share,
+                &self.derivation_path,
+                &self.message,
+                self.random_beacon,
+                node_index as NodeIndex,
+                &self.setup.key.transcript,
+                &self.setup.kappa.transcript,
+            )
+            .expect("Signature share verification failed");
+
+            shares.insert(node_index as NodeIndex, share);
+        }
+
+        Ok(shares)
+    }
+
+    pub fn generate_signature(
+        &self,
+        shares: &BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>,
+    ) -> Result<ThresholdBip340CombinedSigInternal, ThresholdEcdsaCombineSigSharesInternalError>
+    {
+        combine_bip340_sig_shares(
+            &self.derivation_path,
+            &self.message,
+            self.random_beacon,
+            &self.setup.key.transcript,
+            &self.setup.kappa.transcript,
+            self.setup.setup.threshold,
+            shares,
+        )
+    }
+
+    pub fn verify_signature(
+        &self,
+        sig: &ThresholdBip340CombinedSigInternal,
+    ) -> Result<(), ThresholdEcdsaVerifySignatureInternalError> {
+        verify_threshold_bip340_signature(
+            sig,
+            &self.derivation_path,
+            &self.message,
+            self.random_beacon,
+            &self.setup.kappa.transcript,
+            &self.setup.key.transcript,
+        )?;
+
+        // If verification succeeded, check with the plain BIP340 verifier also
+        let pk = self.bip340_public_key()?;
+        let sig = sig.serialize()?;
+        assert_eq!(
+            bip340::verify_bip340_signature(&pk.public_key, &self.message, &sig),
+            Ok(true)
+        );
+
+        Ok(())
+    }
+
+    pub fn bip340_public_key(&self) -> Result<EcdsaPublicKey, ThresholdEcdsaError> {
+        let master_public_key = MasterEcdsaPublicKey {
+            algorithm_id: AlgorithmId::EcdsaSecp256k1,
+            public_key: self.setup.key.transcript.constant_term().serialize(),
+        };
+        bip340::derive_public_key(&master_public_key, &self.derivation_path)
+    }
+}
+
 pub fn random_seed() -> Seed {
     let mut rng = rand::thread_rng();
     Seed::from_rng(&mut rng)
@@ -2,7 +2,8 @@
 
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaCombinedSigInternal,
+    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdBip340CombinedSigInternal,
+    ThresholdBip340SigShareInternal, ThresholdEcdsaCombinedSigInternal,
     ThresholdEcdsaSigShareInternal,
 };
 use ic_types::crypto::canister_threshold_sig::error::{
@@ -153,6 +154,19 @@ pub trait CspThresholdEcdsaSigner {
         key_times_lambda: &IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError>;
+
+    /// Generate a BIP340 signature share.
+    ///
+    /// `kappa_unmasked` is used as the nonce, so the other transcripts of the
+    /// presignature quadruple are not needed.
+    fn bip340_sign_share(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError>;
 }
 
 /// Crypto service provider (CSP) client for threshold ECDSA signature
@@ -199,4 +213,42 @@ pub trait CspThresholdEcdsaSigVerifier {
         kappa_unmasked: &IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError>;
+
+    /// Combine BIP340 signature shares.
+    #[allow(clippy::too_many_arguments)]
+    fn bip340_combine_sig_shares(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+        reconstruction_threshold: NumberOfNodes,
+        sig_shares: &BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>,
+    ) -> Result<ThresholdBip340CombinedSigInternal, ThresholdEcdsaCombineSigSharesError>;
+
+    /// Verify a BIP340 signature share
+    #[allow(clippy::too_many_arguments)]
+    fn bip340_verify_sig_share(
+        &self,
+        share: &ThresholdBip340SigShareInternal,
+        signer_index: NodeIndex,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<(), ThresholdEcdsaVerifySigShareError>;
+
+    /// Verify a combined BIP340 signature with respect to a particular kappa
+    /// transcript
+    fn bip340_verify_combined_signature(
+        &self,
+        signature: &ThresholdBip340CombinedSigInternal,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError>;
 }
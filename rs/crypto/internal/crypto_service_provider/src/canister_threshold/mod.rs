@@ -14,15 +14,19 @@ use crate::keygen::mega_key_id;
 use crate::secret_key_store::SecretKeyStore;
 use crate::Csp;
 use ic_crypto_internal_threshold_sig_ecdsa::{
+    combine_bip340_sig_shares as tbip340_combine_sig_shares,
     combine_sig_shares as tecdsa_combine_sig_shares, create_transcript as tecdsa_create_transcript,
     publicly_verify_dealing as tecdsa_verify_dealing_public,
+    verify_bip340_signature_share as tbip340_verify_signature_share,
     verify_complaint as tecdsa_verify_complaint,
     verify_dealing_opening as tecdsa_verify_dealing_opening,
     verify_signature_share as tecdsa_verify_signature_share,
+    verify_threshold_bip340_signature as tbip340_verify_combined_signature,
     verify_threshold_signature as tecdsa_verify_combined_signature,
     verify_transcript as tecdsa_verify_transcript, CommitmentOpening, DerivationPath,
     IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaCombinedSigInternal,
+    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdBip340CombinedSigInternal,
+    ThresholdBip340SigShareInternal, ThresholdEcdsaCombinedSigInternal,
     ThresholdEcdsaSigShareInternal, ThresholdEcdsaVerifySigShareInternalError,
     ThresholdEcdsaVerifySignatureInternalError,
 };
@@ -300,6 +304,20 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore> Csp
             algorithm_id,
         )
     }
+
+    fn bip340_sign_share(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError> {
+        debug!(self.logger; crypto.method_name => "bip340_sign_share");
+
+        self.csp_vault
+            .bip340_sign_share(derivation_path, message, nonce, key, kappa_unmasked)
+    }
 }
 
 /// Threshold-ECDSA signature verification client.
@@ -365,22 +383,7 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
             key_times_lambda,
             algorithm_id,
         )
-        .map_err(|e| match e {
-            ThresholdEcdsaVerifySigShareInternalError::UnsupportedAlgorithm => {
-                ThresholdEcdsaVerifySigShareError::InternalError {
-                    internal_error: "Algorithm not supported".to_string(),
-                }
-            }
-            ThresholdEcdsaVerifySigShareInternalError::InternalError(s) => {
-                ThresholdEcdsaVerifySigShareError::InternalError { internal_error: s }
-            }
-            ThresholdEcdsaVerifySigShareInternalError::InconsistentCommitments => {
-                ThresholdEcdsaVerifySigShareError::InvalidSignatureShare
-            }
-            ThresholdEcdsaVerifySigShareInternalError::InvalidSignatureShare => {
-                ThresholdEcdsaVerifySigShareError::InvalidSignatureShare
-            }
-        })
+        .map_err(verify_sig_share_error_from_internal)
     }
 
     fn ecdsa_verify_combined_signature(
@@ -404,23 +407,122 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
             key,
             algorithm_id,
         )
-        .map_err(|e| match e {
-            ThresholdEcdsaVerifySignatureInternalError::InvalidSignature => {
-                ThresholdEcdsaVerifyCombinedSignatureError::InvalidSignature
-            }
-            ThresholdEcdsaVerifySignatureInternalError::UnsupportedAlgorithm => {
-                ThresholdEcdsaVerifyCombinedSignatureError::InternalError {
-                    internal_error: "Algorithm not supported".to_string(),
-                }
+        .map_err(verify_combined_signature_error_from_internal)
+    }
+
+    fn bip340_combine_sig_shares(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key_transcript: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+        reconstruction_threshold: NumberOfNodes,
+        sig_shares: &BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>,
+    ) -> Result<ThresholdBip340CombinedSigInternal, ThresholdEcdsaCombineSigSharesError> {
+        debug!(self.logger; crypto.method_name => "bip340_combine_sig_shares");
+
+        tbip340_combine_sig_shares(
+            &DerivationPath::from(derivation_path),
+            message,
+            *nonce,
+            key_transcript,
+            kappa_unmasked,
+            reconstruction_threshold,
+            sig_shares,
+        )
+        .map_err(|e| ThresholdEcdsaCombineSigSharesError::InternalError {
+            internal_error: format!("{:?}", e),
+        })
+    }
+
+    fn bip340_verify_sig_share(
+        &self,
+        share: &ThresholdBip340SigShareInternal,
+        signer_index: NodeIndex,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<(), ThresholdEcdsaVerifySigShareError> {
+        debug!(self.logger; crypto.method_name => "bip340_verify_sig_share");
+
+        tbip340_verify_signature_share(
+            share,
+            &DerivationPath::from(derivation_path),
+            message,
+            *nonce,
+            signer_index,
+            key,
+            kappa_unmasked,
+        )
+        .map_err(verify_sig_share_error_from_internal)
+    }
+
+    fn bip340_verify_combined_signature(
+        &self,
+        signature: &ThresholdBip340CombinedSigInternal,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError> {
+        debug!(self.logger; crypto.method_name => "bip340_verify_combined_signature");
+
+        tbip340_verify_combined_signature(
+            signature,
+            &DerivationPath::from(derivation_path),
+            message,
+            *nonce,
+            kappa_unmasked,
+            key,
+        )
+        .map_err(verify_combined_signature_error_from_internal)
+    }
+}
+
+fn verify_sig_share_error_from_internal(
+    e: ThresholdEcdsaVerifySigShareInternalError,
+) -> ThresholdEcdsaVerifySigShareError {
+    match e {
+        ThresholdEcdsaVerifySigShareInternalError::UnsupportedAlgorithm => {
+            ThresholdEcdsaVerifySigShareError::InternalError {
+                internal_error: "Algorithm not supported".to_string(),
             }
-            ThresholdEcdsaVerifySignatureInternalError::InternalError(s) => {
-                ThresholdEcdsaVerifyCombinedSignatureError::InternalError { internal_error: s }
+        }
+        ThresholdEcdsaVerifySigShareInternalError::InternalError(s) => {
+            ThresholdEcdsaVerifySigShareError::InternalError { internal_error: s }
+        }
+        ThresholdEcdsaVerifySigShareInternalError::InconsistentCommitments => {
+            ThresholdEcdsaVerifySigShareError::InvalidSignatureShare
+        }
+        ThresholdEcdsaVerifySigShareInternalError::InvalidSignatureShare => {
+            ThresholdEcdsaVerifySigShareError::InvalidSignatureShare
+        }
+    }
+}
+
+fn verify_combined_signature_error_from_internal(
+    e: ThresholdEcdsaVerifySignatureInternalError,
+) -> ThresholdEcdsaVerifyCombinedSignatureError {
+    match e {
+        ThresholdEcdsaVerifySignatureInternalError::InvalidSignature => {
+            ThresholdEcdsaVerifyCombinedSignatureError::InvalidSignature
+        }
+        ThresholdEcdsaVerifySignatureInternalError::UnsupportedAlgorithm => {
+            ThresholdEcdsaVerifyCombinedSignatureError::InternalError {
+                internal_error: "Algorithm not supported".to_string(),
             }
-            ThresholdEcdsaVerifySignatureInternalError::InconsistentCommitments => {
-                ThresholdEcdsaVerifyCombinedSignatureError::InternalError {
-                    internal_error: "Wrong commitment types".to_string(),
-                }
+        }
+        ThresholdEcdsaVerifySignatureInternalError::InternalError(s) => {
+            ThresholdEcdsaVerifyCombinedSignatureError::InternalError { internal_error: s }
+        }
+        ThresholdEcdsaVerifySignatureInternalError::InconsistentCommitments => {
+            ThresholdEcdsaVerifyCombinedSignatureError::InternalError {
+                internal_error: "Wrong commitment types".to_string(),
             }
-        })
+        }
     }
 }
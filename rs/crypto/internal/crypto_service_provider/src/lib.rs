@@ -29,6 +29,7 @@ use crate::api::{
 };
 use crate::keygen::{forward_secure_key_id, public_key_hash_as_key_id};
 use crate::public_key_store::read_node_public_keys;
+use crate::secret_key_store::encryption::load_encryption_key;
 use crate::secret_key_store::volatile_store::VolatileSecretKeyStore;
 use crate::secret_key_store::SecretKeyStore;
 use crate::types::CspPublicKey;
@@ -224,14 +225,17 @@ impl Csp<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore> {
             logger,
             "Proceeding with an in-replica csp_vault, CryptoConfig: {:?}", config
         );
-        let secret_key_store = ProtoSecretKeyStore::open(
+        let encryption_key = load_encryption_key(config.sks_encryption_key_file.as_deref());
+        let secret_key_store = ProtoSecretKeyStore::open_with_encryption(
             &config.crypto_root,
             SKS_DATA_FILENAME,
+            encryption_key.clone(),
             Some(new_logger!(&logger)),
         );
-        let canister_key_store = ProtoSecretKeyStore::open(
+        let canister_key_store = ProtoSecretKeyStore::open_with_encryption(
             &config.crypto_root,
             CANISTER_SKS_DATA_FILENAME,
+            encryption_key,
             Some(new_logger!(&logger)),
         );
         let csp_vault = Arc::new(LocalCspVault::new(
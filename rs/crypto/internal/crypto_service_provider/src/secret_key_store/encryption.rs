@@ -0,0 +1,234 @@
+//! Encryption at rest of the data persisted by the `ProtoSecretKeyStore`
+//!
+//! The serialized store is encrypted with AES-256-GCM using a key derived
+//! from an externally supplied key file (e.g. one unsealed by the host at
+//! boot). The encrypted file has the layout
+//! `MAGIC || VERSION || nonce (12 bytes) || ciphertext || tag (16 bytes)`,
+//! and the file name of the store is bound as associated data so that the
+//! files of different stores cannot be swapped.
+use ic_crypto_sha::Sha256;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroize;
+
+const MAGIC: &[u8; 8] = b"ICSKSENC";
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+const KEY_DERIVATION_DOMAIN: &[u8] = b"ic-crypto-sks-encryption-key-v1";
+
+/// The minimum size of the key file, to ensure it has enough entropy.
+pub const MIN_KEY_FILE_LEN: usize = 32;
+
+/// Errors that can occur while encrypting or decrypting the store data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SksEncryptionError {
+    KeyFileError(String),
+    MalformedData(String),
+    DecryptionFailed,
+    EncryptionFailed(String),
+}
+
+impl std::error::Error for SksEncryptionError {}
+
+impl fmt::Display for SksEncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SksEncryptionError::KeyFileError(e) => write!(f, "invalid key file: {}", e),
+            SksEncryptionError::MalformedData(e) => write!(f, "malformed encrypted data: {}", e),
+            SksEncryptionError::DecryptionFailed => write!(
+                f,
+                "decryption failed: the data was tampered with or the key is wrong"
+            ),
+            SksEncryptionError::EncryptionFailed(e) => write!(f, "encryption failed: {}", e),
+        }
+    }
+}
+
+/// The key used to encrypt the secret key store at rest.
+#[derive(Clone, Zeroize)]
+#[zeroize(drop)]
+pub struct SksEncryptionKey([u8; 32]);
+
+impl fmt::Debug for SksEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SksEncryptionKey(REDACTED)")
+    }
+}
+
+impl SksEncryptionKey {
+    /// Derives the encryption key from the contents of `key_file`, which
+    /// must contain at least `MIN_KEY_FILE_LEN` bytes.
+    pub fn from_key_file(key_file: &Path) -> Result<Self, SksEncryptionError> {
+        let mut key_material = fs::read(key_file).map_err(|e| {
+            SksEncryptionError::KeyFileError(format!("{}: {}", key_file.display(), e))
+        })?;
+        let result = Self::from_key_material(&key_material);
+        key_material.zeroize();
+        result
+    }
+
+    /// Derives the encryption key from raw key material.
+    pub fn from_key_material(key_material: &[u8]) -> Result<Self, SksEncryptionError> {
+        if key_material.len() < MIN_KEY_FILE_LEN {
+            return Err(SksEncryptionError::KeyFileError(format!(
+                "expected at least {} bytes of key material but got {}",
+                MIN_KEY_FILE_LEN,
+                key_material.len()
+            )));
+        }
+        let mut hasher = Sha256::new();
+        hasher.write(KEY_DERIVATION_DOMAIN);
+        hasher.write(key_material);
+        Ok(Self(hasher.finish()))
+    }
+
+    /// Encrypts `plaintext`, binding `associated_data` to the ciphertext.
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, SksEncryptionError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let aad = Self::aad(associated_data);
+        let mut tag = [0u8; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            &aad,
+            plaintext,
+            &mut tag,
+        )
+        .map_err(|e| SksEncryptionError::EncryptionFailed(e.to_string()))?;
+
+        let mut result = Vec::with_capacity(HEADER_LEN + NONCE_LEN + ciphertext.len() + TAG_LEN);
+        result.extend_from_slice(MAGIC);
+        result.push(VERSION);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        result.extend_from_slice(&tag);
+        Ok(result)
+    }
+
+    /// Decrypts `data` produced by `encrypt` with the same associated data.
+    pub fn decrypt(
+        &self,
+        data: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, SksEncryptionError> {
+        if !is_encrypted(data) {
+            return Err(SksEncryptionError::MalformedData(
+                "missing encryption header".to_string(),
+            ));
+        }
+        if data[MAGIC.len()] != VERSION {
+            return Err(SksEncryptionError::MalformedData(format!(
+                "unsupported version {}",
+                data[MAGIC.len()]
+            )));
+        }
+        if data.len() < HEADER_LEN + NONCE_LEN + TAG_LEN {
+            return Err(SksEncryptionError::MalformedData(
+                "data too short".to_string(),
+            ));
+        }
+        let nonce = &data[HEADER_LEN..HEADER_LEN + NONCE_LEN];
+        let ciphertext = &data[HEADER_LEN + NONCE_LEN..data.len() - TAG_LEN];
+        let tag = &data[data.len() - TAG_LEN..];
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(nonce),
+            &Self::aad(associated_data),
+            ciphertext,
+            tag,
+        )
+        .map_err(|_| SksEncryptionError::DecryptionFailed)
+    }
+
+    fn aad(associated_data: &[u8]) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(HEADER_LEN + associated_data.len());
+        aad.extend_from_slice(MAGIC);
+        aad.push(VERSION);
+        aad.extend_from_slice(associated_data);
+        aad
+    }
+}
+
+/// Loads the encryption key from `key_file`, if given.
+///
+/// # Panics
+/// * if the key cannot be derived from `key_file`
+pub(crate) fn load_encryption_key(key_file: Option<&Path>) -> Option<SksEncryptionKey> {
+    key_file.map(|key_file| {
+        SksEncryptionKey::from_key_file(key_file)
+            .unwrap_or_else(|e| panic!("Error loading SKS encryption key: {}", e))
+    })
+}
+
+/// Returns true if `data` starts with the header of encrypted store data.
+///
+/// A serialized `SecretKeyStore` protobuf never starts with the header: its
+/// first byte would be the tag of field 9 with wire type 1, which the
+/// message does not have.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.len() > MAGIC.len() && data.starts_with(MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> SksEncryptionKey {
+        SksEncryptionKey::from_key_material(&[byte; MIN_KEY_FILE_LEN]).unwrap()
+    }
+
+    #[test]
+    fn should_decrypt_encrypted_data() {
+        let data = key(1).encrypt(b"secret keys", b"sks_data.pb").unwrap();
+        assert!(is_encrypted(&data));
+        assert_eq!(
+            key(1).decrypt(&data, b"sks_data.pb").unwrap(),
+            b"secret keys".to_vec()
+        );
+    }
+
+    #[test]
+    fn should_fail_to_decrypt_with_wrong_key_or_associated_data() {
+        let data = key(1).encrypt(b"secret keys", b"sks_data.pb").unwrap();
+        assert_eq!(
+            key(2).decrypt(&data, b"sks_data.pb"),
+            Err(SksEncryptionError::DecryptionFailed)
+        );
+        assert_eq!(
+            key(1).decrypt(&data, b"canister_sks_data.pb"),
+            Err(SksEncryptionError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn should_fail_to_decrypt_tampered_data() {
+        let mut data = key(1).encrypt(b"secret keys", b"sks_data.pb").unwrap();
+        let last = data.len() - TAG_LEN - 1;
+        data[last] ^= 1;
+        assert_eq!(
+            key(1).decrypt(&data, b"sks_data.pb"),
+            Err(SksEncryptionError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn should_reject_short_key_material() {
+        assert!(matches!(
+            SksEncryptionKey::from_key_material(&[0; MIN_KEY_FILE_LEN - 1]),
+            Err(SksEncryptionError::KeyFileError(_))
+        ));
+    }
+}
@@ -7,6 +7,7 @@ pub use scope::Scope;
 use std::fmt;
 
 // Implementations
+pub mod encryption;
 pub mod proto_store;
 pub mod volatile_store;
 
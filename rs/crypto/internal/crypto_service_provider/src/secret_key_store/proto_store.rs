@@ -1,5 +1,6 @@
 //! Filesystem-backed secret key store
 #![allow(clippy::unwrap_used)]
+use crate::secret_key_store::encryption::{self, SksEncryptionKey};
 use crate::secret_key_store::{Scope, SecretKeyStore, SecretKeyStoreError};
 use crate::threshold::ni_dkg::{NIDKG_FS_SCOPE, NIDKG_THRESHOLD_SCOPE};
 use crate::types::CspSecretKey;
@@ -14,10 +15,11 @@ use std::borrow::{Borrow, BorrowMut};
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fs;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use zeroize::Zeroize;
 
 const CURRENT_SKS_VERSION: u32 = 2;
 
@@ -58,26 +60,63 @@ type SecretKeys = HashMap<KeyId, (CspSecretKey, Option<Scope>)>;
 
 /// A secret key store that persists data to the filesystem, using protobufs for
 /// serialization
+///
+/// If an encryption key is given, the data is encrypted at rest (see
+/// [`SksEncryptionKey`]). Unencrypted data found on disk is migrated, i.e.,
+/// rewritten encrypted, when the store is opened.
 pub struct ProtoSecretKeyStore {
     proto_file: PathBuf,
     keys: Arc<RwLock<SecretKeys>>,
+    encryption_key: Option<SksEncryptionKey>,
     logger: ReplicaLogger,
 }
 
 impl ProtoSecretKeyStore {
     /// Creates a database instance.
     pub fn open(dir: &Path, file_name: &str, logger: Option<ReplicaLogger>) -> Self {
+        Self::open_with_encryption(dir, file_name, None, logger)
+    }
+
+    /// Creates a database instance whose data is encrypted at rest with
+    /// `encryption_key`, if given.
+    ///
+    /// # Panics
+    /// * if the data on disk is encrypted but no `encryption_key` is given
+    /// * if the data on disk cannot be decrypted with `encryption_key`
+    pub fn open_with_encryption(
+        dir: &Path,
+        file_name: &str,
+        encryption_key: Option<SksEncryptionKey>,
+        logger: Option<ReplicaLogger>,
+    ) -> Self {
         CryptoConfig::check_dir_has_required_permissions(dir)
             .expect("wrong crypto root permissions");
         let proto_file = dir.join(file_name);
-        let secret_keys = match Self::read_sks_data_from_disk(&proto_file) {
-            Some(sks_proto) => sks_proto,
+        let logger = logger.unwrap_or_else(no_op_logger);
+        let secret_keys = match Self::read_sks_data_from_disk(&proto_file, encryption_key.as_ref())
+        {
+            Some((secret_keys, was_encrypted)) => {
+                if let (Some(key), false) = (&encryption_key, was_encrypted) {
+                    info!(
+                        logger,
+                        "Encrypting unencrypted SKS data in {}",
+                        proto_file.display()
+                    );
+                    ProtoSecretKeyStore::write_secret_keys_to_disk(
+                        &proto_file,
+                        Some(key),
+                        &secret_keys,
+                    );
+                }
+                secret_keys
+            }
             None => SecretKeys::new(),
         };
         ProtoSecretKeyStore {
             proto_file,
             keys: Arc::new(RwLock::new(secret_keys)),
-            logger: logger.unwrap_or_else(no_op_logger),
+            encryption_key,
+            logger,
         }
     }
 
@@ -86,12 +125,30 @@ impl ProtoSecretKeyStore {
         self.proto_file.as_path()
     }
 
-    fn read_sks_data_from_disk(sks_data_file: &Path) -> Option<SecretKeys> {
+    /// Reads the secret keys from `sks_data_file`, and returns them together
+    /// with whether the data on disk was encrypted.
+    fn read_sks_data_from_disk(
+        sks_data_file: &Path,
+        encryption_key: Option<&SksEncryptionKey>,
+    ) -> Option<(SecretKeys, bool)> {
         match fs::read(sks_data_file) {
             Ok(data) => {
+                let was_encrypted = encryption::is_encrypted(&data);
+                let data = if was_encrypted {
+                    let key = encryption_key.unwrap_or_else(|| {
+                        panic!(
+                            "SKS data in {} is encrypted but no encryption key is configured",
+                            sks_data_file.display()
+                        )
+                    });
+                    key.decrypt(&data, &Self::associated_data(sks_data_file))
+                        .unwrap_or_else(|e| panic!("Error decrypting SKS data: {}", e))
+                } else {
+                    data
+                };
                 let sks_pb = pb::SecretKeyStore::decode(&*data).expect("error parsing SKS data");
                 let keys = ProtoSecretKeyStore::migrate_to_current_version(sks_pb);
-                Some(keys)
+                Some((keys, was_encrypted))
             }
             Err(err) => {
                 if err.kind() == ErrorKind::NotFound {
@@ -103,6 +160,15 @@ impl ProtoSecretKeyStore {
         }
     }
 
+    /// The file name is bound to the encrypted data so that the files of
+    /// different stores cannot be swapped.
+    fn associated_data(sks_data_file: &Path) -> Vec<u8> {
+        sks_data_file
+            .file_name()
+            .map(|name| name.to_string_lossy().as_bytes().to_vec())
+            .unwrap_or_default()
+    }
+
     // TODO(CRP-532): remove support for the legacy format in a few weeks after
     // merging.
     fn migrate_to_current_version(sks_proto: pb::SecretKeyStore) -> SecretKeys {
@@ -221,9 +287,27 @@ impl ProtoSecretKeyStore {
         sks_proto
     }
 
-    fn write_secret_keys_to_disk(sks_data_file: &Path, secret_keys: &SecretKeys) {
+    fn write_secret_keys_to_disk(
+        sks_data_file: &Path,
+        encryption_key: Option<&SksEncryptionKey>,
+        secret_keys: &SecretKeys,
+    ) {
         let sks_proto = ProtoSecretKeyStore::secret_keys_to_sks_proto(secret_keys);
-        ic_utils::fs::write_protobuf_using_tmp_file(sks_data_file, &sks_proto).unwrap();
+        match encryption_key {
+            None => ic_utils::fs::write_protobuf_using_tmp_file(sks_data_file, &sks_proto).unwrap(),
+            Some(key) => {
+                let mut plaintext = Vec::new();
+                sks_proto
+                    .encode(&mut plaintext)
+                    .expect("error serializing SKS data");
+                let data = key
+                    .encrypt(&plaintext, &Self::associated_data(sks_data_file))
+                    .unwrap_or_else(|e| panic!("Error encrypting SKS data: {}", e));
+                plaintext.zeroize();
+                ic_utils::fs::write_using_tmp_file(sks_data_file, |writer| writer.write_all(&data))
+                    .unwrap();
+            }
+        }
     }
 }
 
@@ -238,7 +322,11 @@ impl SecretKeyStore for ProtoSecretKeyStore {
             Some(_) => Err(SecretKeyStoreError::DuplicateKeyId(id)),
             None => {
                 keys.insert(id, (key, scope));
-                ProtoSecretKeyStore::write_secret_keys_to_disk(&self.proto_file, keys);
+                ProtoSecretKeyStore::write_secret_keys_to_disk(
+                    &self.proto_file,
+                    self.encryption_key.as_ref(),
+                    keys,
+                );
                 Ok(())
             }
         })
@@ -258,7 +346,11 @@ impl SecretKeyStore for ProtoSecretKeyStore {
         let result = with_write_lock(&self.keys, |keys| match keys.get(id) {
             Some(_) => {
                 keys.remove(id);
-                ProtoSecretKeyStore::write_secret_keys_to_disk(&self.proto_file, keys);
+                ProtoSecretKeyStore::write_secret_keys_to_disk(
+                    &self.proto_file,
+                    self.encryption_key.as_ref(),
+                    keys,
+                );
                 Ok(true)
             }
             None => Ok(false),
@@ -285,7 +377,11 @@ impl SecretKeyStore for ProtoSecretKeyStore {
                 }
             }
             if keys.len() < orig_keys_count {
-                ProtoSecretKeyStore::write_secret_keys_to_disk(&self.proto_file, keys);
+                ProtoSecretKeyStore::write_secret_keys_to_disk(
+                    &self.proto_file,
+                    self.encryption_key.as_ref(),
+                    keys,
+                );
             }
             Ok(())
         })
@@ -384,6 +480,108 @@ pub mod tests {
         test_utils::should_retain_expected_keys(proto_key_store());
     }
 
+    #[test]
+    fn should_persist_keys_encrypted() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let key_id = test_utils::make_key_id(1);
+        let key = test_utils::make_secret_key(2);
+        {
+            let mut store = ProtoSecretKeyStore::open_with_encryption(
+                dir.as_ref(),
+                "sks_data.pb",
+                Some(encryption_key(1)),
+                None,
+            );
+            assert!(store.insert(key_id, key.clone(), None).is_ok());
+            let data = fs::read(store.proto_file_path()).unwrap();
+            assert!(encryption::is_encrypted(&data));
+        }
+
+        let store = ProtoSecretKeyStore::open_with_encryption(
+            dir.as_ref(),
+            "sks_data.pb",
+            Some(encryption_key(1)),
+            None,
+        );
+        assert_eq!(store.get(&key_id), Some(key));
+    }
+
+    #[test]
+    fn should_encrypt_unencrypted_store_on_open() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        let key_id = test_utils::make_key_id(1);
+        let key = test_utils::make_secret_key(2);
+        {
+            let mut store = ProtoSecretKeyStore::open(dir.as_ref(), "sks_data.pb", None);
+            assert!(store.insert(key_id, key.clone(), None).is_ok());
+            let data = fs::read(store.proto_file_path()).unwrap();
+            assert!(!encryption::is_encrypted(&data));
+        }
+
+        let store = ProtoSecretKeyStore::open_with_encryption(
+            dir.as_ref(),
+            "sks_data.pb",
+            Some(encryption_key(1)),
+            None,
+        );
+        assert_eq!(store.get(&key_id), Some(key));
+        let data = fs::read(store.proto_file_path()).unwrap();
+        assert!(encryption::is_encrypted(&data));
+    }
+
+    #[test]
+    #[should_panic(expected = "no encryption key is configured")]
+    fn should_panic_opening_encrypted_store_without_key() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        {
+            let mut store = ProtoSecretKeyStore::open_with_encryption(
+                dir.as_ref(),
+                "sks_data.pb",
+                Some(encryption_key(1)),
+                None,
+            );
+            assert!(store
+                .insert(
+                    test_utils::make_key_id(1),
+                    test_utils::make_secret_key(2),
+                    None
+                )
+                .is_ok());
+        }
+        ProtoSecretKeyStore::open(dir.as_ref(), "sks_data.pb", None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error decrypting SKS data")]
+    fn should_panic_opening_encrypted_store_with_wrong_key() {
+        let dir = mk_temp_dir_with_permissions(0o700);
+        {
+            let mut store = ProtoSecretKeyStore::open_with_encryption(
+                dir.as_ref(),
+                "sks_data.pb",
+                Some(encryption_key(1)),
+                None,
+            );
+            assert!(store
+                .insert(
+                    test_utils::make_key_id(1),
+                    test_utils::make_secret_key(2),
+                    None
+                )
+                .is_ok());
+        }
+        ProtoSecretKeyStore::open_with_encryption(
+            dir.as_ref(),
+            "sks_data.pb",
+            Some(encryption_key(2)),
+            None,
+        );
+    }
+
+    fn encryption_key(byte: u8) -> SksEncryptionKey {
+        SksEncryptionKey::from_key_material(&[byte; encryption::MIN_KEY_FILE_LEN]).unwrap()
+    }
+
     fn proto_key_store() -> TempSecretKeyStore {
         TempSecretKeyStore::new()
     }
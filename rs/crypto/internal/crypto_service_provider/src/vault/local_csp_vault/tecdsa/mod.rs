@@ -4,7 +4,8 @@ use crate::vault::api::ThresholdEcdsaSignerCspVault;
 use crate::vault::local_csp_vault::idkg::commitment_key_id;
 use crate::vault::local_csp_vault::LocalCspVault;
 use ic_crypto_internal_threshold_sig_ecdsa::{
-    sign_share as tecdsa_sign_share, CombinedCommitment, CommitmentOpening, IDkgTranscriptInternal,
+    sign_share as tecdsa_sign_share, sign_share_bip340 as tbip340_sign_share, CombinedCommitment,
+    CommitmentOpening, IDkgTranscriptInternal, ThresholdBip340SigShareInternal,
     ThresholdEcdsaSigShareInternal,
 };
 use ic_types::crypto::canister_threshold_sig::error::ThresholdEcdsaSignShareError;
@@ -51,6 +52,32 @@ impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
             internal_error: format!("{:?}", e),
         })
     }
+
+    fn bip340_sign_share(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError> {
+        let key_share = self.combined_commitment_opening_from_sks(&key.combined_commitment)?;
+        let kappa_share =
+            self.combined_commitment_opening_from_sks(&kappa_unmasked.combined_commitment)?;
+
+        tbip340_sign_share(
+            &derivation_path.into(),
+            message,
+            *nonce,
+            key,
+            &key_share,
+            kappa_unmasked,
+            &kappa_share,
+        )
+        .map_err(|e| ThresholdEcdsaSignShareError::InternalError {
+            internal_error: format!("{:?}", e),
+        })
+    }
 }
 
 impl<R: Rng + CryptoRng + Send + Sync, S: SecretKeyStore, C: SecretKeyStore>
@@ -0,0 +1,708 @@
+//! Auditing and rate limiting of the requests to the remote CSP vault that
+//! use secret keys to sign or to decrypt.
+//!
+//! Every such request is recorded as a line in an append-only audit log:
+//! ```text
+//! <seq> <time_nanos> <operation> <key_id> <caller> <result> <prev_hash> <hash>
+//! ```
+//! where `hash` is the SHA-256 hash of the record up to and including
+//! `prev_hash`, which is the `hash` of the previous record (or all zeros for
+//! the first one). Modifying, removing or reordering records thus breaks the
+//! hash chain, which is verified whenever the log is opened.
+//!
+//! The sequence number and hash of the last record are also anchored in a
+//! separate head file (`<audit_log>.head`), so that removing records from the
+//! end of the log is detected as well. The records are written and synced to
+//! disk by a dedicated thread, which the requests wait for without blocking
+//! the async executor.
+use ic_config::crypto::CspVaultOperation;
+use ic_crypto_sha::Sha256;
+use ic_logger::{warn, ReplicaLogger};
+use ic_types::crypto::KeyId;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+
+const GENESIS_HASH: [u8; 32] = [0; 32];
+
+/// The outcome of an audited request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditResult {
+    Ok,
+    Error,
+    RateLimited,
+}
+
+impl AuditResult {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditResult::Ok => "ok",
+            AuditResult::Error => "error",
+            AuditResult::RateLimited => "rate_limited",
+        }
+    }
+}
+
+fn operation_name(operation: CspVaultOperation) -> &'static str {
+    match operation {
+        CspVaultOperation::Sign => "sign",
+        CspVaultOperation::MultiSign => "multi_sign",
+        CspVaultOperation::ThresholdSign => "threshold_sign",
+        CspVaultOperation::TlsSign => "tls_sign",
+        CspVaultOperation::EcdsaSignShare => "ecdsa_sign_share",
+        CspVaultOperation::Bip340SignShare => "bip340_sign_share",
+        CspVaultOperation::IdkgOpenDealing => "idkg_open_dealing",
+        CspVaultOperation::NiDkgCreateDealing => "ni_dkg_create_dealing",
+        CspVaultOperation::NiDkgLoadThresholdSigningKey => "ni_dkg_load_threshold_signing_key",
+        CspVaultOperation::IdkgVerifyDealingPrivate => "idkg_verify_dealing_private",
+        CspVaultOperation::IdkgLoadTranscript => "idkg_load_transcript",
+        CspVaultOperation::IdkgLoadTranscriptWithOpenings => "idkg_load_transcript_with_openings",
+    }
+}
+
+#[derive(Debug)]
+pub enum AuditLogError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Corrupted {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for AuditLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditLogError::Io { path, error } => {
+                write!(f, "I/O error on audit log {}: {}", path.display(), error)
+            }
+            AuditLogError::Corrupted { path, line, reason } => write!(
+                f,
+                "audit log {} is corrupted at line {}: {}",
+                path.display(),
+                line,
+                reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AuditLogError {}
+
+/// The position at the end of a valid audit log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditLogHead {
+    pub next_seq: u64,
+    pub last_hash: [u8; 32],
+}
+
+fn record_hash(record_without_hash: &str) -> [u8; 32] {
+    Sha256::hash(record_without_hash.as_bytes())
+}
+
+const GENESIS_HEAD: AuditLogHead = AuditLogHead {
+    next_seq: 0,
+    last_hash: GENESIS_HASH,
+};
+
+/// Returns the path of the file anchoring the head of the audit log at
+/// `log_path`.
+pub fn audit_log_head_path(log_path: &Path) -> PathBuf {
+    let mut file_name = log_path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(OsString::new);
+    file_name.push(".head");
+    log_path.with_file_name(file_name)
+}
+
+/// Reads the head anchored in the file at `path`. A missing file anchors the
+/// empty log.
+fn read_anchored_head(path: &Path) -> Result<AuditLogHead, AuditLogError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(GENESIS_HEAD),
+        Err(error) => {
+            return Err(AuditLogError::Io {
+                path: path.to_path_buf(),
+                error,
+            })
+        }
+    };
+    let corrupted = |reason: &str| AuditLogError::Corrupted {
+        path: path.to_path_buf(),
+        line: 1,
+        reason: reason.to_string(),
+    };
+    let (next_seq, last_hash) = contents
+        .trim_end()
+        .split_once(' ')
+        .ok_or_else(|| corrupted("missing hash"))?;
+    let next_seq = next_seq
+        .parse()
+        .map_err(|_| corrupted("malformed sequence number"))?;
+    let mut head = AuditLogHead {
+        next_seq,
+        last_hash: GENESIS_HASH,
+    };
+    hex::decode_to_slice(last_hash, &mut head.last_hash)
+        .map_err(|_| corrupted("malformed hash"))?;
+    Ok(head)
+}
+
+/// Atomically replaces the head anchored in the file at `path`.
+fn write_anchored_head(path: &Path, head: &AuditLogHead) -> Result<(), AuditLogError> {
+    let io_error = |error| AuditLogError::Io {
+        path: path.to_path_buf(),
+        error,
+    };
+    let mut tmp_file_name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(OsString::new);
+    tmp_file_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_file_name);
+    let mut tmp_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .map_err(io_error)?;
+    writeln!(
+        tmp_file,
+        "{} {}",
+        head.next_seq,
+        hex::encode(head.last_hash)
+    )
+    .and_then(|()| tmp_file.sync_all())
+    .map_err(io_error)?;
+    fs::rename(&tmp_path, path).map_err(io_error)
+}
+
+/// Verifies the hash chain of the audit log at `path`, and returns its head
+/// together with the head before its last record (if any).
+fn verify_hash_chain(path: &Path) -> Result<(AuditLogHead, Option<AuditLogHead>), AuditLogError> {
+    let io_error = |error| AuditLogError::Io {
+        path: path.to_path_buf(),
+        error,
+    };
+    let file = File::open(path).map_err(io_error)?;
+    let mut head = GENESIS_HEAD;
+    let mut previous_head = None;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        let corrupted = |reason: String| AuditLogError::Corrupted {
+            path: path.to_path_buf(),
+            line: index + 1,
+            reason,
+        };
+        let (record_without_hash, hash) = line
+            .rsplit_once(' ')
+            .ok_or_else(|| corrupted("missing hash".to_string()))?;
+        let fields: Vec<&str> = record_without_hash.split(' ').collect();
+        if fields.len() != 7 {
+            return Err(corrupted(format!(
+                "expected 8 fields, got {}",
+                fields.len() + 1
+            )));
+        }
+        if fields[0] != head.next_seq.to_string() {
+            return Err(corrupted(format!(
+                "expected sequence number {}, got {}",
+                head.next_seq, fields[0]
+            )));
+        }
+        if fields[6] != hex::encode(head.last_hash) {
+            return Err(corrupted("the previous hash does not match".to_string()));
+        }
+        let expected_hash = record_hash(record_without_hash);
+        if hash != hex::encode(expected_hash) {
+            return Err(corrupted("the hash does not match".to_string()));
+        }
+        let next_head = AuditLogHead {
+            next_seq: head.next_seq + 1,
+            last_hash: expected_hash,
+        };
+        previous_head = Some(std::mem::replace(&mut head, next_head));
+    }
+    Ok((head, previous_head))
+}
+
+/// Verifies the hash chain of the audit log at `path` against the head
+/// anchored next to it and returns the head of the log.
+///
+/// The log may be one record ahead of the anchored head, as a crash can
+/// happen after a record is written but before the head is updated. Any
+/// other mismatch, in particular records missing from the end of the log,
+/// is reported as corruption.
+pub fn verify_audit_log(path: &Path) -> Result<AuditLogHead, AuditLogError> {
+    let anchored_head = read_anchored_head(&audit_log_head_path(path))?;
+    let (head, previous_head) = if path.exists() {
+        verify_hash_chain(path)?
+    } else {
+        (GENESIS_HEAD, None)
+    };
+    if head == anchored_head || previous_head.as_ref() == Some(&anchored_head) {
+        return Ok(head);
+    }
+    let reason = if head.next_seq < anchored_head.next_seq {
+        format!(
+            "the log ends before record {} of the anchored head",
+            anchored_head.next_seq - 1
+        )
+    } else {
+        format!(
+            "the log does not match the anchored head at record {}",
+            anchored_head.next_seq
+        )
+    };
+    Err(AuditLogError::Corrupted {
+        path: path.to_path_buf(),
+        line: head.next_seq.min(anchored_head.next_seq) as usize + 1,
+        reason,
+    })
+}
+
+struct AuditLog {
+    path: PathBuf,
+    head_path: PathBuf,
+    file: File,
+    head: AuditLogHead,
+}
+
+impl AuditLog {
+    /// Opens the audit log at `path`, creating it if needed, after verifying
+    /// the existing records.
+    fn open(path: &Path) -> Result<Self, AuditLogError> {
+        let head = verify_audit_log(path)?;
+        let head_path = audit_log_head_path(path);
+        write_anchored_head(&head_path, &head)?;
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|error| AuditLogError::Io {
+                path: path.to_path_buf(),
+                error,
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            head_path,
+            file,
+            head,
+        })
+    }
+
+    fn append(&mut self, record: &AuditRecord) -> Result<(), AuditLogError> {
+        let time_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let record_without_hash = format!(
+            "{} {} {} {} {} {} {}",
+            self.head.next_seq,
+            time_nanos,
+            operation_name(record.operation),
+            record
+                .key_id
+                .map_or_else(|| "-".to_string(), |key_id| hex::encode(key_id.0)),
+            record.caller,
+            record.result.as_str(),
+            hex::encode(self.head.last_hash),
+        );
+        let hash = record_hash(&record_without_hash);
+        writeln!(self.file, "{} {}", record_without_hash, hex::encode(hash))
+            .and_then(|()| self.file.sync_data())
+            .map_err(|error| AuditLogError::Io {
+                path: self.path.clone(),
+                error,
+            })?;
+        self.head = AuditLogHead {
+            next_seq: self.head.next_seq + 1,
+            last_hash: hash,
+        };
+        write_anchored_head(&self.head_path, &self.head)
+    }
+}
+
+/// A request to be recorded in the audit log.
+struct AuditRecord {
+    operation: CspVaultOperation,
+    key_id: Option<KeyId>,
+    caller: String,
+    result: AuditResult,
+}
+
+/// A record sent to the writer thread, which reports back once the record is
+/// on disk.
+type PendingRecord = (AuditRecord, oneshot::Sender<Result<(), AuditLogError>>);
+
+/// Spawns the thread that appends the records received on the returned channel
+/// to `audit_log`. The thread stops when the channel is closed.
+fn spawn_audit_log_writer(
+    mut audit_log: AuditLog,
+) -> Result<mpsc::UnboundedSender<PendingRecord>, AuditLogError> {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<PendingRecord>();
+    let path = audit_log.path.clone();
+    std::thread::Builder::new()
+        .name("csp_vault_audit_log".to_string())
+        .spawn(move || {
+            while let Some((record, written)) = receiver.blocking_recv() {
+                // The requester may have gone away, but the record is written anyway.
+                let _ = written.send(audit_log.append(&record));
+            }
+        })
+        .map_err(|error| AuditLogError::Io { path, error })?;
+    Ok(sender)
+}
+
+/// A token bucket allowing `per_second` requests per second, in bursts of up
+/// to `per_second` requests.
+struct RateLimiter {
+    per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(per_second: u32, now: Instant) -> Self {
+        Self {
+            per_second: per_second as f64,
+            tokens: per_second as f64,
+            last_refill: now,
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.per_second).min(self.per_second);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Audits and rate limits the requests to the vault.
+pub struct VaultAuditor {
+    audit_log_writer: Option<mpsc::UnboundedSender<PendingRecord>>,
+    rate_limiters: BTreeMap<CspVaultOperation, Mutex<RateLimiter>>,
+    logger: ReplicaLogger,
+}
+
+impl VaultAuditor {
+    /// Creates an auditor recording requests in `audit_log_file`, if set, and
+    /// enforcing the per-operation `rate_limits`.
+    pub fn new(
+        audit_log_file: Option<&Path>,
+        rate_limits: &BTreeMap<CspVaultOperation, u32>,
+        logger: ReplicaLogger,
+    ) -> Result<Self, AuditLogError> {
+        let audit_log_writer = audit_log_file
+            .map(|path| AuditLog::open(path).and_then(spawn_audit_log_writer))
+            .transpose()?;
+        let now = Instant::now();
+        let rate_limiters = rate_limits
+            .iter()
+            .map(|(operation, per_second)| {
+                (*operation, Mutex::new(RateLimiter::new(*per_second, now)))
+            })
+            .collect();
+        Ok(Self {
+            audit_log_writer,
+            rate_limiters,
+            logger,
+        })
+    }
+
+    /// Runs `operation_fn` unless the rate limit of `operation` is exceeded,
+    /// in which case the error built by `rate_limited_error` is returned, and
+    /// records the request in the audit log. The result is only returned once
+    /// the record is on disk.
+    ///
+    /// Panics if the request cannot be recorded, so that no operation goes
+    /// unaudited.
+    pub async fn audit<T, E>(
+        &self,
+        operation: CspVaultOperation,
+        key_id: Option<&KeyId>,
+        caller: &str,
+        rate_limited_error: impl FnOnce(String) -> E,
+        operation_fn: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let within_rate_limit = match self.rate_limiters.get(&operation) {
+            Some(limiter) => limiter.lock().unwrap().try_acquire(Instant::now()),
+            None => true,
+        };
+        let (result, audit_result) = if within_rate_limit {
+            let result = operation_fn();
+            let audit_result = match result {
+                Ok(_) => AuditResult::Ok,
+                Err(_) => AuditResult::Error,
+            };
+            (result, audit_result)
+        } else {
+            warn!(
+                self.logger,
+                "Rate limit of CspVault operation {} exceeded by {}",
+                operation_name(operation),
+                caller
+            );
+            (
+                Err(rate_limited_error(format!(
+                    "rate limit of operation {} exceeded",
+                    operation_name(operation)
+                ))),
+                AuditResult::RateLimited,
+            )
+        };
+        if let Some(audit_log_writer) = &self.audit_log_writer {
+            let record = AuditRecord {
+                operation,
+                key_id: key_id.copied(),
+                caller: caller.to_string(),
+                result: audit_result,
+            };
+            let (written_sender, written) = oneshot::channel();
+            audit_log_writer
+                .send((record, written_sender))
+                .unwrap_or_else(|_| panic!("The CspVault audit log writer has stopped"));
+            written
+                .await
+                .unwrap_or_else(|_| panic!("The CspVault audit log writer has stopped"))
+                .unwrap_or_else(|e| panic!("Failed to record CspVault request: {}", e));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const CALLER: &str = "pid=1,uid=2,gid=3";
+
+    fn key_id() -> KeyId {
+        KeyId([42; 32])
+    }
+
+    fn succeed() -> Result<(), String> {
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_record_requests_in_hash_chain() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let auditor = VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).unwrap();
+
+        assert_eq!(
+            auditor
+                .audit(
+                    CspVaultOperation::Sign,
+                    Some(&key_id()),
+                    CALLER,
+                    |e| e,
+                    succeed
+                )
+                .await,
+            Ok(())
+        );
+        assert_eq!(
+            auditor
+                .audit(
+                    CspVaultOperation::TlsSign,
+                    None,
+                    CALLER,
+                    |e| e,
+                    || { Err::<(), _>("failed".to_string()) }
+                )
+                .await,
+            Err("failed".to_string())
+        );
+
+        let head = verify_audit_log(&path).unwrap();
+        assert_eq!(head.next_seq, 2);
+        let log = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(&format!("sign {} {} ok", hex::encode(key_id().0), CALLER)));
+        assert!(lines[1].contains(&format!("tls_sign - {} error", CALLER)));
+    }
+
+    #[tokio::test]
+    async fn should_continue_hash_chain_when_reopened() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        for _ in 0..2 {
+            let auditor = VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).unwrap();
+            auditor
+                .audit(
+                    CspVaultOperation::ThresholdSign,
+                    Some(&key_id()),
+                    CALLER,
+                    |e| e,
+                    succeed,
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(verify_audit_log(&path).unwrap().next_seq, 2);
+    }
+
+    #[tokio::test]
+    async fn should_detect_tampering() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let auditor = VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).unwrap();
+        for _ in 0..3 {
+            auditor
+                .audit(
+                    CspVaultOperation::Sign,
+                    Some(&key_id()),
+                    CALLER,
+                    |e| e,
+                    succeed,
+                )
+                .await
+                .unwrap();
+        }
+        let log = fs::read_to_string(&path).unwrap();
+
+        // Modified record.
+        fs::write(&path, log.replacen(" ok ", " error ", 1)).unwrap();
+        assert!(matches!(
+            verify_audit_log(&path),
+            Err(AuditLogError::Corrupted { line: 1, .. })
+        ));
+
+        // Removed record.
+        let lines: Vec<&str> = log.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(matches!(
+            verify_audit_log(&path),
+            Err(AuditLogError::Corrupted { line: 2, .. })
+        ));
+        assert!(VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_detect_truncation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let auditor = VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).unwrap();
+        let mut logs = vec![];
+        for _ in 0..3 {
+            auditor
+                .audit(
+                    CspVaultOperation::Sign,
+                    Some(&key_id()),
+                    CALLER,
+                    |e| e,
+                    succeed,
+                )
+                .await
+                .unwrap();
+            logs.push(fs::read_to_string(&path).unwrap());
+        }
+        drop(auditor);
+        let head_path = audit_log_head_path(&path);
+        assert_eq!(
+            read_anchored_head(&head_path).unwrap(),
+            verify_audit_log(&path).unwrap()
+        );
+
+        // Last record removed.
+        fs::write(&path, &logs[1]).unwrap();
+        assert!(matches!(
+            verify_audit_log(&path),
+            Err(AuditLogError::Corrupted { line: 3, .. })
+        ));
+        assert!(VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).is_err());
+
+        // Head file removed together with the log records.
+        fs::remove_file(&head_path).unwrap();
+        assert!(matches!(
+            verify_audit_log(&path),
+            Err(AuditLogError::Corrupted { line: 1, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_accept_log_one_record_ahead_of_anchored_head() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let head_path = audit_log_head_path(&path);
+        let auditor = VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).unwrap();
+        let sign = || auditor.audit(CspVaultOperation::Sign, None, CALLER, |e| e, succeed);
+        sign().await.unwrap();
+        let anchored_head = fs::read(&head_path).unwrap();
+        sign().await.unwrap();
+        drop(auditor);
+
+        // Simulates a crash after the record was written but before the head was
+        // updated.
+        fs::write(&head_path, anchored_head).unwrap();
+        assert_eq!(verify_audit_log(&path).unwrap().next_seq, 2);
+
+        // The head is updated when the log is reopened.
+        VaultAuditor::new(Some(&path), &BTreeMap::new(), no_op_logger()).unwrap();
+        assert_eq!(read_anchored_head(&head_path).unwrap().next_seq, 2);
+    }
+
+    #[tokio::test]
+    async fn should_rate_limit_operations() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let mut rate_limits = BTreeMap::new();
+        rate_limits.insert(CspVaultOperation::Sign, 2);
+        let auditor = VaultAuditor::new(Some(&path), &rate_limits, no_op_logger()).unwrap();
+
+        let sign = || auditor.audit(CspVaultOperation::Sign, None, CALLER, |e| e, succeed);
+        assert_eq!(sign().await, Ok(()));
+        assert_eq!(sign().await, Ok(()));
+        assert!(sign().await.unwrap_err().contains("rate limit"));
+        // Other operations are not limited.
+        for _ in 0..10 {
+            assert_eq!(
+                auditor
+                    .audit(CspVaultOperation::MultiSign, None, CALLER, |e| e, succeed)
+                    .await,
+                Ok(())
+            );
+        }
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            log.lines().filter(|l| l.contains(" rate_limited ")).count(),
+            1
+        );
+        assert_eq!(verify_audit_log(&path).unwrap().next_seq, 13);
+    }
+
+    #[test]
+    fn should_refill_rate_limiter_over_time() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1, now);
+        assert!(limiter.try_acquire(now));
+        assert!(!limiter.try_acquire(now));
+        assert!(limiter.try_acquire(now + std::time::Duration::from_secs(1)));
+    }
+}
@@ -5,10 +5,12 @@ use crate::vault::api::{
     CspMultiSignatureKeygenError, CspThresholdSignatureKeygenError, CspTlsKeygenError,
     CspTlsSignError,
 };
+use ic_config::crypto::CspVaultOperation;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors;
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdBip340SigShareInternal,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -29,6 +31,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 use tokio::net::UnixListener;
 
+pub mod audit;
 mod tarpc_csp_vault_client;
 mod tarpc_csp_vault_server;
 
@@ -199,9 +202,32 @@ pub trait TarpcCspVault {
         key_times_lambda: IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError>;
+
+    // Corresponds to `ThresholdEcdsaSignerCspVault.bip340_sign_share`
+    async fn bip340_sign_share(
+        derivation_path: ExtendedDerivationPath,
+        message: Vec<u8>,
+        nonce: Randomness,
+        key: IDkgTranscriptInternal,
+        kappa_unmasked: IDkgTranscriptInternal,
+    ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError>;
 }
 
-pub async fn run_csp_vault_server(sks_dir: &Path, listener: UnixListener, logger: ReplicaLogger) {
-    let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new(sks_dir, listener, logger);
+pub async fn run_csp_vault_server(
+    sks_dir: &Path,
+    sks_encryption_key_file: Option<&Path>,
+    audit_log_file: Option<&Path>,
+    rate_limits: &BTreeMap<CspVaultOperation, u32>,
+    listener: UnixListener,
+    logger: ReplicaLogger,
+) {
+    let server = tarpc_csp_vault_server::TarpcCspVaultServerImpl::new(
+        sks_dir,
+        sks_encryption_key_file,
+        audit_log_file,
+        rate_limits,
+        listener,
+        logger,
+    );
     server.run().await
 }
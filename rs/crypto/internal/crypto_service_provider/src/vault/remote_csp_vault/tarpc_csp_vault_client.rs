@@ -18,7 +18,8 @@ use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
 };
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdBip340SigShareInternal,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -487,4 +488,27 @@ impl ThresholdEcdsaSignerCspVault for RemoteCspVault {
             })
         })
     }
+
+    fn bip340_sign_share(
+        &self,
+        derivation_path: &ExtendedDerivationPath,
+        message: &[u8],
+        nonce: &Randomness,
+        key: &IDkgTranscriptInternal,
+        kappa_unmasked: &IDkgTranscriptInternal,
+    ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError> {
+        thread_universal_block_on(self.tarpc_csp_client.bip340_sign_share(
+            tarpc::context::current(),
+            derivation_path.clone(),
+            message.to_vec(),
+            *nonce,
+            key.clone(),
+            kappa_unmasked.clone(),
+        ))
+        .unwrap_or_else(|e| {
+            Err(ThresholdEcdsaSignShareError::InternalError {
+                internal_error: e.to_string(),
+            })
+        })
+    }
 }
@@ -1,4 +1,5 @@
 use crate::api::{CspCreateMEGaKeyError, CspThresholdSignError};
+use crate::secret_key_store::encryption::load_encryption_key;
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
 use crate::types::{CspPop, CspPublicCoefficients, CspPublicKey, CspSignature};
 use crate::vault::api::{
@@ -9,16 +10,19 @@ use crate::vault::api::{
     ThresholdSignatureCspVault,
 };
 use crate::vault::local_csp_vault::LocalCspVault;
+use crate::vault::remote_csp_vault::audit::VaultAuditor;
 use crate::vault::remote_csp_vault::TarpcCspVault;
 use crate::{TlsHandshakeCspVault, CANISTER_SKS_DATA_FILENAME, SKS_DATA_FILENAME};
+use ic_config::crypto::CspVaultOperation;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
     CspDkgCreateFsKeyError, CspDkgCreateReshareDealingError, CspDkgLoadPrivateKeyError,
-    CspDkgUpdateFsEpochError,
+    CspDkgUpdateFsEpochError, InternalError,
 };
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaSigShareInternal,
+    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdBip340SigShareInternal,
+    ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::encrypt::forward_secure::{
     CspFsEncryptionPop, CspFsEncryptionPublicKey,
@@ -28,7 +32,7 @@ use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::{
 };
 use ic_crypto_internal_types::NodeIndex;
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
-use ic_logger::{new_logger, ReplicaLogger};
+use ic_logger::{new_logger, warn, ReplicaLogger};
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError,
     IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
@@ -50,14 +54,17 @@ use tokio_util::codec::length_delimited::LengthDelimitedCodec;
 
 pub struct TarpcCspVaultServerImpl {
     local_csp_vault: Arc<LocalCspVault<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore>>,
+    auditor: Arc<VaultAuditor>,
     listener: UnixListener,
-    #[allow(unused)]
     logger: ReplicaLogger,
 }
 
 #[derive(Clone)]
 struct TarpcCspVaultServerWorker {
     local_csp_vault: Arc<LocalCspVault<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore>>,
+    auditor: Arc<VaultAuditor>,
+    // The peer credentials of the client, as recorded in the audit log.
+    caller: Arc<str>,
 }
 
 #[tarpc::server]
@@ -70,7 +77,15 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         msg: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspBasicSignatureError> {
-        self.local_csp_vault.sign(algorithm_id, &*msg, key_id)
+        self.auditor
+            .audit(
+                CspVaultOperation::Sign,
+                Some(&key_id),
+                &self.caller,
+                |internal_error| CspBasicSignatureError::InternalError { internal_error },
+                || self.local_csp_vault.sign(algorithm_id, &*msg, key_id),
+            )
+            .await
     }
 
     async fn gen_key_pair(
@@ -89,8 +104,18 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         message: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspMultiSignatureError> {
-        self.local_csp_vault
-            .multi_sign(algorithm_id, &*message, key_id)
+        self.auditor
+            .audit(
+                CspVaultOperation::MultiSign,
+                Some(&key_id),
+                &self.caller,
+                |internal_error| CspMultiSignatureError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault
+                        .multi_sign(algorithm_id, &*message, key_id)
+                },
+            )
+            .await
     }
 
     async fn gen_key_pair_with_pop(
@@ -109,8 +134,18 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         message: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspThresholdSignError> {
-        self.local_csp_vault
-            .threshold_sign(algorithm_id, &*message, key_id)
+        self.auditor
+            .audit(
+                CspVaultOperation::ThresholdSign,
+                Some(&key_id),
+                &self.caller,
+                |internal_error| CspThresholdSignError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault
+                        .threshold_sign(algorithm_id, &*message, key_id)
+                },
+            )
+            .await
     }
 
     async fn threshold_keygen_for_test(
@@ -159,14 +194,26 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         receiver_keys: BTreeMap<NodeIndex, CspFsEncryptionPublicKey>,
         maybe_resharing_secret: Option<KeyId>,
     ) -> Result<CspNiDkgDealing, CspDkgCreateReshareDealingError> {
-        self.local_csp_vault.create_dealing(
-            algorithm_id,
-            dealer_index,
-            threshold,
-            epoch,
-            &receiver_keys,
-            maybe_resharing_secret,
-        )
+        self.auditor
+            .audit(
+                CspVaultOperation::NiDkgCreateDealing,
+                maybe_resharing_secret.as_ref(),
+                &self.caller,
+                |internal_error| {
+                    CspDkgCreateReshareDealingError::InternalError(InternalError { internal_error })
+                },
+                || {
+                    self.local_csp_vault.create_dealing(
+                        algorithm_id,
+                        dealer_index,
+                        threshold,
+                        epoch,
+                        &receiver_keys,
+                        maybe_resharing_secret,
+                    )
+                },
+            )
+            .await
     }
 
     async fn load_threshold_signing_key(
@@ -178,13 +225,25 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         fs_key_id: KeyId,
         receiver_index: NodeIndex,
     ) -> Result<(), CspDkgLoadPrivateKeyError> {
-        self.local_csp_vault.load_threshold_signing_key(
-            algorithm_id,
-            epoch,
-            csp_transcript,
-            fs_key_id,
-            receiver_index,
-        )
+        self.auditor
+            .audit(
+                CspVaultOperation::NiDkgLoadThresholdSigningKey,
+                Some(&fs_key_id),
+                &self.caller,
+                |internal_error| {
+                    CspDkgLoadPrivateKeyError::InternalError(InternalError { internal_error })
+                },
+                || {
+                    self.local_csp_vault.load_threshold_signing_key(
+                        algorithm_id,
+                        epoch,
+                        csp_transcript,
+                        fs_key_id,
+                        receiver_index,
+                    )
+                },
+            )
+            .await
     }
 
     async fn retain_threshold_keys_if_present(
@@ -217,7 +276,15 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         message: Vec<u8>,
         key_id: KeyId,
     ) -> Result<CspSignature, CspTlsSignError> {
-        self.local_csp_vault.tls_sign(&*message, &key_id)
+        self.auditor
+            .audit(
+                CspVaultOperation::TlsSign,
+                Some(&key_id),
+                &self.caller,
+                |internal_error| CspTlsSignError::InternalError { internal_error },
+                || self.local_csp_vault.tls_sign(&*message, &key_id),
+            )
+            .await
     }
 
     // `IDkgProtocolCspVault`-methods.
@@ -251,14 +318,24 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         receiver_key_id: KeyId,
         context_data: Vec<u8>,
     ) -> Result<(), IDkgVerifyDealingPrivateError> {
-        self.local_csp_vault.idkg_verify_dealing_private(
-            algorithm_id,
-            &dealing,
-            dealer_index,
-            receiver_index,
-            receiver_key_id,
-            &context_data,
-        )
+        self.auditor
+            .audit(
+                CspVaultOperation::IdkgVerifyDealingPrivate,
+                Some(&receiver_key_id),
+                &self.caller,
+                IDkgVerifyDealingPrivateError::InternalError,
+                || {
+                    self.local_csp_vault.idkg_verify_dealing_private(
+                        algorithm_id,
+                        &dealing,
+                        dealer_index,
+                        receiver_index,
+                        receiver_key_id,
+                        &context_data,
+                    )
+                },
+            )
+            .await
     }
 
     async fn idkg_load_transcript(
@@ -270,13 +347,23 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         key_id: KeyId,
         transcript: IDkgTranscriptInternal,
     ) -> Result<BTreeMap<NodeIndex, IDkgComplaintInternal>, IDkgLoadTranscriptError> {
-        self.local_csp_vault.idkg_load_transcript(
-            &dealings,
-            &context_data,
-            receiver_index,
-            &key_id,
-            &transcript,
-        )
+        self.auditor
+            .audit(
+                CspVaultOperation::IdkgLoadTranscript,
+                Some(&key_id),
+                &self.caller,
+                |internal_error| IDkgLoadTranscriptError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault.idkg_load_transcript(
+                        &dealings,
+                        &context_data,
+                        receiver_index,
+                        &key_id,
+                        &transcript,
+                    )
+                },
+            )
+            .await
     }
 
     async fn idkg_load_transcript_with_openings(
@@ -289,14 +376,24 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         key_id: KeyId,
         transcript: IDkgTranscriptInternal,
     ) -> Result<(), IDkgLoadTranscriptError> {
-        self.local_csp_vault.idkg_load_transcript_with_openings(
-            &dealings,
-            &openings,
-            &context_data,
-            receiver_index,
-            &key_id,
-            &transcript,
-        )
+        self.auditor
+            .audit(
+                CspVaultOperation::IdkgLoadTranscriptWithOpenings,
+                Some(&key_id),
+                &self.caller,
+                |internal_error| IDkgLoadTranscriptError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault.idkg_load_transcript_with_openings(
+                        &dealings,
+                        &openings,
+                        &context_data,
+                        receiver_index,
+                        &key_id,
+                        &transcript,
+                    )
+                },
+            )
+            .await
     }
 
     async fn idkg_gen_mega_key_pair(
@@ -316,13 +413,23 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         opener_index: NodeIndex,
         opener_key_id: KeyId,
     ) -> Result<CommitmentOpening, IDkgOpenTranscriptError> {
-        self.local_csp_vault.idkg_open_dealing(
-            dealing,
-            dealer_index,
-            &context_data,
-            opener_index,
-            &opener_key_id,
-        )
+        self.auditor
+            .audit(
+                CspVaultOperation::IdkgOpenDealing,
+                Some(&opener_key_id),
+                &self.caller,
+                |internal_error| IDkgOpenTranscriptError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault.idkg_open_dealing(
+                        dealing,
+                        dealer_index,
+                        &context_data,
+                        opener_index,
+                        &opener_key_id,
+                    )
+                },
+            )
+            .await
     }
 
     // `ThresholdEcdsaSignerCspVault`-methods
@@ -339,27 +446,85 @@ impl TarpcCspVault for TarpcCspVaultServerWorker {
         key_times_lambda: IDkgTranscriptInternal,
         algorithm_id: AlgorithmId,
     ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError> {
-        self.local_csp_vault.ecdsa_sign_share(
-            &derivation_path,
-            &hashed_message,
-            &nonce,
-            &key,
-            &kappa_unmasked,
-            &lambda_masked,
-            &kappa_times_lambda,
-            &key_times_lambda,
-            algorithm_id,
-        )
+        // The key shares are identified by the transcripts, not by key ids.
+        self.auditor
+            .audit(
+                CspVaultOperation::EcdsaSignShare,
+                None,
+                &self.caller,
+                |internal_error| ThresholdEcdsaSignShareError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault.ecdsa_sign_share(
+                        &derivation_path,
+                        &hashed_message,
+                        &nonce,
+                        &key,
+                        &kappa_unmasked,
+                        &lambda_masked,
+                        &kappa_times_lambda,
+                        &key_times_lambda,
+                        algorithm_id,
+                    )
+                },
+            )
+            .await
+    }
+
+    async fn bip340_sign_share(
+        self,
+        _: context::Context,
+        derivation_path: ExtendedDerivationPath,
+        message: Vec<u8>,
+        nonce: Randomness,
+        key: IDkgTranscriptInternal,
+        kappa_unmasked: IDkgTranscriptInternal,
+    ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError> {
+        // The key shares are identified by the transcripts, not by key ids.
+        self.auditor
+            .audit(
+                CspVaultOperation::Bip340SignShare,
+                None,
+                &self.caller,
+                |internal_error| ThresholdEcdsaSignShareError::InternalError { internal_error },
+                || {
+                    self.local_csp_vault.bip340_sign_share(
+                        &derivation_path,
+                        &message,
+                        &nonce,
+                        &key,
+                        &kappa_unmasked,
+                    )
+                },
+            )
+            .await
     }
 }
 
 impl TarpcCspVaultServerImpl {
-    pub fn new(sks_dir: &Path, listener: UnixListener, logger: ReplicaLogger) -> Self {
-        let node_secret_key_store =
-            ProtoSecretKeyStore::open(sks_dir, SKS_DATA_FILENAME, Some(new_logger!(&logger)));
-        let canister_secret_key_store = ProtoSecretKeyStore::open(
+    /// Creates a server for the secret key stores in `sks_dir`.
+    ///
+    /// If `audit_log_file` is set, the requests to sign or to decrypt are
+    /// recorded in it, and they are rate limited per operation as configured
+    /// in `rate_limits`. Panics if the existing audit log is corrupted.
+    pub fn new(
+        sks_dir: &Path,
+        sks_encryption_key_file: Option<&Path>,
+        audit_log_file: Option<&Path>,
+        rate_limits: &BTreeMap<CspVaultOperation, u32>,
+        listener: UnixListener,
+        logger: ReplicaLogger,
+    ) -> Self {
+        let encryption_key = load_encryption_key(sks_encryption_key_file);
+        let node_secret_key_store = ProtoSecretKeyStore::open_with_encryption(
+            sks_dir,
+            SKS_DATA_FILENAME,
+            encryption_key.clone(),
+            Some(new_logger!(&logger)),
+        );
+        let canister_secret_key_store = ProtoSecretKeyStore::open_with_encryption(
             sks_dir,
             CANISTER_SKS_DATA_FILENAME,
+            encryption_key,
             Some(new_logger!(&logger)),
         );
         let local_csp_server = Arc::new(LocalCspVault::new(
@@ -368,8 +533,11 @@ impl TarpcCspVaultServerImpl {
             Arc::new(CryptoMetrics::none()),
             new_logger!(&logger),
         ));
+        let auditor = VaultAuditor::new(audit_log_file, rate_limits, new_logger!(&logger))
+            .unwrap_or_else(|e| panic!("Failed to open the CspVault audit log: {}", e));
         Self {
             local_csp_vault: local_csp_server,
+            auditor: Arc::new(auditor),
             listener,
             logger,
         }
@@ -388,12 +556,29 @@ impl TarpcCspVaultServerImpl {
                     e
                 )
             });
+            let caller: Arc<str> = match conn.peer_cred() {
+                Ok(cred) => format!(
+                    "pid={},uid={},gid={}",
+                    cred.pid()
+                        .map_or_else(|| "-".to_string(), |pid| pid.to_string()),
+                    cred.uid(),
+                    cred.gid()
+                )
+                .into(),
+                Err(e) => {
+                    warn!(self.logger, "Failed to get the peer credentials: {}", e);
+                    "-".into()
+                }
+            };
             let local_csp_server = Arc::clone(&self.local_csp_vault);
+            let auditor = Arc::clone(&self.auditor);
             tokio::spawn(async move {
                 let framed = codec_builder.new_framed(conn);
                 let transport = serde_transport::new(framed, Bincode::default());
                 let worker = TarpcCspVaultServerWorker {
                     local_csp_vault: local_csp_server,
+                    auditor,
+                    caller,
                 };
                 let channel_executor =
                     BaseChannel::with_defaults(transport).execute(worker.serve());
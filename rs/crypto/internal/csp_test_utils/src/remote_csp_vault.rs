@@ -1,5 +1,6 @@
 use crate::files::mk_temp_dir_with_permissions;
 use ic_logger::replica_logger::no_op_logger;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use tokio::net::UnixListener;
 
@@ -35,6 +36,9 @@ pub fn start_new_remote_csp_vault_server_for_test() -> PathBuf {
     });
     let server = ic_crypto_internal_csp::vault::remote_csp_vault::TarpcCspVaultServerImpl::new(
         sks_dir.path(),
+        None,
+        None,
+        &BTreeMap::new(),
         listener,
         no_op_logger(),
     );
@@ -49,7 +49,15 @@ async fn main() {
         )
     );
 
-    ic_crypto_internal_csp::run_csp_vault_server(sks_dir, systemd_socket_listener, logger).await;
+    ic_crypto_internal_csp::run_csp_vault_server(
+        sks_dir,
+        ic_config.crypto.sks_encryption_key_file.as_deref(),
+        ic_config.crypto.csp_vault_audit_log_file.as_deref(),
+        &ic_config.crypto.csp_vault_rate_limits,
+        systemd_socket_listener,
+        logger,
+    )
+    .await;
 }
 
 fn get_ic_config(replica_config_file: PathBuf) -> Config {
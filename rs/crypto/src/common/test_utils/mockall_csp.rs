@@ -29,7 +29,8 @@ use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
 };
 use ic_crypto_internal_threshold_sig_ecdsa::{
     CommitmentOpening, IDkgComplaintInternal, IDkgDealingInternal, IDkgTranscriptInternal,
-    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdEcdsaCombinedSigInternal,
+    IDkgTranscriptOperationInternal, MEGaPublicKey, ThresholdBip340CombinedSigInternal,
+    ThresholdBip340SigShareInternal, ThresholdEcdsaCombinedSigInternal,
     ThresholdEcdsaSigShareInternal,
 };
 use ic_crypto_internal_types::sign::threshold_sig::dkg::encryption_public_key::CspEncryptionPublicKey;
@@ -478,6 +479,15 @@ mock! {
             key_times_lambda: &IDkgTranscriptInternal,
             algorithm_id: AlgorithmId,
         ) -> Result<ThresholdEcdsaSigShareInternal, ThresholdEcdsaSignShareError>;
+
+        fn bip340_sign_share(
+            &self,
+            derivation_path: &ExtendedDerivationPath,
+            message: &[u8],
+            nonce: &Randomness,
+            key: &IDkgTranscriptInternal,
+            kappa_unmasked: &IDkgTranscriptInternal,
+        ) -> Result<ThresholdBip340SigShareInternal, ThresholdEcdsaSignShareError>;
     }
 
     pub trait CspThresholdEcdsaSigVerifier {
@@ -518,5 +528,37 @@ mock! {
             kappa_unmasked: &IDkgTranscriptInternal,
             algorithm_id: AlgorithmId,
         ) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError>;
+
+        fn bip340_combine_sig_shares(
+            &self,
+            derivation_path: &ExtendedDerivationPath,
+            message: &[u8],
+            nonce: &Randomness,
+            key: &IDkgTranscriptInternal,
+            kappa_unmasked: &IDkgTranscriptInternal,
+            reconstruction_threshold: NumberOfNodes,
+            sig_shares: &BTreeMap<NodeIndex, ThresholdBip340SigShareInternal>,
+        ) -> Result<ThresholdBip340CombinedSigInternal, ThresholdEcdsaCombineSigSharesError>;
+
+        fn bip340_verify_sig_share(
+            &self,
+            share: &ThresholdBip340SigShareInternal,
+            signer_index: NodeIndex,
+            derivation_path: &ExtendedDerivationPath,
+            message: &[u8],
+            nonce: &Randomness,
+            key: &IDkgTranscriptInternal,
+            kappa_unmasked: &IDkgTranscriptInternal,
+        ) -> Result<(), ThresholdEcdsaVerifySigShareError>;
+
+        fn bip340_verify_combined_signature(
+            &self,
+            signature: &ThresholdBip340CombinedSigInternal,
+            derivation_path: &ExtendedDerivationPath,
+            message: &[u8],
+            nonce: &Randomness,
+            key: &IDkgTranscriptInternal,
+            kappa_unmasked: &IDkgTranscriptInternal,
+        ) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError>;
     }
 }
@@ -596,6 +596,24 @@ impl<C: CryptoServiceProvider + Send + Sync> TlsHandshake for TempCryptoComponen
             .perform_tls_client_handshake_with_rustls(tcp_stream, server, registry_version)
             .await
     }
+
+    fn tls_server_config(
+        &self,
+        allowed_clients: AllowedClients,
+        registry_version: RegistryVersion,
+    ) -> Result<tokio_rustls::rustls::ServerConfig, TlsServerHandshakeError> {
+        self.crypto_component
+            .tls_server_config(allowed_clients, registry_version)
+    }
+
+    fn tls_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<tokio_rustls::rustls::ClientConfig, TlsClientHandshakeError> {
+        self.crypto_component
+            .tls_client_config(server, registry_version)
+    }
 }
 
 impl<C: CryptoServiceProvider, T: Signable> BasicSigVerifier<T> for TempCryptoComponentGeneric<C> {
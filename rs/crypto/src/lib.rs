@@ -24,7 +24,7 @@ pub use sign::utils::{
     threshold_sig_public_key_to_der, user_public_key_from_bytes, verify_combined_threshold_sig,
     KeyBytesContentType,
 };
-pub use sign::{derive_tecdsa_public_key, get_tecdsa_master_public_key};
+pub use sign::{derive_tbip340_public_key, derive_tecdsa_public_key, get_tecdsa_master_public_key};
 
 use crate::common::utils::{derive_node_id, TempCryptoComponent};
 use crate::sign::ThresholdSigDataStoreImpl;
@@ -37,7 +37,7 @@ use ic_crypto_internal_csp::{CryptoServiceProvider, Csp};
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_tls_interfaces::TlsHandshake;
 use ic_interfaces::crypto::{
-    BasicSigVerifier, BasicSigVerifierByPublicKey, KeyManager, MultiSigVerifier,
+    BasicSigVerifier, BasicSigVerifierByPublicKey, BasicSigner, KeyManager, MultiSigVerifier,
     ThresholdSigVerifier, ThresholdSigVerifierByPublicKey,
 };
 use ic_interfaces::registry::RegistryClient;
@@ -82,6 +82,7 @@ pub type CryptoComponent =
 /// modify the secret key store.
 pub trait CryptoComponentForNonReplicaProcess:
     KeyManager
+    + BasicSigner<MessageId>
     + ThresholdSigVerifierByPublicKey<CatchUpContentProtobufBytes>
     + TlsHandshake
     + Send
@@ -93,6 +94,7 @@ pub trait CryptoComponentForNonReplicaProcess:
 // that fulfill the requirements.
 impl<T> CryptoComponentForNonReplicaProcess for T where
     T: KeyManager
+        + BasicSigner<MessageId>
         + ThresholdSigVerifierByPublicKey<CatchUpContentProtobufBytes>
         + TlsHandshake
         + Send
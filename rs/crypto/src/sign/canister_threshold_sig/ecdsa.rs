@@ -1,7 +1,8 @@
 //! Implementations of ThresholdEcdsaSigner
 use ic_crypto_internal_csp::api::{CspThresholdEcdsaSigVerifier, CspThresholdEcdsaSigner};
 use ic_crypto_internal_threshold_sig_ecdsa::{
-    IDkgTranscriptInternal, ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaDerivePublicKeyError,
+    IDkgTranscriptInternal, ThresholdBip340CombinedSigInternal, ThresholdBip340SigShareInternal,
+    ThresholdEcdsaCombinedSigInternal, ThresholdEcdsaDerivePublicKeyError,
     ThresholdEcdsaSigShareInternal,
 };
 use ic_types::crypto::canister_threshold_sig::error::{
@@ -13,7 +14,7 @@ use ic_types::crypto::canister_threshold_sig::idkg::IDkgTranscriptType::{Masked,
 use ic_types::crypto::canister_threshold_sig::idkg::{IDkgReceivers, IDkgTranscript};
 use ic_types::crypto::canister_threshold_sig::{
     EcdsaPublicKey, ThresholdEcdsaCombinedSignature, ThresholdEcdsaSigInputs,
-    ThresholdEcdsaSigShare,
+    ThresholdEcdsaSigShare, ThresholdSignatureScheme,
 };
 use ic_types::crypto::canister_threshold_sig::{ExtendedDerivationPath, MasterEcdsaPublicKey};
 use ic_types::crypto::AlgorithmId;
@@ -29,6 +30,10 @@ pub fn sign_share<C: CspThresholdEcdsaSigner>(
 ) -> Result<ThresholdEcdsaSigShare, ThresholdEcdsaSignShareError> {
     ensure_self_was_receiver(self_node_id, inputs.receivers().get())?;
 
+    if inputs.scheme() == ThresholdSignatureScheme::Bip340 {
+        return bip340_sign_share(csp_client, inputs);
+    }
+
     let kappa_unmasked =
         internal_transcript_from_transcript(inputs.presig_quadruple().kappa_unmasked())?;
     let lambda_masked =
@@ -66,25 +71,17 @@ pub fn verify_sig_share<C: CspThresholdEcdsaSigVerifier>(
     inputs: &ThresholdEcdsaSigInputs,
     share: &ThresholdEcdsaSigShare,
 ) -> Result<(), ThresholdEcdsaVerifySigShareError> {
-    fn internal_transcript_from_transcript(
-        transcript: &IDkgTranscript,
-    ) -> Result<IDkgTranscriptInternal, ThresholdEcdsaVerifySigShareError> {
-        IDkgTranscriptInternal::deserialize(&transcript.internal_transcript_raw).map_err(|e| {
-            ThresholdEcdsaVerifySigShareError::SerializationError {
-                internal_error: format!("{:?}", e),
-            }
-        })
+    if inputs.scheme() == ThresholdSignatureScheme::Bip340 {
+        return bip340_verify_sig_share(csp_client, signer, inputs, share);
     }
 
-    let kappa_unmasked =
-        internal_transcript_from_transcript(inputs.presig_quadruple().kappa_unmasked())?;
-    let lambda_masked =
-        internal_transcript_from_transcript(inputs.presig_quadruple().lambda_masked())?;
+    let kappa_unmasked = verify_sig_share_transcript(inputs.presig_quadruple().kappa_unmasked())?;
+    let lambda_masked = verify_sig_share_transcript(inputs.presig_quadruple().lambda_masked())?;
     let kappa_times_lambda =
-        internal_transcript_from_transcript(inputs.presig_quadruple().kappa_times_lambda())?;
+        verify_sig_share_transcript(inputs.presig_quadruple().kappa_times_lambda())?;
     let key_times_lambda =
-        internal_transcript_from_transcript(inputs.presig_quadruple().key_times_lambda())?;
-    let key = internal_transcript_from_transcript(inputs.key_transcript())?;
+        verify_sig_share_transcript(inputs.presig_quadruple().key_times_lambda())?;
+    let key = verify_sig_share_transcript(inputs.key_transcript())?;
 
     let sig_share =
         ThresholdEcdsaSigShareInternal::deserialize(&share.sig_share_raw).map_err(|e| {
@@ -118,19 +115,13 @@ pub fn verify_combined_signature<C: CspThresholdEcdsaSigVerifier>(
     inputs: &ThresholdEcdsaSigInputs,
     signature: &ThresholdEcdsaCombinedSignature,
 ) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError> {
-    fn internal_transcript_from_transcript(
-        transcript: &IDkgTranscript,
-    ) -> Result<IDkgTranscriptInternal, ThresholdEcdsaVerifyCombinedSignatureError> {
-        IDkgTranscriptInternal::deserialize(&transcript.internal_transcript_raw).map_err(|e| {
-            ThresholdEcdsaVerifyCombinedSignatureError::SerializationError {
-                internal_error: format!("{:?}", e),
-            }
-        })
+    if inputs.scheme() == ThresholdSignatureScheme::Bip340 {
+        return bip340_verify_combined_signature(csp_client, inputs, signature);
     }
 
     let kappa_unmasked =
-        internal_transcript_from_transcript(inputs.presig_quadruple().kappa_unmasked())?;
-    let key = internal_transcript_from_transcript(inputs.key_transcript())?;
+        verify_combined_signature_transcript(inputs.presig_quadruple().kappa_unmasked())?;
+    let key = verify_combined_signature_transcript(inputs.key_transcript())?;
 
     let signature =
         ThresholdEcdsaCombinedSigInternal::deserialize(inputs.algorithm_id(), &signature.signature)
@@ -158,6 +149,10 @@ pub fn combine_sig_shares<C: CspThresholdEcdsaSigVerifier>(
 ) -> Result<ThresholdEcdsaCombinedSignature, ThresholdEcdsaCombineSigSharesError> {
     ensure_sufficient_sig_shares_collected(inputs, shares)?;
 
+    if inputs.scheme() == ThresholdSignatureScheme::Bip340 {
+        return bip340_combine_sig_shares(csp_client, inputs, shares);
+    }
+
     let kappa_unmasked = IDkgTranscriptInternal::deserialize(
         &inputs
             .presig_quadruple()
@@ -194,6 +189,140 @@ pub fn combine_sig_shares<C: CspThresholdEcdsaSigVerifier>(
     })
 }
 
+fn bip340_sign_share<C: CspThresholdEcdsaSigner>(
+    csp_client: &C,
+    inputs: &ThresholdEcdsaSigInputs,
+) -> Result<ThresholdEcdsaSigShare, ThresholdEcdsaSignShareError> {
+    let kappa_unmasked =
+        internal_transcript_from_transcript(inputs.presig_quadruple().kappa_unmasked())?;
+    let key = internal_transcript_from_transcript(inputs.key_transcript())?;
+
+    let internal_sig_share = csp_client.bip340_sign_share(
+        inputs.derivation_path(),
+        inputs.hashed_message(),
+        inputs.nonce(),
+        &key,
+        &kappa_unmasked,
+    )?;
+
+    let sig_share_raw = internal_sig_share.serialize().map_err(|e| {
+        ThresholdEcdsaSignShareError::SerializationError {
+            internal_error: format!("{:?}", e),
+        }
+    })?;
+
+    Ok(ThresholdEcdsaSigShare { sig_share_raw })
+}
+
+fn bip340_verify_sig_share<C: CspThresholdEcdsaSigVerifier>(
+    csp_client: &C,
+    signer: NodeId,
+    inputs: &ThresholdEcdsaSigInputs,
+    share: &ThresholdEcdsaSigShare,
+) -> Result<(), ThresholdEcdsaVerifySigShareError> {
+    let kappa_unmasked = verify_sig_share_transcript(inputs.presig_quadruple().kappa_unmasked())?;
+    let key = verify_sig_share_transcript(inputs.key_transcript())?;
+
+    let sig_share =
+        ThresholdBip340SigShareInternal::deserialize(&share.sig_share_raw).map_err(|e| {
+            ThresholdEcdsaVerifySigShareError::SerializationError {
+                internal_error: format!("{:?}", e),
+            }
+        })?;
+    let signer_index = inputs.key_transcript().index_for_signer_id(signer).ok_or(
+        ThresholdEcdsaVerifySigShareError::InvalidArgumentMissingSignerInTranscript {
+            signer_id: signer,
+        },
+    )?;
+
+    csp_client.bip340_verify_sig_share(
+        &sig_share,
+        signer_index,
+        inputs.derivation_path(),
+        inputs.hashed_message(),
+        inputs.nonce(),
+        &key,
+        &kappa_unmasked,
+    )
+}
+
+fn bip340_combine_sig_shares<C: CspThresholdEcdsaSigVerifier>(
+    csp_client: &C,
+    inputs: &ThresholdEcdsaSigInputs,
+    shares: &BTreeMap<NodeId, ThresholdEcdsaSigShare>,
+) -> Result<ThresholdEcdsaCombinedSignature, ThresholdEcdsaCombineSigSharesError> {
+    let transcript_from_raw = |transcript: &IDkgTranscript| {
+        IDkgTranscriptInternal::deserialize(&transcript.internal_transcript_raw).map_err(|e| {
+            ThresholdEcdsaCombineSigSharesError::SerializationError {
+                internal_error: format!("{:?}", e),
+            }
+        })
+    };
+    let kappa_unmasked = transcript_from_raw(inputs.presig_quadruple().kappa_unmasked())?;
+    let key = transcript_from_raw(inputs.key_transcript())?;
+
+    let internal_shares = shares
+        .iter()
+        .map(|(&id, share)| {
+            let index = inputs
+                .receivers()
+                .position(id)
+                .ok_or(ThresholdEcdsaCombineSigSharesError::SignerNotAllowed { node_id: id })?;
+            let internal_share = ThresholdBip340SigShareInternal::deserialize(&share.sig_share_raw)
+                .map_err(
+                    |e| ThresholdEcdsaCombineSigSharesError::SerializationError {
+                        internal_error: format!("{:?}", e),
+                    },
+                )?;
+            Ok((index, internal_share))
+        })
+        .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+    let internal_combined_sig = csp_client.bip340_combine_sig_shares(
+        inputs.derivation_path(),
+        inputs.hashed_message(),
+        inputs.nonce(),
+        &key,
+        &kappa_unmasked,
+        inputs.reconstruction_threshold(),
+        &internal_shares,
+    )?;
+
+    let signature = internal_combined_sig.serialize().map_err(|e| {
+        ThresholdEcdsaCombineSigSharesError::SerializationError {
+            internal_error: format!("{:?}", e),
+        }
+    })?;
+
+    Ok(ThresholdEcdsaCombinedSignature { signature })
+}
+
+fn bip340_verify_combined_signature<C: CspThresholdEcdsaSigVerifier>(
+    csp_client: &C,
+    inputs: &ThresholdEcdsaSigInputs,
+    signature: &ThresholdEcdsaCombinedSignature,
+) -> Result<(), ThresholdEcdsaVerifyCombinedSignatureError> {
+    let kappa_unmasked =
+        verify_combined_signature_transcript(inputs.presig_quadruple().kappa_unmasked())?;
+    let key = verify_combined_signature_transcript(inputs.key_transcript())?;
+
+    let signature =
+        ThresholdBip340CombinedSigInternal::deserialize(&signature.signature).map_err(|e| {
+            ThresholdEcdsaVerifyCombinedSignatureError::SerializationError {
+                internal_error: format!("{:?}", e),
+            }
+        })?;
+
+    csp_client.bip340_verify_combined_signature(
+        &signature,
+        inputs.derivation_path(),
+        inputs.hashed_message(),
+        inputs.nonce(),
+        &key,
+        &kappa_unmasked,
+    )
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum MasterPublicKeyExtractionError {
     UnsupportedAlgorithm(String),
@@ -251,6 +380,30 @@ pub fn derive_tecdsa_public_key(
     })
 }
 
+/// Derives the x-only BIP340 Schnorr public key from the specified
+/// `master_public_key` for the given `extended_derivation_path`.
+///
+/// The derivation follows [`derive_tecdsa_public_key`], but is domain
+/// separated, so the returned key is unrelated to the ECDSA key derived for
+/// the same path.
+pub fn derive_tbip340_public_key(
+    master_public_key: &MasterEcdsaPublicKey,
+    extended_derivation_path: &ExtendedDerivationPath,
+) -> Result<EcdsaPublicKey, ThresholdEcdsaGetPublicKeyError> {
+    ic_crypto_internal_threshold_sig_ecdsa::derive_bip340_public_key(
+        master_public_key,
+        &extended_derivation_path.into(),
+    )
+    .map_err(|e| match e {
+        ThresholdEcdsaDerivePublicKeyError::InvalidArgument(s) => {
+            ThresholdEcdsaGetPublicKeyError::InvalidArgument(s)
+        }
+        ThresholdEcdsaDerivePublicKeyError::InternalError(e) => {
+            ThresholdEcdsaGetPublicKeyError::InternalError(format!("{:?}", e))
+        }
+    })
+}
+
 fn ensure_self_was_receiver(
     self_node_id: &NodeId,
     receivers: &BTreeSet<NodeId>,
@@ -272,6 +425,26 @@ fn internal_transcript_from_transcript(
     })
 }
 
+fn verify_sig_share_transcript(
+    transcript: &IDkgTranscript,
+) -> Result<IDkgTranscriptInternal, ThresholdEcdsaVerifySigShareError> {
+    IDkgTranscriptInternal::deserialize(&transcript.internal_transcript_raw).map_err(|e| {
+        ThresholdEcdsaVerifySigShareError::SerializationError {
+            internal_error: format!("{:?}", e),
+        }
+    })
+}
+
+fn verify_combined_signature_transcript(
+    transcript: &IDkgTranscript,
+) -> Result<IDkgTranscriptInternal, ThresholdEcdsaVerifyCombinedSignatureError> {
+    IDkgTranscriptInternal::deserialize(&transcript.internal_transcript_raw).map_err(|e| {
+        ThresholdEcdsaVerifyCombinedSignatureError::SerializationError {
+            internal_error: format!("{:?}", e),
+        }
+    })
+}
+
 fn ensure_sufficient_sig_shares_collected(
     inputs: &ThresholdEcdsaSigInputs,
     shares: &BTreeMap<NodeId, ThresholdEcdsaSigShare>,
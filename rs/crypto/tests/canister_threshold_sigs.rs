@@ -1,6 +1,8 @@
 use ic_base_types::PrincipalId;
 use ic_crypto::utils::TempCryptoComponent;
-use ic_crypto::{derive_tecdsa_public_key, get_tecdsa_master_public_key};
+use ic_crypto::{
+    derive_tbip340_public_key, derive_tecdsa_public_key, get_tecdsa_master_public_key,
+};
 use ic_crypto_internal_threshold_sig_ecdsa::{
     test_utils::corrupt_dealing_for_all_recipients, EccScalar, IDkgDealingInternal, MEGaCiphertext,
 };
@@ -31,7 +33,7 @@ use ic_types::crypto::canister_threshold_sig::idkg::{
 };
 use ic_types::crypto::canister_threshold_sig::{
     ExtendedDerivationPath, PreSignatureQuadruple, ThresholdEcdsaCombinedSignature,
-    ThresholdEcdsaSigInputs,
+    ThresholdEcdsaSigInputs, ThresholdSignatureScheme,
 };
 use ic_types::crypto::{AlgorithmId, CombinedMultiSig, CombinedMultiSigOf, CryptoError};
 use ic_types::{Height, NodeId, NodeIndex, Randomness, RegistryVersion};
@@ -1212,6 +1214,92 @@ fn should_verify_combined_sig_successfully() {
     );
 }
 
+#[test]
+fn should_create_and_verify_bip340_signature_successfully() {
+    use ic_crypto_internal_threshold_sig_ecdsa::bip340::verify_bip340_signature;
+    let mut rng = thread_rng();
+
+    let subnet_size = rng.gen_range(1, 10);
+    let env = CanisterThresholdSigTestEnvironment::new(subnet_size);
+
+    let key_transcript = generate_key_transcript(&env, AlgorithmId::ThresholdEcdsaSecp256k1);
+    let quadruple =
+        generate_presig_quadruple(&env, AlgorithmId::ThresholdEcdsaSecp256k1, &key_transcript);
+
+    let master_public_key =
+        get_tecdsa_master_public_key(&key_transcript).expect("Master key extraction failed");
+    let derivation_path = ExtendedDerivationPath {
+        caller: PrincipalId::new_user_test_id(1),
+        derivation_path: vec![b"taproot".to_vec()],
+    };
+    let message = b"message of arbitrary length".to_vec();
+    let seed = Randomness::from(rng.gen::<[u8; 32]>());
+
+    let inputs = ThresholdEcdsaSigInputs::new_with_scheme(
+        ThresholdSignatureScheme::Bip340,
+        &derivation_path,
+        &message,
+        seed,
+        quadruple,
+        key_transcript,
+    )
+    .expect("failed to create signature inputs");
+
+    let sig_shares: BTreeMap<_, _> = inputs
+        .receivers()
+        .get()
+        .iter()
+        .map(|&signer_id| {
+            load_input_transcripts(&env.crypto_components, signer_id, &inputs);
+
+            let sig_share = crypto_for(signer_id, &env.crypto_components)
+                .sign_share(&inputs)
+                .expect("failed to create sig share");
+            (signer_id, sig_share)
+        })
+        .collect();
+
+    let verifier_id = random_receiver_for_inputs(&inputs);
+    let verifier = crypto_for(verifier_id, &env.crypto_components);
+    for (signer_id, sig_share) in sig_shares.iter() {
+        assert!(verifier
+            .verify_sig_share(*signer_id, &inputs, sig_share)
+            .is_ok());
+    }
+
+    let combiner_id = random_receiver_for_inputs(&inputs);
+    let combined_sig = crypto_for(combiner_id, &env.crypto_components)
+        .combine_sig_shares(&inputs, &sig_shares)
+        .expect("failed to combine sig shares");
+    assert_eq!(combined_sig.signature.len(), 64);
+    assert!(verifier.verify_combined_sig(&inputs, &combined_sig).is_ok());
+
+    let public_key = derive_tbip340_public_key(&master_public_key, &derivation_path)
+        .expect("Public key derivation failed");
+    assert_eq!(
+        verify_bip340_signature(&public_key.public_key, &message, &combined_sig.signature),
+        Ok(true),
+        "BIP340 sig verification failed"
+    );
+
+    // An ECDSA signature share is not accepted for the same inputs
+    let ecdsa_inputs = ThresholdEcdsaSigInputs::new(
+        inputs.derivation_path(),
+        &rng.gen::<[u8; 32]>(),
+        seed,
+        inputs.presig_quadruple().clone(),
+        inputs.key_transcript().clone(),
+    )
+    .expect("failed to create signature inputs");
+    let (signer_id, _) = sig_shares.iter().next().expect("no sig shares");
+    let ecdsa_share = crypto_for(*signer_id, &env.crypto_components)
+        .sign_share(&ecdsa_inputs)
+        .expect("failed to create sig share");
+    assert!(verifier
+        .verify_sig_share(*signer_id, &inputs, &ecdsa_share)
+        .is_err());
+}
+
 #[test]
 fn should_return_ecdsa_public_key() {
     let mut rng = thread_rng();
@@ -628,6 +628,48 @@ pub trait TlsHandshake {
         server: NodeId,
         registry_version: RegistryVersion,
     ) -> Result<TlsStream, TlsClientHandshakeError>;
+
+    /// Returns the TLS server configuration used by
+    /// `perform_tls_server_handshake_with_rustls`, for transports such as QUIC
+    /// that perform the TLS handshake themselves.
+    ///
+    /// The configuration verifies during the handshake that the peer is one of
+    /// the nodes in `allowed_clients` as described for
+    /// `perform_tls_server_handshake`. The node ID of the authenticated peer
+    /// is the subject common name of the certificate it presented.
+    ///
+    /// # Errors
+    /// * TlsServerHandshakeError::RegistryError if the registry cannot be
+    ///   accessed.
+    /// * TlsServerHandshakeError::CertificateNotInRegistry if the node's own
+    ///   certificate is not found in the registry.
+    /// * TlsServerHandshakeError::MalformedSelfCertificate if the node's own
+    ///   server certificate is malformed.
+    fn tls_server_config(
+        &self,
+        allowed_clients: AllowedClients,
+        registry_version: RegistryVersion,
+    ) -> Result<tokio_rustls::rustls::ServerConfig, TlsServerHandshakeError>;
+
+    /// Returns the TLS client configuration used by
+    /// `perform_tls_client_handshake_with_rustls`, for transports such as QUIC
+    /// that perform the TLS handshake themselves.
+    ///
+    /// The configuration verifies during the handshake that the peer is the
+    /// `server` as described for `perform_tls_client_handshake`.
+    ///
+    /// # Errors
+    /// * TlsClientHandshakeError::RegistryError if the registry cannot be
+    ///   accessed.
+    /// * TlsClientHandshakeError::CertificateNotInRegistry if the node's own
+    ///   certificate is not found in the registry.
+    /// * TlsClientHandshakeError::MalformedSelfCertificate if the node's own
+    ///   client certificate is malformed.
+    fn tls_client_config(
+        &self,
+        server: NodeId,
+        registry_version: RegistryVersion,
+    ) -> Result<tokio_rustls::rustls::ClientConfig, TlsClientHandshakeError>;
 }
 
 #[derive(Clone, Debug)]
@@ -368,6 +368,8 @@ impl CyclesAccountManager {
                 | Ok(Method::RawRand)
                 | Ok(Method::ECDSAPublicKey)
                 | Ok(Method::SignWithECDSA)
+                | Ok(Method::SchnorrPublicKey)
+                | Ok(Method::SignWithSchnorr)
                 | Ok(Method::ComputeInitialEcdsaDealings)
                 | Ok(Method::BitcoinTestnetGetBalance)
                 | Ok(Method::BitcoinTestnetGetUtxos)
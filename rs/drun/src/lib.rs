@@ -1,10 +1,10 @@
 //! Standalone interface for testing application canisters.
 
-use crate::message::{msg_stream_from_file, Message};
+use crate::message::{line_stream_from_file, parse_message, Assertion, Message, Variables};
 use hex::encode;
 use ic_config::{subnet_config::SubnetConfigs, Config};
 use ic_cycles_account_manager::CyclesAccountManager;
-use ic_error_types::{ErrorCode, UserError};
+use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_execution_environment::ExecutionServices;
 use ic_interfaces::{execution_environment::IngressHistoryReader, messaging::MessageRouting};
 use ic_interfaces_state_manager::StateReader;
@@ -25,6 +25,7 @@ use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::consensus::fake::FakeVerifier;
 use ic_test_utilities_registry::{
@@ -32,10 +33,11 @@ use ic_test_utilities_registry::{
 };
 use ic_types::{
     batch::{Batch, BatchPayload, IngressPayload, SelfValidatingPayload, XNetPayload},
+    ic00::{CanisterIdRecord, Payload},
     ingress::{IngressStatus, WasmResult},
     messages::{MessageId, SignedIngress},
     replica_config::ReplicaConfig,
-    time::UNIX_EPOCH,
+    time::{Time, UNIX_EPOCH},
     CanisterId, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId,
 };
 use slog::{Drain, Logger};
@@ -59,20 +61,23 @@ pub struct DrunOptions {
     pub log_file: Option<PathBuf>,
 }
 
-/// Deliver a single message to the Message Routing layer
+/// Deliver a single message to the Message Routing layer and return its result
 fn deliver_message(
     msg: SignedIngress,
     message_routing: &dyn MessageRouting,
     ingress_hist_reader: &dyn IngressHistoryReader,
     extra_batches: u64,
-) {
+    time: Time,
+) -> Result<WasmResult, UserError> {
     let message_id = msg.id();
 
-    let _ = execute_ingress_message(message_routing, msg, &message_id, ingress_hist_reader);
+    let result =
+        execute_ingress_message(message_routing, msg, &message_id, ingress_hist_reader, time);
     // print result after waiting, to not interleave the result
     // with debug.print messages from subsequent calls. revise after DFN-1269.
-    wait_extra_batches(message_routing, extra_batches);
+    wait_extra_batches(message_routing, extra_batches, time);
     print_ingress_result(&message_id, ingress_hist_reader);
+    result
 }
 
 fn setup_logger(log_file: PathBuf) -> Logger {
@@ -165,7 +170,7 @@ pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
         subnet_id,
     };
 
-    let mut msg_stream = msg_stream_from_file(&msg_filename)?;
+    let line_stream = line_stream_from_file(&msg_filename)?;
     let log = match log_file {
         Some(log_file) => setup_logger(log_file),
         None => slog::Logger::root(slog::Discard, slog::o!()),
@@ -230,46 +235,156 @@ pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
         Arc::clone(&registry) as _,
     );
 
-    msg_stream.try_for_each(|parse_result| {
-        parse_result.map(|msg| match msg {
-            Message::Install(msg) => {
-                deliver_message(
+    let mut vars = Variables::new();
+    let mut time = UNIX_EPOCH;
+    // The result of the last ingress message or query, checked by assertions.
+    let mut last_result = None;
+
+    for line in line_stream {
+        let (i, line) = line?;
+        let msg =
+            parse_message(&line, i as u64, &vars).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        match msg {
+            Message::Install(msg) | Message::Ingress(msg) => {
+                last_result = Some(deliver_message(
                     msg,
                     &message_routing,
                     ingress_hist_reader.as_ref(),
                     extra_batches,
-                );
+                    time,
+                ));
             }
 
             Message::Query(q) => {
                 // NOTE: Data certificates aren't supported in drun yet.
                 // To support them, we'd need to do something similar to
                 // http_handler::get_latest_certified_state_and_data_certificate
-                print_query_result(query_handler.query(
-                    q,
-                    state_manager.get_latest_state().take(),
-                    Vec::new(),
-                ));
+                let result =
+                    query_handler.query(q, state_manager.get_latest_state().take(), Vec::new());
+                print_query_result(result.clone());
+                last_result = Some(result);
             }
 
-            Message::Ingress(msg) => {
-                deliver_message(
+            Message::Create(msg, name) => {
+                let result = deliver_message(
                     msg,
                     &message_routing,
                     ingress_hist_reader.as_ref(),
                     extra_batches,
+                    time,
                 );
+                if let Some(name) = name {
+                    let canister_id = created_canister_id(&result)
+                        .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+                    vars.insert(name, canister_id);
+                }
+                last_result = Some(result);
             }
-            Message::Create(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
+
+            Message::Assert(assertion) => check_assertion(
+                &assertion,
+                last_result.as_ref(),
+                &state_manager.get_latest_state().take(),
+            )
+            .map_err(|e| format!("Line {}: Assertion failed: {}", i + 1, e))?,
+
+            Message::AdvanceTime(duration) => time += duration,
+
+            Message::Heartbeat(rounds) => wait_extra_batches(&message_routing, rounds, time),
+        }
+    }
+    Ok(())
+}
+
+/// Returns the ID of the canister created by a `create` message.
+fn created_canister_id(result: &Result<WasmResult, UserError>) -> Result<CanisterId, String> {
+    match result {
+        Ok(WasmResult::Reply(bytes)) => CanisterIdRecord::decode(bytes)
+            .map(|record| record.get_canister_id())
+            .map_err(|e| format!("Failed to decode the created canister id: {}", e)),
+        Ok(WasmResult::Reject(e)) => Err(format!("Canister creation was rejected: {}", e)),
+        Err(e) => Err(format!("Canister creation failed: {}", e)),
+    }
+}
+
+/// Checks `assertion` against the result of the last message and the latest
+/// state.
+fn check_assertion(
+    assertion: &Assertion,
+    last_result: Option<&Result<WasmResult, UserError>>,
+    state: &ReplicatedState,
+) -> Result<(), String> {
+    match assertion {
+        Assertion::Reply(expected) => match last_result {
+            Some(Ok(WasmResult::Reply(actual))) if actual == expected => Ok(()),
+            Some(Ok(WasmResult::Reply(actual))) => Err(format!(
+                "expected reply 0x{}, got reply 0x{}",
+                encode(expected),
+                encode(actual)
+            )),
+            Some(result) => Err(format!(
+                "expected reply 0x{}, got {}",
+                encode(expected),
+                describe_result(result)
+            )),
+            None => Err("no message has been executed yet".to_string()),
+        },
+        Assertion::Reject(expected) => {
+            let actual = match last_result {
+                Some(Ok(WasmResult::Reject(_))) => Some(RejectCode::CanisterReject),
+                Some(Err(e)) => Some(e.reject_code()),
+                _ => None,
+            };
+            match (actual, last_result) {
+                (Some(actual), _) if actual == *expected => Ok(()),
+                (_, Some(result)) => Err(format!(
+                    "expected reject code {} ({:?}), got {}",
+                    *expected as u64,
+                    expected,
+                    describe_result(result)
+                )),
+                (_, None) => Err("no message has been executed yet".to_string()),
             }
-        })
-    })
+        }
+        Assertion::Balance {
+            canister_id,
+            min,
+            max,
+        } => {
+            let balance = state
+                .canister_state(canister_id)
+                .ok_or_else(|| format!("canister {} does not exist", canister_id))?
+                .system_state
+                .balance()
+                .get();
+            if (*min..=*max).contains(&balance) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "expected the balance of canister {} to be in {}..{}, got {}",
+                    canister_id, min, max, balance
+                ))
+            }
+        }
+    }
+}
+
+fn describe_result(result: &Result<WasmResult, UserError>) -> String {
+    match result {
+        Ok(WasmResult::Reply(v)) => format!("reply 0x{}", encode(v)),
+        Ok(WasmResult::Reject(e)) => format!(
+            "reject code {} ({:?}): {}",
+            RejectCode::CanisterReject as u64,
+            RejectCode::CanisterReject,
+            e
+        ),
+        Err(e) => format!(
+            "reject code {} ({:?}): {}",
+            e.reject_code() as u64,
+            e.reject_code(),
+            e
+        ),
+    }
 }
 
 fn print_query_result(res: Result<WasmResult, UserError>) {
@@ -302,7 +417,11 @@ fn print_wasm_result(wasm_result: WasmResult) {
     }
 }
 
-fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -> Batch {
+fn build_batch(
+    message_routing: &dyn MessageRouting,
+    msgs: Vec<SignedIngress>,
+    time: Time,
+) -> Batch {
     Batch {
         batch_number: message_routing.expected_batch_height(),
         requires_full_state_hash: !msgs.is_empty(),
@@ -316,7 +435,7 @@ fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -
         randomness: Randomness::from([0; 32]),
         ecdsa_subnet_public_key: None,
         registry_version: RegistryVersion::from(1),
-        time: UNIX_EPOCH,
+        time,
         consensus_responses: vec![],
     }
 }
@@ -329,8 +448,9 @@ fn execute_ingress_message(
     msg: SignedIngress,
     msg_id: &MessageId,
     ingress_history: &dyn IngressHistoryReader,
+    time: Time,
 ) -> Result<WasmResult, UserError> {
-    let mut batch = build_batch(message_routing, vec![msg]);
+    let mut batch = build_batch(message_routing, vec![msg], time);
     for _ in 0..MAX_BATCHES_UNTIL_RESPONSE {
         // In the first batch we try to send the ingress message itself. If it fails, we
         // repeat with the same batch.
@@ -341,7 +461,7 @@ fn execute_ingress_message(
         // potential inter-canister messages that the ingress message may have
         // triggered.
         if message_routing.deliver_batch(batch.clone()).is_ok() {
-            batch = build_batch(message_routing, vec![])
+            batch = build_batch(message_routing, vec![], time)
         }
         sleep(WAIT_PER_BATCH);
 
@@ -386,10 +506,10 @@ fn execute_ingress_message(
 ///
 /// This is a temporary measure until DFN-1269 is resolved. In that ticket, we
 /// will actually try to wait until all messages have been executed.
-fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64) {
+fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64, time: Time) {
     for _ in 0..extra_batches {
         loop {
-            let batch = build_batch(message_routing, vec![]);
+            let batch = build_batch(message_routing, vec![], time);
             let ok = message_routing.deliver_batch(batch).is_ok();
             sleep(WAIT_PER_BATCH);
             if ok {
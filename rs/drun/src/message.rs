@@ -1,6 +1,7 @@
 use super::CanisterId;
 
 use hex::decode;
+use ic_error_types::RejectCode;
 use ic_types::{
     ic00,
     ic00::Payload,
@@ -10,20 +11,43 @@ use ic_types::{
 };
 
 use std::{
+    collections::HashMap,
     convert::TryFrom,
     fmt,
     fs::File,
     io::{self, Read},
     str::Chars,
     string::FromUtf8Error,
+    time::Duration,
 };
 
+/// Canister IDs bound to the names given in `create $name` lines.
+pub(crate) type Variables = HashMap<String, CanisterId>;
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum Message {
     Ingress(SignedIngress),
     Query(UserQuery),
     Install(SignedIngress),
-    Create(SignedIngress),
+    /// Creates a canister and optionally binds its ID to the given name.
+    Create(SignedIngress, Option<String>),
+    Assert(Assertion),
+    AdvanceTime(Duration),
+    /// Executes the given number of rounds without new messages, which runs
+    /// the heartbeats of the canisters.
+    Heartbeat(u64),
+}
+
+/// Expectation about the outcome of the previous message or about the state.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Assertion {
+    Reply(Vec<u8>),
+    Reject(RejectCode),
+    Balance {
+        canister_id: CanisterId,
+        min: u128,
+        max: u128,
+    },
 }
 
 #[derive(Debug)]
@@ -124,9 +148,13 @@ impl<R: Read> Iterator for LineIterator<R> {
     }
 }
 
-pub(crate) fn msg_stream_from_file(
+/// Returns the non-empty, non-comment lines of `filename` along with their
+/// zero-based indices. The lines are parsed with [`parse_message`] only when the
+/// preceding messages have been executed, so that they can refer to the
+/// canisters created by them.
+pub(crate) fn line_stream_from_file(
     filename: &str,
-) -> Result<impl Iterator<Item = Result<Message, String>>, String> {
+) -> Result<impl Iterator<Item = Result<(usize, String), String>>, String> {
     let f = File::open(filename).map_err(|e| e.to_string())?;
     let line_iterator = LineIterator::new(f);
 
@@ -138,14 +166,12 @@ pub(crate) fn msg_stream_from_file(
             _ => true,
         })
         .map(|(i, line)| match line {
-            Ok(line) => {
-                parse_message(&line, i as u64).map_err(|e| format!("Line {}: {}", i + 1, e))
-            }
+            Ok(line) => Ok((i, line)),
             Err(e) => Err(format!("Error while reading line {}: {}", i, e)),
         }))
 }
 
-fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
+pub(crate) fn parse_message(s: &str, nonce: u64, vars: &Variables) -> Result<Message, String> {
     let s = s.trim_end();
     let tokens: Vec<&str> = s.splitn(4, char::is_whitespace).collect();
 
@@ -154,7 +180,7 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
         ["ingress", canister_id, method_name, payload] => {
             use ic_test_utilities::types::messages::SignedIngressBuilder;
 
-            let canister_id = parse_canister_id(canister_id)?;
+            let canister_id = parse_canister_id(canister_id, vars)?;
             let method_name = validate_method_name(method_name)?;
             let method_payload = parse_octet_string(payload)?;
 
@@ -170,22 +196,46 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
         }
         ["query", canister_id, method_name, payload] => Ok(Message::Query(UserQuery {
             source: UserId::from(PrincipalId::new_anonymous()),
-            receiver: parse_canister_id(canister_id)?,
+            receiver: parse_canister_id(canister_id, vars)?,
             method_name: validate_method_name(method_name)?,
             method_payload: parse_octet_string(payload)?,
             ingress_expiry: current_time_and_expiry_time().1.as_nanos_since_unix_epoch(),
             nonce: Some(nonce.to_le_bytes().to_vec()),
         })),
-        ["create"] => parse_create(nonce),
+        ["create"] => parse_create(nonce, None),
+        ["create", name] => parse_create(nonce, Some(parse_variable_name(name)?)),
         ["install", canister_id, wasm_file, payload] => {
-            parse_install(nonce, canister_id, payload, wasm_file, "install")
+            parse_install(nonce, canister_id, payload, wasm_file, "install", vars)
         }
         ["reinstall", canister_id, wasm_file, payload] => {
-            parse_install(nonce, canister_id, payload, wasm_file, "reinstall")
+            parse_install(nonce, canister_id, payload, wasm_file, "reinstall", vars)
         }
         ["upgrade", canister_id, wasm_file, payload] => {
-            parse_install(nonce, canister_id, payload, wasm_file, "upgrade")
+            parse_install(nonce, canister_id, payload, wasm_file, "upgrade", vars)
+        }
+        ["assert", "reply", ..] => {
+            // The expected reply may be a quoted string containing whitespace.
+            let payload = s.splitn(3, char::is_whitespace).nth(2).unwrap_or_default();
+            Ok(Message::Assert(Assertion::Reply(parse_octet_string(
+                payload,
+            )?)))
         }
+        ["assert", "reject", code] => {
+            Ok(Message::Assert(Assertion::Reject(parse_reject_code(code)?)))
+        }
+        ["assert", "balance", canister_id, range] => {
+            let (min, max) = parse_cycles_range(range)?;
+            Ok(Message::Assert(Assertion::Balance {
+                canister_id: parse_canister_id(canister_id, vars)?,
+                min,
+                max,
+            }))
+        }
+        ["advance_time", seconds] => Ok(Message::AdvanceTime(Duration::from_secs(parse_number(
+            seconds,
+        )?))),
+        ["heartbeat"] => Ok(Message::Heartbeat(1)),
+        ["heartbeat", rounds] => Ok(Message::Heartbeat(parse_number(rounds)?)),
         _ => Err(format!(
             "Failed to parse line {}, don't have a pattern to match this with",
             s
@@ -193,8 +243,22 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
     }
 }
 
-fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
+fn parse_variable_name(name: &str) -> Result<String, String> {
+    match name.strip_prefix('$') {
+        Some(ident) if validate_method_name(ident).is_ok() => Ok(ident.to_string()),
+        _ => Err(format!("Illegal variable name: {}.", name)),
+    }
+}
+
+fn parse_canister_id(canister_id: &str, vars: &Variables) -> Result<CanisterId, String> {
     use std::str::FromStr;
+    if canister_id.starts_with('$') {
+        let name = parse_variable_name(canister_id)?;
+        return vars
+            .get(&name)
+            .copied()
+            .ok_or_else(|| format!("Undefined variable {}.", canister_id));
+    }
     match PrincipalId::from_str(canister_id) {
         Ok(id) => match CanisterId::new(id) {
             Ok(id) => Ok(id),
@@ -210,7 +274,43 @@ fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
     }
 }
 
-fn parse_create(nonce: u64) -> Result<Message, String> {
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    s.replace('_', "")
+        .parse()
+        .map_err(|e| format!("Failed to parse number {}: {}", s, e))
+}
+
+fn parse_reject_code(code: &str) -> Result<RejectCode, String> {
+    RejectCode::try_from(parse_number::<u64>(code)?)
+        .map_err(|_| format!("Illegal reject code: {}.", code))
+}
+
+/// Parses an inclusive range of cycles in the form `<min>..<max>`, where
+/// either bound may be omitted.
+fn parse_cycles_range(range: &str) -> Result<(u128, u128), String> {
+    let (min, max) = range
+        .split_once("..")
+        .ok_or_else(|| format!("Illegal cycles range {}, expected <min>..<max>.", range))?;
+    let min = if min.is_empty() {
+        0
+    } else {
+        parse_number(min)?
+    };
+    let max = if max.is_empty() {
+        u128::MAX
+    } else {
+        parse_number(max)?
+    };
+    if min > max {
+        return Err(format!("Empty cycles range {}.", range));
+    }
+    Ok((min, max))
+}
+
+fn parse_create(nonce: u64, name: Option<String>) -> Result<Message, String> {
     use ic_test_utilities::types::messages::SignedIngressBuilder;
 
     let signed_ingress = SignedIngressBuilder::new()
@@ -220,7 +320,7 @@ fn parse_create(nonce: u64) -> Result<Message, String> {
         .nonce(nonce)
         .build();
 
-    Ok(Message::Create(signed_ingress))
+    Ok(Message::Create(signed_ingress, name))
 }
 
 fn parse_install(
@@ -229,6 +329,7 @@ fn parse_install(
     payload: &str,
     wasm_file: &str,
     mode: &str,
+    vars: &Variables,
 ) -> Result<Message, String> {
     use ic_test_utilities::types::messages::SignedIngressBuilder;
 
@@ -239,7 +340,7 @@ fn parse_install(
         .read_to_end(&mut wasm_data)
         .map_err(|e| e.to_string())?;
 
-    let canister_id = parse_canister_id(canister_id)?;
+    let canister_id = parse_canister_id(canister_id, vars)?;
     let payload = parse_octet_string(payload)?;
 
     let signed_ingress = SignedIngressBuilder::new()
@@ -384,7 +485,7 @@ mod tests {
             "ingress {} write \"payload \\x0a\\b00010001\"",
             APP_CANISTER_URL
         );
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, &Variables::new()).unwrap();
         let expiry_time = match &parsed_message {
             Message::Ingress(signed_ingress) => signed_ingress.expiry_time(),
             _ => panic!(
@@ -407,7 +508,7 @@ mod tests {
     #[test]
     fn test_parse_message_hex_payload_succeeds() {
         let s = &format!("ingress {} write 0x010203", APP_CANISTER_URL);
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, &Variables::new()).unwrap();
         let expiry_time = match &parsed_message {
             Message::Ingress(signed_ingress) => signed_ingress.expiry_time(),
             _ => panic!(
@@ -428,7 +529,7 @@ mod tests {
 
         let s = &format!("query {} read 0x010203", APP_CANISTER_URL);
         let nonce: u64 = 0;
-        let parsed_message = parse_message(s, 0).unwrap();
+        let parsed_message = parse_message(s, 0, &Variables::new()).unwrap();
         let ingress_expiry = match &parsed_message {
             Message::Query(query) => query.ingress_expiry,
             _ => panic!(
@@ -450,25 +551,93 @@ mod tests {
     #[test]
     fn test_parse_message_invalid_escapes_fails() {
         let s = &format!("query {} read \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, &Variables::new()).is_err());
 
         let s = &format!("query {} read \"\\b01\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, &Variables::new()).is_err());
 
         let s = &format!("query {} read \"\\x1\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, &Variables::new()).is_err());
 
         let s = &format!("query {} read \"\\b2\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, &Variables::new()).is_err());
     }
 
     #[test]
     fn test_illegal_method_name_must_fail() {
         let s = &format!("query {} 0read \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, &Variables::new()).is_err());
 
         let s = &format!("query {} üread \"\\xzz\"", APP_CANISTER_URL);
-        assert!(parse_message(s, 0).is_err());
+        assert!(parse_message(s, 0, &Variables::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_resolves_variables() {
+        let mut vars = Variables::new();
+        assert!(parse_message("query $counter read 0x00", 0, &vars).is_err());
+        assert!(parse_message("create counter", 0, &vars).is_err());
+
+        match parse_message("create $counter", 0, &vars).unwrap() {
+            Message::Create(_, name) => assert_eq!(name, Some("counter".to_string())),
+            msg => panic!("parse_message() returned an unexpected message: {:?}", msg),
+        }
+
+        vars.insert("counter".to_string(), canister_test_id(APP_CANISTER_ID));
+        match parse_message("query $counter read 0x00", 0, &vars).unwrap() {
+            Message::Query(query) => {
+                assert_eq!(query.receiver, canister_test_id(APP_CANISTER_ID))
+            }
+            msg => panic!("parse_message() returned an unexpected message: {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn test_parse_assertions() {
+        let vars = Variables::new();
+        assert_eq!(
+            parse_message("assert reply \"hello world\"", 0, &vars).unwrap(),
+            Message::Assert(Assertion::Reply(b"hello world".to_vec()))
+        );
+        assert_eq!(
+            parse_message("assert reject 4", 0, &vars).unwrap(),
+            Message::Assert(Assertion::Reject(RejectCode::CanisterReject))
+        );
+        assert!(parse_message("assert reject 9", 0, &vars).is_err());
+        assert_eq!(
+            parse_message(
+                &format!("assert balance {} 1_000..2_000", APP_CANISTER_URL),
+                0,
+                &vars
+            )
+            .unwrap(),
+            Message::Assert(Assertion::Balance {
+                canister_id: canister_test_id(APP_CANISTER_ID),
+                min: 1_000,
+                max: 2_000,
+            })
+        );
+        assert_eq!(parse_cycles_range("..5").unwrap(), (0, 5));
+        assert_eq!(parse_cycles_range("5..").unwrap(), (5, u128::MAX));
+        assert!(parse_cycles_range("5..1").is_err());
+        assert!(parse_cycles_range("5").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_and_heartbeat() {
+        let vars = Variables::new();
+        assert_eq!(
+            parse_message("advance_time 60", 0, &vars).unwrap(),
+            Message::AdvanceTime(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_message("heartbeat", 0, &vars).unwrap(),
+            Message::Heartbeat(1)
+        );
+        assert_eq!(
+            parse_message("heartbeat 3", 0, &vars).unwrap(),
+            Message::Heartbeat(3)
+        );
     }
 
     #[test]
@@ -8,7 +8,7 @@ use crate::{
 use candid::Encode;
 use ic_base_types::PrincipalId;
 use ic_config::execution_environment::Config as ExecutionConfig;
-use ic_crypto::derive_tecdsa_public_key;
+use ic_crypto::{derive_tbip340_public_key, derive_tecdsa_public_key};
 use ic_cycles_account_manager::{
     CyclesAccountManager, IngressInductionCost, IngressInductionCostError,
 };
@@ -18,7 +18,8 @@ use ic_ic00_types::{
     ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
     ECDSAPublicKeyResponse, EmptyBlob, InstallCodeArgs, Method as Ic00Method,
     Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, UpdateSettingsArgs, IC_00,
+    SchnorrAlgorithm, SchnorrPublicKeyArgs, SchnorrPublicKeyResponse, SetControllerArgs,
+    SetupInitialDKGArgs, SignWithECDSAArgs, SignWithSchnorrArgs, UpdateSettingsArgs, IC_00,
 };
 use ic_interfaces::execution_environment::AvailableMemory;
 use ic_interfaces::{
@@ -41,7 +42,9 @@ use ic_replicated_state::{
 use ic_types::messages::InternalQuery;
 use ic_types::{
     canister_http::CanisterHttpRequestContext,
-    crypto::canister_threshold_sig::{ExtendedDerivationPath, MasterEcdsaPublicKey},
+    crypto::canister_threshold_sig::{
+        ExtendedDerivationPath, MasterEcdsaPublicKey, ThresholdSignatureScheme,
+    },
     crypto::threshold_sig::ni_dkg::NiDkgTargetId,
     ingress::{IngressStatus, WasmResult},
     messages::{
@@ -581,6 +584,60 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 }
             },
 
+            Ok(Ic00Method::SignWithSchnorr) => match &msg {
+                RequestOrIngress::Request(request) => {
+                    let reject_message = if !state.metadata.own_subnet_features.ecdsa_signatures {
+                        "This API is not enabled on this subnet".to_string()
+                    } else if payload.is_empty() {
+                        "An empty message cannot be signed".to_string()
+                    } else {
+                        String::new()
+                    };
+
+                    if !reject_message.is_empty() {
+                        use ic_types::messages;
+                        state.push_subnet_output_response(Response {
+                            originator: request.sender,
+                            respondent: CanisterId::from(self.own_subnet_id),
+                            originator_reply_callback: request.sender_reply_callback,
+                            refund: request.payment,
+                            response_payload: messages::Payload::Reject(messages::RejectContext {
+                                code: ic_error_types::RejectCode::CanisterReject,
+                                message: reject_message,
+                            }),
+                        });
+                        return (state, instructions_limit);
+                    }
+
+                    let res = match SignWithSchnorrArgs::decode(payload) {
+                        Err(err) => Some((Err(candid_error_to_user_error(err)), msg.take_cycles())),
+                        Ok(args) => self
+                            .sign_with_schnorr(
+                                request.clone(),
+                                args.message,
+                                args.derivation_path,
+                                args.algorithm,
+                                &args.key_id,
+                                &mut state,
+                                rng,
+                            )
+                            .map_or_else(|err| Some((Err(err), msg.take_cycles())), |()| None),
+                    };
+                    (res, instructions_limit)
+                }
+                RequestOrIngress::Ingress(_) => {
+                    error!(self.log, "[EXC-BUG] Ingress messages to SignWithSchnorr should've been filtered earlier.");
+                    let error_string = format!(
+                        "SignWithSchnorr is called by user {}. It can only be called by a canister.",
+                        msg.sender()
+                    );
+                    let user_error =
+                        UserError::new(ErrorCode::CanisterContractViolation, error_string);
+                    let res = Some((Err(user_error), msg.take_cycles()));
+                    (res, instructions_limit)
+                }
+            },
+
             Ok(Ic00Method::ECDSAPublicKey) => {
                 let res = match &msg {
                     RequestOrIngress::Request(_request) => {
@@ -622,6 +679,48 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 (res, instructions_limit)
             }
 
+            Ok(Ic00Method::SchnorrPublicKey) => {
+                let res = match &msg {
+                    RequestOrIngress::Request(_request) => {
+                        if !state.metadata.own_subnet_features.ecdsa_signatures {
+                            Some(Err(UserError::new(ErrorCode::CanisterContractViolation,
+                              "This API is not enabled on this subnet".to_string())))
+                        }
+                        else {
+                            match SchnorrPublicKeyArgs::decode(payload) {
+                                Err(err) => Some(Err(candid_error_to_user_error(err))),
+                                Ok(args) => match ecdsa_subnet_public_key {
+                                    None => Some(Err(UserError::new(ErrorCode::CanisterRejectedMessage,
+                                              "Subnet ECDSA public key is not yet available.".to_string()))),
+                                    Some(pubkey) => {
+                                      let canister_id = match args.canister_id {
+                                        Some(id) => id.into(),
+                                        None => *msg.sender(),
+                                      };
+                                      Some(self.get_schnorr_public_key(
+                                        pubkey,
+                                        canister_id,
+                                        args.derivation_path,
+                                        args.algorithm,
+                                        &args.key_id,
+                                        ).map(|res| res.encode()))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    RequestOrIngress::Ingress(_) => {
+                        error!(self.log, "[EXC-BUG] Ingress messages to SchnorrPublicKey should've been filtered earlier.");
+                        let error_string = format!(
+                            "SchnorrPublicKey is called by user {}. It can only be called by a canister.",
+                            msg.sender()
+                        );
+                        Some(Err(UserError::new(ErrorCode::CanisterContractViolation, error_string)))
+                    }
+                }.map(|res| (res, msg.take_cycles()));
+                (res, instructions_limit)
+            }
+
             Ok(Ic00Method::ComputeInitialEcdsaDealings) => {
                 let res = match &msg {
                     RequestOrIngress::Request(request) => {
@@ -732,8 +831,9 @@ impl ExecutionEnvironment for ExecutionEnvironmentImpl {
                 // responded to (which currently happens in the scheduler).
                 //
                 // This scenario also happens in the case of
-                // Ic00Method::SetupInitialDKG, Ic00Method::HttpRequest, and
-                // Ic00Method::SignWithECDSA. The request is saved and the
+                // Ic00Method::SetupInitialDKG, Ic00Method::HttpRequest,
+                // Ic00Method::SignWithECDSA and Ic00Method::SignWithSchnorr.
+                // The request is saved and the
                 // response from consensus is handled separately.
                 (state, instructions_left)
             }
@@ -954,6 +1054,18 @@ fn verify_ecdsa_key_id(key_id: &str) -> Result<(), UserError> {
     }
 }
 
+fn verify_schnorr_key_id(algorithm: SchnorrAlgorithm, key_id: &str) -> Result<(), UserError> {
+    match algorithm {
+        SchnorrAlgorithm::Bip340Secp256k1 => verify_ecdsa_key_id(key_id),
+        // Threshold Ed25519 needs an IDKG key over the Ed25519 group, which
+        // the threshold signature library does not provide.
+        SchnorrAlgorithm::Ed25519 => Err(UserError::new(
+            ErrorCode::CanisterRejectedMessage,
+            "No Ed25519 key is available on this subnet",
+        )),
+    }
+}
+
 impl ExecutionEnvironmentImpl {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -1548,6 +1660,19 @@ impl ExecutionEnvironmentImpl {
 
             if let IngressInductionCost::Fee { payer, cost } = induction_cost {
                 match state.canister_state(&payer) {
+                    // Canisters about to be migrated away from this subnet do
+                    // not accept any new messages, so that their queues drain.
+                    Some(_)
+                        if state
+                            .metadata
+                            .network_topology
+                            .is_migration_prepared(payer, state.metadata.own_subnet_id) =>
+                    {
+                        return Err(UserError::new(
+                            ErrorCode::CanisterRejectedMessage,
+                            format!("Canister {} is being migrated", payer),
+                        ));
+                    }
                     Some(canister) => {
                         if let Err(err) = self.cycles_account_manager.can_withdraw_cycles(
                             &canister.system_state,
@@ -2015,10 +2140,37 @@ impl ExecutionEnvironmentImpl {
             })
     }
 
+    fn get_schnorr_public_key(
+        &self,
+        subnet_public_key: &MasterEcdsaPublicKey,
+        principal_id: PrincipalId,
+        derivation_path: Vec<Vec<u8>>,
+        algorithm: SchnorrAlgorithm,
+        key_id: &str,
+    ) -> Result<SchnorrPublicKeyResponse, UserError> {
+        let _ = CanisterId::new(principal_id).map_err(|err| {
+            UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!("Not a canister id: {}", err),
+            )
+        })?;
+        verify_schnorr_key_id(algorithm, key_id)?;
+        let path = ExtendedDerivationPath {
+            caller: principal_id,
+            derivation_path,
+        };
+        derive_tbip340_public_key(subnet_public_key, &path)
+            .map_err(|err| UserError::new(ErrorCode::CanisterRejectedMessage, format!("{}", err)))
+            .map(|res| SchnorrPublicKeyResponse {
+                public_key: res.public_key,
+                chain_code: res.chain_key,
+            })
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn sign_with_ecdsa(
         &self,
-        mut request: Request,
+        request: Request,
         message_hash: Vec<u8>,
         derivation_path: Vec<Vec<u8>>,
         key_id: &str,
@@ -2033,6 +2185,58 @@ impl ExecutionEnvironmentImpl {
         }
         verify_ecdsa_key_id(key_id)?;
 
+        self.push_threshold_signature_request(
+            request,
+            Ic00Method::SignWithECDSA,
+            ThresholdSignatureScheme::Ecdsa,
+            message_hash,
+            derivation_path,
+            state,
+            rng,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sign_with_schnorr(
+        &self,
+        request: Request,
+        message: Vec<u8>,
+        derivation_path: Vec<Vec<u8>>,
+        algorithm: SchnorrAlgorithm,
+        key_id: &str,
+        state: &mut ReplicatedState,
+        rng: &mut (dyn RngCore + 'static),
+    ) -> Result<(), UserError> {
+        verify_schnorr_key_id(algorithm, key_id)?;
+        let scheme = match algorithm {
+            SchnorrAlgorithm::Bip340Secp256k1 => ThresholdSignatureScheme::Bip340,
+            SchnorrAlgorithm::Ed25519 => unreachable!("rejected by verify_schnorr_key_id"),
+        };
+
+        self.push_threshold_signature_request(
+            request,
+            Ic00Method::SignWithSchnorr,
+            scheme,
+            message,
+            derivation_path,
+            state,
+            rng,
+        )
+    }
+
+    /// Charges the signature fee and hands the request over to consensus,
+    /// which responds to it once the threshold signature is available.
+    #[allow(clippy::too_many_arguments)]
+    fn push_threshold_signature_request(
+        &self,
+        mut request: Request,
+        method: Ic00Method,
+        scheme: ThresholdSignatureScheme,
+        message: Vec<u8>,
+        derivation_path: Vec<Vec<u8>>,
+        state: &mut ReplicatedState,
+        rng: &mut (dyn RngCore + 'static),
+    ) -> Result<(), UserError> {
         // If the request isn't from the NNS, then we need to charge for it.
         // Consensus will return any remaining cycles.
         let source_subnet = state
@@ -2046,8 +2250,8 @@ impl ExecutionEnvironmentImpl {
                 return Err(UserError::new(
                     ErrorCode::CanisterRejectedMessage,
                     format!(
-                        "sign_with_ecdsa request sent with {} cycles, but {} cycles are required.",
-                        request.payment, signature_fee
+                        "{} request sent with {} cycles, but {} cycles are required.",
+                        method, request.payment, signature_fee
                     ),
                 ));
             } else {
@@ -2060,8 +2264,9 @@ impl ExecutionEnvironmentImpl {
 
         info!(
             self.log,
-            "Assigned the pseudo_random_id {:?} to the new sign_with_ECDSA request from {:?}",
+            "Assigned the pseudo_random_id {:?} to the new {} request from {:?}",
             pseudo_random_id,
+            method,
             request.sender()
         );
         state
@@ -2069,7 +2274,8 @@ impl ExecutionEnvironmentImpl {
             .subnet_call_context_manager
             .push_sign_with_ecdsa_request(SignWithEcdsaContext {
                 request,
-                message_hash,
+                scheme,
+                message_hash: message,
                 derivation_path,
                 pseudo_random_id,
                 batch_time: state.metadata.batch_time,
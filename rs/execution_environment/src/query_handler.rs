@@ -26,7 +26,7 @@ use ic_types::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
-    CanisterId, NumInstructions,
+    CanisterId, Height, NumInstructions,
 };
 use query_allocations::QueryAllocationsUsed;
 use serde::Serialize;
@@ -52,7 +52,7 @@ fn get_latest_certified_state_and_data_certificate(
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     certificate_delegation: Option<CertificateDelegation>,
     canister_id: CanisterId,
-) -> Option<(Arc<ReplicatedState>, Vec<u8>)> {
+) -> Option<(Arc<ReplicatedState>, Vec<u8>, Height)> {
     // The path to fetch the data certificate for the canister.
     let path = SubTree(flatmap! {
         label("canister") => SubTree(
@@ -77,6 +77,7 @@ fn get_latest_certified_state_and_data_certificate(
                     signature: Blob(cert.signed.signature.signature.get().0),
                     delegation: certificate_delegation,
                 }),
+                cert.height,
             )
         })
 }
@@ -201,7 +202,7 @@ impl QueryHandler for HttpQueryHandler {
 }
 
 impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
-    type Response = HttpQueryResponse;
+    type Response = (HttpQueryResponse, Option<Height>);
     type Error = Infallible;
     #[allow(clippy::type_complexity)]
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
@@ -223,16 +224,21 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
                 // We managed to upgrade the weak pointer, so the query was not cancelled.
                 // Canceling the query after this point will have to effect: the query will
                 // be executed anyway. That is fine because the execution will take O(ms).
-                let result = match get_latest_certified_state_and_data_certificate(
+                let (result, height) = match get_latest_certified_state_and_data_certificate(
                     state_reader,
                     certificate_delegation,
                     query.receiver,
                 ) {
-                    Some((state, cert)) => internal.query(query, state, cert),
-                    None => Err(UserError::new(
-                        ErrorCode::CertifiedStateUnavailable,
-                        "Certified state is not available yet. Please try again...",
-                    )),
+                    Some((state, cert, height)) => {
+                        (internal.query(query, state, cert), Some(height))
+                    }
+                    None => (
+                        Err(UserError::new(
+                            ErrorCode::CertifiedStateUnavailable,
+                            "Certified state is not available yet. Please try again...",
+                        )),
+                        None,
+                    ),
                 };
 
                 let http_query_response = match result {
@@ -252,7 +258,7 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
                     },
                 };
 
-                let _ = tx.send(Ok(http_query_response));
+                let _ = tx.send(Ok((http_query_response, height)));
             }
         });
         Box::pin(async move {
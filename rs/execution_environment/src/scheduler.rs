@@ -24,7 +24,7 @@ use ic_types::{
     crypto::canister_threshold_sig::MasterEcdsaPublicKey,
     ic00::{EmptyBlob, InstallCodeArgs, Payload as _, IC_00},
     ingress::{IngressStatus, WasmResult},
-    messages::{Ingress, MessageId, Payload, Response, StopCanisterContext},
+    messages::{Ingress, MessageId, Payload, RequestOrResponse, Response, StopCanisterContext},
     AccumulatedPriority, CanisterId, CanisterStatusType, ComputeAllocation, ExecutionRound,
     InstallCodeContext, MemoryAllocation, NumBytes, NumInstructions, Randomness, SubnetId, Time,
 };
@@ -250,11 +250,16 @@ impl FilteredCanisters {
 /// Separates the ordered canisters into a list of active canisters and a set of canisters that
 /// were heap delta rate limited. Does not alter the order of canisters to be executed.
 ///
+/// Canisters about to be migrated away from `own_subnet_id` are only considered active if they
+/// have input, as their heartbeats are not executed.
+///
 /// Returns the filtered canisters.
 fn filter_canisters(
     ordered_canister_ids: &[CanisterId],
     canisters: &BTreeMap<CanisterId, CanisterState>,
     heartbeat_handling: HeartbeatHandling,
+    network_topology: &NetworkTopology,
+    own_subnet_id: SubnetId,
     heap_delta_rate_limit: NumBytes,
     rate_limiting_of_heap_delta: FlagStatus,
 ) -> (Vec<CanisterId>, BTreeSet<CanisterId>) {
@@ -272,7 +277,8 @@ fn filter_canisters(
             }
             (canister.has_input()
                 || (heartbeat_handling.should_execute_heartbeat()
-                    && canister.exports_heartbeat_method()))
+                    && canister.exports_heartbeat_method()
+                    && !network_topology.is_migration_prepared(**canister_id, own_subnet_id)))
                 && is_under_limit
         })
         .cloned()
@@ -382,6 +388,8 @@ impl SchedulerImpl {
                 ordered_canister_ids,
                 &canisters,
                 heartbeat_handling,
+                &state.metadata.network_topology,
+                self.own_subnet_id,
                 self.config.heap_delta_rate_limit,
                 self.rate_limiting_of_heap_delta,
             );
@@ -565,6 +573,7 @@ impl SchedulerImpl {
                 let logger = new_logger!(self.log; messaging.round => round_id.get());
                 let canister_execution_limits = canister_execution_limits.clone();
                 let rate_limiting_of_heap_delta = self.rate_limiting_of_heap_delta;
+                let own_subnet_id = self.own_subnet_id;
                 scope.execute(move || {
                     *result = execute_canisters_on_thread(
                         canisters,
@@ -575,6 +584,7 @@ impl SchedulerImpl {
                         time,
                         subnet_available_memory.into(),
                         network_topology,
+                        own_subnet_id,
                         heartbeat_handling,
                         logger,
                         rate_limiting_of_heap_delta,
@@ -808,7 +818,9 @@ impl SchedulerImpl {
     ///
     /// This method only handles messages sent to self and to other canisters.
     /// Messages sent to the subnet are not handled i.e. they take the slow path
-    /// through message routing.
+    /// through message routing. Neither are requests to canisters about to be
+    /// migrated away from this subnet: they take the slow path too and are
+    /// rejected by the stream handler, so that the canisters' queues drain.
     pub fn induct_messages_on_same_subnet(&self, state: &mut ReplicatedState) {
         // Compute subnet available memory *before* taking out the canisters.
         let mut subnet_available_memory = self
@@ -837,15 +849,28 @@ impl SchedulerImpl {
                 Some(canister) => canister,
             };
 
-            source_canister.induct_messages_to_self(
-                max_canister_memory_size,
-                &mut subnet_available_memory,
-                state.metadata.own_subnet_type,
-            );
+            let network_topology = &state.metadata.network_topology;
+            let is_migrating = |canister_id: &CanisterId| {
+                network_topology.is_migration_prepared(*canister_id, self.own_subnet_id)
+            };
+
+            if !is_migrating(&source_canister_id) {
+                source_canister.induct_messages_to_self(
+                    max_canister_memory_size,
+                    &mut subnet_available_memory,
+                    state.metadata.own_subnet_type,
+                );
+            }
 
             source_canister
                 .system_state
                 .output_queues_for_each(|canister_id, msg| match canisters.get_mut(canister_id) {
+                    Some(_)
+                        if matches!(*msg, RequestOrResponse::Request(_))
+                            && is_migrating(canister_id) =>
+                    {
+                        Err(())
+                    }
                     Some(dest_canister) => dest_canister
                         .push_input(
                             QUEUE_INDEX_NONE,
@@ -1201,6 +1226,7 @@ fn execute_canisters_on_thread(
     time: Time,
     subnet_available_memory: SubnetAvailableMemory,
     network_topology: Arc<NetworkTopology>,
+    own_subnet_id: SubnetId,
     heartbeat_handling: HeartbeatHandling,
     logger: ReplicaLogger,
     rate_limiting_of_heap_delta: FlagStatus,
@@ -1231,11 +1257,15 @@ fn execute_canisters_on_thread(
 
         // Run heartbeat before processing the messages. Otherwise, if there are many
         // messages, we may reach the instruction limit before running heartbeat.
+        // Canisters about to be migrated away from this subnet do not run their
+        // heartbeat, so that they stop initiating new calls and their queues drain.
         if let HeartbeatHandling::Execute {
             only_track_system_errors,
         } = heartbeat_handling
         {
-            if canister.exports_heartbeat_method() {
+            if canister.exports_heartbeat_method()
+                && !network_topology.is_migration_prepared(canister.canister_id(), own_subnet_id)
+            {
                 let measurement_scope = MeasurementScope::nested(
                     &metrics.round_inner_iteration_thread_heartbeat,
                     &measurement_scope,
@@ -1518,6 +1548,8 @@ fn get_instructions_limit_for_subnet_message(
             | HttpRequest
             | SetupInitialDKG
             | SignWithECDSA
+            | SchnorrPublicKey
+            | SignWithSchnorr
             | ComputeInitialEcdsaDealings
             | StartCanister
             | StopCanister
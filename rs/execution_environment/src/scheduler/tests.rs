@@ -12,7 +12,7 @@ use ic_interfaces::execution_environment::{
 use ic_interfaces::messages::CanisterInputMessage;
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
-use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
+use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::SubnetTopology;
 use ic_replicated_state::{
@@ -458,6 +458,21 @@ fn setup_routing_table() -> (SubnetId, RoutingTable) {
     (subnet_id, routing_table)
 }
 
+/// Records a migration of `canister_id` away from `from_subnet` in the
+/// canister migrations of `state`.
+fn prepare_canister_migration(
+    state: &mut ReplicatedState,
+    canister_id: CanisterId,
+    from_subnet: SubnetId,
+) {
+    state.metadata.network_topology.canister_migrations = Arc::new(
+        CanisterMigrations::try_from(btreemap! {
+            CanisterIdRange{ start: canister_id, end: canister_id } => vec![from_subnet, subnet_test_id(2)],
+        })
+        .unwrap(),
+    );
+}
+
 /// Creates state with two canisters. Source canister has a message for
 /// destination canister in its output queue. Ensures that
 /// `induct_messages_on_same_subnet()` moves the message from source to
@@ -633,6 +648,128 @@ fn induct_messages_to_self_works() {
     )
 }
 
+/// Creates state with two canisters. Source canister has a request for
+/// destination canister, which is about to be migrated away from the subnet.
+/// Ensures that `induct_messages_on_same_subnet()` leaves the request in the
+/// output queue, for the stream handler to reject.
+#[test]
+fn induct_messages_on_same_subnet_skips_requests_to_migrating_canister() {
+    let ingress_history_writer = Arc::new(default_ingress_history_writer_mock(0));
+    let scheduler_test_fixture = SchedulerTestFixture {
+        scheduler_config: SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(1),
+            max_instructions_per_message: NumInstructions::from(1),
+            instruction_overhead_per_message: NumInstructions::from(0),
+            ..SchedulerConfig::system_subnet()
+        },
+        metrics_registry: MetricsRegistry::new(),
+        canister_num: 2,
+        message_num_per_canister: 0,
+    };
+    let exec_env = Arc::new(default_exec_env_mock(
+        &scheduler_test_fixture,
+        0,
+        NumInstructions::from(1),
+        NumBytes::new(0),
+    ));
+    scheduler_test(
+        &scheduler_test_fixture,
+        |scheduler| {
+            let mut state = get_initial_state(2, 0);
+            let mut canisters = state.take_canister_states();
+            let mut canister_ids: Vec<CanisterId> = canisters.keys().copied().collect();
+            let source_canister_id = canister_ids.pop().unwrap();
+            let dest_canister_id = canister_ids.pop().unwrap();
+
+            let source_canister = canisters.get_mut(&source_canister_id).unwrap();
+            source_canister
+                .push_output_request(
+                    RequestBuilder::default()
+                        .sender(source_canister_id)
+                        .receiver(dest_canister_id)
+                        .build(),
+                )
+                .unwrap();
+            state.put_canister_states(canisters);
+
+            let (own_subnet_id, routing_table) = setup_routing_table();
+            state.metadata.network_topology.routing_table = Arc::new(routing_table);
+            state.metadata.own_subnet_id = own_subnet_id;
+            prepare_canister_migration(&mut state, dest_canister_id, own_subnet_id);
+
+            scheduler.induct_messages_on_same_subnet(&mut state);
+
+            let mut canisters = state.take_canister_states();
+            let source_canister = canisters.remove(&source_canister_id).unwrap();
+            let dest_canister = canisters.remove(&dest_canister_id).unwrap();
+            assert!(source_canister.has_output());
+            assert!(!dest_canister.has_input());
+        },
+        ingress_history_writer,
+        exec_env,
+    )
+}
+
+/// Creates state with one canister about to be migrated away from the subnet.
+/// The canister has a request for itself in its output queue. Ensures that
+/// `induct_messages_on_same_subnet()` does not induct the request.
+#[test]
+fn induct_messages_to_self_skips_migrating_canister() {
+    let ingress_history_writer = Arc::new(default_ingress_history_writer_mock(0));
+    let scheduler_test_fixture = SchedulerTestFixture {
+        scheduler_config: SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(1),
+            max_instructions_per_message: NumInstructions::from(1),
+            instruction_overhead_per_message: NumInstructions::from(0),
+            ..SchedulerConfig::system_subnet()
+        },
+        metrics_registry: MetricsRegistry::new(),
+        canister_num: 1,
+        message_num_per_canister: 0,
+    };
+    let exec_env = Arc::new(default_exec_env_mock(
+        &scheduler_test_fixture,
+        0,
+        NumInstructions::from(1),
+        NumBytes::new(0),
+    ));
+    scheduler_test(
+        &scheduler_test_fixture,
+        |scheduler| {
+            let mut state = get_initial_state(1, 0);
+            let mut canisters = state.take_canister_states();
+            let mut canister_ids: Vec<CanisterId> = canisters.keys().copied().collect();
+            let source_canister_id = canister_ids.pop().unwrap();
+            let source_canister = canisters.get_mut(&source_canister_id).unwrap();
+            source_canister
+                .push_output_request(
+                    RequestBuilder::default()
+                        .sender(source_canister_id)
+                        .receiver(source_canister_id)
+                        .build(),
+                )
+                .unwrap();
+            state.put_canister_states(canisters);
+
+            let (own_subnet_id, routing_table) = setup_routing_table();
+            state.metadata.network_topology.routing_table = Arc::new(routing_table);
+            state.metadata.own_subnet_id = own_subnet_id;
+            prepare_canister_migration(&mut state, source_canister_id, own_subnet_id);
+
+            scheduler.induct_messages_on_same_subnet(&mut state);
+
+            let mut canisters = state.take_canister_states();
+            let source_canister = canisters.remove(&source_canister_id).unwrap();
+            assert!(source_canister.has_output());
+            assert!(!source_canister.has_input());
+        },
+        ingress_history_writer,
+        exec_env,
+    )
+}
+
 /// Creates state with two canisters. Source canister has two requests for
 /// itself and two requests for destination canister in its output queues.
 /// Source canister only has enough memory for one request, subnet only has
@@ -1998,6 +2135,68 @@ fn execute_heartbeat_once_per_round_in_system_subnet() {
     );
 }
 
+#[test]
+fn do_not_execute_heartbeat_of_migrating_canister() {
+    // This test sets up a canister on a system subnet with a heartbeat method and
+    // three messages. The canister is about to be migrated away from the subnet,
+    // so the heartbeat is not expected to run. The messages are still expected to
+    // run once each.
+    let scheduler_test_fixture = SchedulerTestFixture {
+        scheduler_config: SchedulerConfig {
+            scheduler_cores: 1,
+            max_instructions_per_round: NumInstructions::from(1000),
+            max_instructions_per_message: NumInstructions::from(100),
+            instruction_overhead_per_message: NumInstructions::from(0),
+            ..SchedulerConfig::system_subnet()
+        },
+        metrics_registry: MetricsRegistry::new(),
+        canister_num: 1,
+        message_num_per_canister: 3,
+    };
+    let mut exec_env = default_exec_env_mock(
+        &scheduler_test_fixture,
+        3,
+        NumInstructions::from(1),
+        NumBytes::new(0),
+    );
+    exec_env.expect_execute_canister_heartbeat().times(0);
+    let exec_env = Arc::new(exec_env);
+
+    let ingress_history_writer = default_ingress_history_writer_mock(3);
+    let ingress_history_writer = Arc::new(ingress_history_writer);
+    scheduler_test(
+        &scheduler_test_fixture,
+        |scheduler| {
+            let mut state = get_initial_state(
+                scheduler_test_fixture.canister_num,
+                scheduler_test_fixture.message_num_per_canister,
+            );
+            let canister_ids: Vec<CanisterId> = state.canister_states.keys().copied().collect();
+            for canister in state.canisters_iter_mut() {
+                if let Some(ref mut execution_state) = canister.execution_state {
+                    execution_state.exports = ExportedFunctions::new(
+                        [WasmMethod::System(SystemMethod::CanisterHeartbeat)]
+                            .iter()
+                            .cloned()
+                            .collect(),
+                    );
+                }
+            }
+            prepare_canister_migration(&mut state, canister_ids[0], scheduler.own_subnet_id);
+            scheduler.execute_round(
+                state,
+                Randomness::from([0; 32]),
+                None,
+                ExecutionRound::from(1),
+                ProvisionalWhitelist::Set(BTreeSet::new()),
+                MAX_NUMBER_OF_CANISTERS,
+            );
+        },
+        ingress_history_writer,
+        exec_env,
+    );
+}
+
 #[test]
 fn execute_heartbeat_before_messages() {
     // This test sets up a canister on a system subnet with a heartbeat method and
@@ -17,7 +17,7 @@ use ic_interfaces::{
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
-use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
+use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     canister_state::WASM_PAGE_SIZE_IN_BYTES, NetworkTopology, SubnetTopology,
@@ -49,6 +49,10 @@ use ic_test_utilities::{
     with_test_replica_logger,
 };
 use ic_types::{
+    crypto::{
+        canister_threshold_sig::{MasterEcdsaPublicKey, ThresholdSignatureScheme},
+        AlgorithmId,
+    },
     ic00,
     ic00::{
         CanisterHttpRequestArgs, CanisterIdRecord, CanisterStatusResultV2, EmptyBlob,
@@ -2780,6 +2784,46 @@ fn message_to_canister_with_not_enough_balance_is_rejected() {
     });
 }
 
+#[test]
+fn message_to_migrating_canister_is_rejected() {
+    with_setup(SubnetType::Application, |exec_env, _, _, _| {
+        let canister_id = canister_test_id(0);
+        let ingress = SignedIngressBuilder::new()
+            .canister_id(canister_id)
+            .build()
+            .content()
+            .clone();
+        let mut state = ReplicatedStateBuilder::default()
+            .with_subnet_id(subnet_test_id(1))
+            .with_canister(
+                CanisterStateBuilder::default()
+                    .with_canister_id(canister_id)
+                    .with_cycles(u128::MAX)
+                    .build(),
+            )
+            .build();
+        state.metadata.network_topology.canister_migrations = Arc::new(
+            CanisterMigrations::try_from(btreemap! {
+                CanisterIdRange { start: canister_id, end: canister_id } =>
+                    vec![subnet_test_id(1), subnet_test_id(2)],
+            })
+            .unwrap(),
+        );
+        assert_eq!(
+            exec_env
+                .should_accept_ingress_message(
+                    Arc::new(state),
+                    &ProvisionalWhitelist::new_empty(),
+                    &ingress,
+                    ExecutionMode::NonReplicated,
+                )
+                .unwrap_err()
+                .code(),
+            ErrorCode::CanisterRejectedMessage,
+        );
+    });
+}
+
 #[test]
 fn message_to_canister_with_enough_balance_is_accepted() {
     with_setup(SubnetType::Application, |exec_env, _, _, _| {
@@ -3546,3 +3590,251 @@ fn ecdsa_signature_fee_ignored_for_nns() {
         assert_eq!(context.request.payment, payment)
     });
 }
+
+fn execute_schnorr_public_key(
+    sender: CanisterId,
+    own_subnet_is_ecdsa_enabled: bool,
+    ecdsa_subnet_public_key: Option<MasterEcdsaPublicKey>,
+    key_id: String,
+    log: ReplicaLogger,
+) -> ReplicatedState {
+    let (mut state, exec_env) = ExecutionEnvironmentBuilder::new()
+        .with_log(log)
+        .with_sender_canister(sender)
+        .build();
+
+    state.metadata.own_subnet_features.ecdsa_signatures = own_subnet_is_ecdsa_enabled;
+
+    let request_payload = ic00::SchnorrPublicKeyArgs {
+        canister_id: None,
+        derivation_path: vec![vec![1, 2, 3]],
+        algorithm: ic00::SchnorrAlgorithm::Bip340Secp256k1,
+        key_id,
+    };
+    state
+        .subnet_queues_mut()
+        .push_input(
+            QUEUE_INDEX_NONE,
+            RequestOrResponse::Request(
+                RequestBuilder::new()
+                    .sender(sender)
+                    .method_name(Method::SchnorrPublicKey)
+                    .method_payload(Encode!(&request_payload).unwrap())
+                    .payment(Cycles::from(0u64))
+                    .build(),
+            ),
+            InputQueueType::RemoteSubnet,
+        )
+        .unwrap();
+
+    exec_env
+        .execute_subnet_message(
+            state.subnet_queues_mut().pop_input().unwrap(),
+            state,
+            MAX_NUM_INSTRUCTIONS,
+            &mut mock_random_number_generator(),
+            &ecdsa_subnet_public_key,
+            &ProvisionalWhitelist::Set(BTreeSet::new()),
+            MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+            MAX_NUMBER_OF_CANISTERS,
+        )
+        .0
+}
+
+/// A master public key whose point is the secp256k1 generator.
+fn secp256k1_master_public_key() -> MasterEcdsaPublicKey {
+    MasterEcdsaPublicKey {
+        algorithm_id: AlgorithmId::EcdsaSecp256k1,
+        public_key: vec![
+            0x02, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce,
+            0x87, 0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81,
+            0x5b, 0x16, 0xf8, 0x17, 0x98,
+        ],
+    }
+}
+
+#[test]
+fn schnorr_public_key_returns_derived_bip340_key() {
+    with_test_replica_logger(|log| {
+        let sender = canister_test_id(1);
+        let mut state = execute_schnorr_public_key(
+            sender,
+            true,
+            Some(secp256k1_master_public_key()),
+            "secp256k1".to_string(),
+            log,
+        );
+
+        let (_refund, response) = state
+            .subnet_queues_mut()
+            .pop_canister_output(&sender)
+            .unwrap();
+        let response = match response {
+            RequestOrResponse::Response(resp) => match resp.response_payload {
+                Payload::Data(data) => ic00::SchnorrPublicKeyResponse::decode(&data).unwrap(),
+                Payload::Reject(reject) => panic!("Unexpected reject: {:?}", reject),
+            },
+            RequestOrResponse::Request(_) => panic!("Expected Response"),
+        };
+        // BIP340 public keys are x-only.
+        assert_eq!(response.public_key.len(), 32);
+        assert_eq!(response.chain_code.len(), 32);
+    });
+}
+
+#[test]
+fn schnorr_public_key_rejected_without_ecdsa_enabled() {
+    with_test_replica_logger(|log| {
+        let sender = canister_test_id(1);
+        let mut state = execute_schnorr_public_key(
+            sender,
+            false,
+            Some(secp256k1_master_public_key()),
+            "secp256k1".to_string(),
+            log,
+        );
+
+        let (_refund, response) = state
+            .subnet_queues_mut()
+            .pop_canister_output(&sender)
+            .unwrap();
+        assert_eq!(
+            get_reject_message(response),
+            "This API is not enabled on this subnet".to_string()
+        );
+    });
+}
+
+#[test]
+fn schnorr_public_key_rejected_with_unknown_key() {
+    with_test_replica_logger(|log| {
+        let sender = canister_test_id(1);
+        let mut state = execute_schnorr_public_key(
+            sender,
+            true,
+            Some(secp256k1_master_public_key()),
+            "foo".to_string(),
+            log,
+        );
+
+        let (_refund, response) = state
+            .subnet_queues_mut()
+            .pop_canister_output(&sender)
+            .unwrap();
+        assert_eq!(
+            get_reject_message(response),
+            "key_id must be \"secp256k1\"".to_string()
+        );
+    });
+}
+
+#[test]
+fn schnorr_public_key_rejected_without_master_key() {
+    with_test_replica_logger(|log| {
+        let sender = canister_test_id(1);
+        let mut state =
+            execute_schnorr_public_key(sender, true, None, "secp256k1".to_string(), log);
+
+        let (_refund, response) = state
+            .subnet_queues_mut()
+            .pop_canister_output(&sender)
+            .unwrap();
+        assert_eq!(
+            get_reject_message(response),
+            "Subnet ECDSA public key is not yet available.".to_string()
+        );
+    });
+}
+
+fn execute_schnorr_signing(
+    sender: CanisterId,
+    algorithm: ic00::SchnorrAlgorithm,
+    log: ReplicaLogger,
+) -> ReplicatedState {
+    let (mut state, exec_env) = ExecutionEnvironmentBuilder::new()
+        .with_log(log)
+        .with_sender_canister(sender)
+        .build();
+
+    state.metadata.own_subnet_features.ecdsa_signatures = true;
+
+    let request_payload = ic00::SignWithSchnorrArgs {
+        message: b"a message of arbitrary length".to_vec(),
+        derivation_path: vec![],
+        algorithm,
+        key_id: "secp256k1".to_string(),
+    };
+    state
+        .subnet_queues_mut()
+        .push_input(
+            QUEUE_INDEX_NONE,
+            RequestOrResponse::Request(
+                RequestBuilder::new()
+                    .sender(sender)
+                    .method_name(Method::SignWithSchnorr)
+                    .method_payload(Encode!(&request_payload).unwrap())
+                    .payment(Cycles::from(0u64))
+                    .build(),
+            ),
+            InputQueueType::RemoteSubnet,
+        )
+        .unwrap();
+
+    exec_env
+        .execute_subnet_message(
+            state.subnet_queues_mut().pop_input().unwrap(),
+            state,
+            MAX_NUM_INSTRUCTIONS,
+            &mut mock_random_number_generator(),
+            &Some(secp256k1_master_public_key()),
+            &ProvisionalWhitelist::Set(BTreeSet::new()),
+            MAX_SUBNET_AVAILABLE_MEMORY.clone(),
+            MAX_NUMBER_OF_CANISTERS,
+        )
+        .0
+}
+
+#[test]
+fn sign_with_schnorr_pushes_bip340_context() {
+    with_test_replica_logger(|log| {
+        let sender = canister_test_id(1);
+        let mut state =
+            execute_schnorr_signing(sender, ic00::SchnorrAlgorithm::Bip340Secp256k1, log);
+
+        assert_eq!(state.subnet_queues_mut().pop_canister_output(&sender), None);
+        let (_, context) = state
+            .metadata
+            .subnet_call_context_manager
+            .sign_with_ecdsa_contexts
+            .iter()
+            .next()
+            .unwrap();
+        assert_eq!(context.scheme, ThresholdSignatureScheme::Bip340);
+        assert_eq!(
+            context.message_hash,
+            b"a message of arbitrary length".to_vec()
+        );
+    });
+}
+
+#[test]
+fn sign_with_schnorr_rejects_ed25519() {
+    with_test_replica_logger(|log| {
+        let sender = canister_test_id(1);
+        let mut state = execute_schnorr_signing(sender, ic00::SchnorrAlgorithm::Ed25519, log);
+
+        let (_refund, response) = state
+            .subnet_queues_mut()
+            .pop_canister_output(&sender)
+            .unwrap();
+        assert_eq!(
+            get_reject_message(response),
+            "No Ed25519 key is available on this subnet".to_string()
+        );
+        assert!(state
+            .metadata
+            .subnet_call_context_manager
+            .sign_with_ecdsa_contexts
+            .is_empty());
+    });
+}
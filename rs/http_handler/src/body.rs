@@ -91,7 +91,7 @@ async fn receive_body_without_timeout(
     Ok(received_body)
 }
 
-async fn receive_body(
+pub(crate) async fn receive_body(
     body: Body,
     max_request_receive_duration: Duration,
     max_request_body_size_bytes: usize,
@@ -1,25 +1,34 @@
-//! Module that deals with requests to /api/v2/canister/.../call
+//! Module that deals with requests to /api/v2/canister/.../call and
+//! /api/v3/canister/.../call
 
 use crate::{
     common::{
-        get_cors_headers, make_plaintext_response, make_response,
+        cbor_response, get_cors_headers, into_cbor, make_plaintext_response, make_response,
         make_response_on_validation_error, map_box_error_to_response,
     },
+    rate_limiter::{IngressRateLimiter, Throttled},
     types::{ApiReqType, RequestType},
     HttpError, HttpHandlerMetrics, IngressFilterService, UNKNOWN_LABEL,
 };
-use hyper::{Body, Response, StatusCode};
+use hyper::{header, Body, Response, StatusCode};
+use ic_crypto_tree_hash::{sparse_labeled_tree_from_paths, Label, Path};
 use ic_interfaces::{crypto::IngressSigVerifier, registry::RegistryClient};
 use ic_interfaces_p2p::{IngressError, IngressIngestionService};
+use ic_interfaces_state_manager::StateReader;
 use ic_logger::{error, info_sample, warn, ReplicaLogger};
 use ic_registry_client_helpers::{
     provisional_whitelist::ProvisionalWhitelistRegistry,
     subnet::{IngressMessageSettings, SubnetRegistry},
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
+use ic_replicated_state::ReplicatedState;
 use ic_types::{
+    ingress::IngressStatus,
     malicious_flags::MaliciousFlags,
-    messages::{SignedIngress, SignedRequestBytes},
+    messages::{
+        Blob, Certificate, CertificateDelegation, HttpReadStateResponse, MessageId, SignedIngress,
+        SignedRequestBytes,
+    },
     time::current_time,
     CountBytes, RegistryVersion, SubnetId,
 };
@@ -27,10 +36,27 @@ use ic_validator::validate_request;
 use std::convert::TryInto;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use tower::{load_shed::LoadShed, BoxError, Service, ServiceBuilder, ServiceExt};
 
+/// How long a synchronous call waits for the message to complete before
+/// falling back to `202 Accepted`.
+const SYNC_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the certified state is checked for the completion of a
+/// synchronous call.
+const SYNC_CALL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What a synchronous call needs to return the certified status of the
+/// message.
+#[derive(Clone)]
+struct SyncCallContext {
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+}
+
 #[derive(Clone)]
 pub(crate) struct CallService {
     log: ReplicaLogger,
@@ -40,7 +66,10 @@ pub(crate) struct CallService {
     validator: Arc<dyn IngressSigVerifier + Send + Sync>,
     ingress_sender: IngressIngestionService,
     ingress_filter: LoadShed<IngressFilterService>,
+    ingress_rate_limiter: Arc<IngressRateLimiter>,
+    max_delegations: usize,
     malicious_flags: MaliciousFlags,
+    sync_call: Option<SyncCallContext>,
 }
 
 impl CallService {
@@ -53,6 +82,8 @@ impl CallService {
         validator: Arc<dyn IngressSigVerifier + Send + Sync>,
         ingress_sender: IngressIngestionService,
         ingress_filter: IngressFilterService,
+        ingress_rate_limiter: Arc<IngressRateLimiter>,
+        max_delegations: usize,
         malicious_flags: MaliciousFlags,
     ) -> Self {
         Self {
@@ -63,9 +94,27 @@ impl CallService {
             validator,
             ingress_sender,
             ingress_filter: ServiceBuilder::new().load_shed().service(ingress_filter),
+            ingress_rate_limiter,
+            max_delegations,
             malicious_flags,
+            sync_call: None,
         }
     }
+
+    /// Makes the service wait, up to `SYNC_CALL_TIMEOUT`, until the message
+    /// completes and respond with the certificate of its status, as in the
+    /// response to a `read_state` request for it.
+    pub(crate) fn with_sync_response(
+        mut self,
+        state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    ) -> Self {
+        self.sync_call = Some(SyncCallContext {
+            state_reader,
+            delegation_from_nns,
+        });
+        self
+    }
 }
 
 fn get_registry_data(
@@ -129,11 +178,15 @@ impl Service<Vec<u8>> for CallService {
 
     fn call(&mut self, body: Vec<u8>) -> Self::Future {
         // Actual parsing.
+        let api_req_type = match self.sync_call {
+            Some(_) => ApiReqType::SyncCall,
+            None => ApiReqType::Call,
+        };
         self.metrics
             .requests_body_size_bytes
             .with_label_values(&[
                 RequestType::Submit.as_str(),
-                ApiReqType::Call.as_str(),
+                api_req_type.as_str(),
                 UNKNOWN_LABEL,
             ])
             .observe(body.len() as f64);
@@ -178,12 +231,27 @@ impl Service<Vec<u8>> for CallService {
             self.validator.as_ref(),
             current_time(),
             registry_version,
+            self.max_delegations,
             &self.malicious_flags,
         ) {
             let res = make_response_on_validation_error(message_id, err, &self.log);
             return Box::pin(async move { Ok(res) });
         }
 
+        // The limits are only checked for authenticated requests, so that
+        // nobody can use up the quota of another sender.
+        if let Err(throttled) =
+            self.ingress_rate_limiter
+                .check(&msg.sender(), &msg.canister_id(), Instant::now())
+        {
+            self.metrics
+                .ingress_throttled_total
+                .with_label_values(&[throttled.reason.as_str()])
+                .inc();
+            let res = make_throttled_response(message_id, throttled);
+            return Box::pin(async move { Ok(res) });
+        }
+
         let ingress_sender = self.ingress_sender.clone();
 
         // In case the inner service has state that's driven to readiness and
@@ -207,6 +275,7 @@ impl Service<Vec<u8>> for CallService {
 
         let mut ingress_filter = self.ingress_filter.clone();
         let log = self.log.clone();
+        let sync_call = self.sync_call.clone();
 
         Box::pin(async move {
             match ingress_filter
@@ -241,7 +310,13 @@ impl Service<Vec<u8>> for CallService {
                         "ingress_message_submit";
                         ingress_message => ingress_log_entry
                     );
-                    make_accepted_response()
+                    match sync_call {
+                        Some(sync_call) => {
+                            wait_for_certified_status(sync_call, message_id, SYNC_CALL_TIMEOUT)
+                                .await
+                        }
+                        None => make_accepted_response(),
+                    }
                 }
             };
             Ok(response)
@@ -249,6 +324,53 @@ impl Service<Vec<u8>> for CallService {
     }
 }
 
+/// Waits until the certified state contains the final status of `message_id`
+/// and returns the certificate for its `request_status`, or `202 Accepted` if
+/// this takes longer than `timeout`.
+async fn wait_for_certified_status(
+    sync_call: SyncCallContext,
+    message_id: MessageId,
+    timeout: Duration,
+) -> Response<Body> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut paths = vec![
+        Path::new(vec![
+            Label::from("request_status"),
+            Label::from(message_id.clone()),
+        ]),
+        Path::from(Label::from("time")),
+    ];
+    let labeled_tree = sparse_labeled_tree_from_paths(&mut paths);
+    loop {
+        if let Some((state, tree, certification)) =
+            sync_call.state_reader.read_certified_state(&labeled_tree)
+        {
+            match state.get_ingress_status(&message_id) {
+                IngressStatus::Completed { .. }
+                | IngressStatus::Failed { .. }
+                | IngressStatus::Done { .. } => {
+                    let signature = certification.signed.signature.signature.get().0;
+                    let delegation = sync_call.delegation_from_nns.read().unwrap().clone();
+                    return cbor_response(&HttpReadStateResponse {
+                        certificate: Blob(into_cbor(&Certificate {
+                            tree,
+                            signature: Blob(signature),
+                            delegation,
+                        })),
+                    });
+                }
+                IngressStatus::Received { .. }
+                | IngressStatus::Processing { .. }
+                | IngressStatus::Unknown => (),
+            }
+        }
+        if tokio::time::Instant::now() + SYNC_CALL_POLL_INTERVAL >= deadline {
+            return make_accepted_response();
+        }
+        tokio::time::sleep(SYNC_CALL_POLL_INTERVAL).await;
+    }
+}
+
 fn make_accepted_response() -> Response<Body> {
     let mut response = Response::new(Body::from(""));
     *response.status_mut() = StatusCode::ACCEPTED;
@@ -256,6 +378,27 @@ fn make_accepted_response() -> Response<Body> {
     response
 }
 
+fn make_throttled_response(message_id: MessageId, throttled: Throttled) -> Response<Body> {
+    let mut response = make_plaintext_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!(
+            "Request {} exceeds the ingress rate limit per {}, try again later.",
+            message_id,
+            throttled.reason.as_str()
+        ),
+    );
+    // Retry-After is given in whole seconds, so round up.
+    let retry_after_secs = throttled
+        .retry_after
+        .as_secs()
+        .saturating_add(u64::from(throttled.retry_after.subsec_nanos() > 0));
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        header::HeaderValue::from(retry_after_secs),
+    );
+    response
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -306,4 +449,34 @@ mod test {
         let message_id_2 = SignedIngress::try_from(request2).unwrap().id();
         assert_eq!(message_id_2, message_id);
     }
+
+    #[test]
+    fn throttled_response_has_retry_after_header() {
+        use crate::rate_limiter::ThrottleReason;
+        use std::time::Duration;
+
+        let retry_after = |retry_after| {
+            let response = make_throttled_response(
+                MessageId::from([0; 32]),
+                Throttled {
+                    reason: ThrottleReason::Sender,
+                    retry_after,
+                },
+            );
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .expect("missing Retry-After header")
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(retry_after(Duration::from_secs(3)), "3");
+        // Rounded up to whole seconds.
+        assert_eq!(retry_after(Duration::from_millis(1500)), "2");
+        // A zero rate never refills, which must not overflow.
+        assert_eq!(retry_after(Duration::MAX), u64::MAX.to_string());
+    }
 }
@@ -97,8 +97,9 @@ pub(crate) fn map_box_error_to_response(err: BoxError) -> Response<Body> {
 }
 
 /// Add CORS headers to provided Response. In particular we allow
-/// wildcard origin, POST and GET and allow Accept, Authorization and
-/// Content Type headers.
+/// wildcard origin, POST and GET and allow Accept, Authorization,
+/// Content Type and minimum certified height headers, and expose the certified
+/// height header.
 pub(crate) fn get_cors_headers() -> HeaderMap {
     use hyper::header;
     let mut headers = HeaderMap::new();
@@ -112,7 +113,13 @@ pub(crate) fn get_cors_headers() -> HeaderMap {
     );
     headers.insert(
         header::ACCESS_CONTROL_ALLOW_HEADERS,
-        header::HeaderValue::from_static("Accept, Authorization, Content-Type"),
+        header::HeaderValue::from_static(
+            "Accept, Authorization, Content-Type, X-IC-Min-Certified-Height",
+        ),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        header::HeaderValue::from_static("X-IC-Certified-Height"),
     );
     headers
 }
@@ -189,7 +196,10 @@ pub(crate) mod test {
 
     fn check_cors_headers(hm: &HeaderMap) {
         let acl_headers = hm.get_all(header::ACCESS_CONTROL_ALLOW_HEADERS).iter();
-        assert!(acl_headers.eq(["Accept, Authorization, Content-Type"].iter()));
+        assert!(acl_headers
+            .eq(["Accept, Authorization, Content-Type, X-IC-Min-Certified-Height"].iter()));
+        let expose_headers = hm.get_all(header::ACCESS_CONTROL_EXPOSE_HEADERS).iter();
+        assert!(expose_headers.eq(["X-IC-Certified-Height"].iter()));
         let acl_methods = hm.get_all(header::ACCESS_CONTROL_ALLOW_METHODS).iter();
         assert!(acl_methods.eq(["POST, GET"].iter()));
         let acl_origin = hm.get_all(header::ACCESS_CONTROL_ALLOW_ORIGIN).iter();
@@ -0,0 +1,820 @@
+//! Module that deals with requests to /http/<canister_id>/...
+//!
+//! In gateway mode, the HTTP handler serves the web assets of canisters: the
+//! HTTP request is translated into an `http_request` query to the canister,
+//! `streaming_strategy` callbacks are followed to assemble bodies that don't
+//! fit into a single reply, and the `IC-Certificate` header of the response is
+//! verified before the response is returned.
+//!
+//! See https://github.com/dfinity/interface-spec/blob/master/spec/index.adoc#the-http-gateway-protocol
+
+use crate::{
+    body::receive_body, common::make_plaintext_response, ReplicaHealthStatus,
+    MAX_REQUEST_RECEIVE_DURATION, MAX_REQUEST_SIZE_BYTES,
+};
+use candid::{
+    parser::value::{IDLArgs, IDLValue},
+    CandidType, Decode, Deserialize, Encode,
+};
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    Body, Request, Response, StatusCode,
+};
+use ic_crypto_sha::Sha256;
+use ic_crypto_tree_hash::{lookup_path, LabeledTree, MixedHashTree};
+use ic_crypto_utils_threshold_sig::verify_combined;
+use ic_interfaces::{execution_environment::QueryExecutionService, registry::RegistryClient};
+use ic_logger::{info, ReplicaLogger};
+use ic_registry_client_helpers::crypto::CryptoRegistry;
+use ic_types::{
+    consensus::certification::CertificationContent,
+    crypto::{
+        threshold_sig::ThresholdSigPublicKey, CombinedThresholdSig, CombinedThresholdSigOf,
+        CryptoHash,
+    },
+    messages::{Certificate, CertificateDelegation, HttpQueryResponse, UserQuery},
+    time::{current_time, current_time_and_expiry_time},
+    CanisterId, CryptoHashOfPartialState, PrincipalId, SubnetId, Time, UserId,
+};
+use serde_bytes::ByteBuf;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tower::ServiceExt;
+use tree_deserializer::{types::Leb128EncodedU64, LabeledTreeDeserializer};
+
+/// The path prefix of requests served by the gateway.
+pub(crate) const HTTP_GATEWAY_PATH_PREFIX: &str = "/http/";
+
+const HTTP_REQUEST_METHOD: &str = "http_request";
+const IC_CERTIFICATE_HEADER: &str = "ic-certificate";
+
+/// Bodies assembled from streaming callbacks are not allowed to grow larger
+/// than this.
+const MAX_RESPONSE_BODY_SIZE_BYTES: usize = 50 * 1024 * 1024; // 50MB
+
+/// The maximum number of streaming callbacks followed for a single request.
+const MAX_STREAMING_CALLBACKS: usize = 1000;
+
+/// Certificates whose time differs from the local time by more than this are
+/// rejected.
+const MAX_CERTIFICATE_TIME_OFFSET: Duration = Duration::from_secs(5 * 60);
+
+/// The argument of the `http_request` query.
+#[derive(CandidType, Deserialize)]
+struct CanisterHttpRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: ByteBuf,
+}
+
+/// The reply of the `http_request` query. The `streaming_strategy` is
+/// extracted separately, as the type of its token is chosen by the canister.
+#[derive(CandidType, Deserialize)]
+struct CanisterHttpResponse {
+    status_code: u16,
+    headers: Vec<(String, String)>,
+    body: ByteBuf,
+}
+
+/// The reply of a streaming callback, without the token (see above).
+#[derive(CandidType, Deserialize)]
+struct StreamingCallbackHttpResponse {
+    body: ByteBuf,
+}
+
+/// The callback to query for the next chunk of a streamed body.
+#[derive(Debug, PartialEq)]
+struct StreamingCallback {
+    canister_id: CanisterId,
+    method_name: String,
+    token: IDLValue,
+}
+
+#[derive(Clone)]
+pub(crate) struct HttpGatewayService {
+    log: ReplicaLogger,
+    subnet_id: SubnetId,
+    health_status: Arc<RwLock<ReplicaHealthStatus>>,
+    delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+    registry_client: Arc<dyn RegistryClient>,
+    query_execution_service: QueryExecutionService,
+}
+
+impl HttpGatewayService {
+    pub(crate) fn new(
+        log: ReplicaLogger,
+        subnet_id: SubnetId,
+        health_status: Arc<RwLock<ReplicaHealthStatus>>,
+        delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
+        registry_client: Arc<dyn RegistryClient>,
+        query_execution_service: QueryExecutionService,
+    ) -> Self {
+        Self {
+            log,
+            subnet_id,
+            health_status,
+            delegation_from_nns,
+            registry_client,
+            query_execution_service,
+        }
+    }
+
+    /// Serves `request`, whose path starts with `HTTP_GATEWAY_PATH_PREFIX`.
+    pub(crate) async fn handle(self, request: Request<Body>) -> Response<Body> {
+        match self.handle_or_error(request).await {
+            Ok(response) => response,
+            Err((status, message)) => make_plaintext_response(status, message),
+        }
+    }
+
+    async fn handle_or_error(
+        self,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, (StatusCode, String)> {
+        if *self.health_status.read().unwrap() != ReplicaHealthStatus::Healthy {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Replica is starting. Check the /api/v2/status for more information.".to_string(),
+            ));
+        }
+
+        let (parts, body) = request.into_parts();
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or_else(|| parts.uri.path());
+        let (canister_id, url) =
+            parse_gateway_path(path_and_query).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+        let body = receive_body(body, MAX_REQUEST_RECEIVE_DURATION, MAX_REQUEST_SIZE_BYTES)
+            .await
+            .map_err(|err| (err.status, err.message))?;
+
+        let http_request = CanisterHttpRequest {
+            method: parts.method.to_string(),
+            url: url.clone(),
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    value
+                        .to_str()
+                        .ok()
+                        .map(|value| (name.to_string(), value.to_string()))
+                })
+                .collect(),
+            body: ByteBuf::from(body),
+        };
+        let payload = Encode!(&http_request).map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to encode the http_request argument: {}", err),
+            )
+        })?;
+
+        let reply = self
+            .query(canister_id, HTTP_REQUEST_METHOD.to_string(), payload)
+            .await?;
+        let http_response = Decode!(&reply, CanisterHttpResponse).map_err(|err| {
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to decode the http_request reply: {}", err),
+            )
+        })?;
+        let mut streaming_callback = streaming_callback(&reply, "streaming_strategy")
+            .map_err(|err| (StatusCode::BAD_GATEWAY, err))?;
+
+        let mut body = http_response.body.into_vec();
+        let mut callbacks = 0;
+        while let Some(callback) = streaming_callback {
+            if callback.canister_id != canister_id {
+                return Err((
+                    StatusCode::BAD_GATEWAY,
+                    format!(
+                        "The streaming callback of canister {} points to canister {}",
+                        canister_id, callback.canister_id
+                    ),
+                ));
+            }
+            callbacks += 1;
+            if callbacks > MAX_STREAMING_CALLBACKS {
+                return Err((
+                    StatusCode::BAD_GATEWAY,
+                    format!(
+                        "The response is streamed in more than {} chunks",
+                        MAX_STREAMING_CALLBACKS
+                    ),
+                ));
+            }
+            let payload = IDLArgs::new(&[callback.token]).to_bytes().map_err(|err| {
+                (
+                    StatusCode::BAD_GATEWAY,
+                    format!("Failed to encode the streaming token: {}", err),
+                )
+            })?;
+            let reply = self
+                .query(canister_id, callback.method_name, payload)
+                .await?;
+            let chunk = Decode!(&reply, StreamingCallbackHttpResponse).map_err(|err| {
+                (
+                    StatusCode::BAD_GATEWAY,
+                    format!("Failed to decode the streaming callback reply: {}", err),
+                )
+            })?;
+            if body.len() + chunk.body.len() > MAX_RESPONSE_BODY_SIZE_BYTES {
+                return Err((
+                    StatusCode::BAD_GATEWAY,
+                    format!(
+                        "The response body is bigger than {} bytes",
+                        MAX_RESPONSE_BODY_SIZE_BYTES
+                    ),
+                ));
+            }
+            body.extend_from_slice(&chunk.body);
+            streaming_callback = streaming_callback_from_token(&reply, canister_id, callback)
+                .map_err(|err| (StatusCode::BAD_GATEWAY, err))?;
+        }
+
+        let certificate_header = http_response
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(IC_CERTIFICATE_HEADER))
+            .map(|(_, value)| value.as_str())
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_GATEWAY,
+                    "The response is not certified".to_string(),
+                )
+            })?;
+        let subnet_public_key = self.subnet_public_key()?;
+        let url_path = url.split('?').next().unwrap_or_default();
+        verify_certified_body(
+            certificate_header,
+            &canister_id,
+            url_path,
+            &body,
+            &subnet_public_key,
+            current_time(),
+        )
+        .map_err(|err| {
+            info!(
+                self.log,
+                "Rejecting the response of canister {} to {}: {}", canister_id, url, err
+            );
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to verify the response certificate: {}", err),
+            )
+        })?;
+
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() =
+            StatusCode::from_u16(http_response.status_code).map_err(|err| {
+                (
+                    StatusCode::BAD_GATEWAY,
+                    format!("Invalid status code: {}", err),
+                )
+            })?;
+        for (name, value) in http_response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Executes an anonymous query and returns the reply.
+    async fn query(
+        &self,
+        receiver: CanisterId,
+        method_name: String,
+        method_payload: Vec<u8>,
+    ) -> Result<Vec<u8>, (StatusCode, String)> {
+        let query = UserQuery {
+            source: UserId::from(PrincipalId::new_anonymous()),
+            receiver,
+            method_name,
+            method_payload,
+            ingress_expiry: current_time_and_expiry_time().1.as_nanos_since_unix_epoch(),
+            nonce: None,
+        };
+        let delegation_from_nns = self.delegation_from_nns.read().unwrap().clone();
+        let (response, _height) = self
+            .query_execution_service
+            .clone()
+            .oneshot((query, delegation_from_nns))
+            .await
+            .map_err(|err| {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!("Failed to execute the query: {}", err),
+                )
+            })?;
+        match response {
+            HttpQueryResponse::Replied { reply } => Ok(reply.arg.0),
+            HttpQueryResponse::Rejected {
+                reject_code,
+                reject_message,
+            } => Err((
+                StatusCode::BAD_GATEWAY,
+                format!(
+                    "The query was rejected with code {}: {}",
+                    reject_code, reject_message
+                ),
+            )),
+        }
+    }
+
+    /// Returns the public key of this subnet, which signs the certificates of
+    /// its canisters.
+    fn subnet_public_key(&self) -> Result<ThresholdSigPublicKey, (StatusCode, String)> {
+        let registry_version = self.registry_client.get_latest_version();
+        match self
+            .registry_client
+            .get_threshold_signing_public_key_for_subnet(self.subnet_id, registry_version)
+        {
+            Ok(Some(public_key)) => Ok(public_key),
+            Ok(None) => Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!(
+                    "No public key for subnet {} at registry version {}",
+                    self.subnet_id, registry_version
+                ),
+            )),
+            Err(err) => Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("Failed to get the subnet public key: {}", err),
+            )),
+        }
+    }
+}
+
+/// Splits `/http/<canister_id>/<url>` into the canister ID and the URL that is
+/// passed to the canister, which keeps its leading `/`.
+fn parse_gateway_path(path: &str) -> Result<(CanisterId, String), String> {
+    let rest = path
+        .strip_prefix(HTTP_GATEWAY_PATH_PREFIX)
+        .ok_or_else(|| format!("Expected a path starting with {}", HTTP_GATEWAY_PATH_PREFIX))?;
+    let (canister_id, url) = match rest.find(|c| c == '/' || c == '?') {
+        Some(index) => (&rest[..index], rest[index..].to_string()),
+        None => (rest, String::new()),
+    };
+    let canister_id = PrincipalId::from_str(canister_id)
+        .map_err(|err| format!("Invalid canister id {}: {}", canister_id, err))
+        .and_then(|principal_id| {
+            CanisterId::try_from(principal_id)
+                .map_err(|err| format!("Invalid canister id {}: {:?}", canister_id, err))
+        })?;
+    let url = if url.starts_with('/') {
+        url
+    } else {
+        format!("/{}", url)
+    };
+    Ok((canister_id, url))
+}
+
+/// Computes the id of a candid field name.
+fn idl_hash(name: &str) -> u32 {
+    name.bytes().fold(0u32, |hash, b| {
+        hash.wrapping_mul(223).wrapping_add(b as u32)
+    })
+}
+
+/// Returns the value of the field `name` of a candid record.
+fn record_field<'a>(value: &'a IDLValue, name: &str) -> Option<&'a IDLValue> {
+    match value {
+        IDLValue::Record(fields) => fields
+            .iter()
+            .find(|field| field.id.get_id() == idl_hash(name))
+            .map(|field| &field.val),
+        _ => None,
+    }
+}
+
+/// Returns the content of an optional candid value, or `None` if it is `null`.
+fn opt_value(value: &IDLValue) -> Option<&IDLValue> {
+    match value {
+        IDLValue::Opt(value) => Some(value.as_ref()),
+        _ => None,
+    }
+}
+
+/// Decodes the record in the candid-encoded `reply`.
+fn decode_record(reply: &[u8]) -> Result<IDLValue, String> {
+    let mut args = IDLArgs::from_bytes(reply)
+        .map_err(|err| format!("Failed to decode the reply: {}", err))?
+        .args;
+    if args.len() != 1 {
+        return Err(format!(
+            "Expected a single reply value but got {}",
+            args.len()
+        ));
+    }
+    Ok(args.remove(0))
+}
+
+/// Extracts the `Callback` streaming strategy of the optional field `field`
+/// of the record in the candid-encoded `reply`.
+fn streaming_callback(reply: &[u8], field: &str) -> Result<Option<StreamingCallback>, String> {
+    let record = decode_record(reply)?;
+    let strategy = match record_field(&record, field).and_then(opt_value) {
+        Some(strategy) => strategy,
+        None => return Ok(None),
+    };
+    let callback = match strategy {
+        IDLValue::Variant(variant) if variant.0.id.get_id() == idl_hash("Callback") => {
+            &variant.0.val
+        }
+        _ => return Err("Unsupported streaming strategy".to_string()),
+    };
+    let (principal, method_name) = match record_field(callback, "callback") {
+        Some(IDLValue::Func(principal, method_name)) => (principal, method_name),
+        _ => return Err("Malformed streaming callback".to_string()),
+    };
+    let token =
+        record_field(callback, "token").ok_or_else(|| "Missing streaming token".to_string())?;
+    let canister_id = PrincipalId::try_from(principal.as_slice())
+        .map_err(|err| format!("Invalid streaming callback principal: {}", err))
+        .and_then(|principal_id| {
+            CanisterId::try_from(principal_id)
+                .map_err(|err| format!("Invalid streaming callback canister: {:?}", err))
+        })?;
+    Ok(Some(StreamingCallback {
+        canister_id,
+        method_name: method_name.clone(),
+        token: token.clone(),
+    }))
+}
+
+/// Returns the callback for the next chunk, given the candid-encoded `reply`
+/// of `callback`, or `None` if `reply` contains the last chunk.
+fn streaming_callback_from_token(
+    reply: &[u8],
+    canister_id: CanisterId,
+    callback: StreamingCallback,
+) -> Result<Option<StreamingCallback>, String> {
+    let record = decode_record(reply)?;
+    Ok(record_field(&record, "token")
+        .and_then(opt_value)
+        .map(|token| StreamingCallback {
+            canister_id,
+            method_name: callback.method_name,
+            token: token.clone(),
+        }))
+}
+
+/// Parses the `IC-Certificate` header of the form
+/// `certificate=:<base64>:, tree=:<base64>:` into the certificate and the
+/// tree.
+fn parse_certificate_header(header: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let mut certificate = None;
+    let mut tree = None;
+    for field in header.split(',') {
+        let (name, value) = match field.trim().split_once('=') {
+            Some(name_and_value) => name_and_value,
+            None => continue,
+        };
+        let value = value
+            .strip_prefix(':')
+            .and_then(|value| value.strip_suffix(':'))
+            .ok_or_else(|| format!("Malformed value of {}", name))?;
+        let value =
+            base64::decode(value).map_err(|err| format!("Malformed value of {}: {}", name, err))?;
+        match name.trim() {
+            "certificate" => certificate = Some(value),
+            "tree" => tree = Some(value),
+            _ => (),
+        }
+    }
+    match (certificate, tree) {
+        (Some(certificate), Some(tree)) => Ok((certificate, tree)),
+        _ => Err("The certificate header must contain a certificate and a tree".to_string()),
+    }
+}
+
+/// Verifies that `body` is certified for `url_path` by `certificate_header`:
+///  * the certificate is signed with `subnet_public_key`,
+///  * the time of the certificate is close to `now`,
+///  * the certified data of `canister_id` is the root hash of the tree, and
+///  * the tree maps `url_path` (or `/index.html`, as a fallback) under
+///    `http_assets` to the SHA-256 hash of `body`.
+///
+/// The certificate may carry a delegation from the NNS, but it is always
+/// signed with the key of the subnet executing the query.
+fn verify_certified_body(
+    certificate_header: &str,
+    canister_id: &CanisterId,
+    url_path: &str,
+    body: &[u8],
+    subnet_public_key: &ThresholdSigPublicKey,
+    now: Time,
+) -> Result<(), String> {
+    #[derive(Deserialize)]
+    struct ReplicaState {
+        time: Leb128EncodedU64,
+    }
+
+    let (certificate, tree) = parse_certificate_header(certificate_header)?;
+    let certificate: Certificate = serde_cbor::from_slice(&certificate)
+        .map_err(|err| format!("Failed to decode the certificate: {}", err))?;
+    let tree: MixedHashTree = serde_cbor::from_slice(&tree)
+        .map_err(|err| format!("Failed to decode the tree: {}", err))?;
+
+    let digest = CryptoHashOfPartialState::from(CryptoHash(certificate.tree.digest().to_vec()));
+    let signature =
+        CombinedThresholdSigOf::new(CombinedThresholdSig(certificate.signature.to_vec()));
+    verify_combined(
+        &CertificationContent::new(digest),
+        &signature,
+        subnet_public_key,
+    )
+    .map_err(|err| format!("Invalid certificate signature: {}", err))?;
+
+    let certified_tree = LabeledTree::<Vec<u8>>::try_from(certificate.tree)
+        .map_err(|err| format!("Malformed certificate tree: {:?}", err))?;
+    let replica_state = ReplicaState::deserialize(LabeledTreeDeserializer::new(&certified_tree))
+        .map_err(|err| format!("Failed to decode the certificate time: {}", err))?;
+    let certificate_time = Time::from_nanos_since_unix_epoch(replica_state.time.0);
+    let offset = MAX_CERTIFICATE_TIME_OFFSET.as_nanos() as u64;
+    let certificate_nanos = certificate_time.as_nanos_since_unix_epoch();
+    let now_nanos = now.as_nanos_since_unix_epoch();
+    if certificate_nanos.saturating_add(offset) < now_nanos
+        || certificate_nanos > now_nanos.saturating_add(offset)
+    {
+        return Err(format!(
+            "The certificate time {} is too far from the local time {}",
+            certificate_time, now
+        ));
+    }
+
+    match lookup_path(
+        &certified_tree,
+        &[
+            b"canister",
+            canister_id.get_ref().as_slice(),
+            b"certified_data",
+        ],
+    ) {
+        Some(LabeledTree::Leaf(certified_data)) if certified_data[..] == tree.digest().0[..] => (),
+        Some(LabeledTree::Leaf(_)) => {
+            return Err("The certified data does not match the tree".to_string())
+        }
+        _ => return Err(format!("No certified data for canister {}", canister_id)),
+    }
+
+    let asset_tree = LabeledTree::<Vec<u8>>::try_from(tree)
+        .map_err(|err| format!("Malformed asset tree: {:?}", err))?;
+    let body_hash = Sha256::hash(body);
+    match lookup_path(&asset_tree, &[b"http_assets", url_path.as_bytes()])
+        .or_else(|| lookup_path(&asset_tree, &[b"http_assets", b"/index.html"]))
+    {
+        Some(LabeledTree::Leaf(hash)) if hash[..] == body_hash[..] => Ok(()),
+        Some(LabeledTree::Leaf(_)) => Err(format!(
+            "The body hash does not match the certified hash of {}",
+            url_path
+        )),
+        _ => Err(format!("No certified hash for {}", url_path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_certified_vars_test_utils::{CertificateBuilder, CertificateData};
+    use ic_crypto_tree_hash::Label;
+
+    const CERTIFICATE_TIME: u64 = 1_600_000_000_000_000_000;
+
+    fn canister_id() -> CanisterId {
+        CanisterId::from_u64(1)
+    }
+
+    fn asset_tree(path: &str, body: &[u8]) -> MixedHashTree {
+        MixedHashTree::Labeled(
+            Label::from("http_assets"),
+            Box::new(MixedHashTree::Labeled(
+                Label::from(path),
+                Box::new(MixedHashTree::Leaf(Sha256::hash(body).to_vec())),
+            )),
+        )
+    }
+
+    // Returns the `IC-Certificate` header certifying `tree` and the key that
+    // signed the certificate.
+    fn certificate_header(tree: &MixedHashTree) -> (String, ThresholdSigPublicKey) {
+        let (_, public_key, certificate) = CertificateBuilder::new(CertificateData::CanisterData {
+            canister_id: canister_id(),
+            certified_data: tree.digest(),
+        })
+        .with_time(CERTIFICATE_TIME)
+        .build();
+        let header = format!(
+            "certificate=:{}:, tree=:{}:",
+            base64::encode(&certificate),
+            base64::encode(&serde_cbor::to_vec(tree).unwrap())
+        );
+        (header, public_key)
+    }
+
+    fn now() -> Time {
+        Time::from_nanos_since_unix_epoch(CERTIFICATE_TIME)
+    }
+
+    #[test]
+    fn parses_gateway_paths() {
+        let canister_id = canister_id();
+        assert_eq!(
+            parse_gateway_path(&format!("/http/{}/index.html?x=1", canister_id)),
+            Ok((canister_id, "/index.html?x=1".to_string()))
+        );
+        assert_eq!(
+            parse_gateway_path(&format!("/http/{}", canister_id)),
+            Ok((canister_id, "/".to_string()))
+        );
+        assert_eq!(
+            parse_gateway_path(&format!("/http/{}?x=1", canister_id)),
+            Ok((canister_id, "/?x=1".to_string()))
+        );
+        assert!(parse_gateway_path("/http/not-a-principal/index.html").is_err());
+    }
+
+    #[test]
+    fn parses_certificate_header() {
+        assert_eq!(
+            parse_certificate_header("certificate=:AQI=:, tree=:AwQ=:"),
+            Ok((vec![1, 2], vec![3, 4]))
+        );
+        assert!(parse_certificate_header("certificate=:AQI=:").is_err());
+        assert!(parse_certificate_header("certificate=AQI=, tree=:AwQ=:").is_err());
+    }
+
+    #[test]
+    fn accepts_certified_body() {
+        let tree = asset_tree("/index.html", b"hello");
+        let (header, public_key) = certificate_header(&tree);
+        assert_eq!(
+            verify_certified_body(
+                &header,
+                &canister_id(),
+                "/index.html",
+                b"hello",
+                &public_key,
+                now()
+            ),
+            Ok(())
+        );
+        // Unknown paths fall back to /index.html.
+        assert_eq!(
+            verify_certified_body(
+                &header,
+                &canister_id(),
+                "/app",
+                b"hello",
+                &public_key,
+                now()
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_uncertified_body() {
+        let tree = asset_tree("/index.html", b"hello");
+        let (header, public_key) = certificate_header(&tree);
+        assert!(verify_certified_body(
+            &header,
+            &canister_id(),
+            "/index.html",
+            b"goodbye",
+            &public_key,
+            now()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_certificate_of_other_canister() {
+        let tree = asset_tree("/index.html", b"hello");
+        let (header, public_key) = certificate_header(&tree);
+        assert!(verify_certified_body(
+            &header,
+            &CanisterId::from_u64(2),
+            "/index.html",
+            b"hello",
+            &public_key,
+            now()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_certificate_signed_by_other_key() {
+        let tree = asset_tree("/index.html", b"hello");
+        let (header, _) = certificate_header(&tree);
+        let (_, other_public_key) = certificate_header(&tree);
+        assert!(verify_certified_body(
+            &header,
+            &canister_id(),
+            "/index.html",
+            b"hello",
+            &other_public_key,
+            now()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_stale_certificate() {
+        let tree = asset_tree("/index.html", b"hello");
+        let (header, public_key) = certificate_header(&tree);
+        let later = now() + MAX_CERTIFICATE_TIME_OFFSET + Duration::from_secs(1);
+        assert!(verify_certified_body(
+            &header,
+            &canister_id(),
+            "/index.html",
+            b"hello",
+            &public_key,
+            later
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn extracts_streaming_callbacks() {
+        #[derive(CandidType)]
+        struct Token {
+            index: u64,
+        }
+        #[derive(CandidType)]
+        enum StreamingStrategy {
+            Callback {
+                callback: candid::Func,
+                token: Token,
+            },
+        }
+        #[derive(CandidType)]
+        struct Response {
+            status_code: u16,
+            headers: Vec<(String, String)>,
+            body: ByteBuf,
+            streaming_strategy: Option<StreamingStrategy>,
+        }
+        #[derive(CandidType)]
+        struct CallbackResponse {
+            body: ByteBuf,
+            token: Option<Token>,
+        }
+
+        let principal = candid::Principal::from_slice(canister_id().get_ref().as_slice());
+        let reply = Encode!(&Response {
+            status_code: 200,
+            headers: vec![],
+            body: ByteBuf::from(b"hello".to_vec()),
+            streaming_strategy: Some(StreamingStrategy::Callback {
+                callback: candid::Func {
+                    principal,
+                    method: "next_chunk".to_string(),
+                },
+                token: Token { index: 1 },
+            }),
+        })
+        .unwrap();
+        let callback = streaming_callback(&reply, "streaming_strategy")
+            .unwrap()
+            .unwrap();
+        assert_eq!(callback.canister_id, canister_id());
+        assert_eq!(callback.method_name, "next_chunk");
+        assert_eq!(
+            IDLArgs::new(&[callback.token.clone()]).to_bytes().unwrap(),
+            Encode!(&Token { index: 1 }).unwrap()
+        );
+
+        let last_chunk = Encode!(&CallbackResponse {
+            body: ByteBuf::from(b"world".to_vec()),
+            token: None,
+        })
+        .unwrap();
+        assert_eq!(
+            streaming_callback_from_token(&last_chunk, canister_id(), callback),
+            Ok(None)
+        );
+
+        let no_streaming = Encode!(&Response {
+            status_code: 200,
+            headers: vec![],
+            body: ByteBuf::from(b"hello".to_vec()),
+            streaming_strategy: None,
+        })
+        .unwrap();
+        assert_eq!(
+            streaming_callback(&no_streaming, "streaming_strategy"),
+            Ok(None)
+        );
+    }
+}
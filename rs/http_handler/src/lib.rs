@@ -10,9 +10,11 @@ mod call;
 mod catch_up_package;
 mod common;
 mod dashboard;
+mod http_gateway;
 mod metrics;
 mod pprof;
 mod query;
+mod rate_limiter;
 mod read_state;
 mod status;
 mod types;
@@ -23,10 +25,12 @@ use crate::{
     catch_up_package::CatchUpPackageService,
     common::{get_cors_headers, make_plaintext_response, map_box_error_to_response},
     dashboard::DashboardService,
+    http_gateway::{HttpGatewayService, HTTP_GATEWAY_PATH_PREFIX},
     metrics::{
         LABEL_REQUEST_TYPE, LABEL_STATUS, LABEL_TYPE, REQUESTS_LABEL_NAMES, REQUESTS_NUM_LABELS,
     },
-    query::QueryService,
+    query::{min_certified_height, QueryService},
+    rate_limiter::IngressRateLimiter,
     read_state::ReadStateService,
     status::StatusService,
     types::*,
@@ -168,6 +172,7 @@ struct HttpHandler {
     malicious_flags: MaliciousFlags,
     delegation_from_nns: Arc<RwLock<Option<CertificateDelegation>>>,
     health_status: Arc<RwLock<ReplicaHealthStatus>>,
+    ingress_rate_limiter: Arc<IngressRateLimiter>,
 }
 
 // Crates a detached tokio blocking task that initializes the server (reading
@@ -284,6 +289,10 @@ pub async fn start_server(
     let listen_addr = config.listen_addr;
     let port_file_path = config.port_file_path.clone();
 
+    let ingress_rate_limiter = Arc::new(IngressRateLimiter::new(
+        config.ingress_rate_limit_per_sender.as_ref(),
+        config.ingress_rate_limit_per_canister.as_ref(),
+    ));
     let http_handler = HttpHandler {
         log: log.clone(),
         config,
@@ -301,6 +310,7 @@ pub async fn start_server(
         malicious_flags,
         delegation_from_nns: Arc::new(RwLock::new(None)),
         health_status: Arc::new(RwLock::new(ReplicaHealthStatus::Starting)),
+        ingress_rate_limiter,
     };
 
     info!(log, "Starting HTTP server...");
@@ -548,19 +558,44 @@ async fn make_router(
 ) -> ResponseWithTimer {
     use http::method::Method;
 
-    let query_service = BoxService::new(
-        ServiceBuilder::new()
-            .layer(BodyReceiverLayer::default())
-            .service(QueryService::new(
-                http_handler.log.clone(),
-                metrics.clone(),
-                Arc::clone(&http_handler.health_status),
-                Arc::clone(&http_handler.delegation_from_nns),
-                Arc::clone(&http_handler.validator),
-                Arc::clone(&http_handler.registry_client),
-                http_handler.query_execution_service.clone(),
-                http_handler.malicious_flags.clone(),
-            )),
+    if req.uri().path().starts_with(HTTP_GATEWAY_PATH_PREFIX) {
+        set_timer_labels(
+            &mut timer,
+            RequestType::HttpGateway,
+            ApiReqType::HttpGateway,
+        );
+        if !http_handler.config.http_gateway_enabled {
+            return (
+                make_plaintext_response(
+                    StatusCode::NOT_FOUND,
+                    "The HTTP gateway is not enabled on this replica.".to_string(),
+                ),
+                timer,
+            );
+        }
+        let http_gateway = HttpGatewayService::new(
+            http_handler.log,
+            http_handler.subnet_id,
+            http_handler.health_status,
+            http_handler.delegation_from_nns,
+            http_handler.registry_client,
+            http_handler.query_execution_service,
+        );
+        return (http_gateway.handle(req).await, timer);
+    }
+
+    let max_delegations = http_handler.config.max_delegations;
+    let query = QueryService::new(
+        http_handler.log.clone(),
+        metrics.clone(),
+        Arc::clone(&http_handler.health_status),
+        Arc::clone(&http_handler.delegation_from_nns),
+        Arc::clone(&http_handler.validator),
+        Arc::clone(&http_handler.registry_client),
+        http_handler.query_execution_service.clone(),
+        Arc::clone(&http_handler.state_reader),
+        max_delegations,
+        http_handler.malicious_flags.clone(),
     );
     let status_service = BoxService::new(StatusService::new(
         http_handler.log.clone(),
@@ -593,23 +628,35 @@ async fn make_router(
                 Arc::clone(&http_handler.state_reader),
                 Arc::clone(&http_handler.validator),
                 Arc::clone(&http_handler.registry_client),
+                max_delegations,
                 http_handler.malicious_flags.clone(),
             )),
     );
-    let call_service = BoxService::new(
+    let call = CallService::new(
+        http_handler.log.clone(),
+        metrics.clone(),
+        http_handler.subnet_id,
+        Arc::clone(&http_handler.registry_client),
+        Arc::clone(&http_handler.validator),
+        http_handler.ingress_sender,
+        http_handler.ingress_filter,
+        http_handler.ingress_rate_limiter,
+        max_delegations,
+        http_handler.malicious_flags.clone(),
+    );
+    let sync_call_service = BoxService::new(
         ServiceBuilder::new()
             .layer(BodyReceiverLayer::default())
-            .service(CallService::new(
-                http_handler.log.clone(),
-                metrics.clone(),
-                http_handler.subnet_id,
-                Arc::clone(&http_handler.registry_client),
-                Arc::clone(&http_handler.validator),
-                http_handler.ingress_sender,
-                http_handler.ingress_filter,
-                http_handler.malicious_flags.clone(),
+            .service(call.clone().with_sync_response(
+                Arc::clone(&http_handler.state_reader),
+                Arc::clone(&http_handler.delegation_from_nns),
             )),
     );
+    let call_service = BoxService::new(
+        ServiceBuilder::new()
+            .layer(BodyReceiverLayer::default())
+            .service(call),
+    );
 
     metrics
         .protocol_version_total
@@ -650,9 +697,23 @@ async fn make_router(
                     set_timer_labels(&mut timer, RequestType::Submit, ApiReqType::Call);
                     call_service
                 }
+                ["", "api", "v3", "canister", _, "call"] => {
+                    set_timer_labels(&mut timer, RequestType::Submit, ApiReqType::SyncCall);
+                    sync_call_service
+                }
                 ["", "api", "v2", "canister", _, "query"] => {
                     set_timer_labels(&mut timer, RequestType::Query, ApiReqType::Query);
-                    query_service
+                    let min_certified_height = match min_certified_height(req.headers()) {
+                        Ok(min_certified_height) => min_certified_height,
+                        Err(err) => {
+                            return (make_plaintext_response(StatusCode::BAD_REQUEST, err), timer)
+                        }
+                    };
+                    BoxService::new(
+                        ServiceBuilder::new()
+                            .layer(BodyReceiverLayer::default())
+                            .service(query.with_min_certified_height(min_certified_height)),
+                    )
                 }
                 ["", "api", "v2", "canister", _, "read_state"] => {
                     set_timer_labels(&mut timer, RequestType::ReadState, ApiReqType::ReadState);
@@ -8,6 +8,7 @@ use tokio::time::Instant;
 
 pub const LABEL_DETAIL: &str = "detail";
 pub const LABEL_PROTOCOL: &str = "protocol";
+pub const LABEL_REASON: &str = "reason";
 pub const LABEL_REQUEST_TYPE: &str = "request_type";
 pub const LABEL_STATUS: &str = "status";
 pub const LABEL_TYPE: &str = "type";
@@ -29,6 +30,7 @@ pub(crate) struct HttpHandlerMetrics {
     pub(crate) protocol_version_total: IntCounterVec,
     pub(crate) connections: IntGauge,
     pub(crate) connections_total: IntCounter,
+    pub(crate) ingress_throttled_total: IntCounterVec,
     connection_setup_duration: HistogramVec,
 }
 
@@ -79,6 +81,11 @@ impl HttpHandlerMetrics {
                 "replica_http_tcp_connections_total",
                 "Total number of accepted TCP connections."
             ),
+            ingress_throttled_total: metrics_registry.int_counter_vec(
+                "replica_http_ingress_throttled_total",
+                "Count of ingress messages rejected by the rate limits, by reason (the exceeded limit).",
+                &[LABEL_REASON],
+            ),
             connection_setup_duration: metrics_registry.histogram_vec(
                 "replica_http_connection_setup_duration_seconds",
                 "HTTP connection setup durations, by status and detail (protocol on status=\"success\", error type on status=\"error\").",
@@ -5,20 +5,22 @@ use crate::{
     types::{ApiReqType, RequestType},
     HttpHandlerMetrics, ReplicaHealthStatus, UNKNOWN_LABEL,
 };
-use futures_util::FutureExt;
-use hyper::{Body, Response, StatusCode};
+use hyper::{header, Body, HeaderMap, Response, StatusCode};
 use ic_interfaces::{
     crypto::IngressSigVerifier, execution_environment::QueryExecutionService,
     registry::RegistryClient,
 };
+use ic_interfaces_state_manager::StateReader;
 use ic_logger::{trace, ReplicaLogger};
+use ic_replicated_state::ReplicatedState;
 use ic_types::{
     malicious_flags::MaliciousFlags,
     messages::{
-        CertificateDelegation, HttpQueryContent, HttpRequest, HttpRequestEnvelope,
-        SignedRequestBytes, UserQuery,
+        CertificateDelegation, HttpQueryContent, HttpQueryResponse, HttpRequest,
+        HttpRequestEnvelope, SignedRequestBytes, UserQuery,
     },
     time::current_time,
+    Height,
 };
 use ic_validator::get_authorized_canisters;
 use std::convert::TryFrom;
@@ -26,7 +28,27 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::{Arc, RwLock};
 use std::task::{Context, Poll};
-use tower::{BoxError, Service};
+use std::time::Duration;
+use tower::{BoxError, Service, ServiceExt};
+
+/// Request header with the minimum certified height that a query must be
+/// executed on, for clients to read their own writes.
+pub(crate) const MIN_CERTIFIED_HEIGHT_HEADER: &str = "x-ic-min-certified-height";
+
+/// Response header with the certified height that a query was executed on.
+pub(crate) const CERTIFIED_HEIGHT_HEADER: &str = "x-ic-certified-height";
+
+/// How long a query waits for the minimum certified height it asks for.
+const MIN_CERTIFIED_HEIGHT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the certified height is checked while waiting for it.
+const MIN_CERTIFIED_HEIGHT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How far the minimum certified height of a query may be ahead of the
+/// latest certified height. Heights further ahead cannot be reached within
+/// [`MIN_CERTIFIED_HEIGHT_TIMEOUT`], so such queries are rejected right away
+/// instead of holding a connection until the timeout.
+const MAX_MIN_CERTIFIED_HEIGHT_LEAD: u64 = 10;
 
 #[derive(Clone)]
 pub(crate) struct QueryService {
@@ -37,6 +59,9 @@ pub(crate) struct QueryService {
     validator: Arc<dyn IngressSigVerifier + Send + Sync>,
     registry_client: Arc<dyn RegistryClient>,
     query_execution_service: QueryExecutionService,
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    min_certified_height: Option<Height>,
+    max_delegations: usize,
     malicious_flags: MaliciousFlags,
 }
 
@@ -50,6 +75,8 @@ impl QueryService {
         validator: Arc<dyn IngressSigVerifier + Send + Sync>,
         registry_client: Arc<dyn RegistryClient>,
         query_execution_service: QueryExecutionService,
+        state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        max_delegations: usize,
         malicious_flags: MaliciousFlags,
     ) -> QueryService {
         Self {
@@ -60,9 +87,70 @@ impl QueryService {
             validator,
             registry_client,
             query_execution_service,
+            state_reader,
+            min_certified_height: None,
+            max_delegations,
             malicious_flags,
         }
     }
+
+    /// Makes the service execute queries only once the state at
+    /// `min_certified_height` is certified, if given.
+    pub(crate) fn with_min_certified_height(
+        mut self,
+        min_certified_height: Option<Height>,
+    ) -> Self {
+        self.min_certified_height = min_certified_height;
+        self
+    }
+}
+
+/// Parses the optional [`MIN_CERTIFIED_HEIGHT_HEADER`] of a query.
+pub(crate) fn min_certified_height(headers: &HeaderMap) -> Result<Option<Height>, String> {
+    headers
+        .get(MIN_CERTIFIED_HEIGHT_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Height::from)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid {} header, expected an unsigned integer.",
+                        MIN_CERTIFIED_HEIGHT_HEADER
+                    )
+                })
+        })
+        .transpose()
+}
+
+/// Waits until the state at `height` is certified, and returns whether it is
+/// within `timeout`.
+async fn wait_for_certified_height(
+    state_reader: &dyn StateReader<State = ReplicatedState>,
+    height: Height,
+    timeout: Duration,
+) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if state_reader.latest_certified_height() >= height {
+            return true;
+        }
+        if tokio::time::Instant::now() + MIN_CERTIFIED_HEIGHT_POLL_INTERVAL >= deadline {
+            return false;
+        }
+        tokio::time::sleep(MIN_CERTIFIED_HEIGHT_POLL_INTERVAL).await;
+    }
+}
+
+/// Returns whether `min_certified_height` is too far ahead of
+/// `latest_certified_height` to be waited for.
+fn is_too_far_ahead(min_certified_height: Height, latest_certified_height: Height) -> bool {
+    min_certified_height.get()
+        > latest_certified_height
+            .get()
+            .saturating_add(MAX_MIN_CERTIFIED_HEIGHT_LEAD)
 }
 
 impl Service<Vec<u8>> for QueryService {
@@ -72,6 +160,11 @@ impl Service<Vec<u8>> for QueryService {
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // A query that waits for a certified height only acquires a slot in
+        // the query execution service once it is done waiting, in `call`.
+        if self.min_certified_height.is_some() {
+            return Poll::Ready(Ok(()));
+        }
         self.query_execution_service.poll_ready(cx)
     }
 
@@ -126,6 +219,7 @@ impl Service<Vec<u8>> for QueryService {
             self.validator.as_ref(),
             current_time(),
             self.registry_client.get_latest_version(),
+            self.max_delegations,
             &self.malicious_flags,
         ) {
             Ok(targets) => {
@@ -140,13 +234,128 @@ impl Service<Vec<u8>> for QueryService {
             }
         };
 
-        Box::pin(
-            self.query_execution_service
-                .call((query.clone(), delegation_from_nns))
-                .map(|result| {
-                    let v = result?;
-                    Ok(cbor_response(&v))
-                }),
-        )
+        let query = query.clone();
+        let min_certified_height = match self.min_certified_height {
+            Some(min_certified_height) => min_certified_height,
+            None => {
+                // Move the service that is ready into the future.
+                let mut query_execution_service = std::mem::replace(
+                    &mut self.query_execution_service,
+                    self.query_execution_service.clone(),
+                );
+                return Box::pin(async move {
+                    let (response, height) = query_execution_service
+                        .call((query, delegation_from_nns))
+                        .await?;
+                    Ok(make_query_response(&response, height))
+                });
+            }
+        };
+
+        let latest_certified_height = self.state_reader.latest_certified_height();
+        if is_too_far_ahead(min_certified_height, latest_certified_height) {
+            let res = make_plaintext_response(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Certified height {} is too far ahead of the latest certified height {}.",
+                    min_certified_height, latest_certified_height
+                ),
+            );
+            return Box::pin(async move { Ok(res) });
+        }
+
+        // Wait for the certified height before acquiring a slot in the query
+        // execution service, so that waiting queries don't hold up others.
+        let query_execution_service = self.query_execution_service.clone();
+        let state_reader = Arc::clone(&self.state_reader);
+        Box::pin(async move {
+            if !wait_for_certified_height(
+                state_reader.as_ref(),
+                min_certified_height,
+                MIN_CERTIFIED_HEIGHT_TIMEOUT,
+            )
+            .await
+            {
+                return Ok(make_plaintext_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!(
+                        "Certified height {} was not reached within {:?}, latest certified height is {}.",
+                        min_certified_height,
+                        MIN_CERTIFIED_HEIGHT_TIMEOUT,
+                        state_reader.latest_certified_height()
+                    ),
+                ));
+            }
+            let (response, height) = query_execution_service
+                .oneshot((query, delegation_from_nns))
+                .await?;
+            Ok(make_query_response(&response, height))
+        })
+    }
+}
+
+/// Encodes the query `response`, with the certified height it was executed
+/// on, if any.
+fn make_query_response(response: &HttpQueryResponse, height: Option<Height>) -> Response<Body> {
+    let mut response = cbor_response(response);
+    if let Some(height) = height {
+        response.headers_mut().insert(
+            CERTIFIED_HEIGHT_HEADER,
+            header::HeaderValue::from(height.get()),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+    use ic_test_utilities::state_manager::FakeStateManager;
+
+    #[test]
+    fn parses_min_certified_height_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(min_certified_height(&headers), Ok(None));
+
+        headers.insert(MIN_CERTIFIED_HEIGHT_HEADER, HeaderValue::from(42_u64));
+        assert_eq!(min_certified_height(&headers), Ok(Some(Height::from(42))));
+
+        headers.insert(
+            MIN_CERTIFIED_HEIGHT_HEADER,
+            HeaderValue::from_static("latest"),
+        );
+        assert!(min_certified_height(&headers).is_err());
+    }
+
+    #[test]
+    fn rejects_heights_far_ahead_of_latest_certified_height() {
+        let latest = Height::from(100);
+        assert!(!is_too_far_ahead(Height::from(0), latest));
+        assert!(!is_too_far_ahead(
+            Height::from(100 + MAX_MIN_CERTIFIED_HEIGHT_LEAD),
+            latest
+        ));
+        assert!(is_too_far_ahead(
+            Height::from(100 + MAX_MIN_CERTIFIED_HEIGHT_LEAD + 1),
+            latest
+        ));
+        assert!(!is_too_far_ahead(
+            Height::from(u64::MAX),
+            Height::from(u64::MAX - 1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn waiting_for_certified_height_times_out() {
+        let state_manager = FakeStateManager::new();
+        assert!(
+            wait_for_certified_height(&state_manager, Height::from(0), Duration::from_millis(0))
+                .await
+        );
+        assert!(
+            !wait_for_certified_height(&state_manager, Height::from(1), Duration::from_millis(200))
+                .await
+        );
     }
 }
@@ -0,0 +1,283 @@
+//! Token bucket rate limiting of ingress messages, per sender and per target
+//! canister.
+
+use ic_config::http_handler::RateLimit;
+use ic_types::{CanisterId, UserId};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// If a limiter tracks more keys than this, the buckets that are full again
+/// are dropped, as they are equivalent to fresh buckets.
+const MAX_TRACKED_KEYS: usize = 100_000;
+
+/// Why a request was throttled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ThrottleReason {
+    Sender,
+    Canister,
+}
+
+impl ThrottleReason {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ThrottleReason::Sender => "sender",
+            ThrottleReason::Canister => "canister",
+        }
+    }
+}
+
+/// A throttled request: why, and after how long it may be retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Throttled {
+    pub(crate) reason: ThrottleReason,
+    pub(crate) retry_after: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A set of token buckets, one per key, all with the same limit.
+struct KeyedRateLimiter<K> {
+    requests_per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Eq + Hash + Clone> KeyedRateLimiter<K> {
+    fn new(limit: &RateLimit) -> Self {
+        Self {
+            requests_per_second: limit.requests_per_second as f64,
+            burst: limit.burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.requests_per_second).min(self.burst);
+        bucket.last_refill = now;
+    }
+
+    /// Returns the bucket of `key` in `buckets` if it has a token available,
+    /// or how long it takes until a token is available. The token is not
+    /// taken.
+    fn available_bucket<'a>(
+        &self,
+        buckets: &'a mut HashMap<K, Bucket>,
+        key: &K,
+        now: Instant,
+    ) -> Result<&'a mut Bucket, Duration> {
+        if buckets.len() >= MAX_TRACKED_KEYS && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| {
+                self.refill(bucket, now);
+                bucket.tokens < self.burst
+            });
+        }
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: self.burst,
+            last_refill: now,
+        });
+        self.refill(bucket, now);
+        if bucket.tokens >= 1.0 {
+            return Ok(bucket);
+        }
+        if self.requests_per_second <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / self.requests_per_second,
+        ))
+    }
+}
+
+/// Limits the rate of ingress messages per sender and per target canister, as
+/// configured.
+pub(crate) struct IngressRateLimiter {
+    per_sender: Option<KeyedRateLimiter<UserId>>,
+    per_canister: Option<KeyedRateLimiter<CanisterId>>,
+}
+
+impl IngressRateLimiter {
+    pub(crate) fn new(per_sender: Option<&RateLimit>, per_canister: Option<&RateLimit>) -> Self {
+        Self {
+            per_sender: per_sender.map(KeyedRateLimiter::new),
+            per_canister: per_canister.map(KeyedRateLimiter::new),
+        }
+    }
+
+    /// Accounts for a message from `sender` to `canister_id` at `now`, or
+    /// returns why it is throttled if either limit is exceeded.
+    pub(crate) fn check(
+        &self,
+        sender: &UserId,
+        canister_id: &CanisterId,
+        now: Instant,
+    ) -> Result<(), Throttled> {
+        let mut sender_buckets = self
+            .per_sender
+            .as_ref()
+            .map(|limiter| (limiter, limiter.buckets.lock().unwrap()));
+        let mut canister_buckets = self
+            .per_canister
+            .as_ref()
+            .map(|limiter| (limiter, limiter.buckets.lock().unwrap()));
+
+        let sender_bucket = sender_buckets
+            .as_mut()
+            .map(|(limiter, buckets)| limiter.available_bucket(buckets, sender, now))
+            .transpose()
+            .map_err(|retry_after| Throttled {
+                reason: ThrottleReason::Sender,
+                retry_after,
+            })?;
+        let canister_bucket = canister_buckets
+            .as_mut()
+            .map(|(limiter, buckets)| limiter.available_bucket(buckets, canister_id, now))
+            .transpose()
+            .map_err(|retry_after| Throttled {
+                reason: ThrottleReason::Canister,
+                retry_after,
+            })?;
+
+        // The tokens are only taken once both limits admit the message, so that
+        // a message throttled per canister does not count against its sender.
+        for bucket in sender_bucket.into_iter().chain(canister_bucket) {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities::types::ids::{canister_test_id, user_test_id};
+
+    fn limit(requests_per_second: u32, burst: u32) -> RateLimit {
+        RateLimit {
+            requests_per_second,
+            burst,
+        }
+    }
+
+    #[test]
+    fn unlimited_without_config() {
+        let limiter = IngressRateLimiter::new(None, None);
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert_eq!(
+                limiter.check(&user_test_id(1), &canister_test_id(1), now),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn throttles_sender_after_burst() {
+        let limiter = IngressRateLimiter::new(Some(&limit(2, 3)), None);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.check(&user_test_id(1), &canister_test_id(1), now),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            limiter.check(&user_test_id(1), &canister_test_id(1), now),
+            Err(Throttled {
+                reason: ThrottleReason::Sender,
+                retry_after: Duration::from_millis(500),
+            })
+        );
+        // Other senders are not affected.
+        assert_eq!(
+            limiter.check(&user_test_id(2), &canister_test_id(1), now),
+            Ok(())
+        );
+        // The bucket refills over time.
+        let later = now + Duration::from_millis(500);
+        assert_eq!(
+            limiter.check(&user_test_id(1), &canister_test_id(1), later),
+            Ok(())
+        );
+        assert!(limiter
+            .check(&user_test_id(1), &canister_test_id(1), later)
+            .is_err());
+    }
+
+    #[test]
+    fn throttles_canister_across_senders() {
+        let limiter = IngressRateLimiter::new(Some(&limit(10, 10)), Some(&limit(1, 2)));
+        let now = Instant::now();
+        assert_eq!(
+            limiter.check(&user_test_id(1), &canister_test_id(1), now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.check(&user_test_id(2), &canister_test_id(1), now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter
+                .check(&user_test_id(3), &canister_test_id(1), now)
+                .map_err(|throttled| throttled.reason),
+            Err(ThrottleReason::Canister)
+        );
+        assert_eq!(
+            limiter.check(&user_test_id(3), &canister_test_id(2), now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn canister_throttling_does_not_use_sender_quota() {
+        let limiter = IngressRateLimiter::new(Some(&limit(1, 2)), Some(&limit(1, 1)));
+        let now = Instant::now();
+        assert_eq!(
+            limiter.check(&user_test_id(1), &canister_test_id(1), now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter
+                .check(&user_test_id(1), &canister_test_id(1), now)
+                .map_err(|throttled| throttled.reason),
+            Err(ThrottleReason::Canister)
+        );
+        // The throttled message did not take a token of the sender.
+        assert_eq!(
+            limiter.check(&user_test_id(1), &canister_test_id(2), now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter
+                .check(&user_test_id(1), &canister_test_id(3), now)
+                .map_err(|throttled| throttled.reason),
+            Err(ThrottleReason::Sender)
+        );
+    }
+
+    #[test]
+    fn zero_rate_never_refills() {
+        let limiter = IngressRateLimiter::new(Some(&limit(0, 1)), None);
+        let now = Instant::now();
+        assert_eq!(
+            limiter.check(&user_test_id(1), &canister_test_id(1), now),
+            Ok(())
+        );
+        assert_eq!(
+            limiter
+                .check(
+                    &user_test_id(1),
+                    &canister_test_id(1),
+                    now + Duration::from_secs(3600)
+                )
+                .map_err(|throttled| throttled.retry_after),
+            Err(Duration::MAX)
+        );
+    }
+}
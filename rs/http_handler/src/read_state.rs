@@ -42,6 +42,7 @@ pub(crate) struct ReadStateService {
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     validator: Arc<dyn IngressSigVerifier + Send + Sync>,
     registry_client: Arc<dyn RegistryClient>,
+    max_delegations: usize,
     malicious_flags: MaliciousFlags,
 }
 
@@ -55,6 +56,7 @@ impl ReadStateService {
         state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
         validator: Arc<dyn IngressSigVerifier + Send + Sync>,
         registry_client: Arc<dyn RegistryClient>,
+        max_delegations: usize,
         malicious_flags: MaliciousFlags,
     ) -> Self {
         Self {
@@ -65,6 +67,7 @@ impl ReadStateService {
             state_reader,
             validator,
             registry_client,
+            max_delegations,
             malicious_flags,
         }
     }
@@ -131,6 +134,7 @@ impl Service<Vec<u8>> for ReadStateService {
             self.validator.as_ref(),
             current_time(),
             self.registry_client.get_latest_version(),
+            self.max_delegations,
             &self.malicious_flags,
         ) {
             Ok(targets) => {
@@ -7,6 +7,8 @@
 pub(crate) enum ApiReqType {
     /// `call`
     Call,
+    /// `call` that waits for the certified response (`/api/v3`)
+    SyncCall,
     /// `query`
     Query,
     /// `read_state`
@@ -20,6 +22,7 @@ pub(crate) enum ApiReqType {
     PprofHome,
     PprofProfile,
     PprofFlamegraph,
+    HttpGateway,
     InvalidArgument,
 }
 
@@ -39,6 +42,8 @@ impl ApiReqType {
             PprofHome => "pprof_home",
             PprofProfile => "pprof_profile",
             PprofFlamegraph => "pprof_flamegraph",
+            HttpGateway => "http_gateway",
+            SyncCall => "sync_call",
         }
     }
 }
@@ -82,6 +87,7 @@ pub(crate) enum RequestType {
     PprofHome,
     PprofProfile,
     PprofFlamegraph,
+    HttpGateway,
 }
 
 impl RequestType {
@@ -100,6 +106,7 @@ impl RequestType {
             PprofHome => "pprof_home",
             PprofProfile => "pprof_profile",
             PprofFlamegraph => "pprof_flamegraph",
+            HttpGateway => "http_gateway",
         }
     }
 }
@@ -489,6 +489,7 @@ impl IcManager {
                 server_port: p2p_port,
                 queue_size: 256,
             }],
+            ..Default::default()
         });
         replica_config.state_manager = Some(StateManagerConfig::new(state_manager_root));
         replica_config.http_handler = Some(http_handler::ExternalConfig {
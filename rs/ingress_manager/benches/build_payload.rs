@@ -10,6 +10,7 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use ic_artifact_pool::ingress_pool::IngressPoolImpl;
+use ic_config::ingress_manager::Config as IngressManagerConfig;
 use ic_constants::MAX_INGRESS_TTL;
 use ic_ingress_manager::IngressManager;
 use ic_interfaces::{
@@ -105,6 +106,7 @@ where
                 no_op_logger(),
                 Arc::new(state_manager),
                 cycles_account_manager,
+                IngressManagerConfig::default(),
                 MaliciousFlags::default(),
             ),
             registry,
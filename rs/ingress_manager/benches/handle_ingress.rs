@@ -15,7 +15,9 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use ic_artifact_pool::ingress_pool::IngressPoolImpl;
-use ic_config::artifact_pool::ArtifactPoolConfig;
+use ic_config::{
+    artifact_pool::ArtifactPoolConfig, ingress_manager::Config as IngressManagerConfig,
+};
 use ic_constants::MAX_INGRESS_TTL;
 use ic_ingress_manager::IngressManager;
 use ic_interfaces::{
@@ -231,6 +233,7 @@ where
                 log.clone(),
                 Arc::new(state_manager),
                 cycles_account_manager,
+                IngressManagerConfig::default(),
                 MaliciousFlags::default(),
             );
             test(
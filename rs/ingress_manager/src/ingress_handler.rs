@@ -94,6 +94,7 @@ impl IngressHandler for IngressManager {
                 self.ingress_signature_crypto.as_ref(),
                 current_time,
                 registry_version,
+                self.max_delegations,
                 &self.malicious_flags,
             ) {
                 debug!(
@@ -28,7 +28,20 @@ use ic_types::{
     CanisterId, CountBytes, Cycles, Height, NumBytes, Time,
 };
 use ic_validator::{validate_request, RequestValidationError};
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+
+/// A payload holds at most this fraction of `max_ingress_messages_per_block`
+/// messages to the same canister, so that a canister flooded with ingress
+/// messages cannot fill every block.
+const MAX_INGRESS_MESSAGES_PER_CANISTER_SHARE: usize = 4;
+
+/// Up to this many times the messages and bytes that fit into a payload are
+/// considered as candidates, which bounds the selection work while leaving
+/// room to choose fairly between canisters.
+const CANDIDATES_PER_PAYLOAD_FACTOR: usize = 4;
 
 impl<'a> IngressSelector for IngressManager {
     fn get_ingress_payload(
@@ -76,45 +89,67 @@ impl<'a> IngressSelector for IngressManager {
             .get_ingress_message_settings(context.registry_version)
             .expect("Couldn't fetch ingress message parameters from the registry.");
 
-        // Select valid ingress messages and stop once the total size
-        // becomes greater than byte_limit.
-        let mut accumulated_size = 0;
-        let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
-        let mut num_messages = 0;
-
-        let messages_in_payload = self.ingress_pool.select_validated(
+        // Select the candidate messages in expiry order, at most
+        // `max_messages_per_canister` per target canister, and stop once there
+        // are enough candidates to choose from. Messages already included in
+        // past payloads are skipped, so that they do not take up the share of
+        // their canister.
+        let max_messages_per_canister = (settings.max_ingress_messages_per_block
+            / MAX_INGRESS_MESSAGES_PER_CANISTER_SHARE)
+            .max(1);
+        let max_candidates =
+            settings.max_ingress_messages_per_block * CANDIDATES_PER_PAYLOAD_FACTOR;
+        let max_candidate_bytes = byte_limit.get() as usize * CANDIDATES_PER_PAYLOAD_FACTOR;
+        let mut num_candidates = 0;
+        let mut candidate_bytes = 0;
+        let mut candidates_per_canister: BTreeMap<CanisterId, usize> = BTreeMap::new();
+        let mut skipped_for_fairness = 0;
+
+        let candidates = self.ingress_pool.select_validated(
             expiry_range,
-            Box::new(move |ingress_obj| {
-                let result = self.validate_ingress(
-                    IngressMessageId::from(ingress_obj),
-                    &ingress_obj.signed_ingress,
+            Box::new(|ingress_obj| {
+                if num_candidates >= max_candidates || candidate_bytes >= max_candidate_bytes {
+                    return SelectResult::Abort;
+                }
+                if past_ingress_set.contains(&IngressMessageId::from(ingress_obj)) {
+                    return SelectResult::Skip;
+                }
+                let canister_id = ingress_obj.signed_ingress.canister_id();
+                let selected_for_canister = candidates_per_canister.entry(canister_id).or_insert(0);
+                if *selected_for_canister >= max_messages_per_canister {
+                    skipped_for_fairness += 1;
+                    return SelectResult::Skip;
+                }
+                *selected_for_canister += 1;
+                num_candidates += 1;
+                candidate_bytes += ingress_obj.signed_ingress.count_bytes();
+                SelectResult::Selected(ingress_obj.signed_ingress.clone())
+            }),
+        );
+        self.metrics
+            .ingress_selector_skipped_for_fairness
+            .inc_by(skipped_for_fairness);
+
+        // Only the messages that are included in the payload are validated,
+        // so that the cycles needed per canister are only accounted for them.
+        let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
+        let messages_in_payload = select_round_robin(
+            candidates,
+            byte_limit,
+            settings.max_ingress_messages_per_block,
+            |ingress, num_messages| {
+                self.validate_ingress(
+                    IngressMessageId::from(ingress),
+                    ingress,
                     &state,
                     context,
                     &settings,
                     &past_ingress_set,
                     num_messages,
                     &mut cycles_needed,
-                );
-                match result {
-                    Ok(()) => {
-                        num_messages += 1;
-                        // Calculate the size and abort once we have hit the limit
-                        accumulated_size += ingress_obj.signed_ingress.count_bytes();
-                        if accumulated_size > byte_limit.get() as usize {
-                            return SelectResult::Abort;
-                        }
-
-                        SelectResult::Selected(ingress_obj.signed_ingress.clone())
-                    }
-                    Err(ValidationError::Permanent(
-                        IngressPermanentError::IngressPayloadTooBig(_, _),
-                    )) => SelectResult::Abort,
-                    Err(ValidationError::Permanent(
-                        IngressPermanentError::IngressPayloadTooManyMessages(_, _),
-                    )) => SelectResult::Abort,
-                    _ => SelectResult::Skip,
-                }
-            }),
+                )
+                .is_ok()
+            },
         );
 
         let payload = IngressPayload::from(messages_in_payload);
@@ -204,7 +239,7 @@ impl<'a> IngressSelector for IngressManager {
                 context,
                 &settings,
                 &past_ingress,
-                0, // message count is checked above.
+                i,
                 &mut cycles_needed,
             )?;
         }
@@ -347,6 +382,18 @@ impl IngressManager {
                 payer,
                 cost: ingress_cost,
             }) => match state.canister_state(&payer) {
+                // Do not include messages to canisters that are about to be
+                // migrated away from this subnet, so that their queues drain.
+                Some(_)
+                    if state
+                        .metadata
+                        .network_topology
+                        .is_migration_prepared(payer, state.metadata.own_subnet_id) =>
+                {
+                    return Err(ValidationError::Permanent(
+                        IngressPermanentError::CanisterMigrating(payer),
+                    ));
+                }
                 Some(canister) => {
                     let cumulative_ingress_cost = cycles_needed
                         .entry(payer)
@@ -386,6 +433,7 @@ impl IngressManager {
             self.ingress_signature_crypto.as_ref(),
             context.time,
             context.registry_version,
+            self.max_delegations,
             &self.malicious_flags,
         ) {
             let message_id = MessageId::from(&ingress_id);
@@ -403,6 +451,58 @@ impl IngressManager {
     }
 }
 
+/// Picks one message per target canister in turn, in the order in which the
+/// canisters first appear in `candidates`, until the payload is full. The
+/// messages to each canister stay in the order of `candidates`.
+///
+/// A message is only picked if `include` accepts it, given the number of
+/// messages picked before it. Rejected messages are dropped and the next
+/// message to the same canister is tried in their place.
+fn select_round_robin(
+    candidates: Vec<SignedIngress>,
+    byte_limit: NumBytes,
+    max_messages: usize,
+    mut include: impl FnMut(&SignedIngress, usize) -> bool,
+) -> Vec<SignedIngress> {
+    let mut queues: Vec<VecDeque<SignedIngress>> = Vec::new();
+    let mut queue_index: BTreeMap<CanisterId, usize> = BTreeMap::new();
+    for ingress in candidates {
+        let index = *queue_index.entry(ingress.canister_id()).or_insert_with(|| {
+            queues.push(VecDeque::new());
+            queues.len() - 1
+        });
+        queues[index].push_back(ingress);
+    }
+
+    let mut selected = Vec::new();
+    let mut accumulated_size = 0;
+    while !queues.is_empty() && selected.len() < max_messages {
+        let mut index = 0;
+        while index < queues.len() && selected.len() < max_messages {
+            let mut fits = false;
+            while let Some(ingress) = queues[index].pop_front() {
+                if accumulated_size + ingress.count_bytes() > byte_limit.get() as usize {
+                    break;
+                }
+                if include(&ingress, selected.len()) {
+                    accumulated_size += ingress.count_bytes();
+                    selected.push(ingress);
+                    fits = true;
+                    break;
+                }
+            }
+            // Once a message does not fit, later messages to the same canister
+            // must not overtake it.
+            if fits && !queues[index].is_empty() {
+                index += 1;
+            } else {
+                queues.remove(index);
+            }
+        }
+    }
+    selected
+}
+
 /// An IngressSetQuery implementation based on IngressHistoryReader.
 struct IngressHistorySet {
     get_status: Box<dyn Fn(&MessageId) -> IngressStatus>,
@@ -500,6 +600,7 @@ mod tests {
         ingress_pool::{ChangeAction, MutableIngressPool},
         time_source::TimeSource,
     };
+    use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations};
     use ic_test_utilities::{
         cycles_account_manager::CyclesAccountManagerBuilder,
         history::MockIngressHistory,
@@ -519,7 +620,11 @@ mod tests {
         time::current_time_and_expiry_time,
         Height, RegistryVersion,
     };
-    use std::{collections::HashSet, convert::TryInto, time::Duration};
+    use std::{
+        collections::HashSet,
+        convert::{TryFrom, TryInto},
+        time::Duration,
+    };
 
     const MAX_SIZE: usize = 1000;
     const MAX_SIZE_AS_NUM_BYTES: NumBytes = NumBytes::new(MAX_SIZE as u64);
@@ -1167,6 +1272,161 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_select_round_robin_alternates_between_canisters() {
+        let message = |canister: u64, nonce: u64| {
+            SignedIngressBuilder::new()
+                .canister_id(canister_test_id(canister))
+                .nonce(nonce)
+                .build()
+        };
+        let candidates = vec![
+            message(0, 1),
+            message(0, 2),
+            message(0, 3),
+            message(1, 4),
+            message(2, 5),
+            message(2, 6),
+        ];
+        let size = candidates[0].count_bytes() as u64;
+
+        let selected =
+            select_round_robin(candidates.clone(), NumBytes::new(4 * size), 10, |_, _| true);
+        assert_eq!(
+            selected,
+            vec![
+                candidates[0].clone(),
+                candidates[3].clone(),
+                candidates[4].clone(),
+                candidates[1].clone(),
+            ]
+        );
+
+        let selected =
+            select_round_robin(candidates.clone(), NumBytes::new(10 * size), 2, |_, _| true);
+        assert_eq!(selected, vec![candidates[0].clone(), candidates[3].clone()]);
+    }
+
+    #[test]
+    fn test_select_round_robin_replaces_rejected_messages() {
+        let message = |canister: u64, nonce: u64| {
+            SignedIngressBuilder::new()
+                .canister_id(canister_test_id(canister))
+                .nonce(nonce)
+                .build()
+        };
+        let candidates = vec![message(0, 1), message(0, 2), message(1, 3), message(1, 4)];
+        let size = candidates[0].count_bytes() as u64;
+
+        // The first message to each canister is rejected, and `include` sees
+        // the number of messages selected so far.
+        let mut num_messages_seen = Vec::new();
+        let selected = select_round_robin(
+            candidates.clone(),
+            NumBytes::new(10 * size),
+            10,
+            |ingress, num_messages| {
+                num_messages_seen.push(num_messages);
+                ingress != &candidates[0] && ingress != &candidates[2]
+            },
+        );
+        assert_eq!(selected, vec![candidates[1].clone(), candidates[3].clone()]);
+        assert_eq!(num_messages_seen, vec![0, 0, 1, 1]);
+    }
+
+    #[tokio::test]
+    // A canister with more messages than its share of the payload cannot
+    // crowd out messages to other canisters.
+    async fn test_get_payload_caps_messages_per_canister() {
+        setup_with_params(
+            None,
+            None,
+            None,
+            Some(
+                ReplicatedStateBuilder::default()
+                    .with_canister(
+                        CanisterStateBuilder::default()
+                            .with_canister_id(canister_test_id(0))
+                            .build(),
+                    )
+                    .with_canister(
+                        CanisterStateBuilder::default()
+                            .with_canister_id(canister_test_id(1))
+                            .build(),
+                    )
+                    .build(),
+            ),
+            |ingress_manager, ingress_pool| {
+                let time_source = FastForwardTimeSource::new();
+                let settings = ingress_manager
+                    .get_ingress_message_settings(RegistryVersion::from(1))
+                    .unwrap();
+                let max_messages_per_canister = settings.max_ingress_messages_per_block
+                    / MAX_INGRESS_MESSAGES_PER_CANISTER_SHARE;
+
+                // The flooded canister's messages all expire before the other one.
+                let mut messages: Vec<_> = (0..=max_messages_per_canister as u64)
+                    .map(|nonce| {
+                        SignedIngressBuilder::new()
+                            .canister_id(canister_test_id(0))
+                            .nonce(nonce)
+                            .expiry_time(mock_time() + MAX_INGRESS_TTL / 2)
+                            .build()
+                    })
+                    .collect();
+                messages.push(
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(1))
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build(),
+                );
+                access_ingress_pool(&ingress_pool, |mut ingress_pool| {
+                    for message in &messages {
+                        ingress_pool.insert(UnvalidatedArtifact {
+                            message: message.clone(),
+                            peer_id: node_test_id(0),
+                            timestamp: time_source.get_relative_time(),
+                        });
+                        ingress_pool.apply_changeset(vec![ChangeAction::MoveToValidated((
+                            IngressMessageId::from(message),
+                            node_test_id(0),
+                            message.count_bytes(),
+                            IngressMessageAttribute::new(message),
+                            crypto_hash(message.binary()).get(),
+                        ))]);
+                    }
+                });
+
+                let validation_context = ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                };
+                let ingress_payload = ingress_manager.get_ingress_payload(
+                    &HashSet::new(),
+                    &validation_context,
+                    NumBytes::new(60 * 1024 * 1024),
+                );
+                let selected: Vec<SignedIngress> = ingress_payload.try_into().unwrap();
+                let to_canister = |canister| {
+                    selected
+                        .iter()
+                        .filter(|m| m.canister_id() == canister_test_id(canister))
+                        .count()
+                };
+                assert_eq!(to_canister(0), max_messages_per_canister);
+                assert_eq!(to_canister(1), 1);
+                assert_eq!(
+                    ingress_manager
+                        .metrics
+                        .ingress_selector_skipped_for_fairness
+                        .get(),
+                    1
+                );
+            },
+        )
+    }
+
     #[tokio::test]
     // If the ingress message is invalid, it should be ignored and the next
     // ingress message should be added to the payload.
@@ -1440,6 +1700,89 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    // The cycles needed by a candidate that is not included in the payload do
+    // not count against the balance of the canister paying for it.
+    async fn test_get_payload_accounts_cycles_of_included_messages_only() {
+        let subnet_id = subnet_test_id(0);
+        let registry = setup_registry(subnet_id, MAX_SIZE);
+        let time = mock_time();
+        // A management message paid for by canister 0 that expires first, but
+        // does not fit into the payload.
+        let management_message = SignedIngressBuilder::new()
+            .canister_id(IC_00)
+            .method_name("canister_status")
+            .method_payload(CanisterIdRecord::from(canister_test_id(0)).encode())
+            .expiry_time(time + MAX_INGRESS_TTL / 2)
+            .build();
+        let message = SignedIngressBuilder::new()
+            .canister_id(canister_test_id(0))
+            .expiry_time(time + MAX_INGRESS_TTL)
+            .build();
+        assert!(management_message.count_bytes() > message.count_bytes());
+
+        let cycles_account_manager = CyclesAccountManagerBuilder::new()
+            .with_subnet_id(subnet_id)
+            .build();
+        let cost = |m: &SignedIngress| {
+            cycles_account_manager
+                .ingress_induction_cost(m.content())
+                .unwrap()
+                .cost()
+        };
+        // Enough cycles for either message, but not for both.
+        let cycles = cost(&management_message);
+        assert!(cycles < cost(&management_message) + cost(&message));
+
+        setup_with_params(
+            None,
+            Some((registry, subnet_id)),
+            None,
+            Some(
+                ReplicatedStateBuilder::default()
+                    .with_subnet_id(subnet_id)
+                    .with_canister(
+                        CanisterStateBuilder::default()
+                            .with_canister_id(canister_test_id(0))
+                            .with_cycles(cycles)
+                            .build(),
+                    )
+                    .build(),
+            ),
+            |ingress_manager, ingress_pool| {
+                let time_source = FastForwardTimeSource::new();
+                for m in [&management_message, &message] {
+                    access_ingress_pool(&ingress_pool, |mut ingress_pool| {
+                        ingress_pool.insert(UnvalidatedArtifact {
+                            message: m.clone(),
+                            peer_id: node_test_id(0),
+                            timestamp: time_source.get_relative_time(),
+                        });
+                        ingress_pool.apply_changeset(vec![ChangeAction::MoveToValidated((
+                            IngressMessageId::from(m),
+                            node_test_id(0),
+                            m.count_bytes(),
+                            IngressMessageAttribute::new(m),
+                            crypto_hash(m.binary()).get(),
+                        ))]);
+                    });
+                }
+
+                let payload = ingress_manager.get_ingress_payload(
+                    &HashSet::new(),
+                    &ValidationContext {
+                        time,
+                        registry_version: RegistryVersion::from(1),
+                        certified_height: Height::from(0),
+                    },
+                    NumBytes::new(message.count_bytes() as u64),
+                );
+                let msgs: Vec<SignedIngress> = payload.try_into().unwrap();
+                assert_eq!(msgs, vec![message.clone()]);
+            },
+        )
+    }
+
     #[tokio::test]
     // Validation should fail if receiving canisters has insufficient balance.
     async fn test_validate_canister_has_insufficient_balance() {
@@ -1494,6 +1837,63 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    // Validation should fail if the receiving canister is about to be migrated
+    // away from this subnet.
+    async fn test_validate_canister_migration_prepared() {
+        let subnet_id = subnet_test_id(0);
+        let registry = setup_registry(subnet_id, MAX_SIZE);
+        let mut state = ReplicatedStateBuilder::default()
+            .with_subnet_id(subnet_id)
+            .with_canister(
+                CanisterStateBuilder::default()
+                    .with_canister_id(canister_test_id(0))
+                    .build(),
+            )
+            .build();
+        state.metadata.network_topology.canister_migrations = Arc::new(
+            CanisterMigrations::try_from(BTreeMap::from([(
+                CanisterIdRange {
+                    start: canister_test_id(0),
+                    end: canister_test_id(0),
+                },
+                vec![subnet_id, subnet_test_id(1)],
+            )]))
+            .unwrap(),
+        );
+        setup_with_params(
+            None,
+            Some((registry, subnet_id)),
+            None,
+            Some(state),
+            |ingress_manager, _| {
+                let time = mock_time();
+                let m1 = SignedIngressBuilder::new()
+                    .canister_id(canister_test_id(0))
+                    .expiry_time(time + MAX_INGRESS_TTL)
+                    .nonce(1)
+                    .build();
+
+                let payload = IngressPayload::from(vec![m1]);
+                let ingress_validation = ingress_manager.validate_ingress_payload(
+                    &payload,
+                    &HashSet::new(),
+                    &ValidationContext {
+                        time: mock_time(),
+                        registry_version: RegistryVersion::from(1),
+                        certified_height: Height::from(0),
+                    },
+                );
+                assert_matches!(
+                    ingress_validation,
+                    Err(ValidationError::Permanent(
+                        IngressPermanentError::CanisterMigrating(canister_id)
+                    )) if canister_id == canister_test_id(0)
+                );
+            },
+        );
+    }
+
     #[tokio::test]
     // Validation should fail if receiving canister doesn't exist.
     async fn test_validate_canister_not_found() {
@@ -5,6 +5,7 @@
 mod ingress_handler;
 mod ingress_selector;
 
+use ic_config::ingress_manager::Config as IngressManagerConfig;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_interfaces::{
     consensus_pool::ConsensusPoolCache,
@@ -26,7 +27,7 @@ use ic_types::{
     time::{Time, UNIX_EPOCH},
     Height, RegistryVersion, SubnetId,
 };
-use prometheus::{Histogram, IntGauge};
+use prometheus::{Histogram, IntCounter, IntGauge};
 use std::{
     collections::{BTreeMap, HashSet},
     ops::RangeInclusive,
@@ -71,6 +72,7 @@ struct IngressManagerMetrics {
     ingress_selector_get_payload_time: Histogram,
     ingress_selector_validate_payload_time: Histogram,
     ingress_payload_cache_size: IntGauge,
+    ingress_selector_skipped_for_fairness: IntCounter,
 }
 
 impl IngressManagerMetrics {
@@ -95,6 +97,10 @@ impl IngressManagerMetrics {
                 "ingress_payload_cache_size",
                 "The number of HashSets in payload builder's ingress payload cache.",
             ),
+            ingress_selector_skipped_for_fairness: metrics_registry.int_counter(
+                "ingress_selector_skipped_for_fairness_total",
+                "Valid ingress messages not selected for a payload because their target canister reached its share of the payload.",
+            ),
         }
     }
 }
@@ -118,6 +124,8 @@ pub struct IngressManager {
     pub(crate) last_purge_time: RwLock<Time>,
     state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
     cycles_account_manager: Arc<CyclesAccountManager>,
+    /// The maximum number of sender delegations in an ingress message.
+    max_delegations: usize,
     malicious_flags: MaliciousFlags,
 }
 
@@ -135,6 +143,7 @@ impl IngressManager {
         log: ReplicaLogger,
         state_manager: Arc<dyn StateManager<State = ReplicatedState>>,
         cycles_account_manager: Arc<CyclesAccountManager>,
+        config: IngressManagerConfig,
         malicious_flags: MaliciousFlags,
     ) -> Self {
         Self {
@@ -151,6 +160,7 @@ impl IngressManager {
             messages_to_purge: RwLock::new(Vec::new()),
             state_manager,
             cycles_account_manager,
+            max_delegations: config.max_delegations,
             malicious_flags,
         }
     }
@@ -308,6 +318,7 @@ pub(crate) mod tests {
                         log,
                         Arc::new(state_manager),
                         cycles_account_manager,
+                        IngressManagerConfig::default(),
                         MaliciousFlags::default(),
                     ),
                     ingress_pool,
@@ -251,8 +251,15 @@ pub type IngressFilterService = Buffer<
 // https://docs.rs/tower/0.4.10/tower/buffer/index.html
 // The buffer also dampens usage by reducing the risk of
 // spiky traffic when users retry in case failed requests.
+//
+// Besides the response, the service returns the height of the certified state
+// that the query was executed on, if any.
 pub type QueryExecutionService = Buffer<
-    BoxService<(UserQuery, Option<CertificateDelegation>), HttpQueryResponse, Infallible>,
+    BoxService<
+        (UserQuery, Option<CertificateDelegation>),
+        (HttpQueryResponse, Option<Height>),
+        Infallible,
+    >,
     (UserQuery, Option<CertificateDelegation>),
 >;
 
@@ -67,6 +67,7 @@ pub enum IngressPermanentError {
     DuplicatedIngressMessage(MessageId),
     InsufficientCycles(CanisterOutOfCyclesError),
     CanisterNotFound(CanisterId),
+    CanisterMigrating(CanisterId),
     InvalidManagementMessage,
     StateRemoved(Height),
 }
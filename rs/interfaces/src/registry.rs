@@ -162,6 +162,21 @@ pub trait RegistryDataProvider: Send + Sync {
         &self,
         version: RegistryVersion,
     ) -> Result<Vec<RegistryTransportRecord>, RegistryDataProviderError>;
+
+    /// If supported, returns the latest record of every key at some registry
+    /// version `v`, together with `v`.
+    ///
+    /// Unlike the records returned by `get_updates_since`, the returned
+    /// records do not represent the updates to the registry at versions below
+    /// `v`, i.e. they only describe the registry at version `v`.
+    ///
+    /// Returns `Ok(None)` if the data provider does not support snapshots.
+    fn get_snapshot(
+        &self,
+    ) -> Result<Option<(Vec<RegistryTransportRecord>, RegistryVersion)>, RegistryDataProviderError>
+    {
+        Ok(None)
+    }
 }
 
 /// Whenever the local store is successfully updated, the time contained in the
@@ -71,6 +71,7 @@ const LABEL_VALUE_SUCCESS: &str = "success";
 const LABEL_VALUE_SENDER_SUBNET_MISMATCH: &str = "SenderSubnetMismatch";
 const LABEL_VALUE_RECEIVER_SUBNET_MISMATCH: &str = "ReceiverSubnetMismatch";
 const LABEL_VALUE_CANISTER_MIGRATED: &str = "CanisterMigrated";
+const LABEL_VALUE_CANISTER_MIGRATING: &str = "CanisterMigrating";
 const LABEL_TYPE: &str = "type";
 const LABEL_VALUE_TYPE_REQUEST: &str = "request";
 const LABEL_VALUE_TYPE_RESPONSE: &str = "response";
@@ -553,8 +554,8 @@ impl StreamHandlerImpl {
     ///  * `Request` or `Response` successfully inducted: accept signal appended
     ///    to the reverse stream;
     ///  * `Request` not inducted (queue full, out of memory, canister not
-    ///    found, canister migrated): accept signal and reject response appended
-    ///    to the reverse stream;
+    ///    found, canister being or having been migrated): accept signal and
+    ///    reject response appended to the reverse stream;
     ///  * `Response` not inducted (canister migrated): reject signal appended
     ///    to loopback stream (canonical versions 9+ only).
     ///  * `Request` or `Response` silently dropped and accept signal appended
@@ -593,6 +594,30 @@ impl StreamHandlerImpl {
 
             let payload_size = msg.payload_size_bytes().get();
             match receiver_host_subnet {
+                // Receiver canister is about to be migrated away from this subnet: reject
+                // requests, so that its queues drain; but keep inducting responses.
+                Some(host_subnet)
+                    if host_subnet == self.subnet_id
+                        && matches!(msg, RequestOrResponse::Request(_))
+                        && state
+                            .metadata
+                            .network_topology
+                            .is_migration_prepared(msg.receiver(), self.subnet_id) =>
+                {
+                    self.observe_inducted_message_status(msg_type, LABEL_VALUE_CANISTER_MIGRATING);
+                    debug!(
+                        self.log,
+                        "Canister {} is being migrated, generating reject response for {:?}",
+                        msg.receiver(),
+                        msg
+                    );
+                    let context = RejectContext::new(
+                        RejectCode::SysTransient,
+                        format!("Canister {} is being migrated", msg.receiver()),
+                    );
+                    stream.push(generate_reject_response(msg, context));
+                }
+
                 // Matching receiver subnet, try inducting message.
                 Some(host_subnet) if host_subnet == self.subnet_id => match state.push_input(
                     QUEUE_INDEX_NONE,
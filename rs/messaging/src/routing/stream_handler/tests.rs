@@ -1810,6 +1810,118 @@ fn induct_stream_slices_with_messages_to_migrated_canister() {
     });
 }
 
+/// Tests that requests to a canister whose migration away from this subnet has
+/// been prepared (but which is still hosted by this subnet) are rejected, while
+/// responses are still inducted.
+#[test]
+fn induct_stream_slices_with_messages_to_migrating_canister() {
+    with_test_replica_logger(|log| {
+        let (stream_handler, mut initial_state, metrics_registry) = new_fixture(&log);
+
+        // A canister with a reservation for one incoming response...
+        let mut initial_canister_state = new_canister_state(
+            *LOCAL_CANISTER,
+            user_test_id(24).get(),
+            *INITIAL_CYCLES,
+            NumSeconds::from(100_000),
+        );
+        make_input_queue_reservations(&mut initial_canister_state, 1, *REMOTE_CANISTER);
+        initial_state.put_canister_state(initial_canister_state);
+
+        // ...that is about to be migrated away from `LOCAL_SUBNET`.
+        initial_state = prepare_canister_migration(
+            initial_state,
+            *LOCAL_CANISTER,
+            LOCAL_SUBNET,
+            CANISTER_MIGRATION_SUBNET,
+        );
+        let mut expected_state = initial_state.clone();
+
+        let outgoing_stream = generate_outgoing_stream(StreamConfig {
+            messages_begin: 21,
+            message_count: 1,
+            signals_end: 43,
+            reject_signals: None,
+        });
+        initial_state.with_streams(btreemap![REMOTE_SUBNET => outgoing_stream]);
+
+        // Incoming slice with one incoming request...
+        let mut stream_slice = generate_stream_slice(StreamSliceConfig {
+            header_begin: 42,
+            header_end: None,
+            messages_begin: 43,
+            message_count: 1,
+            signals_end: 21,
+            reject_signals: None,
+        });
+        // ...and one incoming response.
+        stream_slice.push_message(test_response(*REMOTE_CANISTER, *LOCAL_CANISTER).into());
+
+        // The expected canister state contains the inducted response...
+        push_inputs(
+            &mut expected_state,
+            stream_slice.messages().unwrap().iter().skip(1),
+        );
+
+        // ...and the expected outgoing stream has signals for both messages and a
+        // reject response for the request.
+        let mut expected_outgoing_stream = generate_outgoing_stream(StreamConfig {
+            messages_begin: 21,
+            message_count: 1,
+            signals_end: 45,
+            reject_signals: None,
+        });
+        let rejected_request = stream_slice
+            .messages()
+            .unwrap()
+            .get(43.into())
+            .unwrap()
+            .clone();
+        let context = RejectContext::new(
+            RejectCode::SysTransient,
+            format!("Canister {} is being migrated", *LOCAL_CANISTER),
+        );
+        expected_outgoing_stream.push(generate_reject_response(rejected_request, context));
+        expected_state.with_streams(btreemap![REMOTE_SUBNET => expected_outgoing_stream]);
+
+        // Act
+        let inducted_state = stream_handler
+            .induct_stream_slices(initial_state, btreemap![REMOTE_SUBNET => stream_slice]);
+
+        // Assert
+        assert_eq!(
+            expected_state.system_metadata(),
+            inducted_state.system_metadata(),
+        );
+
+        assert_eq!(expected_state, inducted_state);
+
+        assert_inducted_xnet_messages_eq(
+            metric_vec(&[
+                (
+                    &[
+                        (LABEL_TYPE, LABEL_VALUE_TYPE_REQUEST),
+                        (LABEL_STATUS, LABEL_VALUE_CANISTER_MIGRATING),
+                    ],
+                    1,
+                ),
+                (
+                    &[
+                        (LABEL_TYPE, LABEL_VALUE_TYPE_RESPONSE),
+                        (LABEL_STATUS, LABEL_VALUE_SUCCESS),
+                    ],
+                    1,
+                ),
+            ]),
+            &metrics_registry,
+        );
+        assert_eq!(
+            1,
+            fetch_inducted_payload_sizes_stats(&metrics_registry).count
+        );
+    });
+}
+
 /// Tests that stream slices containing messages from a migrated canister results
 /// can also be inducted when the canister migration trace is set.
 #[test]
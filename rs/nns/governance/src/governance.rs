@@ -465,6 +465,14 @@ impl NnsFunction {
             }
             NnsFunction::RemoveNodeOperators => (REGISTRY_CANISTER_ID, "remove_node_operators"),
             NnsFunction::RerouteCanisterRange => (REGISTRY_CANISTER_ID, "reroute_canister_range"),
+            NnsFunction::PrepareCanisterMigration => {
+                (REGISTRY_CANISTER_ID, "prepare_canister_migration")
+            }
+            NnsFunction::CompleteCanisterMigration => {
+                (REGISTRY_CANISTER_ID, "complete_canister_migration")
+            }
+            NnsFunction::SplitSubnet => (REGISTRY_CANISTER_ID, "split_subnet"),
+            NnsFunction::SetFirewallRules => (REGISTRY_CANISTER_ID, "set_firewall_rules"),
         };
         Ok((canister_id, method))
     }
@@ -1079,11 +1087,16 @@ impl Proposal {
                             NnsFunction::IcpXdrConversionRate => Topic::ExchangeRate,
                             NnsFunction::ClearProvisionalWhitelist => Topic::NetworkEconomics,
                             NnsFunction::SetAuthorizedSubnetworks => Topic::SubnetManagement,
-                            NnsFunction::SetFirewallConfig => Topic::SubnetManagement,
+                            NnsFunction::SetFirewallConfig | NnsFunction::SetFirewallRules => {
+                                Topic::SubnetManagement
+                            }
                             NnsFunction::UninstallCode => Topic::Governance,
                             NnsFunction::UpdateNodeRewardsTable => Topic::NetworkEconomics,
                             NnsFunction::AddOrRemoveDataCenters => Topic::ParticipantManagement,
-                            NnsFunction::RerouteCanisterRange => Topic::SubnetManagement,
+                            NnsFunction::RerouteCanisterRange
+                            | NnsFunction::PrepareCanisterMigration
+                            | NnsFunction::CompleteCanisterMigration
+                            | NnsFunction::SplitSubnet => Topic::SubnetManagement,
                         }
                     } else {
                         Topic::Unspecified
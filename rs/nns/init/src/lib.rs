@@ -3,7 +3,7 @@
 //! Was used at genesis, but now only used to install the nns in testnets.
 
 use canister_test::Wasm;
-use ic_canister_client::Sender;
+use ic_canister_client::{Pkcs11Signer, Sender};
 use ic_interfaces::registry::{RegistryDataProvider, ZERO_REGISTRY_VERSION};
 use ic_nns_constants::NNS_CANISTER_WASMS;
 use ic_registry_common::local_store::{
@@ -15,6 +15,7 @@ use ic_registry_transport::pb::v1::{registry_mutation, RegistryMutation};
 use ic_registry_transport::{delete, upsert};
 use ic_sys::utility_command::{UtilityCommand, UtilityCommandError};
 use std::path::Path;
+use std::sync::Arc;
 
 /// Reads mutations from a file in the format corresponding to what ic-prep
 /// outputs
@@ -186,26 +187,13 @@ pub fn make_hsm_sender(hsm_slot: &str, key_id: &str, pin: &str) -> Sender {
         }
     }
 
-    let pub_key = UtilityCommand::read_public_key(Some(hsm_slot), Some(key_id))
-        .execute()
-        .unwrap_or_else(|e| {
-            panic!(
-                "Error while trying to read the public key from the HSM. Underlying error: {}",
-                e
-            )
-        });
-
-    let key_id = key_id.to_string();
-    let pin = pin.to_string();
-    let hsm_slot = hsm_slot.to_string();
-    let sender = Sender::from_external_hsm(
-        pub_key,
-        std::sync::Arc::new(move |input| {
-            UtilityCommand::sign_message(input.to_vec(), Some(&hsm_slot), Some(&pin), Some(&key_id))
-                .execute()
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
-        }),
-    );
+    let signer = Pkcs11Signer::new(None, hsm_slot, key_id, pin).unwrap_or_else(|e| {
+        panic!(
+            "Error while trying to read the public key from the HSM. Underlying error: {}",
+            e
+        )
+    });
+    let sender = Sender::from_signer(Arc::new(signer));
     UtilityCommand::try_to_detach_hsm();
 
     sender
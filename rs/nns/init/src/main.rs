@@ -1,6 +1,7 @@
 use canister_test::{RemoteTestRuntime, Runtime};
 use ic_base_types::PrincipalId;
 use ic_canister_client::{Agent, Sender};
+use ic_crypto_utils_threshold_sig::parse_threshold_sig_key;
 use ic_nns_common::pb::v1::NeuronId;
 use ic_nns_governance::pb::v1::Governance as GovernanceProto;
 use ic_nns_init::make_hsm_sender;
@@ -20,6 +21,17 @@ struct CliArgs {
     #[structopt(long)]
     url: Option<Url>,
 
+    /// Path to a pem file containing the NNS public key, used to verify
+    /// certified responses from the replica at `--url`.
+    #[structopt(long, parse(from_os_str))]
+    nns_public_key_pem_file: Option<PathBuf>,
+
+    /// Fetch the NNS public key from the replica at `--url` instead of
+    /// reading it from `--nns-public-key-pem-file`. The fetched key is not
+    /// authenticated, so this must only be used against testnets.
+    #[structopt(long)]
+    fetch_root_key: bool,
+
     /// Path to a directory containing the .wasm file for each NNS
     /// canister.
     ///
@@ -181,6 +193,15 @@ async fn main() {
                 Sender::from_keypair(&ic_test_identity::TEST_IDENTITY_KEYPAIR),
             )
         };
+        let agent = match (&args.nns_public_key_pem_file, args.fetch_root_key) {
+            (Some(pem_file), false) => agent.with_root_key(
+                parse_threshold_sig_key(pem_file).expect("Invalid NNS public key pem file."),
+            ),
+            (None, true) => agent.with_fetched_root_key(),
+            _ => panic!(
+                "Exactly one of --nns-public-key-pem-file and --fetch-root-key must be provided to install canisters."
+            ),
+        };
 
         // Don't let the "Test" distract you -- the RemoteTestRuntime is simply a
         // client-side view of a subnet.
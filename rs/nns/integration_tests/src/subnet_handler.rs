@@ -61,6 +61,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                rollout_policy: None,
             };
 
             let key = make_subnet_record_key(subnet_id);
@@ -113,6 +114,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                 max_number_of_canisters: Some(200),
                 ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
                 ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+                rollout_policy: None,
             };
 
             let proposal_id: ProposalId = submit_external_update_proposal(
@@ -182,6 +184,7 @@ fn test_submit_and_accept_update_subnet_proposal() {
                     ssh_readonly_access: vec!["pub_key_0".to_string()],
                     ssh_backup_access: vec!["pub_key_1".to_string()],
                     ecdsa_config: None,
+                    rollout_policy: None,
                 }
             );
             Ok(())
@@ -190,27 +190,34 @@ impl InternalState {
         };
         assert!(subnet_record.start_as_nns);
 
+        // IOErrors are treated as fatal.
+        let earliest_version = self
+            .local_store
+            .get_earliest_version()
+            .expect("Could not read earliest version from disk.");
+
         // let k be the least version at which this node is part of the newly created
-        // subnet
+        // subnet, or the earliest version of the local store if it was bootstrapped
+        // from a snapshot that already contains the subnet
         let mut v = latest_version - RegistryVersion::from(1);
         let k = loop {
-            if self
-                .registry_client
-                .get_subnet_record(subnet_id, v)
-                .map_err(|e| map_to_str("Could not retrieve subnet record", v, e))?
-                .is_none()
+            if v < earliest_version
+                || self
+                    .registry_client
+                    .get_subnet_record(subnet_id, v)
+                    .map_err(|e| map_to_str("Could not retrieve subnet record", v, e))?
+                    .is_none()
             {
                 break v + RegistryVersion::from(1);
             }
             v -= RegistryVersion::from(1);
         };
 
-        // IOErrors are treated as fatal.
         let mut changelog = self
             .local_store
             .get_changelog_since_version(RegistryVersion::from(0))
             .expect("Could not read changelog from disk.");
-        changelog.truncate(k.get() as usize);
+        changelog.truncate((k - earliest_version).get() as usize + 1);
 
         self.apply_switch_over_to_last_changelog_entry(
             changelog.as_mut_slice(),
@@ -221,10 +228,14 @@ impl InternalState {
             .clear()
             .expect("Could not clear registry local store");
 
-        for (v, cle) in changelog.into_iter().enumerate() {
-            self.local_store
-                .store(RegistryVersion::from((v + 1) as u64), cle)
-                .expect("Could not store change log entry");
+        for (i, cle) in changelog.into_iter().enumerate() {
+            let v = earliest_version + RegistryVersion::from(i as u64);
+            let result = if i == 0 && earliest_version > RegistryVersion::from(1) {
+                self.local_store.store_snapshot(v, cle)
+            } else {
+                self.local_store.store(v, cle)
+            };
+            result.expect("Could not store change log entry");
         }
 
         warn!(
@@ -31,16 +31,21 @@
 use crate::internal_state::InternalState;
 use ic_config::{registry_client::DataProviderConfig, Config};
 use ic_crypto_utils_threshold_sig::parse_threshold_sig_key;
-use ic_interfaces::registry::{RegistryClient, RegistryDataProvider, ZERO_REGISTRY_VERSION};
+use ic_interfaces::registry::{
+    RegistryClient, RegistryDataProvider, RegistryTransportRecord, ZERO_REGISTRY_VERSION,
+};
 use ic_logger::{debug, info, warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_protobuf::registry::subnet::v1::SubnetRecord;
 use ic_registry_client::client::{create_data_provider, RegistryClientImpl};
 use ic_registry_common::local_store::{
     Changelog, ChangelogEntry, KeyMutation, LocalStore, LocalStoreImpl,
 };
 use ic_registry_common::registry::RegistryCanister;
+use ic_registry_keys::SUBNET_RECORD_KEY_PREFIX;
 use ic_types::crypto::threshold_sig::ThresholdSigPublicKey;
 use ic_types::{NodeId, RegistryVersion};
+use prost::Message;
 use std::io::{Error, ErrorKind};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -184,6 +189,7 @@ impl RegistryReplicator {
         &self,
         nns_urls: Vec<Url>,
         nns_pub_key: Option<ThresholdSigPublicKey>,
+        max_snapshot_version: Option<RegistryVersion>,
     ) {
         if self
             .local_store
@@ -204,6 +210,44 @@ impl RegistryReplicator {
                 .expect("Could not read registry local store.")
                 .is_empty()
             {
+                // Bootstrap from a certified snapshot rather than replaying every version, if
+                // the registry canister serves one. Registry clients reading from the local
+                // store then only serve versions from the snapshot's version on. A node that
+                // is assigned to a subnet might need older versions to start from its
+                // subnet's CUP, so it only uses a snapshot that is not newer than its local
+                // CUP.
+                match registry_canister
+                    .get_certified_snapshot(&nns_pub_key, max_snapshot_version)
+                    .await
+                {
+                    Ok(Some((records, version, t)))
+                        if !records.is_empty()
+                            && (max_snapshot_version.is_some()
+                                || !self.is_assigned_in_snapshot(&records)) =>
+                    {
+                        let snapshot = records
+                            .into_iter()
+                            .map(|r| KeyMutation {
+                                key: r.key,
+                                value: r.value,
+                            })
+                            .collect();
+                        self.local_store
+                            .store_snapshot(version, snapshot)
+                            .expect("Could not write to local store.");
+                        self.local_store
+                            .update_certified_time(t.as_nanos_since_unix_epoch())
+                            .expect("Could not store certified time");
+                        return;
+                    }
+                    Err(e) => warn!(
+                        self.logger,
+                        "Could not fetch registry snapshot from NNS, fetching its changelog instead: {:?}",
+                        e
+                    ),
+                    _ => {}
+                };
+
                 // Note, code duplicate in internal_state.rs poll()
                 match registry_canister
                     .get_certified_changes_since(0, &nns_pub_key)
@@ -247,13 +291,41 @@ impl RegistryReplicator {
         }
     }
 
+    /// Returns true iff this node is a member of a subnet in the registry
+    /// snapshot `records`.
+    fn is_assigned_in_snapshot(&self, records: &[RegistryTransportRecord]) -> bool {
+        let node_id = match self.node_id {
+            Some(node_id) => node_id,
+            None => return false,
+        };
+        records
+            .iter()
+            .filter(|r| r.key.starts_with(SUBNET_RECORD_KEY_PREFIX))
+            .filter_map(|r| r.value.as_ref())
+            .filter_map(|value| SubnetRecord::decode(value.as_slice()).ok())
+            .any(|subnet_record| {
+                subnet_record
+                    .membership
+                    .iter()
+                    .any(|n| n.as_slice() == node_id.get().as_slice())
+            })
+    }
+
     /// Calls [`Self::poll()`] asynchronously and spawns a background task that
     /// continuously polls for updates. Returns the result of the first poll.
     /// The background task is stopped when the object is dropped.
+    ///
+    /// If the local store is empty, it is bootstrapped from the certified
+    /// snapshot of the registry, unless the snapshot is newer than
+    /// `max_snapshot_version`. Versions below the snapshot are not available,
+    /// so `max_snapshot_version` must not be greater than the registry version
+    /// of any CUP this node might start from. If it is None, a node that is
+    /// assigned to a subnet in the snapshot fetches the full changelog instead.
     pub async fn fetch_and_start_polling(
         &self,
         nns_urls: Vec<Url>,
         nns_pub_key: Option<ThresholdSigPublicKey>,
+        max_snapshot_version: Option<RegistryVersion>,
     ) -> Result<(), Error> {
         if self.started.swap(true, Ordering::Relaxed) {
             return Err(Error::new(
@@ -264,7 +336,8 @@ impl RegistryReplicator {
 
         // Initialize the registry local store. Will not return if the nns is not
         // reachable.
-        self.initialize_local_store(nns_urls, nns_pub_key).await;
+        self.initialize_local_store(nns_urls, nns_pub_key, max_snapshot_version)
+            .await;
 
         let mut internal_state = InternalState::new(
             self.logger.clone(),
@@ -317,18 +390,26 @@ impl RegistryReplicator {
     /// store.
     fn set_local_registry_data(&self, source_registry: &dyn LocalStore) {
         // Read the registry data.
+        let earliest_version = source_registry
+            .get_earliest_version()
+            .expect("Could not read earliest version from source registry.");
         let changelog = source_registry
             .get_changelog_since_version(RegistryVersion::from(0))
             .expect("Could not read changelog from source registry.");
 
-        // Reset the local store and fill it with the read registry data.
+        // Reset the local store and fill it with the read registry data. If the
+        // source registry was bootstrapped from a snapshot, so is the local store.
         self.local_store
             .clear()
             .expect("Could not clear registry local store");
-        for (v, cle) in changelog.into_iter().enumerate() {
-            self.local_store
-                .store(RegistryVersion::from((v + 1) as u64), cle)
-                .expect("Could not store change log entry");
+        for (i, cle) in changelog.into_iter().enumerate() {
+            let v = earliest_version + RegistryVersion::from(i as u64);
+            let result = if i == 0 && earliest_version > RegistryVersion::from(1) {
+                self.local_store.store_snapshot(v, cle)
+            } else {
+                self.local_store.store(v, cle)
+            };
+            result.expect("Could not store change log entry");
         }
     }
 
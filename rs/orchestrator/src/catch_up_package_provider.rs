@@ -16,7 +16,10 @@ use ic_types::{
 use ic_utils::fs::write_protobuf_using_tmp_file;
 use std::convert::TryFrom;
 use std::sync::Arc;
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
 use url::Url;
 
 /// Fetches catch-up packages from peers and local storage.
@@ -200,7 +203,7 @@ impl CatchUpPackageProvider {
     /// Includes the specific type encoded in the file for future-proofing and
     /// ease of debugging.
     pub fn get_cup_path(&self) -> PathBuf {
-        self.cup_dir.join("cup.types.v1.CatchUpPackage.pb")
+        cup_path(&self.cup_dir)
     }
 
     /// Return the most up to date CUP.
@@ -245,24 +248,52 @@ impl CatchUpPackageProvider {
 
     /// Returns the locally persisted CUP.
     pub fn get_local_cup(&self) -> Option<CUPWithOriginalProtobuf> {
-        let path = self.get_cup_path();
-        if !path.exists() {
-            return None;
-        }
-        match File::open(&path) {
-            Ok(reader) => pb::CatchUpPackage::read_from_reader(reader)
-                .and_then(|protobuf| {
-                    Ok(CUPWithOriginalProtobuf {
-                        cup: CatchUpPackage::try_from(&protobuf)?,
-                        protobuf,
-                    })
+        read_local_cup(&self.cup_dir, &self.logger)
+    }
+}
+
+/// Returns the earliest registry version a replica starting from `cup` reads,
+/// i.e. the oldest among the registry versions of its block, its DKG summary
+/// and the current DKG transcripts.
+pub(crate) fn earliest_registry_version(cup: &CatchUpPackage) -> RegistryVersion {
+    let block = cup.content.block.get_value();
+    let summary = &block.payload.as_ref().as_summary().dkg;
+    summary
+        .current_transcripts()
+        .values()
+        .map(|transcript| transcript.registry_version)
+        .fold(
+            std::cmp::min(block.context.registry_version, summary.registry_version),
+            std::cmp::min,
+        )
+}
+
+fn cup_path(cup_dir: &Path) -> PathBuf {
+    cup_dir.join("cup.types.v1.CatchUpPackage.pb")
+}
+
+/// Returns the CUP persisted in `cup_dir`, if any.
+pub(crate) fn read_local_cup(
+    cup_dir: &Path,
+    logger: &ReplicaLogger,
+) -> Option<CUPWithOriginalProtobuf> {
+    let path = cup_path(cup_dir);
+    if !path.exists() {
+        return None;
+    }
+    match File::open(&path) {
+        Ok(reader) => pb::CatchUpPackage::read_from_reader(reader)
+            .and_then(|protobuf| {
+                Ok(CUPWithOriginalProtobuf {
+                    cup: CatchUpPackage::try_from(&protobuf)?,
+                    protobuf,
                 })
-                .map_err(|e| warn!(self.logger, "Failed to read CUP from file {:?}", e))
-                .ok(),
-            Err(err) => {
-                warn!(self.logger, "Couldn't open file {:?}: {:?}", path, err);
-                None
-            }
+            })
+            .map_err(|e| warn!(logger, "Failed to read CUP from file {:?}", e))
+            .ok(),
+        Err(err) => {
+            warn!(logger, "Couldn't open file {:?}: {:?}", path, err);
+            None
         }
     }
 }
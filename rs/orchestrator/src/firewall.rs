@@ -5,7 +5,9 @@ use crate::{
 };
 use ic_config::firewall::{Config as FirewallConfig, FIREWALL_FILE_DEFAULT_PATH};
 use ic_logger::{debug, info, warn, ReplicaLogger};
-use ic_protobuf::registry::firewall::v1::FirewallConfig as FirewallConfigPB;
+use ic_protobuf::registry::firewall::v1::{
+    FirewallAction, FirewallConfig as FirewallConfigPB, FirewallRule,
+};
 use ic_types::RegistryVersion;
 use ic_utils::fs::write_string_using_tmp_file;
 use std::path::PathBuf;
@@ -26,6 +28,9 @@ pub(crate) struct Firewall {
     logger: ReplicaLogger,
     configuration: FirewallConfig,
     source: DataSource,
+    // The firewall rules from the registry that apply to this node, in
+    // priority order
+    firewall_rules: Vec<FirewallRule>,
     compiled_config: String,
     // If true, write the file content even if no change was detected in registry, i.e. first time
     must_write: bool,
@@ -59,6 +64,7 @@ impl Firewall {
             metrics,
             configuration: config,
             source: DataSource::Config,
+            firewall_rules: Vec::new(),
             logger,
             compiled_config: Default::default(),
             must_write: true,
@@ -102,6 +108,16 @@ impl Firewall {
             }
         }
 
+        match self.registry.get_firewall_rules(registry_version) {
+            Ok(firewall_rules) => self.firewall_rules = firewall_rules,
+            Err(e) => warn!(
+                every_n_seconds => 300,
+                self.logger,
+                "Failed to fetch firewall rules from registry. Using previously fetched rules. (Error from registry: {:?})",
+                e
+            ),
+        }
+
         let content = self.generate_firewall_file_content_full();
 
         let changed = content.ne(&self.compiled_config);
@@ -134,16 +150,65 @@ impl Firewall {
 
     /// Generates a string with the content for the firewall rules file
     fn generate_firewall_file_content_full(&self) -> String {
-        self.configuration
+        Self::generate_firewall_file_content(&self.configuration, &self.firewall_rules)
+    }
+
+    /// Fills the placeholders of the firewall config template in
+    /// `configuration` with its prefixes and the compiled `rules`.
+    fn generate_firewall_file_content(
+        configuration: &FirewallConfig,
+        rules: &[FirewallRule],
+    ) -> String {
+        configuration
             .firewall_config
             .replace(
                 "<< ipv4_prefixes >>",
-                &Self::sanitize_prefixes(&self.configuration.ipv4_prefixes).join(",\n"),
+                &Self::sanitize_prefixes(&configuration.ipv4_prefixes).join(",\n"),
             )
             .replace(
                 "<< ipv6_prefixes >>",
-                &Self::sanitize_prefixes(&self.configuration.ipv6_prefixes).join(",\n"),
+                &Self::sanitize_prefixes(&configuration.ipv6_prefixes).join(",\n"),
             )
+            .replace(
+                "<< ipv4_tcp_rule_list >>",
+                &Self::compile_rules("ip", rules, |rule| &rule.ipv4_prefixes).join("\n"),
+            )
+            .replace(
+                "<< ipv6_tcp_rule_list >>",
+                &Self::compile_rules("ip6", rules, |rule| &rule.ipv6_prefixes).join("\n"),
+            )
+    }
+
+    /// Compiles `rules` into nftables rules matching on TCP connections from
+    /// the prefixes returned by `prefixes` (rules without such prefixes are
+    /// skipped), preserving their order.
+    fn compile_rules(
+        family: &str,
+        rules: &[FirewallRule],
+        prefixes: fn(&FirewallRule) -> &Vec<String>,
+    ) -> Vec<String> {
+        rules
+            .iter()
+            .filter(|rule| !prefixes(rule).is_empty())
+            .map(|rule| {
+                let verdict = match FirewallAction::from_i32(rule.action) {
+                    Some(FirewallAction::Allow) => "accept",
+                    Some(FirewallAction::Deny) => "drop",
+                    Some(FirewallAction::Reject) => "reject",
+                    // Rejected by the registry invariants
+                    Some(FirewallAction::Unspecified) | None => "drop",
+                };
+                let ports: Vec<String> = rule.ports.iter().map(|port| port.to_string()).collect();
+                format!(
+                    "{} saddr {{ {} }} ct state {{ new }} tcp dport {{ {} }} {} # {}",
+                    family,
+                    Self::sanitize_prefixes(prefixes(rule)).join(", "),
+                    ports.join(", "),
+                    verdict,
+                    rule.comment.replace("\n", " ")
+                )
+            })
+            .collect()
     }
 
     fn sanitize_prefixes(prefixes: &[String]) -> Vec<String> {
@@ -177,3 +242,77 @@ impl Firewall {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiles_rules_in_order_per_address_family() {
+        let rules = vec![
+            FirewallRule {
+                ipv4_prefixes: vec!["10.0.0.0/8".to_string(), "192.168.0.1".to_string()],
+                ipv6_prefixes: vec![],
+                ports: vec![22, 8080],
+                action: FirewallAction::Allow as i32,
+                comment: "node rule".to_string(),
+            },
+            FirewallRule {
+                ipv4_prefixes: vec!["0.0.0.0/0".to_string()],
+                ipv6_prefixes: vec!["::/0".to_string()],
+                ports: vec![22],
+                action: FirewallAction::Reject as i32,
+                comment: "global\nrule".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            Firewall::compile_rules("ip", &rules, |rule| &rule.ipv4_prefixes),
+            vec![
+                "ip saddr { 10.0.0.0/8, 192.168.0.1 } ct state { new } tcp dport { 22, 8080 } accept # node rule",
+                "ip saddr { 0.0.0.0/0 } ct state { new } tcp dport { 22 } reject # global rule",
+            ]
+        );
+        assert_eq!(
+            Firewall::compile_rules("ip6", &rules, |rule| &rule.ipv6_prefixes),
+            vec!["ip6 saddr { ::/0 } ct state { new } tcp dport { 22 } reject # global rule"]
+        );
+    }
+
+    #[test]
+    fn renders_rules_into_guestos_template() {
+        let template = include_str!("../../../ic-os/guestos/rootfs/opt/ic/share/ic.json5.template");
+        let start = template
+            .find("    firewall: {")
+            .expect("firewall section not found");
+        let end = start + template[start..].find("\n    },").unwrap() + "\n    }".len();
+        let section = &template[start + "    firewall: ".len()..end];
+        let configuration: FirewallConfig =
+            json5::from_str(section).expect("invalid firewall section");
+
+        let rules = vec![FirewallRule {
+            ipv4_prefixes: vec!["198.51.100.0/24".to_string()],
+            ipv6_prefixes: vec!["2001:db8::/32".to_string()],
+            ports: vec![8080],
+            action: FirewallAction::Deny as i32,
+            comment: "deny documentation ranges".to_string(),
+        }];
+        let content = Firewall::generate_firewall_file_content(&configuration, &rules);
+
+        assert!(
+            !content.contains("<<"),
+            "unfilled placeholder in:\n{}",
+            content
+        );
+        let ipv4_rule = "ip saddr { 198.51.100.0/24 } ct state { new } tcp dport { 8080 } drop # deny documentation ranges";
+        let ipv6_rule = "ip6 saddr { 2001:db8::/32 } ct state { new } tcp dport { 8080 } drop # deny documentation ranges";
+        let ipv4_table = content.find("table filter {").unwrap();
+        let ipv6_table = content.find("table ip6 filter {").unwrap();
+        let ipv4_rule_position = content.find(ipv4_rule).expect("IPv4 rule not rendered");
+        let ipv6_rule_position = content.find(ipv6_rule).expect("IPv6 rule not rendered");
+        assert!(ipv4_table < ipv4_rule_position && ipv4_rule_position < ipv6_table);
+        // Rules from the registry take precedence over the default accept rule.
+        let ipv6_default_rule = content.find("ip6 saddr $IPV6_PREFIXES").unwrap();
+        assert!(ipv6_table < ipv6_rule_position && ipv6_rule_position < ipv6_default_rule);
+    }
+}
@@ -42,6 +42,7 @@ pub mod orchestrator;
 mod registration;
 mod registry_helper;
 mod replica_process;
+mod rollout;
 mod ssh_access_manager;
 mod upgrade;
 mod utils;
@@ -1,11 +1,14 @@
 use crate::args::OrchestratorArgs;
-use crate::catch_up_package_provider::CatchUpPackageProvider;
+use crate::catch_up_package_provider::{
+    earliest_registry_version, read_local_cup, CatchUpPackageProvider,
+};
 use crate::crypto_helper::setup_crypto;
 use crate::firewall::Firewall;
 use crate::metrics::OrchestratorMetrics;
 use crate::registration::NodeRegistration;
 use crate::registry_helper::RegistryHelper;
 use crate::replica_process::ReplicaProcess;
+use crate::rollout::StagedRollout;
 use crate::ssh_access_manager::SshAccessManager;
 use crate::upgrade::Upgrade;
 use ic_config::metrics::{Config as MetricsConfig, Exporter};
@@ -84,8 +87,14 @@ impl Orchestrator {
 
         let (nns_urls, nns_pub_key) =
             registry_replicator.parse_registry_access_info_from_config(&config);
+        // If the registry local store is empty, it is bootstrapped from a snapshot that must
+        // not be newer than the registry versions the CUP this node restarts from refers to.
+        // Without a local CUP, a node that is assigned to a subnet fetches the full registry
+        // changelog instead, as the CUP of its subnet might refer to any earlier version.
+        let local_cup_registry_version =
+            read_local_cup(&args.cup_dir, &logger).map(|cup| earliest_registry_version(&cup.cup));
         if let Err(err) = registry_replicator
-            .fetch_and_start_polling(nns_urls, nns_pub_key)
+            .fetch_and_start_polling(nns_urls.clone(), nns_pub_key, local_cup_registry_version)
             .await
         {
             warn!(logger, "{}", err);
@@ -136,6 +145,19 @@ impl Orchestrator {
             logger.clone(),
         ));
 
+        let replica_metrics_addr = match config.metrics.exporter {
+            Exporter::Http(addr) => Some(addr),
+            _ => None,
+        };
+        let rollout = StagedRollout::new(
+            node_id,
+            Arc::clone(&registry),
+            crypto.clone(),
+            nns_urls,
+            replica_metrics_addr,
+            logger.clone(),
+        );
+
         let upgrade = Some(Upgrade::new(
             Arc::clone(&registry),
             replica_process,
@@ -146,6 +168,7 @@ impl Orchestrator {
             ic_binary_directory,
             registry_replicator,
             args.replica_binary_dir.clone(),
+            rollout,
             logger.clone(),
         ));
 
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 use crate::error::{OrchestratorError, OrchestratorResult};
 use candid::Encode;
-use ic_canister_client::{Agent, Sender};
+use ic_canister_client::{Agent, Sender, Signer};
 use ic_config::{
     http_handler::Config as HttpConfig,
     message_routing::Config as MsgRoutingConfig,
@@ -73,13 +73,20 @@ impl NodeRegistration {
             version = self.registry_client.get_latest_version();
         }
 
-        use ic_registry_client_helpers::{node::NodeRegistry, subnet::SubnetRegistry};
+        use ic_registry_client_helpers::{
+            crypto::CryptoRegistry, node::NodeRegistry, subnet::SubnetRegistry,
+        };
 
         let nns_subnet_id = self
             .registry_client
             .get_root_subnet_id(version)
             .expect("Error when fetching nns subnet id.")
             .expect("NNS subnet id not defined");
+        let nns_public_key = self
+            .registry_client
+            .get_threshold_signing_public_key_for_subnet(nns_subnet_id, version)
+            .expect("Error when fetching the nns public key.")
+            .expect("NNS public key not defined");
         let node_ids = self
             .registry_client
             .get_node_ids_on_subnet(nns_subnet_id, version)
@@ -110,15 +117,6 @@ impl NodeRegistration {
         nns_urls.shuffle(&mut rng);
         let mut nns_urls = nns_urls.iter().cycle();
 
-        let sign_cmd = |msg: &[u8]| {
-            UtilityCommand::try_to_attach_hsm();
-            let res = UtilityCommand::sign_message(msg.to_vec(), None, None, None)
-                .execute()
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>);
-            UtilityCommand::try_to_detach_hsm();
-            res
-        };
-
         let add_node_payload = self.assemble_add_node_message();
 
         let read_public_key = UtilityCommand::read_public_key(None, None);
@@ -141,13 +139,13 @@ impl NodeRegistration {
             tokio::time::sleep(Duration::from_secs(2)).await;
         };
         // we have the public key
+        let sender = Sender::from_signer(Arc::new(UsbHsmSigner {
+            public_key_der: hsm_pub_key,
+        }));
 
         while !self.is_node_registered() {
-            let sender = Sender::ExternalHsm {
-                pub_key: hsm_pub_key.clone(),
-                sign: Arc::new(sign_cmd),
-            };
-            let agent = Agent::new(nns_urls.next().unwrap().clone(), sender);
+            let agent = Agent::new(nns_urls.next().unwrap().clone(), sender.clone())
+                .with_root_key(nns_public_key);
 
             if let Err(e) = agent
                 .execute_update(
@@ -225,6 +223,27 @@ impl NodeRegistration {
     }
 }
 
+/// Signs with the node operator key on the USB HSM, which is attached to the
+/// node only while signing.
+struct UsbHsmSigner {
+    public_key_der: Vec<u8>,
+}
+
+impl Signer for UsbHsmSigner {
+    fn sender_pubkey_der(&self) -> Vec<u8> {
+        self.public_key_der.clone()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        UtilityCommand::try_to_attach_hsm();
+        let res = UtilityCommand::sign_message(msg.to_vec(), None, None, None)
+            .execute()
+            .map_err(|e| Box::new(e) as Box<dyn std::error::Error>);
+        UtilityCommand::try_to_detach_hsm();
+        res
+    }
+}
+
 pub(crate) fn http_config_to_endpoint(
     log: &ReplicaLogger,
     http_config: &HttpConfig,
@@ -307,7 +326,7 @@ fn get_endpoint(log: &ReplicaLogger, ip_addr: String, port: u16) -> Orchestrator
 
 /// Create a nonce to be included with the ingress message sent to the node
 /// handler.
-fn generate_nonce() -> Vec<u8> {
+pub(crate) fn generate_nonce() -> Vec<u8> {
     SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
@@ -353,6 +372,7 @@ mod tests {
                     queue_size: 1,
                 },
             ],
+            ..Default::default()
         };
 
         with_test_replica_logger(|log| {
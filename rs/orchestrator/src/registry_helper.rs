@@ -2,12 +2,15 @@ use crate::error::{OrchestratorError, OrchestratorResult};
 use ic_consensus::dkg::make_registry_cup;
 use ic_interfaces::registry::RegistryClient;
 use ic_logger::ReplicaLogger;
-use ic_protobuf::registry::firewall::v1::FirewallConfig;
+use ic_protobuf::registry::firewall::v1::{FirewallConfig, FirewallRule};
 use ic_protobuf::registry::replica_version::v1::ReplicaVersionRecord;
 use ic_protobuf::registry::subnet::v1::SubnetRecord;
+use ic_registry_client_helpers::crypto::CryptoRegistry;
 use ic_registry_client_helpers::firewall::FirewallRegistry;
 use ic_registry_client_helpers::subnet::SubnetRegistry;
+use ic_registry_keys::FirewallRulesScope;
 use ic_types::consensus::CatchUpPackage;
+use ic_types::crypto::threshold_sig::ThresholdSigPublicKey;
 use ic_types::{NodeId, RegistryVersion, ReplicaVersion, SubnetId};
 use std::convert::TryFrom;
 use std::sync::Arc;
@@ -84,6 +87,33 @@ impl RegistryHelper {
         }
     }
 
+    /// Return the public key of the root (NNS) subnet, i.e. the root key that
+    /// certified responses of the NNS are verified against
+    pub(crate) fn get_root_public_key(
+        &self,
+        version: RegistryVersion,
+    ) -> OrchestratorResult<ThresholdSigPublicKey> {
+        let root_subnet_id = self
+            .registry_client
+            .get_root_subnet_id(version)
+            .map_err(OrchestratorError::RegistryClientError)?
+            .ok_or_else(|| {
+                OrchestratorError::InvalidConfigurationError(format!(
+                    "No root subnet at registry version {}",
+                    version
+                ))
+            })?;
+        self.registry_client
+            .get_threshold_signing_public_key_for_subnet(root_subnet_id, version)
+            .map_err(OrchestratorError::RegistryClientError)?
+            .ok_or_else(|| {
+                OrchestratorError::InvalidConfigurationError(format!(
+                    "No public key for the root subnet {} at registry version {}",
+                    root_subnet_id, version
+                ))
+            })
+    }
+
     /// Return the `ReplicaVersionRecord` for the given replica version
     pub(crate) fn get_replica_version_record(
         &self,
@@ -121,6 +151,38 @@ impl RegistryHelper {
         }
     }
 
+    /// Returns the firewall rules that apply to this node in priority order:
+    /// the rules of the node itself, then those of its subnet and of all
+    /// replica nodes (if the node is assigned to a subnet), then the global
+    /// ones.
+    pub(crate) fn get_firewall_rules(
+        &self,
+        version: RegistryVersion,
+    ) -> OrchestratorResult<Vec<FirewallRule>> {
+        let mut scopes = vec![FirewallRulesScope::Node(self.node_id)];
+        match self.get_subnet_id(version) {
+            Ok(subnet_id) => {
+                scopes.push(FirewallRulesScope::Subnet(subnet_id));
+                scopes.push(FirewallRulesScope::ReplicaNodes);
+            }
+            Err(OrchestratorError::NodeUnassignedError(_, _)) => {}
+            Err(e) => return Err(e),
+        }
+        scopes.push(FirewallRulesScope::Global);
+
+        let mut rules = Vec::new();
+        for scope in scopes {
+            if let Some(rule_set) = self
+                .registry_client
+                .get_firewall_rules(version, &scope)
+                .map_err(OrchestratorError::RegistryClientError)?
+            {
+                rules.extend(rule_set.entries);
+            }
+        }
+        Ok(rules)
+    }
+
     pub(crate) fn get_registry_client(&self) -> Arc<dyn RegistryClient> {
         Arc::clone(&self.registry_client)
     }
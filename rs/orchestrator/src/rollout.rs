@@ -0,0 +1,563 @@
+//! Staged, health-gated rollout of replica versions.
+//!
+//! If the subnet record of a node's subnet contains a `RolloutPolicy`, the
+//! node only upgrades to a new replica version once its stage of the rollout
+//! has started: the canary nodes go first, and every subsequent stage covers
+//! a larger percentage of the subnet's nodes. A stage starts once every node
+//! of the previous stages has reported a healthy upgrade to the registry, and
+//! the rollout halts for all remaining nodes as soon as any node reports an
+//! unhealthy one.
+//!
+//! After upgrading, a node observes the rate at which its replica finalizes
+//! blocks for the duration configured in the policy and reports the outcome
+//! to the registry (see `report_upgrade_status` of the registry canister).
+//! Note that a subnet only finalizes blocks at the new replica version once
+//! at least 2f+1 of its nodes run it, so the registry only accepts policies
+//! whose first stage covers that many nodes; otherwise the first stage could
+//! never report a healthy upgrade.
+
+use crate::error::{OrchestratorError, OrchestratorResult};
+use crate::registration::generate_nonce;
+use crate::registry_helper::RegistryHelper;
+use candid::{Decode, Encode};
+use ic_canister_client::{ed25519_public_key_to_der, Agent, Sender, Signer};
+use ic_crypto::CryptoComponentForNonReplicaProcess;
+use ic_interfaces::crypto::DOMAIN_IC_REQUEST;
+use ic_logger::{info, warn, ReplicaLogger};
+use ic_nns_constants::REGISTRY_CANISTER_ID;
+use ic_protobuf::registry::subnet::v1::{
+    NodeUpgradeStatus, NodeUpgradeStatusRecord, RolloutPolicy,
+};
+use ic_registry_client_helpers::node::NodeRegistry;
+use ic_types::messages::MessageId;
+use ic_types::{NodeId, PrincipalId, RegistryVersion, ReplicaVersion, SubnetId};
+use registry_canister::mutations::node_management::do_report_upgrade_status::ReportUpgradeStatusPayload;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// The replica metric holding the height of the latest finalized batch.
+const FINALIZED_HEIGHT_METRIC: &str = "consensus_batch_height";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum RolloutDecision {
+    /// The node may upgrade.
+    Upgrade,
+    /// The node has to wait for the nodes of the previous stages.
+    Wait,
+    /// The given node reported an unhealthy upgrade, the rollout is halted.
+    Halted(NodeId),
+}
+
+/// Splits `members` into the stages of the rollout defined by `policy`: the
+/// first stage consists of the canary nodes, topped up with the remaining
+/// nodes (ordered by node ID) to the first of the given cumulative
+/// percentages, followed by stages for the other percentages and a final
+/// stage covering all nodes. Empty stages are omitted.
+pub(crate) fn rollout_stages(policy: &RolloutPolicy, members: &[NodeId]) -> Vec<Vec<NodeId>> {
+    let mut ordered: Vec<NodeId> = Vec::with_capacity(members.len());
+    for canary_node in &policy.canary_nodes {
+        if let Ok(id) = PrincipalId::try_from(canary_node.as_slice()) {
+            let node_id = NodeId::from(id);
+            if members.contains(&node_id) && !ordered.contains(&node_id) {
+                ordered.push(node_id);
+            }
+        }
+    }
+    let canary_count = ordered.len();
+    let mut others: Vec<NodeId> = members
+        .iter()
+        .filter(|node_id| !ordered.contains(node_id))
+        .copied()
+        .collect();
+    others.sort();
+    ordered.extend(others);
+
+    let total = ordered.len();
+    let mut boundaries: Vec<usize> = policy
+        .stage_percentages
+        .iter()
+        .map(|percentage| {
+            ((*percentage as usize * total + 99) / 100)
+                .min(total)
+                .max(canary_count)
+        })
+        .collect();
+    if boundaries.is_empty() {
+        boundaries.push(canary_count);
+    }
+    boundaries.push(total);
+
+    let mut stages = Vec::new();
+    let mut start = 0;
+    for end in boundaries {
+        if end > start {
+            stages.push(ordered[start..end].to_vec());
+            start = end;
+        }
+    }
+    stages
+}
+
+/// Decides whether `node_id` may upgrade to `replica_version` under `policy`,
+/// given the upgrade statuses reported by the nodes of the subnet.
+pub(crate) fn rollout_decision(
+    policy: &RolloutPolicy,
+    node_id: NodeId,
+    members: &[NodeId],
+    replica_version: &ReplicaVersion,
+    statuses: &BTreeMap<NodeId, NodeUpgradeStatusRecord>,
+) -> RolloutDecision {
+    let status_for_version = |node_id: &NodeId| {
+        statuses
+            .get(node_id)
+            .filter(|record| record.replica_version_id == replica_version.as_ref())
+            .and_then(|record| NodeUpgradeStatus::from_i32(record.status))
+    };
+
+    if let Some(unhealthy) = members
+        .iter()
+        .find(|id| status_for_version(id) == Some(NodeUpgradeStatus::Unhealthy))
+    {
+        return RolloutDecision::Halted(*unhealthy);
+    }
+
+    for stage in rollout_stages(policy, members) {
+        if stage.contains(&node_id) {
+            return RolloutDecision::Upgrade;
+        }
+        if stage
+            .iter()
+            .any(|id| status_for_version(id) != Some(NodeUpgradeStatus::Healthy))
+        {
+            return RolloutDecision::Wait;
+        }
+    }
+    // The node is not a member of the subnet (anymore), so there is nothing
+    // to stage.
+    RolloutDecision::Upgrade
+}
+
+/// The health check of the replica version the node currently runs.
+struct HealthCheck {
+    replica_version: ReplicaVersion,
+    started_at: Instant,
+    start_height: u64,
+    reported: bool,
+}
+
+/// Gates upgrades on the rollout policy of the node's subnet and reports the
+/// outcome of the node's own upgrades.
+pub(crate) struct StagedRollout {
+    node_id: NodeId,
+    registry: Arc<RegistryHelper>,
+    crypto: Arc<dyn CryptoComponentForNonReplicaProcess + Send + Sync>,
+    nns_urls: Vec<Url>,
+    replica_metrics_addr: Option<SocketAddr>,
+    health_check: Mutex<Option<HealthCheck>>,
+    logger: ReplicaLogger,
+}
+
+impl StagedRollout {
+    pub(crate) fn new(
+        node_id: NodeId,
+        registry: Arc<RegistryHelper>,
+        crypto: Arc<dyn CryptoComponentForNonReplicaProcess + Send + Sync>,
+        nns_urls: Vec<Url>,
+        replica_metrics_addr: Option<SocketAddr>,
+        logger: ReplicaLogger,
+    ) -> Self {
+        Self {
+            node_id,
+            registry,
+            crypto,
+            nns_urls,
+            replica_metrics_addr,
+            health_check: Mutex::new(None),
+            logger,
+        }
+    }
+
+    /// Returns whether this node may upgrade to `replica_version` according
+    /// to the rollout policy of `subnet_id` at the latest registry version.
+    pub(crate) fn may_upgrade(
+        &self,
+        subnet_id: SubnetId,
+        replica_version: &ReplicaVersion,
+    ) -> OrchestratorResult<bool> {
+        let version = self.registry.get_latest_version();
+        let subnet_record = self.registry.get_subnet_record(subnet_id, version)?;
+        let policy = match subnet_record.rollout_policy {
+            Some(policy) => policy,
+            None => return Ok(true),
+        };
+        let members: Vec<NodeId> = subnet_record
+            .membership
+            .iter()
+            .filter_map(|id| PrincipalId::try_from(id.as_slice()).ok())
+            .map(NodeId::from)
+            .collect();
+        let mut statuses = BTreeMap::new();
+        for node_id in &members {
+            if let Some(status) = self
+                .registry
+                .registry_client
+                .get_node_upgrade_status(*node_id, version)
+                .map_err(OrchestratorError::RegistryClientError)?
+            {
+                statuses.insert(*node_id, status);
+            }
+        }
+
+        match rollout_decision(&policy, self.node_id, &members, replica_version, &statuses) {
+            RolloutDecision::Upgrade => Ok(true),
+            RolloutDecision::Wait => {
+                info!(
+                    every_n_seconds => 300,
+                    self.logger,
+                    "Waiting for the previous stages of the rollout of {} to complete",
+                    replica_version
+                );
+                Ok(false)
+            }
+            RolloutDecision::Halted(node_id) => {
+                warn!(
+                    every_n_seconds => 300,
+                    self.logger,
+                    "Rollout of {} is halted: node {} reported an unhealthy upgrade",
+                    replica_version,
+                    node_id
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Runs the health check of `replica_version`, the version this node
+    /// runs, if it is the version of `subnet_id`, the subnet has a rollout
+    /// policy and the node has not reported on this version yet. Once the
+    /// health check has lasted as long as the policy requires, reports its
+    /// outcome to the registry.
+    pub(crate) async fn check_health(
+        &self,
+        subnet_id: SubnetId,
+        replica_version: &ReplicaVersion,
+    ) -> OrchestratorResult<()> {
+        let version = self.registry.get_latest_version();
+        let subnet_record = self.registry.get_subnet_record(subnet_id, version)?;
+        let policy = match subnet_record.rollout_policy {
+            Some(policy) if subnet_record.replica_version_id == replica_version.as_ref() => policy,
+            _ => return Ok(()),
+        };
+        let reported = self
+            .registry
+            .registry_client
+            .get_node_upgrade_status(self.node_id, version)
+            .map_err(OrchestratorError::RegistryClientError)?
+            .map_or(false, |record| {
+                record.replica_version_id == replica_version.as_ref()
+            });
+        if reported {
+            return Ok(());
+        }
+
+        let height = self.fetch_finalized_height().await?;
+        let finalization_rate = {
+            let mut health_check = self.health_check.lock().unwrap();
+            match health_check.as_mut() {
+                Some(check) if &check.replica_version == replica_version => {
+                    let elapsed = check.started_at.elapsed();
+                    if check.reported
+                        || elapsed < Duration::from_secs(policy.health_check_duration_seconds)
+                    {
+                        return Ok(());
+                    }
+                    let blocks = height.saturating_sub(check.start_height);
+                    let minutes = elapsed.as_secs_f64().max(1.0) / 60.0;
+                    (blocks as f64 / minutes).min(u32::MAX as f64) as u32
+                }
+                _ => {
+                    info!(
+                        self.logger,
+                        "Starting the health check of replica version {}", replica_version
+                    );
+                    *health_check = Some(HealthCheck {
+                        replica_version: replica_version.clone(),
+                        started_at: Instant::now(),
+                        start_height: height,
+                        reported: false,
+                    });
+                    return Ok(());
+                }
+            }
+        };
+
+        let healthy = finalization_rate >= policy.min_finalization_rate_per_minute;
+        info!(
+            self.logger,
+            "Reporting {} upgrade to replica version {} (finalization rate: {} blocks per minute)",
+            if healthy { "healthy" } else { "unhealthy" },
+            replica_version,
+            finalization_rate
+        );
+        self.report_upgrade_status(ReportUpgradeStatusPayload {
+            replica_version_id: replica_version.to_string(),
+            healthy,
+            finalization_rate_per_minute: finalization_rate,
+        })
+        .await?;
+        if let Some(check) = self.health_check.lock().unwrap().as_mut() {
+            check.reported = true;
+        }
+        Ok(())
+    }
+
+    /// Scrapes the height of the latest finalized batch from the metrics
+    /// endpoint of the replica.
+    async fn fetch_finalized_height(&self) -> OrchestratorResult<u64> {
+        let mut addr = self.replica_metrics_addr.ok_or_else(|| {
+            OrchestratorError::invalid_configuration_error(
+                "The replica metrics endpoint is not configured",
+            )
+        })?;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        let uri: hyper::Uri = format!("http://{}/", addr)
+            .parse()
+            .map_err(|err| OrchestratorError::UpgradeError(format!("{:?}", err)))?;
+        let response = hyper::Client::new().get(uri).await.map_err(|err| {
+            OrchestratorError::UpgradeError(format!("Failed to fetch replica metrics: {}", err))
+        })?;
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|err| {
+                OrchestratorError::UpgradeError(format!("Failed to read replica metrics: {}", err))
+            })?;
+        parse_finalized_height(&String::from_utf8_lossy(&body)).ok_or_else(|| {
+            OrchestratorError::UpgradeError(format!(
+                "Replica metrics do not contain {}",
+                FINALIZED_HEIGHT_METRIC
+            ))
+        })
+    }
+
+    /// Sends `payload` to the registry canister in an update call signed with
+    /// the node signing key of this node.
+    async fn report_upgrade_status(
+        &self,
+        payload: ReportUpgradeStatusPayload,
+    ) -> OrchestratorResult<()> {
+        let pub_key = self
+            .crypto
+            .node_public_keys()
+            .node_signing_pk
+            .ok_or_else(|| {
+                OrchestratorError::UpgradeError("The node signing key is missing".to_string())
+            })?
+            .key_value;
+        let registry_version = self.registry.get_latest_version();
+        let sender = Sender::from_signer(Arc::new(NodeSigningKeySigner {
+            crypto: Arc::clone(&self.crypto),
+            node_id: self.node_id,
+            registry_version,
+            public_key_der: ed25519_public_key_to_der(pub_key),
+        }));
+        let payload = Encode!(&payload).expect("Could not encode the upgrade status payload");
+        let root_key = self.registry.get_root_public_key(registry_version)?;
+
+        let mut last_error = "No NNS URL is configured".to_string();
+        for nns_url in &self.nns_urls {
+            let agent = Agent::new(nns_url.clone(), sender.clone()).with_root_key(root_key);
+            match agent
+                .execute_update(
+                    &REGISTRY_CANISTER_ID,
+                    "report_upgrade_status",
+                    payload.clone(),
+                    generate_nonce(),
+                )
+                .await
+            {
+                Ok(Some(response)) => {
+                    return Decode!(&response, Result<(), String>)
+                        .map_err(|err| err.to_string())
+                        .and_then(|result| result)
+                        .map_err(OrchestratorError::UpgradeError)
+                }
+                Ok(None) => last_error = "No response from the registry canister".to_string(),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(OrchestratorError::UpgradeError(format!(
+            "Failed to report the upgrade status: {}",
+            last_error
+        )))
+    }
+}
+
+/// Signs requests with the node signing key of this node, so that the
+/// registry canister can authenticate the node as the caller.
+struct NodeSigningKeySigner {
+    crypto: Arc<dyn CryptoComponentForNonReplicaProcess + Send + Sync>,
+    node_id: NodeId,
+    registry_version: RegistryVersion,
+    public_key_der: Vec<u8>,
+}
+
+impl Signer for NodeSigningKeySigner {
+    fn sender_pubkey_der(&self) -> Vec<u8> {
+        self.public_key_der.clone()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let message_id = MessageId::try_from(msg.strip_prefix(DOMAIN_IC_REQUEST).unwrap_or(msg))?;
+        let signature = self
+            .crypto
+            .sign_basic(&message_id, self.node_id, self.registry_version)?;
+        Ok(signature.get().0)
+    }
+}
+
+/// Parses the height of the latest finalized batch from the Prometheus text
+/// exposition of the replica metrics.
+fn parse_finalized_height(metrics: &str) -> Option<u64> {
+    metrics.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next() != Some(FINALIZED_HEIGHT_METRIC) {
+            return None;
+        }
+        parts
+            .next()
+            .and_then(|value| value.parse::<f64>().ok())
+            .map(|value| value as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(n: u64) -> NodeId {
+        NodeId::from(PrincipalId::new_node_test_id(n))
+    }
+
+    fn policy(canary_nodes: &[NodeId], stage_percentages: Vec<u32>) -> RolloutPolicy {
+        RolloutPolicy {
+            canary_nodes: canary_nodes.iter().map(|id| id.get().to_vec()).collect(),
+            stage_percentages,
+            min_finalization_rate_per_minute: 10,
+            health_check_duration_seconds: 600,
+        }
+    }
+
+    fn status(version: &str, status: NodeUpgradeStatus) -> NodeUpgradeStatusRecord {
+        NodeUpgradeStatusRecord {
+            replica_version_id: version.to_string(),
+            status: status as i32,
+            finalization_rate_per_minute: 0,
+        }
+    }
+
+    #[test]
+    fn stages_start_with_canaries_and_cover_all_nodes() {
+        let mut members: Vec<NodeId> = (1..=10).map(node).collect();
+        members.reverse();
+        let stages = rollout_stages(&policy(&[node(7), node(42)], vec![70, 90]), &members);
+
+        let mut others: Vec<NodeId> = members
+            .iter()
+            .filter(|id| **id != node(7))
+            .copied()
+            .collect();
+        others.sort();
+        let mut first_stage = vec![node(7)];
+        first_stage.extend_from_slice(&others[..6]);
+        assert_eq!(
+            stages,
+            vec![first_stage, others[6..8].to_vec(), others[8..].to_vec(),]
+        );
+    }
+
+    #[test]
+    fn canaries_alone_form_the_first_stage_if_they_exceed_it() {
+        let members: Vec<NodeId> = (1..=4).map(node).collect();
+        let stages = rollout_stages(&policy(&[node(2), node(3), node(4)], vec![50]), &members);
+
+        assert_eq!(stages, vec![vec![node(2), node(3), node(4)], vec![node(1)]]);
+    }
+
+    #[test]
+    fn nodes_wait_for_healthy_previous_stages() {
+        let members: Vec<NodeId> = (1..=4).map(node).collect();
+        let policy = policy(&[node(4)], vec![75]);
+        let version = ReplicaVersion::try_from("new").unwrap();
+        let stages = rollout_stages(&policy, &members);
+        assert_eq!(stages, vec![vec![node(4), node(1), node(2)], vec![node(3)]]);
+        let mut statuses = BTreeMap::new();
+
+        for first_stage_node in &stages[0] {
+            assert_eq!(
+                rollout_decision(&policy, *first_stage_node, &members, &version, &statuses),
+                RolloutDecision::Upgrade
+            );
+        }
+        assert_eq!(
+            rollout_decision(&policy, node(3), &members, &version, &statuses),
+            RolloutDecision::Wait
+        );
+
+        // A healthy status for another version does not count.
+        for first_stage_node in &stages[0] {
+            statuses.insert(*first_stage_node, status("old", NodeUpgradeStatus::Healthy));
+        }
+        assert_eq!(
+            rollout_decision(&policy, node(3), &members, &version, &statuses),
+            RolloutDecision::Wait
+        );
+
+        // The whole first stage has to be healthy.
+        statuses.insert(node(4), status("new", NodeUpgradeStatus::Healthy));
+        statuses.insert(node(1), status("new", NodeUpgradeStatus::Healthy));
+        assert_eq!(
+            rollout_decision(&policy, node(3), &members, &version, &statuses),
+            RolloutDecision::Wait
+        );
+
+        statuses.insert(node(2), status("new", NodeUpgradeStatus::Healthy));
+        assert_eq!(
+            rollout_decision(&policy, node(3), &members, &version, &statuses),
+            RolloutDecision::Upgrade
+        );
+    }
+
+    #[test]
+    fn unhealthy_upgrade_halts_rollout() {
+        let members: Vec<NodeId> = (1..=4).map(node).collect();
+        let policy = policy(&[node(1)], vec![]);
+        let version = ReplicaVersion::try_from("new").unwrap();
+        let mut statuses = BTreeMap::new();
+        statuses.insert(node(1), status("new", NodeUpgradeStatus::Unhealthy));
+
+        assert_eq!(
+            rollout_decision(&policy, node(2), &members, &version, &statuses),
+            RolloutDecision::Halted(node(1))
+        );
+    }
+
+    #[test]
+    fn parses_finalized_height_from_metrics() {
+        let metrics = "# HELP consensus_batch_height The height of batches\n\
+                       # TYPE consensus_batch_height gauge\n\
+                       consensus_batch_height 1234\n\
+                       consensus_batch_height_other 1\n";
+        assert_eq!(parse_finalized_height(metrics), Some(1234));
+        assert_eq!(parse_finalized_height("other_metric 1\n"), None);
+    }
+}
@@ -2,6 +2,7 @@ use crate::catch_up_package_provider::CatchUpPackageProvider;
 use crate::error::{OrchestratorError, OrchestratorResult};
 use crate::registry_helper::RegistryHelper;
 use crate::replica_process::ReplicaProcess;
+use crate::rollout::StagedRollout;
 use crate::utils;
 use ic_http_utils::file_downloader::FileDownloader;
 use ic_interfaces::registry::RegistryClient;
@@ -42,6 +43,7 @@ pub(crate) struct Upgrade {
     release_content_dir: PathBuf,
     logger: ReplicaLogger,
     node_id: NodeId,
+    rollout: StagedRollout,
 }
 
 impl Upgrade {
@@ -56,6 +58,7 @@ impl Upgrade {
         ic_binary_dir: PathBuf,
         registry_replicator: Arc<RegistryReplicator>,
         release_content_dir: PathBuf,
+        rollout: StagedRollout,
         logger: ReplicaLogger,
     ) -> Self {
         let value = Self {
@@ -68,6 +71,7 @@ impl Upgrade {
             release_content_dir,
             ic_binary_dir,
             registry_replicator,
+            rollout,
             logger,
         };
         value.confirm_boot();
@@ -152,7 +156,11 @@ impl Upgrade {
         let new_replica_version = self
             .registry
             .get_replica_version(subnet_id, cup_registry_version)?;
-        if new_replica_version != self.replica_version {
+        // If the subnet has a rollout policy, we only upgrade once our stage of
+        // the rollout has started, and keep running the current version until then.
+        if new_replica_version != self.replica_version
+            && self.rollout.may_upgrade(subnet_id, &new_replica_version)?
+        {
             info!(
                 self.logger,
                 "Starting version upgrade: {} -> {}", self.replica_version, new_replica_version
@@ -163,8 +171,8 @@ impl Upgrade {
             return self.download_and_upgrade(&new_replica_version).await;
         }
 
-        // If we arrive here, we are on the newest replica version.
-        // Now we check if a subnet recovery is in progress.
+        // If we arrive here, we are on the newest replica version (or waiting for
+        // our stage of its rollout). Now we check if a subnet recovery is in progress.
         // If it is, we restart to pass the unsigned CUP to consensus.
         self.stop_replica_if_new_recovery_cup(&cup.cup, old_cup_height);
 
@@ -175,6 +183,20 @@ impl Upgrade {
         // not arrive at the corresponding CUP yet.
         self.download_image_if_upgrade_scheduled(subnet_id).await?;
 
+        // This will report the outcome of the upgrade to the current version once its
+        // health check is complete, if the subnet has a rollout policy.
+        if let Err(err) = self
+            .rollout
+            .check_health(subnet_id, &self.replica_version)
+            .await
+        {
+            warn!(
+                every_n_seconds => 300,
+                self.logger,
+                "Health check of replica version {} failed: {}", self.replica_version, err
+            );
+        }
+
         Ok(Some(subnet_id))
     }
 
@@ -563,3 +585,176 @@ fn remove_node_state(replica_config_file: PathBuf, cup_path: PathBuf) -> Result<
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto_helper::setup_crypto;
+    use ic_config::crypto::CryptoConfig;
+    use ic_crypto::utils::get_node_keys_or_generate_if_missing;
+    use ic_crypto::CryptoComponentForNonReplicaProcess;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_protobuf::registry::subnet::v1::{
+        NodeUpgradeStatus, NodeUpgradeStatusRecord, RolloutPolicy,
+    };
+    use ic_registry_keys::make_node_upgrade_status_record_key;
+    use ic_test_utilities::types::ids::{node_test_id, subnet_test_id};
+    use ic_test_utilities_registry::{setup_registry_non_final, SubnetRecordBuilder};
+    use std::path::Path;
+
+    const OLD_VERSION: &str = "old";
+    const NEW_VERSION: &str = "new";
+
+    // Runs `Upgrade::check` on `node_id` of a subnet of four nodes that is
+    // rolling out `NEW_VERSION` in two stages: node 4 (the canary) together
+    // with nodes 1 and 2, followed by node 3. Returns whether the node tried
+    // to upgrade, i.e. started downloading the release package.
+    fn check_upgrade(
+        node_id: NodeId,
+        statuses: Vec<(NodeId, NodeUpgradeStatus)>,
+        config: &CryptoConfig,
+        registry_replicator: Arc<RegistryReplicator>,
+        dir: &Path,
+    ) -> bool {
+        let subnet_id = subnet_test_id(1);
+        let members: Vec<NodeId> = (1..=4).map(node_test_id).collect();
+        let mut record = SubnetRecordBuilder::from(&members)
+            .with_replica_version(NEW_VERSION)
+            .build();
+        record.rollout_policy = Some(RolloutPolicy {
+            canary_nodes: vec![node_test_id(4).get().to_vec()],
+            stage_percentages: vec![75],
+            min_finalization_rate_per_minute: 10,
+            health_check_duration_seconds: 600,
+        });
+        let (data_provider, registry_client) =
+            setup_registry_non_final(subnet_id, vec![(1, record)]);
+        for (node_id, status) in statuses {
+            data_provider
+                .add(
+                    &make_node_upgrade_status_record_key(node_id),
+                    RegistryVersion::from(1),
+                    Some(NodeUpgradeStatusRecord {
+                        replica_version_id: NEW_VERSION.to_string(),
+                        status: status as i32,
+                        finalization_rate_per_minute: 60,
+                    }),
+                )
+                .unwrap();
+        }
+        registry_client.update_to_latest_version();
+
+        let logger = no_op_logger();
+        let registry = Arc::new(RegistryHelper::new(
+            node_id,
+            registry_client.clone() as Arc<dyn RegistryClient>,
+            logger.clone(),
+        ));
+        let crypto: Arc<dyn CryptoComponentForNonReplicaProcess + Send + Sync> =
+            Arc::new(setup_crypto(config, registry_client, logger.clone()));
+        let release_content_dir = dir.join("releases");
+        let upgrade = Upgrade::new(
+            Arc::clone(&registry),
+            Arc::new(Mutex::new(ReplicaProcess::new(
+                logger.inner_logger.root.clone(),
+            ))),
+            Arc::new(CatchUpPackageProvider::new(
+                Arc::clone(&registry),
+                dir.to_path_buf(),
+                crypto.clone(),
+                logger.clone(),
+            )),
+            ReplicaVersion::try_from(OLD_VERSION).unwrap(),
+            dir.join("ic.json5"),
+            node_id,
+            // There is no replica binary, so starting the replica fails.
+            dir.join("bin"),
+            registry_replicator,
+            release_content_dir.clone(),
+            StagedRollout::new(node_id, registry, crypto, vec![], None, logger.clone()),
+            logger,
+        );
+
+        let result = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(upgrade.check());
+        // Neither path gets to complete the check: an upgrade fails to find
+        // the release package of the new version, and otherwise the replica
+        // of the old version cannot be started.
+        let tried_to_upgrade = release_content_dir.join(NEW_VERSION).exists();
+        if tried_to_upgrade {
+            assert!(
+                matches!(
+                    result,
+                    Err(OrchestratorError::ReplicaVersionMissingError(..))
+                ),
+                "{:?}",
+                result
+            );
+        } else {
+            assert!(
+                matches!(result, Err(OrchestratorError::IoError(..))),
+                "{:?}",
+                result
+            );
+        }
+        tried_to_upgrade
+    }
+
+    #[test]
+    fn check_upgrades_according_to_rollout_policy() {
+        CryptoConfig::run_with_temp_config(|config| {
+            get_node_keys_or_generate_if_missing(&config.crypto_root);
+            // The replicator registers its metrics globally, so it is shared
+            // by all checks.
+            let local_store_dir = tempfile::tempdir().unwrap();
+            let registry_replicator = Arc::new(RegistryReplicator::new(
+                no_op_logger(),
+                None,
+                local_store_dir.path().to_path_buf(),
+                Duration::from_secs(1),
+            ));
+            let run = |node_id, statuses| {
+                let dir = tempfile::tempdir().unwrap();
+                check_upgrade(
+                    node_id,
+                    statuses,
+                    &config,
+                    Arc::clone(&registry_replicator),
+                    dir.path(),
+                )
+            };
+
+            // The nodes of the first stage upgrade right away.
+            for node in &[1, 2, 4] {
+                assert!(run(node_test_id(*node), vec![]));
+            }
+            // The last node waits for every node of the first stage to be healthy.
+            assert!(!run(node_test_id(3), vec![]));
+            assert!(!run(
+                node_test_id(3),
+                vec![
+                    (node_test_id(4), NodeUpgradeStatus::Healthy),
+                    (node_test_id(1), NodeUpgradeStatus::Healthy),
+                ]
+            ));
+            assert!(run(
+                node_test_id(3),
+                vec![
+                    (node_test_id(4), NodeUpgradeStatus::Healthy),
+                    (node_test_id(1), NodeUpgradeStatus::Healthy),
+                    (node_test_id(2), NodeUpgradeStatus::Healthy),
+                ]
+            ));
+            // An unhealthy upgrade halts the rollout.
+            assert!(!run(
+                node_test_id(3),
+                vec![
+                    (node_test_id(4), NodeUpgradeStatus::Healthy),
+                    (node_test_id(1), NodeUpgradeStatus::Unhealthy),
+                    (node_test_id(2), NodeUpgradeStatus::Healthy),
+                ]
+            ));
+        });
+    }
+}
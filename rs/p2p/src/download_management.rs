@@ -77,6 +77,7 @@ use crate::{
         GossipMessage, GossipRetransmissionRequest, Percentage,
     },
     metrics::{DownloadManagementMetrics, DownloadPrioritizerMetrics},
+    peer_throughput::{median_bytes_per_sec, PeerThroughput},
     utils::FlowMapper,
     P2PError, P2PErrorCode, P2PResult,
 };
@@ -98,7 +99,7 @@ use ic_protobuf::{
 use ic_registry_client_helpers::subnet::SubnetTransportRegistry;
 use ic_types::{
     artifact::{Artifact, ArtifactId},
-    chunkable::{ArtifactErrorCode, ChunkId},
+    chunkable::{ArtifactChunk, ArtifactChunkData, ArtifactErrorCode, ChunkId},
     crypto::CryptoHash,
     p2p::GossipAdvert,
     NodeId, RegistryVersion, SubnetId,
@@ -106,7 +107,7 @@ use ic_types::{
 use lru::LruCache;
 use rand::{seq::SliceRandom, thread_rng};
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     error::Error,
     ops::DerefMut,
     sync::{Arc, Mutex, RwLock},
@@ -228,6 +229,8 @@ pub struct PeerContext {
     disconnect_time: Option<SystemTime>,
     /// The time of the last processed retransmission request from this peer.
     last_retransmission_request_processed_time: Instant,
+    /// The observed download statistics of this peer.
+    throughput: PeerThroughput,
 }
 
 /// A `NodeId` can be converted into a `PeerContext`.
@@ -240,6 +243,7 @@ impl From<NodeId> for PeerContext {
             requested: HashMap::new(),
             disconnect_time: None,
             last_retransmission_request_processed_time: Instant::now(),
+            throughput: PeerThroughput::default(),
         }
     }
 }
@@ -422,12 +426,14 @@ impl DownloadManager for DownloadManagerImpl {
 
         // Remove the chunk request tracker.
         let mut current_peers = self.current_peers.lock().unwrap();
+        let mut requested_from_peer = false;
         if let Some(peer_context) = current_peers.get_mut(&peer_id) {
             if let Some(tracker) = peer_context.requested.remove(&GossipRequestTrackerKey {
                 artifact_id: gossip_chunk.artifact_id.clone(),
                 integrity_hash: gossip_chunk.integrity_hash.clone(),
                 chunk_id: gossip_chunk.chunk_id,
             }) {
+                requested_from_peer = true;
                 let artifact_type = match &gossip_chunk.artifact_id {
                     ArtifactId::ConsensusMessage(_) => "consensus",
                     ArtifactId::CanisterHttpMessage(_) => "canister_http",
@@ -438,10 +444,27 @@ impl DownloadManager for DownloadManagerImpl {
                     ArtifactId::FileTreeSync(_) => "file_tree_sync",
                     ArtifactId::StateSync(_) => "state_sync",
                 };
+                let elapsed = tracker.requested_instant.elapsed();
                 self.metrics
                     .chunk_delivery_time
                     .with_label_values(&[artifact_type])
-                    .observe(tracker.requested_instant.elapsed().as_millis() as f64);
+                    .observe(elapsed.as_millis() as f64);
+
+                // Update the download statistics of the peer.
+                match &gossip_chunk.artifact_chunk {
+                    Ok(artifact_chunk) => {
+                        let bytes = semi_structured_chunk_size(artifact_chunk);
+                        peer_context.throughput.on_success(bytes, elapsed);
+                        if let Some(bytes) = bytes {
+                            self.metrics
+                                .state_sync_peer_bytes_received
+                                .with_label_values(&[&peer_id.to_string()])
+                                .inc_by(bytes as u64);
+                        }
+                    }
+                    Err(_) => peer_context.throughput.on_failure(),
+                }
+                self.observe_peer_throughput(&peer_id, &peer_context.throughput);
             } else {
                 trace!(
                     self.log,
@@ -476,6 +499,20 @@ impl DownloadManager for DownloadManagerImpl {
                 gossip_chunk.chunk_id,
                 peer_id
             );
+            // Release the chunk so that it can be requested from the other
+            // peers that advertised the artifact. Only do so if the chunk was
+            // actually requested from this peer: an unsolicited error must not
+            // release a chunk that is being downloaded from another peer.
+            let retry_peers = if requested_from_peer {
+                self.release_chunk(
+                    &peer_id,
+                    gossip_chunk.artifact_id.clone(),
+                    gossip_chunk.integrity_hash.clone(),
+                    gossip_chunk.chunk_id,
+                )
+            } else {
+                Vec::new()
+            };
             if let P2PErrorCode::NotFound = error.p2p_error_code {
                 // If the artifact is not found on the sender's side, drop the
                 // advert from the context for this peer to prevent it from
@@ -490,6 +527,10 @@ impl DownloadManager for DownloadManagerImpl {
                         .deref_mut(),
                 )
             }
+            std::mem::drop(current_peers);
+            for retry_peer in retry_peers {
+                let _ = self.download_next(retry_peer);
+            }
             return;
         }
 
@@ -815,22 +856,21 @@ impl DownloadManager for DownloadManagerImpl {
             self.refresh_registry();
         }
 
-        // Collect the peers with timed-out requests.
-        let mut timed_out_peers = Vec::new();
+        // Collect the peers with timed-out requests together with the peers
+        // that the timed-out chunks can be requested from instead.
+        let mut timed_out_peers = BTreeSet::new();
         for (node_id, peer_context) in self.current_peers.lock().unwrap().iter_mut() {
-            if self.process_timed_out_requests(node_id, peer_context) {
-                timed_out_peers.push(*node_id);
-            }
+            timed_out_peers.extend(self.process_timed_out_requests(node_id, peer_context));
         }
 
         // Process timed-out artifacts.
         self.process_timed_out_artifacts();
 
         // Compute the set of peers that need to be evaluated by the download manager.
-        let peer_ids = if update_priority_fns {
-            self.peer_manager.get_current_peer_ids().into_iter()
+        let peer_ids: Vec<NodeId> = if update_priority_fns {
+            self.peer_manager.get_current_peer_ids()
         } else {
-            timed_out_peers.into_iter()
+            timed_out_peers.into_iter().collect()
         };
 
         // Invoke download_next(i) for each peer i.
@@ -1088,7 +1128,8 @@ impl DownloadManagerImpl {
     ///
     /// A peer may not be ready for downloads for various reasons:
     ///
-    /// a) The peer's download request capacity has been reached.</br>
+    /// a) The peer's download request capacity, as returned by
+    /// `max_streams()`, has been reached.</br>
     /// b) The peer is not a current peer (e.g., it is an unknown peer or a peer
     /// that was removed)</br>
     /// c) The peer was disconnected (TODO -  P2P512)
@@ -1101,8 +1142,7 @@ impl DownloadManagerImpl {
             // Check that the peer is present and
             // there is available capacity to stream chunks from this peer.
             Some(peer_context)
-                if peer_context.requested.len()
-                    < self.gossip_config.max_artifact_streams_per_peer as usize =>
+                if peer_context.requested.len() < self.max_streams(&peer_id, peer_dictionary) =>
             {
                 Ok(peer_context)
             }
@@ -1188,7 +1228,11 @@ impl DownloadManagerImpl {
         let mut current_peers = self.current_peers.lock().unwrap();
         let peer_context = self.is_peer_ready_for_download(peer_id, &current_peers)?;
         let requested_instant = Instant::now(); // function granularity for instant is good enough
-        let max_streams_per_peer = self.gossip_config.max_artifact_streams_per_peer as usize;
+        let max_streams_per_peer = self.max_streams(&peer_id, &current_peers);
+        self.metrics
+            .peer_max_streams
+            .with_label_values(&[&peer_id.to_string()])
+            .set(max_streams_per_peer as i64);
 
         assert!(peer_context.requested.len() <= max_streams_per_peer);
         let num_downloadable_chunks = max_streams_per_peer - peer_context.requested.len();
@@ -1320,57 +1364,80 @@ impl DownloadManagerImpl {
 
     /// The method processes timed-out requests
     ///
-    /// This method is called by the method on_timer(). It checks if there are
-    /// any chunk requests that timed out from the given peer and returns
-    /// "true" if this is the case.
-    fn process_timed_out_requests(&self, node_id: &NodeId, peer_context: &mut PeerContext) -> bool {
+    /// This method is called by the method on_timer(). It drops the chunk
+    /// requests to the given peer that timed out, as well as the requests
+    /// that stalled for much longer than the peer usually takes to deliver a
+    /// chunk if other peers advertised the artifact. It returns the given
+    /// peer if any request was dropped, together with the other peers that
+    /// the dropped chunks can be requested from.
+    fn process_timed_out_requests(
+        &self,
+        node_id: &NodeId,
+        peer_context: &mut PeerContext,
+    ) -> BTreeSet<NodeId> {
         // Mark time-out chunks.
         let mut timed_out_chunks: Vec<_> = Vec::new();
-        let mut peer_timed_out: bool = false;
+        let throughput = peer_context.throughput.clone();
         peer_context.requested.retain(|key, tracker| {
-            let timed_out = tracker.requested_instant.elapsed().as_millis()
-                >= self.gossip_config.max_chunk_wait_ms as u128;
+            let elapsed = tracker.requested_instant.elapsed();
+            let timed_out = elapsed.as_millis() >= self.gossip_config.max_chunk_wait_ms as u128;
+            let stalled = !timed_out
+                && throughput.is_stalled(elapsed)
+                && self.has_other_advertisers(node_id, &key.artifact_id, &key.integrity_hash);
             if timed_out {
                 self.metrics.chunks_timed_out.inc();
+            }
+            if stalled {
+                self.metrics.chunks_download_retry_attempts.inc();
+            }
+            if timed_out || stalled {
                 timed_out_chunks.push((
                     *node_id,
                     key.chunk_id,
                     key.artifact_id.clone(),
                     key.integrity_hash.clone(),
                 ));
-                peer_timed_out = true;
                 trace!(
                     self.log,
-                    "Chunk timeout Key {:?} Tracker {:?} elapsed{:?} requested {:?} Now {:?}",
+                    "Chunk timeout Key {:?} Tracker {:?} elapsed{:?} requested {:?} Now {:?} stalled {:?}",
                     key,
                     tracker,
-                    tracker.requested_instant.elapsed().as_millis(),
+                    elapsed.as_millis(),
                     tracker.requested_instant,
-                    std::time::Instant::now()
+                    std::time::Instant::now(),
+                    stalled
                 )
             }
-            // Retain chunks that have not timed out.
-            !timed_out
+            // Retain chunks that have neither timed out nor stalled.
+            !(timed_out || stalled)
         });
 
+        let mut retry_peers = BTreeSet::new();
+        if !timed_out_chunks.is_empty() {
+            retry_peers.insert(*node_id);
+        }
         for (node_id, chunk_id, artifact_id, integrity_hash) in timed_out_chunks.into_iter() {
-            self.process_timed_out_chunk(&node_id, artifact_id, integrity_hash, chunk_id)
+            peer_context.throughput.on_failure();
+            retry_peers.extend(self.release_chunk(&node_id, artifact_id, integrity_hash, chunk_id));
         }
+        self.observe_peer_throughput(node_id, &peer_context.throughput);
 
-        peer_timed_out
+        retry_peers
     }
 
-    /// The method processes a timed-out chunk.
-    fn process_timed_out_chunk(
+    /// The method releases a chunk whose request to the given peer timed out
+    /// or failed, and returns the other peers that advertised the artifact,
+    /// which the chunk can be requested from instead.
+    fn release_chunk(
         &self,
         node_id: &NodeId,
         artifact_id: ArtifactId,
         integrity_hash: CryptoHash,
         chunk_id: ChunkId,
-    ) {
+    ) -> Vec<NodeId> {
         // Drop it and switch the preferred primary so that the next node that
         // advertised the chunk picks it up.
-        let _ = self
+        let retry_peers: Vec<NodeId> = self
             .prioritizer
             .get_advert_tracker(&artifact_id, &integrity_hash)
             .map(|advert_tracker| {
@@ -1383,10 +1450,80 @@ impl DownloadManagerImpl {
                 if advert_tracker.is_attempts_round_complete(chunk_id) {
                     advert_tracker.attempts_round_reset(chunk_id)
                 }
-            });
+                advert_tracker
+                    .peers
+                    .iter()
+                    .filter(|peer_id| *peer_id != node_id)
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default();
         #[rustfmt::skip]
-        trace!(self.log, "Timed-out: Peer{:?} Artifact{:?} Chunk{:?}",
+        trace!(self.log, "Released: Peer{:?} Artifact{:?} Chunk{:?}",
                node_id, chunk_id, artifact_id);
+        retry_peers
+    }
+
+    /// The method returns true if peers other than the given peer advertised
+    /// the given artifact.
+    fn has_other_advertisers(
+        &self,
+        node_id: &NodeId,
+        artifact_id: &ArtifactId,
+        integrity_hash: &CryptoHash,
+    ) -> bool {
+        self.prioritizer
+            .get_advert_tracker(artifact_id, integrity_hash)
+            .map_or(false, |advert_tracker| {
+                advert_tracker
+                    .read()
+                    .unwrap()
+                    .peers
+                    .iter()
+                    .any(|peer_id| peer_id != node_id)
+            })
+    }
+
+    /// The method returns the number of chunks that may be requested from the
+    /// given peer in parallel.
+    ///
+    /// The configured maximum number of artifact streams per peer is scaled
+    /// with the throughput of the peer relative to the median throughput of
+    /// all peers, and with its failure rate.
+    fn max_streams(&self, peer_id: &NodeId, peers: &PeerContextDictionary) -> usize {
+        let max_streams = self.gossip_config.max_artifact_streams_per_peer as usize;
+        let reference_bytes_per_sec =
+            median_bytes_per_sec(peers.values().map(|peer_context| &peer_context.throughput));
+        peers.get(peer_id).map_or(max_streams, |peer_context| {
+            peer_context
+                .throughput
+                .max_streams(max_streams, reference_bytes_per_sec)
+        })
+    }
+
+    /// The method exports the download statistics of the given peer.
+    fn observe_peer_throughput(&self, peer_id: &NodeId, throughput: &PeerThroughput) {
+        let peer_label = peer_id.to_string();
+        if let Some(bytes_per_sec) = throughput.bytes_per_sec() {
+            self.metrics
+                .state_sync_peer_throughput
+                .with_label_values(&[&peer_label])
+                .set(bytes_per_sec);
+        }
+        self.metrics
+            .peer_chunk_failure_rate
+            .with_label_values(&[&peer_label])
+            .set(throughput.failure_rate());
+    }
+}
+
+/// The function returns the size of a chunk of a chunked artifact, such as a
+/// state sync chunk. The size of unit chunks, which carry a complete
+/// artifact, is not tracked.
+fn semi_structured_chunk_size(artifact_chunk: &ArtifactChunk) -> Option<usize> {
+    match &artifact_chunk.artifact_chunk_data {
+        ArtifactChunkData::SemiStructuredChunkData(data) => Some(data.len()),
+        ArtifactChunkData::UnitChunkData(_) => None,
     }
 }
 
@@ -2177,6 +2314,127 @@ pub mod tests {
         }
     }
 
+    /// The function tests that peers that delivered chunks at a higher
+    /// throughput are asked for more chunks in parallel.
+    #[tokio::test]
+    async fn download_manager_fast_peers_get_more_streams() {
+        let num_replicas = 4;
+        let logger = p2p_test_setup_logger();
+        let download_manager =
+            new_test_download_manager(num_replicas, &logger, tokio::runtime::Handle::current());
+        let max_streams = download_manager.gossip_config.max_artifact_streams_per_peer as usize;
+
+        // Node 1 is four times as fast as nodes 2 and 3.
+        {
+            let mut current_peers = download_manager.current_peers.lock().unwrap();
+            for (peer_id, bytes_per_sec) in vec![(1, 4000), (2, 1000), (3, 1000)] {
+                current_peers
+                    .get_mut(&node_test_id(peer_id))
+                    .unwrap()
+                    .throughput
+                    .on_success(Some(bytes_per_sec), std::time::Duration::from_secs(1));
+            }
+        }
+
+        for peer_id in 1..num_replicas {
+            test_add_adverts(&download_manager, 0..1000, node_test_id(peer_id as u64));
+        }
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(1))
+            .unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 2 * max_streams);
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(2))
+            .unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), max_streams);
+    }
+
+    /// The function tests that a chunk that a peer failed to serve is
+    /// requested from another peer that advertised the artifact.
+    #[tokio::test]
+    async fn download_manager_failed_chunk_is_requested_from_other_peer() {
+        let num_replicas = 3;
+        let logger = p2p_test_setup_logger();
+        let download_manager =
+            new_test_download_manager(num_replicas, &logger, tokio::runtime::Handle::current());
+
+        // Node 1 and 2 both advertise advert 0, which is downloaded from node 1.
+        for peer_id in 1..num_replicas {
+            test_add_adverts(&download_manager, 0..1, node_test_id(peer_id as u64));
+        }
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(1))
+            .unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 1);
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(2))
+            .unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 0);
+
+        // Node 1 fails to serve the chunk.
+        download_manager.on_chunk(
+            GossipChunk {
+                artifact_id: ArtifactId::FileTreeSync(0.to_string()),
+                integrity_hash: CryptoHash(Vec::from(0u32.to_be_bytes())),
+                chunk_id: ChunkId::from(0),
+                artifact_chunk: Err(P2PError {
+                    p2p_error_code: P2PErrorCode::NotFound,
+                }),
+            },
+            node_test_id(1),
+        );
+
+        // The chunk is now requested from node 2.
+        let current_peers = download_manager.current_peers.lock().unwrap();
+        let peer_context = current_peers.get(&node_test_id(1)).unwrap();
+        assert!(peer_context.requested.is_empty());
+        assert!(peer_context.throughput.failure_rate() > 0.0);
+        let peer_context = current_peers.get(&node_test_id(2)).unwrap();
+        assert_eq!(peer_context.requested.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn download_manager_unsolicited_failed_chunk_is_ignored() {
+        let num_replicas = 3;
+        let logger = p2p_test_setup_logger();
+        let download_manager =
+            new_test_download_manager(num_replicas, &logger, tokio::runtime::Handle::current());
+
+        // Node 1 and 2 both advertise advert 0, which is downloaded from node 1.
+        for peer_id in 1..num_replicas {
+            test_add_adverts(&download_manager, 0..1, node_test_id(peer_id as u64));
+        }
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(1))
+            .unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 1);
+
+        // Node 2 sends an error for the chunk it was never asked for.
+        download_manager.on_chunk(
+            GossipChunk {
+                artifact_id: ArtifactId::FileTreeSync(0.to_string()),
+                integrity_hash: CryptoHash(Vec::from(0u32.to_be_bytes())),
+                chunk_id: ChunkId::from(0),
+                artifact_chunk: Err(P2PError {
+                    p2p_error_code: P2PErrorCode::Busy,
+                }),
+            },
+            node_test_id(2),
+        );
+
+        // The chunk is still being downloaded from node 1 only.
+        let chunks_to_be_downloaded = download_manager
+            .download_next_compute_work(node_test_id(2))
+            .unwrap();
+        assert_eq!(chunks_to_be_downloaded.len(), 0);
+        let current_peers = download_manager.current_peers.lock().unwrap();
+        let peer_context = current_peers.get(&node_test_id(1)).unwrap();
+        assert_eq!(peer_context.requested.len(), 1);
+        let peer_context = current_peers.get(&node_test_id(2)).unwrap();
+        assert!(peer_context.requested.is_empty());
+        assert_eq!(peer_context.throughput.failure_rate(), 0.0);
+    }
+
     /// The function returns an arbitrary Node ID in a BoxedStrategy.
     fn arbitrary_node_id() -> BoxedStrategy<NodeId> {
         any::<u64>().prop_map(node_test_id).boxed()
@@ -121,6 +121,7 @@ mod event_handler;
 mod gossip_protocol;
 mod malicious_gossip;
 mod metrics;
+mod peer_throughput;
 
 pub use event_handler::{AdvertSubscriber, P2PThreadJoiner};
 
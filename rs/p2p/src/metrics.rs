@@ -1,5 +1,7 @@
 use ic_metrics::{buckets::decimal_buckets, MetricsRegistry};
-use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
+use prometheus::{
+    GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
 
 /// The *Gossip* metrics.
 #[derive(Debug, Clone)]
@@ -80,6 +82,16 @@ pub struct DownloadManagementMetrics {
     /// The number of failures to verify a chunk.
     pub chunks_verification_failed: IntCounter,
 
+    // Per-peer download fields.
+    /// The bytes of state sync chunks received, by peer.
+    pub state_sync_peer_bytes_received: IntCounterVec,
+    /// The observed state sync throughput in bytes per second, by peer.
+    pub state_sync_peer_throughput: GaugeVec,
+    /// The observed fraction of failed chunk requests, by peer.
+    pub peer_chunk_failure_rate: GaugeVec,
+    /// The number of chunks that may be requested in parallel, by peer.
+    pub peer_max_streams: IntGaugeVec,
+
     // Advert fields.
     /// The number of sent adverts(total).
     pub adverts_sent: IntCounter,
@@ -212,6 +224,28 @@ impl DownloadManagementMetrics {
                 "Number of chunks that failed verification",
             ),
 
+            // Per-peer download fields.
+            state_sync_peer_bytes_received: metrics_registry.int_counter_vec(
+                "state_sync_peer_bytes_received",
+                "Bytes of state sync chunks received, by peer",
+                &["peer_id"],
+            ),
+            state_sync_peer_throughput: metrics_registry.gauge_vec(
+                "state_sync_peer_throughput",
+                "Observed state sync throughput in bytes per second, by peer",
+                &["peer_id"],
+            ),
+            peer_chunk_failure_rate: metrics_registry.gauge_vec(
+                "gossip_peer_chunk_failure_rate",
+                "Observed fraction of chunk requests that failed or timed out, by peer",
+                &["peer_id"],
+            ),
+            peer_max_streams: metrics_registry.int_gauge_vec(
+                "gossip_peer_max_streams",
+                "Number of chunks that may be requested in parallel, by peer",
+                &["peer_id"],
+            ),
+
             // Adverts fields.
             adverts_sent: metrics_registry.int_counter(
                 "gossip_adverts_sent",
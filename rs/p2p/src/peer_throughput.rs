@@ -0,0 +1,192 @@
+//! Per-peer download statistics.
+//!
+//! The download manager records, for every peer, the throughput at which the
+//! peer delivered chunks, the time it took to deliver them, and how often
+//! chunk requests to the peer failed or timed out. These statistics are used
+//! to balance chunk requests across peers: fast and reliable peers are asked
+//! for more chunks in parallel than slow or unreliable ones, and requests
+//! that are stalled for much longer than the peer usually takes are retried
+//! elsewhere.
+
+use std::time::Duration;
+
+/// The weight of the most recent sample in the moving averages.
+const SAMPLE_WEIGHT: f64 = 0.2;
+
+/// A peer may be asked for at most this many times the configured number of
+/// parallel chunk requests.
+const MAX_STREAMS_SCALE: usize = 2;
+
+/// A request is stalled once it has been outstanding for this many times the
+/// average delivery time of the peer.
+const STALLED_REQUEST_FACTOR: u32 = 4;
+
+/// Requests outstanding for less than this duration are never considered
+/// stalled.
+const MIN_STALLED_REQUEST_TIME: Duration = Duration::from_secs(1);
+
+/// The observed download statistics of a peer.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PeerThroughput {
+    /// The moving average of the throughput in bytes per second.
+    bytes_per_sec: Option<f64>,
+    /// The moving average of the chunk delivery time.
+    delivery_time: Option<Duration>,
+    /// The moving average of the fraction of failed chunk requests.
+    failure_rate: f64,
+}
+
+impl PeerThroughput {
+    /// Records a chunk delivered `elapsed` after it was requested. The
+    /// throughput is only updated if the size of the chunk is known.
+    pub(crate) fn on_success(&mut self, bytes: Option<usize>, elapsed: Duration) {
+        let elapsed_secs = elapsed.as_secs_f64();
+        if let Some(bytes) = bytes {
+            // Guard against a zero duration for chunks that are delivered
+            // instantly.
+            let sample = bytes as f64 / elapsed_secs.max(f64::EPSILON);
+            self.bytes_per_sec = Some(moving_average(self.bytes_per_sec, sample));
+        }
+        self.delivery_time = Some(Duration::from_secs_f64(moving_average(
+            self.delivery_time.map(|time| time.as_secs_f64()),
+            elapsed_secs,
+        )));
+        self.failure_rate = moving_average(Some(self.failure_rate), 0.0);
+    }
+
+    /// Records a chunk request that failed or timed out.
+    pub(crate) fn on_failure(&mut self) {
+        self.failure_rate = moving_average(Some(self.failure_rate), 1.0);
+    }
+
+    /// Returns the observed throughput in bytes per second, if any.
+    pub(crate) fn bytes_per_sec(&self) -> Option<f64> {
+        self.bytes_per_sec
+    }
+
+    /// Returns the observed fraction of failed chunk requests.
+    pub(crate) fn failure_rate(&self) -> f64 {
+        self.failure_rate
+    }
+
+    /// Returns the number of chunks that may be requested from the peer in
+    /// parallel, given the configured number of parallel requests
+    /// `max_streams` and the throughput `reference_bytes_per_sec` of a
+    /// typical peer.
+    ///
+    /// The number scales with the throughput of the peer relative to the
+    /// reference and shrinks with its failure rate, but is always at least 1
+    /// and at most `MAX_STREAMS_SCALE * max_streams`.
+    pub(crate) fn max_streams(
+        &self,
+        max_streams: usize,
+        reference_bytes_per_sec: Option<f64>,
+    ) -> usize {
+        let mut scale = match (self.bytes_per_sec, reference_bytes_per_sec) {
+            (Some(bytes_per_sec), Some(reference)) if reference > 0.0 => (bytes_per_sec
+                / reference)
+                .clamp(1.0 / MAX_STREAMS_SCALE as f64, MAX_STREAMS_SCALE as f64),
+            _ => 1.0,
+        };
+        scale *= 1.0 - self.failure_rate;
+        ((max_streams as f64 * scale).round() as usize)
+            .clamp(1, MAX_STREAMS_SCALE * max_streams.max(1))
+    }
+
+    /// Returns true if a request that has been outstanding for `elapsed` is
+    /// stalled, i.e., takes much longer than the peer usually takes to
+    /// deliver a chunk.
+    pub(crate) fn is_stalled(&self, elapsed: Duration) -> bool {
+        self.delivery_time.map_or(false, |delivery_time| {
+            elapsed >= MIN_STALLED_REQUEST_TIME && elapsed >= delivery_time * STALLED_REQUEST_FACTOR
+        })
+    }
+}
+
+/// Returns the median throughput of the peers that have delivered chunks of
+/// known size.
+pub(crate) fn median_bytes_per_sec<'a>(
+    peers: impl Iterator<Item = &'a PeerThroughput>,
+) -> Option<f64> {
+    let mut samples: Vec<f64> = peers.filter_map(PeerThroughput::bytes_per_sec).collect();
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let middle = samples.len() / 2;
+    if samples.len() % 2 == 0 {
+        Some((samples[middle - 1] + samples[middle]) / 2.0)
+    } else {
+        Some(samples[middle])
+    }
+}
+
+/// Returns the exponentially weighted moving average after adding `sample`.
+fn moving_average(average: Option<f64>, sample: f64) -> f64 {
+    match average {
+        Some(average) => (1.0 - SAMPLE_WEIGHT) * average + SAMPLE_WEIGHT * sample,
+        None => sample,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer_with_throughput(bytes_per_sec: usize) -> PeerThroughput {
+        let mut peer = PeerThroughput::default();
+        peer.on_success(Some(bytes_per_sec), Duration::from_secs(1));
+        peer
+    }
+
+    #[test]
+    fn unknown_peers_get_the_configured_streams() {
+        let peer = PeerThroughput::default();
+        assert_eq!(peer.max_streams(20, None), 20);
+        assert_eq!(peer.max_streams(20, Some(1000.0)), 20);
+    }
+
+    #[test]
+    fn fast_peers_get_more_streams_than_slow_peers() {
+        let fast = peer_with_throughput(4000);
+        let typical = peer_with_throughput(1000);
+        let slow = peer_with_throughput(500);
+        let reference = median_bytes_per_sec(vec![&fast, &typical, &slow].into_iter());
+        assert_eq!(reference, Some(1000.0));
+        assert_eq!(fast.max_streams(20, reference), 40);
+        assert_eq!(typical.max_streams(20, reference), 20);
+        assert_eq!(slow.max_streams(20, reference), 10);
+    }
+
+    #[test]
+    fn failing_peers_get_fewer_streams() {
+        let mut peer = PeerThroughput::default();
+        for _ in 0..100 {
+            peer.on_failure();
+        }
+        assert!(peer.failure_rate() > 0.99);
+        assert_eq!(peer.max_streams(20, None), 1);
+
+        // The failure rate decays as the peer delivers chunks again.
+        for _ in 0..100 {
+            peer.on_success(None, Duration::from_millis(10));
+        }
+        assert!(peer.failure_rate() < 0.01);
+        assert_eq!(peer.max_streams(20, None), 20);
+    }
+
+    #[test]
+    fn requests_stall_relative_to_the_delivery_time() {
+        let mut peer = PeerThroughput::default();
+        assert!(!peer.is_stalled(Duration::from_secs(100)));
+        peer.on_success(None, Duration::from_millis(500));
+        assert!(!peer.is_stalled(Duration::from_millis(1500)));
+        assert!(peer.is_stalled(Duration::from_millis(2000)));
+
+        // Requests to fast peers need some minimum time to stall.
+        let mut fast_peer = PeerThroughput::default();
+        fast_peer.on_success(None, Duration::from_millis(1));
+        assert!(!fast_peer.is_stalled(Duration::from_millis(500)));
+        assert!(fast_peer.is_stalled(MIN_STALLED_REQUEST_TIME));
+    }
+}
@@ -208,7 +208,7 @@ fn build_registry_proto() {
     );
     config.type_attribute(
         ".registry.firewall",
-        "#[derive(serde::Serialize, serde::Deserialize)]",
+        "#[derive(candid::CandidType, serde::Serialize, candid::Deserialize)]",
     );
     config.type_attribute(
         ".registry.routing_table",
@@ -226,6 +226,10 @@ fn build_registry_proto() {
         ".registry.subnet.v1.EcdsaConfig",
         "#[derive(candid::CandidType, Eq)]",
     );
+    config.type_attribute(
+        ".registry.subnet.v1.RolloutPolicy",
+        "#[derive(candid::CandidType, Eq)]",
+    );
     config.type_attribute(
         ".registry.replica_version",
         "#[derive(serde::Serialize, serde::Deserialize)]",
@@ -8,12 +8,12 @@ use async_trait::async_trait;
 use candid::{CandidType, Decode, Encode};
 use clap::Parser;
 use cycles_minting_canister::SetAuthorizedSubnetworkListArgs;
-use ed25519_dalek::Keypair;
-use ic_canister_client::{Agent, Sender};
+use ic_canister_client::{Agent, PemSigner, Sender};
 use ic_config::subnet_config::SchedulerConfig;
 use ic_crypto::threshold_sig_public_key_to_der;
 use ic_crypto_sha::Sha256;
 use ic_crypto_utils_basic_sig::conversions::Ed25519SecretKeyConversions;
+use ic_crypto_utils_threshold_sig::parse_threshold_sig_key;
 use ic_http_utils::file_downloader::{check_file_hash, extract_tar_gz_into_dir, FileDownloader};
 use ic_prep_lib::subnet_configuration;
 use ic_types::p2p;
@@ -57,7 +57,7 @@ use ic_protobuf::registry::{
     provisional_whitelist::v1::ProvisionalWhitelist as ProvisionalWhitelistProto,
     replica_version::v1::{BlessedReplicaVersions, ReplicaVersionRecord},
     routing_table::v1::RoutingTable,
-    subnet::v1::{EcdsaConfig, SubnetListRecord, SubnetRecord as SubnetRecordProto},
+    subnet::v1::{EcdsaConfig, RolloutPolicy, SubnetListRecord, SubnetRecord as SubnetRecordProto},
     unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
 };
 use ic_protobuf::registry::{
@@ -77,8 +77,10 @@ use ic_registry_keys::{
     make_data_center_record_key, make_node_operator_record_key, make_node_record_key,
     make_provisional_whitelist_record_key, make_replica_version_key, make_routing_table_record_key,
     make_subnet_list_record_key, make_subnet_record_key, make_unassigned_nodes_config_record_key,
-    NODE_OPERATOR_RECORD_KEY_PREFIX, NODE_REWARDS_TABLE_KEY, ROOT_SUBNET_ID_KEY,
+    FirewallRulesScope, NODE_OPERATOR_RECORD_KEY_PREFIX, NODE_REWARDS_TABLE_KEY,
+    ROOT_SUBNET_ID_KEY,
 };
+use ic_registry_routing_table::CanisterIdRange;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_registry_transport::Error;
@@ -92,9 +94,11 @@ use ic_types::{
 use prost::Message;
 use registry_canister::mutations::common::decode_registry_value;
 use registry_canister::mutations::do_set_firewall_config::SetFirewallConfigPayload;
+use registry_canister::mutations::do_set_firewall_rules::SetFirewallRulesPayload;
 use registry_canister::mutations::do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload;
 use registry_canister::mutations::node_management::do_remove_nodes::RemoveNodesPayload;
 use registry_canister::mutations::{
+    complete_canister_migration::CompleteCanisterMigrationPayload,
     do_add_node_operator::AddNodeOperatorPayload, do_add_nodes_to_subnet::AddNodesToSubnetPayload,
     do_bless_replica_version::BlessReplicaVersionPayload, do_create_subnet::CreateSubnetPayload,
     do_recover_subnet::RecoverSubnetPayload,
@@ -102,7 +106,8 @@ use registry_canister::mutations::{
     do_update_node_operator_config::UpdateNodeOperatorConfigPayload,
     do_update_subnet::UpdateSubnetPayload,
     do_update_subnet_replica::UpdateSubnetReplicaVersionPayload,
-    reroute_canister_range::RerouteCanisterRangePayload,
+    prepare_canister_migration::PrepareCanisterMigrationPayload,
+    reroute_canister_range::RerouteCanisterRangePayload, split_subnet::SplitSubnetPayload,
 };
 use serde::Serialize;
 use std::collections::{BTreeMap, HashSet};
@@ -162,6 +167,17 @@ struct Opts {
         help = "Only required if use-hsm is set. Ignored otherwise."
     )]
     pin: Option<String>,
+
+    /// The pem file containing the NNS public key used to verify certified
+    /// responses to update calls.
+    #[clap(long)]
+    nns_public_key_pem_file: Option<PathBuf>,
+
+    /// Fetch the NNS public key from the replica instead of using
+    /// --nns-public-key-pem-file. The fetched key is not authenticated, so
+    /// this must only be used against testnets.
+    #[clap(long)]
+    fetch_root_key: bool,
 }
 
 impl ProposeToCreateSubnetCmd {
@@ -287,6 +303,8 @@ enum SubCommand {
     ProposeToUpdateNodeOperatorConfig(ProposeToUpdateNodeOperatorConfigCmd),
     /// Propose to set the firewall config
     ProposeToSetFirewallConfig(ProposeToSetFirewallConfigCmd),
+    /// Propose to set the firewall rules of a scope
+    ProposeToSetFirewallRules(ProposeToSetFirewallRulesCmd),
     /// Propose to remove a node from the registry via proposal.
     ProposeToRemoveNodes(ProposeToRemoveNodesCmd),
     /// Propose to add or remove a node provider from the governance canister
@@ -322,6 +340,13 @@ enum SubCommand {
     ProposeToRemoveNodeOperators(ProposeToRemoveNodeOperatorsCmd),
     /// Propose to change the routing table.
     ProposeToRerouteCanisterRange(ProposeToRerouteCanisterRangeCmd),
+    /// Propose to start migrating canister id ranges between subnets.
+    ProposeToPrepareCanisterMigration(ProposeToPrepareCanisterMigrationCmd),
+    /// Propose to complete (or abort) migrating canister id ranges between
+    /// subnets.
+    ProposeToCompleteCanisterMigration(ProposeToCompleteCanisterMigrationCmd),
+    /// Propose to split canister id ranges off a subnet into another subnet.
+    ProposeToSplitSubnet(ProposeToSplitSubnetCmd),
 }
 
 /// Indicates whether a value should be added or removed.
@@ -1268,6 +1293,56 @@ struct ProposeToUpdateSubnetCmd {
     /// of this field.
     #[clap(long)]
     pub max_number_of_canisters: Option<u64>,
+
+    /// If any of the `rollout_*` options is set, the created proposal sets a
+    /// policy for rolling out new replica versions to the subnet in stages
+    /// (RolloutPolicy in rs/protobuf/def/registry/subnet/v1/subnet.proto).
+    /// The nodes that upgrade first.
+    #[clap(long, multiple_values(true))]
+    rollout_canary_nodes: Option<Vec<PrincipalId>>,
+
+    /// The cumulative percentages of nodes upgraded in each stage. The first
+    /// stage, together with the canary nodes, has to cover at least 2f+1 of
+    /// the subnet's nodes.
+    #[clap(long, multiple_values(true))]
+    rollout_stage_percentages: Option<Vec<u32>>,
+
+    /// The minimum finalization rate, in blocks per minute, for an upgrade to
+    /// be considered healthy.
+    #[clap(long)]
+    rollout_min_finalization_rate_per_minute: Option<u32>,
+
+    /// How long upgraded nodes observe finalization before reporting the
+    /// outcome of their upgrade.
+    #[clap(long)]
+    rollout_health_check_duration_seconds: Option<u64>,
+}
+
+impl ProposeToUpdateSubnetCmd {
+    fn rollout_policy(&self) -> Option<RolloutPolicy> {
+        if self.rollout_canary_nodes.is_none()
+            && self.rollout_stage_percentages.is_none()
+            && self.rollout_min_finalization_rate_per_minute.is_none()
+            && self.rollout_health_check_duration_seconds.is_none()
+        {
+            return None;
+        }
+        Some(RolloutPolicy {
+            canary_nodes: self
+                .rollout_canary_nodes
+                .iter()
+                .flatten()
+                .map(|node_id| node_id.to_vec())
+                .collect(),
+            stage_percentages: self.rollout_stage_percentages.clone().unwrap_or_default(),
+            min_finalization_rate_per_minute: self
+                .rollout_min_finalization_rate_per_minute
+                .unwrap_or_default(),
+            health_check_duration_seconds: self
+                .rollout_health_check_duration_seconds
+                .unwrap_or_default(),
+        })
+    }
 }
 
 #[async_trait]
@@ -1324,6 +1399,7 @@ impl ProposalTitleAndPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
             max_number_of_canisters: self.max_number_of_canisters,
+            rollout_policy: self.rollout_policy(),
         }
     }
 }
@@ -2024,6 +2100,39 @@ impl ProposalTitleAndPayload<SetFirewallConfigPayload> for ProposeToSetFirewallC
     }
 }
 
+/// Sub-command to submit a proposal to set the firewall rules of a scope.
+#[derive_common_proposal_fields]
+#[derive(ProposalMetadata, Parser)]
+struct ProposeToSetFirewallRulesCmd {
+    /// The scope of the rules: "global", "replica_nodes", "subnet_<subnet
+    /// id>" or "node_<node id>".
+    pub scope: FirewallRulesScope,
+    /// JSON file with the list of rules, e.g.
+    /// [{"ipv4_prefixes": ["10.0.0.0/8"], "ipv6_prefixes": [], "ports": [22],
+    /// "action": 1, "comment": "ssh"}], where the action is 1 (allow), 2
+    /// (deny) or 3 (reject). An empty list removes all rules of the scope.
+    pub rules_file: PathBuf,
+}
+
+#[async_trait]
+impl ProposalTitleAndPayload<SetFirewallRulesPayload> for ProposeToSetFirewallRulesCmd {
+    fn title(&self) -> String {
+        match &self.proposal_title {
+            Some(title) => title.clone(),
+            None => format!("Set firewall rules of scope {}", self.scope),
+        }
+    }
+
+    async fn payload(&self, _: Url) -> SetFirewallRulesPayload {
+        let rules = serde_json::from_slice(&read_file_fully(&self.rules_file))
+            .unwrap_or_else(|e| panic!("Failed to parse the firewall rules: {}", e));
+        SetFirewallRulesPayload {
+            scope: self.scope.clone(),
+            rules,
+        }
+    }
+}
+
 /// Sub-command to submit a proposal to remove nodes.
 #[derive_common_proposal_fields]
 #[derive(ProposalMetadata, Parser)]
@@ -2179,12 +2288,174 @@ impl ProposalTitleAndPayload<RerouteCanisterRangePayload> for ProposeToRerouteCa
     }
 }
 
+/// Parses a canister id range of the form `START:END`, where both ends are
+/// inclusive canister ids.
+fn parse_canister_id_range(s: &str) -> Result<CanisterIdRange, String> {
+    let (start, end) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected a canister id range START:END, got {}", s))?;
+    let start = CanisterId::from_str(start).map_err(|e| format!("invalid range start: {}", e))?;
+    let end = CanisterId::from_str(end).map_err(|e| format!("invalid range end: {}", e))?;
+    Ok(CanisterIdRange { start, end })
+}
+
+/// Formats canister id ranges for proposal titles.
+fn format_canister_id_ranges(ranges: &[CanisterIdRange]) -> String {
+    ranges
+        .iter()
+        .map(|range| format!("[{}, {}]", range.start, range.end))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Sub-command to propose to start migrating canister id ranges from one
+/// subnet to another.
+///
+/// Once the proposal is executed, the source subnet rejects new requests to
+/// the canisters in the ranges. After the canister states have been moved to
+/// the destination subnet, the ranges are rerouted with
+/// `propose-to-reroute-canister-range` and the migration is completed with
+/// `propose-to-complete-canister-migration`.
+#[derive_common_proposal_fields]
+#[derive(ProposalMetadata, Parser)]
+struct ProposeToPrepareCanisterMigrationCmd {
+    /// The canister id ranges to migrate, as `START:END` (both inclusive).
+    #[clap(long, required = true, multiple_values(true), parse(try_from_str = parse_canister_id_range))]
+    canister_id_ranges: Vec<CanisterIdRange>,
+    /// The subnet currently hosting the canister id ranges.
+    #[clap(long, required = true)]
+    source_subnet: PrincipalId,
+    /// The subnet to migrate the canister id ranges to.
+    #[clap(long, required = true)]
+    destination_subnet: PrincipalId,
+}
+
+#[async_trait]
+impl ProposalTitleAndPayload<PrepareCanisterMigrationPayload>
+    for ProposeToPrepareCanisterMigrationCmd
+{
+    fn title(&self) -> String {
+        match &self.proposal_title {
+            Some(title) => title.clone(),
+            None => format!(
+                "Prepare migration of canister ranges {} from subnet {} to subnet {}",
+                format_canister_id_ranges(&self.canister_id_ranges),
+                self.source_subnet,
+                self.destination_subnet
+            ),
+        }
+    }
+
+    async fn payload(&self, _: Url) -> PrepareCanisterMigrationPayload {
+        PrepareCanisterMigrationPayload {
+            canister_id_ranges: self.canister_id_ranges.clone(),
+            source_subnet: self.source_subnet,
+            destination_subnet: self.destination_subnet,
+        }
+    }
+}
+
+/// Sub-command to propose to complete migrating canister id ranges.
+///
+/// To abort a migration, reroute the ranges back to the source subnet first
+/// (if they were rerouted already), then complete the migration.
+#[derive_common_proposal_fields]
+#[derive(ProposalMetadata, Parser)]
+struct ProposeToCompleteCanisterMigrationCmd {
+    /// The canister id ranges being migrated, as `START:END` (both inclusive).
+    #[clap(long, required = true, multiple_values(true), parse(try_from_str = parse_canister_id_range))]
+    canister_id_ranges: Vec<CanisterIdRange>,
+    /// The migration trace of the ranges: the source subnet followed by the
+    /// destination subnet.
+    #[clap(long, required = true, multiple_values(true))]
+    migration_trace: Vec<PrincipalId>,
+}
+
+#[async_trait]
+impl ProposalTitleAndPayload<CompleteCanisterMigrationPayload>
+    for ProposeToCompleteCanisterMigrationCmd
+{
+    fn title(&self) -> String {
+        match &self.proposal_title {
+            Some(title) => title.clone(),
+            None => format!(
+                "Complete migration of canister ranges {} along subnets {:?}",
+                format_canister_id_ranges(&self.canister_id_ranges),
+                self.migration_trace
+            ),
+        }
+    }
+
+    async fn payload(&self, _: Url) -> CompleteCanisterMigrationPayload {
+        CompleteCanisterMigrationPayload {
+            canister_id_ranges: self.canister_id_ranges.clone(),
+            migration_trace: self.migration_trace.clone(),
+        }
+    }
+}
+
+/// Sub-command to propose to split canister id ranges off a subnet into
+/// another subnet.
+///
+/// Once the proposal is executed, the ranges are routed to the destination
+/// subnet and recorded as being migrated. The destination subnet is then
+/// started from a checkpoint split off the (halted) source subnet's state with
+/// `state-tool split`, and the migration is completed with
+/// `propose-to-complete-canister-migration`.
+#[derive_common_proposal_fields]
+#[derive(ProposalMetadata, Parser)]
+struct ProposeToSplitSubnetCmd {
+    /// The canister id ranges to split off, as `START:END` (both inclusive).
+    #[clap(long, required = true, multiple_values(true), parse(try_from_str = parse_canister_id_range))]
+    canister_id_ranges: Vec<CanisterIdRange>,
+    /// The subnet being split.
+    #[clap(long, required = true)]
+    source_subnet: PrincipalId,
+    /// The subnet taking over the canister id ranges.
+    #[clap(long, required = true)]
+    destination_subnet: PrincipalId,
+}
+
+#[async_trait]
+impl ProposalTitleAndPayload<SplitSubnetPayload> for ProposeToSplitSubnetCmd {
+    fn title(&self) -> String {
+        match &self.proposal_title {
+            Some(title) => title.clone(),
+            None => format!(
+                "Split canister ranges {} off subnet {} into subnet {}",
+                format_canister_id_ranges(&self.canister_id_ranges),
+                self.source_subnet,
+                self.destination_subnet
+            ),
+        }
+    }
+
+    async fn payload(&self, _: Url) -> SplitSubnetPayload {
+        SplitSubnetPayload {
+            canister_id_ranges: self.canister_id_ranges.clone(),
+            source_subnet: self.source_subnet,
+            destination_subnet: self.destination_subnet,
+        }
+    }
+}
+
 /// `main()` method for the `ic-admin` utility.
 #[tokio::main]
 async fn main() {
     let opts: Opts = Opts::parse();
     let registry_canister = RegistryCanister::new(vec![opts.nns_url.clone()]);
 
+    let root_key = match (&opts.nns_public_key_pem_file, opts.fetch_root_key) {
+        (Some(pem_file), false) => RootKey::Configured(
+            parse_threshold_sig_key(pem_file).expect("Invalid NNS public key pem file."),
+        ),
+        (None, true) => RootKey::FetchedFromReplica,
+        (Some(_), true) => {
+            panic!("--nns-public-key-pem-file and --fetch-root-key are mutually exclusive.")
+        }
+        (None, false) => RootKey::Unset,
+    };
+
     let sender = if opts.secret_key_pem.is_some() || opts.use_hsm {
         // Make sure to let the user know that we only actually use the sender
         // in methods that go through the NNS handlers and not for other methods.
@@ -2206,6 +2477,7 @@ async fn main() {
             SubCommand::ProposeToUpdateRecoveryCup(_) => (),
             SubCommand::ProposeToUpdateNodeOperatorConfig(_) => (),
             SubCommand::ProposeToSetFirewallConfig(_) => (),
+            SubCommand::ProposeToSetFirewallRules(_) => (),
             SubCommand::ProposeToSetAuthorizedSubnetworks(_) => (),
             SubCommand::ProposeToAddOrRemoveNodeProvider(_) => (),
             SubCommand::SubmitRootProposalToUpgradeGovernanceCanister(_) => (),
@@ -2223,15 +2495,8 @@ async fn main() {
 
         if opts.secret_key_pem.is_some() {
             let secret_key_path = opts.secret_key_pem.unwrap();
-            use ic_crypto_internal_types::sign::eddsa::ed25519::SecretKey;
-            let contents = read_to_string(secret_key_path).expect("Could not read key file.");
-            let (secret_key, public_key) =
-                SecretKey::from_pem(&contents).expect("Invalid secret key.");
-            let mut buf = Vec::new();
-            buf.extend(secret_key.as_bytes());
-            buf.extend(public_key.as_bytes());
-            let keypair = Keypair::from_bytes(&buf).expect("Invalid secret key.");
-            Sender::from_keypair(&keypair)
+            let signer = PemSigner::from_pem_file(&secret_key_path).expect("Invalid secret key.");
+            Sender::from_signer(Arc::new(signer))
         } else if opts.use_hsm {
             make_hsm_sender(
                 &opts.hsm_slot.unwrap(),
@@ -2270,6 +2535,7 @@ async fn main() {
                 NnsFunction::RemoveNodesFromSubnet,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2441,6 +2707,7 @@ async fn main() {
                 NnsFunction::UpdateSubnetReplicaVersion,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2464,6 +2731,7 @@ async fn main() {
                 NnsFunction::BlessReplicaVersion,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2473,6 +2741,7 @@ async fn main() {
                 NnsFunction::BlessReplicaVersion,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2483,6 +2752,7 @@ async fn main() {
                 NnsFunction::CreateSubnet,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2492,6 +2762,7 @@ async fn main() {
                 NnsFunction::AddNodeToSubnet,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2501,6 +2772,7 @@ async fn main() {
                 NnsFunction::RecoverSubnet,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2510,6 +2782,7 @@ async fn main() {
                 NnsFunction::UpdateConfigOfSubnet,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2519,6 +2792,7 @@ async fn main() {
                 NnsFunction::NnsCanisterInstall,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2527,13 +2801,25 @@ async fn main() {
                 propose_external_proposal_from_command::<
                     UpgradeRootProposalPayload,
                     ProposeToChangeNnsCanisterCmd,
-                >(cmd, NnsFunction::NnsRootUpgrade, opts.nns_url, sender)
+                >(
+                    cmd,
+                    NnsFunction::NnsRootUpgrade,
+                    opts.nns_url,
+                    sender,
+                    root_key,
+                )
                 .await;
             } else {
                 propose_external_proposal_from_command::<
                     ChangeCanisterProposal,
                     ProposeToChangeNnsCanisterCmd,
-                >(cmd, NnsFunction::NnsCanisterUpgrade, opts.nns_url, sender)
+                >(
+                    cmd,
+                    NnsFunction::NnsCanisterUpgrade,
+                    opts.nns_url,
+                    sender,
+                    root_key,
+                )
                 .await;
             }
         }
@@ -2543,6 +2829,7 @@ async fn main() {
                 NnsFunction::UninstallCode,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2552,6 +2839,7 @@ async fn main() {
                 NnsFunction::StopOrStartNnsCanister,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2561,6 +2849,7 @@ async fn main() {
                 NnsFunction::StopOrStartNnsCanister,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2570,6 +2859,7 @@ async fn main() {
                 NnsFunction::ClearProvisionalWhitelist,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2579,6 +2869,7 @@ async fn main() {
                 NnsFunction::SetAuthorizedSubnetworks,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2599,6 +2890,7 @@ async fn main() {
                 NnsFunction::RemoveNodes,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2608,6 +2900,7 @@ async fn main() {
                 NnsFunction::AssignNoid,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2649,6 +2942,7 @@ async fn main() {
                 NnsFunction::UpdateNodeOperatorConfig,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2658,24 +2952,47 @@ async fn main() {
                 NnsFunction::SetFirewallConfig,
                 opts.nns_url,
                 sender,
+                root_key,
+            )
+            .await;
+        }
+        SubCommand::ProposeToSetFirewallRules(cmd) => {
+            propose_external_proposal_from_command(
+                cmd,
+                NnsFunction::SetFirewallRules,
+                opts.nns_url,
+                sender,
+                root_key,
             )
             .await;
         }
         SubCommand::ProposeToAddOrRemoveNodeProvider(cmd) => {
-            propose_to_add_or_remove_node_provider(cmd, opts.nns_url, sender).await
+            propose_to_add_or_remove_node_provider(cmd, opts.nns_url, sender, root_key).await
         }
         SubCommand::GetRegistryVersion => {
             let latest_version = registry_canister.get_latest_version().await.unwrap();
             println!("{}", latest_version)
         }
         SubCommand::SubmitRootProposalToUpgradeGovernanceCanister(cmd) => {
-            submit_root_proposal_to_upgrade_governance_canister(cmd, opts.nns_url, sender).await
+            submit_root_proposal_to_upgrade_governance_canister(cmd, opts.nns_url, sender, root_key)
+                .await
         }
         SubCommand::GetPendingRootProposalsToUpgradeGovernanceCanister => {
-            get_pending_root_proposals_to_upgrade_governance_canister(opts.nns_url, sender).await
+            get_pending_root_proposals_to_upgrade_governance_canister(
+                opts.nns_url,
+                sender,
+                root_key,
+            )
+            .await
         }
         SubCommand::VoteOnRootProposalToUpgradeGovernanceCanister(cmd) => {
-            vote_on_root_proposal_to_upgrade_governance_canister(cmd, opts.nns_url, sender).await
+            vote_on_root_proposal_to_upgrade_governance_canister(
+                cmd,
+                opts.nns_url,
+                sender,
+                root_key,
+            )
+            .await
         }
         SubCommand::GetDataCenter(cmd) => {
             let (bytes, _) = registry_canister
@@ -2692,6 +3009,7 @@ async fn main() {
                 NnsFunction::AddOrRemoveDataCenters,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2710,6 +3028,7 @@ async fn main() {
                 NnsFunction::UpdateNodeRewardsTable,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2719,6 +3038,7 @@ async fn main() {
                 NnsFunction::UpdateUnassignedNodesConfig,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2737,6 +3057,7 @@ async fn main() {
                 GOVERNANCE_CANISTER_ID,
                 sender,
                 None,
+                root_key,
             ));
 
             let response = canister_client.get_monthly_node_provider_rewards().await;
@@ -2748,6 +3069,7 @@ async fn main() {
                 NnsFunction::RemoveNodeOperators,
                 opts.nns_url,
                 sender,
+                root_key,
             )
             .await;
         }
@@ -2757,6 +3079,37 @@ async fn main() {
                 NnsFunction::RerouteCanisterRange,
                 opts.nns_url,
                 sender,
+                root_key,
+            )
+            .await;
+        }
+        SubCommand::ProposeToPrepareCanisterMigration(cmd) => {
+            propose_external_proposal_from_command(
+                cmd,
+                NnsFunction::PrepareCanisterMigration,
+                opts.nns_url,
+                sender,
+                root_key,
+            )
+            .await;
+        }
+        SubCommand::ProposeToCompleteCanisterMigration(cmd) => {
+            propose_external_proposal_from_command(
+                cmd,
+                NnsFunction::CompleteCanisterMigration,
+                opts.nns_url,
+                sender,
+                root_key,
+            )
+            .await;
+        }
+        SubCommand::ProposeToSplitSubnet(cmd) => {
+            propose_external_proposal_from_command(
+                cmd,
+                NnsFunction::SplitSubnet,
+                opts.nns_url,
+                sender,
+                root_key,
             )
             .await;
         }
@@ -2860,6 +3213,7 @@ async fn propose_external_proposal_from_command<
     nns_function: NnsFunction,
     nns_url: Url,
     sender: Sender,
+    root_key: RootKey,
 ) {
     let (proposer, sender) = cmd.proposer_and_sender(sender);
     let canister_client = GovernanceCanisterClient(make_canister_client(
@@ -2867,6 +3221,7 @@ async fn propose_external_proposal_from_command<
         GOVERNANCE_CANISTER_ID,
         sender,
         Some(proposer),
+        root_key,
     ));
 
     let payload = cmd.payload(nns_url).await;
@@ -3220,6 +3575,7 @@ async fn propose_to_add_or_remove_node_provider(
     cmd: ProposeToAddOrRemoveNodeProviderCmd,
     nns_url: Url,
     sender: Sender,
+    root_key: RootKey,
 ) {
     let (proposer, sender) =
         get_proposer_and_sender(cmd.proposer, sender, cmd.test_neuron_proposer);
@@ -3228,6 +3584,7 @@ async fn propose_to_add_or_remove_node_provider(
         GOVERNANCE_CANISTER_ID,
         sender,
         Some(proposer),
+        root_key,
     ));
     let node_provider = NodeProvider {
         id: Some(cmd.node_provider_pid),
@@ -3380,6 +3737,7 @@ async fn submit_root_proposal_to_upgrade_governance_canister(
     cmd: SubmitRootProposalToUpgradeGovernanceCanisterCmd,
     nns_url: Url,
     sender: Sender,
+    root_key: RootKey,
 ) {
     let sender = get_test_sender_if_set(sender, cmd.test_user_proposer);
     let canister_client = RootCanisterClient(make_canister_client(
@@ -3387,6 +3745,7 @@ async fn submit_root_proposal_to_upgrade_governance_canister(
         ROOT_CANISTER_ID,
         sender,
         None,
+        root_key,
     ));
     let result = canister_client
         .submit_root_proposal_to_upgrade_governance_canister(cmd)
@@ -3402,12 +3761,17 @@ async fn submit_root_proposal_to_upgrade_governance_canister(
 
 /// Returns the current list of pending root proposals to upgrade the governance
 /// canister.
-async fn get_pending_root_proposals_to_upgrade_governance_canister(nns_url: Url, sender: Sender) {
+async fn get_pending_root_proposals_to_upgrade_governance_canister(
+    nns_url: Url,
+    sender: Sender,
+    root_key: RootKey,
+) {
     let canister_client = RootCanisterClient(make_canister_client(
         nns_url,
         ROOT_CANISTER_ID,
         sender,
         None,
+        root_key,
     ));
     let proposals = canister_client
         .get_pending_root_proposals_to_upgrade_governance_canister()
@@ -3428,6 +3792,7 @@ async fn vote_on_root_proposal_to_upgrade_governance_canister(
     cmd: VoteOnRootProposalToUpgradeGovernanceCanisterCmd,
     nns_url: Url,
     sender: Sender,
+    root_key: RootKey,
 ) {
     let sender = get_test_sender_if_set(sender, cmd.test_user_voter);
     let canister_client = RootCanisterClient(make_canister_client(
@@ -3435,6 +3800,7 @@ async fn vote_on_root_proposal_to_upgrade_governance_canister(
         ROOT_CANISTER_ID,
         sender,
         None,
+        root_key,
     ));
     let result = canister_client
         .vote_on_root_proposal_to_upgrade_governance_canister(cmd)
@@ -3488,14 +3854,35 @@ fn make_canister_client(
     handler_id: CanisterId,
     sender: Sender,
     author: Option<NeuronId>,
+    root_key: RootKey,
 ) -> NnsCanisterClient {
+    let agent = Agent::new(nns_url, sender);
+    let agent = match root_key {
+        RootKey::Configured(key) => agent.with_root_key(key),
+        RootKey::FetchedFromReplica => agent.with_fetched_root_key(),
+        RootKey::Unset => agent,
+    };
     NnsCanisterClient {
-        agent: Agent::new(nns_url, sender),
+        agent,
         handler_id,
         author,
     }
 }
 
+/// Where the agent gets the NNS public key used to verify certified
+/// responses from.
+#[derive(Clone, Copy)]
+enum RootKey {
+    /// The key was read from `--nns-public-key-pem-file`.
+    Configured(ThresholdSigPublicKey),
+    /// The key is fetched from the replica itself (`--fetch-root-key`). This
+    /// is only safe against testnets.
+    FetchedFromReplica,
+    /// Neither option was given: queries still work, but the agent refuses
+    /// every certified response.
+    Unset,
+}
+
 impl NnsCanisterClient {
     pub async fn execute_update<S: ToString>(
         &self,
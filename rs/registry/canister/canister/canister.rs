@@ -7,7 +7,7 @@ use dfn_core::{
     over, over_async, over_may_reject, stable,
 };
 use ic_base_types::NodeId;
-use ic_certified_map::{AsHashTree, HashTree};
+use ic_certified_map::HashTree;
 use ic_nervous_system_common::MethodAuthzChange;
 use ic_nns_common::{access_control::check_caller_is_root, pb::v1::CanisterAuthzInfo};
 use ic_nns_constants::{GOVERNANCE_CANISTER_ID, ROOT_CANISTER_ID};
@@ -20,18 +20,20 @@ use ic_registry_transport::{
     deserialize_get_value_request,
     pb::v1::{
         registry_error::Code, CertifiedResponse, RegistryAtomicMutateResponse, RegistryDelta,
-        RegistryError, RegistryGetChangesSinceRequest, RegistryGetChangesSinceResponse,
-        RegistryGetLatestVersionResponse, RegistryGetValueResponse,
+        RegistryError, RegistryGetCertifiedSnapshotRequest, RegistryGetChangesSinceRequest,
+        RegistryGetChangesSinceResponse, RegistryGetLatestVersionResponse,
+        RegistryGetValueResponse,
     },
     serialize_atomic_mutate_response, serialize_get_changes_since_response,
     serialize_get_value_response,
 };
 use ic_types::messages::MAX_INTER_CANISTER_PAYLOAD_IN_BYTES_U64 as MAX_RESPONSE_SIZE;
 use registry_canister::{
-    certification::{current_version_tree, hash_tree_to_proto},
+    certification::{hash_tree_to_proto, pruned_delta_tree, pruned_snapshot_tree, registry_tree},
     common::LOG_PREFIX,
     init::RegistryCanisterInitPayload,
     mutations::{
+        complete_canister_migration::CompleteCanisterMigrationPayload,
         do_add_node_operator::AddNodeOperatorPayload,
         do_add_nodes_to_subnet::AddNodesToSubnetPayload,
         do_bless_replica_version::BlessReplicaVersionPayload,
@@ -46,9 +48,13 @@ use registry_canister::{
         do_update_subnet_replica::UpdateSubnetReplicaVersionPayload,
         do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload,
         node_management::{
-            do_remove_node_directly::RemoveNodeDirectlyPayload, do_remove_nodes::RemoveNodesPayload,
+            do_remove_node_directly::RemoveNodeDirectlyPayload,
+            do_remove_nodes::RemoveNodesPayload,
+            do_report_upgrade_status::ReportUpgradeStatusPayload,
         },
+        prepare_canister_migration::PrepareCanisterMigrationPayload,
         reroute_canister_range::RerouteCanisterRangePayload,
+        split_subnet::SplitSubnetPayload,
     },
     pb::v1::{NodeProvidersMonthlyXdrRewards, RegistryCanisterStableStorage},
     proto_on_wire::protobuf,
@@ -61,6 +67,7 @@ use dfn_core::println;
 
 use ic_protobuf::registry::node_operator::v1::RemoveNodeOperatorsPayload;
 use registry_canister::mutations::do_set_firewall_config::SetFirewallConfigPayload;
+use registry_canister::mutations::do_set_firewall_rules::SetFirewallRulesPayload;
 use registry_canister::mutations::node_management::do_add_node::AddNodePayload;
 
 // Makes expose_build_metadata! available.
@@ -70,6 +77,7 @@ extern crate ic_nervous_system_common;
 static mut REGISTRY: Option<Registry> = None;
 
 const MAX_VERSIONS_PER_QUERY: usize = 1000;
+const MAX_SNAPSHOT_KEYS_PER_QUERY: usize = 10_000;
 // The maximum size of deltas that the registry will attempt to send.
 // We reserve ⅓ of the response buffer capacity for encoding overhead.
 const MAX_REGISTRY_DELTAS_SIZE: usize = (MAX_RESPONSE_SIZE - MAX_RESPONSE_SIZE / 3) as usize;
@@ -237,7 +245,7 @@ fn get_certified_changes_since() {
     over(
         protobuf,
         |req: RegistryGetChangesSinceRequest| -> CertifiedResponse {
-            use ic_certified_map::{fork, labeled, labeled_hash};
+            use ic_certified_map::labeled;
             let latest_version = registry().latest_version();
             let from_version = EncodedVersion::from(req.version.saturating_add(1));
 
@@ -250,13 +258,49 @@ fn get_certified_changes_since() {
                 .changelog()
                 .value_range(from_version.as_ref(), to_version.as_ref());
 
-            let hash_tree = fork(
-                current_version_tree(latest_version),
+            let hash_tree = registry_tree(
+                registry(),
                 if req.version < latest_version {
                     labeled(b"delta", delta_tree)
                 } else {
-                    HashTree::Pruned(labeled_hash(b"delta", &registry().changelog().root_hash()))
+                    pruned_delta_tree(registry())
                 },
+                pruned_snapshot_tree(registry()),
+            );
+
+            certified_response(hash_tree)
+        },
+    )
+}
+
+/// Returns a page of the certified snapshot of the registry, i.e. of the
+/// latest value of every key at the snapshot version, starting at the
+/// requested key (inclusive).
+///
+/// Clients bootstrap from consecutive pages, each starting at the last key of
+/// the previous one, and then follow the deltas from the snapshot version on.
+/// A page is the last one if the hash tree contains no pruned entries after
+/// its last key.
+#[export_name = "canister_query get_certified_snapshot"]
+fn get_certified_snapshot() {
+    over(
+        protobuf,
+        |req: RegistryGetCertifiedSnapshotRequest| -> CertifiedResponse {
+            use ic_certified_map::labeled;
+            let registry = registry();
+            let last_key = registry
+                .last_fitting_snapshot_key(
+                    &req.start_key,
+                    MAX_SNAPSHOT_KEYS_PER_QUERY,
+                    MAX_REGISTRY_DELTAS_SIZE,
+                )
+                .unwrap_or(&req.start_key[..]);
+            let snapshot_tree = registry.snapshot().value_range(&req.start_key, last_key);
+
+            let hash_tree = registry_tree(
+                registry,
+                pruned_delta_tree(registry),
+                labeled(b"snapshot", snapshot_tree),
             );
 
             certified_response(hash_tree)
@@ -318,11 +362,10 @@ fn get_latest_version() {
 #[export_name = "canister_query get_certified_latest_version"]
 fn get_certified_latest_version() {
     over(protobuf, |_: Vec<u8>| -> CertifiedResponse {
-        use ic_certified_map::{fork, labeled_hash};
-        let latest_version = registry().latest_version();
-        let hash_tree = fork(
-            current_version_tree(latest_version),
-            HashTree::Pruned(labeled_hash(b"delta", &registry().changelog().root_hash())),
+        let hash_tree = registry_tree(
+            registry(),
+            pruned_delta_tree(registry()),
+            pruned_snapshot_tree(registry()),
         );
         certified_response(hash_tree)
     });
@@ -589,6 +632,20 @@ fn set_firewal_config_(payload: SetFirewallConfigPayload) {
     recertify_registry();
 }
 
+#[export_name = "canister_update set_firewall_rules"]
+fn set_firewall_rules() {
+    check_caller_is_governance_and_log("set_firewall_rules");
+    over(candid_one, |payload: SetFirewallRulesPayload| {
+        set_firewall_rules_(payload)
+    });
+}
+
+#[candid_method(update, rename = "set_firewall_rules")]
+fn set_firewall_rules_(payload: SetFirewallRulesPayload) {
+    registry_mut().do_set_firewall_rules(payload);
+    recertify_registry();
+}
+
 #[export_name = "canister_update update_node_rewards_table"]
 fn update_node_rewards_table() {
     check_caller_is_governance_and_log("update_node_rewards_table");
@@ -645,6 +702,60 @@ fn reroute_canister_range_(payload: RerouteCanisterRangePayload) -> Result<(), S
     Ok(())
 }
 
+#[export_name = "canister_update prepare_canister_migration"]
+fn prepare_canister_migration() {
+    check_caller_is_governance_and_log("prepare_canister_migration");
+    over_may_reject(candid_one, |payload: PrepareCanisterMigrationPayload| {
+        prepare_canister_migration_(payload)
+    });
+}
+
+#[candid_method(update, rename = "prepare_canister_migration")]
+fn prepare_canister_migration_(payload: PrepareCanisterMigrationPayload) -> Result<(), String> {
+    if let Err(msg) = registry_mut().prepare_canister_migration(payload) {
+        println!("{} Reject: {}", LOG_PREFIX, msg);
+        return Err(msg);
+    }
+    recertify_registry();
+    Ok(())
+}
+
+#[export_name = "canister_update complete_canister_migration"]
+fn complete_canister_migration() {
+    check_caller_is_governance_and_log("complete_canister_migration");
+    over_may_reject(candid_one, |payload: CompleteCanisterMigrationPayload| {
+        complete_canister_migration_(payload)
+    });
+}
+
+#[candid_method(update, rename = "complete_canister_migration")]
+fn complete_canister_migration_(payload: CompleteCanisterMigrationPayload) -> Result<(), String> {
+    if let Err(msg) = registry_mut().complete_canister_migration(payload) {
+        println!("{} Reject: {}", LOG_PREFIX, msg);
+        return Err(msg);
+    }
+    recertify_registry();
+    Ok(())
+}
+
+#[export_name = "canister_update split_subnet"]
+fn split_subnet() {
+    check_caller_is_governance_and_log("split_subnet");
+    over_may_reject(candid_one, |payload: SplitSubnetPayload| {
+        split_subnet_(payload)
+    });
+}
+
+#[candid_method(update, rename = "split_subnet")]
+fn split_subnet_(payload: SplitSubnetPayload) -> Result<(), String> {
+    if let Err(msg) = registry_mut().split_subnet(payload) {
+        println!("{} Reject: {}", LOG_PREFIX, msg);
+        return Err(msg);
+    }
+    recertify_registry();
+    Ok(())
+}
+
 #[export_name = "canister_query get_node_providers_monthly_xdr_rewards"]
 fn get_node_providers_monthly_xdr_rewards() {
     check_caller_is_governance_and_log("get_node_providers_monthly_xdr_rewards");
@@ -699,6 +810,24 @@ fn update_node_directly_(payload: UpdateNodeDirectlyPayload) -> Result<(), Strin
     result
 }
 
+#[export_name = "canister_update report_upgrade_status"]
+fn report_upgrade_status() {
+    // This method can be called by anyone
+    println!(
+        "{}call: report_upgrade_status from: {}",
+        LOG_PREFIX,
+        dfn_core::api::caller()
+    );
+    over_may_reject(candid_one, report_upgrade_status_);
+}
+
+#[candid_method(update, rename = "report_upgrade_status")]
+fn report_upgrade_status_(payload: ReportUpgradeStatusPayload) -> Result<(), String> {
+    let result = registry_mut().do_report_upgrade_status(payload);
+    recertify_registry();
+    result
+}
+
 #[export_name = "canister_update remove_node_directly"]
 fn remove_node_directly() {
     // This method can be called by anyone
@@ -8,18 +8,27 @@
 //! |
 //! +-- current_version -- [ LEB128-encoded VERSION ]
 //! |
-//! `-- delta --+-- [ big-endian encoded 1u64    ] -- [ serialized protobuf ]
-//!             |
-//!             …
-//!             |
-//!             `-- [ big-endian encoded VERSION ] -- [ serialized protobuf ]
+//! +-- delta --+-- [ big-endian encoded 1u64    ] -- [ serialized protobuf ]
+//! |           |
+//! |           …
+//! |           |
+//! |           `-- [ big-endian encoded VERSION ] -- [ serialized protobuf ]
+//! |
+//! +-- snapshot --+-- [ KEY ] -- [ serialized RegistryValue protobuf ]
+//! |              |
+//! |              …
+//! |
+//! `-- snapshot_version -- [ LEB128-encoded SNAPSHOT_VERSION ]
 //! ```
 //!
-//! where lebels under "delta" form contiguous range [1,VERSION].
+//! where lebels under "delta" form contiguous range [1,VERSION], and the
+//! "snapshot" contains the latest value (or tombstone) of every key at
+//! SNAPSHOT_VERSION, the latest multiple of `SNAPSHOT_INTERVAL` not greater
+//! than VERSION.
 
 #[cfg(target_arch = "wasm32")]
 use dfn_core::api::set_certified_data;
-use ic_certified_map::{labeled, HashTree};
+use ic_certified_map::{fork, labeled, labeled_hash, AsHashTree, HashTree};
 use ic_protobuf::messaging::xnet::v1 as pb;
 
 use crate::registry::{Registry, Version};
@@ -29,11 +38,43 @@ use crate::registry::{Registry, Version};
 const MAX_U64_ENCODING_BYTES: usize = 10;
 
 pub fn current_version_tree(v: Version) -> HashTree<'static> {
+    version_tree(b"current_version", v)
+}
+
+fn snapshot_version_tree(v: Version) -> HashTree<'static> {
+    version_tree(b"snapshot_version", v)
+}
+
+fn version_tree(label: &'static [u8], v: Version) -> HashTree<'static> {
     let mut buf = Vec::with_capacity(MAX_U64_ENCODING_BYTES);
     leb128::write::unsigned(&mut buf, v).unwrap();
-    labeled(
-        b"current_version",
-        HashTree::Leaf(std::borrow::Cow::from(buf)),
+    labeled(label, HashTree::Leaf(std::borrow::Cow::from(buf)))
+}
+
+/// Returns the "delta" subtree of the registry with all its contents pruned.
+pub fn pruned_delta_tree(registry: &Registry) -> HashTree<'static> {
+    HashTree::Pruned(labeled_hash(b"delta", &registry.changelog().root_hash()))
+}
+
+/// Returns the "snapshot" subtree of the registry with all its contents
+/// pruned.
+pub fn pruned_snapshot_tree(registry: &Registry) -> HashTree<'static> {
+    HashTree::Pruned(labeled_hash(b"snapshot", &registry.snapshot().root_hash()))
+}
+
+/// Assembles the hash tree of the registry from its (possibly pruned) "delta"
+/// and "snapshot" subtrees.
+pub fn registry_tree<'a>(
+    registry: &Registry,
+    delta: HashTree<'a>,
+    snapshot: HashTree<'a>,
+) -> HashTree<'a> {
+    fork(
+        current_version_tree(registry.latest_version()),
+        fork(
+            delta,
+            fork(snapshot, snapshot_version_tree(registry.snapshot_version())),
+        ),
     )
 }
 
@@ -65,12 +106,12 @@ pub fn hash_tree_to_proto(tree: HashTree<'_>) -> pb::MixedHashTree {
 #[cfg(target_arch = "wasm32")]
 /// Updates the certified data for the canister from the current registry state
 pub fn recertify_registry(registry: &Registry) {
-    use ic_certified_map::{fork_hash, labeled_hash, AsHashTree};
-
-    let root_hash = fork_hash(
-        &current_version_tree(registry.latest_version()).reconstruct(),
-        &labeled_hash(b"delta", &registry.changelog().root_hash()),
-    );
+    let root_hash = registry_tree(
+        registry,
+        pruned_delta_tree(registry),
+        pruned_snapshot_tree(registry),
+    )
+    .reconstruct();
 
     set_certified_data(&root_hash);
 }
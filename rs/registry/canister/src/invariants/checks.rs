@@ -2,7 +2,9 @@ use crate::{
     common::LOG_PREFIX,
     invariants::{
         common::RegistrySnapshot, crypto::check_node_crypto_keys_invariants,
-        endpoint::check_endpoint_invariants, node_operator::check_node_operator_invariants,
+        endpoint::check_endpoint_invariants, firewall::check_firewall_rules_invariants,
+        node_operator::check_node_operator_invariants,
+        node_upgrade_status::check_node_upgrade_status_invariants,
         replica_version::check_replica_version_invariants,
         routing_table::check_routing_table_invariants, subnet::check_subnet_invariants,
         unassigned_nodes_config::check_unassigned_nodes_config_invariants,
@@ -79,6 +81,12 @@ impl Registry {
         // Unassigned node invariants
         result = result.and(check_unassigned_nodes_config_invariants(&snapshot));
 
+        // Firewall rules invariants
+        result = result.and(check_firewall_rules_invariants(&snapshot));
+
+        // Node upgrade status invariants
+        result = result.and(check_node_upgrade_status_invariants(&snapshot));
+
         if let Err(e) = result {
             panic!(
                 "{} invariant check failed with message:{}",
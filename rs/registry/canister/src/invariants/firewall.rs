@@ -0,0 +1,213 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use crate::{
+    common::LOG_PREFIX,
+    invariants::common::{InvariantCheckError, RegistrySnapshot},
+};
+
+use ic_nns_common::registry::decode_or_panic;
+use ic_protobuf::registry::firewall::v1::{FirewallAction, FirewallRule, FirewallRuleSet};
+use ic_registry_keys::{FirewallRulesScope, FIREWALL_RULES_RECORD_KEY_PREFIX};
+
+/// Firewall rules invariants hold iff:
+///    * Every firewall rules record is keyed by a valid scope
+///    * Every rule has at least one source prefix, and all prefixes are
+///      valid IPv4 or IPv6 prefixes, respectively
+///    * Every rule has at least one port, and all ports are non-zero and
+///      fit into 16 bits
+///    * Every rule has a specified action
+pub(crate) fn check_firewall_rules_invariants(
+    snapshot: &RegistrySnapshot,
+) -> Result<(), InvariantCheckError> {
+    println!("{}check_firewall_rules_invariants", LOG_PREFIX);
+
+    for (key, value) in snapshot {
+        let key = String::from_utf8_lossy(key);
+        let scope = match key.strip_prefix(FIREWALL_RULES_RECORD_KEY_PREFIX) {
+            Some(scope) => scope,
+            None => continue,
+        };
+        FirewallRulesScope::from_str(scope).map_err(|err| InvariantCheckError {
+            msg: format!(
+                "Firewall rules record {} has an invalid scope: {}",
+                key, err
+            ),
+            source: None,
+        })?;
+
+        let rule_set = decode_or_panic::<FirewallRuleSet>(value.clone());
+        for rule in &rule_set.entries {
+            validate_firewall_rule(rule).map_err(|err| InvariantCheckError {
+                msg: format!(
+                    "Firewall rules record {} contains an invalid rule {:?}: {}",
+                    key, rule, err
+                ),
+                source: None,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_firewall_rule(rule: &FirewallRule) -> Result<(), String> {
+    if rule.ipv4_prefixes.is_empty() && rule.ipv6_prefixes.is_empty() {
+        return Err("the rule has no prefixes".to_string());
+    }
+    for prefix in &rule.ipv4_prefixes {
+        validate_prefix::<Ipv4Addr>(prefix, 32)?;
+    }
+    for prefix in &rule.ipv6_prefixes {
+        validate_prefix::<Ipv6Addr>(prefix, 128)?;
+    }
+
+    if rule.ports.is_empty() {
+        return Err("the rule has no ports".to_string());
+    }
+    if let Some(port) = rule
+        .ports
+        .iter()
+        .find(|port| **port == 0 || **port > u16::MAX as u32)
+    {
+        return Err(format!("invalid port {}", port));
+    }
+
+    match FirewallAction::from_i32(rule.action) {
+        None | Some(FirewallAction::Unspecified) => Err(format!("invalid action {}", rule.action)),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Checks that `prefix` is an address of type `A`, optionally followed by a
+/// `/` and a prefix length of at most `max_length`.
+fn validate_prefix<A: FromStr>(prefix: &str, max_length: u8) -> Result<(), String> {
+    let (address, length) = match prefix.split_once('/') {
+        Some((address, length)) => (address, Some(length)),
+        None => (prefix, None),
+    };
+    let valid_address = address.parse::<A>().is_ok();
+    let valid_length = length.map_or(true, |length| {
+        length
+            .parse::<u8>()
+            .map_or(false, |length| length <= max_length)
+    });
+    if valid_address && valid_length {
+        Ok(())
+    } else {
+        Err(format!("invalid prefix {}", prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::{PrincipalId, SubnetId};
+    use ic_nns_common::registry::encode_or_panic;
+    use ic_registry_keys::make_firewall_rules_record_key;
+
+    fn valid_rule() -> FirewallRule {
+        FirewallRule {
+            ipv4_prefixes: vec!["10.0.0.0/8".to_string(), "192.168.1.1".to_string()],
+            ipv6_prefixes: vec!["2001:db8::/32".to_string()],
+            ports: vec![22, 8080],
+            action: FirewallAction::Allow as i32,
+            comment: "test rule".to_string(),
+        }
+    }
+
+    fn snapshot_with_rules(key: String, rules: Vec<FirewallRule>) -> RegistrySnapshot {
+        let mut snapshot = RegistrySnapshot::new();
+        snapshot.insert(
+            key.into_bytes(),
+            encode_or_panic(&FirewallRuleSet { entries: rules }),
+        );
+        snapshot
+    }
+
+    #[test]
+    fn valid_rules_pass() {
+        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
+        for scope in &[
+            FirewallRulesScope::Global,
+            FirewallRulesScope::ReplicaNodes,
+            FirewallRulesScope::Subnet(subnet_id),
+        ] {
+            let snapshot =
+                snapshot_with_rules(make_firewall_rules_record_key(scope), vec![valid_rule()]);
+            assert!(check_firewall_rules_invariants(&snapshot).is_ok());
+        }
+
+        // An empty rule set is valid, too.
+        let snapshot = snapshot_with_rules(
+            make_firewall_rules_record_key(&FirewallRulesScope::Global),
+            vec![],
+        );
+        assert!(check_firewall_rules_invariants(&snapshot).is_ok());
+    }
+
+    #[test]
+    fn invalid_scope_fails() {
+        let snapshot = snapshot_with_rules(
+            format!("{}everything", FIREWALL_RULES_RECORD_KEY_PREFIX),
+            vec![valid_rule()],
+        );
+        assert!(check_firewall_rules_invariants(&snapshot).is_err());
+    }
+
+    #[test]
+    fn invalid_rules_fail() {
+        let invalid_rules = vec![
+            FirewallRule {
+                ipv4_prefixes: vec![],
+                ipv6_prefixes: vec![],
+                ..valid_rule()
+            },
+            FirewallRule {
+                ipv4_prefixes: vec!["10.0.0.0/33".to_string()],
+                ..valid_rule()
+            },
+            FirewallRule {
+                ipv4_prefixes: vec!["2001:db8::/32".to_string()],
+                ..valid_rule()
+            },
+            FirewallRule {
+                ipv6_prefixes: vec!["2001:db8::/129".to_string()],
+                ..valid_rule()
+            },
+            FirewallRule {
+                ports: vec![],
+                ..valid_rule()
+            },
+            FirewallRule {
+                ports: vec![0],
+                ..valid_rule()
+            },
+            FirewallRule {
+                ports: vec![65536],
+                ..valid_rule()
+            },
+            FirewallRule {
+                action: FirewallAction::Unspecified as i32,
+                ..valid_rule()
+            },
+            FirewallRule {
+                action: 42,
+                ..valid_rule()
+            },
+        ];
+        for rule in invalid_rules {
+            let snapshot = snapshot_with_rules(
+                make_firewall_rules_record_key(&FirewallRulesScope::Global),
+                vec![valid_rule(), rule.clone()],
+            );
+            assert!(
+                check_firewall_rules_invariants(&snapshot).is_err(),
+                "rule {:?} should be invalid",
+                rule
+            );
+        }
+    }
+}
@@ -2,7 +2,9 @@ mod checks;
 mod common;
 mod crypto;
 mod endpoint;
+mod firewall;
 mod node_operator;
+mod node_upgrade_status;
 mod replica_version;
 mod routing_table;
 mod subnet;
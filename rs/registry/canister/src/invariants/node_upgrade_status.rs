@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use crate::{
+    common::LOG_PREFIX,
+    invariants::common::{InvariantCheckError, RegistrySnapshot},
+};
+
+use ic_base_types::{NodeId, PrincipalId};
+use ic_registry_keys::{make_node_record_key, NODE_UPGRADE_STATUS_RECORD_KEY_PREFIX};
+
+/// Node upgrade status invariants hold iff:
+///    * Every node upgrade status record is keyed by a valid node ID
+///    * Every node upgrade status record belongs to a node in the registry
+pub(crate) fn check_node_upgrade_status_invariants(
+    snapshot: &RegistrySnapshot,
+) -> Result<(), InvariantCheckError> {
+    println!("{}check_node_upgrade_status_invariants", LOG_PREFIX);
+
+    for key in snapshot.keys() {
+        let key = String::from_utf8_lossy(key);
+        let node_id = match key.strip_prefix(NODE_UPGRADE_STATUS_RECORD_KEY_PREFIX) {
+            Some(node_id) => node_id,
+            None => continue,
+        };
+        let node_id = PrincipalId::from_str(node_id)
+            .map(NodeId::from)
+            .map_err(|err| InvariantCheckError {
+                msg: format!(
+                    "Node upgrade status record {} has an invalid node ID: {}",
+                    key, err
+                ),
+                source: None,
+            })?;
+        if !snapshot.contains_key(make_node_record_key(node_id).as_bytes()) {
+            return Err(InvariantCheckError {
+                msg: format!(
+                    "Node upgrade status record {} belongs to node {}, which is not in the registry",
+                    key, node_id
+                ),
+                source: None,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_nns_common::registry::encode_or_panic;
+    use ic_protobuf::registry::{
+        node::v1::NodeRecord,
+        subnet::v1::{NodeUpgradeStatus, NodeUpgradeStatusRecord},
+    };
+    use ic_registry_keys::make_node_upgrade_status_record_key;
+
+    fn snapshot_with_status_record(node_id: NodeId) -> RegistrySnapshot {
+        let mut snapshot = RegistrySnapshot::new();
+        snapshot.insert(
+            make_node_upgrade_status_record_key(node_id).into_bytes(),
+            encode_or_panic(&NodeUpgradeStatusRecord {
+                replica_version_id: "version_42".to_string(),
+                status: NodeUpgradeStatus::Healthy as i32,
+                finalization_rate_per_minute: 60,
+            }),
+        );
+        snapshot
+    }
+
+    #[test]
+    fn status_record_of_existing_node_passes() {
+        let node_id = NodeId::from(PrincipalId::new_node_test_id(1));
+        let mut snapshot = snapshot_with_status_record(node_id);
+        snapshot.insert(
+            make_node_record_key(node_id).into_bytes(),
+            encode_or_panic(&NodeRecord::default()),
+        );
+        assert!(check_node_upgrade_status_invariants(&snapshot).is_ok());
+    }
+
+    #[test]
+    fn status_record_of_missing_node_fails() {
+        let node_id = NodeId::from(PrincipalId::new_node_test_id(1));
+        let mut snapshot = snapshot_with_status_record(node_id);
+        snapshot.insert(
+            make_node_record_key(NodeId::from(PrincipalId::new_node_test_id(2))).into_bytes(),
+            encode_or_panic(&NodeRecord::default()),
+        );
+        assert!(check_node_upgrade_status_invariants(&snapshot).is_err());
+    }
+
+    #[test]
+    fn status_record_with_invalid_node_id_fails() {
+        let mut snapshot = RegistrySnapshot::new();
+        snapshot.insert(
+            format!("{}not-a-node", NODE_UPGRADE_STATUS_RECORD_KEY_PREFIX).into_bytes(),
+            encode_or_panic(&NodeUpgradeStatusRecord::default()),
+        );
+        assert!(check_node_upgrade_status_invariants(&snapshot).is_err());
+    }
+}
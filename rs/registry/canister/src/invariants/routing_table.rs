@@ -3,15 +3,19 @@ use crate::invariants::common::{InvariantCheckError, RegistrySnapshot};
 use std::convert::TryFrom;
 
 use ic_nns_common::registry::decode_or_panic;
-use ic_protobuf::registry::routing_table::v1::RoutingTable as pbRoutingTable;
-use ic_registry_keys::make_routing_table_record_key;
-use ic_registry_routing_table::RoutingTable;
+use ic_protobuf::registry::routing_table::v1::{
+    CanisterMigrations as pbCanisterMigrations, RoutingTable as pbRoutingTable,
+};
+use ic_registry_keys::{make_canister_migrations_record_key, make_routing_table_record_key};
+use ic_registry_routing_table::{CanisterMigrations, RoutingTable};
 
-/// Routing table invariants hold if it reading and conversion succeed.
+/// Routing table invariants hold if it reading and conversion succeed, and
+/// the same holds for the canister migrations, if present.
 pub(crate) fn check_routing_table_invariants(
     snapshot: &RegistrySnapshot,
 ) -> Result<(), InvariantCheckError> {
     get_routing_table(snapshot);
+    get_canister_migrations(snapshot);
     Ok(())
 }
 
@@ -25,3 +29,15 @@ fn get_routing_table(snapshot: &RegistrySnapshot) -> RoutingTable {
         None => panic!("No routing table in snapshot"),
     }
 }
+
+// Return canister migrations from snapshot, if any
+fn get_canister_migrations(snapshot: &RegistrySnapshot) -> Option<CanisterMigrations> {
+    snapshot
+        .get(make_canister_migrations_record_key().as_bytes())
+        .map(|canister_migrations_vec| {
+            CanisterMigrations::try_from(decode_or_panic::<pbCanisterMigrations>(
+                (*canister_migrations_vec).clone(),
+            ))
+            .unwrap()
+        })
+}
@@ -10,7 +10,7 @@ use crate::invariants::common::{
 use ic_base_types::SubnetId;
 use ic_base_types::{NodeId, PrincipalId};
 use ic_nns_common::registry::{decode_or_panic, MAX_NUM_SSH_KEYS};
-use ic_protobuf::registry::subnet::v1::{SubnetRecord, SubnetType};
+use ic_protobuf::registry::subnet::v1::{RolloutPolicy, SubnetRecord, SubnetType};
 use ic_registry_keys::{make_node_record_key, make_subnet_record_key, SUBNET_RECORD_KEY_PREFIX};
 
 /// Subnet invariants hold iff:
@@ -20,6 +20,9 @@ use ic_registry_keys::{make_node_record_key, make_subnet_record_key, SUBNET_RECO
 ///    * Each subnet contains at least one node
 ///    * There is at least one system subnet
 ///    * Each subnet in the registry occurs in the subnet list and vice versa
+///    * The rollout policy of each subnet, if any, only names members of the
+///      subnet as canary nodes, has strictly increasing stage percentages
+///      of at most 100, and its first stage covers at least 2f+1 nodes
 pub(crate) fn check_subnet_invariants(
     snapshot: &RegistrySnapshot,
 ) -> Result<(), InvariantCheckError> {
@@ -103,6 +106,10 @@ pub(crate) fn check_subnet_invariants(
                 the round instruction limit."
         );
 
+        if let Some(rollout_policy) = &subnet_record.rollout_policy {
+            check_rollout_policy_invariants(subnet_id, rollout_policy, &subnet_members)?;
+        }
+
         check_gossip_config_invariants(subnet_id, subnet_record);
     }
     // There is at least one system subnet
@@ -140,6 +147,71 @@ pub(crate) fn get_subnet_records_map(
     subnets
 }
 
+fn check_rollout_policy_invariants(
+    subnet_id: SubnetId,
+    rollout_policy: &RolloutPolicy,
+    subnet_members: &HashSet<NodeId>,
+) -> Result<(), InvariantCheckError> {
+    for canary_node in &rollout_policy.canary_nodes {
+        let is_member = PrincipalId::try_from(canary_node)
+            .map(|id| subnet_members.contains(&NodeId::from(id)))
+            .unwrap_or(false);
+        if !is_member {
+            return Err(InvariantCheckError {
+                msg: format!(
+                    "The rollout policy of subnet {} names a canary node that is not a member \
+                    of the subnet: {:?}",
+                    subnet_id, canary_node
+                ),
+                source: None,
+            });
+        }
+    }
+
+    let percentages = &rollout_policy.stage_percentages;
+    if percentages.windows(2).any(|pair| pair[0] >= pair[1])
+        || percentages.iter().any(|percentage| *percentage > 100)
+    {
+        return Err(InvariantCheckError {
+            msg: format!(
+                "The stage percentages of the rollout policy of subnet {} are not strictly \
+                increasing and at most 100: {:?}",
+                subnet_id, percentages
+            ),
+            source: None,
+        });
+    }
+
+    // A subnet only finalizes blocks at the new replica version once 2f+1 of
+    // its nodes run it, so the first stage (the canaries, topped up to the
+    // first percentage) has to be at least that large. Otherwise its nodes
+    // would report unhealthy upgrades and halt the rollout for good.
+    let total = subnet_members.len();
+    let canary_count = rollout_policy
+        .canary_nodes
+        .iter()
+        .collect::<HashSet<_>>()
+        .len();
+    let first_stage_size = match percentages.first() {
+        Some(percentage) => ((*percentage as usize * total + 99) / 100).max(canary_count),
+        None if canary_count > 0 => canary_count,
+        None => total,
+    };
+    let required_size = total - total.saturating_sub(1) / 3;
+    if first_stage_size < required_size {
+        return Err(InvariantCheckError {
+            msg: format!(
+                "The first stage of the rollout policy of subnet {} covers {} of its {} nodes, \
+                but at least {} are required for the subnet to make progress",
+                subnet_id, first_stage_size, total, required_size
+            ),
+            source: None,
+        });
+    }
+
+    Ok(())
+}
+
 /// Gossip config invariants hold iff:
 ///    * number of chunks requested in parallel > 0
 ///    * timeout for chunk > 200 ms
@@ -219,3 +291,95 @@ fn check_gossip_config_invariants(subnet_id: SubnetId, subnet_record: SubnetReco
         None => panic!("No gossip config defined in subnet record {:}.", subnet_id),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollout_policy_invariants() {
+        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
+        let node = |n: u64| NodeId::from(PrincipalId::new_node_test_id(n));
+        let members: HashSet<NodeId> = (1..=4).map(node).collect();
+        let non_member = node(5);
+        let policy = |canary_nodes: Vec<NodeId>, stage_percentages: Vec<u32>| RolloutPolicy {
+            canary_nodes: canary_nodes.iter().map(|id| id.get().to_vec()).collect(),
+            stage_percentages,
+            min_finalization_rate_per_minute: 10,
+            health_check_duration_seconds: 600,
+        };
+
+        assert!(
+            check_rollout_policy_invariants(subnet_id, &policy(vec![], vec![]), &members).is_ok()
+        );
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![node(1)], vec![75, 100]),
+            &members
+        )
+        .is_ok());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![node(1), node(2), node(3)], vec![]),
+            &members
+        )
+        .is_ok());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![non_member], vec![75]),
+            &members
+        )
+        .is_err());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![], vec![75, 75]),
+            &members
+        )
+        .is_err());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![], vec![75, 101]),
+            &members
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rollout_policy_first_stage_has_to_reach_two_thirds() {
+        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
+        let node = |n: u64| NodeId::from(PrincipalId::new_node_test_id(n));
+        let members: HashSet<NodeId> = (1..=10).map(node).collect();
+        let policy = |canary_nodes: Vec<NodeId>, stage_percentages: Vec<u32>| RolloutPolicy {
+            canary_nodes: canary_nodes.iter().map(|id| id.get().to_vec()).collect(),
+            stage_percentages,
+            min_finalization_rate_per_minute: 10,
+            health_check_duration_seconds: 600,
+        };
+
+        // 10 nodes tolerate f = 3 faults, so the first stage needs 7 nodes.
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![node(1)], vec![10, 50, 100]),
+            &members
+        )
+        .is_err());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![node(1)], vec![60]),
+            &members
+        )
+        .is_err());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![node(1)], vec![]),
+            &members
+        )
+        .is_err());
+        assert!(check_rollout_policy_invariants(
+            subnet_id,
+            &policy(vec![node(1)], vec![70, 100]),
+            &members
+        )
+        .is_ok());
+    }
+}
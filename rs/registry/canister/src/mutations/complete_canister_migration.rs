@@ -0,0 +1,72 @@
+use crate::registry::Registry;
+use candid::CandidType;
+use ic_base_types::{PrincipalId, SubnetId};
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+impl Registry {
+    /// Validates the payload and removes the canister id ranges from the
+    /// canister migrations.
+    ///
+    /// This is the last stage of a canister migration, after the ranges have
+    /// been rerouted to the destination subnet (using `reroute_canister_range`)
+    /// and all messages routed through the source subnet have been delivered.
+    /// It is also used to abort a migration: before rerouting, it unfreezes
+    /// the ranges on the source subnet; after rerouting, the ranges must first
+    /// be rerouted back to the source subnet.
+    pub fn complete_canister_migration(
+        &mut self,
+        payload: CompleteCanisterMigrationPayload,
+    ) -> Result<(), String> {
+        let ranges = CanisterIdRanges::try_from(payload.canister_id_ranges)
+            .map_err(|e| format!("invalid canister id ranges: {:?}", e))?;
+        let trace: Vec<SubnetId> = payload
+            .migration_trace
+            .into_iter()
+            .map(SubnetId::from)
+            .collect();
+
+        let version = self.latest_version();
+
+        // The ranges must be routed to one of the ends of the migration
+        // trace: the source subnet if the migration is aborted, the
+        // destination subnet if it is completed.
+        let routing_table = self.get_routing_table_or_panic(version);
+        let endpoints = [trace.first().copied(), trace.last().copied()];
+        for range in ranges.iter() {
+            let hosted = endpoints
+                .iter()
+                .flatten()
+                .any(|subnet| routing_table.ranges(*subnet).contains_range(range));
+            if !hosted {
+                return Err(format!(
+                    "canister id range {:?} is not hosted by either end of the migration trace {:?}",
+                    range, trace
+                ));
+            }
+        }
+
+        let mut canister_migrations = self.get_canister_migrations(version);
+        canister_migrations
+            .remove_ranges(ranges, trace)
+            .map_err(|e| format!("failed to complete canister migration: {}", e))?;
+
+        self.maybe_apply_mutation_internal(vec![
+            self.canister_migrations_mutation(canister_migrations)
+        ]);
+
+        Ok(())
+    }
+}
+
+/// The argument for the `complete_canister_migration` update call.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct CompleteCanisterMigrationPayload {
+    /// The canister id ranges whose migration is completed or aborted. Each
+    /// range must match an entry of the canister migrations exactly.
+    pub canister_id_ranges: Vec<CanisterIdRange>,
+    /// The migration trace of the ranges, i.e. the source subnet followed by
+    /// the destination subnet.
+    pub migration_trace: Vec<PrincipalId>,
+}
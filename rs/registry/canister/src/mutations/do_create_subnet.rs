@@ -282,6 +282,7 @@ impl From<CreateSubnetPayload> for SubnetRecord {
             ssh_readonly_access: val.ssh_readonly_access,
             ssh_backup_access: val.ssh_backup_access,
             ecdsa_config: None,
+            rollout_policy: None,
         }
     }
 }
@@ -0,0 +1,48 @@
+use crate::{common::LOG_PREFIX, registry::Registry};
+
+use candid::{CandidType, Deserialize};
+#[cfg(target_arch = "wasm32")]
+use dfn_core::println;
+use serde::Serialize;
+
+use crate::mutations::common::encode_or_panic;
+use ic_protobuf::registry::firewall::v1::{FirewallRule, FirewallRuleSet};
+use ic_registry_keys::{make_firewall_rules_record_key, FirewallRulesScope};
+use ic_registry_transport::pb::v1::{registry_mutation, RegistryMutation};
+
+impl Registry {
+    /// Sets the firewall rules of the given scope in the registry, replacing
+    /// any rules previously set for that scope.
+    ///
+    /// This method is called by the proposals canister.
+    pub fn do_set_firewall_rules(&mut self, payload: SetFirewallRulesPayload) {
+        println!(
+            "{}do_set_firewall_rules: scope: {:?}, rules: {:?}",
+            LOG_PREFIX, payload.scope, payload.rules
+        );
+
+        let rule_set = FirewallRuleSet {
+            entries: payload.rules,
+        };
+
+        let mutations = vec![RegistryMutation {
+            mutation_type: registry_mutation::Type::Upsert as i32,
+            key: make_firewall_rules_record_key(&payload.scope).into_bytes(),
+            value: encode_or_panic(&rule_set),
+        }];
+
+        // Check invariants before applying mutations
+        self.maybe_apply_mutation_internal(mutations);
+    }
+}
+
+/// The payload of a proposal to set the firewall rules of a scope.
+///
+/// See /rs/protobuf/def/registry/firewall/v1/firewall.proto
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SetFirewallRulesPayload {
+    /// The scope the rules apply to.
+    pub scope: FirewallRulesScope,
+    /// The rules, in the order in which they are applied.
+    pub rules: Vec<FirewallRule>,
+}
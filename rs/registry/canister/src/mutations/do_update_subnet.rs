@@ -8,7 +8,7 @@ use serde::Serialize;
 use ic_base_types::{subnet_id_into_protobuf, SubnetId};
 use ic_protobuf::registry::{
     crypto::v1::EcdsaSigningSubnetList,
-    subnet::v1::{EcdsaConfig, GossipAdvertConfig, SubnetRecord},
+    subnet::v1::{EcdsaConfig, GossipAdvertConfig, RolloutPolicy, SubnetRecord},
 };
 use ic_registry_keys::{make_ecdsa_signing_subnet_list_key, make_subnet_record_key};
 use ic_registry_subnet_features::SubnetFeatures;
@@ -154,6 +154,8 @@ pub struct UpdateSubnetPayload {
 
     pub ssh_readonly_access: Option<Vec<String>>,
     pub ssh_backup_access: Option<Vec<String>>,
+
+    pub rollout_policy: Option<RolloutPolicy>,
 }
 
 // Sets the value of a field in record `a` if the provided value `b` is not
@@ -241,6 +243,7 @@ fn merge_subnet_record(
         max_number_of_canisters,
         ssh_readonly_access,
         ssh_backup_access,
+        rollout_policy,
     } = payload;
 
     maybe_set!(subnet_record, max_ingress_bytes_per_message);
@@ -302,6 +305,8 @@ fn merge_subnet_record(
     maybe_set!(subnet_record, ssh_readonly_access);
     maybe_set!(subnet_record, ssh_backup_access);
 
+    maybe_set_option!(subnet_record, rollout_policy);
+
     subnet_record
 }
 
@@ -362,6 +367,7 @@ mod tests {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            rollout_policy: None,
         }
     }
 
@@ -399,6 +405,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -445,6 +452,7 @@ mod tests {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            rollout_policy: None,
         };
 
         assert_eq!(
@@ -494,6 +502,7 @@ mod tests {
                 max_number_of_canisters: 10,
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
+                rollout_policy: None,
             }
         );
     }
@@ -534,6 +543,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -572,6 +582,7 @@ mod tests {
             max_number_of_canisters: Some(50),
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            rollout_policy: None,
         };
 
         assert_eq!(
@@ -610,6 +621,7 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                rollout_policy: None,
             }
         );
     }
@@ -717,6 +729,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -755,6 +768,7 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            rollout_policy: None,
         };
 
         merge_subnet_record(subnet_record, payload);
@@ -784,6 +798,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -822,6 +837,7 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            rollout_policy: None,
         };
 
         assert_eq!(
@@ -860,6 +876,7 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                rollout_policy: None,
             }
         );
     }
@@ -900,6 +917,7 @@ mod tests {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         let payload = UpdateSubnetPayload {
@@ -938,6 +956,7 @@ mod tests {
             max_number_of_canisters: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            rollout_policy: None,
         };
 
         assert_eq!(
@@ -976,6 +995,7 @@ mod tests {
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
+                rollout_policy: None,
             }
         );
     }
@@ -1,4 +1,5 @@
 pub mod common;
+pub mod complete_canister_migration;
 mod dkg;
 pub mod do_add_node_operator;
 pub mod do_add_nodes_to_subnet;
@@ -11,6 +12,7 @@ pub mod do_recover_subnet;
 pub mod do_remove_node_operators;
 pub mod do_remove_nodes_from_subnet;
 pub mod do_set_firewall_config;
+pub mod do_set_firewall_rules;
 pub mod do_update_node_directly;
 pub mod do_update_node_operator_config;
 pub mod do_update_node_operator_config_directly;
@@ -19,6 +21,8 @@ pub mod do_update_subnet;
 pub mod do_update_subnet_replica;
 pub mod do_update_unassigned_nodes_config;
 pub mod node_management;
+pub mod prepare_canister_migration;
 pub mod reroute_canister_range;
+pub mod split_subnet;
 mod routing_table;
 mod subnet;
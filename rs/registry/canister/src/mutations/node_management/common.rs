@@ -6,10 +6,13 @@ use ic_protobuf::registry::{
     subnet::v1::{SubnetListRecord, SubnetRecord},
 };
 use ic_registry_keys::{
-    make_node_operator_record_key, make_node_record_key, make_subnet_list_record_key,
-    make_subnet_record_key,
+    make_node_operator_record_key, make_node_record_key, make_node_upgrade_status_record_key,
+    make_subnet_list_record_key, make_subnet_record_key,
+};
+use ic_registry_transport::{
+    delete,
+    pb::v1::{RegistryMutation, RegistryValue},
 };
-use ic_registry_transport::pb::v1::RegistryValue;
 use std::convert::TryFrom;
 
 pub fn find_subnet_for_node(
@@ -111,3 +114,15 @@ pub fn get_node_operator_record(
             },
         )
 }
+
+/// Returns the mutation that deletes the upgrade status record reported by
+/// `node_id`, if the node reported one, so that it is removed with the node.
+pub fn make_remove_node_upgrade_status_mutation(
+    registry: &Registry,
+    node_id: NodeId,
+) -> Option<RegistryMutation> {
+    let key = make_node_upgrade_status_record_key(node_id);
+    registry
+        .get(key.as_bytes(), registry.latest_version())
+        .map(|_| delete(key))
+}
@@ -1,6 +1,6 @@
 use crate::mutations::node_management::common::{
     find_subnet_for_node, get_node_operator_id_for_node, get_node_operator_record,
-    get_subnet_list_record,
+    get_subnet_list_record, make_remove_node_upgrade_status_mutation,
 };
 use crate::{common::LOG_PREFIX, registry::Registry};
 use candid::{CandidType, Deserialize};
@@ -66,15 +66,20 @@ impl Registry {
         // 5. Finally, generate the following mutations:
         //   * Delete the node
         //   * Increment NO's allowance by 1
+        //   * Delete the node's upgrade status record, if any
         let node_key = make_node_record_key(payload.node_id);
         let node_operator_key = make_node_operator_record_key(node_operator_id);
-        let mutations = vec![
+        let mut mutations = vec![
             delete(node_key),
             update(
                 node_operator_key,
                 encode_or_panic(&new_node_operator_record),
             ),
         ];
+        mutations.extend(make_remove_node_upgrade_status_mutation(
+            self,
+            payload.node_id,
+        ));
 
         // 6. Apply mutations after checking invariants
         self.maybe_apply_mutation_internal(mutations);
@@ -0,0 +1,77 @@
+use crate::{common::LOG_PREFIX, registry::Registry};
+use candid::{CandidType, Deserialize};
+#[cfg(target_arch = "wasm32")]
+use dfn_core::println;
+use ic_base_types::NodeId;
+use ic_nns_common::registry::encode_or_panic;
+use ic_protobuf::registry::subnet::v1::{NodeUpgradeStatus, NodeUpgradeStatusRecord};
+use ic_registry_keys::{make_node_record_key, make_node_upgrade_status_record_key};
+use ic_registry_transport::upsert;
+
+impl Registry {
+    /// Records the outcome of the caller's upgrade to a replica version, which
+    /// gates the staged rollout of that version to the other nodes of the
+    /// caller's subnet.
+    ///
+    /// This method is called directly by the node itself.
+    pub fn do_report_upgrade_status(
+        &mut self,
+        payload: ReportUpgradeStatusPayload,
+    ) -> Result<(), String> {
+        println!("{}do_report_upgrade_status: {:?}", LOG_PREFIX, payload);
+
+        // 1. Check that the caller is a node with a node_id that exists
+        let node_id = NodeId::from(dfn_core::api::caller());
+        self.get(
+            make_node_record_key(node_id).as_bytes(),
+            self.latest_version(),
+        )
+        .ok_or_else(|| {
+            format!(
+                "{}do_report_upgrade_status: Node Id {} not found in the registry, \
+                 aborting upgrade status report.",
+                LOG_PREFIX, node_id
+            )
+        })?;
+
+        // 2. Check that the payload names a replica version
+        if payload.replica_version_id.is_empty() {
+            return Err(format!(
+                "{}do_report_upgrade_status: replica_version_id is empty",
+                LOG_PREFIX
+            ));
+        }
+
+        // 3. Create and apply mutation for the node's status record
+        let status = if payload.healthy {
+            NodeUpgradeStatus::Healthy
+        } else {
+            NodeUpgradeStatus::Unhealthy
+        };
+        let record = NodeUpgradeStatusRecord {
+            replica_version_id: payload.replica_version_id,
+            status: status as i32,
+            finalization_rate_per_minute: payload.finalization_rate_per_minute,
+        };
+        let mutations = vec![upsert(
+            make_node_upgrade_status_record_key(node_id).into_bytes(),
+            encode_or_panic(&record),
+        )];
+
+        // Check invariants before applying mutations
+        self.maybe_apply_mutation_internal(mutations);
+
+        Ok(())
+    }
+}
+
+/// The payload of a request of a node to report the outcome of its upgrade.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReportUpgradeStatusPayload {
+    /// The replica version the node upgraded to.
+    pub replica_version_id: String,
+    /// Whether the node was healthy after the upgrade.
+    pub healthy: bool,
+    /// The finalization rate, in blocks per minute, observed by the node.
+    pub finalization_rate_per_minute: u32,
+}
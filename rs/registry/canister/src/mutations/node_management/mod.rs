@@ -2,3 +2,4 @@ mod common;
 pub mod do_add_node;
 pub mod do_remove_node_directly;
 pub mod do_remove_nodes;
+pub mod do_report_upgrade_status;
@@ -0,0 +1,72 @@
+use crate::registry::Registry;
+use candid::CandidType;
+use ic_base_types::{PrincipalId, SubnetId};
+use ic_registry_keys::make_subnet_record_key;
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+impl Registry {
+    /// Validates the payload and adds the canister id ranges to the canister
+    /// migrations, with a migration trace from the source to the destination
+    /// subnet.
+    ///
+    /// This is the first stage of a canister migration: while a range is
+    /// being migrated but still routed to the source subnet, the source subnet
+    /// rejects new requests to the canisters in the range, so that their
+    /// queues and streams drain. The migration can be aborted at this stage
+    /// by calling `complete_canister_migration` with the same ranges and
+    /// migration trace.
+    pub fn prepare_canister_migration(
+        &mut self,
+        payload: PrepareCanisterMigrationPayload,
+    ) -> Result<(), String> {
+        let ranges = CanisterIdRanges::try_from(payload.canister_id_ranges)
+            .map_err(|e| format!("invalid canister id ranges: {:?}", e))?;
+        let source = SubnetId::from(payload.source_subnet);
+        let destination = SubnetId::from(payload.destination_subnet);
+
+        if source == destination {
+            return Err(format!(
+                "source and destination subnet are the same: {}",
+                source
+            ));
+        }
+
+        let version = self.latest_version();
+
+        self.get(&make_subnet_record_key(destination).into_bytes(), version)
+            .ok_or_else(|| format!("destination {} is not a known subnet", destination))?;
+
+        let source_ranges = self.get_routing_table_or_panic(version).ranges(source);
+        if let Some(range) = ranges.iter().find(|r| !source_ranges.contains_range(r)) {
+            return Err(format!(
+                "canister id range {:?} is not hosted by source subnet {}",
+                range, source
+            ));
+        }
+
+        let mut canister_migrations = self.get_canister_migrations(version);
+        canister_migrations
+            .insert_ranges(ranges, source, destination)
+            .map_err(|e| format!("failed to prepare canister migration: {:?}", e))?;
+
+        self.maybe_apply_mutation_internal(vec![
+            self.canister_migrations_mutation(canister_migrations)
+        ]);
+
+        Ok(())
+    }
+}
+
+/// The argument for the `prepare_canister_migration` update call.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct PrepareCanisterMigrationPayload {
+    /// The canister id ranges to migrate. The ranges must be sorted and
+    /// disjoint, and must be hosted by the source subnet.
+    pub canister_id_ranges: Vec<CanisterIdRange>,
+    /// The subnet currently hosting the canister id ranges.
+    pub source_subnet: PrincipalId,
+    /// The subnet the canister id ranges are migrated to.
+    pub destination_subnet: PrincipalId,
+}
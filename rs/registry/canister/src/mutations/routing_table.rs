@@ -6,9 +6,10 @@ use ic_base_types::SubnetId;
 use ic_protobuf::registry::routing_table::v1 as pb;
 use ic_registry_keys::{make_canister_migrations_record_key, make_routing_table_record_key};
 use ic_registry_routing_table::{
-    routing_table_insert_subnet, CanisterIdRange, CanisterMigrations, RoutingTable,
+    routing_table_insert_subnet, CanisterIdRange, CanisterIdRanges, CanisterMigrations,
+    RoutingTable,
 };
-use ic_registry_transport::pb::v1::{RegistryMutation, RegistryValue};
+use ic_registry_transport::pb::v1::{registry_mutation, RegistryMutation, RegistryValue};
 use prost::Message;
 
 fn routing_table_into_registry_mutation(
@@ -25,8 +26,6 @@ fn routing_table_into_registry_mutation(
     }
 }
 
-#[allow(dead_code)]
-// The function will be used when canister migration mutation is fully supported in the future.
 fn canister_migrations_into_registry_mutation(
     canister_migrations: CanisterMigrations,
     mutation_type: i32,
@@ -43,11 +42,7 @@ fn canister_migrations_into_registry_mutation(
 
 impl Registry {
     /// Decodes the routing table at the specified version.
-    fn modify_routing_table(
-        &self,
-        version: u64,
-        f: impl FnOnce(&mut RoutingTable),
-    ) -> RegistryMutation {
+    pub(crate) fn get_routing_table_or_panic(&self, version: u64) -> RoutingTable {
         let RegistryValue {
             value: routing_table_vec,
             version: _,
@@ -55,14 +50,52 @@ impl Registry {
         } = self
             .get(make_routing_table_record_key().as_bytes(), version)
             .unwrap();
-        let mut routing_table = RoutingTable::try_from(decode_registry_value::<pb::RoutingTable>(
+        RoutingTable::try_from(decode_registry_value::<pb::RoutingTable>(
             routing_table_vec.clone(),
         ))
-        .expect("failed to decode the routing table from protobuf");
+        .expect("failed to decode the routing table from protobuf")
+    }
+
+    /// Decodes the routing table at the specified version and applies `f` to
+    /// it.
+    fn modify_routing_table(
+        &self,
+        version: u64,
+        f: impl FnOnce(&mut RoutingTable),
+    ) -> RegistryMutation {
+        let mut routing_table = self.get_routing_table_or_panic(version);
         f(&mut routing_table);
         routing_table_into_registry_mutation(routing_table, 1)
     }
 
+    /// Decodes the canister migrations at the specified version. Returns empty
+    /// canister migrations if there is no record in the registry.
+    pub(crate) fn get_canister_migrations(&self, version: u64) -> CanisterMigrations {
+        match self.get(make_canister_migrations_record_key().as_bytes(), version) {
+            Some(RegistryValue {
+                value: canister_migrations_vec,
+                version: _,
+                deletion_marker: _,
+            }) => CanisterMigrations::try_from(decode_registry_value::<pb::CanisterMigrations>(
+                canister_migrations_vec.clone(),
+            ))
+            .expect("failed to decode the canister migrations from protobuf"),
+            None => CanisterMigrations::new(),
+        }
+    }
+
+    /// Makes a registry mutation that replaces the canister migrations with
+    /// the given ones, creating the record if it does not exist yet.
+    pub(crate) fn canister_migrations_mutation(
+        &self,
+        canister_migrations: CanisterMigrations,
+    ) -> RegistryMutation {
+        canister_migrations_into_registry_mutation(
+            canister_migrations,
+            registry_mutation::Type::Upsert as i32,
+        )
+    }
+
     /// Handle adding a subnet to the routing table.
     pub fn add_subnet_to_routing_table(
         &self,
@@ -98,4 +131,20 @@ impl Registry {
             routing_table.optimize();
         })
     }
+
+    /// Makes a registry mutation that remaps all of the specified canister id
+    /// ranges to another subnet.
+    pub(crate) fn reroute_canister_ranges_mutation(
+        &self,
+        version: u64,
+        canister_id_ranges: &CanisterIdRanges,
+        destination: SubnetId,
+    ) -> RegistryMutation {
+        self.modify_routing_table(version, |routing_table| {
+            for range in canister_id_ranges.iter() {
+                routing_table.assign_range(*range, destination);
+            }
+            routing_table.optimize();
+        })
+    }
 }
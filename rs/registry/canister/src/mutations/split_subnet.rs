@@ -0,0 +1,71 @@
+use crate::registry::Registry;
+use candid::CandidType;
+use ic_base_types::{PrincipalId, SubnetId};
+use ic_registry_keys::make_subnet_record_key;
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+impl Registry {
+    /// Validates the payload and, in a single registry version, routes the
+    /// canister id ranges to the destination subnet and adds them to the
+    /// canister migrations, with a migration trace from the source to the
+    /// destination subnet.
+    ///
+    /// This is used when splitting a subnet: the canisters in the ranges are
+    /// split off from a checkpoint of the (halted) source subnet into a
+    /// checkpoint of the destination subnet, which is then started from it.
+    /// The canister migrations make both subnets reroute messages still in
+    /// flight to or from the moved canisters; they are removed by
+    /// `complete_canister_migration` once these have been delivered.
+    pub fn split_subnet(&mut self, payload: SplitSubnetPayload) -> Result<(), String> {
+        let ranges = CanisterIdRanges::try_from(payload.canister_id_ranges)
+            .map_err(|e| format!("invalid canister id ranges: {:?}", e))?;
+        let source = SubnetId::from(payload.source_subnet);
+        let destination = SubnetId::from(payload.destination_subnet);
+
+        if source == destination {
+            return Err(format!(
+                "source and destination subnet are the same: {}",
+                source
+            ));
+        }
+
+        let version = self.latest_version();
+
+        self.get(&make_subnet_record_key(destination).into_bytes(), version)
+            .ok_or_else(|| format!("destination {} is not a known subnet", destination))?;
+
+        let source_ranges = self.get_routing_table_or_panic(version).ranges(source);
+        if let Some(range) = ranges.iter().find(|r| !source_ranges.contains_range(r)) {
+            return Err(format!(
+                "canister id range {:?} is not hosted by source subnet {}",
+                range, source
+            ));
+        }
+
+        let mut canister_migrations = self.get_canister_migrations(version);
+        canister_migrations
+            .insert_ranges(ranges.clone(), source, destination)
+            .map_err(|e| format!("failed to split subnet: {:?}", e))?;
+
+        self.maybe_apply_mutation_internal(vec![
+            self.reroute_canister_ranges_mutation(version, &ranges, destination),
+            self.canister_migrations_mutation(canister_migrations),
+        ]);
+
+        Ok(())
+    }
+}
+
+/// The argument for the `split_subnet` update call.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct SplitSubnetPayload {
+    /// The canister id ranges to split off. The ranges must be sorted and
+    /// disjoint, and must be hosted by the source subnet.
+    pub canister_id_ranges: Vec<CanisterIdRange>,
+    /// The subnet being split.
+    pub source_subnet: PrincipalId,
+    /// The subnet taking over the canister id ranges.
+    pub destination_subnet: PrincipalId,
+}
@@ -15,7 +15,7 @@ use crate::{
 use ic_certified_map::RbTree;
 use prost::Message;
 use std::cmp::max;
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt;
 
 #[cfg(target_arch = "wasm32")]
@@ -27,6 +27,10 @@ use dfn_core::println;
 /// so that we're able to call pop_front().
 pub type RegistryMap = BTreeMap<Vec<u8>, VecDeque<RegistryValue>>;
 pub type Version = u64;
+
+/// The interval, in versions, at which the certified snapshot of the registry
+/// is taken.
+pub const SNAPSHOT_INTERVAL: Version = 1000;
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default)]
 pub struct EncodedVersion([u8; 8]);
 
@@ -85,6 +89,21 @@ pub struct Registry {
     /// RegistryAtomicMutateRequest.  We keep the serialized version around to
     /// make sure that hash trees stay the same even if protobuf schema evolves.
     pub(crate) changelog: RbTree<EncodedVersion, Vec<u8>>,
+
+    /// The version of the registry `snapshot` was taken at: the latest
+    /// multiple of `SNAPSHOT_INTERVAL` not greater than `version`.
+    snapshot_version: Version,
+
+    /// The latest value (or tombstone) of every key at `snapshot_version`.
+    ///
+    /// Each entry contains a blob which is a serialized RegistryValue. This
+    /// allows clients to bootstrap from a certified view of the registry at
+    /// `snapshot_version` instead of replaying the whole changelog.
+    pub(crate) snapshot: RbTree<Vec<u8>, Vec<u8>>,
+
+    /// The keys mutated after `snapshot_version`, i.e. the entries of
+    /// `snapshot` to update when the next snapshot is taken.
+    keys_mutated_since_snapshot: BTreeSet<Vec<u8>>,
 }
 
 impl Registry {
@@ -157,6 +176,33 @@ impl Registry {
             .count()
     }
 
+    /// Returns the last key of the snapshot page starting at `start_key`
+    /// (inclusive), such that the page contains at most `max_keys` entries
+    /// and its values fit into the specified byte limit. Always includes at
+    /// least one entry if any key is greater than or equal to `start_key`.
+    ///
+    /// Returns None if there is no such key.
+    pub fn last_fitting_snapshot_key(
+        &self,
+        start_key: &[u8],
+        max_keys: usize,
+        max_bytes: usize,
+    ) -> Option<&[u8]> {
+        let mut size = 0;
+        self.store
+            .range(start_key.to_vec()..)
+            // Skip the keys created after the snapshot was taken.
+            .filter_map(|(key, _)| Some((key, self.snapshot.get(key)?)))
+            .take(max_keys.max(1))
+            .enumerate()
+            .take_while(|(i, (key, value))| {
+                size += key.len() + value.len();
+                *i == 0 || size < max_bytes
+            })
+            .last()
+            .map(|(_, (key, _))| key.as_slice())
+    }
+
     /// Returns the last RegistryValue, if any, for the given key.
     ///
     /// As we keep track of deletions in the registry, this value
@@ -215,12 +261,55 @@ impl Registry {
         self.changelog.insert(version.into(), bytes);
 
         for mutation in req.mutations {
+            self.keys_mutated_since_snapshot
+                .insert(mutation.key.clone());
             (*self.store.entry(mutation.key).or_default()).push_back(RegistryValue {
                 version,
                 value: mutation.value,
                 deletion_marker: mutation.mutation_type == Type::Delete as i32,
             });
         }
+
+        if version % SNAPSHOT_INTERVAL == 0 {
+            self.take_snapshot(version);
+        }
+    }
+
+    /// Updates the snapshot to `version`, the current version of the registry,
+    /// by only updating the entries of the keys mutated since the previous
+    /// snapshot.
+    fn take_snapshot(&mut self, version: Version) {
+        for key in std::mem::take(&mut self.keys_mutated_since_snapshot) {
+            let value = self
+                .store
+                .get(&key)
+                .and_then(VecDeque::back)
+                .expect("mutated key must have a value");
+            self.snapshot.insert(key, pb_encode(value));
+        }
+        self.snapshot_version = version;
+    }
+
+    /// Rebuilds the snapshot at the latest multiple of `SNAPSHOT_INTERVAL` from
+    /// the store, in a single pass over all values.
+    fn rebuild_snapshot(&mut self) {
+        let snapshot_version = self.version - self.version % SNAPSHOT_INTERVAL;
+        for (key, values) in self.store.iter() {
+            if values
+                .back()
+                .map_or(false, |value| value.version > snapshot_version)
+            {
+                self.keys_mutated_since_snapshot.insert(key.clone());
+            }
+            if let Some(value) = values
+                .iter()
+                .rev()
+                .find(|value| value.version <= snapshot_version)
+            {
+                self.snapshot.insert(key.clone(), pb_encode(value));
+            }
+        }
+        self.snapshot_version = snapshot_version;
     }
 
     /// Applies the given mutations, without any check corresponding
@@ -332,6 +421,14 @@ impl Registry {
         &self.changelog
     }
 
+    pub fn snapshot(&self) -> &RbTree<Vec<u8>, Vec<u8>> {
+        &self.snapshot
+    }
+
+    pub fn snapshot_version(&self) -> Version {
+        self.snapshot_version
+    }
+
     /// Sets the content of the registry from its serialized representation.
     ///
     /// Panics if not currently empty: this is only meant to be used in
@@ -346,6 +443,8 @@ impl Registry {
     pub fn from_serializable_form(&mut self, stable_repr: RegistryStableStorage) {
         assert!(self.store.is_empty());
         assert!(self.changelog.is_empty());
+        assert!(self.snapshot.is_empty());
+        assert!(self.keys_mutated_since_snapshot.is_empty());
         assert_eq!(self.version, 0);
 
         let repr_version = ReprVersion::from_i32(stable_repr.version).unwrap_or_else(|| {
@@ -415,6 +514,7 @@ impl Registry {
 
                     self.store.insert(delta.key, VecDeque::from(delta.values));
                 }
+                self.rebuild_snapshot();
                 // We iterated over keys in ascending order, so the mutations
                 // must also be sorted by key, resulting in canonical encoding.
                 self.changelog = mutations_by_version
@@ -731,6 +831,98 @@ mod tests {
         assert_eq!(registry.count_fitting_deltas(4, 2000000), 0);
     }
 
+    #[test]
+    fn test_snapshot() {
+        let mut registry = Registry::new();
+        let apply = |registry: &mut Registry, mutation| {
+            assert_empty!(apply_mutations_skip_invariant_checks(
+                registry,
+                vec![mutation]
+            ));
+        };
+        let value_of = |registry: &Registry, key: &[u8]| {
+            registry
+                .snapshot()
+                .get(key)
+                .map(|bytes| RegistryValue::decode(&bytes[..]).unwrap())
+        };
+
+        let (key1, key2, key3, key4) = (vec![1; 10], vec![2; 10], vec![3; 10], vec![4; 10]);
+        apply(&mut registry, upsert(&key1, &[1; 10]));
+        apply(&mut registry, upsert(&key2, &[2; 10]));
+        // No snapshot is taken before the first multiple of the interval.
+        assert_eq!(registry.snapshot_version(), 0);
+        assert!(registry.snapshot().is_empty());
+
+        for i in 3..SNAPSHOT_INTERVAL {
+            apply(&mut registry, upsert(&key3, &i.to_be_bytes()));
+        }
+        apply(&mut registry, delete(&key2));
+        assert_eq!(registry.snapshot_version(), SNAPSHOT_INTERVAL);
+        assert_eq!(
+            value_of(&registry, &key1),
+            Some(RegistryValue {
+                version: 1,
+                value: vec![1; 10],
+                deletion_marker: false,
+            })
+        );
+        let value = value_of(&registry, &key2).unwrap();
+        assert_eq!(value.version, SNAPSHOT_INTERVAL);
+        assert!(value.deletion_marker);
+        assert_eq!(
+            value_of(&registry, &key3),
+            Some(RegistryValue {
+                version: SNAPSHOT_INTERVAL - 1,
+                value: (SNAPSHOT_INTERVAL - 1).to_be_bytes().to_vec(),
+                deletion_marker: false,
+            })
+        );
+
+        // Mutations after the snapshot version are not reflected in the
+        // snapshot until the next one is taken.
+        apply(&mut registry, upsert(&key1, &[5; 10]));
+        apply(&mut registry, upsert(&key4, &[4; 10]));
+        assert_eq!(registry.snapshot_version(), SNAPSHOT_INTERVAL);
+        assert_eq!(value_of(&registry, &key1).unwrap().version, 1);
+        assert_eq!(value_of(&registry, &key4), None);
+
+        let last_key = |registry: &Registry, start_key: &[u8], max_keys, max_bytes| {
+            registry
+                .last_fitting_snapshot_key(start_key, max_keys, max_bytes)
+                .map(<[u8]>::to_vec)
+        };
+        assert_eq!(last_key(&registry, &[], 10, 2000000), Some(key3.clone()));
+        assert_eq!(last_key(&registry, &[], 2, 2000000), Some(key2.clone()));
+        // The first entry is always included, regardless of its size.
+        assert_eq!(last_key(&registry, &[], 10, 1), Some(key1.clone()));
+        assert_eq!(last_key(&registry, &key2, 10, 2000000), Some(key3.clone()));
+        assert_eq!(last_key(&registry, &[2, 2], 10, 2000000), Some(key3));
+        // Keys created after the snapshot version are not part of it.
+        assert_eq!(last_key(&registry, &[4], 10, 2000000), None);
+
+        serialize_then_deserialize(registry.clone());
+
+        while registry.latest_version() < 2 * SNAPSHOT_INTERVAL {
+            apply(&mut registry, upsert(&key2, &[2; 10]));
+        }
+        assert_eq!(registry.snapshot_version(), 2 * SNAPSHOT_INTERVAL);
+        assert_eq!(
+            value_of(&registry, &key1).unwrap().version,
+            SNAPSHOT_INTERVAL + 1
+        );
+        assert_eq!(
+            value_of(&registry, &key2).unwrap().version,
+            2 * SNAPSHOT_INTERVAL
+        );
+        assert_eq!(
+            value_of(&registry, &key4).unwrap().version,
+            SNAPSHOT_INTERVAL + 2
+        );
+
+        serialize_then_deserialize(registry);
+    }
+
     #[test]
     fn test_upsert() {
         let mut registry = Registry::new();
@@ -19,14 +19,17 @@ use ic_nns_test_utils::{
 };
 use ic_nns_test_utils_macros::parameterized_upgrades;
 
-use ic_registry_common::certification::decode_hash_tree;
+use ic_registry_common::certification::{
+    decode_hash_tree, decode_snapshot_hash_tree, SnapshotPage,
+};
 
 use ic_registry_transport::{
-    insert,
+    delete, insert,
     pb::v1::{
         registry_error::Code, CertifiedResponse, RegistryAtomicMutateRequest,
-        RegistryAtomicMutateResponse, RegistryError, RegistryGetChangesSinceRequest,
-        RegistryGetLatestVersionResponse, RegistryGetValueRequest, RegistryGetValueResponse,
+        RegistryAtomicMutateResponse, RegistryError, RegistryGetCertifiedSnapshotRequest,
+        RegistryGetChangesSinceRequest, RegistryGetLatestVersionResponse, RegistryGetValueRequest,
+        RegistryGetValueResponse,
     },
     precondition, update, upsert,
 };
@@ -34,6 +37,7 @@ use ic_types::RegistryVersion;
 use registry_canister::{
     init::{RegistryCanisterInitPayload, RegistryCanisterInitPayloadBuilder},
     proto_on_wire::protobuf,
+    registry::SNAPSHOT_INTERVAL,
 };
 use std::convert::TryInto;
 
@@ -82,6 +86,29 @@ async fn query_certified_changes_since(
     .expect("failed to decode registry deltas")
 }
 
+async fn query_certified_snapshot(canister: &Canister<'_>, start_key: &[u8]) -> SnapshotPage {
+    let certified_response: CertifiedResponse = canister
+        .query_(
+            "get_certified_snapshot",
+            protobuf,
+            RegistryGetCertifiedSnapshotRequest {
+                start_key: start_key.to_vec(),
+            },
+        )
+        .await
+        .expect("failed to query certified snapshot");
+
+    decode_snapshot_hash_tree(
+        start_key,
+        certified_response
+            .hash_tree
+            .expect("no hash tree in a certified response")
+            .try_into()
+            .expect("failed to decode hash tree from protobuf"),
+    )
+    .expect("failed to decode registry snapshot")
+}
+
 fn get_value_request(key: impl AsRef<[u8]>, version: Option<u64>) -> RegistryGetValueRequest {
     RegistryGetValueRequest {
         version,
@@ -331,6 +358,80 @@ async fn get_changes_since_certified(runtime: &Runtime, upgrade_scenario: Upgrad
     assert!(deltas.is_empty());
 }
 
+#[parameterized_upgrades]
+async fn get_snapshot_certified(runtime: &Runtime, upgrade_scenario: UpgradeTestingScenario) {
+    // Initializes the registry at version `SNAPSHOT_INTERVAL - 1`, at which no
+    // snapshot has been taken yet.
+    let mut builder = RegistryCanisterInitPayloadBuilder::new();
+    builder.push_init_mutate_request(invariant_compliant_mutation_as_atomic_req());
+    builder.push_init_mutate_request(RegistryAtomicMutateRequest {
+        mutations: vec![insert("key2", "value2")],
+        preconditions: vec![],
+    });
+    for i in 3..SNAPSHOT_INTERVAL {
+        builder.push_init_mutate_request(RegistryAtomicMutateRequest {
+            mutations: vec![upsert("key1", format!("value{}", i))],
+            preconditions: vec![],
+        });
+    }
+    let mut canister = install_registry_canister(runtime, builder.build()).await;
+
+    let page = query_certified_snapshot(&canister, &[]).await;
+    assert_eq!(page.version, RegistryVersion::from(0));
+    assert_eq!(page.next_start_key, None);
+    assert!(page.records.is_empty());
+
+    // Sets up a universal canister in lieu of the governance canister so it can
+    // impersonate it.
+    let fake_governance_canister = set_up_universal_canister(runtime).await;
+    assert_eq!(
+        fake_governance_canister.canister_id(),
+        GOVERNANCE_CANISTER_ID
+    );
+
+    // The snapshot is taken at version `SNAPSHOT_INTERVAL`, and not updated by
+    // the following version.
+    for mutations in vec![
+        vec![delete("key2")],
+        vec![upsert("key1", "value1'"), insert("key3", "value3")],
+    ] {
+        let mutation_request = RegistryAtomicMutateRequest {
+            mutations,
+            preconditions: vec![],
+        };
+        assert!(
+            forward_call_via_universal_canister(
+                &fake_governance_canister,
+                &canister,
+                "atomic_mutate",
+                encode_or_panic(&mutation_request)
+            )
+            .await
+        );
+    }
+
+    maybe_upgrade_to_self(&mut canister, upgrade_scenario).await;
+
+    let page = query_certified_snapshot(&canister, &[]).await;
+    assert_eq!(page.version, RegistryVersion::from(SNAPSHOT_INTERVAL));
+    assert_eq!(page.next_start_key, None);
+    assert!(page.records.contains(&RegistryTransportRecord {
+        key: "key1".to_string(),
+        value: Some(format!("value{}", SNAPSHOT_INTERVAL - 1).into_bytes()),
+        version: RegistryVersion::from(SNAPSHOT_INTERVAL - 1),
+    }));
+    // Deleted keys and keys added after the snapshot are not part of it, and
+    // every other key was set at version 1.
+    assert!(page
+        .records
+        .iter()
+        .all(|r| r.key == "key1" || r.version == RegistryVersion::from(1)));
+
+    let page = query_certified_snapshot(&canister, b"key1").await;
+    assert_eq!(page.version, RegistryVersion::from(SNAPSHOT_INTERVAL));
+    assert!(page.records.iter().all(|r| r.key.as_str() > "key1"));
+}
+
 #[test]
 fn test_does_not_return_more_than_1000_certified_deltas() {
     fn count_deltas(tree: &LabeledTree<Vec<u8>>) -> usize {
@@ -0,0 +1,253 @@
+use candid::Encode;
+use ic_nns_test_utils::{
+    itest_helpers::{
+        local_test_on_nns_subnet, set_up_registry_canister, set_up_universal_canister,
+        try_call_via_universal_canister,
+    },
+    registry::{get_value, prepare_registry, routing_table_mutation},
+};
+use ic_protobuf::registry::routing_table::v1 as pb;
+use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations, RoutingTable};
+use ic_registry_transport::pb::v1::RegistryAtomicMutateRequest;
+use ic_test_utilities::types::ids::subnet_test_id;
+use ic_types::CanisterId;
+use registry_canister::{
+    init::RegistryCanisterInitPayloadBuilder,
+    mutations::{
+        complete_canister_migration::CompleteCanisterMigrationPayload,
+        prepare_canister_migration::PrepareCanisterMigrationPayload,
+        reroute_canister_range::RerouteCanisterRangePayload,
+    },
+};
+use std::convert::TryFrom;
+
+fn range(start: u64, end: u64) -> CanisterIdRange {
+    CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    }
+}
+
+async fn get_routing_table(canister: &canister_test::Canister<'_>) -> RoutingTable {
+    let pb_routing_table: pb::RoutingTable = get_value(canister, b"routing_table").await;
+    RoutingTable::try_from(pb_routing_table).expect("failed to decode routing table")
+}
+
+async fn get_canister_migrations(canister: &canister_test::Canister<'_>) -> CanisterMigrations {
+    let pb_canister_migrations: pb::CanisterMigrations =
+        get_value(canister, b"canister_migrations").await;
+    CanisterMigrations::try_from(pb_canister_migrations)
+        .expect("failed to decode canister migrations")
+}
+
+fn check_error_message<T: std::fmt::Debug>(result: Result<T, String>, expected_substring: &str) {
+    match result {
+        Ok(value) => panic!(
+            "expected the call to fail with message '{}', got Ok({:?})",
+            expected_substring, value
+        ),
+        Err(e) => assert!(
+            e.contains(expected_substring),
+            "expected the call to fail with message '{}', got:  {}",
+            expected_substring,
+            e
+        ),
+    }
+}
+
+#[test]
+fn test_canister_migration() {
+    local_test_on_nns_subnet(|runtime| {
+        async move {
+            let (subnet_1_mutation, subnet_id_1, _, _) = prepare_registry(
+                /* num_nodes_in_subnet = */ 4, /* num_anassigned_nodes = */ 0,
+            );
+            let nns_subnet = subnet_test_id(1);
+
+            let rt_mutation = {
+                let mut rt = RoutingTable::new();
+                rt.insert(range(0, 255), nns_subnet)
+                    .expect("failed to update the routing table");
+                rt.insert(range(256, 511), subnet_id_1)
+                    .expect("failed to update the routing table");
+
+                RegistryAtomicMutateRequest {
+                    mutations: vec![routing_table_mutation(&rt)],
+                    preconditions: vec![],
+                }
+            };
+
+            let registry = set_up_registry_canister(
+                &runtime,
+                RegistryCanisterInitPayloadBuilder::new()
+                    .push_init_mutate_request(subnet_1_mutation)
+                    .push_init_mutate_request(rt_mutation)
+                    .build(),
+            )
+            .await;
+
+            let governance_fake = set_up_universal_canister(&runtime).await;
+            assert_eq!(
+                governance_fake.canister_id(),
+                ic_nns_constants::GOVERNANCE_CANISTER_ID
+            );
+
+            // Invalid request: range not hosted by the source subnet.
+            check_error_message(
+                try_call_via_universal_canister(
+                    &governance_fake,
+                    &registry,
+                    "prepare_canister_migration",
+                    Encode!(&PrepareCanisterMigrationPayload {
+                        canister_id_ranges: vec![range(250, 260)],
+                        source_subnet: nns_subnet.get(),
+                        destination_subnet: subnet_id_1.get(),
+                    })
+                    .unwrap(),
+                )
+                .await,
+                "is not hosted by source subnet",
+            );
+
+            // Prepare the migration of [10, 11] from the NNS subnet to subnet 1.
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "prepare_canister_migration",
+                Encode!(&PrepareCanisterMigrationPayload {
+                    canister_id_ranges: vec![range(10, 11)],
+                    source_subnet: nns_subnet.get(),
+                    destination_subnet: subnet_id_1.get(),
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+            let canister_migrations = get_canister_migrations(&registry).await;
+            assert_eq!(
+                canister_migrations.lookup(CanisterId::from(10)),
+                Some(vec![nns_subnet, subnet_id_1])
+            );
+            assert_eq!(canister_migrations.lookup(CanisterId::from(12)), None);
+
+            // Invalid request: the range is already being migrated.
+            check_error_message(
+                try_call_via_universal_canister(
+                    &governance_fake,
+                    &registry,
+                    "prepare_canister_migration",
+                    Encode!(&PrepareCanisterMigrationPayload {
+                        canister_id_ranges: vec![range(10, 11)],
+                        source_subnet: nns_subnet.get(),
+                        destination_subnet: subnet_id_1.get(),
+                    })
+                    .unwrap(),
+                )
+                .await,
+                "already being migrated",
+            );
+
+            // Invalid request: wrong migration trace.
+            check_error_message(
+                try_call_via_universal_canister(
+                    &governance_fake,
+                    &registry,
+                    "complete_canister_migration",
+                    Encode!(&CompleteCanisterMigrationPayload {
+                        canister_id_ranges: vec![range(10, 11)],
+                        migration_trace: vec![subnet_id_1.get(), nns_subnet.get()],
+                    })
+                    .unwrap(),
+                )
+                .await,
+                "has migration trace",
+            );
+
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "reroute_canister_range",
+                Encode!(&RerouteCanisterRangePayload {
+                    range_start_inclusive: CanisterId::from(10).into(),
+                    range_end_inclusive: CanisterId::from(11).into(),
+                    destination_subnet: subnet_id_1.get(),
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "complete_canister_migration",
+                Encode!(&CompleteCanisterMigrationPayload {
+                    canister_id_ranges: vec![range(10, 11)],
+                    migration_trace: vec![nns_subnet.get(), subnet_id_1.get()],
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+            assert!(get_canister_migrations(&registry).await.is_empty());
+            assert_eq!(
+                get_routing_table(&registry)
+                    .await
+                    .route(CanisterId::from(10).into()),
+                Some(subnet_id_1)
+            );
+
+            // A prepared migration can be aborted by completing it without
+            // rerouting the range.
+            let payload = PrepareCanisterMigrationPayload {
+                canister_id_ranges: vec![range(20, 29)],
+                source_subnet: nns_subnet.get(),
+                destination_subnet: subnet_id_1.get(),
+            };
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "prepare_canister_migration",
+                Encode!(&payload).unwrap(),
+            )
+            .await
+            .unwrap();
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "complete_canister_migration",
+                Encode!(&CompleteCanisterMigrationPayload {
+                    canister_id_ranges: vec![range(20, 29)],
+                    migration_trace: vec![nns_subnet.get(), subnet_id_1.get()],
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+            assert!(get_canister_migrations(&registry).await.is_empty());
+            assert_eq!(
+                get_routing_table(&registry)
+                    .await
+                    .route(CanisterId::from(20).into()),
+                Some(nns_subnet)
+            );
+
+            // Only governance may prepare a migration.
+            check_error_message(
+                registry
+                    .update_(
+                        "prepare_canister_migration",
+                        dfn_candid::candid_one,
+                        payload,
+                    )
+                    .await as Result<(), String>,
+                "not authorized",
+            );
+
+            Ok(())
+        }
+    });
+}
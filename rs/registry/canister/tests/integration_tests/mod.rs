@@ -2,6 +2,7 @@ mod add_node;
 mod add_node_operator;
 mod add_nodes_to_subnet;
 mod add_or_remove_data_centers;
+mod canister_migration;
 mod clear_provisional_whitelist;
 mod create_subnet;
 mod delete_subnet;
@@ -11,6 +12,7 @@ mod remove_node_operators;
 mod remove_nodes;
 mod remove_nodes_from_subnet;
 mod reroute_canister_range;
+mod split_subnet;
 mod update_node_directly;
 mod update_node_operator_config;
 mod update_node_operator_config_directly;
@@ -11,11 +11,11 @@ use ic_nns_test_utils::{
 use ic_protobuf::registry::{
     node::v1::NodeRecord,
     node_operator::v1::NodeOperatorRecord,
-    subnet::v1::{SubnetListRecord, SubnetRecord},
+    subnet::v1::{NodeUpgradeStatus, NodeUpgradeStatusRecord, SubnetListRecord, SubnetRecord},
 };
 use ic_registry_keys::{
-    make_node_operator_record_key, make_node_record_key, make_subnet_list_record_key,
-    make_subnet_record_key,
+    make_node_operator_record_key, make_node_record_key, make_node_upgrade_status_record_key,
+    make_subnet_list_record_key, make_subnet_record_key,
 };
 use ic_registry_transport::pb::v1::{
     registry_mutation, RegistryAtomicMutateRequest, RegistryMutation,
@@ -63,6 +63,12 @@ fn node_is_removed_on_receiving_the_request() {
         let node_record =
             get_value::<NodeRecord>(&registry, make_node_record_key(node_id).as_bytes()).await;
         assert_eq!(node_record, test_node_record);
+        let status_record = get_value::<NodeUpgradeStatusRecord>(
+            &registry,
+            make_node_upgrade_status_record_key(node_id).as_bytes(),
+        )
+        .await;
+        assert_ne!(status_record, NodeUpgradeStatusRecord::default());
 
         let response: Result<(), String> = registry
             .update_from_sender(
@@ -79,6 +85,14 @@ fn node_is_removed_on_receiving_the_request() {
             get_value::<NodeRecord>(&registry, make_node_record_key(node_id).as_bytes()).await;
         assert_eq!(node_record, NodeRecord::default());
 
+        // Ensure the upgrade status record of the node was removed with it
+        let status_record = get_value::<NodeUpgradeStatusRecord>(
+            &registry,
+            make_node_upgrade_status_record_key(node_id).as_bytes(),
+        )
+        .await;
+        assert_eq!(status_record, NodeUpgradeStatusRecord::default());
+
         // Ensure the node operator's allowance is incremented correctly
         let node_operator_record = get_value::<NodeOperatorRecord>(
             &registry,
@@ -240,6 +254,18 @@ fn init_mutation(node_record: &NodeRecord) -> (NodeId, RegistryAtomicMutateReque
                     key: make_node_record_key(node_id).as_bytes().to_vec(),
                     value: encode_or_panic(node_record),
                 },
+                // Insert the upgrade status reported by the Node
+                RegistryMutation {
+                    mutation_type: registry_mutation::Type::Insert as i32,
+                    key: make_node_upgrade_status_record_key(node_id)
+                        .as_bytes()
+                        .to_vec(),
+                    value: encode_or_panic(&NodeUpgradeStatusRecord {
+                        replica_version_id: "version_42".to_string(),
+                        status: NodeUpgradeStatus::Healthy as i32,
+                        finalization_rate_per_minute: 60,
+                    }),
+                },
                 // Insert the Node's NO
                 RegistryMutation {
                     mutation_type: registry_mutation::Type::Insert as i32,
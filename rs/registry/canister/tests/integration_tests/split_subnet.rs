@@ -0,0 +1,153 @@
+use candid::Encode;
+use ic_nns_test_utils::{
+    itest_helpers::{
+        local_test_on_nns_subnet, set_up_registry_canister, set_up_universal_canister,
+        try_call_via_universal_canister,
+    },
+    registry::{get_value, prepare_registry, routing_table_mutation},
+};
+use ic_protobuf::registry::routing_table::v1 as pb;
+use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations, RoutingTable};
+use ic_registry_transport::pb::v1::RegistryAtomicMutateRequest;
+use ic_test_utilities::types::ids::subnet_test_id;
+use ic_types::CanisterId;
+use registry_canister::{
+    init::RegistryCanisterInitPayloadBuilder,
+    mutations::{
+        complete_canister_migration::CompleteCanisterMigrationPayload,
+        split_subnet::SplitSubnetPayload,
+    },
+};
+use std::convert::TryFrom;
+
+fn range(start: u64, end: u64) -> CanisterIdRange {
+    CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    }
+}
+
+async fn get_routing_table(canister: &canister_test::Canister<'_>) -> RoutingTable {
+    let pb_routing_table: pb::RoutingTable = get_value(canister, b"routing_table").await;
+    RoutingTable::try_from(pb_routing_table).expect("failed to decode routing table")
+}
+
+async fn get_canister_migrations(canister: &canister_test::Canister<'_>) -> CanisterMigrations {
+    let pb_canister_migrations: pb::CanisterMigrations =
+        get_value(canister, b"canister_migrations").await;
+    CanisterMigrations::try_from(pb_canister_migrations)
+        .expect("failed to decode canister migrations")
+}
+
+#[test]
+fn test_split_subnet() {
+    local_test_on_nns_subnet(|runtime| {
+        async move {
+            let (subnet_1_mutation, subnet_id_1, _, _) = prepare_registry(
+                /* num_nodes_in_subnet = */ 4, /* num_anassigned_nodes = */ 0,
+            );
+            let source_subnet = subnet_test_id(1);
+
+            let rt_mutation = {
+                let mut rt = RoutingTable::new();
+                rt.insert(range(0, 255), source_subnet)
+                    .expect("failed to update the routing table");
+                rt.insert(range(256, 511), subnet_id_1)
+                    .expect("failed to update the routing table");
+
+                RegistryAtomicMutateRequest {
+                    mutations: vec![routing_table_mutation(&rt)],
+                    preconditions: vec![],
+                }
+            };
+
+            let registry = set_up_registry_canister(
+                &runtime,
+                RegistryCanisterInitPayloadBuilder::new()
+                    .push_init_mutate_request(subnet_1_mutation)
+                    .push_init_mutate_request(rt_mutation)
+                    .build(),
+            )
+            .await;
+
+            let governance_fake = set_up_universal_canister(&runtime).await;
+            assert_eq!(
+                governance_fake.canister_id(),
+                ic_nns_constants::GOVERNANCE_CANISTER_ID
+            );
+
+            // Invalid request: range not hosted by the source subnet.
+            let result = try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "split_subnet",
+                Encode!(&SplitSubnetPayload {
+                    canister_id_ranges: vec![range(128, 300)],
+                    source_subnet: source_subnet.get(),
+                    destination_subnet: subnet_id_1.get(),
+                })
+                .unwrap(),
+            )
+            .await;
+            assert!(result
+                .unwrap_err()
+                .contains("is not hosted by source subnet"));
+
+            // Split [128, 255] off the source subnet into subnet 1.
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "split_subnet",
+                Encode!(&SplitSubnetPayload {
+                    canister_id_ranges: vec![range(128, 255)],
+                    source_subnet: source_subnet.get(),
+                    destination_subnet: subnet_id_1.get(),
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+            // The range is rerouted and being migrated in the same version.
+            let routing_table = get_routing_table(&registry).await;
+            assert_eq!(
+                routing_table.route(CanisterId::from(127).into()),
+                Some(source_subnet)
+            );
+            assert_eq!(
+                routing_table.route(CanisterId::from(128).into()),
+                Some(subnet_id_1)
+            );
+            assert_eq!(
+                get_canister_migrations(&registry)
+                    .await
+                    .lookup(CanisterId::from(200)),
+                Some(vec![source_subnet, subnet_id_1])
+            );
+
+            // Completing the migration leaves the split routing table in place.
+            try_call_via_universal_canister(
+                &governance_fake,
+                &registry,
+                "complete_canister_migration",
+                Encode!(&CompleteCanisterMigrationPayload {
+                    canister_id_ranges: vec![range(128, 255)],
+                    migration_trace: vec![source_subnet.get(), subnet_id_1.get()],
+                })
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+            assert!(get_canister_migrations(&registry).await.is_empty());
+            assert_eq!(
+                get_routing_table(&registry)
+                    .await
+                    .route(CanisterId::from(200).into()),
+                Some(subnet_id_1)
+            );
+
+            Ok(())
+        }
+    });
+}
@@ -80,6 +80,7 @@ fn test_the_anonymous_user_cannot_update_a_subnets_configuration() {
             max_number_of_canisters: Some(10),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            rollout_policy: None,
         };
 
         // The anonymous end-user tries to update a subnet's configuration, bypassing
@@ -145,6 +146,7 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         // An attacker got a canister that is trying to pass for the governance
@@ -203,6 +205,7 @@ fn test_a_canister_other_than_the_governance_canister_cannot_update_a_subnets_co
             max_number_of_canisters: Some(100),
             ssh_readonly_access: None,
             ssh_backup_access: None,
+            rollout_policy: None,
         };
 
         // The attacker canister tries to update the subnet's configuration, pretending
@@ -269,6 +272,7 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
                             ssh_readonly_access: vec![],
                             ssh_backup_access: vec![],
                             ecdsa_config: None,
+                            rollout_policy: None,
                         }),
                     )],
                     preconditions: vec![],
@@ -318,6 +322,7 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
             max_number_of_canisters: Some(42),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
+            rollout_policy: None,
         };
 
         // Attempt to update the subnet's configuration. Since the update happens from
@@ -372,6 +377,7 @@ fn test_the_governance_canister_can_update_a_subnets_configuration() {
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
                 ecdsa_config: None,
+                rollout_policy: None,
             }
         );
 
@@ -413,6 +419,7 @@ fn test_subnets_configuration_ecdsa_fields_are_updated_correctly() {
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
+            rollout_policy: None,
         };
 
         // Just create the registry canister and wait until the subnet_handler ID is
@@ -597,5 +604,6 @@ fn empty_update_subnet_payload(subnet_id: SubnetId) -> UpdateSubnetPayload {
         ssh_backup_access: None,
         ecdsa_config: None,
         ecdsa_key_signing_enable: None,
+        rollout_policy: None,
     }
 }
@@ -84,6 +84,25 @@ impl RegistryClientImpl {
     /// provider failed. Returns `Ok` if querying the data provider succeeded,
     /// regardless of whether a newer registry version was available or not.
     pub fn poll_once(&self) -> Result<(), RegistryClientError> {
+        // A client that has no records yet bootstraps from a snapshot, if the
+        // data provider supports it, instead of replaying all versions. If that
+        // fails, or no snapshot has been taken yet, we fall back to fetching all
+        // updates.
+        if self.get_latest_version() == ZERO_REGISTRY_VERSION {
+            match self.data_provider.get_snapshot() {
+                Ok(Some((records, version))) if version > ZERO_REGISTRY_VERSION => {
+                    let mut cache_state = self.cache.write().unwrap();
+                    if version > cache_state.latest_version {
+                        self.metrics.registry_version.set(version.get() as i64);
+                        cache_state.update(records, version);
+                        cache_state.earliest_version = version;
+                    }
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+
         let (records, version) = {
             let latest_version = self.cache.read().unwrap().latest_version;
             let records = match self
@@ -146,7 +165,7 @@ impl RegistryClientImpl {
         version: RegistryVersion,
     ) -> Result<RwLockReadGuard<CacheState>, RegistryClientError> {
         let cache_state = self.cache.read().unwrap();
-        if version > cache_state.latest_version {
+        if version > cache_state.latest_version || version < cache_state.earliest_version {
             return Err(RegistryClientError::VersionNotAvailable { version });
         }
         Ok(cache_state)
@@ -191,6 +210,9 @@ struct CacheState {
     records: Vec<RegistryTransportRecord>,
     timestamps: BTreeMap<RegistryVersion, Time>,
     latest_version: RegistryVersion,
+    /// The earliest version the cache holds the complete registry for. This is
+    /// only greater than zero if the cache was bootstrapped from a snapshot.
+    earliest_version: RegistryVersion,
 }
 
 impl CacheState {
@@ -198,6 +220,7 @@ impl CacheState {
         Self {
             records: vec![],
             latest_version: ZERO_REGISTRY_VERSION,
+            earliest_version: ZERO_REGISTRY_VERSION,
             timestamps: Default::default(),
         }
     }
@@ -655,6 +678,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn can_bootstrap_from_snapshot_and_follow_updates() {
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        let set = |key: &str, ver: u64| data_provider.add(key, v(ver), Some(value(ver))).unwrap();
+        set("A", 1);
+        set("A", 3);
+        set("B", 2);
+        data_provider.add::<TestProto>("B", v(4), None).unwrap();
+        set("C", 5);
+
+        let registry = RegistryClientImpl::new(
+            Arc::new(SnapshotDataProvider {
+                snapshot_version: v(4),
+                data_provider: data_provider.clone(),
+            }),
+            None,
+        );
+
+        registry.poll_once().unwrap();
+        assert_eq!(registry.get_latest_version(), v(4));
+        assert_eq!(registry.get_test_proto("A", v(4)).unwrap(), Some(value(3)));
+        assert!(registry.get_test_proto("B", v(4)).unwrap().is_none());
+        // Versions before the snapshot are not available.
+        assert_matches!(
+            registry.get_test_proto("A", v(3)),
+            Err(RegistryClientError::VersionNotAvailable { .. })
+        );
+
+        registry.poll_once().unwrap();
+        assert_eq!(registry.get_latest_version(), v(5));
+        assert_eq!(registry.get_test_proto("C", v(5)).unwrap(), Some(value(5)));
+        assert_eq!(registry.get_key_family("", v(5)).unwrap(), vec!["A", "C"]);
+    }
+
+    #[test]
+    fn falls_back_to_updates_if_no_snapshot_was_taken() {
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        data_provider.add("A", v(1), Some(value(1))).unwrap();
+        data_provider.add("A", v(2), Some(value(2))).unwrap();
+
+        let registry = RegistryClientImpl::new(
+            Arc::new(SnapshotDataProvider {
+                snapshot_version: ZERO_REGISTRY_VERSION,
+                data_provider: data_provider.clone(),
+            }),
+            None,
+        );
+
+        registry.poll_once().unwrap();
+        assert_eq!(registry.get_latest_version(), v(2));
+        assert_eq!(registry.get_test_proto("A", v(1)).unwrap(), Some(value(1)));
+    }
+
     fn v(v: u64) -> RegistryVersion {
         RegistryVersion::new(v)
     }
@@ -704,4 +780,44 @@ mod tests {
             Ok(res)
         }
     }
+
+    struct SnapshotDataProvider {
+        snapshot_version: RegistryVersion,
+        data_provider: Arc<dyn RegistryDataProvider>,
+    }
+
+    impl RegistryDataProvider for SnapshotDataProvider {
+        fn get_updates_since(
+            &self,
+            version: RegistryVersion,
+        ) -> Result<Vec<RegistryTransportRecord>, RegistryDataProviderError> {
+            self.data_provider.get_updates_since(version)
+        }
+
+        fn get_snapshot(
+            &self,
+        ) -> Result<
+            Option<(Vec<RegistryTransportRecord>, RegistryVersion)>,
+            RegistryDataProviderError,
+        > {
+            let mut latest = BTreeMap::<String, RegistryTransportRecord>::new();
+            for record in self
+                .data_provider
+                .get_updates_since(ZERO_REGISTRY_VERSION)?
+                .into_iter()
+                .filter(|r| r.version <= self.snapshot_version)
+            {
+                match latest.get(&record.key) {
+                    Some(prev) if prev.version > record.version => (),
+                    _ => {
+                        latest.insert(record.key.clone(), record);
+                    }
+                }
+            }
+            Ok(Some((
+                latest.into_values().collect(),
+                self.snapshot_version,
+            )))
+        }
+    }
 }
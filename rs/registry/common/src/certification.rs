@@ -2,7 +2,7 @@ use ic_certified_vars::{verify_certificate, CertificateValidationError};
 use ic_crypto_tree_hash::{LabeledTree, MixedHashTree};
 use ic_interfaces::registry::RegistryTransportRecord;
 use ic_registry_transport::pb::v1::{
-    registry_mutation::Type, CertifiedResponse, RegistryAtomicMutateRequest,
+    registry_mutation::Type, CertifiedResponse, RegistryAtomicMutateRequest, RegistryValue,
 };
 use ic_types::{crypto::threshold_sig::ThresholdSigPublicKey, CanisterId, RegistryVersion, Time};
 use prost::Message;
@@ -31,6 +31,9 @@ pub enum CertificationError {
     /// Parsing and signature verification was successful, but the list of
     /// deltas doesn't satisfy postconditions of the method.
     InvalidDeltas(String),
+    /// Parsing and signature verification was successful, but the snapshot
+    /// page doesn't satisfy postconditions of the method.
+    InvalidSnapshot(String),
     /// The hash tree in the response was not well-formed.
     MalformedHashTree(String),
     /// There are multiple delegation levels in the certification which is (currently) not allowed.
@@ -46,6 +49,33 @@ struct CertifiedPayload {
     delta: BTreeMap<u64, Protobuf<RegistryAtomicMutateRequest>>,
 }
 
+#[derive(Deserialize)]
+struct CertifiedSnapshotPayload {
+    current_version: Leb128EncodedU64,
+    snapshot_version: Leb128EncodedU64,
+    #[serde(default)]
+    snapshot: BTreeMap<Vec<u8>, Protobuf<RegistryValue>>,
+}
+
+/// A page of the certified snapshot of the registry, as returned by the
+/// "get_certified_snapshot" method.
+#[derive(Debug, PartialEq)]
+pub struct SnapshotPage {
+    /// The latest record at `version` of every key in the page that is
+    /// greater than the requested start key and exists at `version`.
+    pub records: Vec<RegistryTransportRecord>,
+    /// The version of the registry the snapshot was taken at.
+    pub version: RegistryVersion,
+    /// The start key of the next page, or None if this is the last page.
+    pub next_start_key: Option<Vec<u8>>,
+}
+
+/// A node of the snapshot subtree, in key order.
+enum SnapshotNode<'a> {
+    Key(&'a [u8]),
+    Pruned,
+}
+
 fn embed_certificate_error(err: CertificateValidationError) -> CertificationError {
     type Cve = CertificateValidationError;
     type Ce = CertificationError;
@@ -153,18 +183,174 @@ pub fn decode_hash_tree(
     Ok((changes, RegistryVersion::from(current_version)))
 }
 
-/// Parses a response of the "get_certified_changes_since" registry method,
-/// validates data integrity and authenticity and returns
-///   * The list of changes to apply.
-///   * The latest version available (might be greater than the version of the
-///     last received delta if there were too many deltas to send in one go).
-///   * The time when the received data was last certified by the subnet.
-pub fn decode_certified_deltas(
-    since_version: u64,
+/// Flattens the "snapshot" subtree of `hash_tree` into its revealed keys and
+/// pruned subtrees, in key order.
+fn snapshot_nodes(hash_tree: &MixedHashTree) -> Result<Vec<SnapshotNode<'_>>, CertificationError> {
+    fn find_snapshot(t: &MixedHashTree) -> Option<&MixedHashTree> {
+        match t {
+            MixedHashTree::Fork(lr) => find_snapshot(&lr.0).or_else(|| find_snapshot(&lr.1)),
+            MixedHashTree::Labeled(label, subtree) if label.as_bytes() == b"snapshot" => {
+                Some(&**subtree)
+            }
+            _ => None,
+        }
+    }
+
+    fn collect<'a>(t: &'a MixedHashTree, nodes: &mut Vec<SnapshotNode<'a>>) {
+        match t {
+            MixedHashTree::Fork(lr) => {
+                collect(&lr.0, nodes);
+                collect(&lr.1, nodes);
+            }
+            MixedHashTree::Labeled(label, _) => nodes.push(SnapshotNode::Key(label.as_bytes())),
+            MixedHashTree::Pruned(_) => nodes.push(SnapshotNode::Pruned),
+            MixedHashTree::Empty | MixedHashTree::Leaf(_) => (),
+        }
+    }
+
+    let snapshot = find_snapshot(hash_tree).ok_or_else(|| {
+        CertificationError::InvalidSnapshot("the response contains no snapshot".to_string())
+    })?;
+    let mut nodes = vec![];
+    collect(snapshot, &mut nodes);
+    Ok(nodes)
+}
+
+/// Validates that the revealed keys of the snapshot page starting at
+/// `start_key` form a contiguous range, i.e. that no key was left out, and
+/// returns the start key of the next page, if any. We want to check the
+/// following properties:
+///
+///   1. There are no pruned subtrees between revealed keys.
+///
+///   2. If there is a pruned subtree before the first revealed key, that key is
+///      not greater than `start_key`, i.e. it is either the start key itself or
+///      its predecessor.
+///
+///   3. If there is a pruned subtree after the last revealed key, the page is
+///      not the last one, and the last revealed key is greater than
+///      `start_key`, so that the next page makes progress.
+fn validate_snapshot_range(
+    start_key: &[u8],
+    nodes: &[SnapshotNode<'_>],
+) -> Result<Option<Vec<u8>>, CertificationError> {
+    let keys: Vec<(usize, &[u8])> = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, n)| match n {
+            SnapshotNode::Key(key) => Some((i, *key)),
+            SnapshotNode::Pruned => None,
+        })
+        .collect();
+
+    let (first, last) = match (keys.first(), keys.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ if nodes.is_empty() => return Ok(None),
+        _ => {
+            return Err(CertificationError::InvalidSnapshot(
+                "the snapshot page has no keys".to_string(),
+            ))
+        }
+    };
+
+    if last.0 - first.0 + 1 != keys.len() {
+        return Err(CertificationError::InvalidSnapshot(
+            "the keys of the snapshot page are not contiguous".to_string(),
+        ));
+    }
+
+    if first.0 > 0 && first.1 > start_key {
+        return Err(CertificationError::InvalidSnapshot(format!(
+            "the snapshot page starts at key {:?} after start key {:?}",
+            String::from_utf8_lossy(first.1),
+            String::from_utf8_lossy(start_key),
+        )));
+    }
+
+    if last.0 + 1 == nodes.len() {
+        return Ok(None);
+    }
+
+    if last.1 <= start_key {
+        return Err(CertificationError::InvalidSnapshot(format!(
+            "the snapshot page has no keys after start key {:?}",
+            String::from_utf8_lossy(start_key),
+        )));
+    }
+
+    Ok(Some(last.1.to_vec()))
+}
+
+/// Decodes a page of the registry snapshot starting at `start_key` from its
+/// hash tree representation.
+pub fn decode_snapshot_hash_tree(
+    start_key: &[u8],
+    hash_tree: MixedHashTree,
+) -> Result<SnapshotPage, CertificationError> {
+    let next_start_key = validate_snapshot_range(start_key, &snapshot_nodes(&hash_tree)?)?;
+
+    let labeled_tree = LabeledTree::<Vec<u8>>::try_from(hash_tree).map_err(|err| {
+        CertificationError::MalformedHashTree(format!(
+            "failed to convert hash tree to labeled tree: {:?}",
+            err
+        ))
+    })?;
+
+    let certified_payload =
+        CertifiedSnapshotPayload::deserialize(LabeledTreeDeserializer::new(&labeled_tree))
+            .map_err(|err| {
+                CertificationError::DeserError(format!(
+                    "failed to unpack certified snapshot from the labeled tree: {}",
+                    err
+                ))
+            })?;
+
+    let current_version = certified_payload.current_version.0;
+    let version = certified_payload.snapshot_version.0;
+    if version > current_version {
+        return Err(CertificationError::InvalidSnapshot(format!(
+            "snapshot version {} is newer than the current version {}",
+            version, current_version
+        )));
+    }
+
+    let mut records = vec![];
+    for (key, value) in certified_payload.snapshot {
+        let value = value.0;
+        if value.version > version {
+            return Err(CertificationError::InvalidSnapshot(format!(
+                "key {:?} has version {}, which is newer than the snapshot version {}",
+                String::from_utf8_lossy(&key[..]),
+                value.version,
+                version
+            )));
+        }
+        // Keys deleted before the snapshot version are not part of it.
+        if key.as_slice() <= start_key || value.deletion_marker {
+            continue;
+        }
+        records.push(RegistryTransportRecord {
+            key: String::from_utf8_lossy(&key[..]).to_string(),
+            value: Some(value.value),
+            version: RegistryVersion::from(value.version),
+        });
+    }
+
+    Ok(SnapshotPage {
+        records,
+        version: RegistryVersion::from(version),
+        next_start_key,
+    })
+}
+
+/// Decodes a certified response of the registry canister and verifies the
+/// authenticity of its hash tree, returning the tree and the time when the
+/// data was last certified by the subnet.
+fn decode_certified_response(
     canister_id: &CanisterId,
     nns_pk: &ThresholdSigPublicKey,
     payload: &[u8],
-) -> Result<(Vec<RegistryTransportRecord>, RegistryVersion, Time), CertificationError> {
+) -> Result<(MixedHashTree, Time), CertificationError> {
     let certified_response = CertifiedResponse::decode(payload).map_err(|err| {
         CertificationError::DeserError(format!(
             "failed to decode certified response from {}: {:?}",
@@ -195,11 +381,46 @@ pub fn decode_certified_deltas(
     )
     .map_err(embed_certificate_error)?;
 
+    Ok((mixed_hash_tree, time))
+}
+
+/// Parses a response of the "get_certified_changes_since" registry method,
+/// validates data integrity and authenticity and returns
+///   * The list of changes to apply.
+///   * The latest version available (might be greater than the version of the
+///     last received delta if there were too many deltas to send in one go).
+///   * The time when the received data was last certified by the subnet.
+pub fn decode_certified_deltas(
+    since_version: u64,
+    canister_id: &CanisterId,
+    nns_pk: &ThresholdSigPublicKey,
+    payload: &[u8],
+) -> Result<(Vec<RegistryTransportRecord>, RegistryVersion, Time), CertificationError> {
+    let (mixed_hash_tree, time) = decode_certified_response(canister_id, nns_pk, payload)?;
+
     let (changes, current_version) = decode_hash_tree(since_version, mixed_hash_tree)?;
 
     Ok((changes, current_version, time))
 }
 
+/// Parses a response of the "get_certified_snapshot" registry method for the
+/// page starting at `start_key`, validates data integrity and authenticity and
+/// returns
+///   * The page of the snapshot.
+///   * The time when the received data was last certified by the subnet.
+pub fn decode_certified_snapshot_page(
+    start_key: &[u8],
+    canister_id: &CanisterId,
+    nns_pk: &ThresholdSigPublicKey,
+    payload: &[u8],
+) -> Result<(SnapshotPage, Time), CertificationError> {
+    let (mixed_hash_tree, time) = decode_certified_response(canister_id, nns_pk, payload)?;
+
+    let page = decode_snapshot_hash_tree(start_key, mixed_hash_tree)?;
+
+    Ok((page, time))
+}
+
 /// An auxiliary type that instructs serde to deserialize blob as a protobuf
 /// message.
 struct Protobuf<T>(T);
@@ -1,4 +1,6 @@
-use super::{decode_certified_deltas, CertificationError};
+use super::{
+    decode_certified_deltas, decode_certified_snapshot_page, CertificationError, SnapshotPage,
+};
 use ic_certified_vars_test_utils::{CertificateBuilder, CertificateData};
 use ic_crypto_tree_hash::{
     flatmap, Digest, FlatMap, HashTreeBuilder, HashTreeBuilderImpl, Label, LabeledTree,
@@ -7,7 +9,7 @@ use ic_crypto_tree_hash::{
 use ic_interfaces::registry::RegistryTransportRecord;
 use ic_registry_transport::{
     delete,
-    pb::v1::{CertifiedResponse, RegistryAtomicMutateRequest, RegistryMutation},
+    pb::v1::{CertifiedResponse, RegistryAtomicMutateRequest, RegistryMutation, RegistryValue},
     upsert,
 };
 use ic_types::{
@@ -117,6 +119,106 @@ fn make_certified_delta(
     (cid, pk, encoded_response)
 }
 
+fn make_certified_snapshot(
+    version: u64,
+    snapshot_version: u64,
+    values: Vec<(&str, RegistryValue)>,
+    selection: &[usize],
+) -> (CanisterId, ThresholdSigPublicKey, EncodedResponse) {
+    let cid = CanisterId::from_u64(1);
+
+    let mut encoded_version = vec![];
+    let mut encoded_snapshot_version = vec![];
+
+    let mut b = HashTreeBuilderImpl::new();
+
+    let current_version_label = Label::from("current_version");
+    let snapshot_version_label = Label::from("snapshot_version");
+    b.start_subtree();
+
+    b.new_edge(current_version_label.clone());
+    b.start_leaf();
+    leb128::write::unsigned(&mut encoded_version, version).unwrap();
+    b.write_leaf(&encoded_version[..]);
+    b.finish_leaf();
+
+    let mut map: FlatMap<Label, LabeledTree<Vec<u8>>> = FlatMap::new();
+
+    b.new_edge(Label::from("snapshot"));
+    b.start_subtree();
+    for (i, (key, value)) in values.into_iter().enumerate() {
+        let mut buf = vec![];
+
+        let label = Label::from(key);
+        b.new_edge(label.clone());
+        b.start_leaf();
+        value.encode(&mut buf).unwrap();
+        b.write_leaf(&buf[..]);
+        b.finish_leaf();
+
+        if selection.contains(&i) {
+            map.try_append(label, LabeledTree::Leaf(buf)).unwrap();
+        }
+    }
+    b.finish_subtree();
+
+    b.new_edge(snapshot_version_label.clone());
+    b.start_leaf();
+    leb128::write::unsigned(&mut encoded_snapshot_version, snapshot_version).unwrap();
+    b.write_leaf(&encoded_snapshot_version[..]);
+    b.finish_leaf();
+    b.finish_subtree();
+
+    let witness_gen = b.witness_generator().unwrap();
+    let digest = witness_gen.hash_tree().digest().clone();
+
+    let mut root = flatmap!(current_version_label => LabeledTree::Leaf(encoded_version));
+    if !map.is_empty() {
+        root.try_append(Label::from("snapshot"), LabeledTree::SubTree(map))
+            .unwrap();
+    }
+    root.try_append(
+        snapshot_version_label,
+        LabeledTree::Leaf(encoded_snapshot_version),
+    )
+    .unwrap();
+    let data_tree = LabeledTree::SubTree(root);
+
+    let mixed_hash_tree = witness_gen.mixed_hash_tree(&data_tree).unwrap();
+
+    let (_, pk, cbor) = CertificateBuilder::new(CertificateData::CanisterData {
+        canister_id: cid,
+        certified_data: digest,
+    })
+    .build();
+
+    let response = CertifiedResponse {
+        hash_tree: Some(mixed_hash_tree.into()),
+        certificate: cbor,
+    };
+
+    let mut encoded_response = vec![];
+    response.encode(&mut encoded_response).unwrap();
+
+    (cid, pk, encoded_response)
+}
+
+fn set_value(version: u64, v: impl AsRef<[u8]>) -> RegistryValue {
+    RegistryValue {
+        version,
+        value: v.as_ref().to_vec(),
+        deletion_marker: false,
+    }
+}
+
+fn rem_value(version: u64) -> RegistryValue {
+    RegistryValue {
+        version,
+        value: vec![],
+        deletion_marker: true,
+    }
+}
+
 fn set_key(version: u64, k: impl ToString, v: impl AsRef<[u8]>) -> RegistryTransportRecord {
     RegistryTransportRecord {
         version: RegistryVersion::from(version),
@@ -310,3 +412,116 @@ fn test_decode_empty_prefix() {
         other => panic!("Expected InvalidDeltas error, got {:?}", other),
     }
 }
+
+#[test]
+fn test_decode_snapshot_single_page() {
+    let (cid, pk, payload) = make_certified_snapshot(
+        5,
+        3,
+        vec![
+            ("key1", set_value(1, "value1")),
+            ("key2", rem_value(3)),
+            ("key3", set_value(2, "value3")),
+        ],
+        &[0, 1, 2],
+    );
+    assert_eq!(
+        decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]).unwrap(),
+        (
+            SnapshotPage {
+                records: vec![set_key(1, "key1", "value1"), set_key(2, "key3", "value3")],
+                version: RegistryVersion::from(3u64),
+                next_start_key: None,
+            },
+            Time::from_nanos_since_unix_epoch(REPLICA_TIME),
+        ),
+    )
+}
+
+#[test]
+fn test_decode_snapshot_pages() {
+    let values = vec![
+        ("key1", set_value(1, "value1")),
+        ("key2", set_value(2, "value2")),
+        ("key3", set_value(3, "value3")),
+    ];
+
+    let (cid, pk, payload) = make_certified_snapshot(3, 3, values.clone(), &[0, 1]);
+    let (page, _) = decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]).unwrap();
+    assert_eq!(
+        page.records,
+        vec![set_key(1, "key1", "value1"), set_key(2, "key2", "value2")]
+    );
+    assert_eq!(page.next_start_key, Some(b"key2".to_vec()));
+
+    let (cid, pk, payload) = make_certified_snapshot(3, 3, values, &[1, 2]);
+    let (page, _) = decode_certified_snapshot_page(b"key2", &cid, &pk, &payload[..]).unwrap();
+    assert_eq!(page.records, vec![set_key(3, "key3", "value3")]);
+    assert_eq!(page.next_start_key, None);
+}
+
+#[test]
+fn test_decode_snapshot_missing_middle_key() {
+    let (cid, pk, payload) = make_certified_snapshot(
+        3,
+        3,
+        vec![
+            ("key1", set_value(1, "value1")),
+            ("key2", set_value(2, "value2")),
+            ("key3", set_value(3, "value3")),
+        ],
+        &[0, 2],
+    );
+    match decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]) {
+        Err(CertificationError::InvalidSnapshot(_)) => (),
+        other => panic!("Expected InvalidSnapshot error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_snapshot_missing_first_key() {
+    let (cid, pk, payload) = make_certified_snapshot(
+        3,
+        3,
+        vec![
+            ("key1", set_value(1, "value1")),
+            ("key2", set_value(2, "value2")),
+            ("key3", set_value(3, "value3")),
+        ],
+        &[1, 2],
+    );
+    match decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]) {
+        Err(CertificationError::InvalidSnapshot(_)) => (),
+        other => panic!("Expected InvalidSnapshot error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_snapshot_pruned() {
+    let (cid, pk, payload) =
+        make_certified_snapshot(1, 1, vec![("key1", set_value(1, "value1"))], &[]);
+    match decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]) {
+        Err(CertificationError::InvalidSnapshot(_)) => (),
+        other => panic!("Expected InvalidSnapshot error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_snapshot_newer_than_current_version() {
+    let (cid, pk, payload) =
+        make_certified_snapshot(1, 2, vec![("key1", set_value(1, "value1"))], &[0]);
+    match decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]) {
+        Err(CertificationError::InvalidSnapshot(_)) => (),
+        other => panic!("Expected InvalidSnapshot error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_snapshot_value_newer_than_snapshot_version() {
+    let (cid, pk, payload) =
+        make_certified_snapshot(3, 2, vec![("key1", set_value(3, "value1"))], &[0]);
+    match decode_certified_snapshot_page(&[], &cid, &pk, &payload[..]) {
+        Err(CertificationError::InvalidSnapshot(_)) => (),
+        other => panic!("Expected InvalidSnapshot error, got {:?}", other),
+    }
+}
@@ -95,4 +95,23 @@ impl RegistryDataProvider for CertifiedNnsDataProvider {
         })?;
         Ok(records)
     }
+
+    fn get_snapshot(
+        &self,
+    ) -> Result<Option<(Vec<RegistryTransportRecord>, RegistryVersion)>, RegistryDataProviderError>
+    {
+        let snapshot = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on({
+                let registry_canister = Arc::clone(&self.registry_canister);
+                let nns_public_key = Arc::clone(&self.nns_public_key);
+                async move {
+                    registry_canister
+                        .get_certified_snapshot(&nns_public_key, None)
+                        .await
+                        .map_err(|source| RegistryDataProviderError::Transfer { source })
+                }
+            })
+        })?;
+        Ok(snapshot.map(|(records, version, _time)| (records, version)))
+    }
 }
@@ -3,7 +3,7 @@ use ic_interfaces::registry::{
 };
 use ic_registry_common_proto::pb::local_store::v1::{
     CertifiedTime as PbCertifiedTime, ChangelogEntry as PbChangelogEntry, Delta as PbDelta,
-    KeyMutation as PbKeyMutation, MutationType,
+    EarliestVersion as PbEarliestVersion, KeyMutation as PbKeyMutation, MutationType,
 };
 use ic_types::registry::RegistryDataProviderError;
 use ic_types::RegistryVersion;
@@ -41,7 +41,15 @@ pub trait LocalStoreReader: Send + Sync {
     /// `cl` where the subsequence `cl[0..i]`, `0 <= i <= len(ds)`, applied
     /// to a registry at latest version `v` represents the registry at
     /// version `v+i+1`.
+    ///
+    /// If the store was bootstrapped from a snapshot at version `s` and
+    /// `version < s - 1`, the returned changelog starts with the snapshot,
+    /// i.e. it is the changelog since version `s - 1`.
     fn get_changelog_since_version(&self, version: RegistryVersion) -> io::Result<Changelog>;
+
+    /// Returns the earliest version held by the store: the version of the
+    /// snapshot the store was bootstrapped from, if any, and 1 otherwise.
+    fn get_earliest_version(&self) -> io::Result<RegistryVersion>;
 }
 
 pub trait LocalStoreWriter: Send + Sync {
@@ -53,10 +61,20 @@ pub trait LocalStoreWriter: Send + Sync {
     /// (2) The given change log entry must be nonempty list of KeyMutations.
     fn store(&self, version: RegistryVersion, v: ChangelogEntry) -> io::Result<()>;
 
+    /// Bootstraps an empty store from `snapshot`, the latest record of every
+    /// key of the registry at `version`, which becomes the earliest version
+    /// held by the store. Versions below `version` are not available.
+    ///
+    /// Preconditions:
+    /// (1) The store is empty.
+    /// (2) `snapshot` must be a nonempty list of KeyMutations.
+    fn store_snapshot(&self, version: RegistryVersion, snapshot: ChangelogEntry) -> io::Result<()>;
+
     /// Clears the Local Store.
     ///
-    /// Note: This clears registry versions, stored in directories, but not the
-    /// certified timestamp file in the root of the local store.
+    /// Note: This clears registry versions, stored in directories, and the
+    /// earliest version marker, but not the certified timestamp file in the
+    /// root of the local store.
     fn clear(&self) -> io::Result<()>;
 
     /// Update the locally stored certified time to `unix_epoch_nanos`.
@@ -129,13 +147,18 @@ impl LocalStoreImpl {
         let fname = "time.local_store.v1.CertificationTime.pb";
         self.path.join(fname)
     }
+
+    fn earliest_version_path(&self) -> PathBuf {
+        let fname = "earliest_version.local_store.v1.EarliestVersion.pb";
+        self.path.join(fname)
+    }
 }
 
 impl LocalStore for LocalStoreImpl {}
 
 impl LocalStoreReader for LocalStoreImpl {
     fn get_changelog_since_version(&self, version: RegistryVersion) -> io::Result<Changelog> {
-        let start = version.get() + 1;
+        let start = std::cmp::max(version.get() + 1, self.get_earliest_version()?.get());
         (start..)
             .map(|i| self.get_path(i))
             .take_while(|p| p.exists())
@@ -146,6 +169,17 @@ impl LocalStoreReader for LocalStoreImpl {
                 Ok(res)
             })
     }
+
+    fn get_earliest_version(&self) -> io::Result<RegistryVersion> {
+        let path = self.earliest_version_path();
+        if !path.exists() {
+            return Ok(RegistryVersion::from(1));
+        }
+        let bytes = std::fs::read(path)?;
+        let pb = PbEarliestVersion::decode(bytes.as_slice())
+            .map_err(|e| io::Error::new(std::io::ErrorKind::Other, e))?;
+        Ok(RegistryVersion::from(pb.registry_version))
+    }
 }
 
 impl LocalStoreWriter for LocalStoreImpl {
@@ -155,6 +189,21 @@ impl LocalStoreWriter for LocalStoreImpl {
         self.write_changelog_entry(version.get(), pb_ce)
     }
 
+    fn store_snapshot(&self, version: RegistryVersion, snapshot: ChangelogEntry) -> io::Result<()> {
+        assert!(version.get() > 0, "Version must be > 0.");
+        let pb_ce = changelog_entry_to_protobuf(snapshot);
+        // The snapshot is written before the marker, so that a store that has
+        // the marker always holds the snapshot. Until the marker is written,
+        // the store appears to be empty.
+        let path = self.get_path(version.get());
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        write_protobuf_using_tmp_file(path, &pb_ce)?;
+        let pb = PbEarliestVersion {
+            registry_version: version.get(),
+        };
+        write_protobuf_using_tmp_file(self.earliest_version_path(), &pb)
+    }
+
     fn clear(&self) -> io::Result<()> {
         std::fs::read_dir(self.path.as_path())?.try_for_each(|de| {
             let path = de?.path();
@@ -163,7 +212,12 @@ impl LocalStoreWriter for LocalStoreImpl {
             } else {
                 Ok(())
             }
-        })
+        })?;
+        let earliest_version_path = self.earliest_version_path();
+        if earliest_version_path.exists() {
+            std::fs::remove_file(earliest_version_path)?;
+        }
+        Ok(())
     }
 
     // Store the certified time
@@ -179,26 +233,64 @@ impl RegistryDataProvider for LocalStoreImpl {
         &self,
         version: RegistryVersion,
     ) -> Result<Vec<RegistryTransportRecord>, RegistryDataProviderError> {
-        let changelog = self.get_changelog_since_version(version).map_err(|e| {
-            RegistryDataProviderError::Transfer {
-                source: ic_registry_transport::Error::MalformedMessage(format!(
-                    "Error when reading changelog from local storage: {:?}",
-                    e
-                )),
-            }
-        })?;
+        let to_provider_error = |e| RegistryDataProviderError::Transfer {
+            source: ic_registry_transport::Error::MalformedMessage(format!(
+                "Error when reading changelog from local storage: {:?}",
+                e
+            )),
+        };
+        let changelog = self
+            .get_changelog_since_version(version)
+            .map_err(to_provider_error)?;
+        // The changelog of a store bootstrapped from a snapshot starts at the
+        // snapshot's version.
+        let earliest_version = self.get_earliest_version().map_err(to_provider_error)?;
+        let base = std::cmp::max(version.get(), earliest_version.get() - 1);
         let res: Vec<_> = changelog
             .iter()
             .enumerate()
             .flat_map(|(i, cle)| cle.iter().map(move |km| (i, km)))
             .map(|(i, km)| RegistryTransportRecord {
-                version: version + RegistryVersion::from((i as u64) + 1),
+                version: RegistryVersion::from(base + (i as u64) + 1),
                 key: km.key.clone(),
                 value: km.value.clone(),
             })
             .collect();
         Ok(res)
     }
+
+    /// If the local store was bootstrapped from a snapshot (see
+    /// [`LocalStoreWriter::store_snapshot`]), returns the records of the
+    /// snapshot, so that registry clients do not request the versions below
+    /// it.
+    fn get_snapshot(
+        &self,
+    ) -> Result<Option<(Vec<RegistryTransportRecord>, RegistryVersion)>, RegistryDataProviderError>
+    {
+        let to_provider_error = |e| RegistryDataProviderError::Transfer {
+            source: ic_registry_transport::Error::MalformedMessage(format!(
+                "Error when reading changelog from local storage: {:?}",
+                e
+            )),
+        };
+
+        if !self.earliest_version_path().exists() {
+            return Ok(None);
+        }
+        let version = self.get_earliest_version().map_err(to_provider_error)?;
+        let snapshot = Self::read_changelog_entry(self.get_path(version.get()))
+            .and_then(changelog_entry_try_from_proto)
+            .map_err(to_provider_error)?;
+        let records = snapshot
+            .into_iter()
+            .map(|km| RegistryTransportRecord {
+                version,
+                key: km.key,
+                value: km.value,
+            })
+            .collect();
+        Ok(Some((records, version)))
+    }
 }
 
 fn changelog_entry_try_from_proto(value: PbChangelogEntry) -> Result<ChangelogEntry, io::Error> {
@@ -453,6 +545,92 @@ mod tests {
         assert_eq!(expected_time, actual_time);
     }
 
+    #[test]
+    fn can_bootstrap_from_snapshot() {
+        let tempdir = TempDir::new().unwrap();
+        let store = LocalStoreImpl::new(tempdir.path());
+        let record = |key: &str, value: Option<Vec<u8>>, version: u64| RegistryTransportRecord {
+            key: key.to_string(),
+            value,
+            version: RegistryVersion::from(version),
+        };
+        let mutation = |key: &str, value: Vec<u8>| KeyMutation {
+            key: key.to_string(),
+            value: Some(value),
+        };
+
+        // A store that was not bootstrapped from a snapshot has none.
+        assert_eq!(store.get_snapshot().unwrap(), None);
+        let mut rng = rand::thread_rng();
+        store
+            .store(
+                RegistryVersion::from(1),
+                get_random_changelog(1, &mut rng)[0].clone(),
+            )
+            .unwrap();
+        assert_eq!(store.get_snapshot().unwrap(), None);
+        assert_eq!(
+            store.get_earliest_version().unwrap(),
+            RegistryVersion::from(1)
+        );
+        store.clear().unwrap();
+
+        let snapshot = vec![mutation("A", vec![1]), mutation("B", vec![2])];
+        store
+            .store_snapshot(RegistryVersion::from(4), snapshot.clone())
+            .unwrap();
+        // Versions below the snapshot cannot be stored.
+        assert!(store
+            .store(RegistryVersion::from(3), vec![mutation("A", vec![3])])
+            .is_err());
+        let delta = vec![KeyMutation {
+            key: "A".to_string(),
+            value: None,
+        }];
+        store
+            .store(RegistryVersion::from(5), delta.clone())
+            .unwrap();
+
+        assert_eq!(
+            store.get_earliest_version().unwrap(),
+            RegistryVersion::from(4)
+        );
+        assert_eq!(
+            store.get_snapshot().unwrap(),
+            Some((
+                vec![record("A", Some(vec![1]), 4), record("B", Some(vec![2]), 4)],
+                RegistryVersion::from(4)
+            ))
+        );
+        for version in 0..4 {
+            assert_eq!(
+                store
+                    .get_changelog_since_version(RegistryVersion::from(version))
+                    .unwrap(),
+                vec![snapshot.clone(), delta.clone()]
+            );
+        }
+        assert_eq!(
+            store
+                .get_updates_since(RegistryVersion::from(0))
+                .unwrap()
+                .last(),
+            Some(&record("A", None, 5))
+        );
+        assert_eq!(
+            store.get_updates_since(RegistryVersion::from(4)).unwrap(),
+            vec![record("A", None, 5)]
+        );
+
+        // Clearing the store also removes the snapshot marker.
+        store.clear().unwrap();
+        assert_eq!(store.get_snapshot().unwrap(), None);
+        assert_eq!(
+            store.get_earliest_version().unwrap(),
+            RegistryVersion::from(1)
+        );
+    }
+
     fn get_random_changelog(n: usize, rng: &mut ThreadRng) -> Changelog {
         // some pseudo random entries
         (0..n)
@@ -9,7 +9,8 @@ use ic_interfaces::registry::RegistryTransportRecord;
 use ic_registry_transport::{
     deserialize_atomic_mutate_response, deserialize_get_changes_since_response,
     deserialize_get_value_response, serialize_atomic_mutate_request,
-    serialize_get_changes_since_request, serialize_get_value_request,
+    serialize_get_certified_snapshot_request, serialize_get_changes_since_request,
+    serialize_get_value_request,
 };
 use ic_registry_transport::{
     pb::v1::{Precondition, RegistryDelta, RegistryMutation},
@@ -34,6 +35,12 @@ impl RegistryCanister {
         Self::new_with_agent_transformer(url, |a| a.with_query_timeout(t))
     }
 
+    /// Like `new`, but the certificates of update calls, i.e. of
+    /// `atomic_mutate`, are verified against `root_key`.
+    pub fn new_with_root_key(url: Vec<Url>, root_key: ThresholdSigPublicKey) -> Self {
+        Self::new_with_agent_transformer(url, |a| a.with_root_key(root_key))
+    }
+
     fn new_with_agent_transformer<F>(url: Vec<Url>, f: F) -> Self
     where
         F: FnMut(Agent) -> Agent,
@@ -140,6 +147,70 @@ impl RegistryCanister {
         .map_err(|err| Error::UnknownError(format!("{:?}", err)))
     }
 
+    /// Fetches the certified snapshot of the registry, i.e. the latest record
+    /// of every key at the version of the registry canister's latest
+    /// snapshot, page by page. Keys deleted at that version are not part of
+    /// the snapshot.
+    ///
+    /// Returns `Ok(None)` if the snapshot is newer than `max_version`.
+    ///
+    /// All pages are fetched from the same NNS-hosting replica, chosen at
+    /// random. If the registry canister takes a new snapshot while the pages
+    /// are fetched, the snapshot is fetched again from its first page.
+    pub async fn get_certified_snapshot(
+        &self,
+        nns_public_key: &ThresholdSigPublicKey,
+        max_version: Option<RegistryVersion>,
+    ) -> Result<Option<(Vec<RegistryTransportRecord>, RegistryVersion, Time)>, Error> {
+        let agent = self.choose_random_agent();
+        let mut records = vec![];
+        let mut start_key = vec![];
+        let mut version = None;
+        loop {
+            let payload = serialize_get_certified_snapshot_request(start_key.clone()).unwrap();
+            let response = agent
+                .execute_query(&self.canister_id, "get_certified_snapshot", payload)
+                .await
+                .map_err(|err| {
+                    Error::UnknownError(format!(
+                        "Failed to query get_certified_snapshot on canister {}: {}",
+                        self.canister_id, err,
+                    ))
+                })?
+                .ok_or_else(|| {
+                    Error::UnknownError(format!(
+                        "No response was received when queried get_certified_snapshot on {}",
+                        self.canister_id,
+                    ))
+                })?;
+
+            let (page, time) = crate::certification::decode_certified_snapshot_page(
+                &start_key,
+                &self.canister_id,
+                nns_public_key,
+                &response[..],
+            )
+            .map_err(|err| Error::UnknownError(format!("{:?}", err)))?;
+
+            if max_version.map_or(false, |max_version| page.version > max_version) {
+                return Ok(None);
+            }
+            if version.map_or(false, |version| version != page.version) {
+                records.clear();
+                start_key.clear();
+                version = None;
+                continue;
+            }
+
+            version = Some(page.version);
+            records.extend(page.records);
+            match page.next_start_key {
+                Some(key) => start_key = key,
+                None => return Ok(Some((records, page.version, time))),
+            }
+        }
+    }
+
     pub async fn get_latest_version(&self) -> Result<u64, Error> {
         let agent = self.choose_random_agent();
         match agent
@@ -193,6 +264,9 @@ impl RegistryCanister {
     }
 
     /// Applies 'mutations' to the registry.
+    ///
+    /// The response is only accepted if this `RegistryCanister` was created
+    /// with `new_with_root_key`.
     pub async fn atomic_mutate(
         &self,
         mutations: Vec<RegistryMutation>,
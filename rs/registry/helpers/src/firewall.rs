@@ -1,15 +1,24 @@
 use crate::deserialize_registry_value;
 use ic_interfaces::registry::{RegistryClient, RegistryClientResult};
-use ic_protobuf::registry::firewall::v1::FirewallConfig;
-use ic_registry_keys::make_firewall_config_record_key;
+use ic_protobuf::registry::firewall::v1::{FirewallConfig, FirewallRuleSet};
+use ic_registry_keys::{
+    make_firewall_config_record_key, make_firewall_rules_record_key, FirewallRulesScope,
+};
 use ic_types::RegistryVersion;
 
-/// A trait that allows access to `FirewallConfig`.  The expectation for the
-/// forseeable future is that the `FirewallConfig` will remain small enough so
-/// that we can simply return the entire struct here.
+/// A trait that allows access to `FirewallConfig` and `FirewallRuleSet`s.  The
+/// expectation for the forseeable future is that both will remain small enough
+/// so that we can simply return the entire struct here.
 pub trait FirewallRegistry {
     fn get_firewall_config(&self, version: RegistryVersion)
         -> RegistryClientResult<FirewallConfig>;
+
+    /// Returns the firewall rules of the given scope.
+    fn get_firewall_rules(
+        &self,
+        version: RegistryVersion,
+        scope: &FirewallRulesScope,
+    ) -> RegistryClientResult<FirewallRuleSet>;
 }
 
 impl<T: RegistryClient + ?Sized> FirewallRegistry for T {
@@ -20,4 +29,13 @@ impl<T: RegistryClient + ?Sized> FirewallRegistry for T {
         let bytes = self.get_value(&make_firewall_config_record_key(), version);
         deserialize_registry_value::<FirewallConfig>(bytes)
     }
+
+    fn get_firewall_rules(
+        &self,
+        version: RegistryVersion,
+        scope: &FirewallRulesScope,
+    ) -> RegistryClientResult<FirewallRuleSet> {
+        let bytes = self.get_value(&make_firewall_rules_record_key(scope), version);
+        deserialize_registry_value::<FirewallRuleSet>(bytes)
+    }
 }
@@ -2,7 +2,11 @@ use crate::deserialize_registry_value;
 use crate::subnet::{SubnetListRegistry, SubnetRegistry};
 use ic_interfaces::registry::{RegistryClient, RegistryClientResult};
 pub use ic_protobuf::registry::node::v1::{ConnectionEndpoint, NodeRecord};
-use ic_registry_keys::{get_node_record_node_id, make_node_record_key, NODE_RECORD_KEY_PREFIX};
+pub use ic_protobuf::registry::subnet::v1::NodeUpgradeStatusRecord;
+use ic_registry_keys::{
+    get_node_record_node_id, make_node_record_key, make_node_upgrade_status_record_key,
+    NODE_RECORD_KEY_PREFIX,
+};
 use ic_types::registry::RegistryClientError;
 pub use ic_types::{NodeId, RegistryVersion, SubnetId};
 
@@ -22,6 +26,14 @@ pub trait NodeRegistry {
     /// Returns a list of node ids that contains the id of each node that exists
     /// at version `version`.
     fn get_node_ids(&self, version: RegistryVersion) -> Result<Vec<NodeId>, RegistryClientError>;
+
+    /// Returns the outcome of the latest upgrade reported by the node with id
+    /// `node_id` at version `version`.
+    fn get_node_upgrade_status(
+        &self,
+        node_id: NodeId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<NodeUpgradeStatusRecord>;
 }
 
 impl<T: RegistryClient + ?Sized> NodeRegistry for T {
@@ -61,4 +73,13 @@ impl<T: RegistryClient + ?Sized> NodeRegistry for T {
             .collect();
         Ok(res)
     }
+
+    fn get_node_upgrade_status(
+        &self,
+        node_id: NodeId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<NodeUpgradeStatusRecord> {
+        let bytes = self.get_value(&make_node_upgrade_status_record_key(node_id), version);
+        deserialize_registry_value::<NodeUpgradeStatusRecord>(bytes)
+    }
 }
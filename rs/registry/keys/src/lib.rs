@@ -3,9 +3,12 @@
 //! Since registry mutations come from various NNS canisters, this library MUST
 //! be compilable to WASM as well a native.
 
+use candid::CandidType;
 use ic_base_types::{NodeId, SubnetId};
 use ic_types::crypto::KeyPurpose;
 use ic_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::str::FromStr;
 
 const SUBNET_LIST_KEY: &str = "subnet_list";
@@ -26,6 +29,8 @@ pub const CRYPTO_TLS_CERT_KEY_PREFIX: &str = "crypto_tls_cert_";
 pub const CRYPTO_THRESHOLD_SIGNING_KEY_PREFIX: &str = "crypto_threshold_signing_public_key_";
 pub const DATA_CENTER_KEY_PREFIX: &str = "data_center_record_";
 pub const ECDSA_SIGNING_SUBNET_LIST_KEY_PREFIX: &str = "key_id_";
+pub const FIREWALL_RULES_RECORD_KEY_PREFIX: &str = "firewall_rules_";
+pub const NODE_UPGRADE_STATUS_RECORD_KEY_PREFIX: &str = "node_upgrade_status_";
 
 pub fn make_ecdsa_signing_subnet_list_key<S: AsRef<str>>(key_id: S) -> String {
     format!(
@@ -70,6 +75,64 @@ pub fn make_firewall_config_record_key() -> String {
     "firewall_config".to_string()
 }
 
+/// The scope a set of firewall rules applies to.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum FirewallRulesScope {
+    /// A single node.
+    Node(NodeId),
+    /// All nodes of a subnet.
+    Subnet(SubnetId),
+    /// All nodes assigned to a subnet.
+    ReplicaNodes,
+    /// All nodes.
+    Global,
+}
+
+impl fmt::Display for FirewallRulesScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirewallRulesScope::Node(node_id) => write!(f, "node_{}", node_id),
+            FirewallRulesScope::Subnet(subnet_id) => write!(f, "subnet_{}", subnet_id),
+            FirewallRulesScope::ReplicaNodes => write!(f, "replica_nodes"),
+            FirewallRulesScope::Global => write!(f, "global"),
+        }
+    }
+}
+
+impl FromStr for FirewallRulesScope {
+    type Err = String;
+
+    /// Parses the representation produced by `Display`, e.g. `global` or
+    /// `subnet_<subnet id>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_principal = |id: &str| {
+            PrincipalId::from_str(id).map_err(|e| format!("invalid principal {}: {}", id, e))
+        };
+        match s {
+            "global" => Ok(FirewallRulesScope::Global),
+            "replica_nodes" => Ok(FirewallRulesScope::ReplicaNodes),
+            _ => {
+                if let Some(node_id) = s.strip_prefix("node_") {
+                    Ok(FirewallRulesScope::Node(NodeId::new(parse_principal(
+                        node_id,
+                    )?)))
+                } else if let Some(subnet_id) = s.strip_prefix("subnet_") {
+                    Ok(FirewallRulesScope::Subnet(SubnetId::new(parse_principal(
+                        subnet_id,
+                    )?)))
+                } else {
+                    Err(format!("invalid firewall rules scope: {}", s))
+                }
+            }
+        }
+    }
+}
+
+/// Makes a key for the `FirewallRuleSet` of the given scope.
+pub fn make_firewall_rules_record_key(scope: &FirewallRulesScope) -> String {
+    format!("{}{}", FIREWALL_RULES_RECORD_KEY_PREFIX, scope)
+}
+
 pub fn make_provisional_whitelist_record_key() -> String {
     "provisional_whitelist".to_string()
 }
@@ -103,6 +166,11 @@ pub fn make_node_record_key(node_id: NodeId) -> String {
     format!("{}{}", NODE_RECORD_KEY_PREFIX, node_id.get())
 }
 
+/// Makes a key for the `NodeUpgradeStatusRecord` reported by a node.
+pub fn make_node_upgrade_status_record_key(node_id: NodeId) -> String {
+    format!("{}{}", NODE_UPGRADE_STATUS_RECORD_KEY_PREFIX, node_id.get())
+}
+
 /// Makes a key for a DataCenterRecord registry entry.
 pub fn make_data_center_record_key(dc_id: &str) -> String {
     format!("{}{}", DATA_CENTER_KEY_PREFIX, dc_id)
@@ -255,4 +323,25 @@ mod tests {
         let parsed = maybe_parse_crypto_threshold_signing_pubkey_key(&wrong_key);
         assert!(parsed.is_none());
     }
+
+    #[test]
+    fn should_roundtrip_firewall_rules_scope() {
+        for scope in &[
+            FirewallRulesScope::Node(NodeId::from(PrincipalId::new_node_test_id(42))),
+            FirewallRulesScope::Subnet(SubnetId::from(PrincipalId::new_subnet_test_id(7))),
+            FirewallRulesScope::ReplicaNodes,
+            FirewallRulesScope::Global,
+        ] {
+            let key = make_firewall_rules_record_key(scope);
+            let suffix = key.strip_prefix(FIREWALL_RULES_RECORD_KEY_PREFIX).unwrap();
+            assert_eq!(FirewallRulesScope::from_str(suffix), Ok(scope.clone()));
+        }
+    }
+
+    #[test]
+    fn should_fail_parsing_invalid_firewall_rules_scope() {
+        assert!(FirewallRulesScope::from_str("subnet").is_err());
+        assert!(FirewallRulesScope::from_str("node_not-a-principal").is_err());
+        assert!(FirewallRulesScope::from_str("everything").is_err());
+    }
 }
@@ -1,5 +1,6 @@
 mod proto;
 
+use candid::CandidType;
 use ic_base_types::{CanisterId, PrincipalId, SubnetId};
 use ic_protobuf::proxy::ProxyDecodeError;
 use serde::{Deserialize, Serialize};
@@ -27,7 +28,9 @@ fn canister_id_into_u128(canister_id: CanisterId) -> u128 {
     canister_id_into_u64(canister_id) as u128
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, CandidType, Serialize, Deserialize,
+)]
 pub struct CanisterIdRange {
     pub start: CanisterId,
     pub end: CanisterId,
@@ -115,6 +118,47 @@ impl CanisterIdRanges {
         sum
     }
 
+    /// Returns true if every canister ID in `range` falls into one of the
+    /// canister ID ranges.
+    pub fn contains_range(&self, range: &CanisterIdRange) -> bool {
+        // The ranges are sorted and disjoint, but neighboring ranges are not
+        // necessarily merged, so `range` may be covered by several of them.
+        let mut next = canister_id_into_u128(range.start);
+        let end = canister_id_into_u128(range.end);
+        for r in self.0.iter() {
+            if canister_id_into_u128(r.end) < next {
+                continue;
+            }
+            if canister_id_into_u128(r.start) > next {
+                return false;
+            }
+            if canister_id_into_u128(r.end) >= end {
+                return true;
+            }
+            next = canister_id_into_u128(r.end) + 1;
+        }
+        false
+    }
+
+    /// Returns the number of canister IDs in the canister ID ranges that also
+    /// fall into `range`.
+    pub fn count_in_range(&self, range: &CanisterIdRange) -> u128 {
+        let start = canister_id_into_u128(range.start);
+        let end = canister_id_into_u128(range.end);
+        self.0
+            .iter()
+            .map(|r| {
+                let r_start = canister_id_into_u128(r.start).max(start);
+                let r_end = canister_id_into_u128(r.end).min(end);
+                if r_start <= r_end {
+                    1 + r_end - r_start
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
+
     /// Given location 'loc' in the range [0, total_count()), select a Canister
     /// ID that falls into the Canister ID ranges.
     pub fn locate(&self, loc: u64) -> CanisterId {
@@ -484,6 +528,67 @@ impl CanisterMigrations {
     pub fn lookup(&self, canister_id: CanisterId) -> Option<Vec<SubnetId>> {
         lookup_in_ranges(&self.0, canister_id)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&CanisterIdRange, &Vec<SubnetId>)> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the given ranges to the canister migrations, with a migration
+    /// trace from `source` to `destination`.
+    ///
+    /// Returns an error if any of the ranges overlaps with a range that is
+    /// already being migrated or if the result is not well-formed. If this
+    /// function returns an error, the canister migrations are not modified.
+    pub fn insert_ranges(
+        &mut self,
+        ranges: CanisterIdRanges,
+        source: SubnetId,
+        destination: SubnetId,
+    ) -> Result<(), WellFormedError> {
+        let mut map = self.0.clone();
+        for range in ranges.iter() {
+            if map.insert(*range, vec![source, destination]).is_some() {
+                return Err(WellFormedError::CanisterMigrationsNotDisjoint(format!(
+                    "range {:?} is already being migrated",
+                    range
+                )));
+            }
+        }
+        let canister_migrations = Self::try_from(map)?;
+        *self = canister_migrations;
+        Ok(())
+    }
+
+    /// Removes the given ranges from the canister migrations.
+    ///
+    /// Returns an error if any of the ranges is not an entry of the canister
+    /// migrations with exactly the given migration `trace`. If this function
+    /// returns an error, the canister migrations are not modified.
+    pub fn remove_ranges(
+        &mut self,
+        ranges: CanisterIdRanges,
+        trace: Vec<SubnetId>,
+    ) -> Result<(), String> {
+        let mut map = self.0.clone();
+        for range in ranges.iter() {
+            match map.remove(range) {
+                Some(existing) if existing == trace => {}
+                Some(existing) => {
+                    return Err(format!(
+                        "range {:?} has migration trace {:?}, expected {:?}",
+                        range, existing, trace
+                    ))
+                }
+                None => return Err(format!("range {:?} is not being migrated", range)),
+            }
+        }
+        self.0 = map;
+        Ok(())
+    }
 }
 
 fn lookup_in_ranges<V: Clone>(
@@ -371,3 +371,101 @@ fn valid_canister_migrations() {
     assert_eq!(trace(&t5), rt.lookup(CanisterId::from(0x90000)));
     assert_eq!(None, rt.lookup(CanisterId::from(0xffffffffffffffff)));
 }
+
+#[test]
+fn canister_id_ranges_contains_range() {
+    let ranges = new_canister_id_ranges(vec![(0x100, 0x1ff), (0x200, 0x2ff), (0x400, 0x4ff)]);
+    let range = |start: u64, end: u64| CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    };
+
+    assert!(ranges.contains_range(&range(0x100, 0x1ff)));
+    assert!(ranges.contains_range(&range(0x150, 0x250)));
+    assert!(ranges.contains_range(&range(0x100, 0x2ff)));
+    assert!(ranges.contains_range(&range(0x400, 0x400)));
+    assert!(!ranges.contains_range(&range(0x0, 0x100)));
+    assert!(!ranges.contains_range(&range(0x250, 0x400)));
+    assert!(!ranges.contains_range(&range(0x4ff, 0x500)));
+}
+
+#[test]
+fn canister_id_ranges_count_in_range() {
+    let ranges = new_canister_id_ranges(vec![(0x100, 0x1ff), (0x200, 0x2ff), (0x400, 0x4ff)]);
+    let range = |start: u64, end: u64| CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    };
+
+    assert_eq!(0x100, ranges.count_in_range(&range(0x100, 0x1ff)));
+    assert_eq!(0x100, ranges.count_in_range(&range(0x180, 0x27f)));
+    assert_eq!(0x101, ranges.count_in_range(&range(0x280, 0x400)));
+    assert_eq!(0x300, ranges.count_in_range(&range(0x0, u64::MAX)));
+    assert_eq!(0, ranges.count_in_range(&range(0x300, 0x3ff)));
+    assert_eq!(0, ranges.count_in_range(&range(0x500, u64::MAX)));
+}
+
+#[test]
+fn canister_migrations_insert_and_remove_ranges() {
+    let mut migrations = new_canister_migrations(vec![((0x100, 0x1ff), vec![1, 2])]);
+    let trace = |trace: &[u64]| -> Vec<SubnetId> {
+        trace.iter().map(|&subnet| subnet_test_id(subnet)).collect()
+    };
+
+    // Overlapping and invalid insertions leave the migrations unchanged.
+    let before = migrations.clone();
+    assert_matches!(
+        migrations.insert_ranges(
+            new_canister_id_ranges(vec![(0x100, 0x1ff)]),
+            subnet_test_id(1),
+            subnet_test_id(3)
+        ),
+        Err(WellFormedError::CanisterMigrationsNotDisjoint(_))
+    );
+    assert_matches!(
+        migrations.insert_ranges(
+            new_canister_id_ranges(vec![(0x300, 0x3ff), (0x180, 0x280)]),
+            subnet_test_id(1),
+            subnet_test_id(3)
+        ),
+        Err(WellFormedError::CanisterMigrationsNotDisjoint(_))
+    );
+    assert_matches!(
+        migrations.insert_ranges(
+            new_canister_id_ranges(vec![(0x300, 0x3ff)]),
+            subnet_test_id(1),
+            subnet_test_id(1)
+        ),
+        Err(WellFormedError::CanisterMigrationsInvalidTrace(_))
+    );
+    assert_eq!(before, migrations);
+
+    migrations
+        .insert_ranges(
+            new_canister_id_ranges(vec![(0x300, 0x3ff)]),
+            subnet_test_id(1),
+            subnet_test_id(3),
+        )
+        .unwrap();
+    assert_eq!(
+        Some(trace(&[1, 3])),
+        migrations.lookup(CanisterId::from(0x350))
+    );
+
+    // Ranges can only be removed with their exact migration trace.
+    assert!(migrations
+        .remove_ranges(new_canister_id_ranges(vec![(0x300, 0x3ff)]), trace(&[1, 2]))
+        .is_err());
+    assert!(migrations
+        .remove_ranges(new_canister_id_ranges(vec![(0x300, 0x37f)]), trace(&[1, 3]))
+        .is_err());
+    migrations
+        .remove_ranges(new_canister_id_ranges(vec![(0x300, 0x3ff)]), trace(&[1, 3]))
+        .unwrap();
+    assert_eq!(before, migrations);
+
+    migrations
+        .remove_ranges(new_canister_id_ranges(vec![(0x100, 0x1ff)]), trace(&[1, 2]))
+        .unwrap();
+    assert!(migrations.is_empty());
+}
@@ -234,6 +234,17 @@ pub fn serialize_get_changes_since_request(version: u64) -> Result<Vec<u8>, Erro
     }
 }
 
+/// Serializes a request for a get_certified_snapshot() request to the registry
+/// canister.
+pub fn serialize_get_certified_snapshot_request(start_key: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let request = pb::v1::RegistryGetCertifiedSnapshotRequest { start_key };
+    let mut buf = Vec::new();
+    match request.encode(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
 /// Deserializes the response obtained from the registry canister for a
 /// get_changes_since() call, from protobuf.
 pub fn deserialize_get_changes_since_response(
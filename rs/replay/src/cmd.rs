@@ -55,6 +55,9 @@ pub enum SubCommand {
     /// Restore from the backup.
     RestoreFromBackup(RestoreFromBackupCmd),
 
+    /// Load a consensus pool snapshot and re-run consensus on it offline.
+    RerunConsensus(RerunConsensusCmd),
+
     /// The replay will add a test Neuron to the Governance canister
     /// and the corresponding account in the ledger.
     WithNeuronForTests(WithNeuronCmd),
@@ -104,6 +107,30 @@ pub struct RestoreFromBackupCmd {
     pub replica_version: String,
     /// Height from which the restoration should happen
     pub start_height: u64,
+    /// Directory to write the canonical state tree diffs to. No diffs are
+    /// written if it isn't set.
+    #[clap(long)]
+    pub diff_dir: Option<PathBuf>,
+    /// Write a diff every N heights.
+    #[clap(long, requires = "diff-dir")]
+    pub diff_every: Option<u64>,
+    /// Write a diff at every height whose block contains an ingress message
+    /// addressed to this canister.
+    #[clap(long, requires = "diff-dir")]
+    pub diff_canister: Option<CanisterId>,
+    /// Write a diff at the height whose block contains the ingress message
+    /// with this ID (in hex).
+    #[clap(long, requires = "diff-dir")]
+    pub diff_ingress: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct RerunConsensusCmd {
+    /// Snapshot file written by `ic-consensus-pool-util export-snapshot`.
+    pub snapshot: PathBuf,
+    /// Maximum number of times consensus is invoked on the loaded pool.
+    #[clap(long, default_value = "100")]
+    pub rounds: u64,
 }
 
 #[derive(Parser)]
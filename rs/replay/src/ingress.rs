@@ -27,7 +27,10 @@ use ic_registry_transport::{
     pb::v1::{registry_mutation, Precondition, RegistryMutation},
     serialize_atomic_mutate_request,
 };
-use ic_types::{messages::SignedIngress, CanisterId, PrincipalId, SubnetId, Time};
+use ic_types::{
+    crypto::threshold_sig::ThresholdSigPublicKey, messages::SignedIngress, CanisterId, PrincipalId,
+    SubnetId, Time,
+};
 use ledger_canister::{AccountIdentifier, Memo, SendArgs, Tokens};
 use prost::Message;
 use std::convert::TryFrom;
@@ -78,14 +81,25 @@ fn make_signed_ingress(
         .map_err(|err| format!("Error converting to SignedIngress: {:?}", err))
 }
 
-fn agent_with_principal_as_sender(principal: &PrincipalId) -> Agent {
+/// Returns an agent that constructs ingress messages on behalf of `principal`.
+/// It is never used to talk to a replica, but like every agent it trusts only
+/// certificates signed with `root_key`.
+fn agent_with_principal_as_sender(
+    principal: &PrincipalId,
+    root_key: ThresholdSigPublicKey,
+) -> Agent {
     Agent::new(
         url::Url::parse("http://localhost").unwrap(),
         Sender::PrincipalId(*principal),
     )
+    .with_root_key(root_key)
 }
 
-pub fn cmd_add_neuron(time: Time, cmd: &WithNeuronCmd) -> Result<Vec<SignedIngress>, String> {
+pub fn cmd_add_neuron(
+    time: Time,
+    cmd: &WithNeuronCmd,
+    root_key: ThresholdSigPublicKey,
+) -> Result<Vec<SignedIngress>, String> {
     let mut msgs = vec![];
 
     let controller = cmd.neuron_controller;
@@ -104,7 +118,7 @@ pub fn cmd_add_neuron(time: Time, cmd: &WithNeuronCmd) -> Result<Vec<SignedIngre
     })
     .expect("Couldn't candid-encode ledger transfer");
 
-    let governance_agent = agent_with_principal_as_sender(&GOVERNANCE_CANISTER_ID.get());
+    let governance_agent = agent_with_principal_as_sender(&GOVERNANCE_CANISTER_ID.get(), root_key);
     msgs.push(
         make_signed_ingress(
             &governance_agent,
@@ -128,7 +142,7 @@ pub fn cmd_add_neuron(time: Time, cmd: &WithNeuronCmd) -> Result<Vec<SignedIngre
     })
     .expect("Couldn't candid-encode neuron claim");
 
-    let user_agent = &agent_with_principal_as_sender(&cmd.neuron_controller);
+    let user_agent = &agent_with_principal_as_sender(&cmd.neuron_controller, root_key);
     msgs.push(
         make_signed_ingress(
             user_agent,
@@ -146,6 +160,7 @@ pub fn cmd_add_neuron(time: Time, cmd: &WithNeuronCmd) -> Result<Vec<SignedIngre
 pub fn cmd_make_trusted_neurons_follow_neuron(
     time: Time,
     cmd: &WithTrustedNeuronsFollowingNeuronCmd,
+    root_key: ThresholdSigPublicKey,
 ) -> Result<Vec<SignedIngress>, String> {
     let mut msgs = Vec::new();
 
@@ -162,7 +177,7 @@ pub fn cmd_make_trusted_neurons_follow_neuron(
             })),
         })
         .expect("Couldn't encode payload for manage neuron command");
-        let user_agent = &agent_with_principal_as_sender(&principal);
+        let user_agent = &agent_with_principal_as_sender(&principal, root_key);
         msgs.push(
             make_signed_ingress(
                 user_agent,
@@ -180,6 +195,7 @@ pub fn cmd_make_trusted_neurons_follow_neuron(
 pub fn cmd_add_ledger_account(
     time: Time,
     cmd: &WithLedgerAccountCmd,
+    root_key: ThresholdSigPublicKey,
 ) -> Result<Vec<SignedIngress>, String> {
     let memo = 1234_u64;
 
@@ -193,7 +209,7 @@ pub fn cmd_add_ledger_account(
     })
     .expect("Couldn't candid-encode ledger transfer");
 
-    let governance_agent = agent_with_principal_as_sender(&GOVERNANCE_CANISTER_ID.get());
+    let governance_agent = agent_with_principal_as_sender(&GOVERNANCE_CANISTER_ID.get(), root_key);
 
     Ok(vec![make_signed_ingress(
         &governance_agent,
@@ -1,7 +1,9 @@
 //! The replay tool is to help recover a broken subnet by replaying past blocks
 //! and create a checkpoint of the latest state, which can then be used to
 //! create recovery CatchUpPackage. It is also used to replay the artifacts
-//! stored as backup, to recover a state at any height.
+//! stored as backup, to recover a state at any height, and to re-run
+//! consensus offline on a consensus pool snapshot exported with
+//! `ic-consensus-pool-util`.
 //!
 //! It requires the same replica config file as used on the replica. It will use
 //! it to locate the relevant consensus pool, state, etc. according to the
@@ -17,6 +19,7 @@
 use crate::cmd::{ReplayToolArgs, SubCommand};
 use crate::ingress::*;
 use crate::player::Player;
+use crate::state_diff::StateDiffWriter;
 
 use ic_canister_client::{Agent, Sender};
 use ic_config::{Config, ConfigSource};
@@ -28,6 +31,7 @@ mod backup;
 pub mod cmd;
 pub mod ingress;
 pub mod player;
+pub mod state_diff;
 
 /// Replays the past blocks and creates a checkpoint of the latest state.
 /// # An example of how to set the arguments
@@ -53,6 +57,10 @@ pub mod player;
 ///         replica_version: "8b91ab7c6807a6e842d9e3bb943eadfaf856e082d1094c07852aef09f8cd0c93"
 ///             .to_string(),
 ///         start_height: 0,
+///         diff_dir: None,
+///         diff_every: None,
+///         diff_canister: None,
+///         diff_ingress: None,
 ///     })),
 /// };
 /// // Once the arguments are set well, the local store and spool directories are populated;
@@ -83,6 +91,18 @@ pub fn replay(args: ReplayToolArgs) {
         }
 
         if let Some(SubCommand::RestoreFromBackup(cmd)) = subcmd {
+            let state_diffs = cmd.diff_dir.clone().map(|diff_dir| {
+                StateDiffWriter::new(
+                    diff_dir,
+                    cmd.diff_every,
+                    cmd.diff_canister,
+                    cmd.diff_ingress.as_deref(),
+                )
+                .unwrap_or_else(|err| {
+                    println!("Failed to set up the state diffs:\n  {}", err);
+                    std::process::exit(1);
+                })
+            });
             rt.block_on(async {
                 let mut player = Player::new_for_backup(
                     cfg,
@@ -94,18 +114,31 @@ pub fn replay(args: ReplayToolArgs) {
                     cmd.start_height,
                 )
                 .await
-                .with_replay_target_height(target_height);
+                .with_replay_target_height(target_height)
+                .with_state_diffs(state_diffs);
                 player.restore(cmd.start_height + 1);
             });
             return;
         }
+        if let Some(SubCommand::RerunConsensus(cmd)) = subcmd {
+            rt.block_on(async {
+                Player::new(cfg.clone(), subnet_id).await.rerun_consensus(
+                    &cfg,
+                    &cmd.snapshot,
+                    cmd.rounds,
+                );
+            });
+            return;
+        }
         let extra = move |player: &Player, time| {
             // Use a dummy URL here because we don't send any outgoing ingress.
             // The agent is only used to construct ingress messages.
+            let root_key = player.get_root_public_key();
             let agent = &Agent::new(
                 url::Url::parse("http://localhost").unwrap(),
                 Sender::PrincipalId(canister_caller_id.into()),
-            );
+            )
+            .with_root_key(root_key);
             match subcmd {
                 Some(SubCommand::SetRecoveryCup(cmd)) => {
                     vec![cmd_set_recovery_cup(agent, player, cmd, time).unwrap()]
@@ -123,12 +156,14 @@ pub fn replay(args: ReplayToolArgs) {
                         Vec::new()
                     }
                 }
-                Some(SubCommand::WithNeuronForTests(cmd)) => cmd_add_neuron(time, cmd).unwrap(),
+                Some(SubCommand::WithNeuronForTests(cmd)) => {
+                    cmd_add_neuron(time, cmd, root_key).unwrap()
+                }
                 Some(SubCommand::WithLedgerAccountForTests(cmd)) => {
-                    cmd_add_ledger_account(time, cmd).unwrap()
+                    cmd_add_ledger_account(time, cmd, root_key).unwrap()
                 }
                 Some(SubCommand::WithTrustedNeuronsFollowingNeuronForTests(cmd)) => {
-                    cmd_make_trusted_neurons_follow_neuron(time, cmd).unwrap()
+                    cmd_make_trusted_neurons_follow_neuron(time, cmd, root_key).unwrap()
                 }
                 _ => Vec::new(),
             }
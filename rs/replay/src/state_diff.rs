@@ -0,0 +1,372 @@
+//! Records diffs of the canonical state tree at selected heights of a
+//! restoration from backup. The diffs are computed the same way as by the
+//! `cdiff` command of `state_tool`.
+
+use ic_crypto_tree_hash::HashTree;
+use ic_replicated_state::ReplicatedState;
+use ic_state_manager::{
+    stream_encoding::decode_stream_slice,
+    tree_diff::{diff, PrettyPrintedChanges},
+    tree_hash::hash_state,
+};
+use ic_types::{
+    batch::{IngressPayload, XNetPayload},
+    consensus::Block,
+    messages::MessageId,
+    CanisterId, Height,
+};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/// Writes the canonical tree diffs to `<output_dir>/<height>.diff`.
+///
+/// Every diff describes the changes between the last recorded state and the
+/// state at the given height. Heights at which a block touching the canister
+/// or containing the ingress message of interest is executed are diffed
+/// against the state right before that block (see
+/// [`StateDiffWriter::block_matches`]).
+pub struct StateDiffWriter {
+    output_dir: PathBuf,
+    every_n_heights: Option<u64>,
+    canister_id: Option<CanisterId>,
+    message_id: Option<MessageId>,
+    /// The height and the canonical tree of the last recorded state.
+    base: Option<(Height, HashTree)>,
+}
+
+impl StateDiffWriter {
+    /// Creates a writer storing diffs in `output_dir`, which is created if it
+    /// doesn't exist. `message_id` is the hex-encoded ID of an ingress
+    /// message, optionally prefixed by `0x`.
+    pub fn new(
+        output_dir: PathBuf,
+        every_n_heights: Option<u64>,
+        canister_id: Option<CanisterId>,
+        message_id: Option<&str>,
+    ) -> Result<Self, String> {
+        if every_n_heights == Some(0) {
+            return Err("the number of heights between diffs must be positive".to_string());
+        }
+        let message_id = message_id
+            .map(|id| {
+                let bytes = hex::decode(id.trim_start_matches("0x"))
+                    .map_err(|e| format!("failed to decode message id {}: {}", id, e))?;
+                MessageId::try_from(&bytes[..])
+                    .map_err(|e| format!("invalid message id {}: {}", id, e))
+            })
+            .transpose()?;
+        std::fs::create_dir_all(&output_dir)
+            .map_err(|e| format!("failed to create directory {}: {}", output_dir.display(), e))?;
+        Ok(Self {
+            output_dir,
+            every_n_heights,
+            canister_id,
+            message_id,
+            base: None,
+        })
+    }
+
+    /// Returns the height of the last recorded state, if any.
+    pub fn base_height(&self) -> Option<Height> {
+        self.base.as_ref().map(|(height, _)| *height)
+    }
+
+    /// Returns true if a diff is due at `height` regardless of its block.
+    pub fn is_periodic_stop(&self, height: Height) -> bool {
+        self.every_n_heights
+            .map_or(false, |n| height.get() % n == 0)
+    }
+
+    /// Returns true if `block` contains the ingress message of interest, or an
+    /// ingress message or an XNet message addressed to the canister of
+    /// interest.
+    ///
+    /// Messages that canisters on the same subnet send to the canister of
+    /// interest are not part of any block, so a block that affects the
+    /// canister only through such messages is not matched. Its changes show
+    /// up in the next diff instead.
+    pub fn block_matches(&self, block: &Block) -> bool {
+        if self.canister_id.is_none() && self.message_id.is_none() {
+            return false;
+        }
+        let payload = block.payload.as_ref();
+        if payload.is_summary() {
+            return false;
+        }
+        let batch = &payload.as_data().batch;
+        if let Some(message_id) = &self.message_id {
+            if batch
+                .ingress
+                .message_ids()
+                .iter()
+                .any(|id| &id.message_id == message_id)
+            {
+                return true;
+            }
+        }
+        match self.canister_id {
+            Some(canister_id) => {
+                ingress_addressed_to(&batch.ingress, canister_id)
+                    || xnet_addressed_to(&batch.xnet, canister_id)
+            }
+            None => false,
+        }
+    }
+
+    /// Records `state` at `height` as the base of the next diff.
+    pub fn record(&mut self, height: Height, state: &ReplicatedState) {
+        self.base = Some((height, hash_state(state)));
+    }
+
+    /// Writes the diff between the last recorded state and `state` at
+    /// `height`, then records `state` as the base of the next diff.
+    pub fn write_diff(&mut self, height: Height, state: &ReplicatedState) -> Result<(), String> {
+        let tree = hash_state(state);
+        let (base_height, base_tree) = self
+            .base
+            .take()
+            .ok_or_else(|| format!("no base state recorded before height {}", height))?;
+        let changes = diff(&base_tree, &tree);
+        let path = self.output_dir.join(format!("{}.diff", height));
+        std::fs::write(
+            &path,
+            format!(
+                "Changes from height {} to height {}:\n{}",
+                base_height,
+                height,
+                PrettyPrintedChanges(&changes)
+            ),
+        )
+        .map_err(|e| format!("failed to write file {}: {}", path.display(), e))?;
+        println!(
+            "Wrote the state diff from height {} to height {} to {}",
+            base_height,
+            height,
+            path.display()
+        );
+        self.base = Some((height, tree));
+        Ok(())
+    }
+}
+
+/// Returns true if `ingress` contains a message addressed to `canister_id`.
+fn ingress_addressed_to(ingress: &IngressPayload, canister_id: CanisterId) -> bool {
+    (0..ingress.message_count()).any(|i| {
+        ingress
+            .get(i)
+            .map(|(_, msg)| msg.canister_id() == canister_id)
+            .unwrap_or(false)
+    })
+}
+
+/// Returns true if a stream slice in `xnet` contains a request or a response
+/// addressed to `canister_id`.
+fn xnet_addressed_to(xnet: &XNetPayload, canister_id: CanisterId) -> bool {
+    xnet.stream_slices.values().any(|certified_slice| {
+        // The slices of a finalized block were validated, so they decode.
+        match decode_stream_slice(&certified_slice.payload) {
+            Ok((_, slice)) => slice.messages().map_or(false, |messages| {
+                messages
+                    .iter()
+                    .any(|(_, msg)| msg.receiver() == canister_id)
+            }),
+            Err(_) => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_registry_subnet_type::SubnetType;
+    use ic_replicated_state::{testing::ReplicatedStateTesting, Stream};
+    use ic_state_manager::stream_encoding::{encode_stream_slice, encode_tree};
+    use ic_test_utilities::{
+        consensus::fake::Fake,
+        state::get_running_canister,
+        types::{
+            ids::{canister_test_id, subnet_test_id},
+            messages::{RequestBuilder, SignedIngressBuilder},
+        },
+    };
+    use ic_types::{
+        batch::{BatchPayload, ValidationContext},
+        consensus::{
+            certification::{Certification, CertificationContent},
+            dkg::Dealings,
+            BlockPayload, DataPayload, Payload, Rank,
+        },
+        crypto::{CryptoHash, CryptoHashOf, Signed},
+        messages::SignedIngress,
+        signature::ThresholdSignature,
+        time::UNIX_EPOCH,
+        xnet::{CertifiedStreamSlice, StreamIndex, StreamIndexedQueue},
+        CryptoHashOfPartialState, RegistryVersion,
+    };
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn writer(canister_id: Option<CanisterId>, message_id: Option<&str>) -> StateDiffWriter {
+        let dir = TempDir::new().unwrap();
+        StateDiffWriter::new(dir.path().join("diffs"), None, canister_id, message_id).unwrap()
+    }
+
+    fn block_with(ingress: Vec<SignedIngress>, xnet: XNetPayload) -> Block {
+        Block::new(
+            CryptoHashOf::from(CryptoHash(vec![])),
+            Payload::new(
+                ic_crypto::crypto_hash,
+                BlockPayload::Data(DataPayload {
+                    batch: BatchPayload {
+                        ingress: IngressPayload::from(ingress),
+                        xnet,
+                        ..BatchPayload::default()
+                    },
+                    dealings: Dealings::new_empty(Height::from(0)),
+                    ecdsa: None,
+                }),
+            ),
+            Height::from(1),
+            Rank(0),
+            ValidationContext {
+                registry_version: RegistryVersion::from(1),
+                certified_height: Height::from(0),
+                time: UNIX_EPOCH,
+            },
+        )
+    }
+
+    /// Builds an XNet payload with a stream slice from another subnet that
+    /// carries a request to `receiver`.
+    fn xnet_payload_with_request_to(receiver: CanisterId) -> XNetPayload {
+        let remote_subnet = subnet_test_id(2);
+        let own_subnet = subnet_test_id(1);
+        let mut messages = StreamIndexedQueue::default();
+        messages.push(
+            RequestBuilder::new()
+                .sender(canister_test_id(100))
+                .receiver(receiver)
+                .build()
+                .into(),
+        );
+        let mut remote_state = ReplicatedState::new_rooted_at(
+            remote_subnet,
+            SubnetType::Application,
+            "NOT_USED".into(),
+        );
+        remote_state.modify_streams(|streams| {
+            streams.insert(own_subnet, Stream::new(messages, StreamIndex::new(0)));
+        });
+        let (tree, _) = encode_stream_slice(
+            &remote_state,
+            own_subnet,
+            StreamIndex::new(0),
+            StreamIndex::new(1),
+            None,
+        );
+        let slice = CertifiedStreamSlice {
+            payload: encode_tree(tree),
+            merkle_proof: vec![],
+            certification: Certification {
+                height: Height::from(1),
+                signed: Signed {
+                    signature: ThresholdSignature::fake(),
+                    content: CertificationContent::new(CryptoHashOfPartialState::from(CryptoHash(
+                        vec![],
+                    ))),
+                },
+            },
+        };
+        XNetPayload {
+            stream_slices: vec![(remote_subnet, slice)]
+                .into_iter()
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn periodic_stops_are_multiples_of_the_interval() {
+        let dir = TempDir::new().unwrap();
+        let writer = StateDiffWriter::new(dir.path().to_path_buf(), Some(3), None, None).unwrap();
+        assert!(writer.is_periodic_stop(Height::from(0)));
+        assert!(!writer.is_periodic_stop(Height::from(1)));
+        assert!(!writer.is_periodic_stop(Height::from(2)));
+        assert!(writer.is_periodic_stop(Height::from(3)));
+        assert!(writer.is_periodic_stop(Height::from(6)));
+
+        let writer = StateDiffWriter::new(dir.path().to_path_buf(), None, None, None).unwrap();
+        assert!(!writer.is_periodic_stop(Height::from(3)));
+
+        assert!(StateDiffWriter::new(dir.path().to_path_buf(), Some(0), None, None).is_err());
+    }
+
+    #[test]
+    fn block_matches_ingress_to_canister() {
+        let canister_id = canister_test_id(1);
+        let ingress = SignedIngressBuilder::new().canister_id(canister_id).build();
+        let block = block_with(vec![ingress], XNetPayload::default());
+
+        assert!(writer(Some(canister_id), None).block_matches(&block));
+        assert!(!writer(Some(canister_test_id(2)), None).block_matches(&block));
+        assert!(!writer(None, None).block_matches(&block));
+    }
+
+    #[test]
+    fn block_matches_ingress_message_id() {
+        let ingress = SignedIngressBuilder::new()
+            .canister_id(canister_test_id(1))
+            .build();
+        let message_id = hex::encode(ingress.id().as_bytes());
+        let other_message_id = hex::encode(
+            SignedIngressBuilder::new()
+                .canister_id(canister_test_id(1))
+                .nonce(42)
+                .build()
+                .id()
+                .as_bytes(),
+        );
+        let block = block_with(vec![ingress], XNetPayload::default());
+
+        assert!(writer(None, Some(&message_id)).block_matches(&block));
+        assert!(writer(None, Some(&format!("0x{}", message_id))).block_matches(&block));
+        assert!(!writer(None, Some(&other_message_id)).block_matches(&block));
+    }
+
+    #[test]
+    fn block_matches_xnet_message_to_canister() {
+        let canister_id = canister_test_id(1);
+        let block = block_with(vec![], xnet_payload_with_request_to(canister_id));
+
+        assert!(writer(Some(canister_id), None).block_matches(&block));
+        assert!(!writer(Some(canister_test_id(2)), None).block_matches(&block));
+    }
+
+    #[test]
+    fn write_diff_writes_changes_since_the_recorded_state() {
+        let dir = TempDir::new().unwrap();
+        let mut writer = StateDiffWriter::new(dir.path().to_path_buf(), None, None, None).unwrap();
+        let mut state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            "NOT_USED".into(),
+        );
+
+        // A diff needs a recorded base state.
+        assert!(writer.write_diff(Height::from(1), &state).is_err());
+
+        writer.record(Height::from(1), &state);
+        assert_eq!(writer.base_height(), Some(Height::from(1)));
+        state.put_canister_state(get_running_canister(canister_test_id(7)));
+        writer.write_diff(Height::from(5), &state).unwrap();
+
+        let diff = std::fs::read_to_string(dir.path().join("5.diff")).unwrap();
+        assert!(diff.starts_with("Changes from height 1 to height 5:\n"));
+        assert!(diff.contains("/canister"));
+        assert_eq!(writer.base_height(), Some(Height::from(5)));
+
+        // The next diff is relative to the state written last.
+        writer.write_diff(Height::from(6), &state).unwrap();
+        let diff = std::fs::read_to_string(dir.path().join("6.diff")).unwrap();
+        assert_eq!(diff, "Changes from height 5 to height 6:\n");
+    }
+}
@@ -10,7 +10,8 @@ use ic_artifact_pool::{
     ensure_persistent_pool_replica_version_compatibility, ingress_pool::IngressPoolImpl,
 };
 use ic_config::{
-    artifact_pool::ArtifactPoolConfig, consensus::ConsensusConfig, transport::TransportConfig,
+    artifact_pool::ArtifactPoolConfig, consensus::ConsensusConfig,
+    ingress_manager::Config as IngressManagerConfig, transport::TransportConfig,
 };
 use ic_consensus::{
     certification,
@@ -90,6 +91,7 @@ pub fn create_networking_stack(
     rt_handle: tokio::runtime::Handle,
     transport_config: TransportConfig,
     consensus_config: ConsensusConfig,
+    ingress_manager_config: IngressManagerConfig,
     malicious_flags: MaliciousFlags,
     node_id: NodeId,
     subnet_id: SubnetId,
@@ -126,6 +128,7 @@ pub fn create_networking_stack(
         Arc::clone(&ingress_sig_crypto) as Arc<_>,
         subnet_id,
         consensus_config,
+        ingress_manager_config,
         log.clone(),
         metrics_registry.clone(),
         Arc::clone(&registry_client),
@@ -187,6 +190,7 @@ fn setup_artifact_manager(
     ingress_sig_crypto: Arc<dyn IngressSigVerifier + Send + Sync>,
     subnet_id: SubnetId,
     consensus_config: ConsensusConfig,
+    ingress_manager_config: IngressManagerConfig,
     replica_logger: ReplicaLogger,
     metrics_registry: MetricsRegistry,
     registry_client: Arc<dyn RegistryClient>,
@@ -256,6 +260,7 @@ fn setup_artifact_manager(
         replica_logger.clone(),
         Arc::clone(&state_manager) as Arc<_>,
         cycles_account_manager,
+        ingress_manager_config,
         malicious_flags.clone(),
     );
     let ingress_manager = Arc::new(ingress_manager);
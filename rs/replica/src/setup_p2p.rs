@@ -242,6 +242,7 @@ pub fn construct_ic_stack(
         rt_handle,
         config.transport,
         config.consensus,
+        config.ingress_manager,
         config.malicious_behaviour.malicious_flags,
         node_id,
         subnet_id,
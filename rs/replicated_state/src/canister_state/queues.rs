@@ -368,6 +368,23 @@ impl CanisterQueues {
             .any(|(_, (_, queue))| queue.num_messages() > 0)
     }
 
+    /// Returns `true` if the input or output queue of any remote canister
+    /// matching `predicate` holds messages or reserved slots.
+    pub(crate) fn has_pending_messages_with<F>(&self, predicate: F) -> bool
+    where
+        F: Fn(&CanisterId) -> bool,
+    {
+        self.canister_queues
+            .iter()
+            .filter(|(canister_id, _)| predicate(canister_id))
+            .any(|(_, (input_queue, output_queue))| {
+                input_queue.num_messages() > 0
+                    || input_queue.reserved_slots() > 0
+                    || output_queue.num_messages() > 0
+                    || output_queue.reserved_slots() > 0
+            })
+    }
+
     /// Extracts the next ingress, priority, or normal message (round-robin).
     ///
     /// We define three buckets of queues: messages from canisters on the same
@@ -168,6 +168,19 @@ impl NetworkTopology {
             .map(|(subnet_id, _)| *subnet_id)
             .collect()
     }
+
+    /// Checks whether `canister_id` is part of a canister migration away from
+    /// `subnet_id` that has been prepared, i.e. `subnet_id` is the first (but
+    /// not the last) subnet in its migration trace. Messages to such canisters
+    /// are rejected until the canister is rerouted (or the migration is
+    /// aborted), in order to drain its queues.
+    pub fn is_migration_prepared(&self, canister_id: CanisterId, subnet_id: SubnetId) -> bool {
+        self.canister_migrations
+            .lookup(canister_id)
+            .map_or(false, |trace| {
+                trace.first() == Some(&subnet_id) && trace.last() != Some(&subnet_id)
+            })
+    }
 }
 
 impl From<&NetworkTopology> for pb_metadata::NetworkTopology {
@@ -1071,6 +1084,44 @@ impl IngressHistoryState {
         );
     }
 
+    /// Moves the entries whose status matches `predicate` (together with their
+    /// pruning times) into a new `IngressHistoryState` and returns it.
+    pub fn split_off<F>(&mut self, predicate: F) -> IngressHistoryState
+    where
+        F: Fn(&IngressStatus) -> bool,
+    {
+        let (moved, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = self
+            .statuses
+            .iter()
+            .map(|(id, status)| (id.clone(), Arc::clone(status)))
+            .partition(|(_, status)| predicate(status));
+
+        let mut moved_pruning_times = BTreeMap::<Time, BTreeSet<MessageId>>::new();
+        let mut kept_pruning_times = BTreeMap::<Time, BTreeSet<MessageId>>::new();
+        for (time, messages) in self.pruning_times.iter() {
+            let (moved_messages, kept_messages): (BTreeSet<_>, BTreeSet<_>) = messages
+                .iter()
+                .cloned()
+                .partition(|message_id| moved.contains_key(message_id));
+            if !moved_messages.is_empty() {
+                moved_pruning_times.insert(*time, moved_messages);
+            }
+            if !kept_messages.is_empty() {
+                kept_pruning_times.insert(*time, kept_messages);
+            }
+        }
+
+        self.memory_usage = Self::compute_memory_usage(&kept);
+        self.statuses = Arc::new(kept);
+        self.pruning_times = Arc::new(kept_pruning_times);
+
+        IngressHistoryState {
+            memory_usage: Self::compute_memory_usage(&moved),
+            statuses: Arc::new(moved),
+            pruning_times: Arc::new(moved_pruning_times),
+        }
+    }
+
     /// Returns the memory usage of the statuses in the ingress history. See the
     /// documentation of `IngressStatus` for how the byte size of an individual
     /// `IngressStatus` is computed.
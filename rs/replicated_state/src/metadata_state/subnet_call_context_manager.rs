@@ -1,10 +1,12 @@
 use ic_logger::{info, ReplicaLogger};
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
+    registry::subnet::v1 as pb_subnet,
     state::system_metadata::v1 as pb_metadata,
 };
 use ic_types::{
     canister_http::CanisterHttpRequestContext,
+    crypto::canister_threshold_sig::ThresholdSignatureScheme,
     crypto::threshold_sig::ni_dkg::{id::ni_dkg_target_id, NiDkgTargetId},
     messages::{CallbackId, Request},
     node_id_into_protobuf, node_id_try_from_protobuf, NodeId, RegistryVersion, Time,
@@ -94,6 +96,28 @@ impl SubnetCallContextManager {
                     })
             })
     }
+
+    /// Returns the requests of all pending subnet call contexts.
+    pub fn requests(&self) -> impl Iterator<Item = &Request> {
+        self.setup_initial_dkg_contexts
+            .values()
+            .map(|context| &context.request)
+            .chain(
+                self.sign_with_ecdsa_contexts
+                    .values()
+                    .map(|context| &context.request),
+            )
+            .chain(
+                self.canister_http_request_contexts
+                    .values()
+                    .map(|context| &context.request),
+            )
+            .chain(
+                self.ecdsa_dealings_contexts
+                    .values()
+                    .map(|context| &context.request),
+            )
+    }
 }
 
 impl From<&SubnetCallContextManager> for pb_metadata::SubnetCallContextManager {
@@ -231,6 +255,7 @@ impl TryFrom<pb_metadata::SetupInitialDkgContext> for SetupInitialDkgContext {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SignWithEcdsaContext {
     pub request: Request,
+    pub scheme: ThresholdSignatureScheme,
     pub message_hash: Vec<u8>,
     pub derivation_path: Vec<Vec<u8>>,
     pub pseudo_random_id: [u8; 32],
@@ -241,6 +266,7 @@ impl From<&SignWithEcdsaContext> for pb_metadata::SignWithEcdsaContext {
     fn from(context: &SignWithEcdsaContext) -> Self {
         pb_metadata::SignWithEcdsaContext {
             request: Some((&context.request).into()),
+            scheme: pb_subnet::ThresholdSignatureScheme::from(context.scheme) as i32,
             message_hash: context.message_hash.to_vec(),
             derivation_path_vec: context.derivation_path.clone(),
             pseudo_random_id: context.pseudo_random_id.to_vec(),
@@ -254,7 +280,14 @@ impl TryFrom<pb_metadata::SignWithEcdsaContext> for SignWithEcdsaContext {
     fn try_from(context: pb_metadata::SignWithEcdsaContext) -> Result<Self, Self::Error> {
         let request: Request =
             try_from_option_field(context.request, "SignWithEcdsaContext::request")?;
+        let scheme = pb_subnet::ThresholdSignatureScheme::from_i32(context.scheme)
+            .ok_or(ProxyDecodeError::ValueOutOfRange {
+                typ: "SignWithEcdsaContext::scheme",
+                err: format!("Unknown value: {}", context.scheme),
+            })?
+            .into();
         Ok(SignWithEcdsaContext {
+            scheme,
             message_hash: context.message_hash,
             derivation_path: context.derivation_path_vec,
             request,
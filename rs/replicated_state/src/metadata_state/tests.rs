@@ -103,6 +103,49 @@ fn entries_sorted_lexicographically() {
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn can_split_off_ingress_history_entries() {
+    let mut ingress_history = IngressHistoryState::new();
+    let time = mock_time();
+
+    for i in 0..10u64 {
+        ingress_history.insert(
+            message_test_id(i),
+            IngressStatus::Completed {
+                receiver: canister_test_id(i % 2).get(),
+                user_id: user_test_id(1),
+                result: WasmResult::Reply(vec![]),
+                time,
+            },
+            time,
+        );
+    }
+    let memory_usage = ingress_history.memory_usage();
+
+    let mut split_off =
+        ingress_history.split_off(|status| status.receiver() == Some(canister_test_id(1)));
+
+    assert_eq!(5, ingress_history.len());
+    assert_eq!(5, split_off.len());
+    assert!(ingress_history
+        .statuses()
+        .all(|(_, status)| status.receiver() == Some(canister_test_id(0))));
+    assert!(split_off
+        .statuses()
+        .all(|(_, status)| status.receiver() == Some(canister_test_id(1))));
+    assert_eq!(
+        memory_usage,
+        ingress_history.memory_usage() + split_off.memory_usage()
+    );
+
+    // Both halves retain the pruning times of their own entries.
+    let time = time + MAX_INGRESS_TTL + std::time::Duration::from_secs(10);
+    ingress_history.prune(time);
+    split_off.prune(time);
+    assert!(ingress_history.is_empty());
+    assert!(split_off.is_empty());
+}
+
 #[test]
 fn streams_stats() {
     // Two local canisters, `local_a` and `local_b`.
@@ -17,7 +17,7 @@ use ic_error_types::{ErrorCode, UserError};
 use ic_interfaces::{
     execution_environment::CanisterOutOfCyclesError, messages::CanisterInputMessage,
 };
-use ic_registry_routing_table::RoutingTable;
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges, RoutingTable};
 use ic_registry_subnet_features::BitcoinFeature;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
@@ -436,6 +436,156 @@ impl ReplicatedState {
         self.canister_states = canisters;
     }
 
+    /// Splits off the canisters in `canister_id_ranges` into the state of a
+    /// new subnet `new_subnet_id` and returns it.
+    ///
+    /// The ingress history entries of the moved canisters are moved along
+    /// with them. Streams, subnet queues and subnet call contexts stay with
+    /// `self`, so the moved canisters must not have any messages pending in
+    /// them. The ranges are recorded in the canister migrations of both
+    /// states (with a migration trace from `self` to the new subnet), so that
+    /// Message Routing reroutes any messages still in flight for the moved
+    /// canisters on other subnets. The routing tables of both states are
+    /// updated and the canister ID counters are adjusted so that neither
+    /// subnet generates an ID that was already allocated before the split.
+    ///
+    /// Returns an error and leaves `self` unmodified if `new_subnet_id` is
+    /// this subnet, if the ranges are not all hosted by this subnet or are
+    /// already being migrated, or if any canister in the ranges has messages
+    /// in a stream, the subnet queues or a subnet call context.
+    pub fn split_off(
+        &mut self,
+        new_subnet_id: SubnetId,
+        canister_id_ranges: &CanisterIdRanges,
+    ) -> Result<ReplicatedState, String> {
+        let own_subnet_id = self.metadata.own_subnet_id;
+        if new_subnet_id == own_subnet_id {
+            return Err(format!("cannot split subnet {} into itself", own_subnet_id));
+        }
+        let own_ranges = self
+            .metadata
+            .network_topology
+            .routing_table
+            .ranges(own_subnet_id);
+        if let Some(range) = canister_id_ranges
+            .iter()
+            .find(|range| !own_ranges.contains_range(range))
+        {
+            return Err(format!(
+                "range {:?} is not hosted by subnet {}",
+                range, own_subnet_id
+            ));
+        }
+
+        let in_ranges = |canister_id: &CanisterId| {
+            canister_id_ranges
+                .iter()
+                .any(|range| range.start <= *canister_id && *canister_id <= range.end)
+        };
+        if let Some((subnet_id, _)) = self.metadata.streams.iter().find(|(_, stream)| {
+            stream
+                .messages()
+                .iter()
+                .any(|(_, msg)| in_ranges(&msg.sender()) || in_ranges(&msg.receiver()))
+        }) {
+            return Err(format!(
+                "the stream to subnet {} has messages from or to canisters being split off",
+                subnet_id
+            ));
+        }
+        if self.subnet_queues.has_pending_messages_with(in_ranges) {
+            return Err(
+                "the subnet queues have messages from or to canisters being split off".to_string(),
+            );
+        }
+        if let Some(request) = self
+            .metadata
+            .subnet_call_context_manager
+            .requests()
+            .find(|request| in_ranges(&request.sender))
+        {
+            return Err(format!(
+                "canister {} has a pending subnet call context",
+                request.sender
+            ));
+        }
+
+        let mut routing_table = self
+            .metadata
+            .network_topology
+            .routing_table
+            .as_ref()
+            .clone();
+        for range in canister_id_ranges.iter() {
+            routing_table.assign_range(*range, new_subnet_id);
+        }
+        routing_table.optimize();
+        let mut canister_migrations = self
+            .metadata
+            .network_topology
+            .canister_migrations
+            .as_ref()
+            .clone();
+        canister_migrations
+            .insert_ranges(canister_id_ranges.clone(), own_subnet_id, new_subnet_id)
+            .map_err(|err| format!("failed to update canister migrations: {:?}", err))?;
+
+        // IDs are allocated in order from the subnet's ranges, so the IDs
+        // allocated so far are the ones up to and including the last one.
+        let allocated_id_count =
+            (self.metadata.generated_id_counter as u128).min(own_ranges.total_count()) as u64;
+        let moved_id_count = if allocated_id_count == 0 {
+            0
+        } else {
+            let last_allocated = own_ranges.locate(allocated_id_count - 1);
+            canister_id_ranges
+                .iter()
+                .filter(|range| range.start <= last_allocated)
+                .map(|range| {
+                    own_ranges.count_in_range(&CanisterIdRange {
+                        start: range.start,
+                        end: range.end.min(last_allocated),
+                    })
+                })
+                .sum::<u128>() as u64
+        };
+
+        let (moved_canisters, kept_canisters) = std::mem::take(&mut self.canister_states)
+            .into_iter()
+            .partition(|(canister_id, _)| in_ranges(canister_id));
+        self.canister_states = kept_canisters;
+
+        let mut metadata = SystemMetadata::new(new_subnet_id, self.metadata.own_subnet_type);
+        metadata.ingress_history = self.metadata.ingress_history.split_off(|status| {
+            status
+                .receiver()
+                .map_or(false, |receiver| in_ranges(&receiver))
+        });
+        metadata.generated_id_counter = moved_id_count;
+        metadata.batch_time = self.metadata.batch_time;
+        metadata.own_subnet_features = self.metadata.own_subnet_features;
+        metadata.state_sync_version = self.metadata.state_sync_version;
+        metadata.certification_version = self.metadata.certification_version;
+        metadata.time_of_last_allocation_charge = self.metadata.time_of_last_allocation_charge;
+
+        self.metadata.generated_id_counter -= moved_id_count;
+        self.metadata.network_topology.routing_table = Arc::new(routing_table);
+        self.metadata.network_topology.canister_migrations = Arc::new(canister_migrations);
+        metadata.network_topology = self.metadata.network_topology.clone();
+
+        let mut new_state = ReplicatedState::new_rooted_at(
+            new_subnet_id,
+            self.metadata.own_subnet_type,
+            self.root.clone(),
+        );
+        new_state.metadata = metadata;
+        new_state.canister_states = moved_canisters;
+        new_state.update_stream_responses_size_bytes();
+        self.update_stream_responses_size_bytes();
+
+        Ok(new_state)
+    }
+
     /// Returns an iterator over canister states, ordered by canister ID.
     pub fn canisters_iter(
         &self,
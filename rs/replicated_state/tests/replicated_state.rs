@@ -3,6 +3,8 @@ use ic_btc_types_internal::{
     BitcoinAdapterRequestWrapper, BitcoinAdapterResponse, BitcoinAdapterResponseWrapper,
     GetSuccessorsRequest, GetSuccessorsResponse,
 };
+use ic_ic00_types::HttpMethodType;
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges, RoutingTable};
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::replicated_state::testing::ReplicatedStateTesting;
@@ -12,20 +14,25 @@ use ic_replicated_state::{
     replicated_state::ReplicatedStateMessageRouting, BitcoinStateError, CanisterState,
     InputQueueType, ReplicatedState, SchedulerState, StateError, SystemState,
 };
+use ic_test_utilities::mock_time;
 use ic_test_utilities::state::{
     arb_replicated_state_with_queues, assert_next_eq, get_running_canister, register_callback,
 };
-use ic_test_utilities::types::ids::canister_test_id;
+use ic_test_utilities::types::ids::{canister_test_id, message_test_id};
 use ic_test_utilities::types::{
     ids::{subnet_test_id, user_test_id},
     messages::{RequestBuilder, ResponseBuilder},
 };
 use ic_types::{
+    canister_http::CanisterHttpRequestContext,
+    ingress::IngressStatus,
     messages::{CallbackId, RequestOrResponse, MAX_RESPONSE_COUNT_BYTES},
     CountBytes, Cycles, QueueIndex,
 };
 use proptest::prelude::*;
+use std::convert::TryFrom;
 use std::str::FromStr;
+use std::sync::Arc;
 
 const SUBNET_ID: SubnetId = SubnetId::new(PrincipalId::new(29, [0xfc; 29]));
 const CANISTER_ID: CanisterId = CanisterId::from_u64(42);
@@ -565,7 +572,7 @@ fn validate_response_fails_when_unknown_callback_id() {
             QueueIndex::new(0),
             response.clone(),
             MAX_CANISTER_MEMORY_SIZE,
-            &mut SUBNET_AVAILABLE_MEMORY.clone(),
+            &mut subnet_available_memory,
             SubnetType::Application,
             InputQueueType::RemoteSubnet,
         ),
@@ -635,7 +642,7 @@ fn validate_responses_against_callback_details() {
             QueueIndex::new(0),
             response.clone(),
             MAX_CANISTER_MEMORY_SIZE,
-            &mut SUBNET_AVAILABLE_MEMORY.clone(),
+            &mut subnet_available_memory,
             SubnetType::Application,
             InputQueueType::RemoteSubnet,
         ),
@@ -659,7 +666,7 @@ fn validate_responses_against_callback_details() {
             QueueIndex::new(0),
             response,
             MAX_CANISTER_MEMORY_SIZE,
-            &mut SUBNET_AVAILABLE_MEMORY.clone(),
+            &mut subnet_available_memory,
             SubnetType::Application,
             InputQueueType::RemoteSubnet,
         ),
@@ -680,7 +687,7 @@ fn validate_responses_against_callback_details() {
             QueueIndex::new(0),
             response,
             MAX_CANISTER_MEMORY_SIZE,
-            &mut SUBNET_AVAILABLE_MEMORY.clone(),
+            &mut subnet_available_memory,
             SubnetType::Application,
             InputQueueType::RemoteSubnet,
         ),
@@ -776,6 +783,207 @@ fn push_response_bitcoin_testnet_respects_bitcoin_feature_flag() {
         .unwrap();
 }
 
+#[test]
+fn split_off_partitions_canisters_and_metadata() {
+    let new_subnet_id = subnet_test_id(2);
+    let range = |start: u64, end: u64| CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    };
+    let kept_canister = CanisterId::from(0x10);
+    let moved_canister = CanisterId::from(0x80);
+
+    let mut state =
+        ReplicatedState::new_rooted_at(SUBNET_ID, SubnetType::Application, "unused".into());
+    let mut routing_table = RoutingTable::new();
+    routing_table.insert(range(0, 0xff), SUBNET_ID).unwrap();
+    state.metadata.network_topology.routing_table = Arc::new(routing_table);
+    state.metadata.generated_id_counter = 0x90;
+    for (i, canister_id) in [kept_canister, moved_canister].iter().enumerate() {
+        state.put_canister_state(get_running_canister(*canister_id));
+        state.set_ingress_status(
+            message_test_id(i as u64),
+            IngressStatus::Received {
+                receiver: canister_id.get(),
+                user_id: user_test_id(1),
+                time: mock_time(),
+            },
+        );
+    }
+
+    // The ranges must be hosted by the subnet.
+    assert!(state
+        .clone()
+        .split_off(
+            new_subnet_id,
+            &CanisterIdRanges::try_from(vec![range(0x80, 0x1ff)]).unwrap()
+        )
+        .is_err());
+
+    let split_ranges = CanisterIdRanges::try_from(vec![range(0x80, 0xff)]).unwrap();
+    let new_state = state.split_off(new_subnet_id, &split_ranges).unwrap();
+
+    // Canisters and their ingress history entries are partitioned.
+    assert_eq!(
+        vec![kept_canister],
+        state.canister_states.keys().cloned().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![moved_canister],
+        new_state
+            .canister_states
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, state.metadata.ingress_history.len());
+    assert!(state
+        .metadata
+        .ingress_history
+        .get(&message_test_id(0))
+        .is_some());
+    assert_eq!(1, new_state.metadata.ingress_history.len());
+    assert!(new_state
+        .metadata
+        .ingress_history
+        .get(&message_test_id(1))
+        .is_some());
+
+    // IDs 0x0 through 0x8f were allocated: 0x80 of them on this subnet and 0x10
+    // on the new one.
+    assert_eq!(0x80, state.metadata.generated_id_counter);
+    assert_eq!(0x10, new_state.metadata.generated_id_counter);
+
+    // Both states route the range to the new subnet and record its migration.
+    assert_eq!(new_subnet_id, new_state.metadata.own_subnet_id);
+    for s in &[&state, &new_state] {
+        let network_topology = &s.metadata.network_topology;
+        assert_eq!(
+            Some(SUBNET_ID),
+            network_topology.routing_table.route(kept_canister.get())
+        );
+        assert_eq!(
+            Some(new_subnet_id),
+            network_topology.routing_table.route(moved_canister.get())
+        );
+        assert_eq!(
+            Some(vec![SUBNET_ID, new_subnet_id]),
+            network_topology.canister_migrations.lookup(moved_canister)
+        );
+    }
+}
+
+#[test]
+fn split_off_rejects_canisters_with_pending_subnet_messages() {
+    let new_subnet_id = subnet_test_id(2);
+    let range = |start: u64, end: u64| CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    };
+    let kept_canister = CanisterId::from(0x10);
+    let moved_canister = CanisterId::from(0x80);
+    let remote_canister = CanisterId::from(0x180);
+
+    let mut state =
+        ReplicatedState::new_rooted_at(SUBNET_ID, SubnetType::Application, "unused".into());
+    let mut routing_table = RoutingTable::new();
+    routing_table.insert(range(0, 0xff), SUBNET_ID).unwrap();
+    state.metadata.network_topology.routing_table = Arc::new(routing_table);
+    state.put_canister_state(get_running_canister(kept_canister));
+    state.put_canister_state(get_running_canister(moved_canister));
+    let split_ranges = CanisterIdRanges::try_from(vec![range(0x80, 0xff)]).unwrap();
+    let mut subnet_available_memory = SUBNET_AVAILABLE_MEMORY;
+
+    // A request from a moved canister in a stream.
+    let mut with_stream_message = state.clone();
+    let mut streams = with_stream_message.take_streams();
+    streams.push(
+        subnet_test_id(1),
+        RequestBuilder::default()
+            .sender(moved_canister)
+            .receiver(remote_canister)
+            .build()
+            .into(),
+    );
+    with_stream_message.put_streams(streams);
+    assert!(with_stream_message
+        .split_off(new_subnet_id, &split_ranges)
+        .is_err());
+
+    // A request from a moved canister in the subnet queues.
+    let mut with_subnet_queue_message = state.clone();
+    with_subnet_queue_message
+        .push_input(
+            QueueIndex::from(0),
+            RequestBuilder::default()
+                .sender(moved_canister)
+                .receiver(SUBNET_ID.into())
+                .build()
+                .into(),
+            0.into(),
+            &mut subnet_available_memory,
+        )
+        .unwrap();
+    assert!(with_subnet_queue_message
+        .split_off(new_subnet_id, &split_ranges)
+        .is_err());
+
+    // A subnet call context for a request from a moved canister.
+    let mut with_subnet_call_context = state.clone();
+    with_subnet_call_context
+        .metadata
+        .subnet_call_context_manager
+        .push_http_request(CanisterHttpRequestContext {
+            request: RequestBuilder::default()
+                .sender(moved_canister)
+                .receiver(CanisterId::from(SUBNET_ID))
+                .build(),
+            url: "https://example.com".to_string(),
+            body: None,
+            http_method: HttpMethodType::GET,
+            transform_method_name: None,
+            time: mock_time(),
+        });
+    let unmodified = with_subnet_call_context.clone();
+    assert!(with_subnet_call_context
+        .split_off(new_subnet_id, &split_ranges)
+        .is_err());
+    assert_eq!(unmodified, with_subnet_call_context);
+
+    // Messages from or to canisters that are not moved do not prevent the split.
+    let mut streams = state.take_streams();
+    streams.push(
+        subnet_test_id(1),
+        RequestBuilder::default()
+            .sender(kept_canister)
+            .receiver(remote_canister)
+            .build()
+            .into(),
+    );
+    state.put_streams(streams);
+    state
+        .push_input(
+            QueueIndex::from(0),
+            RequestBuilder::default()
+                .sender(kept_canister)
+                .receiver(SUBNET_ID.into())
+                .build()
+                .into(),
+            0.into(),
+            &mut subnet_available_memory,
+        )
+        .unwrap();
+    let new_state = state.split_off(new_subnet_id, &split_ranges).unwrap();
+    assert_eq!(
+        vec![moved_canister],
+        new_state
+            .canister_states
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+}
+
 proptest! {
     #[test]
     fn peek_and_next_consistent(
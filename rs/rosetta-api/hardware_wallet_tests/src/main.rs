@@ -1,5 +1,5 @@
 pub use ed25519_dalek::Keypair as EdKeypair;
-use ed25519_dalek::Signer;
+use ic_canister_client::Signer;
 use ic_nns_constants::LEDGER_CANISTER_ID;
 use ic_rosetta_api::{
     convert::{from_hex, from_public_key, to_arg, to_hex, to_model_account_identifier},
@@ -56,8 +56,7 @@ pub fn generate_zondax_test(
     keypair: EdKeypair,
     send_args: SendArgs,
 ) -> serde_json::Value {
-    let public_key_der =
-        ic_canister_client::ed25519_public_key_to_der(keypair.public.to_bytes().to_vec());
+    let public_key_der = keypair.sender_pubkey_der();
 
     let public_key =
         models::PublicKey::new(hex::encode(public_key_der.clone()), CurveType::Edwards25519);
@@ -101,7 +100,7 @@ pub fn generate_zondax_test(
     };
 
     let bytes = from_hex(&transaction_payload.hex_bytes).unwrap();
-    let signature_bytes = keypair.sign(&bytes).to_bytes();
+    let signature_bytes = keypair.sign(&bytes).unwrap();
     let hex_bytes = to_hex(&signature_bytes);
 
     let transaction_signature = Signature {
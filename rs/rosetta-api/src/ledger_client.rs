@@ -110,6 +110,7 @@ impl LedgerClient {
                 ic_url.clone(),
                 canister_id,
                 http_client,
+                root_key,
             ));
             Self::verify_store(&blocks, &canister_access).await?;
 
@@ -1183,8 +1184,21 @@ impl CanisterAccess {
     const BLOCKS_BATCH_LEN: u64 = 2000;
     const MAX_BLOCK_QUERIES: usize = 5;
 
-    pub fn new(url: Url, canister_id: CanisterId, client: HttpClient) -> Self {
+    /// Creates an access to the canister `canister_id`, verifying the
+    /// certificates of update calls against `root_key`. Without a root key,
+    /// which is only the case on test networks, the root key reported by the
+    /// replica is used.
+    pub fn new(
+        url: Url,
+        canister_id: CanisterId,
+        client: HttpClient,
+        root_key: Option<ThresholdSigPublicKey>,
+    ) -> Self {
         let agent = Agent::new_with_client(client, url, Sender::Anonymous);
+        let agent = match root_key {
+            Some(root_key) => agent.with_root_key(root_key),
+            None => agent.with_fetched_root_key(),
+        };
         Self {
             agent,
             canister_id,
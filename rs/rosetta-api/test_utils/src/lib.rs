@@ -240,7 +240,7 @@ pub async fn sign_txn(
     keypairs: &[Arc<EdKeypair>],
     payloads: ConstructionPayloadsResponse,
 ) -> Result<ConstructionCombineResponse, RosettaError> {
-    use ed25519_dalek::Signer;
+    use ic_canister_client::Signer;
 
     let mut keypairs_map = HashMap::new();
     for kp in keypairs {
@@ -262,7 +262,7 @@ pub async fn sign_txn(
                 .map(Arc::clone)
                 .unwrap_or_else(|| Arc::clone(&keypairs[0]));
             let bytes = from_hex(&p.hex_bytes).unwrap();
-            let signature_bytes = keypair.sign(&bytes).to_bytes();
+            let signature_bytes = keypair.sign(&bytes).unwrap();
             let hex_bytes = to_hex(&signature_bytes);
             Signature {
                 signing_payload: p,
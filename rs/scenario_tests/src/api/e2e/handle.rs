@@ -35,7 +35,8 @@ impl Node for NodeHandle {
                 self.ic_instance.agent_client.clone(),
                 self.ic_instance.node_api_url(self.id),
                 Sender::from_keypair(&self.ic_instance.caller_principal.0),
-            ),
+            )
+            .with_fetched_root_key(),
         })
     }
 }
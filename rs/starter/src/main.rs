@@ -574,6 +574,7 @@ impl ValidatedConfig {
                 server_port: 0,
                 queue_size: 1024,
             }],
+            ..Default::default()
         });
 
         let hypervisor_config = HypervisorConfig {
@@ -3,6 +3,7 @@
 pub mod checkpoint;
 pub mod labeled_tree_visitor;
 pub mod manifest;
+pub mod split;
 pub mod state_sync;
 pub mod stream_encoding;
 pub mod tree_diff;
@@ -92,6 +93,7 @@ const LABEL_FETCH: &str = "fetch";
 const LABEL_COPY_FILES: &str = "copy_files";
 const LABEL_COPY_CHUNKS: &str = "copy_chunks";
 const LABEL_PREALLOCATE: &str = "preallocate";
+const LABEL_VALUE_DOWNLOADED: &str = "downloaded";
 
 #[derive(Clone)]
 pub struct StateManagerMetrics {
@@ -120,6 +122,7 @@ pub struct ManifestMetrics {
 #[derive(Clone)]
 pub struct StateSyncMetrics {
     state_sync_size: IntCounterVec,
+    state_sync_chunk_bytes: IntCounterVec,
     state_sync_duration: HistogramVec,
     state_sync_step_duration: HistogramVec,
     state_sync_remaining: IntGauge,
@@ -291,6 +294,17 @@ impl StateSyncMetrics {
             state_sync_size.with_label_values(&[*op]);
         }
 
+        let state_sync_chunk_bytes = metrics_registry.int_counter_vec(
+            "state_sync_chunk_bytes_total",
+            "Size of chunks written during all the state syncs by origin ('reused' from local states, 'downloaded' from peers) in bytes.",
+            &["origin"],
+        );
+
+        // Note [Metrics preallocation]
+        for origin in &[LABEL_VALUE_REUSED, LABEL_VALUE_DOWNLOADED] {
+            state_sync_chunk_bytes.with_label_values(&[*origin]);
+        }
+
         let state_sync_remaining = metrics_registry.int_gauge(
             "state_sync_remaining_chunks",
             "Number of chunks not syncronized yet of all active state syncs",
@@ -345,6 +359,7 @@ impl StateSyncMetrics {
 
         Self {
             state_sync_size,
+            state_sync_chunk_bytes,
             state_sync_duration,
             state_sync_step_duration,
             state_sync_remaining,
@@ -367,6 +382,11 @@ struct StateMetadata {
     // None before the values are computed.
     root_hash: Option<CryptoHashOfState>,
     manifest: Option<Manifest>,
+    // Pages modified since the previous checkpoint. We keep them in memory
+    // until the manifest of this checkpoint is computed so that the next
+    // manifest can be computed incrementally even if this one is not ready
+    // yet. They are not persisted.
+    dirty_pages: Option<DirtyPages>,
 }
 
 impl From<&StateMetadata> for pb::StateMetadata {
@@ -392,6 +412,7 @@ impl TryFrom<pb::StateMetadata> for StateMetadata {
                     checkpoint_ref: None,
                     manifest: Some(manifest),
                     root_hash: Some(root_hash),
+                    dirty_pages: None,
                 })
             }
         }
@@ -836,6 +857,54 @@ pub fn get_dirty_pages(state: &ReplicatedState) -> DirtyPages {
         .collect()
 }
 
+/// Extends `dirty_pages` of a new checkpoint with the dirty pages recorded for
+/// the checkpoints after `base_height`, so that the result covers all pages
+/// modified since the checkpoint at `base_height`.
+///
+/// Page maps whose chain of checkpoints does not reach `base_height` (e.g.
+/// canisters created in between) are returned unchanged, which makes the
+/// manifest computation rehash the corresponding files from scratch.
+fn dirty_pages_since(
+    states_metadata: &StatesMetadata,
+    base_height: Height,
+    dirty_pages: DirtyPages,
+) -> DirtyPages {
+    dirty_pages
+        .into_iter()
+        .map(|dirty_page_map| {
+            let mut page_delta_indices = dirty_page_map.page_delta_indices.clone();
+            let mut height = dirty_page_map.height;
+            while height > base_height {
+                let previous = states_metadata
+                    .get(&height)
+                    .and_then(|metadata| metadata.dirty_pages.as_ref())
+                    .and_then(|pages| {
+                        pages
+                            .iter()
+                            .find(|page| page.page_type == dirty_page_map.page_type)
+                    });
+                match previous {
+                    Some(previous) if previous.height < height => {
+                        page_delta_indices.extend_from_slice(&previous.page_delta_indices);
+                        height = previous.height;
+                    }
+                    _ => return dirty_page_map,
+                }
+            }
+            if height != base_height {
+                return dirty_page_map;
+            }
+            page_delta_indices.sort_unstable();
+            page_delta_indices.dedup();
+            DirtyPageMap {
+                height,
+                page_type: dirty_page_map.page_type,
+                page_delta_indices,
+            }
+        })
+        .collect()
+}
+
 /// Strips away the deltas from all page maps of the replicated state.
 /// We execute this procedure before making a checkpoint because we
 /// don't want those deltas to be persisted to TIP as we apply deltas
@@ -1250,7 +1319,7 @@ impl StateManagerImpl {
             id.height,
             id.hash.clone(),
             self.state_layout.clone(),
-            self.latest_manifest(),
+            self.local_manifests(),
             self.metrics.state_sync_metrics.clone(),
             self.own_subnet_type,
             Arc::clone(&self.checkpoint_thread_pool),
@@ -1428,6 +1497,12 @@ impl StateManagerImpl {
             metadata.manifest = Some(manifest);
         }
 
+        // The next manifest is computed relative to this one or a newer one, so
+        // the dirty pages of this and older checkpoints are no longer needed.
+        for (_, metadata) in states.states_metadata.range_mut(..=height) {
+            metadata.dirty_pages = None;
+        }
+
         persist_metadata_or_die(log, metrics, state_layout, &states.states_metadata);
     }
 
@@ -1451,23 +1526,26 @@ impl StateManagerImpl {
         Some((state.take(), certification, hash_tree))
     }
 
-    /// Returns the manifest of the latest checkpoint on disk with its
-    /// checkpoint ref.
-    fn latest_manifest(&self) -> Option<(Manifest, CheckpointRef)> {
-        self.state_layout
+    /// Returns the manifests of all the checkpoints on disk that have one
+    /// computed, together with their checkpoint refs, latest first.
+    fn local_manifests(&self) -> Vec<(Manifest, CheckpointRef)> {
+        let checkpoint_heights = self
+            .state_layout
             .checkpoint_heights()
             .unwrap_or_else(|err| {
                 fatal!(self.log, "Failed to gather checkpoint heights: {:?}", err)
-            })
+            });
+        let states = self.states.read();
+        checkpoint_heights
             .iter()
             .rev()
-            .find_map(|checkpointed_height| {
-                let states = self.states.read();
+            .filter_map(|checkpointed_height| {
                 let metadata = states.states_metadata.get(checkpointed_height)?;
                 let manifest = metadata.manifest.clone()?;
                 let checkpoint_ref = metadata.checkpoint_ref.clone()?;
                 Some((manifest, checkpoint_ref))
             })
+            .collect()
     }
 
     fn compute_certification_metadata(
@@ -1561,6 +1639,7 @@ impl StateManagerImpl {
                     checkpoint_ref: Some(checkpoint_ref),
                     manifest: None,
                     root_hash: None,
+                    dirty_pages: None,
                 },
             );
         }
@@ -1768,6 +1847,7 @@ impl StateManagerImpl {
                 manifest: Some(manifest),
                 checkpoint_ref: Some(self.new_checkpoint_ref(height)),
                 root_hash: Some(root_hash),
+                dirty_pages: None,
             },
         );
 
@@ -2502,7 +2582,7 @@ impl StateManager for StateManagerImpl {
                 });
 
                 if scope == CertificationScope::Full {
-                    let manifest_delta = dirty_pages.and_then(|dirty_memory_pages| {
+                    let manifest_delta = dirty_pages.as_ref().and_then(|dirty_pages| {
                         let (base_manifest, base_height) =
                             states.states_metadata.iter().rev().find_map(
                                 |(base_height, state_metadata)| {
@@ -2514,7 +2594,11 @@ impl StateManager for StateManagerImpl {
                             base_manifest,
                             base_height,
                             target_height: height,
-                            dirty_memory_pages,
+                            dirty_memory_pages: dirty_pages_since(
+                                &states.states_metadata,
+                                base_height,
+                                dirty_pages.clone(),
+                            ),
                         })
                     });
 
@@ -2525,6 +2609,7 @@ impl StateManager for StateManagerImpl {
                             checkpoint_ref: Some(checkpoint_ref.clone()),
                             manifest: None,
                             root_hash: None,
+                            dirty_pages,
                         },
                     );
 
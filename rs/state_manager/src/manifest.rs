@@ -276,18 +276,23 @@ fn build_chunk_table_parallel(
                             Some(mmap) => mmap,
                             None => {
                                 let mmap = Arc::new(
-                                    ScopedMmap::from_path(&file_path)
-                                        .unwrap_or_else(|e| fatal!(log, "failed to mmap file {}: {}", file_path.display(), e)),
+                                    ScopedMmap::from_path(&file_path).unwrap_or_else(|e| {
+                                        fatal!(
+                                            log,
+                                            "failed to mmap file {}: {}",
+                                            file_path.display(),
+                                            e
+                                        )
+                                    }),
                                 );
                                 cache.insert(chunk_info.file_index, Arc::downgrade(&mmap));
                                 mmap
                             }
                         }
                     } else {
-                        Arc::new(
-                            ScopedMmap::from_path(&file_path)
-                                .unwrap_or_else(|e| fatal!(log, "failed to mmap file {}: {}", file_path.display(), e))
-                        )
+                        Arc::new(ScopedMmap::from_path(&file_path).unwrap_or_else(|e| {
+                            fatal!(log, "failed to mmap file {}: {}", file_path.display(), e)
+                        }))
                     };
                     let data = mmap.as_slice();
 
@@ -300,11 +305,17 @@ fn build_chunk_table_parallel(
 
                 chunk_info.hash = match chunk_action {
                     ChunkAction::Recompute => {
-                        metrics.chunk_bytes.with_label_values(&[LABEL_VALUE_HASHED]).inc_by(chunk_info.size_bytes as u64);
+                        metrics
+                            .chunk_bytes
+                            .with_label_values(&[LABEL_VALUE_HASHED])
+                            .inc_by(chunk_info.size_bytes as u64);
                         recompute_chunk_hash()
-                    },
+                    }
                     ChunkAction::RecomputeAndCompare(precomputed_hash) => {
-                        metrics.chunk_bytes.with_label_values(&[LABEL_VALUE_HASHED_AND_COMPARED]).inc_by(chunk_info.size_bytes as u64);
+                        metrics
+                            .chunk_bytes
+                            .with_label_values(&[LABEL_VALUE_HASHED_AND_COMPARED])
+                            .inc_by(chunk_info.size_bytes as u64);
 
                         let recomputed_hash = recompute_chunk_hash();
                         debug_assert_eq!(recomputed_hash, precomputed_hash);
@@ -316,16 +327,19 @@ fn build_chunk_table_parallel(
                                 CRITICAL_ERROR_REUSED_CHUNK_HASH,
                                 chunk_idx,
                                 file_path.display(),
-                                chunk_info.hash,
+                                recomputed_hash,
                                 precomputed_hash
                             );
                         }
                         recomputed_hash
                     }
                     ChunkAction::UseHash(precomputed_hash) => {
-                        metrics.chunk_bytes.with_label_values(&[LABEL_VALUE_REUSED]).inc_by(chunk_info.size_bytes as u64);
+                        metrics
+                            .chunk_bytes
+                            .with_label_values(&[LABEL_VALUE_REUSED])
+                            .inc_by(chunk_info.size_bytes as u64);
                         precomputed_hash
-                    },
+                    }
                 };
             });
         }
@@ -418,7 +432,7 @@ fn build_chunk_table_sequential(
                     ChunkAction::Recompute => {
                         metrics
                             .chunk_bytes
-                            .with_label_values(&[LABEL_VALUE_REUSED])
+                            .with_label_values(&[LABEL_VALUE_HASHED])
                             .inc_by(chunk_size);
                         recompute_chunk_hash()
                     }
@@ -997,6 +1011,46 @@ pub fn diff_manifest(
     }
 }
 
+/// Computes a DiffScript that copies the chunks of `manifest_new` listed in
+/// `chunks` from the state described by `manifest_old`, matching them by hash.
+///
+/// Chunks that have no counterpart in `manifest_old` are listed in
+/// `fetch_chunks` of the resulting script.
+pub fn diff_manifest_chunks(
+    manifest_old: &Manifest,
+    manifest_new: &Manifest,
+    chunks: &HashSet<NewIndex>,
+) -> DiffScript {
+    let chunk_hash_to_index: HashMap<[u8; 32], OldIndex> = manifest_old
+        .chunk_table
+        .iter()
+        .enumerate()
+        .map(|(chunk_index, chunk_info)| (chunk_info.hash, chunk_index))
+        .collect();
+
+    let mut copy_chunks: HashMap<NewIndex, OldIndex> = Default::default();
+    let mut fetch_chunks: HashSet<NewIndex> = Default::default();
+
+    for chunk_index in chunks.iter() {
+        let chunk_info = &manifest_new.chunk_table[*chunk_index];
+        match chunk_hash_to_index.get(&chunk_info.hash) {
+            Some(index) => {
+                copy_chunks.insert(*chunk_index, *index);
+            }
+            None => {
+                fetch_chunks.insert(*chunk_index);
+            }
+        }
+    }
+
+    DiffScript {
+        copy_files: Default::default(),
+        copy_chunks,
+        fetch_chunks,
+        zeros_chunks: 0,
+    }
+}
+
 /// Filters out all-zero chunks in the manifest chunk table and returns the set
 /// of remaining chunks indices.
 pub fn filter_out_zero_chunks(manifest: &Manifest) -> HashSet<usize> {
@@ -1,7 +1,8 @@
 use super::{
-    compute_manifest, diff_manifest, file_chunk_range, filter_out_zero_chunks, hash::ManifestHash,
-    manifest_hash, validate_chunk, validate_manifest, ChunkValidationError, DiffScript,
-    ManifestValidationError, CURRENT_STATE_SYNC_VERSION, STATE_SYNC_V1,
+    compute_manifest, diff_manifest, diff_manifest_chunks, file_chunk_range,
+    filter_out_zero_chunks, hash::ManifestHash, manifest_hash, validate_chunk, validate_manifest,
+    ChunkValidationError, DiffScript, ManifestValidationError, CURRENT_STATE_SYNC_VERSION,
+    STATE_SYNC_V1,
 };
 use crate::ManifestMetrics;
 
@@ -556,6 +557,37 @@ fn test_missing_simple_manifest() {
     );
 }
 
+#[test]
+fn test_diff_manifest_chunks() {
+    let (_, manifest_old) = simple_manifest();
+    let mut manifest_new = manifest_old.clone();
+    // Chunk 3 of the new manifest has contents unknown to the old one.
+    manifest_new.chunk_table[3].hash = [7; 32];
+
+    assert_eq!(
+        diff_manifest_chunks(&manifest_old, &manifest_new, &maplit::hashset! {0, 2, 3}),
+        DiffScript {
+            copy_files: Default::default(),
+            copy_chunks: maplit::hashmap! {
+                0 => 0,
+                2 => 2,
+            },
+            fetch_chunks: maplit::hashset! {3},
+            zeros_chunks: 0,
+        }
+    );
+
+    assert_eq!(
+        diff_manifest_chunks(&manifest_old, &manifest_new, &Default::default()),
+        DiffScript {
+            copy_files: Default::default(),
+            copy_chunks: Default::default(),
+            fetch_chunks: Default::default(),
+            zeros_chunks: 0,
+        }
+    );
+}
+
 #[test]
 fn test_simple_manifest_encoding_roundtrip() {
     let (_hash, manifest) = simple_manifest();
@@ -759,6 +791,174 @@ fn test_hash_plan() {
     assert!(seen_used as f64 <= 0.6 * repetitions as f64);
 }
 
+#[test]
+fn test_incremental_manifest_rehashes_only_dirty_chunks() {
+    use super::ManifestDelta;
+    use crate::{
+        DirtyPageMap, PageMapType, LABEL_VALUE_HASHED, LABEL_VALUE_HASHED_AND_COMPARED,
+        LABEL_VALUE_REUSED,
+    };
+    use ic_replicated_state::PageIndex;
+    use ic_state_layout::{CheckpointLayout, ReadOnly};
+    use ic_sys::PAGE_SIZE;
+    use ic_test_utilities::types::ids::canister_test_id;
+    use ic_types::Height;
+    use std::os::unix::fs::FileExt;
+
+    let dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+    let root = dir.path();
+    let max_chunk_size = 1024 * 1024;
+
+    let page_type = PageMapType::WasmMemory(canister_test_id(1));
+    let layout: CheckpointLayout<ReadOnly> =
+        CheckpointLayout::new(root.to_path_buf(), Height::new(0))
+            .expect("failed to create checkpoint layout");
+    let memory_path = page_type
+        .path(&layout)
+        .expect("failed to get the path of the canister memory");
+    fs::create_dir_all(memory_path.parent().unwrap()).expect("failed to create canister dir");
+    fs::write(&memory_path, vec![1u8; 3 * max_chunk_size as usize])
+        .expect("failed to create the canister memory file");
+    fs::write(root.join("system_metadata.pbuf"), vec![2u8; 100])
+        .expect("failed to create file 'system_metadata.pbuf'");
+
+    let mut thread_pool = scoped_threadpool::Pool::new(NUM_THREADS);
+    let manifest_old = compute_manifest(
+        &mut thread_pool,
+        &ManifestMetrics::new(&MetricsRegistry::new()),
+        &no_op_logger(),
+        CURRENT_STATE_SYNC_VERSION,
+        root,
+        max_chunk_size,
+        None,
+    )
+    .expect("failed to compute manifest");
+
+    // Change a single page in the second chunk of the canister memory.
+    let page_index = PageIndex::new((max_chunk_size as usize / PAGE_SIZE + 3) as u64);
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&memory_path)
+        .expect("failed to open the canister memory file")
+        .write_all_at(&[7u8; PAGE_SIZE], page_index.get() * PAGE_SIZE as u64)
+        .expect("failed to write the dirty page");
+
+    let manifest_metrics = ManifestMetrics::new(&MetricsRegistry::new());
+    let manifest_new = compute_manifest(
+        &mut thread_pool,
+        &manifest_metrics,
+        &no_op_logger(),
+        CURRENT_STATE_SYNC_VERSION,
+        root,
+        max_chunk_size,
+        Some(ManifestDelta {
+            base_manifest: manifest_old.clone(),
+            base_height: Height::new(1),
+            target_height: Height::new(2),
+            dirty_memory_pages: vec![DirtyPageMap {
+                height: Height::new(1),
+                page_type,
+                page_delta_indices: vec![page_index],
+            }],
+        }),
+    )
+    .expect("failed to compute manifest");
+
+    let manifest_from_scratch = compute_manifest(
+        &mut thread_pool,
+        &ManifestMetrics::new(&MetricsRegistry::new()),
+        &no_op_logger(),
+        CURRENT_STATE_SYNC_VERSION,
+        root,
+        max_chunk_size,
+        None,
+    )
+    .expect("failed to compute manifest");
+    assert_eq!(manifest_new, manifest_from_scratch);
+
+    let relative_memory_path = memory_path.strip_prefix(root).unwrap();
+    let memory_file_index = manifest_new
+        .file_table
+        .iter()
+        .position(|file_info| file_info.relative_path == relative_memory_path)
+        .expect("the canister memory is not in the manifest");
+    let memory_chunks = file_chunk_range(&manifest_new.chunk_table, memory_file_index);
+    assert_eq!(memory_chunks.len(), 3);
+
+    // Exactly the chunk containing the dirty page has a new hash.
+    let changed_chunks: Vec<usize> = (0..manifest_new.chunk_table.len())
+        .filter(|i| manifest_old.chunk_table[*i].hash != manifest_new.chunk_table[*i].hash)
+        .collect();
+    assert_eq!(changed_chunks, vec![memory_chunks.start + 1]);
+
+    // Only the dirty chunk and the file without dirty page tracking are rehashed
+    // from scratch; the hashes of the clean chunks are reused (or sampled for
+    // validation) and match the recomputed ones.
+    let chunk_bytes = &manifest_metrics.chunk_bytes;
+    assert_eq!(
+        chunk_bytes.with_label_values(&[LABEL_VALUE_HASHED]).get(),
+        max_chunk_size as u64 + 100
+    );
+    assert_eq!(
+        chunk_bytes.with_label_values(&[LABEL_VALUE_REUSED]).get()
+            + chunk_bytes
+                .with_label_values(&[LABEL_VALUE_HASHED_AND_COMPARED])
+                .get(),
+        2 * max_chunk_size as u64
+    );
+    assert_eq!(manifest_metrics.reused_chunk_hash_error_count.get(), 0);
+}
+
+#[test]
+fn test_dirty_pages_accumulate_across_checkpoints_without_manifest() {
+    use crate::{dirty_pages_since, DirtyPageMap, PageMapType, StateMetadata, StatesMetadata};
+    use ic_replicated_state::PageIndex;
+    use ic_test_utilities::types::ids::canister_test_id;
+    use ic_types::Height;
+
+    let wasm_memory = PageMapType::WasmMemory(canister_test_id(1));
+    let stable_memory = PageMapType::StableMemory(canister_test_id(1));
+    let new_canister_memory = PageMapType::WasmMemory(canister_test_id(2));
+    let dirty_page_map = |height: u64, page_type: PageMapType, pages: &[u64]| DirtyPageMap {
+        height: Height::new(height),
+        page_type,
+        page_delta_indices: pages.iter().map(|p| PageIndex::new(*p)).collect(),
+    };
+
+    // The manifest @10 is available, the one @20 is not computed yet.
+    let mut states_metadata = StatesMetadata::new();
+    states_metadata.insert(Height::new(10), StateMetadata::default());
+    states_metadata.insert(
+        Height::new(20),
+        StateMetadata {
+            dirty_pages: Some(vec![dirty_page_map(10, wasm_memory, &[1, 5])]),
+            ..Default::default()
+        },
+    );
+
+    let dirty_pages = dirty_pages_since(
+        &states_metadata,
+        Height::new(10),
+        vec![
+            dirty_page_map(20, wasm_memory, &[5, 3]),
+            dirty_page_map(20, stable_memory, &[2]),
+            dirty_page_map(20, new_canister_memory, &[4]),
+        ],
+    );
+
+    assert_eq!(
+        dirty_pages,
+        vec![
+            // The pages modified since @10 are merged.
+            dirty_page_map(10, wasm_memory, &[1, 3, 5]),
+            // The chain is broken, so the pages keep their height and the file is
+            // rehashed from scratch.
+            dirty_page_map(20, stable_memory, &[2]),
+            dirty_page_map(20, new_canister_memory, &[4]),
+        ]
+    );
+}
+
 #[test]
 fn test_file_chunk_range() {
     let manifest = simple_manifest().1;
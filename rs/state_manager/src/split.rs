@@ -0,0 +1,120 @@
+//! Splitting of a subnet's checkpoint into the checkpoints of two subnets.
+//!
+//! The canisters in the given canister ID ranges, together with their ingress
+//! history entries, are moved into the checkpoint of a new subnet; everything
+//! else (including streams and subnet queues) stays with the original subnet.
+//! See `ReplicatedState::split_off()` for how the two states are derived.
+//!
+//! The split operates on checkpoint files rather than writing out the split
+//! states from scratch: canister directories are copied from the original
+//! checkpoint as they are and only the subnet-level files are rewritten.
+
+use crate::checkpoint::load_checkpoint;
+use ic_logger::ReplicaLogger;
+use ic_registry_routing_table::CanisterIdRanges;
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::ReplicatedState;
+use ic_state_layout::{CheckpointLayout, RwPolicy, StateLayout};
+use ic_types::{Height, SubnetId};
+use std::path::PathBuf;
+
+/// Splits the checkpoint at `height` under the state root `root` into two
+/// checkpoints at the same height: one for the original subnet, replacing the
+/// original checkpoint (which is archived into the backups directory, so that
+/// the split can be undone); and one for `new_subnet_id`, holding the
+/// canisters in `canister_id_ranges`, under the state root `new_subnet_root`.
+///
+/// Both state roots must be on the same file system.
+pub fn split_checkpoint(
+    log: ReplicaLogger,
+    root: PathBuf,
+    height: Height,
+    subnet_type: SubnetType,
+    new_subnet_id: SubnetId,
+    canister_id_ranges: &CanisterIdRanges,
+    new_subnet_root: PathBuf,
+) -> Result<(), String> {
+    let layout = StateLayout::new(log.clone(), root);
+    let new_layout = StateLayout::new(log, new_subnet_root);
+    let new_subnet_heights = new_layout
+        .checkpoint_heights()
+        .map_err(|err| format!("failed to list checkpoints of the new subnet: {}", err))?;
+    if new_subnet_heights.contains(&height) {
+        return Err(format!(
+            "the new subnet already has a checkpoint at height {}",
+            height
+        ));
+    }
+
+    let mut state = layout
+        .checkpoint(height)
+        .map_err(|err| format!("failed to open checkpoint {}: {}", height, err))
+        .and_then(|cp_layout| {
+            load_checkpoint(&cp_layout, subnet_type, None)
+                .map_err(|err| format!("failed to load checkpoint {}: {}", height, err))
+        })?;
+    let new_state = state.split_off(new_subnet_id, canister_id_ranges)?;
+
+    // Write out the new subnet's checkpoint first, so that the original
+    // checkpoint is only replaced once the rest of the split has succeeded.
+    let scratchpad = layout
+        .checkpoint_to_scratchpad(height)
+        .map_err(|err| format!("failed to copy checkpoint {}: {}", height, err))?;
+    write_split_state(&scratchpad, &new_state)?;
+    new_layout
+        .scratchpad_to_checkpoint(scratchpad, height)
+        .map_err(|err| format!("failed to create the new subnet's checkpoint: {}", err))?;
+
+    let scratchpad = layout
+        .checkpoint_to_scratchpad(height)
+        .map_err(|err| format!("failed to copy checkpoint {}: {}", height, err))?;
+    write_split_state(&scratchpad, &state)?;
+    layout
+        .archive_checkpoint(height)
+        .map_err(|err| format!("failed to archive checkpoint {}: {}", height, err))?;
+    layout
+        .scratchpad_to_checkpoint(scratchpad, height)
+        .map_err(|err| format!("failed to replace checkpoint {}: {}", height, err))?;
+
+    Ok(())
+}
+
+/// Turns `scratchpad`, a writable copy of the checkpoint that `state` was
+/// split off from, into a checkpoint of `state`: removes the canisters that
+/// are not part of `state` and rewrites the subnet-level files.
+fn write_split_state(
+    scratchpad: &CheckpointLayout<RwPolicy>,
+    state: &ReplicatedState,
+) -> Result<(), String> {
+    let canister_ids = scratchpad
+        .canister_ids()
+        .map_err(|err| format!("failed to list canisters: {}", err))?;
+    for canister_id in canister_ids {
+        if state.canister_state(&canister_id).is_some() {
+            continue;
+        }
+        let path = scratchpad
+            .canister(&canister_id)
+            .map_err(|err| format!("failed to open canister {}: {}", canister_id, err))?
+            .raw_path();
+        std::fs::remove_dir_all(&path)
+            .map_err(|err| format!("failed to remove {}: {}", path.display(), err))?;
+    }
+
+    scratchpad
+        .system_metadata()
+        .serialize(state.system_metadata().into())
+        .map_err(|err| format!("failed to write system metadata: {}", err))?;
+    scratchpad
+        .subnet_queues()
+        .serialize(state.subnet_queues().into())
+        .map_err(|err| format!("failed to write subnet queues: {}", err))?;
+    scratchpad
+        .bitcoin_testnet()
+        .and_then(|bitcoin_layout| {
+            bitcoin_layout
+                .bitcoin_state()
+                .serialize(state.bitcoin_testnet().into())
+        })
+        .map_err(|err| format!("failed to write bitcoin testnet state: {}", err))
+}
@@ -1,7 +1,8 @@
 use crate::{
     manifest::{filter_out_zero_chunks, DiffScript},
     CheckpointRef, StateSyncMetrics, StateSyncRefs, CRITICAL_ERROR_STATE_SYNC_CORRUPTED_CHUNKS,
-    LABEL_COPY_CHUNKS, LABEL_COPY_FILES, LABEL_FETCH, LABEL_PREALLOCATE,
+    LABEL_COPY_CHUNKS, LABEL_COPY_FILES, LABEL_FETCH, LABEL_PREALLOCATE, LABEL_VALUE_DOWNLOADED,
+    LABEL_VALUE_REUSED,
 };
 use ic_logger::{debug, error, fatal, info, trace, warn, ReplicaLogger};
 use ic_registry_subnet_type::SubnetType;
@@ -66,7 +67,8 @@ pub struct IncompleteState {
     height: Height,
     root_hash: CryptoHashOfState,
     state: DownloadState,
-    manifest_with_checkpoint_ref: Option<(Manifest, CheckpointRef)>,
+    /// Manifests of the local checkpoints, latest first.
+    manifests_with_checkpoint_refs: Vec<(Manifest, CheckpointRef)>,
     metrics: StateSyncMetrics,
     started_at: Instant,
     own_subnet_type: SubnetType,
@@ -151,7 +153,7 @@ impl IncompleteState {
         height: Height,
         root_hash: CryptoHashOfState,
         state_layout: StateLayout,
-        manifest_with_checkpoint_ref: Option<(Manifest, CheckpointRef)>,
+        manifests_with_checkpoint_refs: Vec<(Manifest, CheckpointRef)>,
         metrics: StateSyncMetrics,
         own_subnet_type: SubnetType,
         thread_pool: Arc<Mutex<scoped_threadpool::Pool>>,
@@ -174,7 +176,7 @@ impl IncompleteState {
             height,
             root_hash,
             state: DownloadState::Blank,
-            manifest_with_checkpoint_ref,
+            manifests_with_checkpoint_refs,
             metrics,
             started_at: Instant::now(),
             own_subnet_type,
@@ -389,6 +391,10 @@ impl IncompleteState {
                             metrics
                                 .state_sync_remaining
                                 .sub(new_chunk_range.len() as i64);
+                            metrics
+                                .state_sync_chunk_bytes
+                                .with_label_values(&[LABEL_VALUE_REUSED])
+                                .inc_by(src_data.len() as u64);
                         } else {
                             // Copy the chunks that passed validation to the
                             // destination, the rest will be fetched and applied later.
@@ -453,6 +459,10 @@ impl IncompleteState {
                                     });
                                 }
                                 metrics.state_sync_remaining.sub(1);
+                                metrics
+                                    .state_sync_chunk_bytes
+                                    .with_label_values(&[LABEL_VALUE_REUSED])
+                                    .inc_by(chunk.size_bytes as u64);
                             }
                         }
                     } else {
@@ -470,6 +480,10 @@ impl IncompleteState {
                         metrics
                             .state_sync_remaining
                             .sub(new_chunk_range.len() as i64);
+                        metrics
+                            .state_sync_chunk_bytes
+                            .with_label_values(&[LABEL_VALUE_REUSED])
+                            .inc_by(manifest_new.file_table[*new_index].size_bytes);
                     }
                 });
             }
@@ -672,6 +686,10 @@ impl IncompleteState {
                                 });
                         }
                         metrics.state_sync_remaining.sub(1);
+                        metrics
+                            .state_sync_chunk_bytes
+                            .with_label_values(&[LABEL_VALUE_REUSED])
+                            .inc_by(dst_chunk.size_bytes as u64);
                     }
                 });
             }
@@ -710,6 +728,10 @@ impl IncompleteState {
             )
         });
         metrics.state_sync_remaining.sub(1);
+        metrics
+            .state_sync_chunk_bytes
+            .with_label_values(&[LABEL_VALUE_DOWNLOADED])
+            .inc_by(bytes.len() as u64);
     }
 
     fn build_artifact(
@@ -856,7 +878,7 @@ impl IncompleteState {
 
         // Get a DiffData from the cache or checkpoint_ref, or neither
         let diff_data: Option<DiffData> =
-            match (cache.as_ref(), self.manifest_with_checkpoint_ref.as_ref()) {
+            match (cache.as_ref(), self.manifests_with_checkpoint_refs.first()) {
                 (Some(cache_entry), Some((checkpoint_manifest, checkpoint_ref))) => {
                     let cache_height = cache_entry.height;
                     let checkpoint_height = checkpoint_ref.0.height;
@@ -957,13 +979,62 @@ impl IncompleteState {
                 },
                 height_old
             );
-            let diff_script =
+            let mut diff_script =
                 crate::manifest::diff_manifest(manifest_old, &missing_chunks, manifest_new);
             debug!(
                 self.log,
                 "State sync diff script (@{} -> @{}): {:?}", height_old, self.height, diff_script
             );
 
+            // Chunks missing from the state we diff against might still be present
+            // in one of the other local checkpoints, so we look them up by hash
+            // before requesting them from peers.
+            let mut checkpoint_diff_scripts = Vec::new();
+            for (checkpoint_manifest, checkpoint_ref) in self.manifests_with_checkpoint_refs.iter()
+            {
+                if diff_script.fetch_chunks.is_empty() {
+                    break;
+                }
+                let checkpoint_height = checkpoint_ref.0.height;
+                let checkpoint_root =
+                    match checkpoint_ref.0.state_layout.checkpoint(checkpoint_height) {
+                        Ok(checkpoint) => checkpoint.raw_path().to_path_buf(),
+                        Err(err) => {
+                            warn!(
+                                self.log,
+                                "Failed to get checkpoint path for height {}: {}",
+                                checkpoint_height,
+                                err
+                            );
+                            continue;
+                        }
+                    };
+                if checkpoint_root == root_old {
+                    continue;
+                }
+                let checkpoint_diff_script = crate::manifest::diff_manifest_chunks(
+                    checkpoint_manifest,
+                    manifest_new,
+                    &diff_script.fetch_chunks,
+                );
+                if checkpoint_diff_script.copy_chunks.is_empty() {
+                    continue;
+                }
+                debug!(
+                    self.log,
+                    "State sync diff script (@{} -> @{}): {:?}",
+                    checkpoint_height,
+                    self.height,
+                    checkpoint_diff_script
+                );
+                diff_script.fetch_chunks = checkpoint_diff_script.fetch_chunks.clone();
+                checkpoint_diff_scripts.push((
+                    checkpoint_root,
+                    checkpoint_manifest,
+                    checkpoint_diff_script,
+                ));
+            }
+
             // diff_script contains indices into the manifest chunk table, but p2p
             // counts the manifest itself as chunk 0, so all other chunk indices are
             // shifted by 1
@@ -1023,6 +1094,26 @@ impl IncompleteState {
                 &mut fetch_chunks,
             );
 
+            // Chunks copied from other checkpoints are always validated: the
+            // fetch set above no longer contains them, so a corrupted chunk is
+            // only requested from peers if its validation fails here.
+            for (checkpoint_root, checkpoint_manifest, checkpoint_diff_script) in
+                checkpoint_diff_scripts.iter()
+            {
+                Self::copy_chunks(
+                    &self.log,
+                    &self.metrics,
+                    &mut thread_pool,
+                    checkpoint_root,
+                    &self.root,
+                    checkpoint_manifest,
+                    manifest_new,
+                    checkpoint_diff_script,
+                    true,
+                    &mut fetch_chunks,
+                );
+            }
+
             fetch_chunks
         } else {
             info!(
@@ -82,7 +82,7 @@ fn incomplete_state_for_tests(
         height,
         hash,
         env.state_layout.clone(),
-        None,
+        Vec::new(),
         env.metrics.clone(),
         SubnetType::Application,
         Arc::new(Mutex::new(scoped_threadpool::Pool::new(NUM_THREADS))),
@@ -8,11 +8,16 @@ use ic_interfaces::{
 use ic_interfaces_state_manager::*;
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges, RoutingTable};
 use ic_replicated_state::{
     page_map::PageIndex, testing::ReplicatedStateTesting, NumWasmPages, PageMap, ReplicatedState,
     Stream,
 };
-use ic_state_manager::{DirtyPageMap, PageMapType, StateManagerImpl};
+use ic_state_layout::StateLayout;
+use ic_state_manager::{
+    checkpoint::load_checkpoint, split::split_checkpoint, DirtyPageMap, PageMapType,
+    StateManagerImpl,
+};
 use ic_sys::PAGE_SIZE;
 use ic_test_utilities::{
     consensus::fake::FakeVerifier,
@@ -35,7 +40,7 @@ use ic_types::{
     CanisterId, CryptoHashOfPartialState, CryptoHashOfState, Height, PrincipalId,
 };
 use proptest::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{
     collections::HashSet,
@@ -536,6 +541,87 @@ fn checkpoints_outlive_state_manager() {
     });
 }
 
+#[test]
+fn can_split_checkpoint() {
+    let tmp = Builder::new().prefix("test").tempdir().unwrap();
+    let config = Config::new(tmp.path().join("own_subnet"));
+    let new_subnet_root = tmp.path().join("new_subnet");
+    let own_subnet = subnet_test_id(42);
+    let new_subnet = subnet_test_id(43);
+    let range = |start: u64, end: u64| CanisterIdRange {
+        start: CanisterId::from(start),
+        end: CanisterId::from(end),
+    };
+
+    with_test_replica_logger(|log| {
+        {
+            let metrics_registry = MetricsRegistry::new();
+            let verifier: Arc<dyn Verifier> = Arc::new(FakeVerifier::new());
+            let state_manager = StateManagerImpl::new(
+                verifier,
+                own_subnet,
+                SubnetType::Application,
+                log.clone(),
+                &metrics_registry,
+                &config,
+                None,
+                ic_types::malicious_flags::MaliciousFlags::default(),
+            );
+            let (_height, mut state) = state_manager.take_tip();
+            let mut routing_table = RoutingTable::new();
+            routing_table.insert(range(0, 0xff), own_subnet).unwrap();
+            state.metadata.network_topology.routing_table = Arc::new(routing_table);
+            insert_dummy_canister(&mut state, canister_test_id(1));
+            insert_dummy_canister(&mut state, canister_test_id(0x80));
+
+            state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+            wait_for_checkpoint(&state_manager, height(1));
+        }
+
+        split_checkpoint(
+            log.clone(),
+            config.state_root(),
+            height(1),
+            SubnetType::Application,
+            new_subnet,
+            &CanisterIdRanges::try_from(vec![range(0x80, 0xff)]).unwrap(),
+            new_subnet_root.clone(),
+        )
+        .unwrap();
+
+        let load = |root: PathBuf| {
+            let layout = StateLayout::new(log.clone(), root);
+            let cp_layout = layout.checkpoint(height(1)).unwrap();
+            load_checkpoint(&cp_layout, SubnetType::Application, None).unwrap()
+        };
+        let own_state = load(config.state_root());
+        let new_state = load(new_subnet_root);
+
+        assert_eq!(own_subnet, own_state.metadata.own_subnet_id);
+        assert_eq!(canister_ids(&own_state), vec![canister_test_id(1)]);
+        assert_eq!(new_subnet, new_state.metadata.own_subnet_id);
+        assert_eq!(canister_ids(&new_state), vec![canister_test_id(0x80)]);
+        for state in &[&own_state, &new_state] {
+            assert_eq!(
+                Some(new_subnet),
+                state
+                    .metadata
+                    .network_topology
+                    .routing_table
+                    .route(canister_test_id(0x80).get())
+            );
+        }
+
+        // The original checkpoint is kept around, in case the split is undone.
+        assert_eq!(
+            vec![height(1)],
+            StateLayout::new(log, config.state_root())
+                .backup_heights()
+                .unwrap()
+        );
+    });
+}
+
 #[test]
 fn certifications_are_not_persisted() {
     let tmp = Builder::new().prefix("test").tempdir().unwrap();
@@ -1576,6 +1662,165 @@ fn can_state_sync_from_cache() {
     })
 }
 
+#[test]
+fn can_state_sync_reusing_chunks_of_older_checkpoints() {
+    state_manager_test(|src_metrics, src_state_manager| {
+        let (_height, mut state) = src_state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+
+        src_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        let hash = wait_for_checkpoint(&src_state_manager, height(1));
+
+        let msg = src_state_manager
+            .get_validated_by_identifier(&StateSyncArtifactId {
+                height: height(1),
+                hash: hash.clone(),
+            })
+            .expect("failed to get state sync messages");
+
+        assert_error_counters(src_metrics);
+
+        state_manager_test(|dst_metrics, dst_state_manager| {
+            // The destination has the same state at height 1, but the canister
+            // is gone in its latest checkpoint.
+            let (_height, mut state) = dst_state_manager.take_tip();
+            insert_dummy_canister(&mut state, canister_test_id(100));
+            dst_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+            wait_for_checkpoint(&dst_state_manager, height(1));
+
+            let (_height, mut state) = dst_state_manager.take_tip();
+            state.take_canister_state(&canister_test_id(100));
+            dst_state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+            wait_for_checkpoint(&dst_state_manager, height(2));
+
+            // Same state just higher height
+            let id = StateSyncArtifactId {
+                height: height(3),
+                hash,
+            };
+            let mut chunkable = dst_state_manager.create_chunkable_state(&id);
+
+            // The chunks missing from the checkpoint @2 are copied from the
+            // checkpoint @1, so the manifest alone is enough to complete the sync
+            let dst_msg = pipe_manifest(&msg, &mut *chunkable).unwrap();
+            dst_state_manager
+                .check_artifact_acceptance(dst_msg, &node_test_id(0))
+                .expect("Failed to process state sync artifact");
+
+            assert_eq!(height(3), dst_state_manager.latest_state_height());
+
+            let chunk_bytes = fetch_int_counter_vec(dst_metrics, "state_sync_chunk_bytes_total");
+            let mut reused_label = Labels::new();
+            reused_label.insert("origin".to_string(), "reused".to_string());
+            let mut downloaded_label = Labels::new();
+            downloaded_label.insert("origin".to_string(), "downloaded".to_string());
+            assert_ne!(0, chunk_bytes[&reused_label]);
+            assert_eq!(0, chunk_bytes[&downloaded_label]);
+
+            assert_eq!(
+                0,
+                fetch_int_gauge(dst_metrics, "state_sync_remaining_chunks").unwrap()
+            );
+            assert_error_counters(dst_metrics);
+        })
+    })
+}
+
+#[test]
+fn can_refetch_corrupted_chunks_of_older_checkpoints() {
+    use ic_state_layout::{CheckpointLayout, RwPolicy};
+
+    state_manager_test(|src_metrics, src_state_manager| {
+        let (_height, mut state) = src_state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+
+        src_state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+        let hash = wait_for_checkpoint(&src_state_manager, height(1));
+        let id = StateSyncArtifactId {
+            height: height(1),
+            hash: hash.clone(),
+        };
+
+        let msg = src_state_manager
+            .get_validated_by_identifier(&id)
+            .expect("failed to get state sync messages");
+
+        assert_error_counters(src_metrics);
+
+        state_manager_test(|dst_metrics, dst_state_manager| {
+            // The destination fetched the state at height 1, so it doesn't
+            // validate it when using it as the base of the next state sync.
+            let chunkable = dst_state_manager.create_chunkable_state(&id);
+            let dst_msg = pipe_state_sync(msg.clone(), chunkable);
+            dst_state_manager
+                .check_artifact_acceptance(dst_msg, &node_test_id(0))
+                .expect("Failed to process state sync artifact");
+
+            let (_height, mut state) = dst_state_manager.take_tip();
+            state.take_canister_state(&canister_test_id(100));
+            dst_state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+            wait_for_checkpoint(&dst_state_manager, height(2));
+
+            // Corrupt the canister in the checkpoint @1, which is the only local
+            // source of its chunks.
+            let state_layout = dst_state_manager.state_layout();
+            let mutable_cp_layout = CheckpointLayout::<RwPolicy>::new(
+                state_layout
+                    .checkpoint(height(1))
+                    .unwrap()
+                    .raw_path()
+                    .to_path_buf(),
+                height(1),
+            )
+            .unwrap();
+            let canister_100_raw_pb = mutable_cp_layout
+                .canister(&canister_test_id(100))
+                .unwrap()
+                .canister()
+                .raw_path()
+                .to_path_buf();
+            make_mutable(&canister_100_raw_pb).unwrap();
+            write_all_at(&canister_100_raw_pb, b"Garbage", 0).unwrap();
+
+            // Same state just higher height
+            let id = StateSyncArtifactId {
+                height: height(3),
+                hash,
+            };
+            let chunkable = dst_state_manager.create_chunkable_state(&id);
+            let dst_msg = pipe_state_sync(msg, chunkable);
+            dst_state_manager
+                .check_artifact_acceptance(dst_msg, &node_test_id(0))
+                .expect("Failed to process state sync artifact");
+
+            assert_eq!(height(3), dst_state_manager.latest_state_height());
+            let expected_state = src_state_manager.get_latest_state();
+            assert_eq!(
+                dst_state_manager.take_tip().1,
+                *expected_state.take().as_ref()
+            );
+
+            // The corrupted chunk is downloaded instead of being copied.
+            let corrupted_chunks =
+                fetch_int_counter_vec(dst_metrics, "state_sync_corrupted_chunks");
+            let mut copy_chunks_label = Labels::new();
+            copy_chunks_label.insert("source".to_string(), "copy_chunks".to_string());
+            assert_ne!(0, corrupted_chunks[&copy_chunks_label]);
+
+            let chunk_bytes = fetch_int_counter_vec(dst_metrics, "state_sync_chunk_bytes_total");
+            let mut downloaded_label = Labels::new();
+            downloaded_label.insert("origin".to_string(), "downloaded".to_string());
+            assert_ne!(0, chunk_bytes[&downloaded_label]);
+
+            assert_eq!(
+                0,
+                fetch_int_gauge(dst_metrics, "state_sync_remaining_chunks").unwrap()
+            );
+            assert_error_counters(dst_metrics);
+        })
+    })
+}
+
 #[test]
 fn can_state_sync_into_existing_checkpoint() {
     state_manager_test(|src_metrics, src_state_manager| {
@@ -1,8 +1,14 @@
 //! Command implementations.
+pub mod canister;
+pub mod canister_migration;
 pub mod cdiff;
 pub mod chash;
 pub mod decode;
 pub mod import_state;
 pub mod list;
 pub mod manifest;
+pub mod split;
+#[cfg(test)]
+mod test_utils;
 mod utils;
+pub mod verify;
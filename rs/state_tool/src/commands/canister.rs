@@ -0,0 +1,296 @@
+//! Inspects canisters inside a checkpoint.
+
+use crate::commands::utils::checkpoint_height;
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{num_bytes_try_from, CanisterState, Memory, PageMap, ReplicatedState};
+use ic_state_layout::CompleteCheckpointLayout;
+use ic_state_manager::checkpoint::load_checkpoint;
+use ic_types::CanisterId;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Loads the checkpoint rooted at `path` of a subnet of type `subnet_type`.
+fn load_state(path: PathBuf, subnet_type: SubnetType) -> Result<ReplicatedState, String> {
+    let height = checkpoint_height(&path)?;
+    let cp_layout = CompleteCheckpointLayout::new(path.clone(), height)
+        .map_err(|e| format!("failed to create checkpoint layout: {}", e))?;
+
+    load_checkpoint(&cp_layout, subnet_type, None)
+        .map_err(|e| format!("failed to load checkpoint at {}: {}", path.display(), e))
+}
+
+/// Returns the canister with the given ID from `state`.
+fn get_canister(
+    state: &ReplicatedState,
+    canister_id: CanisterId,
+) -> Result<&CanisterState, String> {
+    state
+        .canister_state(&canister_id)
+        .ok_or_else(|| format!("canister {} not found in the checkpoint", canister_id))
+}
+
+/// Returns the size of `memory` in bytes as a human-readable string.
+fn memory_size(memory: &Memory) -> String {
+    num_bytes_try_from(memory.size)
+        .map(|bytes| bytes.get().to_string())
+        .unwrap_or_else(|e| format!("<{}>", e))
+}
+
+/// Lists the canisters of the checkpoint rooted at `path`.
+pub fn do_list(path: PathBuf, subnet_type: SubnetType) -> Result<(), String> {
+    let state = load_state(path, subnet_type)?;
+
+    if state.canister_states.is_empty() {
+        println!("No canisters to display");
+        return Ok(());
+    }
+
+    println!(
+        "{:<30}    {:<10}    {:>40}    {:<64}",
+        "CANISTER ID", "STATUS", "CYCLES", "MODULE HASH"
+    );
+
+    for canister in state.canisters_iter() {
+        let module_hash = canister
+            .execution_state
+            .as_ref()
+            .map(|es| hex::encode(es.wasm_binary.binary.module_hash()))
+            .unwrap_or_else(|| "-".to_string());
+
+        println!(
+            "{:<30}    {:<10}    {:>40}    {:<64}",
+            canister.canister_id().to_string(),
+            canister.system_state.status_string(),
+            canister.system_state.balance(),
+            module_hash
+        );
+    }
+
+    Ok(())
+}
+
+/// Displays the state of the canister `canister_id` in the checkpoint rooted
+/// at `path`.
+pub fn do_show(
+    path: PathBuf,
+    canister_id: CanisterId,
+    subnet_type: SubnetType,
+) -> Result<(), String> {
+    let state = load_state(path, subnet_type)?;
+    let canister = get_canister(&state, canister_id)?;
+    let system_state = &canister.system_state;
+
+    println!("CANISTER ID:         {}", canister_id);
+    println!("STATUS:              {}", system_state.status_string());
+    println!(
+        "CONTROLLERS:         {}",
+        system_state.collect_controllers_as_string()
+    );
+    println!("CYCLES BALANCE:      {}", system_state.balance());
+    println!("FREEZE THRESHOLD:    {}", system_state.freeze_threshold);
+    println!("MEMORY ALLOCATION:   {}", system_state.memory_allocation);
+    println!(
+        "COMPUTE ALLOCATION:  {}",
+        canister.scheduler_state.compute_allocation
+    );
+    println!(
+        "MEMORY USAGE:        {}",
+        canister.memory_usage(state.metadata.own_subnet_type)
+    );
+
+    match canister.execution_state.as_ref() {
+        Some(execution_state) => {
+            println!(
+                "MODULE HASH:         {}",
+                hex::encode(execution_state.wasm_binary.binary.module_hash())
+            );
+            println!(
+                "MODULE SIZE:         {}",
+                execution_state.wasm_binary.binary.len()
+            );
+            println!(
+                "HEAP SIZE:           {}",
+                memory_size(&execution_state.wasm_memory)
+            );
+            println!(
+                "STABLE MEMORY SIZE:  {}",
+                memory_size(&execution_state.stable_memory)
+            );
+        }
+        None => println!("MODULE HASH:         <empty canister>"),
+    }
+
+    let queues = system_state.queues();
+    println!(
+        "INGRESS QUEUE:       {} messages, {} bytes",
+        queues.ingress_queue_message_count(),
+        queues.ingress_queue_size_bytes()
+    );
+    println!(
+        "INPUT QUEUES:        {} messages, {} bytes",
+        queues.input_queues_message_count(),
+        queues.input_queues_size_bytes()
+    );
+    println!();
+    println!("QUEUES: {:#?}", queues);
+
+    Ok(())
+}
+
+/// Writes the contents of `page_map` to a new file at `path`, padded with
+/// zeros to `size_bytes`.
+fn export_page_map(page_map: &PageMap, size_bytes: u64, path: &Path) -> Result<(), String> {
+    let mut file = File::create(path)
+        .map_err(|e| format!("failed to create file {}: {}", path.display(), e))?;
+    for (_, page) in page_map.host_pages_iter() {
+        file.write_all(&page[..])
+            .map_err(|e| format!("failed to write to file {}: {}", path.display(), e))?;
+    }
+    let written = file
+        .metadata()
+        .map_err(|e| format!("failed to get metadata of file {}: {}", path.display(), e))?
+        .len();
+    if written < size_bytes {
+        file.set_len(size_bytes)
+            .map_err(|e| format!("failed to truncate file {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Exports the Wasm module, the heap and the stable memory of the canister
+/// `canister_id` in the checkpoint rooted at `path` to the `output` directory.
+pub fn do_export(
+    path: PathBuf,
+    canister_id: CanisterId,
+    subnet_type: SubnetType,
+    output: PathBuf,
+) -> Result<(), String> {
+    let state = load_state(path, subnet_type)?;
+    let canister = get_canister(&state, canister_id)?;
+    let execution_state = canister
+        .execution_state
+        .as_ref()
+        .ok_or_else(|| format!("canister {} has no Wasm module installed", canister_id))?;
+
+    std::fs::create_dir_all(&output)
+        .map_err(|e| format!("failed to create directory {}: {}", output.display(), e))?;
+
+    let module_path = output.join("module.wasm");
+    std::fs::write(&module_path, execution_state.wasm_binary.binary.as_slice())
+        .map_err(|e| format!("failed to write file {}: {}", module_path.display(), e))?;
+    println!("Wasm module written to {}", module_path.display());
+
+    for (memory, file_name) in &[
+        (&execution_state.wasm_memory, "heap.bin"),
+        (&execution_state.stable_memory, "stable_memory.bin"),
+    ] {
+        let size_bytes = num_bytes_try_from(memory.size)?.get();
+        let memory_path = output.join(file_name);
+        export_page_map(&memory.page_map, size_bytes, &memory_path)?;
+        println!("{} bytes written to {}", size_bytes, memory_path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::write_checkpoint;
+    use ic_test_utilities::{
+        state::CanisterStateBuilder,
+        types::ids::{canister_test_id, subnet_test_id},
+    };
+    use ic_types::Height;
+    use tempfile::Builder;
+
+    const HEIGHT: Height = Height::new(42);
+
+    fn empty_wasm() -> Vec<u8> {
+        vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x04, 0x6e, 0x61, 0x6d,
+            0x65, 0x02, 0x01, 0x00,
+        ]
+    }
+
+    /// Writes a checkpoint at `HEIGHT` under `root` holding one canister with
+    /// a Wasm module and stable memory, and returns the checkpoint path.
+    fn fixture_checkpoint(root: &Path) -> PathBuf {
+        let mut state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            "NOT_USED".into(),
+        );
+        state.put_canister_state(
+            CanisterStateBuilder::new()
+                .with_canister_id(canister_test_id(10))
+                .with_wasm(empty_wasm())
+                .with_stable_memory(vec![1, 2, 3, 4])
+                .build(),
+        );
+        write_checkpoint(root, &state, HEIGHT)
+    }
+
+    #[test]
+    fn load_state_uses_checkpoint_height_and_subnet_type() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let path = fixture_checkpoint(tmp.path());
+
+        let state = load_state(path, SubnetType::System).unwrap();
+        assert_eq!(state.metadata.own_subnet_type, SubnetType::System);
+        assert!(state.canister_state(&canister_test_id(10)).is_some());
+    }
+
+    #[test]
+    fn load_state_fails_on_invalid_checkpoint_name() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let path = fixture_checkpoint(tmp.path());
+        let renamed = tmp.path().join("not_a_height");
+        std::fs::rename(&path, &renamed).unwrap();
+
+        let err = load_state(renamed, SubnetType::Application).unwrap_err();
+        assert!(
+            err.contains("failed to convert checkpoint name not_a_height"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn list_and_show_canisters() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let path = fixture_checkpoint(tmp.path());
+
+        assert_eq!(do_list(path.clone(), SubnetType::Application), Ok(()));
+        assert_eq!(
+            do_show(path.clone(), canister_test_id(10), SubnetType::Application),
+            Ok(())
+        );
+        assert!(do_show(path, canister_test_id(11), SubnetType::Application).is_err());
+    }
+
+    #[test]
+    fn export_canister() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let path = fixture_checkpoint(&tmp.path().join("state"));
+        let output = tmp.path().join("export");
+
+        do_export(
+            path,
+            canister_test_id(10),
+            SubnetType::Application,
+            output.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read(output.join("module.wasm")).unwrap(),
+            empty_wasm()
+        );
+        let stable_memory = std::fs::read(output.join("stable_memory.bin")).unwrap();
+        assert_eq!(stable_memory.len(), 64 * 1024);
+        assert_eq!(&stable_memory[..4], &[1, 2, 3, 4]);
+        assert!(output.join("heap.bin").exists());
+    }
+}
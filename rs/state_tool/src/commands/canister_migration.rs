@@ -0,0 +1,167 @@
+//! Moves canister states between checkpoints, as part of migrating canister
+//! id ranges from one subnet to another.
+//!
+//! A migration goes through the following stages:
+//!
+//!  1. The `prepare_canister_migration` proposal adds the ranges to the
+//!     canister migrations. The source subnet then rejects new requests to
+//!     the migrating canisters, so that their queues drain.
+//!  2. Once the canisters are drained, both subnets are halted and `extract`
+//!     copies the canister states from a source checkpoint into a separate
+//!     directory. `extract` refuses canisters that still have messages in
+//!     their queues.
+//!  3. `insert` adds the extracted canister states to a (writable copy of a)
+//!     destination checkpoint and `remove` deletes them from a (writable copy
+//!     of a) source checkpoint. The resulting checkpoints are imported with
+//!     `import`.
+//!  4. The `reroute_canister_range` proposal routes the ranges to the
+//!     destination subnet and both subnets are recovered from the imported
+//!     checkpoints. `complete_canister_migration` then removes the ranges
+//!     from the canister migrations, once all messages routed through the
+//!     source subnet have been delivered.
+//!
+//! The migration can be aborted at any stage before the subnets are recovered
+//! in step 4: by discarding the extracted states and modified checkpoints,
+//! rerouting the ranges back to the source subnet (if they were rerouted
+//! already) and recovering the subnets from their original checkpoints (if
+//! they were halted). Completing the migration with the same migration trace
+//! then unfreezes the canisters on the source subnet.
+
+use crate::commands::import_state::copy_recursively;
+use ic_registry_routing_table::CanisterIdRange;
+use ic_state_layout::CompleteCheckpointLayout;
+use ic_state_manager::checkpoint::load_canister_state;
+use ic_types::{CanisterId, Height};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The directory holding the canister states, both within a checkpoint and
+/// within a directory of extracted canisters.
+const CANISTER_STATES_DIR: &str = "canister_states";
+
+/// Returns the IDs of the canisters in the checkpoint rooted at `path`.
+fn canister_ids(path: &Path) -> Result<Vec<CanisterId>, String> {
+    CompleteCheckpointLayout::new(path.to_path_buf(), Height::new(0))
+        .and_then(|cp_layout| cp_layout.canister_ids())
+        .map_err(|e| format!("failed to list canisters at {}: {}", path.display(), e))
+}
+
+/// Returns the directory of the canister `canister_id` under `root`.
+fn canister_dir(root: &Path, canister_id: &CanisterId) -> PathBuf {
+    root.join(CANISTER_STATES_DIR)
+        .join(hex::encode(canister_id.get_ref().as_slice()))
+}
+
+/// Returns true if `canister_id` falls into any of `ranges`.
+fn in_ranges(canister_id: &CanisterId, ranges: &[CanisterIdRange]) -> bool {
+    ranges
+        .iter()
+        .any(|range| range.start <= *canister_id && *canister_id <= range.end)
+}
+
+/// Copies the states of the canisters in `ranges` from the checkpoint rooted
+/// at `path` into the `output` directory.
+///
+/// Fails if any of the canisters still has messages in its queues, i.e. has
+/// not been drained since the migration was prepared.
+pub fn do_extract(
+    path: PathBuf,
+    ranges: Vec<CanisterIdRange>,
+    output: PathBuf,
+) -> Result<(), String> {
+    let cp_layout = CompleteCheckpointLayout::new(path.clone(), Height::new(0))
+        .map_err(|e| format!("failed to create checkpoint layout: {}", e))?;
+    let canister_ids: Vec<_> = canister_ids(&path)?
+        .into_iter()
+        .filter(|canister_id| in_ranges(canister_id, &ranges))
+        .collect();
+
+    for canister_id in &canister_ids {
+        let canister_layout = cp_layout
+            .canister(canister_id)
+            .map_err(|e| format!("failed to open canister {}: {}", canister_id, e))?;
+        let canister = load_canister_state(&canister_layout, canister_id, Height::new(0))
+            .map_err(|e| format!("failed to load canister {}: {}", canister_id, e))?;
+        let queues = canister.system_state.queues();
+        if queues.has_input() || queues.has_output() {
+            return Err(format!(
+                "canister {} has not been drained yet: {} ingress messages, {} input messages, output queues {}",
+                canister_id,
+                queues.ingress_queue_message_count(),
+                queues.input_queues_message_count(),
+                if queues.has_output() { "not empty" } else { "empty" }
+            ));
+        }
+    }
+
+    for canister_id in &canister_ids {
+        copy_recursively(
+            &canister_dir(&path, canister_id),
+            &canister_dir(&output, canister_id),
+        )?;
+        println!("Extracted canister {}", canister_id);
+    }
+    println!(
+        "Extracted {} canisters to {}",
+        canister_ids.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Copies the canister states extracted into `input` into the checkpoint
+/// rooted at `path`, which must be writable.
+///
+/// Fails without modifying the checkpoint if any of the canisters already
+/// exists in it.
+pub fn do_insert(path: PathBuf, input: PathBuf) -> Result<(), String> {
+    let existing = canister_ids(&path)?;
+    let canister_ids = canister_ids(&input)?;
+
+    if let Some(canister_id) = canister_ids.iter().find(|id| existing.contains(id)) {
+        return Err(format!(
+            "canister {} already exists in the checkpoint at {}",
+            canister_id,
+            path.display()
+        ));
+    }
+
+    for canister_id in &canister_ids {
+        copy_recursively(
+            &canister_dir(&input, canister_id),
+            &canister_dir(&path, canister_id),
+        )?;
+        println!("Inserted canister {}", canister_id);
+    }
+    println!(
+        "Inserted {} canisters into {}",
+        canister_ids.len(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Removes the states of the canisters in `ranges` from the checkpoint rooted
+/// at `path`, which must be writable.
+pub fn do_remove(path: PathBuf, ranges: Vec<CanisterIdRange>) -> Result<(), String> {
+    let canister_ids: Vec<_> = canister_ids(&path)?
+        .into_iter()
+        .filter(|canister_id| in_ranges(canister_id, &ranges))
+        .collect();
+
+    for canister_id in &canister_ids {
+        let dir = canister_dir(&path, canister_id);
+        fs::remove_dir_all(&dir)
+            .map_err(|e| format!("failed to remove directory {}: {}", dir.display(), e))?;
+        println!("Removed canister {}", canister_id);
+    }
+    println!(
+        "Removed {} canisters from {}",
+        canister_ids.len(),
+        path.display()
+    );
+
+    Ok(())
+}
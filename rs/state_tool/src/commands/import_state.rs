@@ -13,7 +13,7 @@ use std::string::ToString;
 ///
 /// Function is not crash-safe. Caller is responsible to follow guidelines
 /// regarding crash-safe I/O.
-fn copy_recursively(src: &Path, dst: &Path) -> Result<(), String> {
+pub(crate) fn copy_recursively(src: &Path, dst: &Path) -> Result<(), String> {
     enum CanCloneFiles {
         Yes,
         No,
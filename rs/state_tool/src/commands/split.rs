@@ -0,0 +1,60 @@
+//! Splits a checkpoint of a subnet into the checkpoints of two subnets.
+//!
+//! Splitting a subnet goes through the following steps:
+//!
+//!  1. The subnet is halted and `split` is run on a checkpoint of one of its
+//!     nodes, writing the checkpoint of the new subnet into a separate state
+//!     root and replacing the original checkpoint (which is archived as a
+//!     backup).
+//!  2. The state hashes of both checkpoints are computed with `manifest`.
+//!  3. The `split_subnet` proposal routes the ranges to the new subnet and
+//!     records their migration from the original subnet to the new one.
+//!  4. The original subnet is recovered from its new checkpoint, and the new
+//!     subnet is started from the other checkpoint by means of a recovery
+//!     CUP (`replay ... set-recovery-cup <state hash> <height>`).
+//!  5. Once all messages routed through the original subnet have been
+//!     delivered, `complete_canister_migration` removes the ranges from the
+//!     canister migrations.
+
+use crate::commands::utils;
+use ic_logger::replica_logger::no_op_logger;
+use ic_registry_routing_table::{CanisterIdRange, CanisterIdRanges};
+use ic_registry_subnet_type::SubnetType;
+use ic_state_manager::split::split_checkpoint;
+use ic_types::{Height, PrincipalId, SubnetId};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/// Splits off the canisters in `ranges` from the checkpoint at `height` of the
+/// subnet configured in `config` into a checkpoint of `new_subnet_id` under
+/// `new_state_root`.
+pub fn do_split(
+    config: PathBuf,
+    height: u64,
+    subnet_type: SubnetType,
+    new_subnet_id: PrincipalId,
+    ranges: Vec<CanisterIdRange>,
+    new_state_root: PathBuf,
+) -> Result<(), String> {
+    let state_layout = utils::locate_state_root(config)?;
+    let ranges = CanisterIdRanges::try_from(ranges)
+        .map_err(|e| format!("invalid canister id ranges: {:?}", e))?;
+
+    split_checkpoint(
+        no_op_logger(),
+        state_layout.raw_path().to_path_buf(),
+        Height::new(height),
+        subnet_type,
+        SubnetId::new(new_subnet_id),
+        &ranges,
+        new_state_root.clone(),
+    )?;
+
+    println!(
+        "Split off checkpoint {} of subnet {} into {}",
+        height,
+        new_subnet_id,
+        new_state_root.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,25 @@
+//! Fixtures shared by the command tests.
+
+use ic_logger::replica_logger::no_op_logger;
+use ic_metrics::MetricsRegistry;
+use ic_replicated_state::ReplicatedState;
+use ic_state_layout::StateLayout;
+use ic_state_manager::{
+    checkpoint::make_checkpoint, CheckpointMetrics, NUMBER_OF_CHECKPOINT_THREADS,
+};
+use ic_types::Height;
+use std::path::{Path, PathBuf};
+
+/// Writes `state` as the checkpoint at `height` under the state root `root`
+/// and returns the path of the checkpoint.
+pub fn write_checkpoint(root: &Path, state: &ReplicatedState, height: Height) -> PathBuf {
+    let log = no_op_logger();
+    let layout = StateLayout::new(log.clone(), root.to_path_buf());
+    let metrics = CheckpointMetrics::new(&MetricsRegistry::new());
+    let mut thread_pool = scoped_threadpool::Pool::new(NUMBER_OF_CHECKPOINT_THREADS);
+
+    make_checkpoint(state, height, &layout, &log, &metrics, &mut thread_pool)
+        .unwrap_or_else(|err| panic!("Expected make_checkpoint to succeed, got {:?}", err));
+
+    layout.checkpoint(height).unwrap().raw_path().to_path_buf()
+}
@@ -3,7 +3,8 @@
 use ic_config::{config_parser::ConfigSource, ConfigOptional};
 use ic_logger::replica_logger::no_op_logger;
 use ic_state_layout::StateLayout;
-use std::path::PathBuf;
+use ic_types::Height;
+use std::path::{Path, PathBuf};
 
 /// Loads the location of the state root from the given `replica` configuration
 /// file.
@@ -24,3 +25,19 @@ pub fn locate_state_root(config_path: PathBuf) -> Result<StateLayout, String> {
 
     Ok(StateLayout::new(no_op_logger(), state_root))
 }
+
+/// Returns the height of the checkpoint rooted at `path`, as encoded in the
+/// (hex) name of the checkpoint directory.
+pub fn checkpoint_height(path: &Path) -> Result<Height, String> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("failed to get checkpoint name of {}", path.display()))?;
+
+    u64::from_str_radix(name, 16).map(Height::new).map_err(|e| {
+        format!(
+            "failed to convert checkpoint name {} into a height: {}",
+            name, e
+        )
+    })
+}
@@ -0,0 +1,268 @@
+//! Verifies a checkpoint against the state hash agreed on by consensus.
+
+use crate::commands::{manifest::compute_checkpoint_manifest, utils::checkpoint_height};
+use ic_protobuf::types::v1 as pb;
+use ic_state_manager::manifest::{file_chunk_range, manifest_hash};
+use ic_types::state_sync::Manifest;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Reads the state hash and the height from the `CatchUpPackage` protobuf
+/// stored at `path`.
+fn read_cup_state_hash(path: PathBuf) -> Result<(Vec<u8>, u64), String> {
+    let bytes = std::fs::read(&path)
+        .map_err(|e| format!("failed to read CUP file {}: {}", path.display(), e))?;
+    let cup = pb::CatchUpPackage::decode(&bytes[..])
+        .map_err(|e| format!("failed to deserialize CUP {}: {}", path.display(), e))?;
+    let content = pb::CatchUpContent::decode(&cup.content[..]).map_err(|e| {
+        format!(
+            "failed to deserialize CUP content {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    let height = content.block.map(|block| block.height).unwrap_or_default();
+    Ok((content.state_hash, height))
+}
+
+/// Prints the files and chunks that differ between the `expected` and the
+/// `actual` manifests.
+fn print_manifest_diff(expected: &Manifest, actual: &Manifest) {
+    let files = |manifest: &Manifest| -> BTreeMap<PathBuf, usize> {
+        manifest
+            .file_table
+            .iter()
+            .enumerate()
+            .map(|(index, file_info)| (file_info.relative_path.clone(), index))
+            .collect()
+    };
+    let expected_files = files(expected);
+    let actual_files = files(actual);
+
+    for (path, expected_index) in expected_files.iter() {
+        let expected_file = &expected.file_table[*expected_index];
+        let actual_index = match actual_files.get(path) {
+            Some(index) => *index,
+            None => {
+                println!("  missing file {}", path.display());
+                continue;
+            }
+        };
+        let actual_file = &actual.file_table[actual_index];
+        if expected_file.hash == actual_file.hash {
+            continue;
+        }
+        println!(
+            "  file {} differs (expected {} bytes with hash {}, got {} bytes with hash {})",
+            path.display(),
+            expected_file.size_bytes,
+            hex::encode(expected_file.hash),
+            actual_file.size_bytes,
+            hex::encode(actual_file.hash)
+        );
+
+        let expected_chunks =
+            &expected.chunk_table[file_chunk_range(&expected.chunk_table, *expected_index)];
+        let actual_chunks =
+            &actual.chunk_table[file_chunk_range(&actual.chunk_table, actual_index)];
+        for i in 0..expected_chunks.len().max(actual_chunks.len()) {
+            match (expected_chunks.get(i), actual_chunks.get(i)) {
+                (Some(e), Some(a)) if e.hash == a.hash && e.size_bytes == a.size_bytes => {}
+                (Some(e), _) => println!(
+                    "    chunk {} at offset {} ({} bytes) differs",
+                    i, e.offset, e.size_bytes
+                ),
+                (None, Some(a)) => println!(
+                    "    unexpected chunk {} at offset {} ({} bytes)",
+                    i, a.offset, a.size_bytes
+                ),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+
+    for path in actual_files.keys() {
+        if !expected_files.contains_key(path) {
+            println!("  unexpected file {}", path.display());
+        }
+    }
+}
+
+/// `verify` command entry point.
+///
+/// Computes the manifest of the checkpoint at `path` and compares its root
+/// hash to the state hash in the CUP at `cup` or to the hex-encoded `hash`.
+/// When verifying against a CUP, the CUP height must match the height of the
+/// checkpoint.
+/// If the hashes don't match and a `reference` checkpoint is given, reports
+/// the files and chunks that differ from it.
+pub fn do_verify(
+    path: PathBuf,
+    cup: Option<PathBuf>,
+    hash: Option<String>,
+    reference: Option<PathBuf>,
+) -> Result<(), String> {
+    let expected_hash = match (cup, hash) {
+        (Some(cup), None) => {
+            let (state_hash, cup_height) = read_cup_state_hash(cup)?;
+            let height = checkpoint_height(&path)?;
+            println!("CUP HEIGHT:    {}", cup_height);
+            if cup_height != height.get() {
+                return Err(format!(
+                    "CUP height {} doesn't match the height {} of checkpoint {}",
+                    cup_height,
+                    height,
+                    path.display()
+                ));
+            }
+            state_hash
+        }
+        (None, Some(hash)) => {
+            hex::decode(&hash).map_err(|e| format!("failed to decode hash {}: {}", hash, e))?
+        }
+        _ => return Err("exactly one of --cup and --hash must be specified".to_string()),
+    };
+
+    let manifest = compute_checkpoint_manifest(path.clone())?;
+    let actual_hash = manifest_hash(&manifest);
+
+    println!("EXPECTED HASH: {}", hex::encode(&expected_hash));
+    println!("ACTUAL HASH:   {}", hex::encode(actual_hash));
+
+    if actual_hash[..] == expected_hash[..] {
+        println!(
+            "✓ Checkpoint {} matches the expected state hash",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    match reference {
+        Some(reference) => {
+            let reference_manifest = compute_checkpoint_manifest(reference.clone())?;
+            if manifest_hash(&reference_manifest)[..] != expected_hash[..] {
+                println!(
+                    "⚠ Reference checkpoint {} doesn't match the expected state hash either",
+                    reference.display()
+                );
+            }
+            println!(
+                "Differences from the reference checkpoint {}:",
+                reference.display()
+            );
+            print_manifest_diff(&reference_manifest, &manifest);
+        }
+        None => println!(
+            "Pass --reference with a checkpoint matching the expected hash to see which files differ"
+        ),
+    }
+
+    Err(format!(
+        "✗ Verification FAILED: checkpoint {} doesn't match the expected state hash",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::write_checkpoint;
+    use ic_registry_subnet_type::SubnetType;
+    use ic_replicated_state::ReplicatedState;
+    use ic_test_utilities::types::ids::subnet_test_id;
+    use ic_types::Height;
+    use std::path::Path;
+    use tempfile::Builder;
+
+    const HEIGHT: Height = Height::new(42);
+
+    /// Writes an empty checkpoint at `HEIGHT` under `root` and returns its path
+    /// and root hash.
+    fn empty_checkpoint(root: &Path) -> (PathBuf, Vec<u8>) {
+        let state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            "NOT_USED".into(),
+        );
+        let path = write_checkpoint(root, &state, HEIGHT);
+        let manifest = compute_checkpoint_manifest(path.clone()).unwrap();
+        (path, manifest_hash(&manifest).to_vec())
+    }
+
+    /// Writes a `CatchUpPackage` with the given `height` and `state_hash` to
+    /// `path`.
+    fn write_cup(path: &Path, height: u64, state_hash: Vec<u8>) {
+        let content = pb::CatchUpContent {
+            block: Some(pb::Block {
+                height,
+                ..Default::default()
+            }),
+            state_hash,
+            ..Default::default()
+        };
+        let mut content_bytes = vec![];
+        content.encode(&mut content_bytes).unwrap();
+
+        let cup = pb::CatchUpPackage {
+            content: content_bytes,
+            ..Default::default()
+        };
+        let mut cup_bytes = vec![];
+        cup.encode(&mut cup_bytes).unwrap();
+        std::fs::write(path, cup_bytes).unwrap();
+    }
+
+    #[test]
+    fn verify_succeeds_on_matching_cup() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let (path, root_hash) = empty_checkpoint(&tmp.path().join("state"));
+        let cup = tmp.path().join("cup.pb");
+        write_cup(&cup, HEIGHT.get(), root_hash);
+
+        assert_eq!(do_verify(path, Some(cup), None, None), Ok(()));
+    }
+
+    #[test]
+    fn verify_succeeds_on_matching_hash() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let (path, root_hash) = empty_checkpoint(tmp.path());
+
+        assert_eq!(
+            do_verify(path, None, Some(hex::encode(root_hash)), None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn verify_fails_on_cup_height_mismatch() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let (path, root_hash) = empty_checkpoint(&tmp.path().join("state"));
+        let cup = tmp.path().join("cup.pb");
+        write_cup(&cup, HEIGHT.get() + 1, root_hash);
+
+        let err = do_verify(path, Some(cup), None, None).unwrap_err();
+        assert!(
+            err.contains("CUP height 43 doesn't match the height 42"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn verify_fails_on_hash_mismatch() {
+        let tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let (path, _) = empty_checkpoint(&tmp.path().join("state"));
+        let reference_tmp = Builder::new().prefix("test").tempdir().unwrap();
+        let (reference, _) = empty_checkpoint(reference_tmp.path());
+        let cup = tmp.path().join("cup.pb");
+        write_cup(&cup, HEIGHT.get(), vec![0; 32]);
+
+        let err = do_verify(path, Some(cup), None, Some(reference)).unwrap_err();
+        assert!(
+            err.contains("Verification FAILED"),
+            "unexpected error: {}",
+            err
+        );
+    }
+}
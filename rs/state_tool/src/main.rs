@@ -2,9 +2,14 @@
 //!
 //! A command-line tool to manage Internet Computer replicated states (decode
 //! persisted state files, diff checkpoints, compute partial state hashes and
-//! checkpoint manifests, import state trees).
+//! checkpoint manifests, import state trees, inspect canisters and move them
+//! between checkpoints, split checkpoints, verify checkpoints against CUPs).
 
+use ic_registry_routing_table::CanisterIdRange;
+use ic_registry_subnet_type::SubnetType;
+use ic_types::{CanisterId, PrincipalId};
 use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
 
 mod commands;
@@ -49,6 +54,29 @@ enum Opt {
         path: PathBuf,
     },
 
+    /// Verifies that the root hash of a checkpoint matches the state hash
+    /// agreed on by consensus.
+    #[structopt(name = "verify")]
+    Verify {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Path to a `CatchUpPackage` protobuf file holding the expected state
+        /// hash.
+        #[structopt(long = "cup", required_unless = "hash", conflicts_with = "hash")]
+        cup: Option<PathBuf>,
+
+        /// Expected state hash, hex-encoded.
+        #[structopt(long = "hash")]
+        hash: Option<String>,
+
+        /// Path to a checkpoint matching the expected hash, used to report
+        /// which files and chunks differ.
+        #[structopt(long = "reference")]
+        reference: Option<PathBuf>,
+    },
+
     /// Enumerates persisted states.
     #[structopt(name = "list")]
     ListStates {
@@ -64,6 +92,153 @@ enum Opt {
         #[structopt(long = "file")]
         file: PathBuf,
     },
+
+    /// Splits off the canisters in the given ranges from a checkpoint into a
+    /// checkpoint of a new subnet.
+    #[structopt(name = "split")]
+    Split {
+        /// Path to the replica configuration (ic.json) of the subnet to split.
+        #[structopt(long = "config")]
+        config: PathBuf,
+
+        /// Height of the checkpoint to split.
+        #[structopt(long = "height", short = "h")]
+        height: u64,
+
+        /// Type of the subnet to split.
+        #[structopt(long = "subnet-type", default_value = "application")]
+        subnet_type: SubnetType,
+
+        /// ID of the new subnet.
+        #[structopt(long = "new-subnet-id")]
+        new_subnet_id: PrincipalId,
+
+        /// Canister id ranges to move to the new subnet, as `START:END` (both
+        /// inclusive).
+        #[structopt(long = "ranges", required = true, parse(try_from_str = parse_canister_id_range))]
+        ranges: Vec<CanisterIdRange>,
+
+        /// State root to write the checkpoint of the new subnet to. Must be
+        /// on the same file system as the state root of the subnet to split.
+        #[structopt(long = "new-state-root")]
+        new_state_root: PathBuf,
+    },
+
+    /// Inspects canisters inside a checkpoint and moves them between
+    /// checkpoints.
+    #[structopt(name = "canister")]
+    Canister(CanisterOpt),
+}
+
+/// Supported `state_tool canister` subcommands and their arguments.
+#[derive(StructOpt, Debug)]
+enum CanisterOpt {
+    /// Lists the canisters of a checkpoint.
+    #[structopt(name = "list")]
+    List {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Type of the subnet the checkpoint belongs to.
+        #[structopt(long = "subnet-type", default_value = "application")]
+        subnet_type: SubnetType,
+    },
+
+    /// Displays controllers, module hash, cycles balance, memory sizes and
+    /// queues of a canister.
+    #[structopt(name = "show")]
+    Show {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// ID of the canister to display.
+        #[structopt(long = "canister")]
+        canister_id: CanisterId,
+
+        /// Type of the subnet the checkpoint belongs to.
+        #[structopt(long = "subnet-type", default_value = "application")]
+        subnet_type: SubnetType,
+    },
+
+    /// Exports the Wasm module, heap and stable memory of a canister.
+    #[structopt(name = "export")]
+    Export {
+        /// Path to a checkpoint.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// ID of the canister to export.
+        #[structopt(long = "canister")]
+        canister_id: CanisterId,
+
+        /// Type of the subnet the checkpoint belongs to.
+        #[structopt(long = "subnet-type", default_value = "application")]
+        subnet_type: SubnetType,
+
+        /// Directory to write the exported files to.
+        #[structopt(long = "output")]
+        output: PathBuf,
+    },
+
+    /// Copies the states of the canisters in the given ranges out of a
+    /// checkpoint, for migrating them to another subnet. Fails if any of the
+    /// canisters has not been drained.
+    #[structopt(name = "extract")]
+    Extract {
+        /// Path to a checkpoint of the source subnet.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Canister id ranges to extract, as `START:END` (both inclusive).
+        #[structopt(long = "ranges", required = true, parse(try_from_str = parse_canister_id_range))]
+        ranges: Vec<CanisterIdRange>,
+
+        /// Directory to write the extracted canister states to.
+        #[structopt(long = "output")]
+        output: PathBuf,
+    },
+
+    /// Inserts extracted canister states into a writable copy of a
+    /// checkpoint.
+    #[structopt(name = "insert")]
+    Insert {
+        /// Path to a writable copy of a checkpoint of the destination subnet.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Directory holding the canister states written by `extract`.
+        #[structopt(long = "input")]
+        input: PathBuf,
+    },
+
+    /// Removes the states of the canisters in the given ranges from a
+    /// writable copy of a checkpoint.
+    #[structopt(name = "remove")]
+    Remove {
+        /// Path to a writable copy of a checkpoint of the source subnet.
+        #[structopt(long = "state")]
+        path: PathBuf,
+
+        /// Canister id ranges to remove, as `START:END` (both inclusive).
+        #[structopt(long = "ranges", required = true, parse(try_from_str = parse_canister_id_range))]
+        ranges: Vec<CanisterIdRange>,
+    },
+}
+
+/// Parses a canister id range of the form `START:END`, where both ends are
+/// inclusive canister ids.
+fn parse_canister_id_range(s: &str) -> Result<CanisterIdRange, String> {
+    let (start, end) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected a canister id range START:END, got {}", s))?;
+    let start = CanisterId::from_str(start).map_err(|e| format!("invalid range start: {}", e))?;
+    let end = CanisterId::from_str(end).map_err(|e| format!("invalid range end: {}", e))?;
+    if start > end {
+        return Err(format!("invalid canister id range {}: start > end", s));
+    }
+    Ok(CanisterIdRange { start, end })
 }
 
 fn main() {
@@ -77,8 +252,54 @@ fn main() {
             height,
         } => commands::import_state::do_import(state, config, height),
         Opt::Manifest { path } => commands::manifest::do_compute_manifest(path),
+        Opt::Verify {
+            path,
+            cup,
+            hash,
+            reference,
+        } => commands::verify::do_verify(path, cup, hash, reference),
         Opt::ListStates { config } => commands::list::do_list(config),
         Opt::Decode { file } => commands::decode::do_decode(file),
+        Opt::Split {
+            config,
+            height,
+            subnet_type,
+            new_subnet_id,
+            ranges,
+            new_state_root,
+        } => commands::split::do_split(
+            config,
+            height,
+            subnet_type,
+            new_subnet_id,
+            ranges,
+            new_state_root,
+        ),
+        Opt::Canister(CanisterOpt::List { path, subnet_type }) => {
+            commands::canister::do_list(path, subnet_type)
+        }
+        Opt::Canister(CanisterOpt::Show {
+            path,
+            canister_id,
+            subnet_type,
+        }) => commands::canister::do_show(path, canister_id, subnet_type),
+        Opt::Canister(CanisterOpt::Export {
+            path,
+            canister_id,
+            subnet_type,
+            output,
+        }) => commands::canister::do_export(path, canister_id, subnet_type, output),
+        Opt::Canister(CanisterOpt::Extract {
+            path,
+            ranges,
+            output,
+        }) => commands::canister_migration::do_extract(path, ranges, output),
+        Opt::Canister(CanisterOpt::Insert { path, input }) => {
+            commands::canister_migration::do_insert(path, input)
+        }
+        Opt::Canister(CanisterOpt::Remove { path, ranges }) => {
+            commands::canister_migration::do_remove(path, ranges)
+        }
     };
 
     if let Err(e) = result {
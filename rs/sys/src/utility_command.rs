@@ -1,5 +1,6 @@
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use std::process::{Command as StdCommand, ExitStatus, Stdio};
 
 const VSOCK_AGENT_PATH: &str = "/opt/ic/bin/vsock_agent";
@@ -51,6 +52,14 @@ impl UtilityCommand {
         self
     }
 
+    /// Makes a `pkcs11-tool` command use the PKCS#11 library at `module`
+    /// instead of its default one.
+    pub fn with_pkcs11_module(mut self, module: &Path) -> Self {
+        self.args
+            .splice(0..0, ["--module".to_string(), module.display().to_string()]);
+        self
+    }
+
     /// Execute the command and capture the output.
     pub fn execute(&self) -> UtilityCommandResult<Vec<u8>> {
         let mut cmd = StdCommand::new(self.program.clone());
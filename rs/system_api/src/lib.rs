@@ -902,6 +902,42 @@ impl SystemApiImpl {
         }
     }
 
+    /// Checks whether the canister is about to be migrated away from its
+    /// subnet, in which case it may not make any new outgoing calls (so that
+    /// its queues and call contexts drain).
+    fn is_migration_prepared(&self) -> bool {
+        match &self.api_type {
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => false,
+            ApiType::Update {
+                own_subnet_id,
+                network_topology,
+                ..
+            }
+            | ApiType::Heartbeat {
+                own_subnet_id,
+                network_topology,
+                ..
+            }
+            | ApiType::ReplyCallback {
+                own_subnet_id,
+                network_topology,
+                ..
+            }
+            | ApiType::RejectCallback {
+                own_subnet_id,
+                network_topology,
+                ..
+            } => network_topology
+                .is_migration_prepared(self.sandbox_safe_system_state.canister_id, *own_subnet_id),
+        }
+    }
+
     fn ic0_call_cycles_add_helper(
         &mut self,
         method_name: &str,
@@ -1051,8 +1087,9 @@ impl SystemApiImpl {
     /// Wrapper around `self.sandbox_safe_system_state.push_output_request()` that
     /// tries to allocate memory for the `Request` before pushing it.
     ///
-    /// On failure to allocate memory or withdraw cycles; or on queue full;
-    /// returns `Ok(RejectCode::SysTransient as i32)`.
+    /// On failure to allocate memory or withdraw cycles; on queue full; or if
+    /// the canister is about to be migrated to a different subnet; returns
+    /// `Ok(RejectCode::SysTransient as i32)`.
     ///
     /// Note that this function is made public only for the tests
     #[doc(hidden)]
@@ -1063,6 +1100,10 @@ impl SystemApiImpl {
             Ok(RejectCode::SysTransient as i32)
         };
 
+        if self.is_migration_prepared() {
+            return abort(req, &mut self.sandbox_safe_system_state);
+        }
+
         let reservation_bytes = (memory_required_to_push_request(&req) as u64).into();
         let enforce_message_memory_usage = ENFORCE_MESSAGE_MEMORY_USAGE
             && self.execution_parameters.subnet_type != SubnetType::System;
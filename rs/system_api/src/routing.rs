@@ -112,6 +112,8 @@ pub(super) fn resolve_destination(
         }
         Ok(method @ Ic00Method::ECDSAPublicKey)
         | Ok(method @ Ic00Method::SignWithECDSA)
+        | Ok(method @ Ic00Method::SchnorrPublicKey)
+        | Ok(method @ Ic00Method::SignWithSchnorr)
         | Ok(method @ Ic00Method::ComputeInitialEcdsaDealings) => {
             // We currently assume there is only one subnet with ecdsa enabled.
             // When there is more than one, we should decode the payload to get
@@ -5,6 +5,7 @@ use ic_interfaces::execution_environment::{
     HypervisorResult, SubnetAvailableMemory, SystemApi, TrapCode,
 };
 use ic_logger::replica_logger::no_op_logger;
+use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations};
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
     canister_state::ENFORCE_MESSAGE_MEMORY_USAGE, testing::CanisterQueuesTesting, CallOrigin,
@@ -19,7 +20,7 @@ use ic_test_utilities::{
     mock_time,
     state::SystemStateBuilder,
     types::{
-        ids::{call_context_test_id, canister_test_id, user_test_id},
+        ids::{call_context_test_id, canister_test_id, subnet_test_id, user_test_id},
         messages::RequestBuilder,
     },
 };
@@ -28,8 +29,9 @@ use ic_types::{
     methods::{Callback, WasmClosure},
     CountBytes, Cycles, NumBytes, NumInstructions, Time,
 };
+use maplit::btreemap;
 use std::{
-    convert::{From, TryInto},
+    convert::{From, TryFrom, TryInto},
     sync::Arc,
 };
 
@@ -1691,6 +1693,65 @@ fn push_output_request_respects_memory_limits() {
     run_test(1 << 30, MAX_RESPONSE_COUNT_BYTES as i64 + 13);
 }
 
+#[test]
+fn push_output_request_rejected_if_canister_is_being_migrated() {
+    let mut system_state = SystemStateBuilder::default().build();
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let mut sandbox_safe_system_state =
+        SandboxSafeSystemState::new(&system_state, cycles_account_manager);
+    let own_canister_id = system_state.canister_id;
+    let callback_id = sandbox_safe_system_state
+        .register_callback(Callback::new(
+            call_context_test_id(0),
+            Some(own_canister_id),
+            Some(canister_test_id(0)),
+            Cycles::from(0),
+            WasmClosure::new(0, 0),
+            WasmClosure::new(0, 0),
+            None,
+        ))
+        .unwrap();
+
+    // The canister is about to be migrated away from its subnet.
+    let mut api_type_builder = ApiTypeBuilder::new();
+    let mut network_topology = (*api_type_builder.network_topology).clone();
+    network_topology.canister_migrations = Arc::new(
+        CanisterMigrations::try_from(btreemap! {
+            CanisterIdRange{ start: own_canister_id, end: own_canister_id } => vec![api_type_builder.own_subnet_id, subnet_test_id(2)],
+        })
+        .unwrap(),
+    );
+    api_type_builder.network_topology = Arc::new(network_topology);
+
+    let mut api = SystemApiImpl::new(
+        api_type_builder.build_update_api(),
+        sandbox_safe_system_state,
+        CANISTER_CURRENT_MEMORY_USAGE,
+        execution_parameters(),
+        Memory::default(),
+        Arc::new(DefaultOutOfInstructionsHandler {}),
+        no_op_logger(),
+    );
+
+    let req = RequestBuilder::default()
+        .sender(own_canister_id)
+        .sender_reply_callback(callback_id)
+        .build();
+
+    // The request is rejected without allocating any memory.
+    assert_eq!(
+        RejectCode::SysTransient as i32,
+        api.push_output_request(req).unwrap()
+    );
+    assert_eq!(NumBytes::from(0), api.get_allocated_memory());
+    assert_eq!(NumBytes::from(0), api.get_allocated_message_memory());
+
+    // And no output request was pushed.
+    let system_state_changes = api.into_system_state_changes();
+    system_state_changes.apply_changes(&mut system_state);
+    assert_eq!(0, system_state.queues().output_queues_len());
+}
+
 #[test]
 fn push_output_request_oversized_request_memory_limits() {
     let subnet_available_memory_bytes = 3 * MAX_RESPONSE_COUNT_BYTES as i64;
@@ -75,4 +75,20 @@ impl TlsHandshake for FakeTlsHandshake {
     ) -> Result<TlsStream, TlsClientHandshakeError> {
         unimplemented!()
     }
+
+    fn tls_server_config(
+        &self,
+        _allowed_clients: AllowedClients,
+        _registry_version: RegistryVersion,
+    ) -> Result<tokio_rustls::rustls::ServerConfig, TlsServerHandshakeError> {
+        unimplemented!()
+    }
+
+    fn tls_client_config(
+        &self,
+        _server: NodeId,
+        _registry_version: RegistryVersion,
+    ) -> Result<tokio_rustls::rustls::ClientConfig, TlsClientHandshakeError> {
+        unimplemented!()
+    }
 }
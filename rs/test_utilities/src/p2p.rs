@@ -329,6 +329,7 @@ pub fn get_replica_transport_config(
             server_port: port,
             queue_size: 8,
         }],
+        ..Default::default()
     }
 }
 
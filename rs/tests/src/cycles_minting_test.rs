@@ -791,7 +791,8 @@ struct TestAgent {
 
 impl TestAgent {
     pub fn new(ic_url: &Url, agent_client: &HttpClient) -> Self {
-        let agent = Agent::new_with_client(agent_client.clone(), ic_url.clone(), Sender::Anonymous);
+        let agent = Agent::new_with_client(agent_client.clone(), ic_url.clone(), Sender::Anonymous)
+            .with_fetched_root_key();
         Self { agent }
     }
 
@@ -890,7 +891,8 @@ impl UserHandle {
             http_client.clone(),
             ic_url.clone(),
             Sender::from_keypair(user_keypair),
-        );
+        )
+        .with_fetched_root_key();
         Self {
             agent,
             ledger_id,
@@ -361,7 +361,8 @@ pub fn install_nns_canisters(
         let agent = Agent::new(
             url,
             Sender::from_keypair(&ic_test_identity::TEST_IDENTITY_KEYPAIR),
-        );
+        )
+        .with_fetched_root_key();
         let runtime = Runtime::Remote(RemoteTestRuntime { agent });
 
         NnsCanisters::set_up(&runtime, init_payloads.build()).await;
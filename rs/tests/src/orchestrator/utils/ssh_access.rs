@@ -176,6 +176,7 @@ pub(crate) fn get_updatesubnetpayload_with_keys(
         max_number_of_canisters: None,
         ssh_readonly_access: readonly_keys,
         ssh_backup_access: backup_keys,
+        rollout_policy: None,
     }
 }
 
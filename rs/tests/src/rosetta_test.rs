@@ -381,7 +381,8 @@ pub fn test_everything(handle: IcHandle, ctx: &ic_fondue::pot::Context) {
         let agent = ic_canister_client::Agent::new(
             node_url.clone(),
             Sender::from_keypair(&ic_test_identity::TEST_IDENTITY_KEYPAIR),
-        );
+        )
+        .with_fetched_root_key();
         let root_key = agent.root_key().await.unwrap().unwrap();
         let remote_runtime = Runtime::Remote(RemoteTestRuntime { agent });
 
@@ -55,7 +55,10 @@ pub fn runtime_from_url(url: Url) -> Runtime {
     let agent = DeprecatedAgent::new(
         url,
         Sender::from_keypair(&ic_test_identity::TEST_IDENTITY_KEYPAIR),
-    );
+    )
+    // System tests run against test networks, whose root key is not known in
+    // advance.
+    .with_fetched_root_key();
     Runtime::Remote(RemoteTestRuntime { agent })
 }
 
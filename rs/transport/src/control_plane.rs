@@ -9,13 +9,14 @@
 //! [`TransportImpl`](../types/struct.TransportImpl.html).
 
 use crate::types::{
-    ClientState, Connecting, ConnectionRole, ConnectionState, FlowState, PeerState, QueueSize,
-    ServerPort, ServerPortState, TransportImpl,
+    ClientState, Connecting, ConnectionRole, ConnectionState, FlowReader, FlowState, FlowWriter,
+    PeerState, QueueSize, ServerPort, ServerPortState, TransportImpl,
 };
 use crate::utils::{get_flow_ips, get_flow_label, SendQueueImpl};
 use futures::future::{AbortHandle, Abortable, Aborted};
 use ic_base_types::{NodeId, RegistryVersion};
-use ic_crypto_tls_interfaces::{AllowedClients, AuthenticatedPeer, TlsReadHalf, TlsWriteHalf};
+use ic_config::transport::TransportBackend;
+use ic_crypto_tls_interfaces::{AllowedClients, AuthenticatedPeer};
 use ic_interfaces_transport::{AsyncTransportEventHandler, FlowId, FlowTag, TransportErrorCode};
 use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_protobuf::registry::node::v1::NodeRecord;
@@ -60,6 +61,9 @@ impl TransportImpl {
             self.allowed_clients.write().unwrap().insert(*peer_id);
         }
         *self.registry_version.write().unwrap() = registry_version;
+        if let Some(quic_endpoint) = &client_state.quic_endpoint {
+            self.update_quic_server_config(quic_endpoint);
+        }
         info!(
             self.log,
             "ControlPlane::start_peer_connections(): node_id = {:?}, peer_id = {:?}, registry_version = {}",
@@ -98,6 +102,9 @@ impl TransportImpl {
             }
         }
         client_state.peer_map.remove(peer_id);
+        if client_state.quic_endpoint.is_some() {
+            self.close_quic_connection(*peer_id);
+        }
 
         info!(
             self.log,
@@ -159,6 +166,20 @@ impl TransportImpl {
             return Ok(());
         }
 
+        // With QUIC, all flows share the connection to the endpoint of the first
+        // flow.
+        let quic_peer_addr = match &client_state.quic_endpoint {
+            Some(_) => peer_record
+                .p2p_flow_endpoints
+                .iter()
+                .find_map(|flow_endpoint| flow_endpoint.endpoint.as_ref())
+                .map(|endpoint| {
+                    let peer_ip = IpAddr::from_str(endpoint.ip_addr.as_str())
+                        .unwrap_or_else(|_| panic!("Invalid node IP: {}", endpoint.ip_addr));
+                    SocketAddr::new(peer_ip, endpoint.port as u16)
+                }),
+            None => None,
+        };
         for flow_endpoint in &peer_record.p2p_flow_endpoints {
             let endpoint = match &flow_endpoint.endpoint {
                 Some(x) => x,
@@ -189,14 +210,28 @@ impl TransportImpl {
                 .unwrap_or_else(|_| panic!("Invalid node IP: {}", endpoint.ip_addr));
             let flow_label = get_flow_label(endpoint.ip_addr.as_str(), peer_id);
             let server_port = endpoint.port as u16;
-            let connecting_task = self.spawn_connect_task(
-                flow_endpoint.flow_tag.into(),
-                *peer_id,
-                peer_ip,
-                ServerPort::from(server_port),
-            );
+            let (peer_addr, connecting_task) = match (&client_state.quic_endpoint, quic_peer_addr) {
+                (Some(quic_endpoint), Some(quic_peer_addr)) => (
+                    quic_peer_addr,
+                    self.spawn_quic_connect_task(
+                        flow_tag,
+                        *peer_id,
+                        quic_peer_addr,
+                        quic_endpoint.endpoint.clone(),
+                    ),
+                ),
+                _ => (
+                    SocketAddr::new(peer_ip, server_port),
+                    self.spawn_connect_task(
+                        flow_tag,
+                        *peer_id,
+                        peer_ip,
+                        ServerPort::from(server_port),
+                    ),
+                ),
+            };
             let connecting_state = Connecting {
-                peer_addr: SocketAddr::new(peer_ip, server_port),
+                peer_addr,
                 connecting_task,
             };
             let flow_id = FlowId {
@@ -426,43 +461,37 @@ impl TransportImpl {
     /// server/client sides). Does the validation, sets up the connection state
     /// and spawns the read task for the connection.
     #[allow(clippy::too_many_arguments)]
-    async fn process_handshake_result(
+    pub(crate) async fn process_handshake_result(
         &self,
         peer_id: NodeId,
         role: ConnectionRole,
         flow_tag: FlowTag,
         local_addr: SocketAddr,
         peer_addr: SocketAddr,
-        tls_reader: TlsReadHalf,
-        tls_writer: TlsWriteHalf,
+        reader: FlowReader,
+        writer: FlowWriter,
     ) -> Result<(), TransportErrorCode> {
         // Pass the established connection to the data plane to start IOs.
         let flow_id = FlowId { peer_id, flow_tag };
-        self.on_connect(
-            flow_id,
-            role,
-            peer_addr,
-            Box::new(tls_reader),
-            Box::new(tls_writer),
-        )
-        .await
-        .map_err(|e| {
-            warn!(
-                every_n_seconds => 30,
-                self.log,
-                "ControlPlane::handshake_result(): failed to add flow: \
-                 node = {:?}/{:?}, flow_tag = {:?}, peer = {:?}/{:?}, role = {:?}, \
-                 error = {:?}",
-                self.node_id,
-                local_addr,
-                flow_tag,
-                peer_id,
-                peer_addr,
-                Self::connection_role(&self.node_id, &peer_id),
+        self.on_connect(flow_id, role, peer_addr, reader, writer)
+            .await
+            .map_err(|e| {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "ControlPlane::handshake_result(): failed to add flow: \
+                     node = {:?}/{:?}, flow_tag = {:?}, peer = {:?}/{:?}, role = {:?}, \
+                     error = {:?}",
+                    self.node_id,
+                    local_addr,
+                    flow_tag,
+                    peer_id,
+                    peer_addr,
+                    Self::connection_role(&self.node_id, &peer_id),
+                    e
+                );
                 e
-            );
-            e
-        })
+            })
     }
 
     /// Retries to establish a connection
@@ -512,15 +541,26 @@ impl TransportImpl {
                 flow_id.peer_id
             );
         } else {
-            // reconnect if we have a listener
-            if client_state.accept_ports.contains_key(&flow_id.flow_tag) {
-                let socket_addr = sa.peer_addr;
-                let connecting_task = self.spawn_connect_task(
+            let socket_addr = sa.peer_addr;
+            let connecting_task = match &client_state.quic_endpoint {
+                Some(quic_endpoint) => Some(self.spawn_quic_connect_task(
                     flow_id.flow_tag,
                     flow_id.peer_id,
-                    socket_addr.ip(),
-                    ServerPort::from(socket_addr.port()),
-                );
+                    socket_addr,
+                    quic_endpoint.endpoint.clone(),
+                )),
+                // reconnect if we have a listener
+                None if client_state.accept_ports.contains_key(&flow_id.flow_tag) => {
+                    Some(self.spawn_connect_task(
+                        flow_id.flow_tag,
+                        flow_id.peer_id,
+                        socket_addr.ip(),
+                        ServerPort::from(socket_addr.port()),
+                    ))
+                }
+                None => None,
+            };
+            if let Some(connecting_task) = connecting_task {
                 let connecting_state = Connecting {
                     peer_addr: socket_addr,
                     connecting_task,
@@ -692,8 +732,8 @@ impl TransportImpl {
             flow_tag,
             local_addr,
             peer_addr,
-            tls_reader,
-            tls_writer,
+            Box::new(tls_reader),
+            Box::new(tls_writer),
         )
         .await
         .map(|_| {
@@ -765,8 +805,8 @@ impl TransportImpl {
             flow_tag,
             local_addr,
             peer_addr,
-            tls_reader,
-            tls_writer,
+            Box::new(tls_reader),
+            Box::new(tls_writer),
         )
         .await
         .map(|_| {
@@ -894,7 +934,9 @@ impl TransportImpl {
     }
 
     /// Extract the socket address from the result
-    fn sock_addr(result: std::io::Result<SocketAddr>) -> Result<SocketAddr, TransportErrorCode> {
+    pub(crate) fn sock_addr(
+        result: std::io::Result<SocketAddr>,
+    ) -> Result<SocketAddr, TransportErrorCode> {
         result.map_err(|_| TransportErrorCode::InvalidSockAddr)
     }
 
@@ -908,6 +950,17 @@ impl TransportImpl {
             return Err(TransportErrorCode::TransportClientAlreadyRegistered);
         }
 
+        if self.config.backend == TransportBackend::Quic {
+            let quic_endpoint = self.init_quic_endpoint()?;
+            client_map.replace(ClientState {
+                accept_ports: HashMap::new(),
+                quic_endpoint: Some(quic_endpoint),
+                peer_map: HashMap::new(),
+                event_handler,
+            });
+            return Ok(());
+        }
+
         // Bind to the server ports.
         let mut listeners = Vec::new();
         for flow_config in &self.config.p2p_flows {
@@ -927,6 +980,7 @@ impl TransportImpl {
         }
         client_map.replace(ClientState {
             accept_ports,
+            quic_endpoint: None,
             peer_map: HashMap::new(),
             event_handler,
         });
@@ -939,15 +993,15 @@ impl TransportImpl {
 mod tests {
     use crate::transport::create_transport;
     use async_trait::async_trait;
-    use crossbeam_channel::{bounded, Sender};
+    use crossbeam_channel::{unbounded, Receiver, Sender};
     use ic_base_types::{NodeId, RegistryVersion};
-    use ic_config::transport::{TransportConfig, TransportFlowConfig};
+    use ic_config::transport::{TransportBackend, TransportConfig, TransportFlowConfig};
     use ic_crypto::utils::TempCryptoComponent;
     use ic_interfaces_transport::{
-        AsyncTransportEventHandler, FlowId, SendError, TransportErrorCode, TransportPayload,
-        TransportStateChange,
+        AsyncTransportEventHandler, FlowId, FlowTag, SendError, Transport, TransportErrorCode,
+        TransportPayload, TransportStateChange,
     };
-    use ic_logger::warn;
+    use ic_logger::{warn, ReplicaLogger};
     use ic_metrics::MetricsRegistry;
     use ic_protobuf::registry::node::v1::{
         connection_endpoint::Protocol, ConnectionEndpoint, FlowEndpoint, NodeRecord,
@@ -958,6 +1012,7 @@ mod tests {
     use ic_test_utilities::types::ids::{NODE_1, NODE_2};
     use ic_test_utilities::with_test_replica_logger;
     use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
     const NODE_ID_1: NodeId = NODE_1;
     const NODE_ID_2: NodeId = NODE_2;
@@ -968,23 +1023,33 @@ mod tests {
     const PORT_1: u16 = 65001;
     const PORT_2: u16 = 65002;
 
+    /// How long to wait for a transport event
+    const EVENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Transport events observed by a peer.
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        FlowUp,
+        FlowDown,
+        Message(Vec<u8>),
+    }
+
     struct FakeEventHandler {
-        connected: Sender<bool>,
+        events: Sender<Event>,
     }
 
     impl FakeEventHandler {
-        fn on_message(
-            &self,
-            _flow: FlowId,
-            _message: TransportPayload,
-        ) -> Option<TransportPayload> {
+        fn on_message(&self, _flow: FlowId, message: TransportPayload) -> Option<TransportPayload> {
+            self.events.send(Event::Message(message.0)).unwrap();
             None
         }
 
         fn on_state_change(&self, state_change: TransportStateChange) {
-            if let TransportStateChange::PeerFlowUp(_) = state_change {
-                self.connected.send(true).unwrap();
-            }
+            let event = match state_change {
+                TransportStateChange::PeerFlowUp(_) => Event::FlowUp,
+                TransportStateChange::PeerFlowDown(_) => Event::FlowDown,
+            };
+            self.events.send(event).unwrap();
         }
 
         fn on_error(&self, _flow: FlowId, _error: TransportErrorCode) {}
@@ -1010,101 +1075,293 @@ mod tests {
         }
     }
 
+    /// Two transports with one flow between them.
+    struct Peers {
+        transport_1: Arc<dyn Transport>,
+        transport_2: Arc<dyn Transport>,
+        events_1: Receiver<Event>,
+        events_2: Receiver<Event>,
+        /// The node record of node 1, as seen by node 2
+        node_record_2: NodeRecord,
+        flow_tag_1: FlowTag,
+        flow_tag_2: FlowTag,
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn should_handshake() {
-        let registry_version = REG_V1;
-        let (connected_1, done_1) = bounded(0);
-        let (connected_2, done_2) = bounded(0);
+        handshake(
+            TransportBackend::Tcp,
+            FLOW_TAG_1,
+            FLOW_TAG_2,
+            PORT_1,
+            PORT_2,
+        )
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_handshake_over_quic() {
+        // QUIC identifies the flows on a connection by their tag, so both
+        // peers must use the same tag.
+        handshake(
+            TransportBackend::Quic,
+            FLOW_TAG_1,
+            FLOW_TAG_1,
+            PORT_1,
+            PORT_2,
+        )
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_send_messages() {
+        send_messages(TransportBackend::Tcp, FLOW_TAG_1, FLOW_TAG_2, 65003, 65004).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_send_messages_over_quic() {
+        send_messages(TransportBackend::Quic, FLOW_TAG_1, FLOW_TAG_1, 65005, 65006).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_keep_idle_flow_up_with_heartbeats() {
+        keep_idle_flow_up(TransportBackend::Tcp, FLOW_TAG_1, FLOW_TAG_2, 65007, 65008).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_keep_idle_flow_up_with_heartbeats_over_quic() {
+        keep_idle_flow_up(TransportBackend::Quic, FLOW_TAG_1, FLOW_TAG_1, 65009, 65010).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_reconnect() {
+        reconnect(TransportBackend::Tcp, FLOW_TAG_1, FLOW_TAG_2, 65011, 65012).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn should_reconnect_over_quic() {
+        reconnect(TransportBackend::Quic, FLOW_TAG_1, FLOW_TAG_1, 65013, 65014).await;
+    }
+
+    async fn handshake(
+        backend: TransportBackend,
+        flow_tag_1: u32,
+        flow_tag_2: u32,
+        port_1: u16,
+        port_2: u16,
+    ) {
         with_test_replica_logger(|logger| {
-            // Setup registry and crypto component
-            let registry_and_data = empty_registry();
-            let crypto_1 =
-                temp_crypto_component_with_tls_keys_in_registry(&registry_and_data, NODE_ID_1);
-            let crypto_2 =
-                temp_crypto_component_with_tls_keys_in_registry(&registry_and_data, NODE_ID_2);
-            registry_and_data.registry.update_to_latest_version();
-
-            let mut client_config_1 = TransportConfig {
-                node_ip: "0.0.0.0".to_string(),
-                p2p_flows: Vec::new(),
-            };
-            let flow_internal_1 = TransportFlowConfig {
-                flow_tag: FLOW_TAG_1,
-                server_port: PORT_1,
-                queue_size: 10,
-            };
-            client_config_1.p2p_flows.push(flow_internal_1);
-            let control_plane_1 = create_transport(
-                NODE_ID_1,
-                client_config_1,
-                registry_version,
-                MetricsRegistry::new(),
-                Arc::new(crypto_1),
-                tokio::runtime::Handle::current(),
-                logger.clone(),
-            );
+            let peers = setup_peers(&logger, backend, flow_tag_1, flow_tag_2, port_1, port_2);
+            expect_event(&peers.events_1, Event::FlowUp);
+            expect_event(&peers.events_2, Event::FlowUp);
+            warn!(logger, "done");
+        });
+    }
+
+    async fn send_messages(
+        backend: TransportBackend,
+        flow_tag_1: u32,
+        flow_tag_2: u32,
+        port_1: u16,
+        port_2: u16,
+    ) {
+        with_test_replica_logger(|logger| {
+            let peers = setup_peers(&logger, backend, flow_tag_1, flow_tag_2, port_1, port_2);
+            expect_event(&peers.events_1, Event::FlowUp);
+            expect_event(&peers.events_2, Event::FlowUp);
+
+            for i in 0..10u8 {
+                peers
+                    .transport_1
+                    .send(&NODE_ID_2, peers.flow_tag_1, TransportPayload(vec![i; 100]))
+                    .expect("send");
+                expect_event(&peers.events_2, Event::Message(vec![i; 100]));
+            }
+            peers
+                .transport_2
+                .send(
+                    &NODE_ID_1,
+                    peers.flow_tag_2,
+                    TransportPayload(vec![42; 1 << 16]),
+                )
+                .expect("send");
+            expect_event(&peers.events_1, Event::Message(vec![42; 1 << 16]));
+        });
+    }
+
+    async fn keep_idle_flow_up(
+        backend: TransportBackend,
+        flow_tag_1: u32,
+        flow_tag_2: u32,
+        port_1: u16,
+        port_2: u16,
+    ) {
+        with_test_replica_logger(|logger| {
+            let peers = setup_peers(&logger, backend, flow_tag_1, flow_tag_2, port_1, port_2);
+            expect_event(&peers.events_1, Event::FlowUp);
+            expect_event(&peers.events_2, Event::FlowUp);
+
+            // Stay idle for more than twice the time a peer waits for a
+            // message before considering the flow down. Only heartbeats are
+            // exchanged in the meantime.
+            std::thread::sleep(Duration::from_secs(12));
+            assert_eq!(peers.events_1.try_recv().ok(), None);
+            assert_eq!(peers.events_2.try_recv().ok(), None);
+
+            peers
+                .transport_1
+                .send(
+                    &NODE_ID_2,
+                    peers.flow_tag_1,
+                    TransportPayload(vec![1, 2, 3]),
+                )
+                .expect("send");
+            expect_event(&peers.events_2, Event::Message(vec![1, 2, 3]));
+        });
+    }
+
+    async fn reconnect(
+        backend: TransportBackend,
+        flow_tag_1: u32,
+        flow_tag_2: u32,
+        port_1: u16,
+        port_2: u16,
+    ) {
+        with_test_replica_logger(|logger| {
+            let peers = setup_peers(&logger, backend, flow_tag_1, flow_tag_2, port_1, port_2);
+            expect_event(&peers.events_1, Event::FlowUp);
+            expect_event(&peers.events_2, Event::FlowUp);
+
+            // Node 2 tears down the flow, node 1 notices and keeps retrying.
+            peers
+                .transport_2
+                .stop_connections(&NODE_ID_1)
+                .expect("stop_connections");
+            expect_event(&peers.events_1, Event::FlowDown);
+
+            // Once node 2 accepts the peer again, the flow is re-established.
+            peers
+                .transport_2
+                .start_connections(&NODE_ID_1, &peers.node_record_2, REG_V1)
+                .expect("start_connections");
+            expect_event(&peers.events_1, Event::FlowUp);
+            expect_event(&peers.events_2, Event::FlowUp);
+
+            peers
+                .transport_1
+                .send(
+                    &NODE_ID_2,
+                    peers.flow_tag_1,
+                    TransportPayload(vec![1, 2, 3]),
+                )
+                .expect("send");
+            expect_event(&peers.events_2, Event::Message(vec![1, 2, 3]));
+            peers
+                .transport_2
+                .send(
+                    &NODE_ID_1,
+                    peers.flow_tag_2,
+                    TransportPayload(vec![4, 5, 6]),
+                )
+                .expect("send");
+            expect_event(&peers.events_1, Event::Message(vec![4, 5, 6]));
+        });
+    }
 
-            let mut client_config_2 = TransportConfig {
+    /// Waits for `expected`, skipping other events, and panics if it is not
+    /// observed within `EVENT_TIMEOUT`.
+    fn expect_event(events: &Receiver<Event>, expected: Event) {
+        let deadline = Instant::now() + EVENT_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match events.recv_timeout(remaining) {
+                Ok(event) if event == expected => return,
+                Ok(_) => continue,
+                Err(e) => panic!("{:?} not observed: {:?}", expected, e),
+            }
+        }
+    }
+
+    /// Creates two transports that connect to each other over `127.0.0.1`.
+    fn setup_peers(
+        logger: &ReplicaLogger,
+        backend: TransportBackend,
+        flow_tag_1: u32,
+        flow_tag_2: u32,
+        port_1: u16,
+        port_2: u16,
+    ) -> Peers {
+        let registry_version = REG_V1;
+        // Setup registry and crypto component
+        let registry_and_data = empty_registry();
+        let crypto_1 =
+            temp_crypto_component_with_tls_keys_in_registry(&registry_and_data, NODE_ID_1);
+        let crypto_2 =
+            temp_crypto_component_with_tls_keys_in_registry(&registry_and_data, NODE_ID_2);
+        registry_and_data.registry.update_to_latest_version();
+
+        let transport = |node_id, crypto, flow_tag, server_port| {
+            let config = TransportConfig {
                 node_ip: "0.0.0.0".to_string(),
-                p2p_flows: Vec::new(),
-            };
-            let flow_internal_2 = TransportFlowConfig {
-                flow_tag: FLOW_TAG_2,
-                server_port: PORT_2,
-                queue_size: 10,
+                p2p_flows: vec![TransportFlowConfig {
+                    flow_tag,
+                    server_port,
+                    queue_size: 10,
+                }],
+                backend,
             };
-            client_config_2.p2p_flows.push(flow_internal_2);
-            let control_plane_2 = create_transport(
-                NODE_ID_2,
-                client_config_2,
+            create_transport(
+                node_id,
+                config,
                 registry_version,
                 MetricsRegistry::new(),
-                Arc::new(crypto_2),
+                Arc::new(crypto),
                 tokio::runtime::Handle::current(),
                 logger.clone(),
-            );
-
-            let fake_event_handler_1 = Arc::new(FakeEventHandler {
-                connected: connected_1,
-            });
-            control_plane_1
-                .register_client(fake_event_handler_1)
-                .expect("register_client");
-            let mut node_record_1: NodeRecord = Default::default();
-            node_record_1.p2p_flow_endpoints.push(FlowEndpoint {
-                flow_tag: FLOW_TAG_1,
+            )
+        };
+        let node_record = |flow_tag, port: u16| {
+            let mut node_record: NodeRecord = Default::default();
+            node_record.p2p_flow_endpoints.push(FlowEndpoint {
+                flow_tag,
                 endpoint: Some(ConnectionEndpoint {
                     ip_addr: "127.0.0.1".to_string(),
-                    port: PORT_2 as u32,
+                    port: port as u32,
                     protocol: Protocol::P2p1Tls13 as i32,
                 }),
             });
-            control_plane_1
-                .start_connections(&NODE_ID_2, &node_record_1, REG_V1)
-                .expect("start_connections");
+            node_record
+        };
 
-            let fake_event_handler_2 = Arc::new(FakeEventHandler {
-                connected: connected_2,
-            });
-            control_plane_2
-                .register_client(fake_event_handler_2)
-                .expect("register_client");
-            let mut node_record_2: NodeRecord = Default::default();
-            node_record_2.p2p_flow_endpoints.push(FlowEndpoint {
-                flow_tag: FLOW_TAG_2,
-                endpoint: Some(ConnectionEndpoint {
-                    ip_addr: "127.0.0.1".to_string(),
-                    port: PORT_1 as u32,
-                    protocol: Protocol::P2p1Tls13 as i32,
-                }),
-            });
-            control_plane_2
-                .start_connections(&NODE_ID_1, &node_record_2, REG_V1)
-                .expect("start_connections");
-            assert_eq!(done_1.recv(), Ok(true));
-            assert_eq!(done_2.recv(), Ok(true));
-            warn!(logger, "done");
-        });
+        let transport_1 = transport(NODE_ID_1, crypto_1, flow_tag_1, port_1);
+        let transport_2 = transport(NODE_ID_2, crypto_2, flow_tag_2, port_2);
+        let (sender_1, events_1) = unbounded();
+        let (sender_2, events_2) = unbounded();
+        transport_1
+            .register_client(Arc::new(FakeEventHandler { events: sender_1 }))
+            .expect("register_client");
+        transport_2
+            .register_client(Arc::new(FakeEventHandler { events: sender_2 }))
+            .expect("register_client");
+
+        let node_record_1 = node_record(flow_tag_1, port_2);
+        transport_1
+            .start_connections(&NODE_ID_2, &node_record_1, REG_V1)
+            .expect("start_connections");
+        let node_record_2 = node_record(flow_tag_2, port_1);
+        transport_2
+            .start_connections(&NODE_ID_1, &node_record_2, REG_V1)
+            .expect("start_connections");
+
+        Peers {
+            transport_1,
+            transport_2,
+            events_1,
+            events_2,
+            node_record_2,
+            flow_tag_1: FlowTag::from(flow_tag_1),
+            flow_tag_2: FlowTag::from(flow_tag_2),
+        }
     }
 
     struct RegistryAndDataProvider {
@@ -13,6 +13,8 @@
 //! connection: one each for send and receive. The connections established by
 //! the control plane are split into read and write halves and given to these
 //! two tasks.
+//! With the QUIC backend, the halves are the receive and send sides of the
+//! QUIC stream that carries the flow.
 //!
 //! The data plane module implements data plane functionality for
 //! [`TransportImpl`](../types/struct.TransportImpl.html).
@@ -20,12 +22,11 @@
 use crate::{
     metrics::DataPlaneMetrics,
     types::{
-        Connected, ConnectionRole, ConnectionState, SendQueueReader, TransportHeader,
-        TransportImpl, TRANSPORT_FLAGS_IS_HEARTBEAT, TRANSPORT_FLAGS_SENDER_ERROR,
+        Connected, ConnectionRole, ConnectionState, FlowReader, FlowWriter, SendQueueReader,
+        TransportHeader, TransportImpl, TRANSPORT_FLAGS_IS_HEARTBEAT, TRANSPORT_FLAGS_SENDER_ERROR,
         TRANSPORT_HEADER_SIZE,
     },
 };
-use ic_crypto_tls_interfaces::{TlsReadHalf, TlsWriteHalf};
 use ic_interfaces_transport::{
     AsyncTransportEventHandler, FlowId, TransportErrorCode, TransportPayload, TransportStateChange,
 };
@@ -129,7 +130,7 @@ impl TransportImpl {
         flow_id: FlowId,
         flow_label: String,
         mut send_queue_reader: Box<dyn SendQueueReader + Send + Sync>,
-        mut writer: Box<TlsWriteHalf>,
+        mut writer: FlowWriter,
         metrics: DataPlaneMetrics,
         state: Weak<TransportImpl>,
     ) {
@@ -221,7 +222,7 @@ impl TransportImpl {
         flow_id: FlowId,
         flow_label: String,
         event_handler: Arc<dyn AsyncTransportEventHandler>,
-        mut reader: Box<TlsReadHalf>,
+        mut reader: FlowReader,
         metrics: DataPlaneMetrics,
         state: Weak<TransportImpl>,
     ) {
@@ -301,7 +302,7 @@ impl TransportImpl {
     /// socket. The timeout is for each socket read (header, payload chunks)
     /// and not the full message.
     async fn read_one_message(
-        reader: &mut Box<TlsReadHalf>,
+        reader: &mut FlowReader,
         timeout: Duration,
     ) -> Result<(TransportHeader, Option<TransportPayload>), ReadError> {
         // Read the hdr
@@ -337,7 +338,7 @@ impl TransportImpl {
 
     /// Reads the requested bytes from the socket with a timeout
     async fn read_from_socket(
-        reader: &mut Box<TlsReadHalf>,
+        reader: &mut FlowReader,
         buf: &mut [u8],
         timeout: Duration,
     ) -> Result<(), ReadError> {
@@ -384,8 +385,8 @@ impl TransportImpl {
         flow_id: FlowId,
         role: ConnectionRole,
         peer_addr: SocketAddr,
-        reader: Box<TlsReadHalf>,
-        writer: Box<TlsWriteHalf>,
+        reader: FlowReader,
+        writer: FlowWriter,
     ) -> Result<Arc<dyn AsyncTransportEventHandler>, TransportErrorCode> {
         let mut client_map = self.client_map.write().unwrap();
         let client_state = match client_map.as_mut() {
@@ -484,8 +485,8 @@ impl TransportImpl {
         flow_id: FlowId,
         role: ConnectionRole,
         peer_addr: SocketAddr,
-        reader: Box<TlsReadHalf>,
-        writer: Box<TlsWriteHalf>,
+        reader: FlowReader,
+        writer: FlowWriter,
     ) -> Result<(), TransportErrorCode> {
         self.on_connect_setup(flow_id, role, peer_addr, reader, writer)?
             // Notify the client that peer flow is up.
@@ -35,6 +35,7 @@
 mod control_plane;
 mod data_plane;
 mod metrics;
+mod quic;
 pub mod transport;
 mod types;
 mod utils;
@@ -1,7 +1,7 @@
 //! Transport related metrics
 
 use ic_metrics::{buckets::decimal_buckets, MetricsRegistry};
-use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec};
+use prometheus::{HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
 
 #[derive(Clone)]
 pub(crate) struct ControlPlaneMetrics {
@@ -17,6 +17,11 @@ pub(crate) struct ControlPlaneMetrics {
     pub(crate) tcp_client_handshake_failed: IntCounterVec,
     pub(crate) tcp_client_handshake_success: IntCounterVec,
     pub(crate) retry_connection: IntCounterVec,
+    pub(crate) quic_accepts: IntCounter,
+    pub(crate) quic_connects: IntCounterVec,
+    pub(crate) quic_handshake_failed: IntCounterVec,
+    pub(crate) quic_flow_streams: IntCounterVec,
+    pub(crate) quic_flow_stream_failed: IntCounterVec,
 }
 
 impl ControlPlaneMetrics {
@@ -82,6 +87,30 @@ impl ControlPlaneMetrics {
                 "Connection retries to reconnect to a peer from Transport",
                 &["peer_id", "flow_tag"],
             ),
+            quic_accepts: metrics_registry.int_counter(
+                "transport_quic_accepts",
+                "Total incoming QUIC connections in server mode",
+            ),
+            quic_connects: metrics_registry.int_counter_vec(
+                "transport_quic_connects",
+                "Total outgoing QUIC connects in client mode",
+                &["peer_id"],
+            ),
+            quic_handshake_failed: metrics_registry.int_counter_vec(
+                "transport_quic_handshake_failed",
+                "Error completing the QUIC handshake in the given role",
+                &["role"],
+            ),
+            quic_flow_streams: metrics_registry.int_counter_vec(
+                "transport_quic_flow_streams",
+                "Flow streams set up on QUIC connections in the given role",
+                &["role", "flow_tag"],
+            ),
+            quic_flow_stream_failed: metrics_registry.int_counter_vec(
+                "transport_quic_flow_stream_failed",
+                "Error setting up a flow stream on a QUIC connection in the given role",
+                &["role"],
+            ),
         }
     }
 }
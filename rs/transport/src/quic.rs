@@ -0,0 +1,583 @@
+//! QUIC backend - Transport connection management over QUIC.
+//!
+//! Instead of a TLS/TCP connection per flow, the QUIC backend keeps a single
+//! QUIC connection per peer and carries each flow on its own bidirectional
+//! stream of that connection. A slow flow then only stalls its own stream,
+//! instead of the other flows with the same peer. The TLS handshake of the
+//! connection uses the node certificates, with the configurations provided by
+//! the crypto component.
+//!
+//! The connection roles are the same as for TCP. The client opens the
+//! connection to the peer, if there is none yet, and then one stream per flow.
+//! It announces the flow by sending the flow tag at the start of the stream.
+//! The server accepts connections from allowed clients, and passes each
+//! announced stream to the data plane. From then on, IOs, heartbeats and
+//! reconnects work the same way as for TCP.
+//!
+//! The QUIC module implements the QUIC backend for
+//! [`TransportImpl`](../types/struct.TransportImpl.html).
+
+use crate::types::{ConnectionRole, QuicConnectionCell, QuicEndpointState, TransportImpl};
+use futures::future::{AbortHandle, Abortable, Aborted};
+use futures::StreamExt;
+use ic_base_types::{NodeId, PrincipalId};
+use ic_crypto_tls_interfaces::{AllowedClients, SomeOrAllNodes, TlsPublicKeyCert};
+use ic_interfaces_transport::{FlowTag, TransportErrorCode};
+use ic_logger::{info, warn};
+use openssl::nid::Nid;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+/// Time to wait before retrying an unsuccessful connection attempt
+const CONNECT_RETRY_SECONDS: u64 = 3;
+
+/// Time to wait for the QUIC handshake (for both client/server sides), and for
+/// the flow tag of a new stream
+const QUIC_HANDSHAKE_TIMEOUT_SECONDS: u64 = 30;
+
+/// Server name sent by the client. Server names are not verified, the server
+/// is authenticated by its node ID instead.
+const SERVER_NAME: &str = "domain.is-irrelevant-as-hostname-verification-is.disabled";
+
+/// Size of the flow tag that announces the flow at the start of a stream
+const FLOW_TAG_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Implementation for the QUIC backend
+impl TransportImpl {
+    /// Binds the QUIC endpoint to the server port of the first flow, and
+    /// starts accepting connections on it.
+    pub(crate) fn init_quic_endpoint(&self) -> Result<QuicEndpointState, TransportErrorCode> {
+        let flow_config = self
+            .config
+            .p2p_flows
+            .first()
+            .ok_or(TransportErrorCode::FlowNotFound)?;
+        let local_addr = SocketAddr::new(self.node_ip, flow_config.server_port);
+
+        let mut builder = quinn::Endpoint::builder();
+        builder.listen(self.quic_server_config()?);
+        // Binding registers the endpoint driver with the tokio runtime.
+        let _guard = self.tokio_runtime.enter();
+        let (endpoint, incoming) = builder.bind(&local_addr).map_err(|e| {
+            warn!(
+                self.log,
+                "Quic::init_quic_endpoint(): Failed to bind: local_addr = {:?}, error = {:?}",
+                local_addr,
+                e
+            );
+            TransportErrorCode::ServerSocketBindFailed
+        })?;
+        let accept_task = self.spawn_quic_accept_task(local_addr, incoming);
+        Ok(QuicEndpointState {
+            endpoint,
+            accept_task,
+        })
+    }
+
+    /// Refreshes the server configuration of the endpoint, so that clients
+    /// are verified against the current registry version.
+    pub(crate) fn update_quic_server_config(&self, quic_endpoint: &QuicEndpointState) {
+        if let Ok(server_config) = self.quic_server_config() {
+            quic_endpoint
+                .endpoint
+                .set_server_config(Some(server_config));
+        }
+    }
+
+    /// Closes the QUIC connection to a peer, if any.
+    pub(crate) fn close_quic_connection(&self, peer_id: NodeId) {
+        let quic_connections = self.quic_connections.clone();
+        self.tokio_runtime.spawn(async move {
+            if let Some(connection_cell) = quic_connections.lock().await.remove(&peer_id) {
+                if let Some(connection) = connection_cell.get() {
+                    connection.close(quinn::VarInt::from_u32(0), b"peer removed");
+                }
+            }
+        });
+    }
+
+    /// Builds the server configuration. The TLS handshake accepts any node
+    /// whose certificate matches the registry, whether the node is an allowed
+    /// client is checked once the connection is established.
+    fn quic_server_config(&self) -> Result<quinn::ServerConfig, TransportErrorCode> {
+        let registry_version = *self.registry_version.read().unwrap();
+        let allowed_clients = AllowedClients::new(SomeOrAllNodes::All, HashSet::new())
+            .expect("All nodes are never empty");
+        let tls_config = self
+            .crypto
+            .tls_server_config(allowed_clients, registry_version)
+            .map_err(|e| {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::quic_server_config(): failed: node = {:?}, registry_version = {}, error = {:?}",
+                    self.node_id,
+                    registry_version,
+                    e
+                );
+                TransportErrorCode::PeerTlsInfoNotFound
+            })?;
+        let mut server_config = quinn::ServerConfig::default();
+        server_config.crypto = Arc::new(tls_config);
+        Ok(server_config)
+    }
+
+    /// Builds the client configuration for connecting to `peer_id`.
+    fn quic_client_config(
+        &self,
+        peer_id: NodeId,
+    ) -> Result<quinn::ClientConfig, TransportErrorCode> {
+        let registry_version = *self.registry_version.read().unwrap();
+        let tls_config = self
+            .crypto
+            .tls_client_config(peer_id, registry_version)
+            .map_err(|e| {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::quic_client_config(): failed: node = {:?}, peer = {:?}, error = {:?}",
+                    self.node_id,
+                    peer_id,
+                    e
+                );
+                TransportErrorCode::PeerTlsInfoNotFound
+            })?;
+        Ok(quinn::ClientConfig {
+            transport: Arc::new(quinn::TransportConfig::default()),
+            crypto: Arc::new(tls_config),
+        })
+    }
+
+    /// Starts the async task to accept the incoming QUIC connections.
+    fn spawn_quic_accept_task(
+        &self,
+        local_addr: SocketAddr,
+        mut incoming: quinn::Incoming,
+    ) -> AbortHandle {
+        let weak_self = self.weak_self.read().unwrap().clone();
+        let tokio_runtime = self.tokio_runtime.clone();
+        let metrics = self.control_plane_metrics.clone();
+        let accept_task = async move {
+            while let Some(connecting) = incoming.next().await {
+                // If the TransportImpl has been deleted, abort.
+                let arc_self = match weak_self.upgrade() {
+                    Some(arc_self) => arc_self,
+                    _ => return,
+                };
+                metrics.quic_accepts.inc();
+                tokio_runtime.spawn(async move {
+                    arc_self
+                        .accept_quic_connection(local_addr, connecting)
+                        .await;
+                });
+            }
+        };
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let log_cl = self.log.clone();
+        self.tokio_runtime.spawn(async move {
+            if let Err(Aborted) = Abortable::new(accept_task, abort_registration).await {
+                warn!(
+                    log_cl,
+                    "Quic: accept task aborted: local_addr = {:?}", local_addr
+                );
+            }
+        });
+        abort_handle
+    }
+
+    /// Completes the server side handshake of an incoming connection, and
+    /// passes the flow streams that the peer opens to the data plane.
+    async fn accept_quic_connection(
+        self: Arc<Self>,
+        local_addr: SocketAddr,
+        connecting: quinn::Connecting,
+    ) {
+        let new_connection = match timeout(
+            Duration::from_secs(QUIC_HANDSHAKE_TIMEOUT_SECONDS),
+            connecting,
+        )
+        .await
+        {
+            Ok(Ok(new_connection)) => new_connection,
+            Ok(Err(e)) => {
+                self.quic_handshake_failed(ConnectionRole::Server);
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::accept_quic_connection(): handshake failed: node = {:?}/{:?}, error = {:?}",
+                    self.node_id,
+                    local_addr,
+                    e
+                );
+                return;
+            }
+            Err(_) => {
+                self.quic_handshake_failed(ConnectionRole::Server);
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::accept_quic_connection(): handshake timed out: node = {:?}/{:?}",
+                    self.node_id,
+                    local_addr
+                );
+                return;
+            }
+        };
+        let connection = new_connection.connection;
+        let peer_addr = connection.remote_address();
+        let peer_id = match peer_node_id(&connection) {
+            Ok(peer_id) if self.allowed_clients.read().unwrap().contains(&peer_id) => peer_id,
+            result => {
+                self.quic_handshake_failed(ConnectionRole::Server);
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::accept_quic_connection(): client not allowed: node = {:?}/{:?}, \
+                     peer = {:?}/{:?}",
+                    self.node_id,
+                    local_addr,
+                    result,
+                    peer_addr
+                );
+                connection.close(quinn::VarInt::from_u32(0), b"client not allowed");
+                return;
+            }
+        };
+        let connection_cell = QuicConnectionCell::default();
+        let _ = connection_cell.set(connection);
+        self.quic_connections
+            .lock()
+            .await
+            .insert(peer_id, connection_cell);
+
+        let mut bi_streams = new_connection.bi_streams;
+        while let Some(stream) = bi_streams.next().await {
+            let (send, recv) = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    info!(
+                        self.log,
+                        "Quic::accept_quic_connection(): connection closed: peer = {:?}/{:?}, \
+                         error = {:?}",
+                        peer_id,
+                        peer_addr,
+                        e
+                    );
+                    return;
+                }
+            };
+            let arc_self = self.clone();
+            self.tokio_runtime.spawn(async move {
+                // Errors are reported in accept_quic_flow_stream
+                let _ = arc_self
+                    .accept_quic_flow_stream(peer_id, local_addr, peer_addr, send, recv)
+                    .await;
+            });
+        }
+    }
+
+    /// Reads the flow tag from a stream opened by the client, and passes the
+    /// stream to the data plane.
+    async fn accept_quic_flow_stream(
+        &self,
+        peer_id: NodeId,
+        local_addr: SocketAddr,
+        peer_addr: SocketAddr,
+        send: quinn::SendStream,
+        mut recv: quinn::RecvStream,
+    ) -> Result<(), TransportErrorCode> {
+        let mut flow_tag = [0u8; FLOW_TAG_SIZE];
+        let result = timeout(
+            Duration::from_secs(QUIC_HANDSHAKE_TIMEOUT_SECONDS),
+            recv.read_exact(&mut flow_tag),
+        )
+        .await;
+        if !matches!(result, Ok(Ok(()))) {
+            self.quic_flow_stream_failed(ConnectionRole::Server);
+            warn!(
+                every_n_seconds => 30,
+                self.log,
+                "Quic::accept_quic_flow_stream(): failed to read flow tag: \
+                 peer = {:?}/{:?}, result = {:?}",
+                peer_id,
+                peer_addr,
+                result
+            );
+            return Err(TransportErrorCode::FlowNotFound);
+        }
+        let flow_tag = FlowTag::from(u32::from_le_bytes(flow_tag));
+        self.process_handshake_result(
+            peer_id,
+            ConnectionRole::Server,
+            flow_tag,
+            local_addr,
+            peer_addr,
+            Box::new(recv),
+            Box::new(send),
+        )
+        .await
+        .map(|_| self.quic_flow_stream_opened(ConnectionRole::Server, flow_tag))
+    }
+
+    /// Spawn a task that tries to open the stream of a flow with a peer
+    /// (forever, or until the stream is established or peer is removed)
+    pub(crate) fn spawn_quic_connect_task(
+        &self,
+        flow_tag: FlowTag,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+        endpoint: quinn::Endpoint,
+    ) -> AbortHandle {
+        let weak_self = self.weak_self.read().unwrap().clone();
+        let connect_task = async move {
+            // Loop till the flow stream is established
+            let mut retries: u32 = 0;
+            loop {
+                retries += 1;
+                // If the TransportImpl has been deleted, abort.
+                let arc_self = match weak_self.upgrade() {
+                    Some(arc_self) => arc_self,
+                    _ => return,
+                };
+                match arc_self
+                    .open_quic_flow_stream(&endpoint, peer_id, peer_addr, flow_tag)
+                    .await
+                {
+                    Ok(()) => {
+                        info!(
+                            arc_self.log,
+                            "Quic::spawn_quic_connect_task(): flow stream established: \
+                             flow_tag = {:?}, peer = {:?}/{:?}, retries = {}",
+                            flow_tag,
+                            peer_id,
+                            peer_addr,
+                            retries,
+                        );
+                        return;
+                    }
+                    Err(e) => {
+                        info!(
+                            every_n_seconds => 300,
+                            arc_self.log,
+                            "Quic::spawn_quic_connect_task(): failed to establish flow stream: \
+                             flow_tag = {:?}, peer = {:?}/{:?}, error = {:?}, retries = {}",
+                            flow_tag,
+                            peer_id,
+                            peer_addr,
+                            e,
+                            retries
+                        );
+                        sleep(Duration::from_secs(CONNECT_RETRY_SECONDS)).await;
+                    }
+                }
+            }
+        };
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let log_cl = self.log.clone();
+        self.tokio_runtime.spawn(async move {
+            if let Err(Aborted) = Abortable::new(connect_task, abort_registration).await {
+                warn!(
+                    log_cl,
+                    "Quic: connect task aborted: flow_tag = {:?}, peer_id = {:?}",
+                    flow_tag,
+                    peer_id
+                );
+            }
+        });
+        abort_handle
+    }
+
+    /// Opens the stream of a flow on the connection to the peer, announces
+    /// the flow, and passes the stream to the data plane.
+    async fn open_quic_flow_stream(
+        &self,
+        endpoint: &quinn::Endpoint,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+        flow_tag: FlowTag,
+    ) -> Result<(), TransportErrorCode> {
+        let local_addr = Self::sock_addr(endpoint.local_addr())?;
+        let connection = self.quic_connection(endpoint, peer_id, peer_addr).await?;
+        let (mut send, recv) = match connection.open_bi().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                // The connection is gone, establish a new one on the next attempt.
+                self.quic_connections.lock().await.remove(&peer_id);
+                self.quic_flow_stream_failed(ConnectionRole::Client);
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::open_quic_flow_stream(): failed to open stream: \
+                     flow_tag = {:?}, peer = {:?}/{:?}, error = {:?}",
+                    flow_tag,
+                    peer_id,
+                    peer_addr,
+                    e
+                );
+                return Err(TransportErrorCode::FlowConnectionDown);
+            }
+        };
+        let flow_tag_bytes = flow_tag.get().to_le_bytes();
+        if let Err(e) = send.write_all(&flow_tag_bytes).await {
+            self.quic_flow_stream_failed(ConnectionRole::Client);
+            warn!(
+                every_n_seconds => 30,
+                self.log,
+                "Quic::open_quic_flow_stream(): failed to send flow tag: \
+                 flow_tag = {:?}, peer = {:?}/{:?}, error = {:?}",
+                flow_tag,
+                peer_id,
+                peer_addr,
+                e
+            );
+            return Err(TransportErrorCode::FlowConnectionDown);
+        }
+        self.process_handshake_result(
+            peer_id,
+            ConnectionRole::Client,
+            flow_tag,
+            local_addr,
+            peer_addr,
+            Box::new(recv),
+            Box::new(send),
+        )
+        .await
+        .map(|_| self.quic_flow_stream_opened(ConnectionRole::Client, flow_tag))
+    }
+
+    /// Returns the connection to the peer, and establishes it if there is
+    /// none yet. Flows that connect concurrently wait for the same handshake.
+    /// The map of connections is only locked to look up the peer's entry, so
+    /// that a slow or unreachable peer does not hold up connections to the
+    /// other peers.
+    async fn quic_connection(
+        &self,
+        endpoint: &quinn::Endpoint,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> Result<quinn::Connection, TransportErrorCode> {
+        let connection_cell = self
+            .quic_connections
+            .lock()
+            .await
+            .entry(peer_id)
+            .or_default()
+            .clone();
+        let connection = connection_cell
+            .get_or_try_init(|| self.connect_quic(endpoint, peer_id, peer_addr))
+            .await?;
+        Ok(connection.clone())
+    }
+
+    /// Establishes a new connection to the peer.
+    async fn connect_quic(
+        &self,
+        endpoint: &quinn::Endpoint,
+        peer_id: NodeId,
+        peer_addr: SocketAddr,
+    ) -> Result<quinn::Connection, TransportErrorCode> {
+        self.control_plane_metrics
+            .quic_connects
+            .with_label_values(&[&peer_id.to_string()])
+            .inc();
+        let client_config = self.quic_client_config(peer_id)?;
+        let connecting = endpoint
+            .connect_with(client_config, &peer_addr, SERVER_NAME)
+            .map_err(|e| {
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::quic_connection(): failed to connect: peer = {:?}/{:?}, error = {:?}",
+                    peer_id,
+                    peer_addr,
+                    e
+                );
+                TransportErrorCode::ConnectOsError
+            })?;
+        let new_connection = match timeout(
+            Duration::from_secs(QUIC_HANDSHAKE_TIMEOUT_SECONDS),
+            connecting,
+        )
+        .await
+        {
+            Ok(Ok(new_connection)) => new_connection,
+            Ok(Err(e)) => {
+                self.quic_handshake_failed(ConnectionRole::Client);
+                warn!(
+                    every_n_seconds => 30,
+                    self.log,
+                    "Quic::quic_connection(): handshake failed: peer = {:?}/{:?}, error = {:?}",
+                    peer_id,
+                    peer_addr,
+                    e
+                );
+                return Err(TransportErrorCode::PeerTlsInfoNotFound);
+            }
+            Err(_) => {
+                self.quic_handshake_failed(ConnectionRole::Client);
+                return Err(TransportErrorCode::TimeoutExpired);
+            }
+        };
+        Ok(new_connection.connection)
+    }
+
+    fn quic_handshake_failed(&self, role: ConnectionRole) {
+        self.control_plane_metrics
+            .quic_handshake_failed
+            .with_label_values(&[role_label(&role)])
+            .inc();
+    }
+
+    fn quic_flow_stream_opened(&self, role: ConnectionRole, flow_tag: FlowTag) {
+        self.control_plane_metrics
+            .quic_flow_streams
+            .with_label_values(&[role_label(&role), &flow_tag.to_string()])
+            .inc();
+    }
+
+    fn quic_flow_stream_failed(&self, role: ConnectionRole) {
+        self.control_plane_metrics
+            .quic_flow_stream_failed
+            .with_label_values(&[role_label(&role)])
+            .inc();
+    }
+}
+
+fn role_label(role: &ConnectionRole) -> &'static str {
+    match role {
+        ConnectionRole::Client => "client",
+        ConnectionRole::Server => "server",
+    }
+}
+
+/// Returns the node ID of the authenticated peer of a connection, i.e. the
+/// subject common name of the certificate it presented.
+fn peer_node_id(connection: &quinn::Connection) -> Result<NodeId, String> {
+    let certs = connection
+        .authentication_data()
+        .peer_certificates
+        .ok_or_else(|| "missing peer certificates".to_string())?;
+    let cert = certs
+        .iter()
+        .next()
+        .ok_or_else(|| "empty peer certificate chain".to_string())?;
+    let cert = TlsPublicKeyCert::new_from_der(cert.0.clone()).map_err(|e| e.internal_error)?;
+    let common_name = cert
+        .as_x509()
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .ok_or_else(|| "missing subject common name".to_string())?
+        .data()
+        .as_utf8()
+        .map_err(|e| format!("ASN1 to UTF-8 conversion error: {}", e))?;
+    let principal_id = PrincipalId::from_str(common_name.as_ref())
+        .map_err(|e| format!("Principal ID parse error: {}", e))?;
+    Ok(NodeId::from(principal_id))
+}
@@ -41,6 +41,11 @@
 //! client's on_message(flow_id, message) callback is invoked for
 //! messages delivery.
 //!
+//! With the QUIC backend (see `TransportConfig::backend`), a flow is a stream
+//! of the QUIC connection with the peer rather than a TCP connection of its
+//! own. The control plane then sets up the connection and its streams, and
+//! the data plane performs IOs on the stream as described above.
+//!
 //! ```text
 //! +-----------------------------------------------------+
 //! |    Transport Client (Gossip)                        |
@@ -77,7 +82,7 @@ use ic_interfaces_transport::{
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_protobuf::registry::node::v1::NodeRecord;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::net::IpAddr;
 use std::str::FromStr;
 use std::sync::{Arc, RwLock, Weak};
@@ -102,6 +107,7 @@ impl TransportImpl {
             config,
             allowed_clients: Arc::new(RwLock::new(BTreeSet::<NodeId>::new())),
             crypto,
+            quic_connections: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             registry_version: Arc::new(RwLock::new(registry_version)),
             tokio_runtime,
             data_plane_metrics: DataPlaneMetrics::new(metrics_registry.clone()),
@@ -16,9 +16,16 @@ use std::fmt::{self, Debug, Formatter};
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::sync::{Arc, RwLock, Weak};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::runtime::Handle;
+use tokio::sync::Mutex;
 use tokio::time::Duration;
 
+/// The QUIC connection to a peer, initialized by the first flow that
+/// establishes it. Flows that connect concurrently wait for the same
+/// handshake, without holding the lock on the map of all connections.
+pub type QuicConnectionCell = Arc<tokio::sync::OnceCell<quinn::Connection>>;
+
 /// A tag for the server port
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ServerPortTag;
@@ -31,6 +38,13 @@ pub struct QueueSizeTag;
 /// Type definition for a queue's size
 pub type QueueSize = AmountOf<QueueSizeTag, usize>;
 
+/// The read end of an established flow: the read half of a TLS stream, or a
+/// QUIC receive stream
+pub(crate) type FlowReader = Box<dyn AsyncRead + Send + Unpin>;
+/// The write end of an established flow: the write half of a TLS stream, or a
+/// QUIC send stream
+pub(crate) type FlowWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
 /// The size (in bytes) of the transport header
 pub const TRANSPORT_HEADER_SIZE: usize = 8;
 
@@ -94,6 +108,9 @@ pub(crate) struct TransportImpl {
     pub registry_version: Arc<RwLock<RegistryVersion>>,
     /// Reference to the crypto component
     pub crypto: Arc<dyn TlsHandshake + Send + Sync>,
+    /// QUIC connections to peers, shared by all flows with the peer. Only
+    /// used by the QUIC backend.
+    pub quic_connections: Arc<Mutex<HashMap<NodeId, QuicConnectionCell>>>,
 
     /// Data plane metrics
     pub data_plane_metrics: DataPlaneMetrics,
@@ -124,6 +141,9 @@ pub(crate) enum ConnectionRole {
 pub(crate) struct ClientState {
     /// Ports used to accept connections for this transport-client
     pub accept_ports: HashMap<FlowTag, ServerPortState>,
+    /// The endpoint used to accept and initiate QUIC connections, if the QUIC
+    /// backend is used
+    pub quic_endpoint: Option<QuicEndpointState>,
     /// Mapping of peers to their corresponding state
     pub peer_map: HashMap<NodeId, PeerState>,
     /// Event handler to report back to the transport client
@@ -142,6 +162,20 @@ impl Drop for ServerPortState {
     }
 }
 
+/// State about the QUIC endpoint
+pub(crate) struct QuicEndpointState {
+    /// The endpoint, bound to the server port of the first flow
+    pub endpoint: quinn::Endpoint,
+    /// Handle to the accept task for the endpoint
+    pub accept_task: AbortHandle,
+}
+
+impl Drop for QuicEndpointState {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
 /// Per-peer state, specific to a transport client
 pub(crate) struct PeerState {
     /// State of the flows with the peer
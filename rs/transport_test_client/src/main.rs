@@ -471,6 +471,7 @@ fn generate_config_and_registry(node_id: &NodeId) -> ConfigAndRecords {
                         queue_size: 1024,
                     },
                 ],
+                ..Default::default()
             });
         }
 
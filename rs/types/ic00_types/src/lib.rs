@@ -29,6 +29,8 @@ pub enum Method {
     SetController,
     SetupInitialDKG,
     SignWithECDSA,
+    SchnorrPublicKey,
+    SignWithSchnorr,
     StartCanister,
     StopCanister,
     UninstallCode,
@@ -812,6 +814,92 @@ pub struct ECDSAPublicKeyResponse {
 
 impl Payload<'_> for ECDSAPublicKeyResponse {}
 
+/// The signature scheme of the sign_with_schnorr and schnorr_public_key
+/// APIs.
+/// ```text
+/// variant { bip340secp256k1; ed25519 }
+/// ```
+#[derive(CandidType, Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SchnorrAlgorithm {
+    /// BIP340 Schnorr signatures over secp256k1, as used by Taproot.
+    #[serde(rename = "bip340secp256k1")]
+    Bip340Secp256k1,
+    #[serde(rename = "ed25519")]
+    Ed25519,
+}
+
+impl fmt::Display for SchnorrAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchnorrAlgorithm::Bip340Secp256k1 => write!(f, "bip340secp256k1"),
+            SchnorrAlgorithm::Ed25519 => write!(f, "ed25519"),
+        }
+    }
+}
+
+/// Represents the argument of the sign_with_schnorr API.
+/// ```text
+/// (record {
+///   message : blob;
+///   derivation_path : vec blob;
+///   algorithm : variant { bip340secp256k1; ed25519 };
+///   key_id : text;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SignWithSchnorrArgs {
+    pub message: Vec<u8>,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub algorithm: SchnorrAlgorithm,
+    pub key_id: String,
+}
+
+impl Payload<'_> for SignWithSchnorrArgs {}
+
+/// Struct used to return a Schnorr signature.
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SignWithSchnorrReply {
+    pub signature: Vec<u8>,
+}
+
+impl Payload<'_> for SignWithSchnorrReply {}
+
+/// Represents the argument of the schnorr_public_key API.
+/// ```text
+/// (record {
+///   canister_id : opt canister_id;
+///   derivation_path : vec blob;
+///   algorithm : variant { bip340secp256k1; ed25519 };
+///   key_id : text;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SchnorrPublicKeyArgs {
+    pub canister_id: Option<CanisterId>,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub algorithm: SchnorrAlgorithm,
+    pub key_id: String,
+}
+
+impl Payload<'_> for SchnorrPublicKeyArgs {}
+
+/// Represents the response of the schnorr_public_key API.
+///
+/// For BIP340 the public key is the 32 byte x-only encoding.
+/// ```text
+/// (record {
+///   public_key : blob;
+///   chain_code : blob;
+/// })
+/// ```
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SchnorrPublicKeyResponse {
+    pub public_key: Vec<u8>,
+    pub chain_code: Vec<u8>,
+}
+
+impl Payload<'_> for SchnorrPublicKeyResponse {}
+
 /// Argument of the compute_initial_ecdsa_dealings API.
 /// `(record {
 ///     key_id: text;
@@ -16,6 +16,7 @@ use crate::crypto::{
     },
     canister_threshold_sig::{
         ExtendedDerivationPath, PreSignatureQuadruple, ThresholdEcdsaSigInputs,
+        ThresholdSignatureScheme,
     },
     AlgorithmId,
 };
@@ -1187,6 +1188,7 @@ impl TryFrom<&pb::PreSignatureQuadrupleRef> for PreSignatureQuadrupleRef {
 /// instead of the transcripts.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ThresholdEcdsaSigInputsRef {
+    pub scheme: ThresholdSignatureScheme,
     pub derivation_path: ExtendedDerivationPath,
     pub hashed_message: Vec<u8>,
     pub nonce: Randomness,
@@ -1203,6 +1205,7 @@ pub enum ThresholdEcdsaSigInputsError {
 
 impl ThresholdEcdsaSigInputsRef {
     pub fn new(
+        scheme: ThresholdSignatureScheme,
         derivation_path: ExtendedDerivationPath,
         hashed_message: Vec<u8>,
         nonce: Randomness,
@@ -1210,6 +1213,7 @@ impl ThresholdEcdsaSigInputsRef {
         key_transcript_ref: UnmaskedTranscript,
     ) -> Self {
         Self {
+            scheme,
             derivation_path,
             hashed_message,
             nonce,
@@ -1230,7 +1234,8 @@ impl ThresholdEcdsaSigInputsRef {
         let key_transcript = resolver
             .transcript(self.key_transcript_ref.as_ref())
             .map_err(ThresholdEcdsaSigInputsError::KeyTranscript)?;
-        ThresholdEcdsaSigInputs::new(
+        ThresholdEcdsaSigInputs::new_with_scheme(
+            self.scheme,
             &self.derivation_path,
             &self.hashed_message,
             self.nonce,
@@ -1264,6 +1269,7 @@ impl ThresholdEcdsaSigInputsRef {
 impl From<&ThresholdEcdsaSigInputsRef> for pb::ThresholdEcdsaSigInputsRef {
     fn from(sig_inputs: &ThresholdEcdsaSigInputsRef) -> Self {
         Self {
+            scheme: subnet_pb::ThresholdSignatureScheme::from(sig_inputs.scheme) as i32,
             derivation_path: Some((sig_inputs.derivation_path.clone()).into()),
             hashed_message: sig_inputs.hashed_message.clone(),
             nonce: sig_inputs.nonce.get().to_vec(),
@@ -1309,7 +1315,15 @@ impl TryFrom<&pb::ThresholdEcdsaSigInputsRef> for ThresholdEcdsaSigInputsRef {
             .ok_or("pb::ThresholdEcdsaSigInputsRef:: Missing key_transcript_ref")?;
         let key_transcript_ref: UnmaskedTranscript = proto.try_into()?;
 
+        let scheme = subnet_pb::ThresholdSignatureScheme::from_i32(sig_inputs.scheme)
+            .ok_or(format!(
+                "pb::ThresholdEcdsaSigInputsRef:: Invalid scheme: {:?}",
+                sig_inputs.scheme
+            ))?
+            .into();
+
         Ok(Self::new(
+            scheme,
             derivation_path,
             sig_inputs.hashed_message.clone(),
             nonce,
@@ -197,9 +197,21 @@ pub struct ExtendedDerivationPath {
     pub derivation_path: Vec<Vec<u8>>,
 }
 
+/// The signature scheme of a canister threshold signature.
+///
+/// Both schemes use the same key transcript and consume a presignature
+/// quadruple. BIP340 Schnorr signatures only use the unmasked kappa
+/// transcript of the quadruple.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ThresholdSignatureScheme {
+    Ecdsa,
+    Bip340,
+}
+
 /// All inputs required to generate a canister threshold signature.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ThresholdEcdsaSigInputs {
+    scheme: ThresholdSignatureScheme,
     derivation_path: ExtendedDerivationPath,
     hashed_message: Vec<u8>,
     nonce: Randomness,
@@ -223,12 +235,35 @@ impl ThresholdEcdsaSigInputs {
         nonce: Randomness,
         presig_quadruple: PreSignatureQuadruple,
         key_transcript: IDkgTranscript,
+    ) -> Result<Self, error::ThresholdEcdsaSigInputsCreationError> {
+        Self::new_with_scheme(
+            ThresholdSignatureScheme::Ecdsa,
+            derivation_path,
+            hashed_message,
+            nonce,
+            presig_quadruple,
+            key_transcript,
+        )
+    }
+
+    /// Construct the inputs to signature generation with the given scheme.
+    ///
+    /// The checks are the same as for [`Self::new`]. For BIP340,
+    /// `hashed_message` is the message itself, which may be of any length.
+    pub fn new_with_scheme(
+        scheme: ThresholdSignatureScheme,
+        derivation_path: &ExtendedDerivationPath,
+        hashed_message: &[u8],
+        nonce: Randomness,
+        presig_quadruple: PreSignatureQuadruple,
+        key_transcript: IDkgTranscript,
     ) -> Result<Self, error::ThresholdEcdsaSigInputsCreationError> {
         Self::check_algorithm_ids(&presig_quadruple, &key_transcript)?;
         Self::check_receivers_are_equal(&presig_quadruple, &key_transcript)?;
         Self::check_consistency_of_transcripts(&presig_quadruple, &key_transcript)?;
 
         Ok(Self {
+            scheme,
             derivation_path: derivation_path.clone(),
             hashed_message: hashed_message.to_vec(),
             nonce,
@@ -237,6 +272,10 @@ impl ThresholdEcdsaSigInputs {
         })
     }
 
+    pub fn scheme(&self) -> ThresholdSignatureScheme {
+        self.scheme
+    }
+
     pub fn derivation_path(&self) -> &ExtendedDerivationPath {
         &self.derivation_path
     }
@@ -6,7 +6,7 @@ use crate::crypto::canister_threshold_sig::idkg::{
     IDkgDealing, IDkgMultiSignedDealing, IDkgReceivers, IDkgTranscript, IDkgTranscriptId,
     IDkgTranscriptOperation, IDkgTranscriptParams, IDkgTranscriptType, InitialIDkgDealings,
 };
-use crate::crypto::canister_threshold_sig::ExtendedDerivationPath;
+use crate::crypto::canister_threshold_sig::{ExtendedDerivationPath, ThresholdSignatureScheme};
 use crate::crypto::{AlgorithmId, CombinedMultiSig, CombinedMultiSigOf};
 use crate::{node_id_into_protobuf, node_id_try_from_protobuf, Height, NodeIndex};
 use ic_base_types::{
@@ -21,6 +21,7 @@ use ic_protobuf::registry::subnet::v1::IDkgTranscriptId as IDkgTranscriptIdProto
 use ic_protobuf::registry::subnet::v1::IDkgTranscriptOperation as IDkgTranscriptOperationProto;
 use ic_protobuf::registry::subnet::v1::IDkgTranscriptParams as IDkgTranscriptParamsProto;
 use ic_protobuf::registry::subnet::v1::InitialIDkgDealings as InitialIDkgDealingsProto;
+use ic_protobuf::registry::subnet::v1::ThresholdSignatureScheme as ThresholdSignatureSchemeProto;
 use ic_protobuf::registry::subnet::v1::VerifiedIDkgDealing as VerifiedIDkgDealingProto;
 use ic_protobuf::types::v1::NodeId as NodeIdProto;
 use ic_protobuf::types::v1::PrincipalId as PrincipalIdProto;
@@ -141,6 +142,28 @@ impl TryFrom<ExtendedDerivationPathProto> for ExtendedDerivationPath {
     }
 }
 
+impl From<ThresholdSignatureScheme> for ThresholdSignatureSchemeProto {
+    fn from(scheme: ThresholdSignatureScheme) -> Self {
+        match scheme {
+            ThresholdSignatureScheme::Ecdsa => ThresholdSignatureSchemeProto::Ecdsa,
+            ThresholdSignatureScheme::Bip340 => ThresholdSignatureSchemeProto::Bip340,
+        }
+    }
+}
+
+impl From<ThresholdSignatureSchemeProto> for ThresholdSignatureScheme {
+    fn from(proto: ThresholdSignatureSchemeProto) -> Self {
+        match proto {
+            // Signature requests recorded before BIP340 was supported do
+            // not set the scheme.
+            ThresholdSignatureSchemeProto::Unspecified | ThresholdSignatureSchemeProto::Ecdsa => {
+                ThresholdSignatureScheme::Ecdsa
+            }
+            ThresholdSignatureSchemeProto::Bip340 => ThresholdSignatureScheme::Bip340,
+        }
+    }
+}
+
 // ----- Conversion helpers.
 fn idkg_transcript_id_proto(idkg_transcript_id: &IDkgTranscriptId) -> IDkgTranscriptIdProto {
     IDkgTranscriptIdProto {
@@ -14,15 +14,21 @@ use ic_types::{
 };
 use std::{collections::BTreeSet, convert::TryFrom, fmt};
 
+/// The maximum number of delegations allowed in a request, as specified in
+/// the interface spec.
+pub const MAXIMUM_NUMBER_OF_DELEGATIONS: usize = 20;
+
 /// Validates the `request` and that the sender is authorized to send
 /// a message to the receiving canister.
 ///
-/// See notes on request validity in the crate docs.
+/// See notes on request validity in the crate docs. Requests with more than
+/// `max_delegations` sender delegations are rejected.
 pub fn validate_request<C: HttpRequestContent + HasCanisterId>(
     request: &HttpRequest<C>,
     ingress_signature_verifier: &dyn IngressSigVerifier,
     current_time: Time,
     registry_version: RegistryVersion,
+    max_delegations: usize,
     malicious_flags: &MaliciousFlags,
 ) -> Result<(), RequestValidationError> {
     #[cfg(feature = "malicious_code")]
@@ -37,6 +43,7 @@ pub fn validate_request<C: HttpRequestContent + HasCanisterId>(
         ingress_signature_verifier,
         current_time,
         registry_version,
+        max_delegations,
         malicious_flags,
     )
     .and_then(|targets| {
@@ -53,12 +60,14 @@ pub fn validate_request<C: HttpRequestContent + HasCanisterId>(
 /// Returns the set of canisters that the request is authorized to act on.
 ///
 /// The request must be valid for this call to be successful. See notes on
-/// request validity in the crate docs.
+/// request validity in the crate docs. Requests with more than
+/// `max_delegations` sender delegations are rejected.
 pub fn get_authorized_canisters<C: HttpRequestContent>(
     request: &HttpRequest<C>,
     ingress_signature_verifier: &dyn IngressSigVerifier,
     current_time: Time,
     registry_version: RegistryVersion,
+    max_delegations: usize,
     #[allow(unused_variables)] malicious_flags: &MaliciousFlags,
 ) -> Result<CanisterIdSet, RequestValidationError> {
     #[cfg(feature = "malicious_code")]
@@ -79,6 +88,7 @@ pub fn get_authorized_canisters<C: HttpRequestContent>(
         },
         current_time,
         registry_version,
+        max_delegations,
     )
 }
 
@@ -93,6 +103,7 @@ pub enum RequestValidationError {
     MissingSignature(UserId),
     AnonymousSignatureNotAllowed,
     CanisterNotInDelegationTargets(CanisterId),
+    TooManyDelegations { length: usize, maximum: usize },
 }
 
 impl fmt::Display for RequestValidationError {
@@ -117,6 +128,11 @@ impl fmt::Display for RequestValidationError {
                 "Canister {} is not one of the delegation targets",
                 canister_id
             ),
+            TooManyDelegations { length, maximum } => write!(
+                f,
+                "The request has {} delegations, but at most {} are allowed",
+                length, maximum
+            ),
         }
     }
 }
@@ -239,13 +255,21 @@ fn validate_signature(
     signature: &UserSignature,
     current_time: Time,
     registry_version: RegistryVersion,
+    max_delegations: usize,
 ) -> Result<CanisterIdSet, RequestValidationError> {
-    validate_sender_delegation_expiry(&signature.sender_delegation, current_time)?;
     let empty_vec = Vec::new();
     let signed_delegations = match &signature.sender_delegation {
         None => &empty_vec,
         Some(delegations) => delegations,
     };
+    // Reject over-long chains before verifying any of their signatures.
+    if signed_delegations.len() > max_delegations {
+        return Err(TooManyDelegations {
+            length: signed_delegations.len(),
+            maximum: max_delegations,
+        });
+    }
+    validate_sender_delegation_expiry(&signature.sender_delegation, current_time)?;
 
     let (pubkey, targets) = validate_delegations(
         validator,
@@ -382,6 +406,7 @@ fn validate_user_id_and_signature(
     signature: Option<&UserSignature>,
     current_time: Time,
     registry_version: RegistryVersion,
+    max_delegations: usize,
 ) -> Result<CanisterIdSet, RequestValidationError> {
     match signature {
         None => {
@@ -402,6 +427,7 @@ fn validate_user_id_and_signature(
                         signature,
                         current_time,
                         registry_version,
+                        max_delegations,
                     )
                 })
             }
@@ -457,7 +483,8 @@ mod tests {
             &message_id,
             &user_signature,
             UNIX_EPOCH,
-            mock_registry_version()
+            mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
         )
         .is_ok());
 
@@ -474,7 +501,8 @@ mod tests {
             &message_id,
             &user_signature,
             UNIX_EPOCH,
-            mock_registry_version()
+            mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
         )
         .is_ok());
     }
@@ -504,7 +532,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Err(InvalidSignature(InvalidBasicSignature(_)))
         );
@@ -559,7 +588,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Ok(CanisterIdSet::All)
         );
@@ -572,7 +602,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH + Duration::from_secs(1),
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Err(RequestValidationError::InvalidDelegationExpiry(_))
         );
@@ -627,7 +658,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Ok(CanisterIdSet::Some(set)) if set == btreeset! {canister_test_id(1)}
         );
@@ -728,7 +760,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Ok(CanisterIdSet::Some(set)) if set == btreeset! {canister_test_id(1)}
         );
@@ -738,7 +771,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH + Duration::from_secs(2),
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Ok(_)
         );
@@ -750,7 +784,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH + Duration::from_secs(3),
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Err(RequestValidationError::InvalidDelegationExpiry(_))
         );
@@ -785,7 +820,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Err(InvalidDelegation(InvalidBasicSignature(_)))
         );
@@ -835,7 +871,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Err(InvalidDelegation(InvalidPublicKey(_)))
         );
@@ -863,7 +900,8 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Ok(CanisterIdSet::All)
         );
@@ -900,9 +938,183 @@ mod tests {
                 &message_id,
                 &user_signature,
                 UNIX_EPOCH,
-                mock_registry_version()
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
             ),
             Ok(CanisterIdSet::All)
         );
     }
+
+    // Returns a signature of PK2 (see the tests above) for message_test_id(1),
+    // and PK2 in DER format.
+    fn message_id_signature_and_pubkey_of_kp2() -> (Vec<u8>, Vec<u8>) {
+        let signature = base64::decode(
+            "MwqQH8l2vCNhRTzYmBA95p7tQWg4S0G4v0zyIiX21H6c6E1oL8xWDuOe67Yh98yt6z8n84D875I2qmvLliWODA==",
+        )
+        .unwrap();
+        let pubkey = ed25519_public_key_to_der(
+            base64::decode("SyP7C1lwpbsWjwT7ow5CnbiL5JzbyjzQrdDVQQb18yE=").unwrap(),
+        )
+        .unwrap();
+        (signature, pubkey)
+    }
+
+    // Validates a request signed by KP2 on behalf of `sender_pubkey`, which
+    // delegates to KP2 with `delegation_signature` for canisters 1 and 2.
+    fn validate_signature_with_scoped_delegation_to_kp2(
+        sender_pubkey: Vec<u8>,
+        delegation_signature: Vec<u8>,
+    ) -> Result<CanisterIdSet, RequestValidationError> {
+        let sig_verifier = temp_crypto_component_with_fake_registry(node_test_id(0));
+        let (message_id_signature, pk2) = message_id_signature_and_pubkey_of_kp2();
+        let delegation = Delegation::new_with_targets(
+            pk2,
+            UNIX_EPOCH,
+            vec![canister_test_id(1), canister_test_id(2)],
+        );
+        let user_signature = UserSignature {
+            signature: message_id_signature,
+            signer_pubkey: sender_pubkey,
+            sender_delegation: Some(vec![SignedDelegation::new(
+                delegation,
+                delegation_signature,
+            )]),
+        };
+        validate_signature(
+            &sig_verifier,
+            &message_test_id(1),
+            &user_signature,
+            UNIX_EPOCH,
+            mock_registry_version(),
+            MAXIMUM_NUMBER_OF_DELEGATIONS,
+        )
+    }
+
+    #[test]
+    fn ecdsa_p256_sender_with_scoped_delegation() {
+        // Public key for the secret key
+        // 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+        let pk = hex::decode("3059301306072a8648ce3d020106082a8648ce3d03010703420004471c3e758c4904285bba7e53118ed0f524adeb0757d25bd2f8e7b0d76dfa714cdd520f7aca8a8b917acc37f51de8f0c9bbe3ad858382e702dc25a12d09f7a858").unwrap();
+        let delegation_signature = hex::decode("af464af660b13e13ff681f1dbaf64e92484c08e88071cd8ef08f71bdb1fab831260b4a1a0845a1b812b4e0db4bcc9e8586853a5b6877835b3de64b3e64ffa82d").unwrap();
+
+        let targets =
+            validate_signature_with_scoped_delegation_to_kp2(pk, delegation_signature).unwrap();
+        assert_eq!(
+            targets,
+            CanisterIdSet::Some(btreeset! {canister_test_id(1), canister_test_id(2)})
+        );
+        assert!(!targets.contains(&canister_test_id(3)));
+    }
+
+    #[test]
+    fn ecdsa_secp256k1_sender_with_scoped_delegation() {
+        // Public key for the secret key
+        // fedcba0987654321fedcba0987654321fedcba0987654321fedcba0987654321
+        let pk = hex::decode("3056301006072a8648ce3d020106052b8104000a0342000497855f402631f09e602e5ccadc219503f07cdd4c73b2215b5418f52a7fdbfcd97c59d67b478562b62269ec23d6dfc5566bacbdc25606d4ccfd5de7cfadcf4be8").unwrap();
+        let delegation_signature = hex::decode("4cd8645aa0cad1de1e09e6a8c0c537fe095e5bde359078cb3811ebdd014470b1499f354121a58644d74f6871d84ee262518ad561a971e91d0c0c95e79abd8f83").unwrap();
+
+        let targets =
+            validate_signature_with_scoped_delegation_to_kp2(pk, delegation_signature).unwrap();
+        assert_eq!(
+            targets,
+            CanisterIdSet::Some(btreeset! {canister_test_id(1), canister_test_id(2)})
+        );
+        assert!(!targets.contains(&canister_test_id(3)));
+    }
+
+    #[test]
+    fn webauthn_sender_with_scoped_delegation() {
+        // COSE-encoded ECDSA P-256 public key for the secret key
+        // 0badc0ffee0badc0ffee0badc0ffee0badc0ffee0badc0ffee0badc0ffee0bad
+        let pk = hex::decode("305e300c060a2b0601040183b8430101034e00a50102032620012158202a6d75aa2fc6bcacdad49b497e03a77d3325aad307b6be04965efd5c7b9bca222258204789708ad5a518e70f27d3d0258e651e2bb09f9154dd303b5b5d3bcfc0ea60c5").unwrap();
+        let delegation_signature = hex::decode("d9d9f7a37261757468656e74696361746f725f646174615825bfabc37432958b063360d3ad6461c9c4735ae7f8edd46592a5e0f01452b2e4b5010000000170636c69656e745f646174615f6a736f6e58997b2274797065223a2022776562617574686e2e676574222c20226368616c6c656e6765223a2022476d6c6a4c584a6c6358566c63335174595856306143316b5a57786c5a324630615739756e3533655f66363367795457463851774678446b3132496f73654f5f5a6a73726f49366236514f69707351222c20226f726967696e223a202268747470733a2f2f6578616d706c652e6f7267227d697369676e6174757265584730450221008fe796b156be1797b4d39d5e51888c10dfa43ce9f619e4e1088220abe22f0a1002201fc0c88d652930278e8ee81f91864a58d77170bce6efb3e214386311a2af2212").unwrap();
+
+        let targets =
+            validate_signature_with_scoped_delegation_to_kp2(pk, delegation_signature).unwrap();
+        assert_eq!(
+            targets,
+            CanisterIdSet::Some(btreeset! {canister_test_id(1), canister_test_id(2)})
+        );
+        assert!(!targets.contains(&canister_test_id(3)));
+    }
+
+    #[test]
+    fn should_reject_too_many_delegations() {
+        let sig_verifier = temp_crypto_component_with_fake_registry(node_test_id(0));
+        let (message_id_signature, pk2) = message_id_signature_and_pubkey_of_kp2();
+        let pk1 = ed25519_public_key_to_der(
+            base64::decode("rrkzV33aO4TcH2DMz3ducPaZyIiG/8YbnNjHW+0hRvg=").unwrap(),
+        )
+        .unwrap();
+
+        // The length of the chain is checked before any of the signatures, so
+        // the delegations don't need to be valid.
+        let delegations = vec![
+            SignedDelegation::new(Delegation::new(pk2, UNIX_EPOCH), vec![]);
+            MAXIMUM_NUMBER_OF_DELEGATIONS + 1
+        ];
+        let user_signature = UserSignature {
+            signature: message_id_signature,
+            signer_pubkey: pk1,
+            sender_delegation: Some(delegations),
+        };
+
+        assert_matches!(
+            validate_signature(
+                &sig_verifier,
+                &message_test_id(1),
+                &user_signature,
+                UNIX_EPOCH,
+                mock_registry_version(),
+                MAXIMUM_NUMBER_OF_DELEGATIONS,
+            ),
+            Err(TooManyDelegations { length, maximum })
+                if length == MAXIMUM_NUMBER_OF_DELEGATIONS + 1
+                    && maximum == MAXIMUM_NUMBER_OF_DELEGATIONS
+        );
+    }
+
+    #[test]
+    fn should_honour_configured_maximum_number_of_delegations() {
+        let sig_verifier = temp_crypto_component_with_fake_registry(node_test_id(0));
+        let (message_id_signature, pk2) = message_id_signature_and_pubkey_of_kp2();
+        let pk1 = ed25519_public_key_to_der(
+            base64::decode("rrkzV33aO4TcH2DMz3ducPaZyIiG/8YbnNjHW+0hRvg=").unwrap(),
+        )
+        .unwrap();
+        // Signature of SK1 (see plain_authentication_with_one_scoped_delegation)
+        // for a delegation to KP2 targeting canister 1.
+        let delegation_signature = base64::decode(
+            "yULx4bstJpKWTcymC3T9kQUVC0fD04pxuHtMSOH2c9NkM5AqplrRmJgeb92p583nuexafMS6SXWfmWszSo14CA==",
+        )
+        .unwrap();
+        let delegation = Delegation::new_with_targets(pk2, UNIX_EPOCH, vec![canister_test_id(1)]);
+        let user_signature = UserSignature {
+            signature: message_id_signature,
+            signer_pubkey: pk1,
+            sender_delegation: Some(vec![SignedDelegation::new(
+                delegation,
+                delegation_signature,
+            )]),
+        };
+        let validate = |max_delegations| {
+            validate_signature(
+                &sig_verifier,
+                &message_test_id(1),
+                &user_signature,
+                UNIX_EPOCH,
+                mock_registry_version(),
+                max_delegations,
+            )
+        };
+
+        assert_matches!(validate(1), Ok(_));
+        assert_matches!(
+            validate(0),
+            Err(TooManyDelegations {
+                length: 1,
+                maximum: 0
+            })
+        );
+    }
 }
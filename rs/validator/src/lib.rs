@@ -4,10 +4,11 @@
 //!  * The request hasn't expired relative to `current_time`.
 //!  * The delegations haven't expired relative to `current_time`.
 //!  * The signatures are corrrect.
+//!  * The number of delegations does not exceed the given maximum.
 mod ingress_validation;
 mod webauthn;
 
 pub use ingress_validation::{
     get_authorized_canisters, validate_request, AuthenticationError, CanisterIdSet,
-    RequestValidationError,
+    RequestValidationError, MAXIMUM_NUMBER_OF_DELEGATIONS,
 };
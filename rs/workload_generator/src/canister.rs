@@ -67,6 +67,7 @@ pub(crate) async fn create_canister(
     // nonce, due to the presence of expiry_time. Therefore this function
     // will create a NEW canister id every time it is invoked.
     let agent = Agent::new_with_client(http_client, Url::parse(url).unwrap(), agent_sender)
+        .with_fetched_root_key()
         .with_ingress_timeout(Duration::from_secs(5 * 60));
 
     debug!("Create canister with agent: {:?}", agent);
@@ -110,6 +111,7 @@ pub(crate) async fn install_canister(
     wasm_file_path: Option<&Path>,
 ) -> Result<(), String> {
     let agent = Agent::new_with_client(http_client, Url::parse(url).unwrap(), agent_sender)
+        .with_fetched_root_key()
         .with_ingress_timeout(Duration::from_secs(5 * 60));
 
     let bytes = if let Some(wasm_file_path) = wasm_file_path {
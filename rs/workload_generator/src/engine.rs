@@ -94,6 +94,7 @@ impl Engine {
                 agent_sender.clone(),
                 http_client_config,
             )
+            .with_fetched_root_key()
             .with_query_timeout(QUERY_TIMEOUT);
             agent.sender_field = sender_field.clone();
             agent
@@ -719,7 +720,8 @@ impl Engine {
     ) -> Result<(String, Option<u32>), String> {
         let call_response = agent
             .wait_ingress(request_id, deadline, canister_id)
-            .await?;
+            .await
+            .map_err(|e| e.to_string())?;
 
         if let Ok(f) = env::var("RESULT_FILE") {
             let bytes: Vec<u8> = call_response.reply.clone().unwrap_or_default();
@@ -324,6 +324,7 @@ class BaseExperiment:
                 self._get_ic_admin_path(),
                 "--nns-url",
                 self._get_nns_url(),
+                "--fetch-root-key",
                 "propose-to-add-nodes-to-subnet",
                 "--test-neuron-proposer",
                 "--subnet-id",
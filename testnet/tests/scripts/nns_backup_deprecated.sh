@@ -78,7 +78,7 @@ echo "➡️  Increase the block rate"
 (
     set -x
 
-    ic-admin --nns-url "$nns_url" propose-to-update-subnet --subnet "$SUBNET_ID" --initial-notary-delay-millis 400 --test-neuron-proposer
+    ic-admin --nns-url "$nns_url" --fetch-root-key propose-to-update-subnet --subnet "$SUBNET_ID" --initial-notary-delay-millis 400 --test-neuron-proposer
 )
 
 # Because this a self-upgrade test, we need to download the guest-os for this version
@@ -104,7 +104,7 @@ echo "➡️  Triggering upgrade"
     SHA256=$(sha256sum "$UPGRADE_IMG" | awk '{ print $1}')
     echo "Checksum is: ${SHA256}"
 
-    ic-admin --nns-url "$nns_url" propose-to-bless-replica-version-flexible \
+    ic-admin --nns-url "$nns_url" --fetch-root-key propose-to-bless-replica-version-flexible \
         --test-neuron-proposer "$VERSION" \
         "$UPGRADE_URL" "$SHA256" 2>&1 | tee "$LOG_BLESSING"
 
@@ -113,7 +113,7 @@ echo "➡️  Triggering upgrade"
         sleep 5
     done
 
-    ic-admin --nns-url="$nns_url" propose-to-update-subnet-replica-version \
+    ic-admin --nns-url="$nns_url" --fetch-root-key propose-to-update-subnet-replica-version \
         --test-neuron-proposer "$SUBNET" "$VERSION"
 )
 
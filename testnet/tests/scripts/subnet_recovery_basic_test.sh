@@ -72,6 +72,7 @@ function bless_replica_version() {
     local sha256=$2
     local proposal_id
     proposal_id=$(ic-admin --nns-url "$NNS_URL" \
+        --fetch-root-key \
         propose-to-bless-replica-version-flexible \
         --test-neuron-proposer "$version" \
         "https://download.dfinity.systems/ic/$version/guest-os/update-img/update-img.tar.gz" \
@@ -87,6 +88,7 @@ function update_subnet_replica_version() {
     local version=$2
     local proposal_id
     proposal_id=$(ic-admin --nns-url "$NNS_URL" \
+        --fetch-root-key \
         propose-to-update-subnet-replica-version \
         --test-neuron-proposer "$subnet_id" "$version" | grep -i proposal | grep -oE "[0-9]*")
 
@@ -246,7 +248,7 @@ step 3.E Propose to update recovery cup || time (
 
     # shellcheck disable=SC2068,SC2128,SC2086
     PROPOSAL_ID=$(
-        ic-admin --nns-url="$NNS_URL" propose-to-update-recovery-cup \
+        ic-admin --nns-url="$NNS_URL" --fetch-root-key propose-to-update-recovery-cup \
             --test-neuron-proposer \
             --subnet-index "$subnet_index" \
             --height "$RECOVERY_HEIGHT" \
@@ -302,7 +304,7 @@ step 4 Install and run workload generator || time (
 step 5 Stall the network and recover it again using failover nodes || true
 
 step 5.A Halt the subnet || time (
-    PROPOSAL_OUTPUT=$(ic-admin --nns-url="$NNS_URL" propose-to-update-subnet \
+    PROPOSAL_OUTPUT=$(ic-admin --nns-url="$NNS_URL" --fetch-root-key propose-to-update-subnet \
         --test-neuron-proposer --is-halted true --subnet 1)
     PROPOSAL_ID=$(echo "$PROPOSAL_OUTPUT" | grep -i proposal | grep -oE "[0-9]*")
     wait_for_proposal_execution "$testnet" "$PROPOSAL_ID"
@@ -346,7 +348,7 @@ step 5.C Copy state to one of the failover nodes || time (
 step 5.D propose to update recovery CUP || time (
     # shellcheck disable=SC2068,SC2128,SC2086
     PROPOSAL_ID=$(
-        ic-admin --nns-url="$NNS_URL" propose-to-update-recovery-cup \
+        ic-admin --nns-url="$NNS_URL" --fetch-root-key propose-to-update-recovery-cup \
             --test-neuron-proposer \
             --subnet-index "$subnet_index" \
             --height "$FAILOVER_RECOVERY_HEIGHT" \
@@ -361,7 +363,7 @@ step 5.D propose to update recovery CUP || time (
 )
 
 step 6 Propose to unhalt the subnet || time (
-    PROPOSAL_OUTPUT=$(ic-admin --nns-url="$NNS_URL" propose-to-update-subnet \
+    PROPOSAL_OUTPUT=$(ic-admin --nns-url="$NNS_URL" --fetch-root-key propose-to-update-subnet \
         --test-neuron-proposer --is-halted false --subnet 1)
     PROPOSAL_ID=$(echo "$PROPOSAL_OUTPUT" | grep -i proposal | grep -oE "[0-9]*")
     wait_for_proposal_execution "$testnet" "$PROPOSAL_ID"
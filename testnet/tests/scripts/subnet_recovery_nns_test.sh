@@ -160,7 +160,7 @@ step 3.A.1 "Deploy the parent IC" || time (
 step 3.A.2 "Increase the block rate" || time (
     set -x
     SUBNET_ID=$("$IC_ADMIN" --nns-url="$NNS_URL" get-subnet 0 | jq '.records[0].key' | sed "s/subnet_record_//" | xargs echo)
-    "$IC_ADMIN" --nns-url "$NNS_URL" propose-to-update-subnet --subnet "$SUBNET_ID" --initial-notary-delay-millis 400 --test-neuron-proposer
+    "$IC_ADMIN" --nns-url "$NNS_URL" --fetch-root-key propose-to-update-subnet --subnet "$SUBNET_ID" --initial-notary-delay-millis 400 --test-neuron-proposer
 )
 
 ORIGINAL_NNS_ID=$("$IC_REGEDIT" snapshot "$ORIGINAL_NNS_DATA/data/ic_registry_local_store" | jq -r .nns_subnet_id.principal_id.raw | cut -d')' -f2-)
@@ -174,6 +174,7 @@ step 3.B "Create the new child nns subnet" || time (
 
     "$IC_ADMIN" \
         --nns-url "$NNS_URL" \
+        --fetch-root-key \
         propose-to-create-subnet \
         --test-neuron-proposer \
         --unit-delay-millis 2000 \
@@ -270,7 +271,7 @@ step 6.A "Propose recovery CUP" || time (
     STATE_HASH=${STATE_HASH:-$(get_state_hash "$SCRATCH/ic-replay-0.log")}
 
     echo "Proposing cup with state hash: $STATE_HASH, TARBALL_HASH: $TARBALL_HASH, REGISTRY_VERSION: $REGISTRY_VERSION"
-    "$IC_ADMIN" --nns-url="$NNS_URL" propose-to-update-recovery-cup \
+    "$IC_ADMIN" --nns-url="$NNS_URL" --fetch-root-key propose-to-update-recovery-cup \
         --subnet "$ORIGINAL_NNS_ID" --height $((CHECKPOINT_HEIGHT + 100)) --time-ns "$(date +%s%N)" \
         --state-hash "$STATE_HASH" --test-neuron-proposer \
         --registry-store-uri "$REGISTRY_STORE_URI" \
@@ -76,6 +76,7 @@ function bless_replica_version() {
     local sha256=$2
     local proposal_id
     proposal_id=$(ic-admin --nns-url "$NNS_URL" \
+        --fetch-root-key \
         propose-to-bless-replica-version-flexible \
         --test-neuron-proposer "$version" \
         "https://download.dfinity.systems/ic/$version/guest-os/update-img/update-img.tar.gz" \
@@ -91,6 +92,7 @@ function update_subnet_replica_version() {
     local version=$2
     local proposal_id
     proposal_id=$(ic-admin --nns-url "$NNS_URL" \
+        --fetch-root-key \
         propose-to-update-subnet-replica-version \
         --test-neuron-proposer "$subnet_id" "$version" | grep -i proposal | grep -oE "[0-9]*")
 